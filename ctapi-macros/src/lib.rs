@@ -0,0 +1,299 @@
+//! Compile-time checked tag references for ctapi-rs
+//!
+//! [`tag!`] checks a string literal against the grammar of
+//! `ctapi_rs::tag_path::TagPath::parse`, which both share through the
+//! `ctapi-tag-grammar` crate, while the crate compiles and expands to the
+//! parsed `TagPath` as a constant. A literal that `TagPath::parse` would
+//! reject is a compile error naming the offending character and pointing at
+//! it.
+//! [`tag_checked!`] additionally requires the tag to be listed in a manifest
+//! of known tag names.
+//!
+//! Use the macros through the `macros` feature of ctapi-rs, which re-exports
+//! them; the expansion refers to `::ctapi_rs`.
+
+use ctapi_tag_grammar::{Parts, known_field, parse};
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variable naming the tag manifest read by [`tag_checked!`]
+const MANIFEST_VAR: &str = "CTAPI_TAG_MANIFEST";
+
+/// Tag reference checked at compile time
+///
+/// Accepts the forms of `TagPath::parse`: `Tag`, `Tag[n]`, `Tag.Field`,
+/// `Cluster.Tag` and `Cluster.Tag[n].Field`. The expansion is a constant
+/// expression building the `TagPath` from the parts the macro found, so it
+/// can initialise a `const` or `static` and does no parsing at run time.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::tag;
+/// use ctapi_rs::tag_path::{AddressingForm, TagPath};
+///
+/// const FLOW: TagPath = tag!("Cluster1.Flow[2].V");
+/// assert_eq!(FLOW, TagPath::parse("Cluster1.Flow[2].V")?);
+/// assert_eq!(FLOW.form(), AddressingForm::Field);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+///
+/// A malformed reference does not compile:
+/// ```compile_fail
+/// let path = ctapi_rs::tag!("Flow[x]");
+/// ```
+#[proc_macro]
+pub fn tag(input: TokenStream) -> TokenStream {
+    match expand(input, false) {
+        Ok(expansion) | Err(expansion) => expansion,
+    }
+}
+
+/// Tag reference checked at compile time against a manifest of tag names
+///
+/// Checks the literal like [`tag!`] and then looks its tag name, without
+/// cluster, index or field, up in the manifest named by the
+/// `CTAPI_TAG_MANIFEST` environment variable. A relative path is resolved
+/// against the directory of the crate's `Cargo.toml`. The manifest lists one
+/// tag name per line; blank lines and lines starting with `#` are skipped and
+/// names compare case-insensitively, as in Citect SCADA. Set the variable in
+/// `.cargo/config.toml`:
+///
+/// ```toml
+/// [env]
+/// CTAPI_TAG_MANIFEST = { value = "tags.txt", relative = true }
+/// ```
+///
+/// The crate is rebuilt when the manifest changes.
+#[proc_macro]
+pub fn tag_checked(input: TokenStream) -> TokenStream {
+    match expand(input, true) {
+        Ok(expansion) | Err(expansion) => expansion,
+    }
+}
+
+/// Check the literal in `input` and build the expansion, or a `compile_error!`
+fn expand(input: TokenStream, checked: bool) -> Result<TokenStream, TokenStream> {
+    let (literal, path) = string_literal(input)?;
+    let span = literal.span();
+    let parts = parse(&path).map_err(|invalid| error(span, &invalid.render(&path)))?;
+
+    let mut expansion = constructor(&parts);
+    if checked {
+        let (manifest, names) = read_manifest().map_err(|message| error(span, &message))?;
+        if !lists(&names, parts.tag) {
+            let message = format!(
+                "tag `{}` is not in the tag manifest {}",
+                parts.tag,
+                manifest.display()
+            );
+            return Err(error(span, &message));
+        }
+        // include_bytes! makes cargo rebuild the crate when the manifest changes
+        let manifest = Literal::string(&manifest.display().to_string());
+        expansion =
+            format!("{{ const _: &[u8] = ::core::include_bytes!({manifest}); {expansion} }}");
+    }
+    Ok(expansion.parse().expect("expansion is valid Rust"))
+}
+
+/// The single string literal in `input` and its value
+fn string_literal(input: TokenStream) -> Result<(Literal, String), TokenStream> {
+    let mut tokens = input.into_iter();
+    let first = tokens.next();
+    let token = match first {
+        // Literals passed on by macro_rules! arrive in an invisible group
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::None => {
+            let mut inner = group.stream().into_iter();
+            match (inner.next(), inner.next()) {
+                (Some(token), None) => Some(token),
+                _ => Some(TokenTree::Group(group)),
+            }
+        }
+        token => token,
+    };
+    let span = token.as_ref().map_or_else(Span::call_site, TokenTree::span);
+    let expected = || error(span, "expected a string literal, e.g. `tag!(\"Pump1.V\")`");
+    if tokens.next().is_some() {
+        return Err(expected());
+    }
+    match token {
+        Some(TokenTree::Literal(literal)) => match unquote(&literal.to_string()) {
+            Some(value) => Ok((literal, value)),
+            None => Err(expected()),
+        },
+        _ => Err(expected()),
+    }
+}
+
+/// Value of a string literal from its source text, `None` for other literals
+fn unquote(source: &str) -> Option<String> {
+    if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let closing = format!("\"{}", "#".repeat(hashes));
+        return raw[hashes..]
+            .strip_prefix('"')?
+            .strip_suffix(closing.as_str())
+            .map(str::to_string);
+    }
+    let body = source.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                value.push(char::from(u8::from_str_radix(&hex, 16).ok()?));
+            }
+            'u' => {
+                let hex: String = chars
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|c| *c != '}')
+                    .filter(|c| *c != '_')
+                    .collect();
+                value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Constant expression building the `TagPath` of `parts`
+fn constructor(parts: &Parts<'_>) -> String {
+    const SOME: &str = "::core::option::Option::Some";
+    const NONE: &str = "::core::option::Option::None";
+    const FIELD: &str = "::ctapi_rs::tag_path::TagField";
+
+    // The Debug form of a str is a Rust string literal of it
+    let cluster = match parts.cluster {
+        Some(cluster) => format!("{SOME}({cluster:?})"),
+        None => NONE.to_string(),
+    };
+    let index = match parts.index {
+        Some(index) => format!("{SOME}({index}u32)"),
+        None => NONE.to_string(),
+    };
+    let field = match parts.field.map(|field| (field, known_field(field))) {
+        Some((_, Some((_, variant)))) => format!("{SOME}({FIELD}::{variant})"),
+        Some((field, None)) => {
+            format!("{SOME}({FIELD}::Custom(::std::borrow::Cow::Borrowed({field:?})))")
+        }
+        None => NONE.to_string(),
+    };
+    let tag = parts.tag;
+    format!("::ctapi_rs::tag_path::TagPath::__from_parts({cluster}, {tag:?}, {index}, {field})")
+}
+
+/// Path and contents of the tag manifest
+fn read_manifest() -> Result<(PathBuf, String), String> {
+    let Some(path) = env::var_os(MANIFEST_VAR) else {
+        return Err(format!(
+            "tag_checked! needs a tag manifest, set {MANIFEST_VAR} to its path"
+        ));
+    };
+    let mut manifest = PathBuf::from(path);
+    if manifest.is_relative()
+        && let Some(dir) = env::var_os("CARGO_MANIFEST_DIR")
+    {
+        manifest = PathBuf::from(dir).join(manifest);
+    }
+    match fs::read_to_string(&manifest) {
+        Ok(names) => Ok((manifest, names)),
+        Err(e) => Err(format!(
+            "cannot read the tag manifest {}: {e}",
+            manifest.display()
+        )),
+    }
+}
+
+/// Whether the manifest `names` lists `tag`
+fn lists(names: &str, tag: &str) -> bool {
+    names
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .any(|name| name.eq_ignore_ascii_case(tag))
+}
+
+/// `::core::compile_error!` with `message`, reported at `span`
+fn error(span: Span, message: &str) -> TokenStream {
+    let mut message = Literal::string(message);
+    message.set_span(span);
+    let tokens = [
+        TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+        TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+        TokenTree::Ident(Ident::new("core", span)),
+        TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+        TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(Group::new(
+            Delimiter::Parenthesis,
+            TokenTree::Literal(message).into(),
+        )),
+    ];
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructor() {
+        let parts = parse("Cluster1.Flow[2].qt").unwrap();
+        assert_eq!(
+            constructor(&parts),
+            "::ctapi_rs::tag_path::TagPath::__from_parts(\
+             ::core::option::Option::Some(\"Cluster1\"), \"Flow\", \
+             ::core::option::Option::Some(2u32), \
+             ::core::option::Option::Some(::ctapi_rs::tag_path::TagField::QualityTimestamp))"
+        );
+        let custom = constructor(&parse("Site.Pump1.Scale").unwrap());
+        assert!(custom.ends_with("Custom(::std::borrow::Cow::Borrowed(\"Scale\"))))"));
+        let unicode = constructor(&parse("温度").unwrap());
+        assert!(unicode.contains(r#"None, "温度", "#), "{unicode}");
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(
+            unquote(r#""Boiler1\\Temp""#).as_deref(),
+            Some("Boiler1\\Temp")
+        );
+        assert_eq!(unquote(r##"r#"A"B"#"##).as_deref(), Some("A\"B"));
+        assert_eq!(unquote(r#""\x41\u{42}""#).as_deref(), Some("AB"));
+        assert_eq!(unquote("42"), None);
+        assert_eq!(unquote("b\"A\""), None);
+    }
+
+    #[test]
+    fn test_manifest_lookup() {
+        let names = "# plant tags\nBoiler1\\Temp\n\n  Pump1  \n";
+        assert!(lists(names, "boiler1\\temp"));
+        assert!(lists(names, "Pump1"));
+        assert!(!lists(names, "Pump2"));
+        assert!(!lists(names, "# plant tags"));
+    }
+}
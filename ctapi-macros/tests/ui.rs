@@ -0,0 +1,8 @@
+//! Accepted and rejected `tag!` literals
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass.rs");
+    t.compile_fail("tests/ui/fail_*.rs");
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = ctapi_rs::tag!(".Pump1");
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = ctapi_rs::tag!("Flow[4294967296]");
+}
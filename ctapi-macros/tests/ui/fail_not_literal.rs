@@ -0,0 +1,5 @@
+const PUMP: &str = "Pump1";
+
+fn main() {
+    let _ = ctapi_rs::tag!(PUMP);
+}
@@ -0,0 +1,28 @@
+use ctapi_rs::tag;
+use ctapi_rs::tag_path::TagPath;
+
+macro_rules! forward {
+    ($path:literal) => {
+        tag!($path)
+    };
+}
+
+const PUMP: TagPath = tag!("Site.Pump1.Q");
+static FLOW: TagPath = tag!("Site.Flow[2].Scale");
+
+fn main() {
+    for (path, text) in [
+        (tag!("Pump1"), "Pump1"),
+        (tag!("Flow[3]"), "Flow[3]"),
+        (tag!("Pump1.v"), "Pump1.v"),
+        (tag!("Cluster1.Pump1"), "Cluster1.Pump1"),
+        (tag!("Cluster1.Flow[0].Scale"), "Cluster1.Flow[0].Scale"),
+        (tag!("温度"), "温度"),
+        (tag!(r"Boiler1.Temp.QT"), "Boiler1.Temp.QT"),
+        (forward!("Site.Pump1.Q"), "Site.Pump1.Q"),
+    ] {
+        assert_eq!(path, TagPath::parse(text).unwrap(), "{text}");
+    }
+    assert_eq!(PUMP, TagPath::parse("Site.Pump1.Q").unwrap());
+    assert_eq!(FLOW, TagPath::parse("Site.Flow[2].Scale").unwrap());
+}
@@ -0,0 +1,37 @@
+//! Records per second a journal audit sink stores under each fsync policy
+//!
+//! Writes to a fresh directory under the system temporary directory.
+//!
+//! ```text
+//! cargo bench --bench audit_journal
+//! ```
+
+use ctapi_rs::audit::{AuditRecord, AuditSink, FsyncPolicy, JournalAuditSink, JournalOptions};
+use std::time::{Duration, Instant};
+
+const RECORDS: usize = 2000;
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("ctapi-rs-bench-audit-{}", std::process::id()));
+    let policies = [
+        FsyncPolicy::EveryRecord,
+        FsyncPolicy::EveryRecords(100),
+        FsyncPolicy::Interval(Duration::from_millis(100)),
+    ];
+    for (n, policy) in policies.into_iter().enumerate() {
+        let options = JournalOptions::default().fsync(policy);
+        let sink = JournalAuditSink::open(&dir, &format!("bench{n}"), options).unwrap();
+        let started = Instant::now();
+        for n in 0..RECORDS {
+            let record = AuditRecord::new("tag_write", "Setpoint").value(&n.to_string());
+            sink.record(&record).unwrap();
+        }
+        sink.sync().unwrap();
+        let rate = RECORDS as f64 / started.elapsed().as_secs_f64();
+        println!(
+            "audit_journal: {policy:?}: {rate:.0} records/s, {:?}",
+            sink.stats()
+        );
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
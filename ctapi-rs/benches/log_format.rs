@@ -0,0 +1,104 @@
+//! Cost of formatting quality and connection state for a log record
+//!
+//! Prints the time and the heap allocations per record; the allocations must
+//! be zero, see [`ctapi_rs::quality`].
+//!
+//! ```text
+//! cargo bench --bench log_format
+//! ```
+
+use ctapi_rs::quality::{QUAL_BAD, QUAL_GOOD, QUAL_UNCERTAIN};
+use ctapi_rs::{CitectError, ConnectionState, Quality};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const RECORDS: usize = 1_000_000;
+
+/// System allocator counting allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Reused log line buffer
+struct Line {
+    bytes: [u8; 128],
+    len: usize,
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(std::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+fn main() {
+    let qualities = [
+        (QUAL_GOOD, 0),
+        (QUAL_UNCERTAIN, 0),
+        (QUAL_BAD, 2),
+        (QUAL_BAD, 0x8004_1234),
+    ]
+    .map(|(general, code)| Quality {
+        general,
+        datasource_error: CitectError::from_code(code),
+        ..Quality::default()
+    });
+    let states = [
+        ConnectionState::Connected,
+        ConnectionState::Reconnecting,
+        ConnectionState::Down,
+    ];
+    let mut line = Line {
+        bytes: [0; 128],
+        len: 0,
+    };
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for i in 0..RECORDS {
+        let quality = &qualities[i % qualities.len()];
+        let state = states[i % states.len()];
+        line.len = 0;
+        write!(
+            line,
+            "state={state} quality={quality} status={}",
+            quality.status()
+        )
+        .unwrap();
+        black_box(&line.bytes[..line.len]);
+    }
+    let elapsed = started.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "log_format: {:.1} ns/record, {} allocations/record",
+        elapsed.as_nanos() as f64 / RECORDS as f64,
+        allocations as f64 / RECORDS as f64
+    );
+    assert_eq!(allocations, 0, "formatting a record allocated");
+}
@@ -0,0 +1,31 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+/// Write the C header for the `capi` feature to `$OUT_DIR/include/ctapi_rs.h`
+///
+/// The build must not write into the source tree, which may be read-only
+/// (a vendored or registry copy).
+#[cfg(feature = "capi")]
+fn generate_header() {
+    use std::env;
+    use std::path::Path;
+
+    let manifest_dir_string = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let manifest_dir = Path::new(&manifest_dir_string);
+    let include_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("include");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src/capi.rs");
+
+    let config = cbindgen::Config::from_file(manifest_dir.join("cbindgen.toml"))
+        .expect("failed to read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(manifest_dir)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate C header")
+        .write_to_file(include_dir.join("ctapi_rs.h"));
+}
@@ -0,0 +1,559 @@
+//! Trait over the client calls application code makes
+//!
+//! Code that reads and writes tags through [`CitectApi`] instead of a
+//! concrete [`CtClient`] can be unit tested without CtAPI: with the `mock`
+//! feature, [`MockCitectClient`] answers from in-memory tag values, Cicode
+//! results and tables, and can be told to fail or to be slow.
+//!
+//! [`MockCitectClient`] only knows values. Tests of behaviour that depends on
+//! CtAPI itself - quality, list handles, reconnects, overlapped I/O - open a
+//! real [`CtClient`] on a [`MockServer`](crate::mock::MockServer) instead.
+//!
+//! ```
+//! use ctapi_rs::{CitectApi, CtApiError, CtClient, MockCitectClient};
+//!
+//! /// Start the pump unless it runs already
+//! fn start_pump<C: CitectApi>(client: &C) -> Result<bool, CtApiError> {
+//!     if client.tag_read("Pump1")? == "1" {
+//!         return Ok(false);
+//!     }
+//!     client.tag_write("Pump_Start", 1)?;
+//!     Ok(true)
+//! }
+//!
+//! let client = MockCitectClient::new().with_tag("Pump1", "0");
+//! assert!(start_pump(&client)?);
+//! assert_eq!(client.tag("Pump_Start").as_deref(), Some("1"));
+//!
+//! let client = CtClient::open_mock()?;
+//! assert!(!start_pump(&client)?);
+//! # Ok::<(), CtApiError>(())
+//! ```
+
+use crate::cicode::CicodeWindow;
+use crate::error::Result;
+use crate::query::Record;
+use crate::write::TagValue;
+use crate::{CtClient, CtList, CtTagValueItems};
+use std::sync::Arc;
+
+#[cfg(feature = "mock")]
+pub use self::mock::{MockCitectClient, MockCitectList};
+
+/// Tag, Cicode, search and list calls of a client
+///
+/// Implemented by [`CtClient`] and, with the `mock` feature, by
+/// [`MockCitectClient`]. The methods behave like the [`CtClient`] methods of
+/// the same name.
+pub trait CitectApi {
+    /// Tag list created by [`list_new`](Self::list_new)
+    type List: CitectList;
+
+    /// Read a tag value
+    fn tag_read(&self, tag: &str) -> Result<String>;
+
+    /// Read a tag value with its timestamps and quality
+    fn tag_read_ex(&self, tag: &str, tagvalue_items: &mut CtTagValueItems) -> Result<String>;
+
+    /// Write a tag value
+    fn tag_write(&self, tag: &str, value: impl TagValue) -> Result<()>;
+
+    /// Run a Cicode command and return its result
+    fn cicode(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String>;
+
+    /// Records of `table_name` matching `filter`
+    fn find_first(
+        &self,
+        table_name: &str,
+        filter: &str,
+        cluster: Option<&str>,
+    ) -> Box<dyn Iterator<Item = Result<Record>> + '_>;
+
+    /// Create a tag list
+    fn list_new(&self, mode: u32) -> Result<Self::List>;
+}
+
+/// Tag list calls, the list side of [`CitectApi`]
+pub trait CitectList {
+    /// Add a tag to the list
+    fn add_tag(&self, tag: &str) -> Result<()>;
+
+    /// Remove a tag from the list
+    fn delete_tag(&self, tag: &str) -> Result<()>;
+
+    /// Read all tags of the list
+    fn read(&self) -> Result<()>;
+
+    /// Value of a tag from the last [`read`](Self::read)
+    fn read_tag(&self, tag: &str, mode: u32) -> Result<String>;
+
+    /// Write a tag of the list
+    fn write_tag(&self, tag: &str, value: &str) -> Result<()>;
+}
+
+impl CitectApi for CtClient {
+    type List = CtList;
+
+    fn tag_read(&self, tag: &str) -> Result<String> {
+        CtClient::tag_read(self, tag)
+    }
+
+    fn tag_read_ex(&self, tag: &str, tagvalue_items: &mut CtTagValueItems) -> Result<String> {
+        CtClient::tag_read_ex(self, tag, tagvalue_items)
+    }
+
+    fn tag_write(&self, tag: &str, value: impl TagValue) -> Result<()> {
+        CtClient::tag_write(self, tag, value)
+    }
+
+    fn cicode(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String> {
+        CtClient::cicode(self, cmd, vh_win, mode)
+    }
+
+    fn find_first(
+        &self,
+        table_name: &str,
+        filter: &str,
+        cluster: Option<&str>,
+    ) -> Box<dyn Iterator<Item = Result<Record>> + '_> {
+        Box::new(CtClient::find_first(self, table_name, filter, cluster).records())
+    }
+
+    /// Create a list on a clone of the client, which shares its connection
+    fn list_new(&self, mode: u32) -> Result<CtList> {
+        Arc::new(self.clone()).list_new(mode)
+    }
+}
+
+impl CitectList for CtList {
+    fn add_tag(&self, tag: &str) -> Result<()> {
+        CtList::add_tag(self, tag)
+    }
+
+    fn delete_tag(&self, tag: &str) -> Result<()> {
+        CtList::delete_tag(self, tag)
+    }
+
+    fn read(&self) -> Result<()> {
+        CtList::read(self)
+    }
+
+    fn read_tag(&self, tag: &str, mode: u32) -> Result<String> {
+        CtList::read_tag(self, tag, mode)
+    }
+
+    fn write_tag(&self, tag: &str, value: &str) -> Result<()> {
+        CtList::write_tag(self, tag, value)
+    }
+}
+
+#[cfg(feature = "mock")]
+mod mock {
+    use super::{CitectApi, CitectList};
+    use crate::CtTagValueItems;
+    use crate::cicode::CicodeWindow;
+    use crate::error::{CtApiError, Result};
+    use crate::filetime;
+    use crate::mock::{matches_filter, parse_filter};
+    use crate::quality::QUAL_GOOD;
+    use crate::query::Record;
+    use crate::write::TagValue;
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::io;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Win32 `ERROR_INVALID_FUNCTION`, reported for Cicode without a result
+    const ERROR_INVALID_FUNCTION: i32 = 1;
+
+    /// Win32 `ERROR_NOT_FOUND`, reported for unknown tags and tables
+    const ERROR_NOT_FOUND: i32 = 1168;
+
+    /// Names are compared case-insensitively, like Citect SCADA does
+    fn key(name: &str) -> String {
+        name.to_ascii_lowercase()
+    }
+
+    fn os_error(code: i32) -> CtApiError {
+        io::Error::from_raw_os_error(code).into()
+    }
+
+    #[derive(Debug, Default)]
+    struct State {
+        tags: HashMap<String, String>,
+        cicode: HashMap<String, String>,
+        tables: HashMap<String, Vec<Vec<(String, String)>>>,
+        /// Win32 error codes of the tags, commands and tables that fail
+        failures: HashMap<String, i32>,
+        latency: Duration,
+    }
+
+    /// In-memory [`CitectApi`] for unit tests of application code
+    ///
+    /// Tag reads and writes go to a `HashMap` of values, Cicode commands are
+    /// answered from a table of results, and searches walk preloaded records.
+    /// Names are case-insensitive. Clones share their state, so a test can
+    /// hand one to the code under test and check the writes with
+    /// [`tag`](Self::tag) on another.
+    ///
+    /// Unknown tags and tables fail with `ERROR_NOT_FOUND` and unknown Cicode
+    /// with `ERROR_INVALID_FUNCTION`, as [`CtClient`](crate::CtClient) reports
+    /// them.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CitectApi, MockCitectClient};
+    /// use std::time::Duration;
+    ///
+    /// let client = MockCitectClient::new()
+    ///     .with_tag("Level", "42.5")
+    ///     .with_record("Tag", &[("TAG", "Level"), ("UNITS", "%")])
+    ///     .with_failure("Valve1", 1236)
+    ///     .with_latency(Duration::from_millis(1));
+    /// assert_eq!(client.tag_read("LEVEL")?, "42.5");
+    /// assert!(client.tag_write("Valve1", 1).is_err());
+    ///
+    /// let record = client.find_first("Tag", "TAG=Level", None).next().unwrap()?;
+    /// assert_eq!(record.get("UNITS"), Some("%"));
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct MockCitectClient {
+        state: Arc<Mutex<State>>,
+    }
+
+    impl MockCitectClient {
+        /// Client without tags, Cicode results or tables
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Add a tag with its value
+        pub fn with_tag(self, name: &str, value: &str) -> Self {
+            self.lock().tags.insert(key(name), value.to_string());
+            self
+        }
+
+        /// Answer the Cicode command `cmd` with `result`
+        pub fn with_cicode(self, cmd: &str, result: &str) -> Self {
+            self.lock().cicode.insert(key(cmd), result.to_string());
+            self
+        }
+
+        /// Add a record to `table`, as `(field, value)` pairs
+        pub fn with_record(self, table: &str, fields: &[(&str, &str)]) -> Self {
+            let record = fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            self.lock()
+                .tables
+                .entry(key(table))
+                .or_default()
+                .push(record);
+            self
+        }
+
+        /// Fail every call on the tag, Cicode command or table `name` with
+        /// the Win32 error `code`
+        pub fn with_failure(self, name: &str, code: i32) -> Self {
+            self.lock().failures.insert(key(name), code);
+            self
+        }
+
+        /// Delay every call by `latency`
+        pub fn with_latency(self, latency: Duration) -> Self {
+            self.lock().latency = latency;
+            self
+        }
+
+        /// Current value of a tag, including the values written to it
+        pub fn tag(&self, name: &str) -> Option<String> {
+            self.lock().tags.get(&key(name)).cloned()
+        }
+
+        fn lock(&self) -> MutexGuard<'_, State> {
+            self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        /// Wait out the latency, then fail with the error code `name` is set
+        /// to fail with
+        fn call(&self, name: &str) -> std::result::Result<MutexGuard<'_, State>, i32> {
+            let latency = self.lock().latency;
+            if !latency.is_zero() {
+                thread::sleep(latency);
+            }
+            let state = self.lock();
+            match state.failures.get(&key(name)) {
+                Some(&code) => Err(code),
+                None => Ok(state),
+            }
+        }
+
+        /// Value of `tag`, or the Win32 error code reading it fails with
+        fn value(&self, tag: &str) -> std::result::Result<String, i32> {
+            let state = self.call(tag)?;
+            state.tags.get(&key(tag)).cloned().ok_or(ERROR_NOT_FOUND)
+        }
+    }
+
+    impl CitectApi for MockCitectClient {
+        type List = MockCitectList;
+
+        fn tag_read(&self, tag: &str) -> Result<String> {
+            self.value(tag).map_err(os_error)
+        }
+
+        /// The value, stamped now with good quality
+        fn tag_read_ex(&self, tag: &str, tagvalue_items: &mut CtTagValueItems) -> Result<String> {
+            let value = self.tag_read(tag)?;
+            let now = filetime::from_utc(Utc::now());
+            *tagvalue_items = CtTagValueItems {
+                length: tagvalue_items.length,
+                timestamp: now,
+                value_timestamp: now,
+                quality_timestamp: now,
+                quality_general: QUAL_GOOD,
+                ..CtTagValueItems::default()
+            };
+            Ok(value)
+        }
+
+        /// Store the value, also for tags the client did not know
+        fn tag_write(&self, tag: &str, value: impl TagValue) -> Result<()> {
+            let value = value.to_tag_value().into_owned();
+            let mut state = self.call(tag).map_err(os_error)?;
+            state.tags.insert(key(tag), value);
+            Ok(())
+        }
+
+        fn cicode(&self, cmd: &str, _vh_win: CicodeWindow, _mode: u32) -> Result<String> {
+            let state = self.call(cmd).map_err(os_error)?;
+            let result = state.cicode.get(&key(cmd)).cloned();
+            result.ok_or_else(|| os_error(ERROR_INVALID_FUNCTION))
+        }
+
+        /// Records of a preloaded table matching `filter`; `cluster` is ignored
+        fn find_first(
+            &self,
+            table_name: &str,
+            filter: &str,
+            _cluster: Option<&str>,
+        ) -> Box<dyn Iterator<Item = Result<Record>> + '_> {
+            let records = self.call(table_name).and_then(|state| {
+                let table = state.tables.get(&key(table_name)).ok_or(ERROR_NOT_FOUND)?;
+                let filter = parse_filter(filter);
+                Ok(table
+                    .iter()
+                    .filter(|record| matches_filter(record, &filter))
+                    .map(|record| Ok(Record::new(record.clone())))
+                    .collect::<Vec<Result<Record>>>())
+            });
+            match records {
+                Ok(records) => Box::new(records.into_iter()),
+                Err(code) => Box::new(std::iter::once(Err(os_error(code)))),
+            }
+        }
+
+        fn list_new(&self, _mode: u32) -> Result<MockCitectList> {
+            Ok(MockCitectList {
+                client: self.clone(),
+                tags: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    /// Tag list of a [`MockCitectClient`]
+    ///
+    /// [`read`](CitectList::read) copies the current values of its tags;
+    /// [`read_tag`](CitectList::read_tag) returns the copy, whatever the
+    /// `mode`.
+    #[derive(Debug)]
+    pub struct MockCitectList {
+        client: MockCitectClient,
+        tags: Mutex<Vec<ListTag>>,
+    }
+
+    /// Tag on a [`MockCitectList`], with the value or error code of its last read
+    type ListTag = (String, Option<std::result::Result<String, i32>>);
+
+    impl MockCitectList {
+        fn lock(&self) -> MutexGuard<'_, Vec<ListTag>> {
+            self.tags.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        fn position(tags: &[ListTag], tag: &str) -> Result<usize> {
+            tags.iter()
+                .position(|(name, _)| name.eq_ignore_ascii_case(tag))
+                .ok_or_else(|| CtApiError::TagNotFound {
+                    tag: tag.to_string(),
+                })
+        }
+    }
+
+    impl CitectList for MockCitectList {
+        fn add_tag(&self, tag: &str) -> Result<()> {
+            let mut tags = self.lock();
+            if Self::position(&tags, tag).is_err() {
+                tags.push((tag.to_string(), None));
+            }
+            Ok(())
+        }
+
+        fn delete_tag(&self, tag: &str) -> Result<()> {
+            let mut tags = self.lock();
+            let index = Self::position(&tags, tag)?;
+            tags.remove(index);
+            Ok(())
+        }
+
+        /// Read every tag; a tag that fails only fails its
+        /// [`read_tag`](CitectList::read_tag)
+        fn read(&self) -> Result<()> {
+            let mut tags = self.lock();
+            for (name, value) in tags.iter_mut() {
+                *value = Some(self.client.value(name));
+            }
+            Ok(())
+        }
+
+        fn read_tag(&self, tag: &str, _mode: u32) -> Result<String> {
+            let tags = self.lock();
+            let index = Self::position(&tags, tag)?;
+            match &tags[index].1 {
+                Some(Ok(value)) => Ok(value.clone()),
+                Some(Err(code)) => Err(os_error(*code)),
+                None => Err(os_error(ERROR_NOT_FOUND)),
+            }
+        }
+
+        fn write_tag(&self, tag: &str, value: &str) -> Result<()> {
+            Self::position(&self.lock(), tag)?;
+            self.client.tag_write(tag, value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Application code under test, written against the trait
+    fn total<C: CitectApi>(client: &C, tags: &[&str]) -> Result<f64> {
+        let list = client.list_new(0)?;
+        for tag in tags {
+            list.add_tag(tag)?;
+        }
+        list.read()?;
+        let mut total = 0.0;
+        for tag in tags {
+            total += list.read_tag(tag, 0)?.parse::<f64>().unwrap_or_default();
+        }
+        Ok(total)
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_client_and_mock_client_are_interchangeable() {
+        let client = CtClient::open_mock().unwrap();
+        let tags = ["Temperature", "Pressure"];
+        let mock = MockCitectClient::new()
+            .with_tag("Temperature", "25.5")
+            .with_tag("Pressure", "1.2");
+        assert_eq!(total(&client, &tags).unwrap(), total(&mock, &tags).unwrap());
+
+        let records: Vec<_> = CitectApi::find_first(&client, "Tag", "", None).collect();
+        assert!(!records.is_empty());
+        assert!(records.iter().all(|record| record.is_ok()));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_mock_client_reads_writes_and_failures() {
+        use crate::CtApiError;
+        use crate::reconnect::is_connection_down;
+        use std::time::{Duration, Instant};
+
+        let client = MockCitectClient::new()
+            .with_tag("Level", "10")
+            .with_cicode("Time(1)", "10:30:00")
+            .with_failure("Valve1", 1236)
+            .with_latency(Duration::from_millis(5));
+        let code = client.clone();
+
+        let started = Instant::now();
+        assert_eq!(code.tag_read("level").unwrap(), "10");
+        assert!(started.elapsed() >= Duration::from_millis(5));
+
+        code.tag_write("Level", 12.5).unwrap();
+        code.tag_write("Setpoint", 3).unwrap();
+        assert_eq!(client.tag("Level").as_deref(), Some("12.5"));
+        assert_eq!(client.tag("SETPOINT").as_deref(), Some("3"));
+
+        let mut items = CtTagValueItems::default();
+        assert_eq!(code.tag_read_ex("Level", &mut items).unwrap(), "12.5");
+        assert_ne!({ items.timestamp }, 0);
+
+        assert_eq!(
+            code.cicode("Time(1)", CicodeWindow::NONE, 0).unwrap(),
+            "10:30:00"
+        );
+        assert!(code.cicode("Unknown()", CicodeWindow::NONE, 0).is_err());
+        assert!(code.tag_read("Missing").is_err());
+        let error = code.tag_write("Valve1", 1).unwrap_err();
+        assert!(is_connection_down(&error));
+
+        // A list reports the failure of its tag at read_tag, not at read
+        let list = code.list_new(0).unwrap();
+        list.add_tag("Level").unwrap();
+        list.add_tag("Valve1").unwrap();
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Level", 0).unwrap(), "12.5");
+        assert!(is_connection_down(&list.read_tag("Valve1", 0).unwrap_err()));
+        list.delete_tag("Valve1").unwrap();
+        assert!(matches!(
+            list.read_tag("Valve1", 0),
+            Err(CtApiError::TagNotFound { .. })
+        ));
+        list.write_tag("Level", "7").unwrap();
+        assert_eq!(client.tag("Level").as_deref(), Some("7"));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_mock_client_find() {
+        use crate::reconnect::is_connection_down;
+
+        let client = MockCitectClient::new()
+            .with_record("Tag", &[("TAG", "Pump1"), ("CLUSTER", "Cluster1")])
+            .with_record("Tag", &[("TAG", "Pump2"), ("CLUSTER", "Cluster2")])
+            .with_record("Tag", &[("TAG", "Valve1"), ("CLUSTER", "Cluster1")])
+            .with_failure("Alarm", 1236);
+
+        let names = |filter: &str| -> Vec<String> {
+            client
+                .find_first("TAG", filter, None)
+                .map(|record| record.unwrap().get("TAG").unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names(""), ["Pump1", "Pump2", "Valve1"]);
+        assert_eq!(names("TAG=Pump*"), ["Pump1", "Pump2"]);
+        assert_eq!(
+            names("CLUSTER=Cluster1 OR TAG=Pump2"),
+            ["Pump1", "Pump2", "Valve1"]
+        );
+
+        let mut failed = client.find_first("Alarm", "", None);
+        assert!(is_connection_down(&failed.next().unwrap().unwrap_err()));
+        assert!(failed.next().is_none());
+        assert!(
+            client
+                .find_first("Trend", "", None)
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+}
@@ -12,14 +12,14 @@
 //!
 //! # Examples
 //!
-//! ```no_run
-//! use ctapi_rs::{CtClient, FutureCtClient};
+//! ```
+//! use ctapi_rs::{CicodeWindow, CtClient, FutureCtClient};
 //!
 //! async fn run() -> anyhow::Result<()> {
-//!     let client = CtClient::open(None, None, None, 0)?;
+//!     let client = CtClient::open_mock()?;
 //!
 //!     // Await directly — no tokio::spawn_blocking needed
-//!     let result = client.cicode_future("Time(1)", 0, 0)?.await?;
+//!     let result = client.cicode_future("Time(1)", CicodeWindow::NONE, 0)?.await?;
 //!     println!("Time: {}", result);
 //!     Ok(())
 //! }
@@ -28,15 +28,20 @@
 use std::future::Future;
 use std::os::windows::io::RawHandle;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
 use crate::CtClient;
+use crate::cicode::CicodeWindow;
+use crate::client::ERROR_IO_PENDING;
 use crate::error::{CtApiError, Result};
-use crate::util::encode_to_gbk_cstring;
-use ctapi_sys::*;
-use encoding_rs::GBK;
+use crate::ffi::*;
+use crate::io_stats::IoKind;
+use crate::pending::{PendingOps, PendingPermit};
+use crate::sync::{AtomicBool, Mutex, MutexGuard, Ordering};
+use crate::util::{decode_gbk_until_nul, encode_to_gbk_cstring};
+use crate::write::wait_millis;
 use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
 use windows_sys::Win32::System::Threading::CreateEventA;
 use windows_sys::Win32::System::Threading::WaitForSingleObject;
@@ -87,18 +92,122 @@ unsafe impl Send for WinEvent {}
 unsafe impl Sync for WinEvent {}
 
 // ───────────────────────────────────────────────
-// FutureState — shared between CtApiFuture and the waker thread
+// WakerSlot — shared between CtApiFuture and the waker thread
 // ───────────────────────────────────────────────
 
-struct FutureState {
-    waker: Mutex<Option<Waker>>,
-    cancelled: AtomicBool,
+#[derive(Default)]
+struct SlotState {
+    waker: Option<Waker>,
+    fired: bool,
+    cancelled: bool,
+}
+
+/// Hand-off of a completion from the waker thread to the awaiting task.
+///
+/// The thread [`fire`](Self::fire)s the slot once the event is signalled, the
+/// future [`register`](Self::register)s its current waker on every pending
+/// poll and [`cancel`](Self::cancel)s the slot when dropped. All three take
+/// the same lock, so a waker registered after the completion is reported
+/// instead of being lost, and nothing is woken once the slot is cancelled.
+struct WakerSlot {
+    state: Mutex<SlotState>,
+}
+
+impl WakerSlot {
+    fn new(waker: Waker) -> Self {
+        Self {
+            state: Mutex::new(SlotState {
+                waker: Some(waker),
+                ..SlotState::default()
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SlotState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the waker to wake; `false` if the slot has already fired.
+    fn register(&self, waker: &Waker) -> bool {
+        let mut state = self.lock();
+        if state.fired {
+            return false;
+        }
+        state.waker = Some(waker.clone());
+        true
+    }
+
+    /// Wake the registered waker, unless the slot was cancelled.
+    fn fire(&self) {
+        let waker = {
+            let mut state = self.lock();
+            if state.cancelled {
+                return;
+            }
+            state.fired = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Drop the waker and stop any later [`fire`](Self::fire) from waking.
+    fn cancel(&self) {
+        let mut state = self.lock();
+        state.cancelled = true;
+        state.waker = None;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.lock().cancelled
+    }
+}
+
+// ───────────────────────────────────────────────
+// InFlight — guards an OVERLAPPED against a second start
+// ───────────────────────────────────────────────
+
+/// Marks an operation as started until its result is collected.
+///
+/// Starting a second operation on an OVERLAPPED that CtAPI is still using
+/// would let both write into the same status and buffer.
+struct InFlight(AtomicBool);
+
+impl InFlight {
+    fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+
+    /// Mark the operation started; `false` if it already was.
+    fn begin(&self) -> bool {
+        self.0
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// Mark the result collected.
+    fn finish(&self) {
+        self.0.store(false, Ordering::Release);
+    }
 }
 
 // ───────────────────────────────────────────────
 // AsyncOperation
 // ───────────────────────────────────────────────
 
+/// How CtAPI accepted an asynchronous call.
+///
+/// Either way the result is collected from the [`AsyncOperation`]; a call
+/// that failed to start is reported as an error instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Started {
+    /// The call was queued (`ERROR_IO_PENDING`) and completes later.
+    Pending,
+    /// The call completed before CtAPI returned.
+    Completed,
+}
+
 /// Represents an asynchronous operation handle.
 ///
 /// This structure wraps a Windows OVERLAPPED structure and provides safe
@@ -112,19 +221,19 @@ struct FutureState {
 ///
 /// # Examples
 ///
-/// ```no_run
-/// use ctapi_rs::{CtClient, AsyncOperation};
+/// ```
+/// use ctapi_rs::{AsyncOperation, CicodeWindow, CtClient};
 ///
-/// let client = CtClient::open(None, None, None, 0)?;
+/// let client = CtClient::open_mock()?;
 /// let mut async_op = AsyncOperation::new();
 ///
 /// // Start async cicode execution
 /// use ctapi_rs::AsyncCtClient;
-/// client.cicode_async("SomeFunction()", 0, 0, &mut async_op)?;
+/// client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut async_op)?;
 ///
 /// // Wait for completion
 /// let result = async_op.get_result(&client)?;
-/// println!("Result: {}", result);
+/// assert_eq!(result, "10:30:00");
 /// # Ok::<(), ctapi_rs::CtApiError>(())
 /// ```
 pub struct AsyncOperation {
@@ -133,6 +242,9 @@ pub struct AsyncOperation {
     /// Ref-counted event handle — shared with [`CtApiFuture`]'s waker thread so
     /// that the kernel object is not closed while a thread is waiting on it.
     win_event: Arc<WinEvent>,
+    in_flight: InFlight,
+    /// Slot of the client's pending-operation limit held while started
+    permit: Option<PendingPermit>,
 }
 
 impl AsyncOperation {
@@ -159,6 +271,8 @@ impl AsyncOperation {
             overlapped,
             buffer,
             win_event,
+            in_flight: InFlight::new(),
+            permit: None,
         }
     }
 
@@ -172,16 +286,72 @@ impl AsyncOperation {
         &mut self.overlapped
     }
 
+    /// Mark the operation started before handing it to CtAPI.
+    ///
+    /// Takes a slot from `pending`, the limit of the client the operation is
+    /// started on, until the operation is finished.
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - The previous operation started on
+    ///   it is still pending
+    /// * [`CtApiError::TooManyPendingOps`] - The client has no free slot
+    /// * [`CtApiError::Timeout`] - No slot became free while queued
+    pub(crate) fn begin(&mut self, pending: &Arc<PendingOps>) -> Result<()> {
+        // A completed operation may be restarted even if its result was never collected
+        if !self.in_flight.begin() && !self.is_complete() {
+            return Err(CtApiError::InvalidParameter {
+                param: "async_op".to_string(),
+                value: "operation still pending".to_string(),
+            });
+        }
+        // Return the slot of an uncollected previous operation before queueing
+        self.permit = None;
+        match pending.acquire() {
+            Ok(permit) => {
+                self.permit = Some(permit);
+                Ok(())
+            }
+            Err(e) => {
+                self.in_flight.finish();
+                Err(e)
+            }
+        }
+    }
+
+    /// Release the operation for reuse and return its slot.
+    fn finish(&mut self) {
+        self.in_flight.finish();
+        self.permit = None;
+    }
+
+    /// Interpret the return value `ok` of the CtAPI call that started this operation.
+    ///
+    /// `ERROR_IO_PENDING` means the call was queued. Any other failure means
+    /// it never started: the operation is released for reuse, as CtAPI will
+    /// never signal its OVERLAPPED. Must be called before anything else can
+    /// overwrite the thread's last Win32 error.
+    pub(crate) fn started(&mut self, ok: bool) -> Result<Started> {
+        if ok {
+            return Ok(Started::Completed);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(ERROR_IO_PENDING) {
+            return Ok(Started::Pending);
+        }
+        self.finish();
+        Err(err.into())
+    }
+
     /// Return `true` if the async operation has completed.
     ///
     /// The check is based on `dwStatus != STATUS_PENDING (0x103)`.
     ///
     /// # Examples
-    /// ```no_run
-    /// # use ctapi_rs::{CtClient, AsyncOperation, AsyncCtClient};
-    /// # let client = CtClient::open(None, None, None, 0)?;
+    /// ```
+    /// # use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
+    /// # let client = CtClient::open_mock()?;
     /// let mut op = AsyncOperation::new();
-    /// client.cicode_async("Sleep(5)", 0, 0, &mut op)?;
+    /// client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut op)?;
     ///
     /// while !op.is_complete() {
     ///     std::thread::sleep(std::time::Duration::from_millis(100));
@@ -208,13 +378,13 @@ impl AsyncOperation {
     /// * [`CtApiError::System`] - Operation failed or was cancelled.
     ///
     /// # Examples
-    /// ```no_run
-    /// # use ctapi_rs::{CtClient, AsyncOperation, AsyncCtClient};
-    /// # let client = CtClient::open(None, None, None, 0)?;
+    /// ```
+    /// # use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
+    /// # let client = CtClient::open_mock()?;
     /// let mut op = AsyncOperation::new();
-    /// client.cicode_async("Time(1)", 0, 0, &mut op)?;
+    /// client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut op)?;
     /// let result = op.get_result(&client)?;
-    /// println!("Time: {}", result);
+    /// assert_eq!(result, "10:30:00");
     /// # Ok::<(), ctapi_rs::CtApiError>(())
     /// ```
     pub fn get_result(&mut self, client: &CtClient) -> Result<String> {
@@ -229,11 +399,11 @@ impl AsyncOperation {
     /// * `client` - The [`CtClient`] used to start this operation.
     ///
     /// # Examples
-    /// ```no_run
-    /// # use ctapi_rs::{CtClient, AsyncOperation, AsyncCtClient};
-    /// # let client = CtClient::open(None, None, None, 0)?;
+    /// ```
+    /// # use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
+    /// # let client = CtClient::open_mock()?;
     /// let mut op = AsyncOperation::new();
-    /// client.cicode_async("LongFunc()", 0, 0, &mut op)?;
+    /// client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut op)?;
     ///
     /// loop {
     ///     match op.try_get_result(&client) {
@@ -257,18 +427,17 @@ impl AsyncOperation {
                 &mut bytes_transferred,
                 false,
             ) {
+                self.finish();
                 let result_len = bytes_transferred.min(self.buffer.len() as u32) as usize;
                 let result_slice = &self.buffer[..result_len];
-                let result = std::ffi::CStr::from_bytes_until_nul(result_slice)
-                    .map_err(CtApiError::FromBytesUntilNul)
-                    .map(|cstr| GBK.decode(cstr.to_bytes()).0.to_string());
-                Some(result)
+                Some(decode_gbk_until_nul(result_slice))
             } else {
                 let err = std::io::Error::last_os_error();
-                if err.raw_os_error() == Some(997) {
+                if err.raw_os_error() == Some(ERROR_IO_PENDING) {
                     // ERROR_IO_INCOMPLETE — still pending
                     None
                 } else {
+                    self.finish();
                     Some(Err(err.into()))
                 }
             }
@@ -278,28 +447,26 @@ impl AsyncOperation {
     /// Attempt to cancel the pending async operation.
     ///
     /// Cancellation may not be immediate; the operation might still complete.
+    /// Same as [`CtClient::cancel_io`] with `Some(self)`.
     ///
     /// # Parameters
     /// * `client` - The [`CtClient`] used to start this operation.
     ///
+    /// # Errors
+    /// * [`CtApiError::CannotCancel`] - The operation has already completed
+    /// * [`CtApiError::System`] - Cancellation failed
+    ///
     /// # Examples
     /// ```no_run
-    /// # use ctapi_rs::{CtClient, AsyncOperation, AsyncCtClient};
+    /// # use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
     /// # let client = CtClient::open(None, None, None, 0)?;
     /// let mut op = AsyncOperation::new();
-    /// client.cicode_async("Sleep(60)", 0, 0, &mut op)?;
+    /// client.cicode_async("Sleep(60)", CicodeWindow::NONE, 0, &mut op)?;
     /// op.cancel(&client)?;
     /// # Ok::<(), ctapi_rs::CtApiError>(())
     /// ```
     pub fn cancel(&mut self, client: &CtClient) -> Result<()> {
-        // SAFETY: client.handle() is a valid CtAPI handle. &mut self.overlapped
-        // points to the OVERLAPPED struct associated with the pending operation.
-        unsafe {
-            if !ctCancelIO(client.handle(), &mut self.overlapped) {
-                return Err(std::io::Error::last_os_error().into());
-            }
-            Ok(())
-        }
+        client.cancel_io(Some(self))
     }
 
     /// Reset this `AsyncOperation` for reuse.
@@ -312,6 +479,7 @@ impl AsyncOperation {
         self.overlapped.hEvent = event_handle;
         self.overlapped.pData = self.buffer.as_mut_ptr();
         self.buffer.fill(0);
+        self.finish();
     }
 
     // ── internal ────────────────────────────────────────────────────────────
@@ -332,20 +500,48 @@ impl AsyncOperation {
                 &mut bytes_transferred,
                 wait,
             ) {
-                return Err(std::io::Error::last_os_error().into());
+                let err = std::io::Error::last_os_error();
+                if err.raw_os_error() != Some(ERROR_IO_PENDING) {
+                    self.finish();
+                }
+                return Err(err.into());
             }
+            self.finish();
             // Operations like tag writes may transfer 0 bytes — return empty string.
             if bytes_transferred == 0 {
                 return Ok(String::new());
             }
             let result_len = bytes_transferred.min(self.buffer.len() as u32) as usize;
             let result_slice = &self.buffer[..result_len];
-            let cstr = std::ffi::CStr::from_bytes_until_nul(result_slice)
-                .map_err(CtApiError::FromBytesUntilNul)?;
-            Ok(GBK.decode(cstr.to_bytes()).0.to_string())
+            decode_gbk_until_nul(result_slice)
         }
     }
 
+    /// Wait up to `timeout` for the operation and return its result.
+    ///
+    /// `None` waits forever. When the timeout elapses the operation is
+    /// cancelled with `ctCancelIO` and [`CtApiError::Timeout`] is returned
+    /// once the cancellation has completed, so the OVERLAPPED structure is
+    /// no longer in use when this returns.
+    pub(crate) fn wait_timeout(
+        &mut self,
+        client_handle: RawHandle,
+        timeout: Option<Duration>,
+    ) -> Result<String> {
+        // SAFETY: the event handle is owned by self.win_event and stays valid
+        // for the duration of the wait.
+        let waited = unsafe { WaitForSingleObject(self.win_event.handle(), wait_millis(timeout)) };
+        if waited == WAIT_TIMEOUT {
+            // SAFETY: client_handle is a valid CtAPI handle and &mut self.overlapped
+            // is the OVERLAPPED struct of the pending operation.
+            unsafe { ctCancelIO(client_handle, &mut self.overlapped) };
+            // The cancelled operation reports an error; only the timeout matters.
+            let _ = self.get_result_impl(client_handle, true);
+            return Err(CtApiError::Timeout);
+        }
+        self.get_result_impl(client_handle, true)
+    }
+
     /// Non-blocking result extraction — used by [`CtApiFuture`] after the
     /// operation is known to have completed.
     pub(crate) fn get_result_with_handle(&mut self, client_handle: RawHandle) -> Result<String> {
@@ -391,6 +587,9 @@ impl std::fmt::Debug for AsyncOperation {
 /// Dropping this future before it resolves will:
 /// 1. Signal the internal waker thread to stop.
 /// 2. Call `ctCancelIO` to cancel the pending I/O operation.
+/// 3. Wait for the cancelled operation to complete, so CtAPI no longer
+///    writes into the OVERLAPPED structure and result buffer when they
+///    are freed. The drop blocks until CtAPI has processed the cancellation.
 ///
 /// # Thread Safety
 ///
@@ -402,15 +601,15 @@ impl std::fmt::Debug for AsyncOperation {
 /// # Examples
 ///
 /// ```no_run
-/// use ctapi_rs::{CtClient, FutureCtClient};
+/// use ctapi_rs::{CicodeWindow, CtClient, FutureCtClient};
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
 ///     let client = CtClient::open(None, None, None, 0)?;
 ///
 ///     // Uses OVERLAPPED internally — no spawn_blocking needed
-///     let time  = client.cicode_future("Time(1)", 0, 0)?.await?;
-///     let date  = client.cicode_future("Date(4)", 0, 0)?.await?;
+///     let time  = client.cicode_future("Time(1)", CicodeWindow::NONE, 0)?.await?;
+///     let date  = client.cicode_future("Date(4)", CicodeWindow::NONE, 0)?.await?;
 ///     println!("{} {}", time, date);
 ///     Ok(())
 /// }
@@ -425,7 +624,7 @@ pub struct CtApiFuture {
     /// during async operations — moving the future must not move the
     /// OVERLAPPED, otherwise CtAPI writes to a dangling pointer.
     async_op: Box<AsyncOperation>,
-    state: Option<Arc<FutureState>>,
+    state: Option<Arc<WakerSlot>>,
     /// Set to true when poll returns Ready. Prevents ctCancelIO in Drop
     /// for futures that completed normally. Only accessed under &mut self
     /// (poll and drop are never concurrent for the same future).
@@ -445,7 +644,7 @@ impl CtApiFuture {
 
 // SAFETY: Arc<CtClient> is Send + Sync. Box<AsyncOperation> is Send because
 // its fields (OVERLAPPED: now Send + Sync, Vec<u8>: Send, Arc<WinEvent>: Send + Sync)
-// are all Send. Option<Arc<FutureState>> is auto-Send.
+// are all Send. Option<Arc<WakerSlot>> is auto-Send.
 unsafe impl Send for CtApiFuture {}
 
 impl Future for CtApiFuture {
@@ -463,10 +662,7 @@ impl Future for CtApiFuture {
         match &this.state {
             None => {
                 // First poll: create shared state and spawn the waker thread.
-                let state = Arc::new(FutureState {
-                    waker: Mutex::new(Some(cx.waker().clone())),
-                    cancelled: AtomicBool::new(false),
-                });
+                let state = Arc::new(WakerSlot::new(cx.waker().clone()));
                 this.state = Some(Arc::clone(&state));
 
                 // Clone the Arc so the event handle stays alive while the
@@ -477,38 +673,33 @@ impl Future for CtApiFuture {
                 std::thread::Builder::new()
                     .name("ctapi-waker".into())
                     .spawn(move || {
-                    loop {
-                        if thread_state.cancelled.load(Ordering::Relaxed) {
-                            return;
-                        }
-                        // 100 ms timeout lets us check `cancelled` regularly
-                        // so that dropping the future doesn't strand this thread.
-                        // SAFETY: win_event.handle() is a valid HANDLE from CreateEventA.
-                        // The Arc<WinEvent> keeps it alive for the thread's lifetime.
-                        let status = unsafe { WaitForSingleObject(win_event.handle(), 100) };
-
-                        if thread_state.cancelled.load(Ordering::Relaxed) {
-                            return;
-                        }
-
-                        if status != WAIT_TIMEOUT {
-                            // Operation finished (or handle error) — wake the task.
-                            if let Ok(mut lock) = thread_state.waker.lock()
-                                && let Some(waker) = lock.take()
-                            {
-                                waker.wake();
+                        loop {
+                            if thread_state.is_cancelled() {
+                                return;
+                            }
+                            // 100 ms timeout lets us check `cancelled` regularly
+                            // so that dropping the future doesn't strand this thread.
+                            // SAFETY: win_event.handle() is a valid HANDLE from CreateEventA.
+                            // The Arc<WinEvent> keeps it alive for the thread's lifetime.
+                            let status = unsafe { WaitForSingleObject(win_event.handle(), 100) };
+
+                            if status != WAIT_TIMEOUT {
+                                // Operation finished (or handle error) — wake the
+                                // task unless the future has been dropped meanwhile.
+                                thread_state.fire();
+                                return;
                             }
-                            return;
+                            // WAIT_TIMEOUT — loop and try again.
                         }
-                        // WAIT_TIMEOUT — loop and try again.
-                    }
-                })
-                .expect("failed to spawn ctapi-waker thread");
+                    })
+                    .expect("failed to spawn ctapi-waker thread");
             }
             Some(state) => {
                 // Subsequent polls (e.g. spurious wake-up): refresh the waker.
-                if let Ok(mut lock) = state.waker.lock() {
-                    *lock = Some(cx.waker().clone());
+                // If the thread fired in between, the new waker would never be
+                // woken, so poll again right away to pick up the result.
+                if !state.register(cx.waker()) {
+                    cx.waker().wake_by_ref();
                 }
             }
         }
@@ -524,16 +715,21 @@ impl Drop for CtApiFuture {
         }
         // 1. Tell the waker thread to stop.
         if let Some(state) = &self.state {
-            state.cancelled.store(true, Ordering::Relaxed);
+            state.cancel();
         }
-        // 2. Cancel the pending I/O to avoid a dangling OVERLAPPED pointer.
+        // 2. Cancel the pending I/O and 3. wait for the cancellation, as
+        // wait_timeout does: CtAPI may write into the OVERLAPPED and the
+        // buffer until the operation completes, and both are freed below.
         if !self.async_op.is_complete() {
+            let handle = self.client.handle();
             // SAFETY: self.client is an Arc<CtClient> that keeps the CtAPI
             // connection alive until this drop completes. The OVERLAPPED pointer
             // is from self.async_op which is Box-allocated and stable.
             unsafe {
-                let _ = ctCancelIO(self.client.handle(), self.async_op.overlapped_mut());
+                let _ = ctCancelIO(handle, self.async_op.overlapped_mut());
             }
+            // The cancelled operation reports an error nobody is waiting for.
+            let _ = self.async_op.get_result_impl(handle, true);
         }
     }
 }
@@ -563,68 +759,81 @@ pub trait AsyncCtClient {
     ///
     /// # Parameters
     /// * `cmd`      - Cicode command string.
-    /// * `vh_win`   - Window handle, usually `0`.
+    /// * `vh_win`   - Window to run the function in, usually `0` ([`CicodeWindow::NONE`]).
     /// * `mode`     - Execution mode flag.
     /// * `async_op` - [`AsyncOperation`] to associate with this call.
     ///
+    /// Returns whether CtAPI queued the call or completed it immediately;
+    /// in both cases the result is collected from `async_op`.
+    ///
     /// # Errors
-    /// * [`CtApiError::System`] - Failed to start the operation.
+    /// * [`CtApiError::System`] - Failed to start the operation. `async_op`
+    ///   is not in use and must not be waited on.
+    /// * [`CtApiError::InvalidParameter`] - `async_op` is still in use by a
+    ///   pending operation.
+    /// * [`CtApiError::TooManyPendingOps`] - The client's [pending-operation
+    ///   limit](CtClient::set_pending_limit) is reached.
+    /// * [`CtApiError::Timeout`] - The call was queued by that limit and no
+    ///   slot became free in time.
     ///
     /// # Examples
-    /// ```no_run
-    /// use ctapi_rs::{CtClient, AsyncOperation, AsyncCtClient};
+    /// ```
+    /// use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
     ///
-    /// let client = CtClient::open(None, None, None, 0)?;
+    /// let client = CtClient::open_mock()?;
     /// let mut op = AsyncOperation::new();
-    /// client.cicode_async("Time(1)", 0, 0, &mut op)?;
+    /// let started = client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut op)?;
+    /// println!("{started:?}");
     /// let result = op.get_result(&client)?;
     /// # Ok::<(), ctapi_rs::CtApiError>(())
     /// ```
     fn cicode_async(
         &self,
         cmd: &str,
-        vh_win: u32,
+        vh_win: CicodeWindow,
         mode: u32,
         async_op: &mut AsyncOperation,
-    ) -> Result<()>;
+    ) -> Result<Started>;
 }
 
 impl AsyncCtClient for CtClient {
     fn cicode_async(
         &self,
         cmd: &str,
-        vh_win: u32,
+        vh_win: CicodeWindow,
         mode: u32,
         async_op: &mut AsyncOperation,
-    ) -> Result<()> {
+    ) -> Result<Started> {
+        self.ensure_open()?;
         let cmd = encode_to_gbk_cstring(cmd).map_err(|_| CtApiError::InvalidParameter {
             param: "cmd".to_string(),
             value: cmd.to_string(),
         })?;
+        async_op.begin(self.pending())?;
+        let request_bytes = cmd.as_bytes().len();
+        self.io_counters().record(IoKind::Cicode, request_bytes, 0);
 
         // SAFETY: self.handle() is a valid CtAPI connection handle. cmd is a
         // GBK-encoded CString whose pointer is valid for this call. The buffer
         // pointer and length come from async_op which outlives this call.
         // async_op.overlapped_mut() returns a pointer to the OVERLAPPED struct
         // that will track the async completion.
-        unsafe {
-            if !ctCicode(
+        let calls = self.call_log();
+        let started = calls.start();
+        let ok = unsafe {
+            ctCicode(
                 self.handle(),
                 cmd.as_ptr(),
-                vh_win,
+                vh_win.raw(),
                 mode,
                 async_op.buffer.as_mut_ptr() as *mut i8,
                 async_op.buffer.len() as u32,
                 async_op.overlapped_mut(),
-            ) {
-                let err = std::io::Error::last_os_error();
-                // ERROR_IO_PENDING (997) is expected for async operations.
-                if err.raw_os_error() != Some(997) {
-                    return Err(err.into());
-                }
-            }
-            Ok(())
-        }
+            )
+        };
+        // An overlapped call is logged when it starts
+        calls.finish(started, "ctCicode", cmd.as_bytes(), ok);
+        async_op.started(ok)
     }
 }
 
@@ -640,18 +849,18 @@ impl AsyncCtClient for CtClient {
 ///
 /// # Examples
 ///
-/// ```no_run
-/// use ctapi_rs::{CtClient, FutureCtClient};
+/// ```
+/// use ctapi_rs::{CicodeWindow, CtClient, FutureCtClient};
 /// use std::sync::Arc;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let client = Arc::new(CtClient::open(None, None, None, 0)?);
+///     let client = Arc::new(CtClient::open_mock()?);
 ///
 ///     // Fire two Cicode calls concurrently
 ///     let (time, date) = tokio::try_join!(
-///         client.cicode_future("Time(1)", 0, 0)?,
-///         client.cicode_future("Date(4)", 0, 0)?,
+///         client.cicode_future("Time(1)", CicodeWindow::NONE, 0)?,
+///         client.cicode_future("Date(4)", CicodeWindow::NONE, 0)?,
 ///     )?;
 ///     println!("{} {}", time, date);
 ///     Ok(())
@@ -666,24 +875,24 @@ pub trait FutureCtClient {
     ///
     /// # Parameters
     /// * `cmd`    - Cicode command string.
-    /// * `vh_win` - Window handle, usually `0`.
+    /// * `vh_win` - Window to run the function in, usually `0` ([`CicodeWindow::NONE`]).
     /// * `mode`   - Execution mode flag.
     ///
     /// # Errors
     /// Returns `Err` immediately if the operation cannot be started.
     ///
     /// # Examples
-    /// ```no_run
-    /// use ctapi_rs::{CtClient, FutureCtClient};
+    /// ```
+    /// use ctapi_rs::{CicodeWindow, CtClient, FutureCtClient};
     ///
     /// # async fn run() -> anyhow::Result<()> {
-    /// let client = CtClient::open(None, None, None, 0)?;
-    /// let result = client.cicode_future("Version()", 0, 0)?.await?;
+    /// let client = CtClient::open_mock()?;
+    /// let result = client.cicode_future("Version(0)", CicodeWindow::NONE, 0)?.await?;
     /// println!("Version: {}", result);
     /// # Ok(())
     /// # }
     /// ```
-    fn cicode_future(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<CtApiFuture>;
+    fn cicode_future(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<CtApiFuture>;
 
     /// Write a tag value asynchronously and return a [`CtApiFuture`] that can be
     /// `.await`ed.
@@ -699,11 +908,11 @@ pub trait FutureCtClient {
     /// Returns `Err` immediately if the operation cannot be started.
     ///
     /// # Examples
-    /// ```no_run
+    /// ```
     /// use ctapi_rs::{CtClient, FutureCtClient};
     ///
     /// # async fn run() -> anyhow::Result<()> {
-    /// let client = CtClient::open(None, None, None, 0)?;
+    /// let client = CtClient::open_mock()?;
     /// client.tag_write_future("Setpoint", "25.5")?.await?;
     /// # Ok(())
     /// # }
@@ -712,9 +921,10 @@ pub trait FutureCtClient {
 }
 
 impl FutureCtClient for CtClient {
-    fn cicode_future(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<CtApiFuture> {
-        // Wrap in Arc so CtApiFuture owns a reference that keeps the
-        // CtAPI handle alive for the full lifetime of the future.
+    fn cicode_future(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<CtApiFuture> {
+        // Wrap a clone in Arc so CtApiFuture owns a reference that keeps the
+        // CtAPI handle alive for the full lifetime of the future; clones
+        // share the handle, which is closed when the last one is dropped.
         let client = Arc::new(self.clone());
         let mut async_op = Box::new(AsyncOperation::new());
         client.cicode_async(cmd, vh_win, mode, async_op.as_mut())?;
@@ -722,6 +932,7 @@ impl FutureCtClient for CtClient {
     }
 
     fn tag_write_future(&self, tag: &str, value: &str) -> Result<CtApiFuture> {
+        self.ensure_open()?;
         let client = Arc::new(self.clone());
         let mut async_op = Box::new(AsyncOperation::new());
 
@@ -734,31 +945,28 @@ impl FutureCtClient for CtClient {
                 param: "value".to_string(),
                 value: value.to_string(),
             })?;
+        client.check_write_permit("tag_write_future", tag, value)?;
+        async_op.begin(client.pending())?;
 
         // SAFETY: client.handle() is a valid CtAPI connection handle. tag_cstr
         // and value_cstr are GBK-encoded CStrings valid for this call.
         // async_op.overlapped_mut() returns a valid OVERLAPPED pointer.
-        unsafe {
-            if !ctTagWriteEx(
+        let ok = unsafe {
+            ctTagWriteEx(
                 client.handle(),
                 tag_cstr.as_ptr(),
                 value_cstr.as_ptr(),
                 async_op.as_mut().overlapped_mut(),
-            ) {
-                let err = std::io::Error::last_os_error();
-                // ERROR_IO_PENDING (997) is expected for async operations.
-                if err.raw_os_error() != Some(997) {
-                    return Err(err.into());
-                }
-            }
-        }
+            )
+        };
+        async_op.started(ok)?;
 
         Ok(CtApiFuture::from_boxed(&client, async_op))
     }
 }
 
 impl FutureCtClient for Arc<CtClient> {
-    fn cicode_future(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<CtApiFuture> {
+    fn cicode_future(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<CtApiFuture> {
         // self is &Arc<CtClient> — the future stores a clone of this Arc.
         let mut async_op = Box::new(AsyncOperation::new());
         (**self).cicode_async(cmd, vh_win, mode, async_op.as_mut())?;
@@ -766,6 +974,7 @@ impl FutureCtClient for Arc<CtClient> {
     }
 
     fn tag_write_future(&self, tag: &str, value: &str) -> Result<CtApiFuture> {
+        self.ensure_open()?;
         let mut async_op = Box::new(AsyncOperation::new());
 
         let tag_cstr = encode_to_gbk_cstring(tag).map_err(|_| CtApiError::InvalidParameter {
@@ -777,22 +986,20 @@ impl FutureCtClient for Arc<CtClient> {
                 param: "value".to_string(),
                 value: value.to_string(),
             })?;
+        self.check_write_permit("tag_write_future", tag, value)?;
+        async_op.begin(self.pending())?;
 
         // SAFETY: (**self).handle() is a valid CtAPI connection handle.
         // tag_cstr and value_cstr are GBK-encoded CStrings valid for this call.
-        unsafe {
-            if !ctTagWriteEx(
+        let ok = unsafe {
+            ctTagWriteEx(
                 (**self).handle(),
                 tag_cstr.as_ptr(),
                 value_cstr.as_ptr(),
                 async_op.as_mut().overlapped_mut(),
-            ) {
-                let err = std::io::Error::last_os_error();
-                if err.raw_os_error() != Some(997) {
-                    return Err(err.into());
-                }
-            }
-        }
+            )
+        };
+        async_op.started(ok)?;
 
         Ok(CtApiFuture::from_boxed(self, async_op))
     }
@@ -861,4 +1068,132 @@ mod tests {
         // This just verifies the method compiles and returns a bool.
         let _ = op.is_complete();
     }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_dropping_pending_future_waits_for_cancellation() {
+        use crate::mock::{self, MockServer};
+
+        let server = MockServer::new().with_pending_cicode("Sleep(30)", "0");
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let future = client
+            .cicode_future("Sleep(30)", CicodeWindow::NONE, 0)
+            .unwrap();
+        assert!(!future.async_op.is_complete());
+        assert_eq!(mock::in_flight_calls(&client), 1);
+
+        // The cancelled call is collected before its buffer is freed
+        drop(future);
+        assert_eq!(mock::in_flight_calls(&client), 0);
+        assert_eq!(client.pending().outstanding(), 0);
+    }
+
+    #[test]
+    fn test_begin_rejects_pending_operation() {
+        const STATUS_PENDING: DWORD = 0x103;
+        let pending = Arc::new(PendingOps::default());
+        let mut op = AsyncOperation::new();
+        op.begin(&pending).unwrap();
+        op.overlapped.dwStatus = STATUS_PENDING;
+        assert!(matches!(
+            op.begin(&pending),
+            Err(CtApiError::InvalidParameter { .. })
+        ));
+
+        // Completed but uncollected operations may be restarted
+        op.overlapped.dwStatus = 0;
+        op.begin(&pending).unwrap();
+        assert_eq!(pending.outstanding(), 1);
+        op.reset();
+        assert_eq!(pending.outstanding(), 0);
+        op.begin(&pending).unwrap();
+        drop(op);
+        assert_eq!(pending.outstanding(), 0);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::sync::atomic::AtomicUsize;
+    use loom::thread;
+    use std::task::Wake;
+
+    /// Waker counting how often it was woken
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counting_waker() -> (std::sync::Arc<CountingWaker>, Waker) {
+        let counter = std::sync::Arc::new(CountingWaker::default());
+        (counter.clone(), Waker::from(counter))
+    }
+
+    fn wakes(counter: &CountingWaker) -> usize {
+        counter.0.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn loom_double_start_has_one_winner() {
+        loom::model(|| {
+            let in_flight = Arc::new(InFlight::new());
+            let other = {
+                let in_flight = in_flight.clone();
+                thread::spawn(move || in_flight.begin())
+            };
+            let started = in_flight.begin();
+            assert!(started ^ other.join().unwrap());
+            in_flight.finish();
+            assert!(in_flight.begin());
+        });
+    }
+
+    /// A waker registered while the operation completes is woken or told to poll
+    #[test]
+    fn loom_completion_racing_repoll_is_not_lost() {
+        loom::model(|| {
+            let (first, first_waker) = counting_waker();
+            let (second, second_waker) = counting_waker();
+            let slot = Arc::new(WakerSlot::new(first_waker));
+            let completion = {
+                let slot = slot.clone();
+                thread::spawn(move || slot.fire())
+            };
+            let registered = slot.register(&second_waker);
+            completion.join().unwrap();
+
+            if registered {
+                assert_eq!((wakes(&first), wakes(&second)), (0, 1));
+            } else {
+                assert_eq!((wakes(&first), wakes(&second)), (1, 0));
+            }
+        });
+    }
+
+    /// The waker is dropped with the future and later completions wake nothing
+    #[test]
+    fn loom_completion_racing_drop_never_wakes_after_cancel() {
+        loom::model(|| {
+            let (counter, waker) = counting_waker();
+            let slot = Arc::new(WakerSlot::new(waker));
+            let completion = {
+                let slot = slot.clone();
+                thread::spawn(move || slot.fire())
+            };
+            slot.cancel();
+            completion.join().unwrap();
+            let woken = wakes(&counter);
+            assert!(woken <= 1);
+
+            // A completion reported after the drop
+            slot.fire();
+            assert_eq!(wakes(&counter), woken);
+        });
+    }
 }
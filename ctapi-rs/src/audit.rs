@@ -0,0 +1,875 @@
+//! Audit trail of operations on the SCADA system
+//!
+//! An [`AuditSink`] receives an [`AuditRecord`] for every audited operation,
+//! typically tag writes. [`JournalAuditSink`] keeps them in local JSON Lines
+//! files and is built to survive power failures:
+//!
+//! - records are flushed to disk according to an [`FsyncPolicy`]
+//! - files are rotated by size, keeping a configurable number of old files
+//! - every line carries the CRC-32 of its record, so that a record torn by a
+//!   crash or a failed write is detected and cut off when the journal is
+//!   opened again, and a corrupt record is skipped without losing the
+//!   records after it
+//!
+//! A journal line is the CRC as 8 hex digits, a space and the record as a
+//! JSON object:
+//!
+//! ```text
+//! 3f2a9c01 {"time":1718000000000,"operation":"tag_write","target":"Setpoint","value":"30"}
+//! ```
+//!
+//! [`read_journal`] returns the valid records of a journal file.
+
+use crate::secret::REDACTED;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::windows::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_BACKUP_SEMANTICS;
+
+/// An audited operation
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::audit::AuditRecord;
+///
+/// let record = AuditRecord::new("tag_write", "Setpoint").value("30");
+/// assert!(record.to_json().contains(r#""target":"Setpoint""#));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the operation was made
+    pub time: SystemTime,
+    /// Operation, such as `tag_write`
+    pub operation: String,
+    /// Tag or object the operation applies to
+    pub target: String,
+    /// Value written, if any
+    pub value: Option<String>,
+    /// Whether [`value`](Self::value) was replaced by `***` because it was
+    /// too long, see [`redact_value_over`](Self::redact_value_over)
+    pub redacted: bool,
+    /// Error the operation failed with, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    /// Record `operation` on `target`, made now
+    pub fn new(operation: &str, target: &str) -> Self {
+        Self {
+            time: SystemTime::now(),
+            operation: operation.to_string(),
+            target: target.to_string(),
+            value: None,
+            redacted: false,
+            error: None,
+        }
+    }
+
+    /// Value written by the operation
+    pub fn value(mut self, value: &str) -> Self {
+        self.value = Some(value.to_string());
+        self
+    }
+
+    /// Replace a value longer than `max_chars` characters with `***`
+    ///
+    /// Long values are user-supplied text rather than setpoints and may
+    /// carry anything pasted into a field; [`redacted`](Self::redacted)
+    /// records that the value was hidden.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::audit::AuditRecord;
+    ///
+    /// let record = AuditRecord::new("tag_write", "Recipe").value("a long recipe text");
+    /// let record = record.redact_value_over(8);
+    /// assert_eq!(record.value.as_deref(), Some("***"));
+    /// assert!(record.redacted);
+    /// ```
+    pub fn redact_value_over(mut self, max_chars: usize) -> Self {
+        let long = |value: &String| value.chars().count() > max_chars;
+        if self.value.as_ref().is_some_and(long) {
+            self.value = Some(REDACTED.to_string());
+            self.redacted = true;
+        }
+        self
+    }
+
+    /// Error the operation failed with
+    pub fn error(mut self, error: impl fmt::Display) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    /// The record as a single line JSON object, with the time in Unix milliseconds
+    pub fn to_json(&self) -> String {
+        let millis = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut json = format!("{{\"time\":{millis}");
+        push_field(&mut json, "operation", Some(&self.operation));
+        push_field(&mut json, "target", Some(&self.target));
+        push_field(&mut json, "value", self.value.as_deref());
+        if self.redacted {
+            json.push_str(",\"redacted\":true");
+        }
+        push_field(&mut json, "error", self.error.as_deref());
+        json.push('}');
+        json
+    }
+}
+
+/// Append `,"name":"value"` to a JSON object, escaping the value
+fn push_field(json: &mut String, name: &str, value: Option<&str>) {
+    let Some(value) = value else {
+        return;
+    };
+    let _ = write!(json, ",\"{name}\":\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Destination of audit records
+pub trait AuditSink: Send + Sync {
+    /// Store `record`
+    ///
+    /// # Errors
+    /// Returns the I/O error that prevented the record from being stored.
+    fn record(&self, record: &AuditRecord) -> io::Result<()>;
+}
+
+/// When a [`JournalAuditSink`] forces its records to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// After every record: nothing acknowledged is lost
+    #[default]
+    EveryRecord,
+    /// After every `n` records: at most `n - 1` acknowledged records are lost
+    EveryRecords(u32),
+    /// Every interval, from a background thread: records acknowledged in
+    /// the last interval may be lost. `Duration::ZERO` syncs after every
+    /// record.
+    Interval(Duration),
+}
+
+/// Settings of a [`JournalAuditSink`]
+#[derive(Debug, Clone)]
+pub struct JournalOptions {
+    fsync: FsyncPolicy,
+    max_file_size: u64,
+    retention: usize,
+    redact_over: Option<usize>,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        Self {
+            fsync: FsyncPolicy::default(),
+            max_file_size: 16 * 1024 * 1024,
+            retention: 5,
+            redact_over: None,
+        }
+    }
+}
+
+impl JournalOptions {
+    /// When records are forced to disk, after every record by default
+    pub fn fsync(mut self, policy: FsyncPolicy) -> Self {
+        self.fsync = policy;
+        self
+    }
+
+    /// Size after which the journal file is rotated, 16 MiB by default
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Number of rotated files kept, 5 by default
+    pub fn retention(mut self, files: usize) -> Self {
+        self.retention = files;
+        self
+    }
+
+    /// Store values longer than `max_chars` characters as `***`, see
+    /// [`AuditRecord::redact_value_over`]; values are stored as given by default
+    pub fn redact_values_over(mut self, max_chars: usize) -> Self {
+        self.redact_over = Some(max_chars);
+        self
+    }
+}
+
+/// Counters of a [`JournalAuditSink`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JournalStats {
+    /// Records appended
+    pub records: u64,
+    /// Bytes appended, including line framing
+    pub bytes: u64,
+    /// Syncs to disk
+    pub syncs: u64,
+    /// Files rotated
+    pub rotations: u64,
+    /// Appends that failed and were rolled back
+    pub failed_writes: u64,
+    /// Bytes of a torn record cut off when the journal was opened
+    pub recovered_bytes: u64,
+    /// Corrupt records skipped when the journal was opened
+    pub corrupt_records: u64,
+}
+
+/// Writer used to append to the journal file, replaced in tests to inject failures
+type WrapWriter = for<'a> fn(&'a File) -> Box<dyn Write + 'a>;
+
+#[derive(Debug)]
+struct Journal {
+    file: File,
+    len: u64,
+    unsynced: u32,
+    /// Error of a sync made by the [`Flusher`], returned by the next append
+    failed_sync: Option<io::Error>,
+    stats: JournalStats,
+}
+
+fn lock(journal: &Mutex<Journal>) -> MutexGuard<'_, Journal> {
+    journal.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Thread syncing a journal under [`FsyncPolicy::Interval`]
+#[derive(Debug)]
+struct Flusher {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Flusher {
+    fn spawn(journal: Weak<Mutex<Journal>>, interval: Duration) -> io::Result<Self> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("ctapi-audit-fsync".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let Some(journal) = journal.upgrade() else {
+                        return;
+                    };
+                    let mut journal = lock(&journal);
+                    if let Err(error) = sync(&mut journal) {
+                        journal.failed_sync = Some(error);
+                    }
+                }
+            })?;
+        Ok(Self { stop, thread })
+    }
+
+    /// Stop the thread and wait for it
+    fn stop(self) {
+        drop(self.stop);
+        let _ = self.thread.join();
+    }
+}
+
+/// Crash-safe audit sink writing JSON Lines files
+///
+/// Records are appended to `<name>.jsonl` in the journal directory. When the
+/// file would grow beyond the maximum size it is renamed to `<name>.1.jsonl`,
+/// older files move up by one, and files beyond the retention count are
+/// deleted.
+///
+/// A record whose write fails is cut off again before the error is returned,
+/// so that retrying it cannot leave a torn or duplicated entry. A record torn
+/// by a crash is cut off when the journal is opened. A complete line that
+/// fails its checksum is left in the file for inspection and skipped by
+/// [`read_journal`].
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::audit::{AuditRecord, AuditSink, FsyncPolicy, JournalAuditSink, JournalOptions};
+/// use std::time::Duration;
+///
+/// let options = JournalOptions::default()
+///     .fsync(FsyncPolicy::Interval(Duration::from_secs(1)))
+///     .max_file_size(4 * 1024 * 1024)
+///     .retention(10);
+/// let sink = JournalAuditSink::open("C:\\ProgramData\\scada\\audit", "writes", options)?;
+/// sink.record(&AuditRecord::new("tag_write", "Setpoint").value("30"))?;
+/// println!("{:?}", sink.stats());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct JournalAuditSink {
+    dir: PathBuf,
+    name: String,
+    options: JournalOptions,
+    journal: Arc<Mutex<Journal>>,
+    flusher: Option<Flusher>,
+    wrap: WrapWriter,
+}
+
+impl fmt::Debug for JournalAuditSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JournalAuditSink")
+            .field("dir", &self.dir)
+            .field("name", &self.name)
+            .field("options", &self.options)
+            .field("journal", &self.journal)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JournalAuditSink {
+    /// Open the journal `name` in `dir`, creating both if needed
+    ///
+    /// A torn record at the end of the current file is cut off and counted
+    /// in [`JournalStats::recovered_bytes`]; corrupt records are counted in
+    /// [`JournalStats::corrupt_records`]. With [`FsyncPolicy::Interval`] a
+    /// thread syncing the journal runs until the sink is dropped.
+    ///
+    /// # Errors
+    /// Returns the I/O error that prevented the journal from being opened
+    /// or recovered.
+    pub fn open(dir: impl AsRef<Path>, name: &str, options: JournalOptions) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let path = file_path(&dir, name, 0);
+        let recovered = recover(&path)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let journal = Arc::new(Mutex::new(Journal {
+            file,
+            len: recovered.len,
+            unsynced: 0,
+            failed_sync: None,
+            stats: JournalStats {
+                recovered_bytes: recovered.cut,
+                corrupt_records: recovered.corrupt,
+                ..JournalStats::default()
+            },
+        }));
+        let flusher = match options.fsync {
+            FsyncPolicy::Interval(interval) if !interval.is_zero() => {
+                Some(Flusher::spawn(Arc::downgrade(&journal), interval)?)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            dir,
+            name: name.to_string(),
+            options,
+            journal,
+            flusher,
+            wrap: |file| Box::new(file),
+        })
+    }
+
+    /// Path of the file records are currently appended to
+    pub fn path(&self) -> PathBuf {
+        file_path(&self.dir, &self.name, 0)
+    }
+
+    /// Counters since the journal was opened
+    pub fn stats(&self) -> JournalStats {
+        self.lock().stats
+    }
+
+    /// Force appended records to disk, whatever the fsync policy
+    ///
+    /// # Errors
+    /// Returns the I/O error of the sync.
+    pub fn sync(&self) -> io::Result<()> {
+        let mut journal = self.lock();
+        sync(&mut journal)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Journal> {
+        lock(&self.journal)
+    }
+
+    /// Rename the current file to `.1`, shifting older files and dropping the oldest
+    fn rotate(&self, journal: &mut Journal) -> io::Result<()> {
+        sync(journal)?;
+        let retention = self.options.retention;
+        // With no retention this drops the current file itself
+        let oldest = file_path(&self.dir, &self.name, retention);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..retention).rev() {
+            let from = file_path(&self.dir, &self.name, index);
+            if from.exists() {
+                fs::rename(&from, file_path(&self.dir, &self.name, index + 1))?;
+            }
+        }
+        journal.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path(&self.dir, &self.name, 0))?;
+        // The renames and the new file only survive a crash once the
+        // directory is synced too
+        sync_dir(&self.dir)?;
+        journal.len = 0;
+        journal.stats.rotations += 1;
+        Ok(())
+    }
+
+    /// Append an encoded line, rolling the file back if the write fails
+    fn append(&self, line: &[u8]) -> io::Result<()> {
+        let mut journal = self.lock();
+        if let Some(error) = journal.failed_sync.take() {
+            return Err(error);
+        }
+        let size = line.len() as u64;
+        if journal.len > 0 && journal.len + size > self.options.max_file_size {
+            self.rotate(&mut journal)?;
+        }
+
+        let written = {
+            let mut writer = (self.wrap)(&journal.file);
+            writer.write_all(line).and_then(|()| writer.flush())
+        };
+        if let Err(error) = written {
+            journal.stats.failed_writes += 1;
+            // Cut off whatever part of the record reached the file
+            journal.file.set_len(journal.len)?;
+            return Err(error);
+        }
+        journal.len += size;
+        journal.unsynced += 1;
+        journal.stats.records += 1;
+        journal.stats.bytes += size;
+
+        let due = match self.options.fsync {
+            FsyncPolicy::EveryRecord => true,
+            FsyncPolicy::EveryRecords(n) => journal.unsynced >= n.max(1),
+            // Otherwise synced by the flusher
+            FsyncPolicy::Interval(interval) => interval.is_zero(),
+        };
+        if due {
+            sync(&mut journal)?;
+        }
+        Ok(())
+    }
+}
+
+impl AuditSink for JournalAuditSink {
+    fn record(&self, record: &AuditRecord) -> io::Result<()> {
+        match self.options.redact_over {
+            Some(max_chars) => {
+                let record = record.clone().redact_value_over(max_chars);
+                self.append(&encode(&record.to_json()))
+            }
+            None => self.append(&encode(&record.to_json())),
+        }
+    }
+}
+
+impl Drop for JournalAuditSink {
+    fn drop(&mut self) {
+        if let Some(flusher) = self.flusher.take() {
+            flusher.stop();
+        }
+        let _ = self.sync();
+    }
+}
+
+fn sync(journal: &mut Journal) -> io::Result<()> {
+    if journal.unsynced > 0 {
+        journal.file.sync_data()?;
+        journal.unsynced = 0;
+        journal.stats.syncs += 1;
+    }
+    Ok(())
+}
+
+/// Force the entries of `dir`, such as renamed and created files, to disk
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    // A directory can only be opened with backup semantics
+    let dir = OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(dir)?;
+    dir.sync_all()
+}
+
+/// `<name>.jsonl` for the current file, `<name>.<index>.jsonl` for rotated ones
+fn file_path(dir: &Path, name: &str, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(format!("{name}.jsonl")),
+        index => dir.join(format!("{name}.{index}.jsonl")),
+    }
+}
+
+/// Journal line of `record`
+fn encode(record: &str) -> Vec<u8> {
+    format!("{:08x} {record}\n", crc32(record.as_bytes())).into_bytes()
+}
+
+/// Record of a journal line without its newline, `None` if torn or corrupt
+fn decode(line: &[u8]) -> Option<&str> {
+    let line = std::str::from_utf8(line).ok()?;
+    let (crc, record) = line.split_once(' ')?;
+    let crc = u32::from_str_radix(crc, 16).ok()?;
+    (crc == crc32(record.as_bytes())).then_some(record)
+}
+
+/// Records of the complete lines of a journal
+struct Scan<'a> {
+    /// Length of the complete lines; anything after them is torn
+    len: usize,
+    records: Vec<&'a str>,
+    /// Complete lines that failed their checksum
+    corrupt: u64,
+}
+
+fn scan(data: &[u8]) -> Scan<'_> {
+    let mut scan = Scan {
+        len: 0,
+        records: Vec::new(),
+        corrupt: 0,
+    };
+    while let Some(end) = data[scan.len..].iter().position(|&b| b == b'\n') {
+        match decode(&data[scan.len..scan.len + end]) {
+            Some(record) => scan.records.push(record),
+            None => scan.corrupt += 1,
+        }
+        scan.len += end + 1;
+    }
+    scan
+}
+
+/// Outcome of [`recover`]
+struct Recovered {
+    /// Length of the journal kept
+    len: u64,
+    /// Bytes of a torn record cut off
+    cut: u64,
+    /// Corrupt records left in the journal
+    corrupt: u64,
+}
+
+/// Cut a torn record off the end of the journal at `path`
+fn recover(path: &Path) -> io::Result<Recovered> {
+    let mut data = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut data)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(Recovered {
+                len: 0,
+                cut: 0,
+                corrupt: 0,
+            });
+        }
+        Err(error) => return Err(error),
+    };
+    let scan = scan(&data);
+    if scan.len < data.len() {
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(scan.len as u64)?;
+        file.sync_all()?;
+    }
+    Ok(Recovered {
+        len: scan.len as u64,
+        cut: (data.len() - scan.len) as u64,
+        corrupt: scan.corrupt,
+    })
+}
+
+/// Valid records of the journal file at `path`, as JSON objects
+///
+/// Corrupt records and a torn record at the end are skipped.
+///
+/// # Errors
+/// Returns the I/O error that prevented the file from being read.
+pub fn read_journal(path: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    let data = fs::read(path)?;
+    let records = scan(&data).records;
+    Ok(records.into_iter().map(str::to_string).collect())
+}
+
+/// CRC-32 (IEEE) of `data`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    /// Fresh directory under the system temporary directory
+    fn temp_dir(test: &str) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "ctapi-rs-audit-{test}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn record(n: usize) -> AuditRecord {
+        AuditRecord {
+            time: UNIX_EPOCH + Duration::from_millis(n as u64),
+            ..AuditRecord::new("tag_write", "Setpoint").value(&n.to_string())
+        }
+    }
+
+    /// Writer that accepts `budget` bytes, then fails as a full disk would
+    struct ShortWrite<W> {
+        inner: W,
+        budget: usize,
+    }
+
+    impl<W: Write> Write for ShortWrite<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"));
+            }
+            let n = self.inner.write(&buf[..buf.len().min(self.budget)])?;
+            self.budget -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_record_json() {
+        let record = AuditRecord {
+            time: UNIX_EPOCH + Duration::from_millis(1500),
+            ..AuditRecord::new("tag_write", "Tag \"A\"\n").error("denied\\")
+        };
+        assert_eq!(
+            record.to_json(),
+            r#"{"time":1500,"operation":"tag_write","target":"Tag \"A\"\n","error":"denied\\"}"#
+        );
+    }
+
+    #[test]
+    fn test_round_trip_and_reopen() {
+        let dir = temp_dir("reopen");
+        let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+        for n in 0..3 {
+            sink.record(&record(n)).unwrap();
+        }
+        let stats = sink.stats();
+        assert_eq!((stats.records, stats.syncs), (3, 3));
+        drop(sink);
+
+        let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+        sink.record(&record(3)).unwrap();
+        let records = read_journal(sink.path()).unwrap();
+        let expected: Vec<_> = (0..4).map(|n| record(n).to_json()).collect();
+        assert_eq!(records, expected);
+        assert_eq!(sink.stats().recovered_bytes, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fsync_policies() {
+        let dir = temp_dir("fsync");
+        let options = JournalOptions::default().fsync(FsyncPolicy::EveryRecords(4));
+        let sink = JournalAuditSink::open(&dir, "every4", options).unwrap();
+        for n in 0..10 {
+            sink.record(&record(n)).unwrap();
+        }
+        assert_eq!(sink.stats().syncs, 2);
+        sink.sync().unwrap();
+        assert_eq!(sink.stats().syncs, 3);
+
+        let options =
+            JournalOptions::default().fsync(FsyncPolicy::Interval(Duration::from_secs(3600)));
+        let sink = JournalAuditSink::open(&dir, "hourly", options).unwrap();
+        for n in 0..10 {
+            sink.record(&record(n)).unwrap();
+        }
+        assert_eq!(sink.stats().syncs, 0);
+
+        let options = JournalOptions::default().fsync(FsyncPolicy::Interval(Duration::ZERO));
+        let sink = JournalAuditSink::open(&dir, "always", options).unwrap();
+        sink.record(&record(0)).unwrap();
+        assert_eq!(sink.stats().syncs, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_interval_policy_syncs_without_further_records() {
+        let dir = temp_dir("interval");
+        let interval = Duration::from_millis(10);
+        let options = JournalOptions::default().fsync(FsyncPolicy::Interval(interval));
+        let sink = JournalAuditSink::open(&dir, "audit", options).unwrap();
+        sink.record(&record(0)).unwrap();
+
+        // The last record is synced by the flusher, not by the next append
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while sink.stats().syncs == 0 {
+            assert!(Instant::now() < deadline, "record never synced");
+            std::thread::sleep(interval);
+        }
+        assert_eq!(sink.stats().syncs, 1);
+        drop(sink);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_long_values_are_redacted() {
+        let dir = temp_dir("redact");
+        let options = JournalOptions::default().redact_values_over(8);
+        let sink = JournalAuditSink::open(&dir, "audit", options).unwrap();
+        let short = AuditRecord::new("tag_write", "Setpoint").value("30");
+        let long = AuditRecord::new("tag_write", "Recipe").value("hunter2 hunter2");
+        sink.record(&short).unwrap();
+        sink.record(&long).unwrap();
+
+        let records = read_journal(sink.path()).unwrap();
+        assert_eq!(records[0], short.to_json());
+        assert!(records[1].contains(r#""value":"***","redacted":true"#));
+        assert!(!records[1].contains("hunter2"));
+        drop(sink);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_and_retention() {
+        let dir = temp_dir("rotation");
+        let line = encode(&record(0).to_json()).len() as u64;
+        let options = JournalOptions::default()
+            .max_file_size(line * 2)
+            .retention(2);
+        let sink = JournalAuditSink::open(&dir, "audit", options).unwrap();
+        for n in 0..9 {
+            sink.record(&record(n)).unwrap();
+        }
+
+        // Files hold records 0-1, 2-3, 4-5, 6-7 and 8; the oldest two are gone
+        assert_eq!(sink.stats().rotations, 4);
+        let read = |index| read_journal(file_path(&dir, "audit", index)).unwrap();
+        let json = |range: std::ops::Range<usize>| -> Vec<_> {
+            range.map(|n| record(n).to_json()).collect()
+        };
+        assert_eq!(read(0), json(8..9));
+        assert_eq!(read(1), json(6..8));
+        assert_eq!(read(2), json(4..6));
+        assert!(!file_path(&dir, "audit", 3).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write_is_rolled_back() {
+        let dir = temp_dir("short");
+        let mut sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+        sink.record(&record(0)).unwrap();
+
+        sink.wrap = |file| {
+            Box::new(ShortWrite {
+                inner: file,
+                budget: 10,
+            })
+        };
+        let error = sink.record(&record(1)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(sink.stats().failed_writes, 1);
+
+        // Retrying after the failure stores the record exactly once
+        sink.wrap = |file| Box::new(file);
+        sink.record(&record(1)).unwrap();
+        let records = read_journal(sink.path()).unwrap();
+        assert_eq!(records, [record(0).to_json(), record(1).to_json()]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recovery_after_torn_write_at_every_offset() {
+        let dir = temp_dir("torn");
+        let torn = encode(&record(2).to_json());
+        for cut in 0..torn.len() {
+            let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+            let path = sink.path();
+            sink.record(&record(0)).unwrap();
+            sink.record(&record(1)).unwrap();
+            drop(sink);
+
+            // A crash in the middle of the next append
+            let file = OpenOptions::new().append(true).open(&path).unwrap();
+            let mut writer = ShortWrite {
+                inner: &file,
+                budget: cut,
+            };
+            assert!(writer.write_all(&torn).is_err());
+            drop(file);
+
+            let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+            assert_eq!(sink.stats().recovered_bytes, cut as u64);
+            sink.record(&record(3)).unwrap();
+            let records = read_journal(&path).unwrap();
+            let expected: Vec<_> = [0, 1, 3].into_iter().map(|n| record(n).to_json()).collect();
+            assert_eq!(records, expected, "cut at {cut}");
+            drop(sink);
+            fs::remove_file(path).unwrap();
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_record_is_skipped() {
+        let dir = temp_dir("corrupt");
+        let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+        let path = sink.path();
+        sink.record(&record(0)).unwrap();
+        drop(sink);
+
+        // A complete line whose record does not match its checksum
+        let mut line = encode(&record(1).to_json());
+        let last = line.len() - 2;
+        line[last] = b'X';
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&line)
+            .unwrap();
+
+        let sink = JournalAuditSink::open(&dir, "audit", JournalOptions::default()).unwrap();
+        sink.record(&record(2)).unwrap();
+        let stats = sink.stats();
+        assert_eq!((stats.recovered_bytes, stats.corrupt_records), (0, 1));
+        let records = read_journal(&path).unwrap();
+        assert_eq!(records, [record(0).to_json(), record(2).to_json()]);
+        drop(sink);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+//! Delay strategies between retries
+//!
+//! A [`Backoff`] decides how long to wait before the next attempt of a failed
+//! operation, or that no further attempt should be made. It is consulted by
+//! [`RetryPolicy`](crate::RetryPolicy) (and so
+//! [`CtClient::open_with_retry`](crate::CtClient::open_with_retry)) and by
+//! the probes of a [`ReconnectGate`](crate::ReconnectGate).
+//!
+//! The provided strategies cover the usual curves: [`Exponential`] (the
+//! default), [`Fixed`], [`Fibonacci`] and [`Decorrelated`] jitter. Custom
+//! implementations can look at the error, for example to give up at once on
+//! an authentication failure:
+//!
+//! ```
+//! use ctapi_rs::backoff::{Backoff, Fixed};
+//! use ctapi_rs::retry::is_retryable_open_error;
+//! use ctapi_rs::{CtApiError, RetryPolicy};
+//! use std::time::Duration;
+//!
+//! #[derive(Debug, Clone)]
+//! struct LanBackoff(Fixed);
+//!
+//! impl Backoff for LanBackoff {
+//!     fn next_delay(&mut self, attempt: u32, last_error: &CtApiError) -> Option<Duration> {
+//!         if !is_retryable_open_error(last_error) {
+//!             return None;
+//!         }
+//!         self.0.next_delay(attempt, last_error)
+//!     }
+//! }
+//!
+//! let policy = RetryPolicy::default()
+//!     .backoff(LanBackoff(Fixed::new(Duration::from_millis(200)).max_attempts(10)));
+//! ```
+
+use crate::error::CtApiError;
+use crate::retry::random_unit;
+use std::fmt;
+use std::time::Duration;
+
+/// Source of random values in `[0, 1)`
+type Random = fn() -> f64;
+
+/// Strategy choosing the delay before each retry
+pub trait Backoff: Send + fmt::Debug {
+    /// Delay before attempt `attempt + 1`, `None` to give up
+    ///
+    /// `attempt` is the 1-based number of the attempt that just failed with
+    /// `last_error`.
+    fn next_delay(&mut self, attempt: u32, last_error: &CtApiError) -> Option<Duration>;
+
+    /// Forget state kept between delays before a new series of attempts
+    fn reset(&mut self) {}
+}
+
+/// Exponentially growing delay with jitter
+///
+/// The delay before attempt `n + 1` is `initial * multiplier^(n - 1)`, capped
+/// at `max`, of which the `jitter` fraction is randomised.
+#[derive(Debug, Clone, Copy)]
+pub struct Exponential {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+    random: Random,
+}
+
+impl Exponential {
+    /// Start at `initial`, doubling up to `max`, with 20 % jitter
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            multiplier: 2.0,
+            jitter: 0.2,
+            random: random_unit,
+        }
+    }
+
+    /// Factor applied to the delay after each attempt (at least `1.0`)
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Fraction of each delay that is randomised, between `0.0` and `1.0`
+    ///
+    /// With jitter `j` a delay `d` becomes a random value in `[d * (1 - j), d]`.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    #[cfg(test)]
+    fn with_random(mut self, random: Random) -> Self {
+        self.random = random;
+        self
+    }
+
+    /// Delay before attempt `attempt + 1`, without jitter
+    pub(crate) fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let delay = self.initial.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max.as_secs_f64()))
+    }
+
+    /// Delay with jitter applied, `random` in `[0, 1)`
+    pub(crate) fn delay(&self, attempt: u32, random: f64) -> Duration {
+        self.base_delay(attempt).mul_f64(1.0 - self.jitter * random)
+    }
+}
+
+impl Backoff for Exponential {
+    fn next_delay(&mut self, attempt: u32, _last_error: &CtApiError) -> Option<Duration> {
+        Some(self.delay(attempt, (self.random)()))
+    }
+}
+
+/// The same delay before every attempt
+#[derive(Debug, Clone, Copy)]
+pub struct Fixed {
+    delay: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl Fixed {
+    /// Wait `delay` between attempts, without limit
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            max_attempts: None,
+        }
+    }
+
+    /// Give up once `attempts` attempts have failed
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+}
+
+impl Backoff for Fixed {
+    fn next_delay(&mut self, attempt: u32, _last_error: &CtApiError) -> Option<Duration> {
+        match self.max_attempts {
+            Some(max) if attempt >= max => None,
+            _ => Some(self.delay),
+        }
+    }
+}
+
+/// Delays following the Fibonacci sequence: `unit`, `unit`, `2 * unit`, `3 * unit`, ...
+///
+/// Grows more gently than doubling, capped at `max`.
+#[derive(Debug, Clone, Copy)]
+pub struct Fibonacci {
+    unit: Duration,
+    max: Duration,
+}
+
+impl Fibonacci {
+    /// Multiples of `unit`, capped at `max`
+    pub fn new(unit: Duration, max: Duration) -> Self {
+        Self { unit, max }
+    }
+}
+
+impl Backoff for Fibonacci {
+    fn next_delay(&mut self, attempt: u32, _last_error: &CtApiError) -> Option<Duration> {
+        let (mut previous, mut current) = (0u32, 1u32);
+        for _ in 1..attempt {
+            (previous, current) = (current, previous.saturating_add(current));
+        }
+        Some(self.unit.saturating_mul(current).min(self.max))
+    }
+}
+
+/// "Decorrelated jitter": a random delay between `base` and three times the previous one
+///
+/// Spreads clients restarted together more evenly than jittered exponential
+/// backoff. Each delay is capped at `max`.
+#[derive(Debug, Clone, Copy)]
+pub struct Decorrelated {
+    base: Duration,
+    max: Duration,
+    previous: Duration,
+    random: Random,
+}
+
+impl Decorrelated {
+    /// Random delays of at least `base`, capped at `max`
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            previous: base,
+            random: random_unit,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_random(mut self, random: Random) -> Self {
+        self.random = random;
+        self
+    }
+}
+
+impl Backoff for Decorrelated {
+    fn next_delay(&mut self, _attempt: u32, _last_error: &CtApiError) -> Option<Duration> {
+        let upper = self.previous.saturating_mul(3).max(self.base);
+        let delay = self.base + (upper - self.base).mul_f64((self.random)());
+        self.previous = delay.min(self.max);
+        Some(self.previous)
+    }
+
+    fn reset(&mut self) {
+        self.previous = self.base;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delays(backoff: &mut dyn Backoff, attempts: u32) -> Vec<Option<Duration>> {
+        (1..=attempts)
+            .map(|attempt| backoff.next_delay(attempt, &CtApiError::Timeout))
+            .collect()
+    }
+
+    fn millis(values: &[u64]) -> Vec<Option<Duration>> {
+        values
+            .iter()
+            .map(|&ms| Some(Duration::from_millis(ms)))
+            .collect()
+    }
+
+    fn secs(values: &[f64]) -> Vec<Option<Duration>> {
+        values
+            .iter()
+            .map(|&s| Some(Duration::from_secs_f64(s)))
+            .collect()
+    }
+
+    #[test]
+    fn test_exponential() {
+        let mut backoff =
+            Exponential::new(Duration::from_secs(1), Duration::from_secs(10)).jitter(0.0);
+        assert_eq!(
+            delays(&mut backoff, 6),
+            secs(&[1.0, 2.0, 4.0, 8.0, 10.0, 10.0])
+        );
+
+        let mut jittered = Exponential::new(Duration::from_secs(1), Duration::from_secs(10))
+            .multiplier(3.0)
+            .jitter(0.5)
+            .with_random(|| 0.5);
+        assert_eq!(delays(&mut jittered, 3), secs(&[0.75, 2.25, 6.75]));
+    }
+
+    #[test]
+    fn test_fixed() {
+        let mut backoff = Fixed::new(Duration::from_millis(250));
+        assert_eq!(delays(&mut backoff, 3), millis(&[250, 250, 250]));
+
+        let mut limited = Fixed::new(Duration::from_millis(250)).max_attempts(3);
+        let mut expected = millis(&[250, 250]);
+        expected.extend([None, None]);
+        assert_eq!(delays(&mut limited, 4), expected);
+    }
+
+    #[test]
+    fn test_fibonacci() {
+        let mut backoff = Fibonacci::new(Duration::from_millis(100), Duration::from_millis(1000));
+        assert_eq!(
+            delays(&mut backoff, 8),
+            millis(&[100, 100, 200, 300, 500, 800, 1000, 1000])
+        );
+        assert_eq!(
+            backoff.next_delay(u32::MAX, &CtApiError::Timeout),
+            Some(Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn test_decorrelated() {
+        let (base, max) = (Duration::from_secs(1), Duration::from_secs(10));
+        let mut lowest = Decorrelated::new(base, max).with_random(|| 0.0);
+        assert_eq!(delays(&mut lowest, 3), secs(&[1.0, 1.0, 1.0]));
+
+        let mut highest = Decorrelated::new(base, max).with_random(|| 1.0);
+        assert_eq!(delays(&mut highest, 4), secs(&[3.0, 9.0, 10.0, 10.0]));
+        highest.reset();
+        assert_eq!(delays(&mut highest, 1), secs(&[3.0]));
+
+        let mut random = Decorrelated::new(base, max);
+        for delay in delays(&mut random, 50) {
+            let delay = delay.unwrap();
+            assert!((base..=max).contains(&delay), "{delay:?}");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Debug-build detector for blocking CtAPI calls made on the Tokio runtime
+//!
+//! The synchronous entry points ([`CtClient::tag_read`], [`CtClient::cicode`],
+//! [`CtList::read`] and [`CtFind`] iteration) block the calling thread until
+//! CtAPI answers. Called from an async task they stall every other task
+//! scheduled on the same worker, which usually only shows up under load.
+//!
+//! With `debug_assertions` and the `tokio-support` feature enabled, those
+//! entry points check whether they run inside a Tokio runtime context and
+//! emit a `tracing` error event (with the `tracing` feature) naming the
+//! `*_tokio` equivalent, or [`blocking_section`] where there is none.
+//! Setting the environment variable `CTAPI_RS_STRICT_ASYNC=1` turns the
+//! event into a panic, which is useful in test suites. Release builds, and
+//! builds without `tokio-support`, compile the check to nothing.
+//!
+//! Threads of Tokio's blocking pool also carry a runtime context, so blocking
+//! calls there are told apart by a thread-local marker. The crate's own
+//! `spawn_blocking` based methods set it; application closures passed to
+//! [`tokio::task::spawn_blocking`] wrap their CtAPI calls in
+//! [`blocking_section`].
+//!
+//! [`CtClient::tag_read`]: crate::CtClient::tag_read
+//! [`CtClient::cicode`]: crate::CtClient::cicode
+//! [`CtList::read`]: crate::CtList::read
+//! [`CtFind`]: crate::CtFind
+//! [`blocking_section`]: crate::tokio_async::blocking_section
+
+/// Environment variable that makes a detected blocking call panic
+pub(crate) const STRICT_ASYNC_VAR: &str = "CTAPI_RS_STRICT_ASYNC";
+
+/// Alternative named for blocking calls without a `*_tokio` equivalent
+pub(crate) const IN_BLOCKING_SECTION: &str =
+    "ctapi_rs::blocking_section inside tokio::task::spawn_blocking";
+
+#[cfg(all(debug_assertions, feature = "tokio-support"))]
+mod detect {
+    use super::STRICT_ASYNC_VAR;
+    use std::cell::Cell;
+    use std::sync::OnceLock;
+
+    thread_local! {
+        static ALLOWED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Whether a blocking call here would stall a Tokio runtime
+    pub(crate) fn on_async_runtime() -> bool {
+        !ALLOWED.get() && tokio::runtime::Handle::try_current().is_ok()
+    }
+
+    /// Run `f` with blocking calls allowed on this thread
+    pub(crate) fn allow<R>(f: impl FnOnce() -> R) -> R {
+        struct Reset(bool);
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                ALLOWED.set(self.0);
+            }
+        }
+        let _reset = Reset(ALLOWED.replace(true));
+        f()
+    }
+
+    fn strict() -> bool {
+        static STRICT: OnceLock<bool> = OnceLock::new();
+        *STRICT.get_or_init(|| std::env::var(STRICT_ASYNC_VAR).is_ok_and(|v| v == "1"))
+    }
+
+    pub(crate) fn check(operation: &str, alternative: &str) {
+        if !on_async_runtime() {
+            return;
+        }
+        let message = format!(
+            "blocking CtAPI call {operation} on a Tokio runtime thread stalls the \
+             reactor; use {alternative} instead"
+        );
+        if strict() {
+            panic!("{message} ({STRICT_ASYNC_VAR}=1)");
+        }
+        #[cfg(feature = "tracing")]
+        tracing::error!(operation, alternative, "{message}");
+    }
+}
+
+#[cfg(all(debug_assertions, feature = "tokio-support"))]
+pub(crate) use detect::{allow, check, on_async_runtime};
+
+/// Report a blocking `operation` called from async code, see the module docs
+#[cfg(not(all(debug_assertions, feature = "tokio-support")))]
+#[inline(always)]
+pub(crate) fn check(_operation: &str, _alternative: &str) {}
+
+/// Run `f` with blocking calls allowed on this thread
+#[cfg(not(all(debug_assertions, feature = "tokio-support")))]
+#[inline(always)]
+pub(crate) fn allow<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+#[cfg(all(test, debug_assertions, feature = "tokio-support"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_thread_is_not_flagged() {
+        assert!(!on_async_runtime());
+        assert!(!std::thread::spawn(on_async_runtime).join().unwrap());
+    }
+
+    #[test]
+    fn test_runtime_tasks_are_flagged() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(on_async_runtime());
+            assert!(tokio::spawn(async { on_async_runtime() }).await.unwrap());
+        });
+    }
+
+    #[test]
+    fn test_blocking_sections_are_not_flagged() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(!allow(on_async_runtime));
+            assert!(on_async_runtime());
+            let in_pool = tokio::task::spawn_blocking(|| allow(on_async_runtime));
+            assert!(!in_pool.await.unwrap());
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_sync_read_on_runtime_reports() {
+        if std::env::var(STRICT_ASYNC_VAR).is_ok() {
+            return;
+        }
+        let client = crate::CtClient::open_mock().unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        // Reported but still performed
+        let value = runtime.block_on(async { client.tag_read("Pressure") });
+        assert_eq!(value.unwrap(), "1.2");
+    }
+}
@@ -0,0 +1,472 @@
+//! Ready-to-publish messages for MQTT bridges
+//!
+//! [`ChangeFeed`] turns the [`TagChange`]s of a [`TagWatcher`] and the
+//! connection state transitions of its client into [`OutboundMessage`]s: a
+//! topic, an encoded payload and the retain and QoS hints to publish with.
+//! The module has no MQTT dependency; the `mqtt-bridge` example forwards the
+//! messages with rumqttc.
+//!
+//! Tag topics are rendered from a template in which `{tag}` and `{cluster}`
+//! are replaced. Characters that MQTT treats specially (`/`, `+` and `#`) are
+//! replaced with `_` in substituted names, so every tag maps to exactly one
+//! topic level.
+//!
+//! With a [status topic](FeedOptions::with_status_topic), the feed also emits
+//! a birth message when the connection is up and a death message when it
+//! goes down, both retained. [`FeedOptions::death_message`] is meant to be
+//! registered as the MQTT last will, so that subscribers see the bridge go
+//! offline even if it dies without closing the connection.
+//!
+//! Payloads are the plain value string, or with the `json` feature a JSON
+//! object with the general quality (`3` is
+//! [`QUAL_GOOD`](crate::quality::QUAL_GOOD)) and the time of the poll in
+//! milliseconds since the Unix epoch:
+//!
+//! ```text
+//! {"quality":3,"tag":"Temperature","timestamp":1700000000000,"value":"25.5"}
+//! ```
+
+use crate::state::{ConnectionState, StateChange};
+use crate::watcher::{TagChange, TagWatcher};
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, channel};
+use std::time::SystemTime;
+
+/// Delivery guarantee requested for a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QoS {
+    /// Fire and forget (QoS 0)
+    #[default]
+    AtMostOnce,
+    /// Acknowledged delivery (QoS 1)
+    AtLeastOnce,
+    /// Assured single delivery (QoS 2)
+    ExactlyOnce,
+}
+
+/// How tag values and states are encoded into payloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadEncoder {
+    /// The value string as UTF-8, and `online`/`offline` for states
+    #[default]
+    Plain,
+    /// A JSON object with the tag, value, quality and timestamp
+    #[cfg(feature = "json")]
+    Json,
+}
+
+/// A message ready to hand to an MQTT client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundMessage {
+    /// Topic to publish to
+    pub topic: String,
+    /// Encoded payload
+    pub payload: Vec<u8>,
+    /// Whether the broker should retain the message
+    pub retain: bool,
+    /// Requested delivery guarantee
+    pub qos: QoS,
+}
+
+/// Topic layout, encoding and publish hints of a [`ChangeFeed`]
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::bridge::{FeedOptions, QoS};
+///
+/// let options = FeedOptions::new("plant/{cluster}/{tag}")
+///     .with_cluster("Cluster1")
+///     .with_retain(true)
+///     .with_tag_hints("Alarm_Horn", false, QoS::AtLeastOnce)
+///     .with_status_topic("plant/{cluster}/status");
+/// assert_eq!(options.topic_for("Tank/1 Level"), "plant/Cluster1/Tank_1 Level");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FeedOptions {
+    topic_template: String,
+    cluster: String,
+    encoder: PayloadEncoder,
+    retain: bool,
+    qos: QoS,
+    tag_hints: HashMap<String, (bool, QoS)>,
+    status_topic: Option<String>,
+}
+
+impl FeedOptions {
+    /// Publish tag changes to topics rendered from `topic_template`
+    ///
+    /// Messages are not retained and use [`QoS::AtMostOnce`] unless
+    /// configured otherwise.
+    pub fn new(topic_template: impl Into<String>) -> Self {
+        Self {
+            topic_template: topic_template.into(),
+            cluster: String::new(),
+            encoder: PayloadEncoder::default(),
+            retain: false,
+            qos: QoS::default(),
+            tag_hints: HashMap::new(),
+            status_topic: None,
+        }
+    }
+
+    /// Value substituted for `{cluster}`
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = cluster.into();
+        self
+    }
+
+    /// Payload encoding
+    pub fn with_encoder(mut self, encoder: PayloadEncoder) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    /// Retain flag of tag messages without their own hints
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// QoS of tag messages without their own hints
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Retain flag and QoS for the messages of one tag
+    pub fn with_tag_hints(mut self, tag: impl Into<String>, retain: bool, qos: QoS) -> Self {
+        self.tag_hints.insert(tag.into(), (retain, qos));
+        self
+    }
+
+    /// Publish birth and death messages to the topic rendered from `template`
+    ///
+    /// Only `{cluster}` is substituted in the template.
+    pub fn with_status_topic(mut self, template: impl Into<String>) -> Self {
+        self.status_topic = Some(template.into());
+        self
+    }
+
+    /// Topic of the messages for `tag`
+    pub fn topic_for(&self, tag: &str) -> String {
+        self.topic_template
+            .replace("{cluster}", &topic_level(&self.cluster))
+            .replace("{tag}", &topic_level(tag))
+    }
+
+    /// Message announcing `change`
+    pub fn message_for(&self, change: &TagChange) -> OutboundMessage {
+        let (retain, qos) = self
+            .tag_hints
+            .get(&change.tag)
+            .copied()
+            .unwrap_or((self.retain, self.qos));
+        let payload = match self.encoder {
+            PayloadEncoder::Plain => change.current.value.clone().into_bytes(),
+            #[cfg(feature = "json")]
+            PayloadEncoder::Json => {
+                let mut object = serde_json::Map::new();
+                object.insert("tag".to_string(), change.tag.as_str().into());
+                object.insert("value".to_string(), change.current.value.as_str().into());
+                object.insert("quality".to_string(), change.current.quality.into());
+                object.insert("timestamp".to_string(), unix_millis(change.at).into());
+                serde_json::Value::Object(object).to_string().into_bytes()
+            }
+        };
+        OutboundMessage {
+            topic: self.topic_for(&change.tag),
+            payload,
+            retain,
+            qos,
+        }
+    }
+
+    /// Birth or death message for a connection state transition
+    ///
+    /// `None` without a status topic, and for
+    /// [`Reconnecting`](ConnectionState::Reconnecting), which is neither.
+    pub fn state_message(&self, change: &StateChange) -> Option<OutboundMessage> {
+        let online = match change.state {
+            ConnectionState::Connected => true,
+            ConnectionState::Down => false,
+            ConnectionState::Reconnecting => return None,
+        };
+        self.status_message(online, change.at)
+    }
+
+    /// Message announcing that the bridge is online
+    pub fn birth_message(&self) -> Option<OutboundMessage> {
+        self.status_message(true, SystemTime::now())
+    }
+
+    /// Message announcing that the bridge is offline, for use as last will
+    pub fn death_message(&self) -> Option<OutboundMessage> {
+        self.status_message(false, SystemTime::now())
+    }
+
+    #[cfg_attr(not(feature = "json"), allow(unused_variables))]
+    fn status_message(&self, online: bool, at: SystemTime) -> Option<OutboundMessage> {
+        let topic = self
+            .status_topic
+            .as_ref()?
+            .replace("{cluster}", &topic_level(&self.cluster));
+        let status = if online { "online" } else { "offline" };
+        let payload = match self.encoder {
+            PayloadEncoder::Plain => status.as_bytes().to_vec(),
+            #[cfg(feature = "json")]
+            PayloadEncoder::Json => {
+                let mut object = serde_json::Map::new();
+                object.insert("status".to_string(), status.into());
+                object.insert("timestamp".to_string(), unix_millis(at).into());
+                serde_json::Value::Object(object).to_string().into_bytes()
+            }
+        };
+        Some(OutboundMessage {
+            topic,
+            payload,
+            retain: true,
+            qos: QoS::AtLeastOnce,
+        })
+    }
+}
+
+/// Replace the characters that would split or wildcard a topic level
+fn topic_level(name: &str) -> String {
+    name.replace(['/', '+', '#'], "_")
+}
+
+/// Milliseconds since the Unix epoch, `0` for earlier times
+#[cfg(feature = "json")]
+fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Outbound messages for the changes of a [`TagWatcher`]
+///
+/// Subscribes to the watcher's [change events](TagWatcher::change_events)
+/// and to the state transitions of its client. Call [`drain`](Self::drain)
+/// after each [`poll`](TagWatcher::poll) and publish the returned messages.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::bridge::{ChangeFeed, FeedOptions};
+/// use ctapi_rs::{CtClient, TagWatcher};
+/// use std::sync::Arc;
+///
+/// let client = Arc::new(CtClient::open_mock()?);
+/// let list = Arc::new(Arc::clone(&client).list_new(0)?);
+/// let watcher = TagWatcher::new(list);
+/// watcher.watch("Temperature")?;
+///
+/// let options = FeedOptions::new("plant/{tag}").with_status_topic("plant/status");
+/// let mut feed = ChangeFeed::new(&watcher, options);
+/// watcher.poll()?;
+/// for message in feed.drain() {
+///     println!("{} <- {}", message.topic, String::from_utf8_lossy(&message.payload));
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct ChangeFeed {
+    options: FeedOptions,
+    changes: Receiver<TagChange>,
+    states: Receiver<StateChange>,
+    pending: Vec<OutboundMessage>,
+}
+
+impl ChangeFeed {
+    /// Start converting the changes of `watcher`
+    ///
+    /// If the client is connected, the first [`drain`](Self::drain) starts
+    /// with a birth message.
+    pub fn new(watcher: &TagWatcher, options: FeedOptions) -> Self {
+        let client = watcher.list().client();
+        let (sender, states) = channel();
+        client.on_state_change(move |change| {
+            let _ = sender.send(change.clone());
+        });
+        let pending = match client.connection_state() {
+            ConnectionState::Connected => options.birth_message().into_iter().collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            changes: watcher.change_events(),
+            options,
+            states,
+            pending,
+        }
+    }
+
+    /// Options the feed was created with
+    pub fn options(&self) -> &FeedOptions {
+        &self.options
+    }
+
+    /// Messages for everything that happened since the last call
+    ///
+    /// State messages come before tag messages. Never blocks.
+    pub fn drain(&mut self) -> Vec<OutboundMessage> {
+        let mut messages = std::mem::take(&mut self.pending);
+        messages.extend(
+            self.states
+                .try_iter()
+                .filter_map(|change| self.options.state_message(&change)),
+        );
+        messages.extend(
+            self.changes
+                .try_iter()
+                .map(|change| self.options.message_for(&change)),
+        );
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality::QUAL_GOOD;
+    use crate::watcher::TimedValue;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    fn change(tag: &str, value: &str, quality: u8) -> TagChange {
+        TagChange {
+            tag: tag.to_string(),
+            previous: None,
+            current: TimedValue {
+                value: value.to_string(),
+                timestamp: Instant::now(),
+                quality,
+                datasource_error: None,
+            },
+            at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        }
+    }
+
+    fn state(state: ConnectionState) -> StateChange {
+        StateChange {
+            state,
+            at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            error: None,
+            recent_calls: Arc::new([]),
+        }
+    }
+
+    #[test]
+    fn test_topic_rendering() {
+        let options = FeedOptions::new("plant/{cluster}/{tag}/value").with_cluster("North");
+        assert_eq!(
+            options.topic_for("Temperature"),
+            "plant/North/Temperature/value"
+        );
+        assert_eq!(options.topic_for("A/B+C#D"), "plant/North/A_B_C_D/value");
+        assert_eq!(FeedOptions::new("{tag}").topic_for("T"), "T");
+    }
+
+    #[test]
+    fn test_plain_payload_and_hints() {
+        let options = FeedOptions::new("p/{tag}")
+            .with_retain(true)
+            .with_qos(QoS::AtLeastOnce)
+            .with_tag_hints("Horn", false, QoS::ExactlyOnce);
+
+        let message = options.message_for(&change("Level", "63.0", QUAL_GOOD));
+        assert_eq!(
+            message,
+            OutboundMessage {
+                topic: "p/Level".to_string(),
+                payload: b"63.0".to_vec(),
+                retain: true,
+                qos: QoS::AtLeastOnce,
+            }
+        );
+
+        let message = options.message_for(&change("Horn", "1", QUAL_GOOD));
+        assert_eq!((message.retain, message.qos), (false, QoS::ExactlyOnce));
+    }
+
+    #[test]
+    fn test_state_messages() {
+        let options = FeedOptions::new("p/{tag}").with_cluster("C1");
+        assert_eq!(options.state_message(&state(ConnectionState::Down)), None);
+        assert_eq!(options.death_message(), None);
+
+        let options = options.with_status_topic("p/{cluster}/status");
+        let birth = options
+            .state_message(&state(ConnectionState::Connected))
+            .unwrap();
+        assert_eq!(birth.topic, "p/C1/status");
+        assert_eq!(birth.payload, b"online");
+        assert!(birth.retain);
+
+        let death = options
+            .state_message(&state(ConnectionState::Down))
+            .unwrap();
+        assert_eq!(death.payload, b"offline");
+        assert_eq!(death, options.death_message().unwrap());
+        assert_eq!(
+            options.state_message(&state(ConnectionState::Reconnecting)),
+            None
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_payloads() {
+        let options = FeedOptions::new("p/{tag}")
+            .with_encoder(PayloadEncoder::Json)
+            .with_status_topic("p/status");
+
+        let message = options.message_for(&change("Name", "say \"hi\"", 0));
+        assert_eq!(
+            String::from_utf8(message.payload).unwrap(),
+            r#"{"quality":0,"tag":"Name","timestamp":1700000000123,"value":"say \"hi\""}"#
+        );
+
+        let death = options
+            .state_message(&state(ConnectionState::Down))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(death.payload).unwrap(),
+            r#"{"status":"offline","timestamp":1700000000000}"#
+        );
+    }
+
+    #[test]
+    fn test_feed_drains_states_then_changes() {
+        let client = Arc::new(crate::CtClient::from_handle(std::ptr::null_mut()));
+        let list = Arc::new(crate::CtList::new(
+            Arc::clone(&client),
+            std::ptr::null_mut(),
+            0,
+        ));
+        let watcher = TagWatcher::new(list);
+        let options = FeedOptions::new("p/{tag}").with_status_topic("p/status");
+        let mut feed = ChangeFeed::new(&watcher, options);
+
+        watcher.record("Level", "1.0", QUAL_GOOD);
+        watcher.record("Level", "1.0", QUAL_GOOD);
+        client.state_tracker().set(ConnectionState::Down, None);
+
+        let messages = feed.drain();
+        let summary: Vec<_> = messages
+            .iter()
+            .map(|m| {
+                (
+                    m.topic.as_str(),
+                    String::from_utf8_lossy(&m.payload).into_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("p/status", "online".to_string()),
+                ("p/status", "offline".to_string()),
+                ("p/Level", "1.0".to_string()),
+            ]
+        );
+        assert!(feed.drain().is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! Fluent construction of [`CtClient`]
+//!
+//! [`CtClientBuilder`] names the connection parameters that
+//! [`CtClient::open`] takes positionally and assembles the `CT_OPEN_*` mode
+//! bits from [`constants`](crate::constants). CtAPI refuses remote
+//! connections with a blank password; the builder reports that before any
+//! call is made. With [`retry_open`](CtClientBuilder::retry_open) a failed
+//! open is retried like [`CtClient::open_with_retry`], and with
+//! [`check_versions`](CtClientBuilder::check_versions) the CtAPI.dll and
+//! server versions are compared once connected.
+
+use crate::client::{ConnectionInfo, CtClient};
+use crate::clock::{Clock, SystemClock};
+use crate::constants::{CT_OPEN_BATCH, CT_OPEN_CRYPT, CT_OPEN_READ_ONLY, CT_OPEN_RECONNECT};
+use crate::credentials::CredentialSource;
+use crate::error::{CtApiError, Result};
+use crate::retry::RetryPolicy;
+use crate::secret::SecretString;
+use std::time::Duration;
+
+/// Computer names that address the local computer
+const LOCAL_COMPUTERS: &[&str] = &["localhost", "127.0.0.1", "::1", "."];
+
+/// Builder for [`CtClient`], created by [`CtClient::builder`]
+///
+/// Without [`computer`](Self::computer) the client connects to the local
+/// computer.
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::CtClient;
+/// use std::time::Duration;
+///
+/// let client = CtClient::builder()
+///     .computer("192.168.1.100")
+///     .user("Manager")
+///     .password("pw")
+///     .read_only()
+///     .connect_timeout(Duration::from_secs(10))
+///     .build()?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CtClientBuilder {
+    info: ConnectionInfo,
+    connect_timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    check_versions: bool,
+    strict_versions: bool,
+}
+
+impl CtClientBuilder {
+    /// Builder connecting to the local computer without credentials
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Computer name or IP address of the Citect SCADA server
+    pub fn computer(mut self, computer: impl Into<String>) -> Self {
+        self.info.computer = Some(computer.into());
+        self
+    }
+
+    /// User name
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.info.user = Some(user.into());
+        self
+    }
+
+    /// Password, required for remote computers
+    pub fn password(mut self, password: impl Into<SecretString>) -> Self {
+        self.info.password = Some(password.into());
+        self
+    }
+
+    /// Computer, user and password supplied by `source`
+    ///
+    /// Values the source leaves out keep what was set before.
+    pub fn credentials(mut self, source: impl CredentialSource) -> Self {
+        if let Some(computer) = source.computer() {
+            self.info.computer = Some(computer.to_string());
+        }
+        if let Some(user) = source.user() {
+            self.info.user = Some(user.to_string());
+        }
+        if let Some(password) = source.password() {
+            self.info.password = Some(password.clone());
+        }
+        self
+    }
+
+    /// Only allow reads ([`CT_OPEN_READ_ONLY`])
+    pub fn read_only(mut self) -> Self {
+        self.info.mode |= CT_OPEN_READ_ONLY;
+        self
+    }
+
+    /// Let CtAPI re-establish a lost connection ([`CT_OPEN_RECONNECT`])
+    pub fn reconnect(mut self) -> Self {
+        self.info.mode |= CT_OPEN_RECONNECT;
+        self
+    }
+
+    /// Disable the connection status message box ([`CT_OPEN_BATCH`])
+    pub fn batch(mut self) -> Self {
+        self.info.mode |= CT_OPEN_BATCH;
+        self
+    }
+
+    /// Encrypt the communication ([`CT_OPEN_CRYPT`])
+    pub fn encrypted(mut self) -> Self {
+        self.info.mode |= CT_OPEN_CRYPT;
+        self
+    }
+
+    /// Give up connecting after `timeout`
+    ///
+    /// The client is then created with `ctClientCreate` and connected with
+    /// `ctOpenEx`, which a watchdog thread cancels with `ctCancelIO` once the
+    /// timeout has passed. Without a timeout [`build`](Self::build) calls
+    /// `ctOpen`, which waits as long as CtAPI does.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry a failed open according to `policy`
+    ///
+    /// Failures that mean the server is not reachable yet are retried until
+    /// the policy deadline, see [`CtClient::open_with_retry`]; each attempt
+    /// honours the [connect timeout](Self::connect_timeout).
+    pub fn retry_open(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Compare the CtAPI.dll and server versions once connected
+    ///
+    /// Runs [`CtClient::check_versions`], whose result is then available
+    /// from [`CtClient::version_info`]. Differing major versions are
+    /// reported as a `tracing` warning (with the `tracing` feature), or fail
+    /// the build with [`strict_versions`](Self::strict_versions).
+    pub fn check_versions(mut self, check: bool) -> Self {
+        self.check_versions = check;
+        self
+    }
+
+    /// Fail with [`CtApiError::VersionSkew`] when the major versions differ
+    ///
+    /// Implies [`check_versions`](Self::check_versions).
+    pub fn strict_versions(mut self, strict: bool) -> Self {
+        self.strict_versions = strict;
+        self
+    }
+
+    /// `CT_OPEN_*` bits assembled so far
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CT_OPEN_BATCH, CT_OPEN_RECONNECT, CtClient};
+    ///
+    /// let builder = CtClient::builder().reconnect().batch();
+    /// assert_eq!(builder.mode(), CT_OPEN_RECONNECT | CT_OPEN_BATCH);
+    /// ```
+    pub fn mode(&self) -> u32 {
+        self.info.mode
+    }
+
+    /// Whether the connection goes to another computer
+    fn is_remote(&self) -> bool {
+        self.info.computer.as_deref().is_some_and(|computer| {
+            let computer = computer.trim();
+            !computer.is_empty()
+                && !LOCAL_COMPUTERS
+                    .iter()
+                    .any(|local| computer.eq_ignore_ascii_case(local))
+        })
+    }
+
+    /// Check the parameters without connecting
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - The computer is remote and the
+    ///   password is missing or blank
+    pub fn validate(&self) -> Result<()> {
+        let blank = self
+            .info
+            .password
+            .as_ref()
+            .is_none_or(|password| password.expose().trim().is_empty());
+        if self.is_remote() && blank {
+            let computer = self.info.computer.as_deref().unwrap_or_default();
+            return Err(CtApiError::InvalidParameter {
+                param: "password".to_string(),
+                value: format!("blank for remote computer {computer}"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Validate the parameters and open the connection
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - See [`validate`](Self::validate)
+    /// * [`CtApiError::Timeout`] - The [connect timeout](Self::connect_timeout)
+    ///   passed
+    /// * [`CtApiError::System`] - The connection failed; with
+    ///   [`retry_open`](Self::retry_open), the last attempt failed
+    /// * [`CtApiError::VersionSkew`] - See [`strict_versions`](Self::strict_versions)
+    pub fn build(self) -> Result<CtClient> {
+        self.build_with(&SystemClock, Self::open)
+    }
+
+    /// [`build`](Self::build) timing the retries with `clock` and connecting with `open`
+    fn build_with<F>(&self, clock: &dyn Clock, open: F) -> Result<CtClient>
+    where
+        F: Fn(&Self) -> Result<CtClient>,
+    {
+        self.validate()?;
+        let client = match &self.retry {
+            Some(policy) => policy.run(clock, || open(self))?,
+            None => open(self)?,
+        };
+        if self.check_versions || self.strict_versions {
+            let versions = client.check_versions(self.strict_versions)?;
+            #[cfg(feature = "tracing")]
+            if versions.is_skewed() {
+                tracing::warn!(%versions, "CtAPI.dll and server major versions differ");
+            }
+            #[cfg(not(feature = "tracing"))]
+            let _ = versions;
+        }
+        Ok(client)
+    }
+
+    /// One attempt to open the connection
+    fn open(&self) -> Result<CtClient> {
+        match self.connect_timeout {
+            Some(timeout) => CtClient::open_within(self.info.clone(), timeout),
+            None => {
+                let info = &self.info;
+                CtClient::open(
+                    info.computer.as_deref(),
+                    info.user.as_deref(),
+                    info.password.as_ref().map(SecretString::expose),
+                    info.mode,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_bits() {
+        assert_eq!(CtClientBuilder::new().mode(), 0);
+        let builder = CtClientBuilder::new()
+            .read_only()
+            .reconnect()
+            .batch()
+            .encrypted()
+            .reconnect();
+        let all = CT_OPEN_READ_ONLY | CT_OPEN_RECONNECT | CT_OPEN_BATCH | CT_OPEN_CRYPT;
+        assert_eq!(builder.mode(), all);
+    }
+
+    #[test]
+    fn test_remote_connections_need_a_password() {
+        let local = CtClientBuilder::new().user("Manager");
+        assert!(local.validate().is_ok());
+        assert!(local.clone().computer("LOCALHOST").validate().is_ok());
+        assert!(local.clone().computer(" ").validate().is_ok());
+
+        let remote = local.computer("scada1");
+        let err = remote.validate().unwrap_err();
+        assert!(
+            matches!(err, CtApiError::InvalidParameter { ref param, .. } if param == "password")
+        );
+        let err = remote.clone().password("  ").build().unwrap_err();
+        assert!(matches!(err, CtApiError::InvalidParameter { .. }));
+        assert!(remote.password("pw").validate().is_ok());
+    }
+
+    #[test]
+    fn test_credentials_fill_what_the_source_supplies() {
+        struct UserOnly;
+        impl CredentialSource for UserOnly {
+            fn computer(&self) -> Option<&str> {
+                None
+            }
+            fn user(&self) -> Option<&str> {
+                Some("Operator")
+            }
+            fn password(&self) -> Option<&SecretString> {
+                None
+            }
+        }
+
+        let builder = CtClientBuilder::new()
+            .computer("scada1")
+            .user("Manager")
+            .credentials(UserOnly);
+        assert_eq!(builder.info.computer.as_deref(), Some("scada1"));
+        assert_eq!(builder.info.user.as_deref(), Some("Operator"));
+        assert!(builder.validate().is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_retry_open() {
+        use crate::clock::MockClock;
+        use std::cell::Cell;
+        use std::io;
+
+        let policy = RetryPolicy::default()
+            .initial_delay(Duration::from_secs(1))
+            .jitter(0.0);
+        let builder = CtClientBuilder::new().retry_open(policy);
+        let clock = MockClock::new();
+        let attempts = Cell::new(0);
+        let client = builder
+            .build_with(&*clock, |_| {
+                attempts.set(attempts.get() + 1);
+                match attempts.get() {
+                    // RPC_S_SERVER_UNAVAILABLE
+                    1 | 2 => Err(io::Error::from_raw_os_error(1722).into()),
+                    _ => CtClient::open_mock(),
+                }
+            })
+            .unwrap();
+        assert_eq!(client.tag_read("Temperature").unwrap(), "25.5");
+        assert_eq!(attempts.get(), 3);
+        assert_eq!(clock.elapsed(), Duration::from_secs(1 + 2));
+
+        // Rejected credentials are not retried
+        attempts.set(0);
+        let err = builder
+            .build_with(&*clock, |_| {
+                attempts.set(attempts.get() + 1);
+                // ERROR_LOGON_FAILURE
+                Err(io::Error::from_raw_os_error(1326).into())
+            })
+            .unwrap_err();
+        assert!(matches!(err, CtApiError::System(..)));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_check_versions_once_connected() {
+        use crate::clock::MockClock;
+        use crate::mock::MockServer;
+
+        let clock = MockClock::new();
+        let open = |_: &CtClientBuilder| CtClient::open_mock();
+        let client = CtClientBuilder::new().build_with(&*clock, open).unwrap();
+        assert_eq!(client.version_info(), None);
+
+        let builder = CtClientBuilder::new().check_versions(true);
+        let client = builder.build_with(&*clock, open).unwrap();
+        let versions = client.version_info().unwrap();
+        assert_eq!(versions.server.as_deref(), Some("8.20.0.0"));
+
+        // An unknown server version is never a skew, even when strict
+        let builder = CtClientBuilder::new().strict_versions(true);
+        let client = builder
+            .build_with(&*clock, |_| {
+                CtClient::open_mock_with(MockServer::seeded().with_cicode("Version(0)", ""))
+            })
+            .unwrap();
+        assert_eq!(client.version_info().unwrap().server, None);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_connect_within_cancels_slow_connect() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::seeded().with_connect_latency(Duration::from_secs(10));
+        let client = CtClient::open_mock_with(server).unwrap();
+        let started = std::time::Instant::now();
+        let err = client
+            .connect_within(Duration::from_millis(50))
+            .unwrap_err();
+        assert!(matches!(err, CtApiError::Timeout));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let server = MockServer::seeded().with_connect_latency(Duration::from_millis(10));
+        let client = CtClient::open_mock_with(server).unwrap();
+        let client = client.connect_within(Duration::from_secs(5)).unwrap();
+        assert_eq!(client.tag_read("Temperature").unwrap(), "25.5");
+
+        let client = CtClient::open_mock_with(MockServer::seeded().unreachable()).unwrap();
+        let err = client.connect_within(Duration::from_secs(5)).unwrap_err();
+        assert!(matches!(err, CtApiError::System(..)));
+    }
+}
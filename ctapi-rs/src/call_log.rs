@@ -0,0 +1,459 @@
+//! Ring buffer of the most recent CtAPI calls
+//!
+//! Every [`CtClient`](crate::CtClient) keeps the last
+//! [`DEFAULT_CALL_LOG_CAPACITY`] calls it made into CtAPI.dll: the function,
+//! the tag or command it was given, how long it took and whether it failed.
+//! The log is shared with clones of the client, read with
+//! [`recent_calls`](crate::CtClient::recent_calls), included in
+//! [support bundles](crate::support) and attached to
+//! [`ConnectionState::Down`](crate::ConnectionState::Down) transitions, so
+//! the calls leading up to a failure are available after the fact without
+//! running with verbose logging.
+//!
+//! Entries have a fixed size: the subject is stored GBK-encoded and cut
+//! after [`SUBJECT_LEN`] bytes. The buffer is allocated when the first call
+//! is recorded or the capacity changes, and recording takes a short mutex
+//! without allocating. A capacity of 0 disables the log; calls then only
+//! load one atomic.
+//!
+//! With the `tracing` feature every call is also reported as a `tracing`
+//! event with the fields `op`, `subject` and `duration`: a successful call
+//! at `TRACE` level, a failed one at `WARN` with its error `code` and the
+//! translated `error` message. Calls are timed for the events even when the
+//! log is disabled. Without the feature none of this is compiled in.
+//!
+//! The crate never prints. The other conditions it reports go to `tracing`
+//! too, and are dropped without the feature: a handle that fails to close in
+//! `CtClient::drop` and a denied write the audit sink could not store
+//! (`WARN`), a find cursor resumed after it expired (`WARN`) and, in debug
+//! builds with `tokio-support`, a blocking call made on a Tokio runtime
+//! thread (`ERROR`).
+
+use crate::citect_code::CitectCode;
+use crate::error::CtApiError;
+use encoding_rs::GBK;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Number of calls a new client keeps
+pub const DEFAULT_CALL_LOG_CAPACITY: usize = 500;
+
+/// Bytes of the subject kept per call
+pub const SUBJECT_LEN: usize = 64;
+
+/// One CtAPI call, as returned by [`CtClient::recent_calls`](crate::CtClient::recent_calls)
+#[derive(Clone, Copy)]
+pub struct CallRecord {
+    /// CtAPI function, such as `ctTagRead`
+    pub op: &'static str,
+    /// When the call returned
+    pub at: SystemTime,
+    /// Time spent in the call
+    pub duration: Duration,
+    /// Error code of a failed call, `None` if it succeeded
+    ///
+    /// The Win32 error code reported by CtAPI, or the
+    /// [crate error code](crate::CtApiError::code) for failures without one,
+    /// such as a write that timed out.
+    pub error: Option<u32>,
+    subject: [u8; SUBJECT_LEN],
+    subject_len: u8,
+}
+
+impl CallRecord {
+    /// Tag name, Cicode command or other subject of the call, possibly cut short
+    pub fn subject(&self) -> String {
+        let bytes = &self.subject[..self.subject_len as usize];
+        GBK.decode_without_bom_handling(bytes).0.into_owned()
+    }
+
+    /// Whether the call succeeded
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl std::fmt::Debug for CallRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallRecord")
+            .field("op", &self.op)
+            .field("subject", &self.subject())
+            .field("at", &self.at)
+            .field("duration", &self.duration)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Subject of a call on several tags, joined with commas on the stack
+///
+/// Only the first [`SUBJECT_LEN`] bytes are kept, which is all a record
+/// stores, so logging a read of a large list does not allocate.
+pub(crate) struct JoinedSubject {
+    bytes: [u8; SUBJECT_LEN],
+    len: usize,
+    names: usize,
+}
+
+impl JoinedSubject {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: [0; SUBJECT_LEN],
+            len: 0,
+            names: 0,
+        }
+    }
+
+    /// Append `name`, after a comma unless it is the first
+    pub(crate) fn push(&mut self, name: &str) {
+        if self.names > 0 {
+            self.extend(b",");
+        }
+        self.names += 1;
+        self.extend(name.as_bytes());
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        let n = bytes.len().min(SUBJECT_LEN - self.len);
+        self.bytes[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Win32 error code behind `error`, or its crate error code if it has none
+fn error_code(error: &CtApiError) -> u32 {
+    match error.root() {
+        CtApiError::System(e, ..) => e.raw_os_error().map_or(error.code(), |code| code as u32),
+        CtApiError::Citect { code, .. } => CitectCode::new(*code).os_error() as u32,
+        _ => error.code(),
+    }
+}
+
+/// Whether calls are reported to a `tracing` subscriber
+#[inline]
+fn traced() -> bool {
+    #[cfg(feature = "tracing")]
+    return tracing::enabled!(tracing::Level::WARN);
+    #[cfg(not(feature = "tracing"))]
+    false
+}
+
+/// The error CtAPI reports with the Win32 error `code`, translated
+#[cfg(feature = "tracing")]
+fn os_error(code: u32) -> CtApiError {
+    std::io::Error::from_raw_os_error(code as i32).into()
+}
+
+/// Report a call to the `tracing` subscriber, with its error code and error if it failed
+#[cfg(feature = "tracing")]
+fn trace<E: std::fmt::Display>(
+    op: &'static str,
+    subject: &[u8],
+    duration: Duration,
+    error: Option<(u32, E)>,
+) {
+    let subject = GBK.decode_without_bom_handling(subject).0;
+    match error {
+        None => tracing::trace!(op, %subject, ?duration, "CtAPI call"),
+        Some((code, error)) => {
+            tracing::warn!(op, %subject, ?duration, code, %error, "CtAPI call failed")
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ring {
+    entries: Vec<CallRecord>,
+    /// Slot the next record overwrites once `entries` is full
+    next: usize,
+}
+
+/// Call log of one client and its clones
+#[derive(Debug)]
+pub(crate) struct CallLog {
+    capacity: AtomicUsize,
+    ring: Mutex<Ring>,
+}
+
+impl Default for CallLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CALL_LOG_CAPACITY)
+    }
+}
+
+impl CallLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: AtomicUsize::new(capacity),
+            ring: Mutex::new(Ring::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Ring> {
+        self.ring.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of calls kept
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Keep the last `capacity` calls, dropping older ones; 0 disables the log
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut ring = self.lock();
+        let mut entries = Self::ordered(&ring);
+        entries.drain(..entries.len().saturating_sub(capacity));
+        if capacity == 0 {
+            entries = Vec::new();
+        } else {
+            entries.reserve_exact(capacity - entries.len());
+        }
+        *ring = Ring { entries, next: 0 };
+        self.capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    /// Start timing a call, `None` if the log is disabled and calls are not traced
+    #[inline]
+    pub(crate) fn start(&self) -> Option<Instant> {
+        (self.capacity() > 0 || traced()).then(Instant::now)
+    }
+
+    /// Record a call started with [`start`](Self::start)
+    ///
+    /// The Win32 error of a failed call is read with `GetLastError` and left
+    /// in place for the caller.
+    #[inline]
+    pub(crate) fn finish(
+        &self,
+        started: Option<Instant>,
+        op: &'static str,
+        subject: &[u8],
+        ok: bool,
+    ) {
+        if let Some(started) = started {
+            let error =
+                (!ok).then(|| std::io::Error::last_os_error().raw_os_error().unwrap_or(0) as u32);
+            let duration = started.elapsed();
+            self.push(op, subject, duration, error);
+            #[cfg(feature = "tracing")]
+            {
+                let failure = error.map(|code| (code, os_error(code)));
+                trace(op, subject, duration, failure);
+            }
+            if let Some(code) = error {
+                // SAFETY: SetLastError only stores the code for the calling thread.
+                unsafe { windows_sys::Win32::Foundation::SetLastError(code) };
+            }
+        }
+    }
+
+    /// Record a call started with [`start`](Self::start) from its result
+    pub(crate) fn finish_result<T>(
+        &self,
+        started: Option<Instant>,
+        op: &'static str,
+        subject: &[u8],
+        result: &crate::error::Result<T>,
+    ) {
+        if let Some(started) = started {
+            let error = result.as_ref().err();
+            let duration = started.elapsed();
+            self.push(op, subject, duration, error.map(error_code));
+            #[cfg(feature = "tracing")]
+            trace(op, subject, duration, error.map(|e| (error_code(e), e)));
+        }
+    }
+
+    fn push(&self, op: &'static str, subject: &[u8], duration: Duration, error: Option<u32>) {
+        // Started for tracing only
+        if self.capacity() == 0 {
+            return;
+        }
+        let len = subject.len().min(SUBJECT_LEN);
+        let mut record = CallRecord {
+            op,
+            at: SystemTime::now(),
+            duration,
+            error,
+            subject: [0; SUBJECT_LEN],
+            subject_len: len as u8,
+        };
+        record.subject[..len].copy_from_slice(&subject[..len]);
+
+        let mut ring = self.lock();
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        if ring.entries.len() < capacity {
+            if ring.entries.capacity() == 0 {
+                ring.entries.reserve_exact(capacity);
+            }
+            ring.entries.push(record);
+        } else {
+            let next = ring.next;
+            ring.entries[next] = record;
+            ring.next = (next + 1) % capacity;
+        }
+    }
+
+    /// Entries from oldest to newest
+    fn ordered(ring: &Ring) -> Vec<CallRecord> {
+        let (newer, older) = ring.entries.split_at(ring.next);
+        older.iter().chain(newer).copied().collect()
+    }
+
+    /// Recorded calls, oldest first
+    pub(crate) fn recent(&self) -> Vec<CallRecord> {
+        Self::ordered(&self.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subjects(log: &CallLog) -> Vec<String> {
+        log.recent().iter().map(CallRecord::subject).collect()
+    }
+
+    fn record(log: &CallLog, subject: &str) {
+        let started = log.start();
+        log.finish(started, "ctTagRead", subject.as_bytes(), true);
+    }
+
+    #[test]
+    fn test_records_in_order() {
+        let log = CallLog::new(4);
+        record(&log, "A");
+        record(&log, "B");
+        let started = log.start();
+        log.finish(started, "ctCicode", b"Beep()", false);
+
+        let calls = log.recent();
+        assert_eq!(subjects(&log), ["A", "B", "Beep()"]);
+        assert_eq!(calls[2].op, "ctCicode");
+        assert!(calls[0].is_ok() && !calls[2].is_ok());
+        assert!(calls[0].at <= calls[2].at);
+    }
+
+    #[test]
+    fn test_wraps_around_keeping_newest() {
+        let log = CallLog::new(3);
+        for subject in ["A", "B", "C", "D", "E"] {
+            record(&log, subject);
+        }
+        assert_eq!(subjects(&log), ["C", "D", "E"]);
+        assert_eq!(log.lock().entries.capacity(), 3);
+
+        log.set_capacity(2);
+        assert_eq!(subjects(&log), ["D", "E"]);
+        record(&log, "F");
+        assert_eq!(subjects(&log), ["E", "F"]);
+    }
+
+    #[test]
+    fn test_long_subjects_are_cut() {
+        let log = CallLog::new(1);
+        record(&log, &"x".repeat(200));
+        assert_eq!(log.recent()[0].subject().len(), SUBJECT_LEN);
+    }
+
+    #[test]
+    fn test_joined_subject() {
+        let mut subject = JoinedSubject::new();
+        assert_eq!(subject.as_bytes(), b"");
+        subject.push("A");
+        subject.push("B");
+        assert_eq!(subject.as_bytes(), b"A,B");
+
+        let long = "x".repeat(40);
+        subject.push(&long);
+        subject.push(&long);
+        subject.push("C");
+        assert_eq!(subject.as_bytes().len(), SUBJECT_LEN);
+        assert!(subject.as_bytes().starts_with(b"A,B,xxx"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_calls_are_traced_with_the_log_disabled() {
+        use std::fmt::Debug;
+        use std::sync::Arc;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Level, Metadata};
+
+        /// Level and `field=value` pairs of every event
+        #[derive(Clone, Default)]
+        struct Events(Arc<Mutex<Vec<(Level, String)>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0 += &format!("{}={:?} ", field.name(), value);
+            }
+        }
+
+        impl tracing::Subscriber for Events {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields(String::new());
+                event.record(&mut fields);
+                let level = *event.metadata().level();
+                self.0.lock().unwrap().push((level, fields.0));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let events = Events::default();
+        let log = CallLog::new(0);
+        tracing::subscriber::with_default(events.clone(), || {
+            record(&log, "Temperature");
+            let started = log.start();
+            // SAFETY: SetLastError only stores the code for the calling thread.
+            unsafe { windows_sys::Win32::Foundation::SetLastError(1168) }; // ERROR_NOT_FOUND
+            log.finish(started, "ctTagRead", b"NoSuchTag", false);
+            assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(1168));
+        });
+        assert!(log.recent().is_empty());
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, Level::TRACE);
+        assert!(events[0].1.contains("subject=Temperature"));
+        assert_eq!(events[1].0, Level::WARN);
+        assert!(events[1].1.contains("op=\"ctTagRead\""));
+        assert!(events[1].1.contains("code=1168"));
+    }
+
+    #[test]
+    fn test_disabled_log_records_nothing() {
+        let log = CallLog::new(0);
+        assert!(log.start().is_none());
+        record(&log, "A");
+        assert!(log.recent().is_empty());
+        assert_eq!(log.lock().entries.capacity(), 0);
+
+        log.set_capacity(2);
+        record(&log, "B");
+        log.set_capacity(0);
+        assert!(log.recent().is_empty());
+        record(&log, "C");
+        assert!(log.recent().is_empty());
+    }
+}
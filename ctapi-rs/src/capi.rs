@@ -0,0 +1,402 @@
+//! C ABI for non-Rust hosts
+//!
+//! Exposes a small `extern "C"` surface so that C, C++ or .NET hosts can use
+//! this crate instead of linking CtAPI directly. Available only when the
+//! `capi` feature is enabled, which also generates `ctapi_rs.h` with cbindgen
+//! during the build. The header is written to `include/` under the build
+//! script's `OUT_DIR` (`target/<profile>/build/ctapi-rs-*/out/`), not into
+//! the source tree.
+//!
+//! # Conventions
+//!
+//! * Clients are identified by opaque non-zero `u64` handles; `0` is never a
+//!   valid handle.
+//! * Every function except [`ctrs_open`] returns one of the `CTRS_*` status
+//!   codes. On failure the message is available from
+//!   [`ctrs_last_error_message`] and the stable error code from
+//!   [`ctrs_last_error_code`] on the same thread.
+//! * All strings are NUL-terminated UTF-8 in both directions; conversion to
+//!   the SCADA encoding happens inside the crate.
+//! * Output strings are written into caller buffers. If the buffer is too
+//!   small nothing is written, [`CTRS_BUFFER_TOO_SMALL`] is returned and the
+//!   required size including the NUL terminator is stored in `required`.
+//! * Panics never cross the boundary; they are reported as [`CTRS_PANIC`].
+
+use crate::CtClient;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+
+/// Success
+pub const CTRS_OK: i32 = 0;
+/// The operation failed; see [`ctrs_last_error_message`]
+pub const CTRS_ERROR: i32 = -1;
+/// The output buffer is too small; the required size was stored
+pub const CTRS_BUFFER_TOO_SMALL: i32 = -2;
+/// The client handle is unknown or already closed
+pub const CTRS_INVALID_HANDLE: i32 = -3;
+/// A pointer argument is NULL or a string is not valid UTF-8
+pub const CTRS_INVALID_ARGUMENT: i32 = -4;
+/// The call panicked internally
+pub const CTRS_PANIC: i32 = -5;
+
+static CLIENTS: LazyLock<Mutex<HashMap<u64, Arc<CtClient>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static LAST_ERROR: RefCell<(u32, String)> = const { RefCell::new((0, String::new())) };
+}
+
+fn clients() -> MutexGuard<'static, HashMap<u64, Arc<CtClient>>> {
+    CLIENTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Record a failure that did not come from a [`CtApiError`](crate::CtApiError)
+fn set_last_error(message: impl Into<String>) {
+    set_last_error_with_code(0, message);
+}
+
+fn set_last_error_with_code(code: u32, message: impl Into<String>) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = (code, message.into()));
+}
+
+/// Store a client and return its new handle
+fn register(client: CtClient) -> u64 {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    clients().insert(handle, Arc::new(client));
+    handle
+}
+
+fn lookup(handle: u64) -> Result<Arc<CtClient>, i32> {
+    clients().get(&handle).cloned().ok_or_else(|| {
+        set_last_error(format!("invalid client handle {handle}"));
+        CTRS_INVALID_HANDLE
+    })
+}
+
+/// Run `f`, turning a panic into [`CTRS_PANIC`]
+fn guard(f: impl FnOnce() -> Result<i32, i32>) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(status)) | Ok(Err(status)) => status,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {message}"));
+            CTRS_PANIC
+        }
+    }
+}
+
+/// Map a crate error to [`CTRS_ERROR`], recording its code and message
+fn fail(error: crate::CtApiError) -> i32 {
+    set_last_error_with_code(error.code(), error.to_string());
+    CTRS_ERROR
+}
+
+/// Borrow a required UTF-8 string argument
+///
+/// # Safety
+/// `ptr` must be NULL or point to a NUL-terminated string.
+unsafe fn required_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, i32> {
+    // SAFETY: guaranteed by the caller
+    unsafe { optional_str(ptr, name) }?.ok_or_else(|| {
+        set_last_error(format!("{name} must not be NULL"));
+        CTRS_INVALID_ARGUMENT
+    })
+}
+
+/// Borrow an optional UTF-8 string argument, `None` for NULL
+///
+/// # Safety
+/// `ptr` must be NULL or point to a NUL-terminated string.
+unsafe fn optional_str<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>, i32> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: ptr is non-null and NUL-terminated per the caller's contract
+    let cstr = unsafe { CStr::from_ptr(ptr) };
+    cstr.to_str().map(Some).map_err(|_| {
+        set_last_error(format!("{name} is not valid UTF-8"));
+        CTRS_INVALID_ARGUMENT
+    })
+}
+
+/// Copy `value` and a NUL terminator into `buf` if it fits
+///
+/// # Safety
+/// `buf` must be NULL or valid for `buf_len` bytes; `required` must be NULL or
+/// valid for a write.
+unsafe fn write_out(value: &str, buf: *mut c_char, buf_len: usize, required: *mut usize) -> i32 {
+    let needed = value.len() + 1;
+    if !required.is_null() {
+        // SAFETY: required is non-null and writable per the caller's contract
+        unsafe { required.write(needed) };
+    }
+    if buf.is_null() || buf_len < needed {
+        set_last_error(format!(
+            "buffer of {buf_len} bytes too small, {needed} required"
+        ));
+        return CTRS_BUFFER_TOO_SMALL;
+    }
+    // SAFETY: buf is valid for buf_len >= needed bytes and cannot overlap value,
+    // which is owned by Rust.
+    unsafe {
+        std::ptr::copy_nonoverlapping(value.as_ptr(), buf.cast::<u8>(), value.len());
+        buf.add(value.len()).write(0);
+    }
+    CTRS_OK
+}
+
+/// Open a connection, returning a client handle or `0` on failure
+///
+/// `computer`, `user` and `password` may be NULL for the local defaults.
+/// `mode` takes the `CT_OPEN_*` flags.
+///
+/// # Safety
+/// Each string argument must be NULL or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ctrs_open(
+    computer: *const c_char,
+    user: *const c_char,
+    password: *const c_char,
+    mode: u32,
+) -> u64 {
+    let mut handle = 0;
+    guard(|| {
+        // SAFETY: forwarded caller contract
+        let (computer, user, password) = unsafe {
+            (
+                optional_str(computer, "computer")?,
+                optional_str(user, "user")?,
+                optional_str(password, "password")?,
+            )
+        };
+        let client = CtClient::open(computer, user, password, mode).map_err(fail)?;
+        handle = register(client);
+        Ok(CTRS_OK)
+    });
+    handle
+}
+
+/// Read a tag value as UTF-8 into `buf`
+///
+/// `required` (optional) receives the buffer size needed for the value,
+/// including the NUL terminator, even when the call fails with
+/// [`CTRS_BUFFER_TOO_SMALL`]. Pass a NULL `buf` to query the size only.
+///
+/// # Safety
+/// `tag` must point to a NUL-terminated string, `buf` must be NULL or valid
+/// for `buf_len` bytes and `required` must be NULL or valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ctrs_tag_read(
+    handle: u64,
+    tag: *const c_char,
+    buf: *mut c_char,
+    buf_len: usize,
+    required: *mut usize,
+) -> i32 {
+    guard(|| {
+        // SAFETY: forwarded caller contract
+        let tag = unsafe { required_str(tag, "tag") }?;
+        let client = lookup(handle)?;
+        let value = client.tag_read(tag).map_err(fail)?;
+        // SAFETY: forwarded caller contract
+        Ok(unsafe { write_out(&value, buf, buf_len, required) })
+    })
+}
+
+/// Write a tag value given as UTF-8
+///
+/// # Safety
+/// `tag` and `value` must point to NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ctrs_tag_write(
+    handle: u64,
+    tag: *const c_char,
+    value: *const c_char,
+) -> i32 {
+    guard(|| {
+        // SAFETY: forwarded caller contract
+        let (tag, value) = unsafe { (required_str(tag, "tag")?, required_str(value, "value")?) };
+        let client = lookup(handle)?;
+        client.tag_write_str(tag, value).map_err(fail)?;
+        Ok(CTRS_OK)
+    })
+}
+
+/// Copy the calling thread's last error message into `buf`
+///
+/// Uses the same size negotiation as [`ctrs_tag_read`]. The message is empty
+/// if no call on this thread has failed yet.
+///
+/// # Safety
+/// `buf` must be NULL or valid for `buf_len` bytes and `required` must be
+/// NULL or valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ctrs_last_error_message(
+    buf: *mut c_char,
+    buf_len: usize,
+    required: *mut usize,
+) -> i32 {
+    guard(|| {
+        let (code, message) = LAST_ERROR.with(|last| last.borrow().clone());
+        // SAFETY: forwarded caller contract. Restore the error afterwards so
+        // that a too-small buffer does not replace it.
+        let status = unsafe { write_out(&message, buf, buf_len, required) };
+        set_last_error_with_code(code, message);
+        Ok(status)
+    })
+}
+
+/// Stable code of the calling thread's last error
+///
+/// This is [`CtApiError::code`](crate::CtApiError::code) of the error behind
+/// the last [`CTRS_ERROR`]. It is `0` if no call on this thread has failed
+/// yet, or if the last failure was reported by another status such as
+/// [`CTRS_INVALID_HANDLE`].
+#[unsafe(no_mangle)]
+pub extern "C" fn ctrs_last_error_code() -> u32 {
+    LAST_ERROR.with(|last| last.borrow().0)
+}
+
+/// Close a client handle
+///
+/// The connection is closed once no call using it is still running.
+#[unsafe(no_mangle)]
+pub extern "C" fn ctrs_close(handle: u64) -> i32 {
+    guard(|| match clients().remove(&handle) {
+        Some(_) => Ok(CTRS_OK),
+        None => {
+            set_last_error(format!("invalid client handle {handle}"));
+            Err(CTRS_INVALID_HANDLE)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::ptr;
+
+    fn last_error() -> String {
+        let mut required = 0;
+        // SAFETY: NULL buffer with a valid `required` pointer
+        let status = unsafe { ctrs_last_error_message(ptr::null_mut(), 0, &mut required) };
+        assert_eq!(status, CTRS_BUFFER_TOO_SMALL);
+        let mut buf = vec![0 as c_char; required];
+        // SAFETY: buf is valid for `required` bytes
+        let status =
+            unsafe { ctrs_last_error_message(buf.as_mut_ptr(), buf.len(), ptr::null_mut()) };
+        assert_eq!(status, CTRS_OK);
+        // SAFETY: write_out NUL-terminated the buffer
+        unsafe { CStr::from_ptr(buf.as_ptr()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_handle_lifecycle() {
+        let handle = register(CtClient::from_handle(ptr::null_mut()));
+        assert_ne!(handle, 0);
+        assert!(lookup(handle).is_ok());
+
+        assert_eq!(ctrs_close(handle), CTRS_OK);
+        assert_eq!(ctrs_close(handle), CTRS_INVALID_HANDLE);
+        assert!(last_error().contains("invalid client handle"));
+
+        let tag = CString::new("Tag1").unwrap();
+        // SAFETY: valid NUL-terminated strings
+        let status = unsafe { ctrs_tag_write(handle, tag.as_ptr(), tag.as_ptr()) };
+        assert_eq!(status, CTRS_INVALID_HANDLE);
+    }
+
+    #[test]
+    fn test_buffer_negotiation() {
+        let value = "温度 23.5";
+        let mut required = 0;
+        // SAFETY: NULL buffer, valid `required`
+        let status = unsafe { write_out(value, ptr::null_mut(), 0, &mut required) };
+        assert_eq!(status, CTRS_BUFFER_TOO_SMALL);
+        assert_eq!(required, value.len() + 1);
+
+        let mut small = vec![0x7f as c_char; required - 1];
+        // SAFETY: small is valid for its length
+        let status = unsafe { write_out(value, small.as_mut_ptr(), small.len(), &mut required) };
+        assert_eq!(status, CTRS_BUFFER_TOO_SMALL);
+        assert!(
+            small.iter().all(|&b| b == 0x7f),
+            "nothing written on failure"
+        );
+
+        let mut buf = vec![0x7f as c_char; required];
+        // SAFETY: buf is valid for its length
+        let status = unsafe { write_out(value, buf.as_mut_ptr(), buf.len(), ptr::null_mut()) };
+        assert_eq!(status, CTRS_OK);
+        // SAFETY: write_out NUL-terminated the buffer
+        let out = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        assert_eq!(out.to_str().unwrap(), value);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let handle = register(CtClient::from_handle(ptr::null_mut()));
+        // SAFETY: NULL tag is handled; other pointers are NULL
+        let status =
+            unsafe { ctrs_tag_read(handle, ptr::null(), ptr::null_mut(), 0, ptr::null_mut()) };
+        assert_eq!(status, CTRS_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "tag must not be NULL");
+
+        let invalid = [0xffu8 as c_char, 0];
+        let value = CString::new("1").unwrap();
+        // SAFETY: both strings are NUL-terminated
+        let status = unsafe { ctrs_tag_write(handle, invalid.as_ptr(), value.as_ptr()) };
+        assert_eq!(status, CTRS_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "tag is not valid UTF-8");
+        assert_eq!(ctrs_close(handle), CTRS_OK);
+    }
+
+    #[test]
+    fn test_panic_is_caught() {
+        let status = guard(|| panic!("boom"));
+        assert_eq!(status, CTRS_PANIC);
+        assert_eq!(last_error(), "panic: boom");
+    }
+
+    #[test]
+    fn test_last_error_code() {
+        assert_eq!(
+            fail(crate::CtApiError::Timeout.context("reading Tag1")),
+            CTRS_ERROR
+        );
+        assert_eq!(ctrs_last_error_code(), 4001);
+        assert_eq!(last_error(), "[E4001] reading Tag1");
+        assert_eq!(
+            ctrs_last_error_code(),
+            4001,
+            "kept after reading the message"
+        );
+
+        assert_eq!(ctrs_close(0), CTRS_INVALID_HANDLE);
+        assert_eq!(ctrs_last_error_code(), 0);
+    }
+
+    #[test]
+    fn test_last_error_survives_small_buffer() {
+        set_last_error("device offline");
+        let mut tiny = [0 as c_char; 2];
+        // SAFETY: tiny is valid for its length
+        let status =
+            unsafe { ctrs_last_error_message(tiny.as_mut_ptr(), tiny.len(), ptr::null_mut()) };
+        assert_eq!(status, CTRS_BUFFER_TOO_SMALL);
+        assert_eq!(last_error(), "device offline");
+    }
+}
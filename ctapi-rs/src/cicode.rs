@@ -0,0 +1,651 @@
+//! Cicode command construction helpers
+//!
+//! Builds Cicode command strings for [`CtClient::cicode`](crate::CtClient::cicode)
+//! with string arguments quoted using Cicode's `^` escape character, so that
+//! user supplied text cannot break out of its argument.
+//!
+//! [`CicodeCall`] composes a whole function call from typed arguments, so
+//! that callers never quote by hand.
+//!
+//! Window handles returned by Cicode are wrapped in [`CicodeWindow`] so that
+//! they cannot be confused with the mode argument of
+//! [`CtClient::cicode`](crate::CtClient::cicode).
+
+use crate::error::{CtApiError, Result};
+use crate::util::encode_to_gbk_strict;
+use std::str::FromStr;
+
+/// Maximum length of an alarm comment in GBK bytes.
+///
+/// Citect SCADA silently truncates longer comments, so the limit is enforced
+/// client-side instead.
+pub const MAX_COMMENT_LEN: usize = 128;
+
+/// Maximum length of an event log message (including the category prefix) in GBK bytes.
+pub const MAX_EVENT_MESSAGE_LEN: usize = 200;
+
+/// Quote a string as a Cicode string literal
+///
+/// Wraps the value in double quotes and escapes `^`, `"` and control
+/// characters with the Cicode escape character `^`.
+///
+/// # Errors
+/// * [`CtApiError::InvalidParameter`] - Value contains a null character
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::cicode::quote;
+///
+/// assert_eq!(quote(r#"say "hi""#)?, r#""say ^"hi^"""#);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn quote(value: &str) -> Result<String> {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\0' => {
+                return Err(CtApiError::InvalidParameter {
+                    param: "value".to_string(),
+                    value: value.to_string(),
+                });
+            }
+            '^' => quoted.push_str("^^"),
+            '"' => quoted.push_str("^\""),
+            '\n' => quoted.push_str("^n"),
+            '\r' => quoted.push_str("^r"),
+            '\t' => quoted.push_str("^t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+/// A Cicode function call built from typed arguments
+///
+/// String arguments are quoted with [`quote`], so embedded quotes and `^`
+/// cannot end the argument early. Argument problems are reported when the
+/// command is built by [`command`](Self::command) or run by
+/// [`CtClient::cicode_call`](crate::CtClient::cicode_call), so calls can be
+/// chained freely.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::cicode::CicodeCall;
+///
+/// let call = CicodeCall::new("TagInfo").arg_str("Cluster1.Tag_1").arg_int(5);
+/// assert_eq!(call.command()?, r#"TagInfo("Cluster1.Tag_1",5)"#);
+///
+/// let call = CicodeCall::new("Prompt").arg_str(r#"Valve "V1" ^ open"#);
+/// assert_eq!(call.command()?, r#"Prompt("Valve ^"V1^" ^^ open")"#);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CicodeCall {
+    function: String,
+    args: Vec<String>,
+    /// First problem found, reported when the command is built
+    rejected: Option<Rejected>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Rejected {
+    Invalid { param: String, value: String },
+    Encoding(String),
+}
+
+impl Rejected {
+    fn error(&self) -> CtApiError {
+        match self {
+            Rejected::Invalid { param, value } => CtApiError::InvalidParameter {
+                param: param.clone(),
+                value: value.clone(),
+            },
+            Rejected::Encoding(text) => CtApiError::Encoding { text: text.clone() },
+        }
+    }
+}
+
+impl CicodeCall {
+    /// Call of the Cicode function `function`, without arguments yet
+    ///
+    /// `function` must be a Cicode identifier: letters, digits and `_`, not
+    /// starting with a digit.
+    pub fn new(function: &str) -> Self {
+        let mut chars = function.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        Self {
+            function: function.to_string(),
+            args: Vec::new(),
+            rejected: (!valid).then(|| Rejected::Invalid {
+                param: "function".to_string(),
+                value: function.to_string(),
+            }),
+        }
+    }
+
+    /// Append a string argument
+    ///
+    /// The value may not contain line breaks or null characters, and must be
+    /// representable in GBK.
+    pub fn arg_str(mut self, value: &str) -> Self {
+        let param = format!("argument {}", self.args.len() + 1);
+        if value.contains(['\0', '\n', '\r']) {
+            self.reject(Rejected::Invalid {
+                param,
+                value: value.to_string(),
+            });
+        } else if encode_to_gbk_strict(value).is_err() {
+            self.reject(Rejected::Encoding(value.to_string()));
+        }
+        // Cannot fail: null characters were checked above
+        let quoted = quote(value).unwrap_or_default();
+        self.args.push(quoted);
+        self
+    }
+
+    /// Append an integer argument
+    pub fn arg_int(mut self, value: i64) -> Self {
+        self.args.push(value.to_string());
+        self
+    }
+
+    /// Append a real argument
+    ///
+    /// The value must be finite: Cicode has no literal for infinity or NaN.
+    pub fn arg_real(mut self, value: f64) -> Self {
+        if !value.is_finite() {
+            let param = format!("argument {}", self.args.len() + 1);
+            self.reject(Rejected::Invalid {
+                param,
+                value: value.to_string(),
+            });
+        }
+        self.args.push(value.to_string());
+        self
+    }
+
+    fn reject(&mut self, rejected: Rejected) {
+        self.rejected.get_or_insert(rejected);
+    }
+
+    /// Name of the function called
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Command string to pass to [`CtClient::cicode`](crate::CtClient::cicode)
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - The function name is not an
+    ///   identifier, a string argument contains a line break or null
+    ///   character, or a real argument is not finite; `param` names it, e.g.
+    ///   `argument 2`
+    /// * [`CtApiError::Encoding`] - A string argument cannot be encoded as GBK
+    pub fn command(&self) -> Result<String> {
+        if let Some(rejected) = &self.rejected {
+            return Err(rejected.error());
+        }
+        Ok(format!("{}({})", self.function, self.args.join(",")))
+    }
+}
+
+/// Check that `text` is GBK-representable and at most `max` bytes once encoded
+fn check_text(field: &str, text: &str, max: usize) -> Result<()> {
+    let len = encode_to_gbk_strict(text)?.len();
+    if len > max {
+        return Err(CtApiError::TextTooLong {
+            field: field.to_string(),
+            len,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// Build the command that looks up the first alarm record of a tag
+pub(crate) fn alarm_first_tag_rec_command(alarm_tag: &str, cluster: &str) -> Result<String> {
+    Ok(format!(
+        "AlarmFirstTagRec({},\"\",\"\",{})",
+        quote(alarm_tag)?,
+        quote(cluster)?
+    ))
+}
+
+/// Build the command that sets the comment of an active alarm record
+pub(crate) fn alarm_comment_command(record: i32, text: &str) -> Result<String> {
+    check_text("comment", text, MAX_COMMENT_LEN)?;
+    Ok(format!(
+        "AlarmSumSet(AlarmSumFind({record},AlarmGetFieldRec({record},\"ONTIME\")),\"Comment\",{})",
+        quote(text)?
+    ))
+}
+
+/// Build the command that writes a categorised message to the event log
+pub(crate) fn log_event_command(category: &str, message: &str) -> Result<String> {
+    let text = format!("[{category}] {message}");
+    check_text("message", &text, MAX_EVENT_MESSAGE_LEN)?;
+    Ok(format!("SysLog({})", quote(&text)?))
+}
+
+/// Build the command that opens `page` in a new window at `(x, y)`
+pub(crate) fn win_new_at_command(page: &str, x: i32, y: i32, mode: u32) -> Result<String> {
+    Ok(format!("WinNewAt({},{x},{y},{mode})", quote(page)?))
+}
+
+/// Command that closes the window it runs in
+pub(crate) const WIN_FREE_COMMAND: &str = "WinFree()";
+
+/// Interpret the window number returned by `WinNewAt`
+///
+/// `WinNewAt` returns `-1` if the window cannot be opened. A window number
+/// of `0` comes back as [`CicodeWindow::NONE`]: `ctCicode` runs calls with a
+/// `vhWin` of 0 without a particular window, so the two cannot be told apart.
+pub(crate) fn parse_window(result: &str) -> Result<CicodeWindow> {
+    match result.trim().parse::<u32>() {
+        Ok(handle) => Ok(CicodeWindow(handle)),
+        Err(_) => Err(CtApiError::UnexpectedCicodeResult {
+            function: "WinNewAt".to_string(),
+            result: result.to_string(),
+        }),
+    }
+}
+
+/// Handle of a Citect window
+///
+/// Returned by [`CtClient::open_window`](crate::CtClient::open_window) and
+/// taken wherever a Cicode call takes a window (`vhWin`) argument. There is
+/// no conversion from a bare number, so the window cannot be swapped with
+/// the `mode` argument by mistake; pass [`NONE`](Self::NONE) for no
+/// particular window or wrap a known number with
+/// [`from_raw`](Self::from_raw).
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CicodeWindow;
+///
+/// assert_eq!(CicodeWindow::from_raw(0), CicodeWindow::NONE);
+/// assert_eq!(CicodeWindow::from_raw(3).raw(), 3);
+/// ```
+///
+/// ```compile_fail
+/// use ctapi_rs::CtClient;
+///
+/// let client = CtClient::open_mock()?;
+/// client.cicode("Time(1)", 0, 0)?; // window must be a CicodeWindow
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CicodeWindow(u32);
+
+impl CicodeWindow {
+    /// No particular window
+    pub const NONE: CicodeWindow = CicodeWindow(0);
+
+    /// Wrap a window number obtained elsewhere, for example from `WinNumber()`
+    pub const fn from_raw(handle: u32) -> Self {
+        Self(handle)
+    }
+
+    /// Window number as passed to CtAPI
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for CicodeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "window {}", self.0)
+    }
+}
+
+/// Map the return value of a Cicode function that returns `0` on success
+pub(crate) fn check_status(result: &str) -> Result<()> {
+    match result.trim().parse::<u32>() {
+        Ok(0) => Ok(()),
+        Ok(code) => Err(CtApiError::from_error_code(code)),
+        Err(_) => Err(CtApiError::Other {
+            code: 0,
+            message: format!("unexpected Cicode result: {result}"),
+        }),
+    }
+}
+
+/// Delimited list returned by a Cicode function
+///
+/// Many Cicode functions pack a list into a single string, for example
+/// `"Cluster1,Cluster2"`. `CicodeResult` splits such a string once, trimming
+/// whitespace around each item. Items enclosed in double quotes may contain
+/// the delimiter and `^` escapes, which are decoded.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::cicode::CicodeResult;
+///
+/// let result = CicodeResult::parse(r#"Alpha, "Beta, Gamma" ,Delta"#, ',');
+/// assert_eq!(result.items(), ["Alpha", "Beta, Gamma", "Delta"]);
+/// assert_eq!(result.get(1), Some("Beta, Gamma"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CicodeResult {
+    raw: String,
+    items: Vec<String>,
+}
+
+impl CicodeResult {
+    /// Split `raw` at every unquoted `delimiter`
+    ///
+    /// An empty or all-whitespace string yields an empty list.
+    pub fn parse(raw: &str, delimiter: char) -> Self {
+        let mut items = Vec::new();
+        if !raw.trim().is_empty() {
+            let mut item = String::new();
+            // Byte range of `item` that came from inside quotes and must not be trimmed
+            let mut quoted_range: Option<(usize, usize)> = None;
+            let mut chars = raw.chars();
+            let mut quoted = false;
+            while let Some(c) = chars.next() {
+                let start = item.len();
+                match c {
+                    '"' => {
+                        quoted = !quoted;
+                        continue;
+                    }
+                    '^' if quoted => match chars.next() {
+                        Some('n') => item.push('\n'),
+                        Some('r') => item.push('\r'),
+                        Some('t') => item.push('\t'),
+                        Some(c) => item.push(c),
+                        None => item.push('^'),
+                    },
+                    c if c == delimiter && !quoted => {
+                        items.push(trim_unquoted(&item, quoted_range.take()));
+                        item.clear();
+                        continue;
+                    }
+                    c => item.push(c),
+                }
+                if quoted {
+                    let from = quoted_range.map_or(start, |(from, _)| from);
+                    quoted_range = Some((from, item.len()));
+                }
+            }
+            items.push(trim_unquoted(&item, quoted_range));
+        }
+        Self {
+            raw: raw.to_string(),
+            items,
+        }
+    }
+
+    /// The unsplit string returned by the server
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// All items in order
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Item at `index`, if present
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.items.get(index).map(String::as_str)
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the list has no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Parse every item as `T`
+    ///
+    /// # Errors
+    /// * [`CtApiError::Other`] - An item could not be parsed; the message names the item
+    pub fn parse_all<T: FromStr>(&self) -> Result<Vec<T>> {
+        self.items
+            .iter()
+            .map(|item| {
+                item.parse().map_err(|_| CtApiError::Other {
+                    code: 0,
+                    message: format!("cannot parse Cicode list item: {item:?}"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Trim whitespace from `item`, except inside the quoted byte range
+fn trim_unquoted(item: &str, quoted_range: Option<(usize, usize)>) -> String {
+    match quoted_range {
+        Some((from, to)) => format!(
+            "{}{}{}",
+            item[..from].trim_start(),
+            &item[from..to],
+            item[to..].trim_end()
+        ),
+        None => item.trim().to_string(),
+    }
+}
+
+impl IntoIterator for CicodeResult {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CicodeResult {
+    type Item = &'a String;
+    type IntoIter = std::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_escapes() {
+        assert_eq!(quote("plain").unwrap(), "\"plain\"");
+        assert_eq!(quote("a\"b").unwrap(), "\"a^\"b\"");
+        assert_eq!(quote("a^b").unwrap(), "\"a^^b\"");
+        assert_eq!(quote("a\nb").unwrap(), "\"a^nb\"");
+        assert!(quote("a\0b").is_err());
+    }
+
+    #[test]
+    fn test_call_commands() {
+        let time = CicodeCall::new("Time").arg_int(1);
+        assert_eq!(time.command().unwrap(), "Time(1)");
+        assert_eq!(CicodeCall::new("_Init").command().unwrap(), "_Init()");
+        let call = CicodeCall::new("TagWrite")
+            .arg_str("Tank \"A\" Level")
+            .arg_real(-2.5)
+            .arg_int(0)
+            .arg_str("");
+        assert_eq!(call.function(), "TagWrite");
+        assert_eq!(
+            call.command().unwrap(),
+            "TagWrite(\"Tank ^\"A^\" Level\",-2.5,0,\"\")"
+        );
+        let call = CicodeCall::new("Message").arg_str("温度^高").arg_real(3.0);
+        assert_eq!(call.command().unwrap(), "Message(\"温度^^高\",3)");
+    }
+
+    #[test]
+    fn test_call_rejects_bad_arguments() {
+        let rejected = |call: CicodeCall| match call.command().unwrap_err() {
+            CtApiError::InvalidParameter { param, .. } => param,
+            err => panic!("{err:?}"),
+        };
+        let line_break = CicodeCall::new("Prompt").arg_int(1).arg_str("two\nlines");
+        assert_eq!(rejected(line_break), "argument 2");
+        for text in ["a\rb", "a\0b"] {
+            let call = CicodeCall::new("Prompt").arg_str(text);
+            assert_eq!(rejected(call), "argument 1", "{text:?}");
+        }
+        let nan = CicodeCall::new("Sqrt").arg_real(f64::NAN);
+        assert_eq!(rejected(nan), "argument 1");
+        for function in ["", "1Time", "Time()", "Time;Shutdown"] {
+            let call = CicodeCall::new(function);
+            assert_eq!(rejected(call), "function", "{function}");
+        }
+
+        // The first problem is reported
+        let call = CicodeCall::new("Prompt").arg_str("a\nb").arg_str("c\0d");
+        assert_eq!(rejected(call), "argument 1");
+        let emoji = CicodeCall::new("Prompt").arg_str("ok 👍");
+        assert!(matches!(emoji.command(), Err(CtApiError::Encoding { .. })));
+    }
+
+    #[test]
+    fn test_alarm_comment_command_quotes_embedded_quotes() {
+        let cmd = alarm_comment_command(12, "valve \"V1\" stuck").unwrap();
+        assert_eq!(
+            cmd,
+            "AlarmSumSet(AlarmSumFind(12,AlarmGetFieldRec(12,\"ONTIME\")),\"Comment\",\"valve ^\"V1^\" stuck\")"
+        );
+        let cmd = alarm_first_tag_rec_command("Tag\"1", "Cluster1").unwrap();
+        assert_eq!(cmd, "AlarmFirstTagRec(\"Tag^\"1\",\"\",\"\",\"Cluster1\")");
+    }
+
+    #[test]
+    fn test_overlong_text_rejected() {
+        let text = "x".repeat(MAX_COMMENT_LEN);
+        assert!(alarm_comment_command(1, &text).is_ok());
+
+        let text = "x".repeat(MAX_COMMENT_LEN + 1);
+        let err = alarm_comment_command(1, &text).unwrap_err();
+        assert!(matches!(
+            err,
+            CtApiError::TextTooLong { len, max, .. } if len == MAX_COMMENT_LEN + 1 && max == MAX_COMMENT_LEN
+        ));
+
+        // Limit is measured in encoded bytes: each CJK character is two GBK bytes
+        let text = "温".repeat(MAX_COMMENT_LEN / 2 + 1);
+        assert!(alarm_comment_command(1, &text).is_err());
+
+        let message = "x".repeat(MAX_EVENT_MESSAGE_LEN);
+        assert!(log_event_command("Ops", &message).is_err());
+    }
+
+    #[test]
+    fn test_unrepresentable_text_rejected() {
+        let err = alarm_comment_command(1, "pump ok 👍").unwrap_err();
+        assert!(matches!(err, CtApiError::Encoding { .. }));
+        assert!(log_event_command("Ops", "温度 ok").is_ok());
+    }
+
+    #[test]
+    fn test_log_event_command() {
+        assert_eq!(
+            log_event_command("Ops", "pump \"P1\" started").unwrap(),
+            "SysLog(\"[Ops] pump ^\"P1^\" started\")"
+        );
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert!(check_status("0").is_ok());
+        assert!(matches!(
+            check_status("274"),
+            Err(CtApiError::Other { code: 274, .. })
+        ));
+        assert!(check_status("garbage").is_err());
+    }
+
+    #[test]
+    fn test_window_commands() {
+        assert_eq!(
+            win_new_at_command("Pump \"A\"", 10, -5, 2).unwrap(),
+            "WinNewAt(\"Pump ^\"A^\"\",10,-5,2)"
+        );
+        assert_eq!(parse_window(" 3 ").unwrap(), CicodeWindow::from_raw(3));
+        assert_eq!(parse_window("0").unwrap(), CicodeWindow::NONE);
+
+        for raw in ["-1", "", "Page not found"] {
+            let err = parse_window(raw).unwrap_err();
+            assert!(
+                matches!(&err, CtApiError::UnexpectedCicodeResult { function, result }
+                    if function == "WinNewAt" && result == raw),
+                "{err:?}"
+            );
+        }
+        assert!(
+            parse_window("-1")
+                .unwrap_err()
+                .to_string()
+                .contains("\"-1\"")
+        );
+    }
+
+    #[test]
+    fn test_cicode_result_split_and_trim() {
+        let result = CicodeResult::parse(" Cluster1 ,Cluster2,  Cluster3 ", ',');
+        assert_eq!(result.items(), ["Cluster1", "Cluster2", "Cluster3"]);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result.get(2), Some("Cluster3"));
+        assert_eq!(result.get(3), None);
+        assert_eq!(result.raw(), " Cluster1 ,Cluster2,  Cluster3 ");
+    }
+
+    #[test]
+    fn test_cicode_result_empty() {
+        assert!(CicodeResult::parse("", ',').is_empty());
+        assert!(CicodeResult::parse("   ", ',').is_empty());
+        // A lone delimiter is two empty items, not an empty list
+        assert_eq!(CicodeResult::parse(",", ',').items(), ["", ""]);
+        assert_eq!(CicodeResult::parse("single", ',').items(), ["single"]);
+    }
+
+    #[test]
+    fn test_cicode_result_quoted_items() {
+        let result = CicodeResult::parse(r#""a,b",c,"say ^"hi^"",  "  padded  ""#, ',');
+        assert_eq!(result.items(), ["a,b", "c", "say \"hi\"", "  padded  "]);
+
+        let result = CicodeResult::parse(r#""line^none"^"x^^y""#, '^');
+        assert_eq!(result.items(), ["line\none", "x^y"]);
+    }
+
+    #[test]
+    fn test_cicode_result_other_delimiters() {
+        let result = CicodeResult::parse("Page1^Page2^Page3", '^');
+        assert_eq!(result.items(), ["Page1", "Page2", "Page3"]);
+        let result = CicodeResult::parse("1\t2\t3", '\t');
+        assert_eq!(result.parse_all::<u32>().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_cicode_result_parse_all() {
+        let result = CicodeResult::parse("1.5, 2, -3", ',');
+        assert_eq!(result.parse_all::<f64>().unwrap(), [1.5, 2.0, -3.0]);
+        let err = CicodeResult::parse("1,two,3", ',')
+            .parse_all::<i32>()
+            .unwrap_err();
+        assert!(err.to_string().contains("two"));
+        assert!(
+            CicodeResult::default()
+                .parse_all::<i32>()
+                .unwrap()
+                .is_empty()
+        );
+
+        let collected: Vec<String> = CicodeResult::parse("x,y", ',').into_iter().collect();
+        assert_eq!(collected, ["x", "y"]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Citect error codes reported through `GetLastError`
+//!
+//! CtAPI reports its own failures as Citect error codes offset by
+//! [`ERROR_USER_DEFINED_BASE`], which Windows would format as a meaningless
+//! "OS error 268435716". Converting such an [`io::Error`](std::io::Error) into a
+//! [`CtApiError`] gives [`CtApiError::Citect`] with the code, a stable name
+//! and a description instead; Win32 codes stay
+//! [`System`](CtApiError::System) errors.
+//!
+//! The general errors of the Citect error list, the cancellation errors
+//! `ctCancelIO` reports, tag-not-found and licence errors are named. Other
+//! codes keep their number and are reported as `unknown`.
+//!
+//! ```
+//! use ctapi_rs::{CitectCode, CtApiError, ERROR_USER_DEFINED_BASE};
+//!
+//! let os_error = std::io::Error::from_raw_os_error((ERROR_USER_DEFINED_BASE + 260) as i32);
+//! let error = CtApiError::from(os_error);
+//! assert!(matches!(error, CtApiError::Citect { code: 260, name: "invalid_argument", .. }));
+//! assert_eq!(CitectCode::new(260).description(), Some("Invalid argument passed"));
+//! ```
+
+use crate::constants::ERROR_USER_DEFINED_BASE;
+use crate::error::CtApiError;
+use std::fmt;
+
+/// Name and description of the Citect errors CtAPI reports
+///
+/// Source: Citect SCADA help, *Cicode Reference > Cicode Errors > Cicode and
+/// General Errors*, for the entries up to `wrong_area`. The numbers of the
+/// entries after it (`GENERIC_INVALID_DATA`, `GENERIC_CANNOT_CANCEL`, the
+/// cancelled, tag-not-found and licence errors) are assumptions that have
+/// not been checked against a server yet; a wrong number only changes the
+/// name reported for a code.
+const CITECT_ERRORS: &[(u32, &str, &str)] = &[
+    (256, "general_error", "General software error"),
+    (257, "no_literal", "Literal value does not exist"),
+    (258, "out_of_memory", "Out of memory"),
+    (259, "busy", "Function is busy"),
+    (260, "invalid_argument", "Invalid argument passed"),
+    (261, "cannot_read", "Cannot read data"),
+    (262, "cannot_write", "Cannot write data"),
+    (267, "no_privilege", "No privilege for operation"),
+    (268, "wrong_area", "Not in correct area for operation"),
+    (263, "invalid_data", "Invalid data"),
+    (264, "cannot_cancel", "Operation cannot be cancelled"),
+    (265, "cancelled", "Operation was cancelled"),
+    (266, "tag_not_found", "Tag does not exist"),
+    (269, "no_licence", "No licence available"),
+    (270, "licence_expired", "Licence has expired"),
+];
+
+/// Citect error code, without the [`ERROR_USER_DEFINED_BASE`] offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CitectCode(u32);
+
+impl CitectCode {
+    /// Wrap a Citect error code
+    pub fn new(code: u32) -> Self {
+        Self(code)
+    }
+
+    /// Citect code carried by a `GetLastError` value, `None` for Win32 codes
+    /// and HRESULTs
+    pub fn from_os_error(os_error: i32) -> Option<Self> {
+        let os_error = u32::try_from(os_error).ok()?;
+        let code = os_error.checked_sub(ERROR_USER_DEFINED_BASE)?;
+        (code < ERROR_USER_DEFINED_BASE).then_some(Self(code))
+    }
+
+    /// Raw Citect error code
+    pub fn code(self) -> u32 {
+        self.0
+    }
+
+    /// `GetLastError` value CtAPI reports the code as
+    pub fn os_error(self) -> i32 {
+        ERROR_USER_DEFINED_BASE.wrapping_add(self.0) as i32
+    }
+
+    /// Stable name of the code, `unknown` for codes not in the table
+    pub fn name(self) -> &'static str {
+        self.entry().map_or("unknown", |(name, _)| name)
+    }
+
+    /// Description of a known code
+    pub fn description(self) -> Option<&'static str> {
+        self.entry().map(|(_, description)| description)
+    }
+
+    fn entry(self) -> Option<(&'static str, &'static str)> {
+        CITECT_ERRORS
+            .iter()
+            .find(|(code, _, _)| *code == self.0)
+            .map(|(_, name, description)| (*name, *description))
+    }
+}
+
+impl fmt::Display for CitectCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{description} (Citect error {})", self.0),
+            None => write!(f, "Citect error {}", self.0),
+        }
+    }
+}
+
+impl From<CitectCode> for CtApiError {
+    fn from(code: CitectCode) -> Self {
+        CtApiError::Citect {
+            code: code.0,
+            name: code.name(),
+            description: code.description().unwrap_or("unknown Citect error"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Error of a call that failed with Citect error `code`
+    fn failed_with(code: u32) -> CtApiError {
+        io::Error::from_raw_os_error(CitectCode::new(code).os_error()).into()
+    }
+
+    #[test]
+    fn test_citect_codes_are_translated() {
+        let error = failed_with(261);
+        assert_eq!(error.code(), 10_261);
+        assert_eq!(
+            error.to_string(),
+            "[E10261] Citect error 261 (cannot_read): Cannot read data"
+        );
+
+        let CtApiError::Citect { name, .. } = failed_with(999) else {
+            panic!("not a Citect error");
+        };
+        assert_eq!(name, "unknown");
+
+        assert_eq!(CitectCode::new(264).name(), "cannot_cancel");
+        assert_eq!(CitectCode::new(266).name(), "tag_not_found");
+        let mut codes: Vec<_> = CITECT_ERRORS.iter().map(|(code, _, _)| code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), CITECT_ERRORS.len());
+
+        // Win32 codes are left to the system error
+        let win32 = CtApiError::from(io::Error::from_raw_os_error(1168));
+        assert!(matches!(win32, CtApiError::System(..)));
+        assert_eq!(CitectCode::from_os_error(1168), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! Time source abstraction for time-based features
+//!
+//! Components that measure elapsed time or wait take a [`Clock`] instead of
+//! calling [`Instant::now`] and [`std::thread::sleep`] directly, so that tests
+//! can substitute [`MockClock`] and run without real delays.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Source of the current time and blocking waits
+pub(crate) trait Clock: Debug + Send + Sync {
+    /// Current instant
+    fn now(&self) -> Instant;
+
+    /// Block the calling thread for `duration`
+    fn sleep(&self, duration: Duration);
+
+    /// Block the calling thread until `deadline`, returning immediately if it has passed
+    fn sleep_until(&self, deadline: Instant) {
+        let now = self.now();
+        if deadline > now {
+            self.sleep(deadline - now);
+        }
+    }
+}
+
+/// Shared handle to a clock
+pub(crate) type SharedClock = Arc<dyn Clock>;
+
+/// Clock backed by the operating system
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Default clock used when none is injected
+pub(crate) fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Manually driven clock for tests
+///
+/// Time only moves when [`advance`](MockClock::advance) is called or when a
+/// caller sleeps, in which case the clock jumps forward by the requested
+/// duration instead of blocking.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct MockClock {
+    start: Instant,
+    elapsed: std::sync::Mutex<Duration>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    /// Create a mock clock frozen at the current instant
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            start: Instant::now(),
+            elapsed: std::sync::Mutex::new(Duration::ZERO),
+        })
+    }
+
+    /// Move the clock forward by `duration`
+    pub(crate) fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Total time the clock has moved since creation
+    pub(crate) fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advance() {
+        let clock = MockClock::new();
+        let t0 = clock.now();
+        assert_eq!(clock.now(), t0);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now() - t0, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_mock_clock_sleep_does_not_block() {
+        let clock = MockClock::new();
+        let real = Instant::now();
+        clock.sleep(Duration::from_secs(3600));
+        assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+        assert!(real.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sleep_until() {
+        let clock = MockClock::new();
+        let deadline = clock.now() + Duration::from_millis(250);
+        clock.sleep_until(deadline);
+        assert_eq!(clock.now(), deadline);
+
+        // A deadline in the past returns immediately
+        clock.sleep_until(deadline - Duration::from_millis(100));
+        assert_eq!(clock.now(), deadline);
+    }
+
+    #[test]
+    fn test_shared_clock_is_object_safe() {
+        let mock = MockClock::new();
+        let shared: SharedClock = mock.clone();
+        mock.advance(Duration::from_secs(1));
+        assert_eq!(shared.now(), mock.now());
+
+        let system = system_clock();
+        assert!(system.now() <= Instant::now());
+    }
+}
@@ -0,0 +1,249 @@
+//! Connection credentials from the environment
+//!
+//! [`EnvCredentials::load`] reads the connection parameters from
+//! environment variables so that no password has to appear in source code:
+//!
+//! | Variable | Meaning |
+//! |----------|---------|
+//! | `CTAPI_COMPUTER` | Computer name or IP address; unset for the local computer |
+//! | `CTAPI_USER` | User name |
+//! | `CTAPI_PASSWORD_FILE` | Path of a file holding the password, e.g. a mounted secret |
+//! | `CTAPI_PASSWORD` | Password, used when `CTAPI_PASSWORD_FILE` is unset |
+//!
+//! With the `cli` feature, [`EnvCredentials::prompt`] asks on the terminal
+//! for what the environment leaves out, reading the password without
+//! echoing it.
+//!
+//! Any [`CredentialSource`] can be handed to
+//! [`CtClientBuilder::credentials`](crate::CtClientBuilder::credentials).
+
+use crate::error::{CtApiError, Result};
+use crate::secret::SecretString;
+
+/// Variable naming the computer to connect to
+pub const COMPUTER_VAR: &str = "CTAPI_COMPUTER";
+/// Variable naming the user
+pub const USER_VAR: &str = "CTAPI_USER";
+/// Variable holding the password
+pub const PASSWORD_VAR: &str = "CTAPI_PASSWORD";
+/// Variable holding the path of a file containing the password
+pub const PASSWORD_FILE_VAR: &str = "CTAPI_PASSWORD_FILE";
+
+/// Something that supplies connection credentials
+pub trait CredentialSource {
+    /// Computer name or IP address, `None` for the local computer
+    fn computer(&self) -> Option<&str>;
+    /// User name
+    fn user(&self) -> Option<&str>;
+    /// Password
+    fn password(&self) -> Option<&SecretString>;
+}
+
+/// Credentials read from `CTAPI_*` environment variables
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::credentials::EnvCredentials;
+///
+/// let client = CtClient::builder()
+///     .credentials(EnvCredentials::load()?)
+///     .build()?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvCredentials {
+    computer: Option<String>,
+    user: String,
+    password: SecretString,
+}
+
+/// Asks for a missing value: `(question, hidden)`
+type Ask<'a> = &'a mut dyn FnMut(&str, bool) -> std::io::Result<String>;
+
+impl EnvCredentials {
+    /// Read the credentials from the environment
+    ///
+    /// The password comes from the file named by `CTAPI_PASSWORD_FILE`,
+    /// without its trailing line break, or else from `CTAPI_PASSWORD`.
+    /// Empty variables count as unset.
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `CTAPI_USER` or both password
+    ///   variables are unset, or the password file cannot be read; the
+    ///   message says which variable to set
+    pub fn load() -> Result<Self> {
+        Self::resolve(|name| std::env::var(name).ok(), None)
+    }
+
+    /// Read the credentials from the environment, asking on the terminal
+    /// for the user and password if they are not set
+    ///
+    /// The password is read without echoing it.
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - The password file cannot be read,
+    ///   or nothing was entered
+    /// * [`CtApiError::System`] - The terminal could not be read
+    #[cfg(feature = "cli")]
+    pub fn prompt() -> Result<Self> {
+        let mut ask = |question: &str, hidden: bool| {
+            if hidden {
+                return rpassword::prompt_password(question);
+            }
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            write!(stdout, "{question}")?;
+            stdout.flush()?;
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            Ok(line)
+        };
+        Self::resolve(|name| std::env::var(name).ok(), Some(&mut ask))
+    }
+
+    fn resolve(var: impl Fn(&str) -> Option<String>, mut ask: Option<Ask<'_>>) -> Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let mut ask_for = |name: &str, question: &str, hidden: bool| -> Result<String> {
+            let Some(ask) = ask.as_mut() else {
+                return Err(missing(name));
+            };
+            let answer = ask(question, hidden)?;
+            let answer = answer.trim_end_matches(['\r', '\n']);
+            if answer.is_empty() {
+                return Err(missing(name));
+            }
+            Ok(answer.to_string())
+        };
+
+        let computer = var(COMPUTER_VAR);
+        let user = match var(USER_VAR) {
+            Some(user) => user,
+            None => ask_for(USER_VAR, "CtAPI user: ", false)?,
+        };
+        let password = if let Some(path) = var(PASSWORD_FILE_VAR) {
+            let password =
+                std::fs::read_to_string(&path).map_err(|e| CtApiError::InvalidParameter {
+                    param: PASSWORD_FILE_VAR.to_string(),
+                    value: format!("cannot read {path}: {e}"),
+                })?;
+            password.trim_end_matches(['\r', '\n']).to_string()
+        } else if let Some(password) = var(PASSWORD_VAR) {
+            password
+        } else {
+            ask_for(PASSWORD_VAR, &format!("CtAPI password for {user}: "), true)?
+        };
+        Ok(Self {
+            computer,
+            user,
+            password: SecretString::from(password),
+        })
+    }
+}
+
+impl CredentialSource for EnvCredentials {
+    fn computer(&self) -> Option<&str> {
+        self.computer.as_deref()
+    }
+
+    fn user(&self) -> Option<&str> {
+        Some(&self.user)
+    }
+
+    fn password(&self) -> Option<&SecretString> {
+        Some(&self.password)
+    }
+}
+
+fn missing(name: &str) -> CtApiError {
+    let value = if name == PASSWORD_VAR {
+        format!("not set; set {PASSWORD_VAR}, or {PASSWORD_FILE_VAR} to a file holding it")
+    } else {
+        format!("not set; set {name} to the CtAPI user name")
+    };
+    CtApiError::InvalidParameter {
+        param: name.to_string(),
+        value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn password_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("ctapi-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_file_over_env_over_prompt() {
+        let path = password_file("secret", "from-file\n");
+        let mut asked = Vec::new();
+        let mut ask = |question: &str, _: bool| {
+            asked.push(question.to_string());
+            Ok("from-prompt".to_string())
+        };
+
+        let vars = [
+            (COMPUTER_VAR, "scada1"),
+            (USER_VAR, "Manager"),
+            (PASSWORD_VAR, "from-env"),
+            (PASSWORD_FILE_VAR, path.as_str()),
+        ];
+        let credentials = EnvCredentials::resolve(env(&vars), Some(&mut ask)).unwrap();
+        assert_eq!(credentials.computer(), Some("scada1"));
+        assert_eq!(credentials.user(), Some("Manager"));
+        assert_eq!(credentials.password().unwrap().expose(), "from-file");
+
+        let credentials = EnvCredentials::resolve(env(&vars[..3]), Some(&mut ask)).unwrap();
+        assert_eq!(credentials.password().unwrap().expose(), "from-env");
+
+        let credentials = EnvCredentials::resolve(env(&vars[..2]), Some(&mut ask)).unwrap();
+        assert_eq!(credentials.password().unwrap().expose(), "from-prompt");
+        assert_eq!(asked, ["CtAPI password for Manager: "]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_variables_name_the_fix() {
+        let err = EnvCredentials::resolve(env(&[]), None).unwrap_err();
+        assert!(matches!(err, CtApiError::InvalidParameter { ref param, .. } if param == USER_VAR));
+
+        // Blank values count as unset; the local computer needs no variable
+        let vars = [(USER_VAR, "Manager"), (PASSWORD_VAR, " ")];
+        let err = EnvCredentials::resolve(env(&vars), None).unwrap_err();
+        assert!(err.to_string().contains(PASSWORD_FILE_VAR));
+
+        let vars = [
+            (USER_VAR, "Manager"),
+            (PASSWORD_FILE_VAR, "/no/such/secret"),
+        ];
+        let err = EnvCredentials::resolve(env(&vars), None).unwrap_err();
+        assert!(err.to_string().contains("cannot read /no/such/secret"));
+
+        let mut empty = |_: &str, _: bool| Ok("\n".to_string());
+        let err = EnvCredentials::resolve(env(&vars[..1]), Some(&mut empty)).unwrap_err();
+        assert!(
+            matches!(err, CtApiError::InvalidParameter { ref param, .. } if param == PASSWORD_VAR)
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_password() {
+        let vars = [(USER_VAR, "Manager"), (PASSWORD_VAR, "s3cr3t-pw")];
+        let credentials = EnvCredentials::resolve(env(&vars), None).unwrap();
+        assert!(credentials.computer().is_none());
+        assert!(!format!("{credentials:?}").contains("s3cr3t-pw"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Reusable demo routines
+//!
+//! The example binaries under `examples/` are thin wrappers around these
+//! functions, and the same functions are exercised by the integration tests,
+//! so API changes that break the demos are caught by `cargo test`.
+//!
+//! # Feature flag
+//!
+//! Available only when the `demos` feature is enabled:
+//!
+//! ```toml
+//! ctapi-rs = { version = "...", features = ["demos"] }
+//! ```
+//!
+//! # Connection settings
+//!
+//! [`DemoConfig::from_env`] reads the endpoint with
+//! [`EnvCredentials`](crate::credentials::EnvCredentials) from the
+//! `CTAPI_COMPUTER`, `CTAPI_USER` and `CTAPI_PASSWORD` (or
+//! `CTAPI_PASSWORD_FILE`) environment variables. There are no defaults: a
+//! setting left unset is passed to `ctOpen` as NULL, which connects to the
+//! local server.
+//!
+//! The demos only write tags when `CTAPI_DEMO_WRITES=1` is also set, see
+//! [`DemoConfig::allow_writes`].
+
+use crate::credentials::{CredentialSource, EnvCredentials};
+use crate::error::Result;
+use crate::{
+    AsyncCtClient, AsyncOperation, CicodeWindow, CtClient, CtList, CtTagValueItems, SecretString,
+};
+use std::sync::Arc;
+
+/// Environment variable that lets the demos write tags when set to `1`
+pub const DEMO_WRITES_VAR: &str = "CTAPI_DEMO_WRITES";
+
+/// Connection settings for the demos
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DemoConfig {
+    /// Computer name or IP address, `None` for the local server
+    pub computer: Option<String>,
+    /// User name
+    pub user: Option<String>,
+    /// Password, redacted in `Debug` output
+    pub password: Option<SecretString>,
+    /// Whether the demos may write tags
+    ///
+    /// Off unless [`DEMO_WRITES_VAR`] is `1`, so pointing a demo at a live
+    /// plant only reads.
+    pub allow_writes: bool,
+}
+
+impl DemoConfig {
+    /// Read connection settings from the environment
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`](crate::CtApiError::InvalidParameter) -
+    ///   See [`EnvCredentials::load`]
+    pub fn from_env() -> Result<Self> {
+        let credentials = EnvCredentials::load()?;
+        Ok(Self {
+            computer: credentials.computer().map(str::to_string),
+            user: credentials.user().map(str::to_string),
+            password: credentials.password().cloned(),
+            allow_writes: std::env::var_os(DEMO_WRITES_VAR).is_some_and(|value| value == "1"),
+        })
+    }
+
+    /// Open a client with these settings
+    pub fn open(&self) -> Result<CtClient> {
+        CtClient::open(
+            self.computer.as_deref(),
+            self.user.as_deref(),
+            self.password.as_ref().map(SecretString::expose),
+            0,
+        )
+    }
+
+    /// `value` when [`allow_writes`](Self::allow_writes) is set
+    pub fn write_value<'a>(&self, value: &'a str) -> Option<&'a str> {
+        self.allow_writes.then_some(value)
+    }
+}
+
+/// Result of [`run_tag_roundtrip`]
+#[derive(Debug, Clone)]
+pub struct TagRoundtrip {
+    /// Value read before the write
+    pub before: String,
+    /// Value read back after the write
+    pub after: String,
+    /// Extended metadata of the read-back
+    pub items: CtTagValueItems,
+}
+
+/// Read a tag, write `value` to it if given and read it back
+///
+/// With `None` nothing is written; pass
+/// [`DemoConfig::write_value`] to write only when the user opted in.
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::demos::{DemoConfig, run_tag_roundtrip};
+///
+/// let config = DemoConfig::from_env()?;
+/// let client = config.open()?;
+/// let value = config.write_value("1");
+/// let roundtrip = run_tag_roundtrip(&client, "TagExt_DemoTag1", value)?;
+/// println!("{} -> {}", roundtrip.before, roundtrip.after);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn run_tag_roundtrip(
+    client: &CtClient,
+    tag: &str,
+    value: Option<&str>,
+) -> Result<TagRoundtrip> {
+    let before = client.tag_read(tag)?;
+    if let Some(value) = value {
+        client.tag_write_str(tag, value)?;
+    }
+    let mut items = CtTagValueItems::default();
+    let after = client.tag_read_ex(tag, &mut items)?;
+    Ok(TagRoundtrip {
+        before,
+        after,
+        items,
+    })
+}
+
+/// Create the list read by [`run_list_cycle`] and add `tags` to it
+pub fn new_demo_list(client: Arc<CtClient>, tags: &[&str]) -> Result<CtList> {
+    let list = client.list_new(0)?;
+    for tag in tags {
+        list.add_tag(tag)?;
+    }
+    Ok(list)
+}
+
+/// Read `list` once and return each tag's value in the order they were added
+///
+/// The list is kept between cycles, as a polling loop should.
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::demos::{DemoConfig, new_demo_list, run_list_cycle};
+/// use std::sync::Arc;
+///
+/// let client = Arc::new(DemoConfig::from_env()?.open()?);
+/// let list = new_demo_list(client, &["TagExt_DemoTag1"])?;
+/// for (tag, value) in run_list_cycle(&list)? {
+///     println!("{tag} = {value}");
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn run_list_cycle(list: &CtList) -> Result<Vec<(String, String)>> {
+    list.read()?;
+    values_of(list)
+}
+
+/// Value of every tag from the last read of `list`
+fn values_of(list: &CtList) -> Result<Vec<(String, String)>> {
+    let values = list.read_all_ordered(0).into_iter();
+    values.map(|(tag, value)| Ok((tag, value?))).collect()
+}
+
+/// Start all `commands` as OVERLAPPED Cicode calls, then collect the results in order
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::demos::{DemoConfig, run_async_cicode};
+///
+/// let client = DemoConfig::from_env()?.open()?;
+/// let results = run_async_cicode(&client, &["Time(1)", "Date(4)"])?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn run_async_cicode(client: &CtClient, commands: &[&str]) -> Result<Vec<String>> {
+    let mut ops: Vec<AsyncOperation> = commands.iter().map(|_| AsyncOperation::new()).collect();
+    for (op, cmd) in ops.iter_mut().zip(commands) {
+        client.cicode_async(cmd, CicodeWindow::NONE, 0, op)?;
+    }
+    ops.iter_mut().map(|op| op.get_result(client)).collect()
+}
+
+/// Tokio variant of [`run_list_cycle`] plus a Cicode call, using the async traits
+///
+/// Requires the `tokio-support` feature.
+#[cfg(feature = "tokio-support")]
+pub async fn run_tokio_cycle(
+    client: &Arc<CtClient>,
+    list: &CtList,
+) -> Result<(String, Vec<(String, String)>)> {
+    use crate::{TokioCtClient, TokioCtList};
+
+    let time = client
+        .cicode_tokio("Time(1)", CicodeWindow::NONE, 0)
+        .await?;
+    list.read_tokio().await?;
+    Ok((time, values_of(list)?))
+}
@@ -0,0 +1,324 @@
+//! Discovery of reachable Citect SCADA servers
+//!
+//! On the local node [`CtClient::open`] works without a computer name, but
+//! remote nodes need the exact name or address of the server. [`probe`]
+//! tries a short-lived connection to each candidate concurrently and reports
+//! which ones answered, how fast and with which server version.
+//! [`discover_from_config_paths`] collects candidates from the Citect.ini
+//! files of local installations.
+//!
+//! Both are meant for installers and self tests, not the hot path: every
+//! probe opens and closes a connection.
+
+use crate::CtClient;
+use crate::error::{CtApiError, Result};
+use crate::retry::is_credentials_error;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Citect.ini entries naming the servers a node connects to, as `(section, key)`
+const SERVER_KEYS: &[(&str, &str)] = &[("Client", "Primary"), ("Client", "Standby")];
+
+/// Names of `%ProgramData%` directories that hold Citect SCADA installations
+const VENDOR_DIRS: &[&str] = &["AVEVA", "Citect", "Plant SCADA", "Schneider Electric"];
+
+/// How a candidate answered a [`probe`]
+#[derive(Debug)]
+pub enum ProbeOutcome {
+    /// A connection was established
+    Reachable {
+        /// Result of the server's Cicode `Version()`, if it could be read
+        server_version: Option<String>,
+    },
+    /// A server answered but rejected the connection without credentials
+    CredentialsRejected,
+    /// The connection failed
+    Unreachable(CtApiError),
+    /// No answer within the probe timeout
+    TimedOut,
+}
+
+/// Result of probing one candidate
+#[derive(Debug)]
+pub struct ProbeResult {
+    /// Computer name or address probed
+    pub computer: String,
+    /// How the candidate answered
+    pub outcome: ProbeOutcome,
+    /// Time until the answer, or the timeout
+    pub elapsed: Duration,
+}
+
+impl ProbeResult {
+    /// Whether a Citect SCADA server answered, even if it rejected the connection
+    pub fn is_reachable(&self) -> bool {
+        matches!(
+            self.outcome,
+            ProbeOutcome::Reachable { .. } | ProbeOutcome::CredentialsRejected
+        )
+    }
+}
+
+/// Probe each candidate computer concurrently
+///
+/// Each candidate gets a connection without credentials, opened with
+/// [`CtClient::open_with_timeout`] and closed as soon as the server version
+/// has been read. Connection attempts still running after `timeout` are
+/// cancelled and reported as [`ProbeOutcome::TimedOut`], so no probe
+/// outlives the call. Results are returned in the order of `candidates`.
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::discovery;
+/// use std::time::Duration;
+///
+/// for result in discovery::probe(&["scada01", "192.168.1.100"], Duration::from_secs(5)) {
+///     println!("{}: {:?} in {:?}", result.computer, result.outcome, result.elapsed);
+/// }
+/// ```
+pub fn probe(candidates: &[&str], timeout: Duration) -> Vec<ProbeResult> {
+    probe_with(candidates, timeout, |computer, timeout| {
+        CtClient::open_with_timeout(Some(computer), None, None, 0, timeout)
+    })
+}
+
+/// [`probe`] with the connection made by `open`, which must give up with
+/// [`CtApiError::Timeout`] once the timeout it is passed has expired
+pub(crate) fn probe_with<F>(candidates: &[&str], timeout: Duration, open: F) -> Vec<ProbeResult>
+where
+    F: Fn(&str, Duration) -> Result<CtClient> + Sync,
+{
+    thread::scope(|scope| {
+        let probes: Vec<_> = candidates
+            .iter()
+            .map(|computer| {
+                let open = &open;
+                thread::Builder::new()
+                    .name("ctapi-probe".into())
+                    .spawn_scoped(scope, move || probe_one(computer, timeout, open))
+            })
+            .collect();
+        candidates
+            .iter()
+            .zip(probes)
+            .map(|(computer, probe)| {
+                let (outcome, elapsed) = match probe {
+                    Ok(probe) => probe
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+                    Err(e) => (ProbeOutcome::Unreachable(e.into()), Duration::ZERO),
+                };
+                ProbeResult {
+                    computer: computer.to_string(),
+                    outcome,
+                    elapsed,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Outcome of probing `computer` and the time it took
+fn probe_one<F>(computer: &str, timeout: Duration, open: &F) -> (ProbeOutcome, Duration)
+where
+    F: Fn(&str, Duration) -> Result<CtClient>,
+{
+    let started = Instant::now();
+    let outcome = match open(computer, timeout) {
+        Ok(client) => ProbeOutcome::Reachable {
+            server_version: client.check_versions(false).ok().and_then(|v| v.server),
+        },
+        Err(e) if matches!(e.root(), CtApiError::Timeout) => ProbeOutcome::TimedOut,
+        Err(e) if is_credentials_error(&e) => ProbeOutcome::CredentialsRejected,
+        Err(e) => ProbeOutcome::Unreachable(e),
+    };
+    (outcome, started.elapsed())
+}
+
+/// Server names configured in the Citect.ini files of local installations
+///
+/// Reads `Citect.ini` from the `Config` directories of Citect SCADA and
+/// Plant SCADA installations under `%ProgramData%`, and from `%WINDIR%` for
+/// older versions. Candidates are taken from `[Client] Primary` and
+/// `[Client] Standby`, without duplicates, in the order found. Returns an
+/// empty list if no file is found.
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::discovery;
+/// use std::time::Duration;
+///
+/// let candidates = discovery::discover_from_config_paths();
+/// let candidates: Vec<&str> = candidates.iter().map(String::as_str).collect();
+/// let reachable = discovery::probe(&candidates, Duration::from_secs(5))
+///     .into_iter()
+///     .filter(|result| result.is_reachable());
+/// ```
+pub fn discover_from_config_paths() -> Vec<String> {
+    candidates_from_files(&config_paths())
+}
+
+/// Citect.ini files that may exist on this computer
+fn config_paths() -> Vec<PathBuf> {
+    let program_data = std::env::var_os("ProgramData")
+        .map_or_else(|| PathBuf::from(r"C:\ProgramData"), PathBuf::from);
+    let mut paths = Vec::new();
+    // <ProgramData>\<vendor>[\<product>]\Config\Citect.ini
+    for vendor in subdirectories(&program_data) {
+        let name = vendor.file_name().unwrap_or_default().to_string_lossy();
+        if !VENDOR_DIRS.iter().any(|known| name.contains(known)) {
+            continue;
+        }
+        paths.push(vendor.join("Config").join("Citect.ini"));
+        for product in subdirectories(&vendor) {
+            paths.push(product.join("Config").join("Citect.ini"));
+        }
+    }
+    if let Some(windows) = std::env::var_os("WINDIR") {
+        paths.push(PathBuf::from(windows).join("Citect.ini"));
+    }
+    paths
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Candidates from the files in `paths` that exist, without duplicates
+fn candidates_from_files(paths: &[PathBuf]) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for path in paths {
+        let Ok(bytes) = std::fs::read(path) else {
+            continue;
+        };
+        for candidate in candidates_from_ini(&String::from_utf8_lossy(&bytes)) {
+            if !candidates
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(&candidate))
+            {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// Server names in the text of a Citect.ini file
+fn candidates_from_ini(text: &str) -> Vec<String> {
+    let mut section = "";
+    let mut candidates = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            section = name.trim();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let wanted = SERVER_KEYS
+            .iter()
+            .any(|(s, k)| s.eq_ignore_ascii_case(section) && k.eq_ignore_ascii_case(key.trim()));
+        if wanted {
+            candidates.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_from_ini() {
+        let ini = "\
+[Client]
+Primary = scada01
+standby=scada02, 10.0.0.12
+; Primary=commented
+[Alarm]
+Primary=alarms01
+[client]
+Password=secret
+";
+        assert_eq!(
+            candidates_from_ini(ini),
+            ["scada01", "scada02", "10.0.0.12"]
+        );
+        assert!(candidates_from_ini("").is_empty());
+    }
+
+    #[test]
+    fn test_candidates_from_files_skips_missing_and_duplicates() {
+        let dir = std::env::temp_dir().join(format!("ctapi-discovery-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.ini");
+        let second = dir.join("second.ini");
+        std::fs::write(&first, "[Client]\nPrimary=SCADA01\n").unwrap();
+        std::fs::write(&second, "[Client]\nPrimary=scada01\nStandby=scada02\n").unwrap();
+
+        let paths = [dir.join("missing.ini"), first, second];
+        let candidates = candidates_from_files(&paths);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(candidates, ["SCADA01", "scada02"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_probe_mixed_candidates() {
+        use crate::mock::MockServer;
+
+        let results = probe_with(
+            &["scada01", "offline", "secured", "slow", "busy"],
+            Duration::from_millis(200),
+            |computer, timeout| {
+                assert_eq!(timeout, Duration::from_millis(200));
+                match computer {
+                    "scada01" => CtClient::open_mock_with(
+                        MockServer::new().with_cicode("Version(0)", "8.20.0.0"),
+                    ),
+                    "offline" => Err(std::io::Error::from_raw_os_error(1722).into()),
+                    "secured" => Err(std::io::Error::from_raw_os_error(1326).into()),
+                    "slow" => Err(CtApiError::Timeout),
+                    // ERROR_NOT_ENOUGH_MEMORY says nothing about credentials
+                    _ => Err(std::io::Error::from_raw_os_error(8).into()),
+                }
+            },
+        );
+
+        let computers: Vec<_> = results.iter().map(|r| r.computer.as_str()).collect();
+        assert_eq!(computers, ["scada01", "offline", "secured", "slow", "busy"]);
+        assert!(matches!(
+            &results[0].outcome,
+            ProbeOutcome::Reachable { server_version: Some(v) } if v == "8.20.0.0"
+        ));
+        assert!(matches!(results[1].outcome, ProbeOutcome::Unreachable(_)));
+        assert!(matches!(
+            results[2].outcome,
+            ProbeOutcome::CredentialsRejected
+        ));
+        assert!(matches!(results[3].outcome, ProbeOutcome::TimedOut));
+        assert!(matches!(results[4].outcome, ProbeOutcome::Unreachable(_)));
+        let reachable: Vec<_> = results.iter().map(ProbeResult::is_reachable).collect();
+        assert_eq!(reachable, [true, false, true, false, false]);
+    }
+}
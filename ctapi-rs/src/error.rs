@@ -1,44 +1,193 @@
 //! CtAPI error handling module
 //!
 //! Provides definition and handling of CtAPI-specific error types.
+//!
+//! Errors keep their cause reachable through [`std::error::Error::source`]:
+//! system errors wrap the [`io::Error`] reported by CtAPI, and layers that add
+//! context wrap the inner [`CtApiError`] with [`CtApiError::context`]. With the
+//! `backtrace` feature, system errors also capture a [`Backtrace`] where they
+//! are created, available from [`CtApiError::backtrace`] and printed by the
+//! alternate `Debug` format (`{:#?}`).
+//!
+//! # Error Codes
+//!
+//! Every error has a stable numeric [`code`](CtApiError::code) for consumers
+//! that cannot rely on messages, such as the C ABI and log pipelines. The
+//! code is also printed at the start of the message, as in
+//! `[E4001] Operation timeout`. Codes are never reused or renumbered:
+//!
+//! | Code | Error |
+//! |------|-------|
+//! | 1001 | [`FromBytesUntilNul`](CtApiError::FromBytesUntilNul) |
+//! | 1002 | [`InvalidCString`](CtApiError::InvalidCString) |
+//! | 1003 | [`Encoding`](CtApiError::Encoding) |
+//! | 1004 | [`TextTooLong`](CtApiError::TextTooLong) |
+//! | 1005 | [`Truncated`](CtApiError::Truncated) |
+//! | 2001 | [`ConnectionFailed`](CtApiError::ConnectionFailed) |
+//! | 2002 | [`GlobalNotInitialized`](CtApiError::GlobalNotInitialized) |
+//! | 2003 | [`GlobalAlreadyInitialized`](CtApiError::GlobalAlreadyInitialized) |
+//! | 2004 | [`VersionSkew`](CtApiError::VersionSkew) |
+//! | 2005 | [`AmbiguousOutcome`](CtApiError::AmbiguousOutcome) |
+//! | 2006 | [`SessionClosed`](CtApiError::SessionClosed) |
+//! | 2007 | [`ConnectionLost`](CtApiError::ConnectionLost) |
+//! | 2008 | [`TooManyPendingOps`](CtApiError::TooManyPendingOps) |
+//! | 2009 | [`InvalidHandle`](CtApiError::InvalidHandle) |
+//! | 3001 | [`TagNotFound`](CtApiError::TagNotFound) |
+//! | 3002 | [`InvalidParameter`](CtApiError::InvalidParameter) |
+//! | 3003 | [`CursorExpired`](CtApiError::CursorExpired) |
+//! | 3004 | [`StaleFindObject`](CtApiError::StaleFindObject) |
+//! | 3005 | [`UnexpectedCicodeResult`](CtApiError::UnexpectedCicodeResult) |
+//! | 3006 | [`UnsupportedOperation`](CtApiError::UnsupportedOperation) |
+//! | 3007 | [`BadQuality`](CtApiError::BadQuality) |
+//! | 3008 | [`WriteNotVerified`](CtApiError::WriteNotVerified) |
+//! | 3009 | [`CannotCancel`](CtApiError::CannotCancel) |
+//! | 3010 | [`WriteNotPermitted`](CtApiError::WriteNotPermitted) |
+//! | 3011 | [`InvalidPageToken`](CtApiError::InvalidPageToken) |
+//! | 3012 | [`ParseError`](CtApiError::ParseError) |
+//! | 3013 | [`InvalidTagName`](CtApiError::InvalidTagName) |
+//! | 3014 | [`DuplicateTag`](CtApiError::DuplicateTag) |
+//! | 4001 | [`Timeout`](CtApiError::Timeout) |
+//! | 5001 | [`System`](CtApiError::System) |
+//! | 10000 + n | [`Citect`](CtApiError::Citect) or [`Other`](CtApiError::Other) with Citect error code `n` |
+//!
+//! The ranges group encoding (1xxx), connection (2xxx), tag and query (3xxx),
+//! timeout (4xxx) and operating system (5xxx) errors. Citect error codes
+//! reported through `GetLastError` are translated (see
+//! [`citect_code`](crate::citect_code)) rather than kept as system errors. [`Context`](CtApiError::Context)
+//! layers report the code of the error they wrap.
 
+use crate::citect_code::CitectCode;
+use crate::quality::Quality;
+use crate::tag_path::TagNameError;
 use std::ffi::NulError;
 
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
 use std::ffi::FromBytesUntilNulError;
+use std::fmt;
 use std::io;
 use thiserror::Error;
 
+/// Offset added to Citect error codes carried by [`CtApiError::Other`]
+pub const CITECT_CODE_BASE: u32 = 10_000;
+
+/// Backtrace captured when an error was created
+///
+/// Empty, and zero-sized, without the `backtrace` feature, so that
+/// [`CtApiError::System`] has the same fields with and without it.
+///
+/// `Debug` prints `Backtrace` unless the alternate format is requested, which
+/// prints the frames, so that `{:?}` output of an error stays short.
+pub struct ErrorBacktrace {
+    #[cfg(feature = "backtrace")]
+    frames: Backtrace,
+}
+
+impl ErrorBacktrace {
+    fn capture() -> Self {
+        Self {
+            #[cfg(feature = "backtrace")]
+            frames: Backtrace::capture(),
+        }
+    }
+}
+
+impl fmt::Debug for ErrorBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "backtrace")]
+        if f.alternate() {
+            return write!(f, "{}", self.frames);
+        }
+        f.write_str("Backtrace")
+    }
+}
+
 /// CtAPI-specific error type
 #[derive(Error, Debug)]
 pub enum CtApiError {
-    /// CtAPI system call failed
-    #[error("CtAPI system call failed: {0}")]
-    System(#[from] io::Error),
+    /// CtAPI system call failed with a Win32 error
+    ///
+    /// The second field holds the backtrace captured when the error was
+    /// created, which is empty without the `backtrace` feature.
+    #[error("[E5001] CtAPI system call failed: {0}")]
+    System(#[source] io::Error, ErrorBacktrace),
 
     /// Conversion error from bytes until null character
-    #[error("Conversion error from bytes until null character: {0}")]
+    #[error("[E1001] Conversion error from bytes until null character: {0}")]
     FromBytesUntilNul(#[from] FromBytesUntilNulError),
 
     /// CString conversion failed (interior null byte)
-    #[error("Invalid C string: {0}")]
+    #[error("[E1002] Invalid C string: {0}")]
     InvalidCString(#[from] NulError),
 
     /// Tag not found
-    #[error("Tag '{tag}' not found")]
+    #[error("[E3001] Tag '{tag}' not found")]
     TagNotFound {
         /// Name of tag not found
         tag: String,
     },
 
+    /// A value was read with a quality below the requested threshold
+    #[error("[E3007] Tag '{tag}' rejected for quality: {quality}")]
+    BadQuality {
+        /// Tag name
+        tag: String,
+        /// Quality of the rejected value
+        quality: Quality,
+    },
+
+    /// A value read back after a write differs from the value written
+    #[error("[E3008] Tag '{tag}' reads {actual:?} after writing {expected:?}")]
+    WriteNotVerified {
+        /// Tag name
+        tag: String,
+        /// Value written
+        expected: String,
+        /// Value read back
+        actual: String,
+    },
+
+    /// An asynchronous operation could not be cancelled because it has
+    /// already completed
+    ///
+    /// Collect its result as usual; this is not a failure of the operation.
+    #[error("[E3009] Operation already completed and cannot be cancelled")]
+    CannotCancel,
+
+    /// A write was denied by the client's [write permit](crate::permit)
+    #[error("[E3010] Write not permitted: {reason}")]
+    WriteNotPermitted {
+        /// Why the permit denied the write
+        reason: String,
+    },
+
+    /// A [paging](crate::paging) continuation token was rejected
+    #[error("[E3011] Invalid page token: {reason}")]
+    InvalidPageToken {
+        /// Why the token was rejected
+        reason: String,
+    },
+
+    /// A tag value could not be parsed as the requested type
+    #[error("[E3012] Cannot parse {tag} = {raw:?} as {target_type}")]
+    ParseError {
+        /// Tag that was read
+        tag: String,
+        /// Value as read, before trimming
+        raw: String,
+        /// Name of the requested type
+        target_type: String,
+    },
+
     /// Connection failed
-    #[error("Connection to Citect SCADA failed: {message}")]
+    #[error("[E2001] Connection to Citect SCADA failed: {message}")]
     ConnectionFailed {
         /// Error message description
         message: String,
     },
 
     /// Invalid parameter
-    #[error("Invalid parameter: {param} = {value}")]
+    #[error("[E3002] Invalid parameter: {param} = {value}")]
     InvalidParameter {
         /// Parameter name
         param: String,
@@ -46,19 +195,164 @@ pub enum CtApiError {
         value: String,
     },
 
+    /// Text cannot be represented in the GBK encoding used by CtAPI
+    #[error("[E1003] Text cannot be encoded as GBK: {text}")]
+    Encoding {
+        /// Offending text
+        text: String,
+    },
+
+    /// Text exceeds the length Citect SCADA accepts for the field
+    #[error("[E1004] {field} is {len} bytes long, maximum is {max}")]
+    TextTooLong {
+        /// Field name
+        field: String,
+        /// Encoded length in bytes
+        len: usize,
+        /// Maximum accepted length in bytes
+        max: usize,
+    },
+
+    /// A returned string filled the read buffer and was probably cut off
+    #[error("[E1005] Result truncated, retry with a buffer of at least {needed} bytes")]
+    Truncated {
+        /// Buffer size in bytes that the result needs at least
+        needed: usize,
+    },
+
+    /// Server-side find cursor expired during a non-resumable search
+    #[error("[E3003] Find cursor expired after {position} records; restart the query")]
+    CursorExpired {
+        /// Number of records returned before the cursor expired
+        position: usize,
+    },
+
+    /// A found object was used after its search moved past it
+    #[error("[E3004] Find object used after its search moved to another record")]
+    StaleFindObject,
+
+    /// The process-wide client of [`global`](crate::global) is not installed
+    #[error("[E2002] Global client not initialized; call ctapi_rs::global::init first")]
+    GlobalNotInitialized,
+
+    /// [`global::init`](crate::global::init) was called while a client is installed
+    #[error("[E2003] Global client already initialized")]
+    GlobalAlreadyInitialized,
+
+    /// The major versions of the local CtAPI.dll and the server differ
+    #[error("[E2004] CtAPI version skew: client {client}, server {server}")]
+    VersionSkew {
+        /// Local CtAPI.dll version
+        client: String,
+        /// Server version
+        server: String,
+    },
+
+    /// The connection failed mid-call and the operation is not safe to repeat
+    ///
+    /// The server may or may not have carried out the call. See
+    /// [`idempotency`](crate::idempotency).
+    #[error("[E2005] Outcome unknown after connection loss, not retried: {source}")]
+    AmbiguousOutcome {
+        /// Failure of the call
+        #[source]
+        source: Box<CtApiError>,
+    },
+
+    /// A `TokioCtSession` was shut down before it ran the command
+    #[error("[E2006] Session closed before the command was run")]
+    SessionClosed,
+
+    /// The client reconnected since the handle was created, so the handle
+    /// is stale; see [`CtList::resubscribe`](crate::CtList::resubscribe)
+    #[error("[E2007] Connection re-established (generation {generation}); resubscribe")]
+    ConnectionLost {
+        /// [Reconnect generation](crate::CtClient::reconnect_generation) of
+        /// the client
+        generation: u64,
+    },
+
+    /// The client's [pending-operation
+    /// limit](crate::CtClient::set_pending_limit) is reached
+    #[error("[E2008] Too many pending overlapped operations (limit {limit})")]
+    TooManyPendingOps {
+        /// Maximum number of outstanding operations
+        limit: usize,
+    },
+
+    /// The client has no connection handle, or it was closed with
+    /// [`close_ex`](crate::CtClient::close_ex)
+    #[error("[E2009] Client handle is null or closed")]
+    InvalidHandle,
+
+    /// A Cicode function returned a value that could not be interpreted
+    #[error("[E3005] Unexpected result from Cicode {function}: {result:?}")]
+    UnexpectedCicodeResult {
+        /// Cicode function that was called
+        function: String,
+        /// Raw result
+        result: String,
+    },
+
+    /// A tag name Citect SCADA would reject, caught before calling CtAPI;
+    /// see [`validate_tag_name`](crate::tag_path::validate_tag_name)
+    #[error("[E3013] Invalid tag name {name:?}: {reason}")]
+    InvalidTagName {
+        /// Name as given
+        name: String,
+        /// What is wrong with it
+        #[source]
+        reason: TagNameError,
+    },
+
+    /// A tag added to a [`CtList`](crate::CtList) it is already on
+    #[error("[E3014] Tag '{tag}' is already on the list")]
+    DuplicateTag {
+        /// Tag name as given
+        tag: String,
+    },
+
     /// Timeout error
-    #[error("Operation timeout")]
+    #[error("[E4001] Operation timeout")]
     Timeout,
 
     /// Unsupported operation
-    #[error("Unsupported operation: {operation}")]
+    #[error("[E3006] Unsupported operation: {operation}")]
     UnsupportedOperation {
         /// Name of unsupported operation
         operation: String,
     },
 
+    /// Error annotated with the operation that failed
+    #[error("[E{}] {context}", source.code())]
+    Context {
+        /// Description of the failed operation
+        context: String,
+        /// Underlying error
+        #[source]
+        source: Box<CtApiError>,
+    },
+
+    /// CtAPI call failed with a Citect error code, see [`CitectCode`](crate::CitectCode)
+    #[error(
+        "[E{}] Citect error {code} ({name}): {description}",
+        CITECT_CODE_BASE.saturating_add(*code)
+    )]
+    Citect {
+        /// Citect error code, without the `ERROR_USER_DEFINED_BASE` offset
+        code: u32,
+        /// Stable name of the code, `unknown` if it is not documented
+        name: &'static str,
+        /// Description of the code
+        description: &'static str,
+    },
+
     /// Other CtAPI error
-    #[error("CtAPI error code: {code}{}", if message.is_empty() { String::new() } else { format!(", message: {}", message) })]
+    #[error(
+        "[E{}] CtAPI error code: {code}{}",
+        CITECT_CODE_BASE.saturating_add(*code),
+        if message.is_empty() { String::new() } else { format!(", message: {}", message) }
+    )]
     Other {
         /// Error code
         code: u32,
@@ -86,17 +380,163 @@ impl CtApiError {
         }
     }
 
+    /// Stable numeric code of this error
+    ///
+    /// See the [module documentation](crate::error#error-codes) for the
+    /// registry.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtApiError;
+    ///
+    /// assert_eq!(CtApiError::Timeout.code(), 4001);
+    /// assert_eq!(CtApiError::from_error_code(123).code(), 10_123);
+    /// assert_eq!(CtApiError::Timeout.context("reading Temperature").code(), 4001);
+    /// ```
+    pub fn code(&self) -> u32 {
+        match self {
+            CtApiError::FromBytesUntilNul(_) => 1001,
+            CtApiError::InvalidCString(_) => 1002,
+            CtApiError::Encoding { .. } => 1003,
+            CtApiError::TextTooLong { .. } => 1004,
+            CtApiError::Truncated { .. } => 1005,
+            CtApiError::ConnectionFailed { .. } => 2001,
+            CtApiError::GlobalNotInitialized => 2002,
+            CtApiError::GlobalAlreadyInitialized => 2003,
+            CtApiError::VersionSkew { .. } => 2004,
+            CtApiError::AmbiguousOutcome { .. } => 2005,
+            CtApiError::SessionClosed => 2006,
+            CtApiError::ConnectionLost { .. } => 2007,
+            CtApiError::TooManyPendingOps { .. } => 2008,
+            CtApiError::InvalidHandle => 2009,
+            CtApiError::TagNotFound { .. } => 3001,
+            CtApiError::InvalidParameter { .. } => 3002,
+            CtApiError::CursorExpired { .. } => 3003,
+            CtApiError::StaleFindObject => 3004,
+            CtApiError::UnexpectedCicodeResult { .. } => 3005,
+            CtApiError::UnsupportedOperation { .. } => 3006,
+            CtApiError::BadQuality { .. } => 3007,
+            CtApiError::WriteNotVerified { .. } => 3008,
+            CtApiError::CannotCancel => 3009,
+            CtApiError::WriteNotPermitted { .. } => 3010,
+            CtApiError::InvalidPageToken { .. } => 3011,
+            CtApiError::ParseError { .. } => 3012,
+            CtApiError::InvalidTagName { .. } => 3013,
+            CtApiError::DuplicateTag { .. } => 3014,
+            CtApiError::Timeout => 4001,
+            CtApiError::System(..) => 5001,
+            CtApiError::Citect { code, .. } | CtApiError::Other { code, .. } => {
+                CITECT_CODE_BASE.saturating_add(*code)
+            }
+            CtApiError::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Code and message as a JSON object, for structured logs
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtApiError;
+    ///
+    /// let json = CtApiError::Timeout.to_json();
+    /// assert_eq!(json.to_string(), r#"{"code":4001,"message":"[E4001] Operation timeout"}"#);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+        object.insert("code".to_string(), self.code().into());
+        object.insert("message".to_string(), self.to_string().into());
+        serde_json::Value::Object(object)
+    }
+
     /// Check if this is a connection-related error
     pub fn is_connection_error(&self) -> bool {
         matches!(
-            self,
-            CtApiError::ConnectionFailed { .. } | CtApiError::Timeout
+            self.root(),
+            CtApiError::ConnectionFailed { .. }
+                | CtApiError::Timeout
+                | CtApiError::AmbiguousOutcome { .. }
+                | CtApiError::ConnectionLost { .. }
         )
     }
 
     /// Check if this is a tag-related error
     pub fn is_tag_error(&self) -> bool {
-        matches!(self, CtApiError::TagNotFound { .. })
+        matches!(
+            self.root(),
+            CtApiError::TagNotFound { .. }
+                | CtApiError::InvalidTagName { .. }
+                | CtApiError::DuplicateTag { .. }
+        )
+    }
+
+    /// Wrap this error with a description of the operation that failed
+    ///
+    /// The original error stays available through `source()` and
+    /// [`root`](Self::root).
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtApiError;
+    /// use std::error::Error;
+    ///
+    /// let error = CtApiError::Timeout.context("reading Temperature");
+    /// assert_eq!(error.to_string(), "[E4001] reading Temperature");
+    /// assert_eq!(error.source().unwrap().to_string(), "[E4001] Operation timeout");
+    /// ```
+    pub fn context(self, context: impl Into<String>) -> Self {
+        CtApiError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Innermost error, skipping [`Context`](Self::Context) layers
+    pub fn root(&self) -> &CtApiError {
+        let mut error = self;
+        while let CtApiError::Context { source, .. } = error {
+            error = source;
+        }
+        error
+    }
+
+    /// Backtrace captured where the innermost error was created
+    ///
+    /// Only system errors capture a backtrace. Capturing follows the
+    /// standard library rules, so frames are only recorded when
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` enables them.
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self.root() {
+            CtApiError::System(_, backtrace) => Some(&backtrace.frames),
+            _ => None,
+        }
+    }
+}
+
+/// The [`code`](CtApiError::code) and message, like
+/// [`to_json`](CtApiError::to_json)
+#[cfg(feature = "serde")]
+impl serde::Serialize for CtApiError {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut error = serializer.serialize_struct("CtApiError", 2)?;
+        error.serialize_field("code", &self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+impl From<io::Error> for CtApiError {
+    fn from(error: io::Error) -> Self {
+        if let Some(code) = error.raw_os_error().and_then(CitectCode::from_os_error) {
+            return code.into();
+        }
+        CtApiError::System(error, ErrorBacktrace::capture())
     }
 }
 
@@ -113,19 +553,215 @@ mod tests {
             tag: "test_tag".to_string(),
         };
         assert!(error.is_tag_error());
-        assert_eq!(error.to_string(), "Tag 'test_tag' not found");
+        assert_eq!(error.to_string(), "[E3001] Tag 'test_tag' not found");
     }
 
     #[test]
     fn test_error_from_io() {
         let io_error = io::Error::new(io::ErrorKind::NotFound, "file not found");
         let ct_error: CtApiError = io_error.into();
-        assert!(matches!(ct_error, CtApiError::System(_)));
+        assert!(matches!(ct_error, CtApiError::System(..)));
     }
 
     #[test]
     fn test_error_code() {
         let error = CtApiError::from_error_code(123);
-        assert_eq!(error.to_string(), "CtAPI error code: 123");
+        assert_eq!(error.to_string(), "[E10123] CtAPI error code: 123");
+    }
+
+    #[test]
+    fn test_source_chain_through_context_layers() {
+        use std::error::Error;
+
+        let io_error = io::Error::new(io::ErrorKind::TimedOut, "device timed out");
+        let error = CtApiError::from(io_error)
+            .context("reading Temperature")
+            .context("polling boiler");
+        assert_eq!(error.to_string(), "[E5001] polling boiler");
+
+        let mut chain = Vec::new();
+        let mut current: Option<&dyn Error> = Some(&error);
+        while let Some(e) = current {
+            chain.push(e.to_string());
+            current = e.source();
+        }
+        assert_eq!(
+            chain,
+            [
+                "[E5001] polling boiler",
+                "[E5001] reading Temperature",
+                "[E5001] CtAPI system call failed: device timed out",
+                "device timed out",
+            ]
+        );
+        assert!(matches!(error.root(), CtApiError::System(..)));
+    }
+
+    /// One error of every variant
+    fn samples() -> Vec<CtApiError> {
+        let text = |s: &str| s.to_string();
+        vec![
+            io::Error::other("failed").into(),
+            std::ffi::CStr::from_bytes_until_nul(b"x")
+                .unwrap_err()
+                .into(),
+            std::ffi::CString::new("a\0b").unwrap_err().into(),
+            CtApiError::TagNotFound { tag: text("T") },
+            CtApiError::BadQuality {
+                tag: text("T"),
+                quality: Quality::default(),
+            },
+            CtApiError::WriteNotVerified {
+                tag: text("T"),
+                expected: text("1"),
+                actual: text("0"),
+            },
+            CtApiError::ConnectionFailed {
+                message: text("down"),
+            },
+            CtApiError::InvalidParameter {
+                param: text("p"),
+                value: text("v"),
+            },
+            CtApiError::Encoding {
+                text: text("\u{1F600}"),
+            },
+            CtApiError::TextTooLong {
+                field: text("f"),
+                len: 300,
+                max: 255,
+            },
+            CtApiError::Truncated { needed: 257 },
+            CtApiError::CursorExpired { position: 1 },
+            CtApiError::StaleFindObject,
+            CtApiError::CannotCancel,
+            CtApiError::WriteNotPermitted {
+                reason: "outside the write window".into(),
+            },
+            CtApiError::InvalidPageToken {
+                reason: "signature mismatch".into(),
+            },
+            CtApiError::ParseError {
+                tag: text("Level"),
+                raw: text("#COM"),
+                target_type: text("f64"),
+            },
+            CtApiError::InvalidTagName {
+                name: text("Flow rate"),
+                reason: TagNameError::Empty,
+            },
+            CtApiError::DuplicateTag {
+                tag: text("Temperature"),
+            },
+            CtApiError::GlobalNotInitialized,
+            CtApiError::GlobalAlreadyInitialized,
+            CtApiError::VersionSkew {
+                client: text("7.40"),
+                server: text("8.20"),
+            },
+            CtApiError::AmbiguousOutcome {
+                source: Box::new(CtApiError::Timeout),
+            },
+            CtApiError::SessionClosed,
+            CtApiError::ConnectionLost { generation: 1 },
+            CtApiError::TooManyPendingOps { limit: 64 },
+            CtApiError::InvalidHandle,
+            CtApiError::UnexpectedCicodeResult {
+                function: text("WinNewAt"),
+                result: text("-1"),
+            },
+            CtApiError::Timeout,
+            CtApiError::UnsupportedOperation {
+                operation: text("op"),
+            },
+            CtApiError::Timeout.context("wrapped"),
+            CtApiError::from_error_code(0),
+            CitectCode::new(260).into(),
+        ]
+    }
+
+    #[test]
+    fn test_error_codes_are_unique_and_displayed() {
+        // Exhaustive on purpose: a new variant does not compile until it is
+        // added to `samples` and given a code
+        fn sampled(error: &CtApiError) {
+            match error {
+                CtApiError::System(..)
+                | CtApiError::FromBytesUntilNul(_)
+                | CtApiError::InvalidCString(_)
+                | CtApiError::TagNotFound { .. }
+                | CtApiError::BadQuality { .. }
+                | CtApiError::WriteNotVerified { .. }
+                | CtApiError::ConnectionFailed { .. }
+                | CtApiError::InvalidParameter { .. }
+                | CtApiError::Encoding { .. }
+                | CtApiError::TextTooLong { .. }
+                | CtApiError::Truncated { .. }
+                | CtApiError::CursorExpired { .. }
+                | CtApiError::StaleFindObject
+                | CtApiError::CannotCancel
+                | CtApiError::WriteNotPermitted { .. }
+                | CtApiError::InvalidPageToken { .. }
+                | CtApiError::ParseError { .. }
+                | CtApiError::InvalidTagName { .. }
+                | CtApiError::DuplicateTag { .. }
+                | CtApiError::GlobalNotInitialized
+                | CtApiError::GlobalAlreadyInitialized
+                | CtApiError::VersionSkew { .. }
+                | CtApiError::AmbiguousOutcome { .. }
+                | CtApiError::SessionClosed
+                | CtApiError::ConnectionLost { .. }
+                | CtApiError::TooManyPendingOps { .. }
+                | CtApiError::InvalidHandle
+                | CtApiError::UnexpectedCicodeResult { .. }
+                | CtApiError::Timeout
+                | CtApiError::UnsupportedOperation { .. }
+                | CtApiError::Context { .. }
+                | CtApiError::Citect { .. }
+                | CtApiError::Other { .. } => {}
+            }
+        }
+
+        let samples = samples();
+        let mut codes = std::collections::HashSet::new();
+        for error in &samples {
+            sampled(error);
+            let code = error.code();
+            assert!(
+                error.to_string().starts_with(&format!("[E{code}] ")),
+                "{error}"
+            );
+            if !matches!(error, CtApiError::Context { .. }) {
+                assert!(codes.insert(code), "code {code} assigned twice");
+            }
+            let citect = matches!(error, CtApiError::Citect { .. } | CtApiError::Other { .. });
+            assert!(code < CITECT_CODE_BASE || citect);
+        }
+        assert_eq!(codes.len(), samples.len() - 1);
+
+        #[cfg(all(feature = "serde", feature = "json"))]
+        for error in &samples {
+            let json = serde_json::to_value(error).unwrap();
+            assert_eq!(json, error.to_json());
+        }
+    }
+
+    #[test]
+    fn test_classification_sees_through_context() {
+        assert!(CtApiError::Timeout.context("open").is_connection_error());
+        let tag = CtApiError::TagNotFound {
+            tag: "T".to_string(),
+        };
+        assert!(tag.context("read").is_tag_error());
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn test_backtrace_captured_at_origin() {
+        let error = CtApiError::from(io::Error::other("failed")).context("outer");
+        assert!(error.backtrace().is_some());
+        assert!(CtApiError::Timeout.backtrace().is_none());
+        assert!(!format!("{error:?}").contains('\n'));
+        assert!(!error.to_string().contains('\n'));
     }
 }
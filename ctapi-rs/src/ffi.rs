@@ -0,0 +1,16 @@
+//! CtAPI entry points used by the safe wrappers
+//!
+//! Everything from `ctapi-sys`. With the `mock` feature the functions the
+//! crate calls are replaced by the dispatchers in [`crate::mock`], which
+//! serve mock handles in process and forward all other calls to CtAPI.dll.
+
+pub(crate) use ctapi_sys::*;
+
+#[cfg(feature = "mock")]
+pub(crate) use crate::mock::{
+    ctCancelIO, ctCicode, ctClientDestroy, ctClose, ctCloseEx, ctFindClose, ctFindFirst,
+    ctFindFirstEx, ctFindNext, ctFindScroll, ctGetOverlappedResult, ctGetProperty, ctListAdd,
+    ctListAddEx, ctListData, ctListDelete, ctListEvent, ctListFree, ctListItem, ctListNew,
+    ctListRead, ctListWrite, ctOpenEx, ctTagGetProperty, ctTagRead, ctTagReadEx, ctTagWrite,
+    ctTagWriteEx,
+};
@@ -0,0 +1,75 @@
+//! CtAPI timestamps
+//!
+//! CtAPI reports the timestamps of a tag value as Windows FILETIME values:
+//! 100 ns ticks since 1601-01-01 UTC, with `0` for a timestamp the server did
+//! not fill in. [`to_utc`] converts them for
+//! [`TagReading`](crate::TagReading) and [`from_utc`] goes the other way.
+//!
+//! ```
+//! use chrono::{TimeZone, Utc};
+//! use ctapi_rs::filetime;
+//!
+//! let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+//! assert_eq!(filetime::from_utc(time), 133_537_680_000_000_000);
+//! assert_eq!(filetime::to_utc(133_537_680_000_000_000), Some(time));
+//! assert_eq!(filetime::to_utc(0), None);
+//! ```
+
+use chrono::{DateTime, Utc};
+
+/// Difference between the FILETIME epoch (1601) and the Unix epoch in 100 ns ticks
+pub const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// FILETIME ticks per second
+const TICKS_PER_SECOND: i128 = 10_000_000;
+
+/// Time of a FILETIME value, `None` for `0` (not set)
+pub fn to_utc(ticks: u64) -> Option<DateTime<Utc>> {
+    if ticks == 0 {
+        return None;
+    }
+    let since_unix = i128::from(ticks) - i128::from(FILETIME_UNIX_EPOCH);
+    let secs = i64::try_from(since_unix.div_euclid(TICKS_PER_SECOND)).ok()?;
+    let nanos = (since_unix.rem_euclid(TICKS_PER_SECOND) * 100) as u32;
+    DateTime::from_timestamp(secs, nanos)
+}
+
+/// FILETIME value of `time`, truncated to 100 ns and clamped to the FILETIME range
+pub fn from_utc(time: DateTime<Utc>) -> u64 {
+    let secs = i128::from(time.timestamp());
+    let sub_ticks = i128::from(time.timestamp_subsec_nanos() / 100);
+    let ticks = secs * TICKS_PER_SECOND + sub_ticks + i128::from(FILETIME_UNIX_EPOCH);
+    ticks.clamp(0, i128::from(u64::MAX)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_known_timestamps() {
+        let unix_epoch = Utc.timestamp_opt(0, 0).unwrap();
+        assert_eq!(to_utc(FILETIME_UNIX_EPOCH), Some(unix_epoch));
+
+        // 2024-03-01 12:00:00.1234567 UTC
+        let ticks = 133_537_680_001_234_567;
+        let time = to_utc(ticks).unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-03-01T12:00:00.123456700+00:00");
+        assert_eq!(from_utc(time), ticks);
+
+        // The FILETIME epoch itself is a valid time before the Unix epoch
+        let epoch = Utc.with_ymd_and_hms(1601, 1, 1, 0, 0, 0).unwrap();
+        let first_tick = to_utc(1).unwrap();
+        assert_eq!(first_tick - epoch, chrono::Duration::nanoseconds(100));
+        assert_eq!(from_utc(first_tick), 1);
+    }
+
+    #[test]
+    fn test_unset_and_out_of_range() {
+        assert_eq!(to_utc(0), None);
+        assert!(to_utc(u64::MAX).is_some());
+        let before_epoch = Utc.with_ymd_and_hms(1600, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(from_utc(before_epoch), 0);
+    }
+}
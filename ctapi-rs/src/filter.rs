@@ -0,0 +1,233 @@
+//! Find filter construction
+//!
+//! Bulk lookups need filters such as `TAG=A OR TAG=B OR ...` for hundreds of
+//! names, but CtAPI truncates or rejects filters longer than a fixed limit.
+//! [`chunked_in`] splits such a filter into several that each fit, and
+//! [`CtClient::find_in`](crate::CtClient::find_in) runs one search per chunk
+//! and merges the results.
+
+use crate::cicode::quote;
+use crate::error::{CtApiError, Result};
+use crate::query::Record;
+use crate::util::encode_to_gbk_strict;
+use std::collections::HashSet;
+
+/// Default maximum filter length in GBK bytes
+///
+/// Override per client with
+/// [`CtClient::set_max_filter_len`](crate::CtClient::set_max_filter_len).
+pub const MAX_FILTER_LEN: usize = 256;
+
+/// Separator between the criteria of an IN-style filter
+const OR: &str = " OR ";
+
+/// Format a value for use in a filter criterion
+///
+/// Values containing whitespace, `=`, `"` or `^` are quoted with the Cicode
+/// escape character; other values are used as is. Wildcards (`*`, `?`) keep
+/// their meaning and cannot be escaped.
+///
+/// # Errors
+/// * [`CtApiError::InvalidParameter`] - Value contains a null character
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::filter::escape_value;
+///
+/// assert_eq!(escape_value("Pump1")?, "Pump1");
+/// assert_eq!(escape_value("Pump 1")?, r#""Pump 1""#);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn escape_value(value: &str) -> Result<String> {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '=' | '"' | '^'));
+    if needs_quotes {
+        quote(value)
+    } else if value.contains('\0') {
+        Err(CtApiError::InvalidParameter {
+            param: "value".to_string(),
+            value: value.to_string(),
+        })
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Build `field=v1 OR field=v2 ...` filters, each at most `max_len` GBK bytes
+///
+/// Duplicate values are dropped, keeping the first occurrence, and the
+/// remaining values keep their order across the chunks. No values yield no
+/// filters.
+///
+/// # Errors
+/// * [`CtApiError::TextTooLong`] - A single criterion is longer than `max_len`
+/// * [`CtApiError::Encoding`] - A value cannot be represented in GBK
+/// * [`CtApiError::InvalidParameter`] - A value contains a null character
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::filter::chunked_in;
+///
+/// let filters = chunked_in("TAG", ["A", "B", "C"], 14)?;
+/// assert_eq!(filters, ["TAG=A OR TAG=B", "TAG=C"]);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn chunked_in<I, S>(field: &str, values: I, max_len: usize) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut seen = HashSet::new();
+    let mut filters = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for value in values {
+        let value = value.as_ref();
+        if !seen.insert(value.to_string()) {
+            continue;
+        }
+        let term = format!("{field}={}", escape_value(value)?);
+        let term_len = encode_to_gbk_strict(&term)?.len();
+        if term_len > max_len {
+            return Err(CtApiError::TextTooLong {
+                field: format!("filter criterion {term}"),
+                len: term_len,
+                max: max_len,
+            });
+        }
+
+        if current.is_empty() {
+            current = term;
+            current_len = term_len;
+        } else if current_len + OR.len() + term_len <= max_len {
+            current.push_str(OR);
+            current.push_str(&term);
+            current_len += OR.len() + term_len;
+        } else {
+            filters.push(std::mem::replace(&mut current, term));
+            current_len = term_len;
+        }
+    }
+    if !current.is_empty() {
+        filters.push(current);
+    }
+    Ok(filters)
+}
+
+/// Run `find` once per filter and merge the results
+///
+/// Records are de-duplicated by the value of `key_field`, keeping the first;
+/// records without that field are always kept.
+pub(crate) fn merge_chunks<F>(
+    filters: &[String],
+    key_field: &str,
+    mut find: F,
+) -> Result<Vec<Record>>
+where
+    F: FnMut(&str) -> Result<Vec<Record>>,
+{
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    for filter in filters {
+        for record in find(filter)? {
+            let is_new = match record.get(key_field) {
+                Some(key) => seen.insert(key.to_string()),
+                None => true,
+            };
+            if is_new {
+                merged.push(record);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_metacharacters() {
+        assert_eq!(escape_value("Pump_1").unwrap(), "Pump_1");
+        assert_eq!(escape_value("Pump*").unwrap(), "Pump*");
+        assert_eq!(escape_value("A B").unwrap(), r#""A B""#);
+        assert_eq!(escape_value("A=B").unwrap(), r#""A=B""#);
+        assert_eq!(escape_value(r#"say "hi""#).unwrap(), r#""say ^"hi^"""#);
+        assert_eq!(escape_value("x^y").unwrap(), r#""x^^y""#);
+        assert_eq!(escape_value("").unwrap(), r#""""#);
+        assert!(escape_value("a\0b").is_err());
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        // "TAG=A OR TAG=B" is exactly 14 bytes
+        assert_eq!(
+            chunked_in("TAG", ["A", "B"], 14).unwrap(),
+            ["TAG=A OR TAG=B"]
+        );
+        assert_eq!(
+            chunked_in("TAG", ["A", "B"], 13).unwrap(),
+            ["TAG=A", "TAG=B"]
+        );
+        assert_eq!(chunked_in("TAG", ["A"], 5).unwrap(), ["TAG=A"]);
+        assert!(chunked_in("TAG", Vec::<&str>::new(), 5).unwrap().is_empty());
+
+        let values: Vec<String> = (0..100).map(|i| format!("Tag{i:03}")).collect();
+        let filters = chunked_in("TAG", &values, MAX_FILTER_LEN).unwrap();
+        assert!(filters.len() > 1);
+        assert!(filters.iter().all(|f| f.len() <= MAX_FILTER_LEN));
+        let terms: Vec<&str> = filters.iter().flat_map(|f| f.split(OR)).collect();
+        let expected: Vec<String> = values.iter().map(|v| format!("TAG={v}")).collect();
+        assert_eq!(terms, expected);
+    }
+
+    #[test]
+    fn test_chunk_length_counts_escaping_and_gbk() {
+        // Quoting adds 2 bytes and each Chinese character is 2 GBK bytes
+        let filters = chunked_in("TAG", ["A B", "泵"], 11).unwrap();
+        assert_eq!(filters, [r#"TAG="A B""#, "TAG=泵"]);
+        let filters = chunked_in("TAG", ["A B", "泵"], 19).unwrap();
+        assert_eq!(filters, [r#"TAG="A B" OR TAG=泵"#]);
+    }
+
+    #[test]
+    fn test_duplicates_and_oversized_terms() {
+        assert_eq!(
+            chunked_in("TAG", ["A", "A", "B"], 100).unwrap(),
+            ["TAG=A OR TAG=B"]
+        );
+        let error = chunked_in("TAG", ["A", "LongName"], 8).unwrap_err();
+        assert!(matches!(
+            error,
+            CtApiError::TextTooLong {
+                len: 12,
+                max: 8,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_merge_deduplicates_by_key() {
+        let record = |tag: &str| Record::new(vec![("TAG".to_string(), tag.to_string())]);
+        let filters = vec!["f1".to_string(), "f2".to_string()];
+        let mut calls = Vec::new();
+        let merged = merge_chunks(&filters, "tag", |filter| {
+            calls.push(filter.to_string());
+            Ok(match filter {
+                "f1" => vec![record("A"), record("B")],
+                _ => vec![record("B"), record("C"), Record::default()],
+            })
+        })
+        .unwrap();
+        assert_eq!(calls, ["f1", "f2"]);
+        let tags: Vec<_> = merged.iter().map(|r| r.get("TAG")).collect();
+        assert_eq!(tags, [Some("A"), Some("B"), Some("C"), None]);
+
+        let failed = merge_chunks(&filters, "TAG", |_| Err(CtApiError::Timeout));
+        assert!(matches!(failed, Err(CtApiError::Timeout)));
+    }
+}
@@ -1,9 +1,87 @@
 //! Object search related implementation
-use crate::error::Result;
-use ctapi_sys::*;
+use crate::call_log::CallLog;
+use crate::error::{CtApiError, Result};
+use crate::ffi::*;
+use crate::intern::StringInterner;
+use crate::io_stats::{IoCounters, IoKind};
+use crate::property::{DbBuffer, DbType, PropertyValue};
+use crate::query::{Record, materialize, materialize_with};
 use encoding_rs::*;
-use std::ffi::{CString, c_void};
+use std::cell::{Cell, OnceCell};
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::marker::PhantomData;
 use std::os::windows::io::RawHandle;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Default number of records [`FindRecords`] reads ahead
+pub const DEFAULT_FIND_PREFETCH: usize = 16;
+
+/// Win32 `ERROR_INVALID_HANDLE`, reported by `ctFindNext` once the server-side cursor has expired
+const ERROR_INVALID_HANDLE: i32 = 6;
+
+/// Win32 errors reported by `ctFindFirst` and `ctFindScroll` when there is no record
+const NO_RECORD_ERRORS: &[i32] = &[
+    0,   // no error recorded
+    18,  // ERROR_NO_MORE_FILES
+    259, // ERROR_NO_MORE_ITEMS
+];
+
+/// Check whether an OS error code means the find cursor is no longer valid
+fn is_cursor_expired(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(ERROR_INVALID_HANDLE)
+}
+
+/// Check whether an OS error code means there was no record to return
+fn is_no_record(error: &std::io::Error) -> bool {
+    error
+        .raw_os_error()
+        .is_none_or(|code| NO_RECORD_ERRORS.contains(&code))
+}
+
+/// Interpret the return value of `ctFindScroll`
+///
+/// `ctFindScroll` returns the new 1-based record number, or `0` on failure
+/// with the reason in `GetLastError`. Scrolling outside the result set is not
+/// an error but yields `Ok(None)`; `last_error` is only consulted on failure.
+fn interpret_scroll(
+    record: u32,
+    position: usize,
+    last_error: impl FnOnce() -> std::io::Error,
+) -> Result<Option<u32>> {
+    if record != 0 {
+        return Ok(Some(record));
+    }
+    let error = last_error();
+    if is_cursor_expired(&error) {
+        Err(CtApiError::CursorExpired { position })
+    } else if is_no_record(&error) {
+        Ok(None)
+    } else {
+        Err(error.into())
+    }
+}
+
+/// Name and type of a column of a find result, see [`CtFind::schema`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    /// Field name
+    pub name: String,
+    /// Field type from `object.fields(n).type`
+    pub db_type: DbType,
+    /// Size of the field in bytes from `object.fields(n).actualsize`
+    pub actual_size: usize,
+}
+
+/// Record reached by [`CtFind::scroll`]
+#[derive(Debug)]
+pub struct ScrollOutcome<'a> {
+    /// 1-based position of the record in the result set
+    pub position: u32,
+    /// The record itself
+    pub object: FindObject<'a>,
+}
 
 /// Wrapper struct containing handle returned by [`CtClient::find_first`] function
 ///
@@ -15,6 +93,24 @@ use std::os::windows::io::RawHandle;
 ///
 /// Note: `CtFind` does not implement `Send` or `Sync` by default due to the
 /// interior mutability in `Iterator::next()` and the FFI handle management.
+///
+/// # Cursor Expiry
+///
+/// The server discards idle find cursors, so a search that pauses for a long
+/// time between records can fail part way through. By default this ends the
+/// iteration and records [`CtApiError::CursorExpired`], which can be retrieved
+/// with [`take_error`](Self::take_error). A search made [`resumable`](Self::resumable)
+/// instead re-executes the query and scrolls back to where it left off.
+///
+/// # Object Lifetime
+///
+/// Each [`FindObject`] is only valid until the search moves to another
+/// record or is closed. Objects from [`next_object`](Self::next_object)
+/// borrow the search, so the compiler enforces this. Objects from the
+/// iterator, [`try_next`](Self::try_next) and [`scroll`](Self::scroll) are
+/// only tied to the client; holding one past the next move is a
+/// use-after-free in CtAPI that [`strict`](Self::strict) turns into an error.
+/// These will borrow the search as well in the next major release.
 #[derive(Debug)]
 pub struct CtFind<'a> {
     client: &'a super::CtClient,
@@ -23,6 +119,14 @@ pub struct CtFind<'a> {
     filter: CString,
     cluster: Option<CString>,
     is_end: bool,
+    resumable: bool,
+    strict: bool,
+    /// Number of cursor calls made, used to detect stale objects
+    moves: Rc<Cell<u64>>,
+    position: usize,
+    error: Option<CtApiError>,
+    /// Columns read by [`schema`](Self::schema)
+    schema: OnceCell<Vec<ColumnInfo>>,
 }
 
 impl<'a> CtFind<'a> {
@@ -39,60 +143,461 @@ impl<'a> CtFind<'a> {
             filter,
             cluster,
             is_end: false,
+            resumable: false,
+            strict: false,
+            moves: Rc::default(),
+            position: 0,
+            error: None,
+            schema: OnceCell::new(),
         }
     }
-}
 
-impl Iterator for CtFind<'_> {
-    type Item = FindObject;
+    /// Re-execute the query if the server-side cursor expires mid-iteration
+    ///
+    /// Only use this for tables whose record order is stable between queries,
+    /// otherwise records may be skipped or repeated after a resume.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// for object in client.find_first("Tag", "CLUSTER=Cluster1", None).resumable() {
+    ///     println!("{}", object.get_property("TAG")?);
+    /// }
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn resumable(mut self) -> Self {
+        self.resumable = true;
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Reject reads through objects the search has moved past
+    ///
+    /// CtAPI supersedes the object handle of a record when the search
+    /// advances. By default reading a stale object is passed to CtAPI, which
+    /// may fail or return another record's data. A strict search instead
+    /// fails such reads with [`CtApiError::StaleFindObject`].
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CtApiError, CtClient};
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// let mut find = client.find_first("Tag", "", None).strict();
+    /// let first = find.next().unwrap();
+    /// let _second = find.next().unwrap();
+    /// assert!(matches!(first.get_property("TAG"), Err(CtApiError::StaleFindObject)));
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Stream the remaining records, reading each object as it is found
+    ///
+    /// Unlike [`into_records`](Self::into_records) this does not hold the
+    /// whole result in memory, and unlike iterating the search directly it
+    /// never leaves more than one object handle open.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// for record in client.find_first("Tag", "", None).records().with_prefetch(4) {
+    ///     let record = record?;
+    ///     println!("{:?}", record.get("TAG"));
+    /// }
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn records(self) -> FindRecords<'a> {
+        FindRecords {
+            find: self,
+            interner: StringInterner::default(),
+            prefetch: DEFAULT_FIND_PREFETCH,
+            pending: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// Name, type and size of every column of the result
+    ///
+    /// The columns are read from the first record of a separate execution
+    /// of the query, so the position of this search is not affected, and
+    /// cached for later calls. An empty result has no columns.
+    ///
+    /// # Errors
+    /// * [`CtApiError::System`] - System call failed
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// let find = client.find_first("Tag", "", None);
+    /// for column in find.schema()? {
+    ///     println!("{} {} {:?}", column.name, column.db_type, column.db_type.column_kind());
+    /// }
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn schema(&self) -> Result<Vec<ColumnInfo>> {
+        if let Some(schema) = self.schema.get() {
+            return Ok(schema.clone());
+        }
+        let mut probe = CtFind::new(
+            self.client,
+            self.table_name.clone(),
+            self.filter.clone(),
+            self.cluster.clone(),
+        );
+        let schema = match probe.try_next()? {
+            Some(object) => object.columns()?,
+            None => Vec::new(),
+        };
+        Ok(self.schema.get_or_init(|| schema).clone())
+    }
+
+    /// Number of records yielded so far
+    ///
+    /// After a [`scroll`](Self::scroll) this is the 1-based position of the
+    /// record scrolled to, so iteration continues with the record after it.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Move the cursor and return the record it lands on
+    ///
+    /// `mode` is one of the `CT_FIND_SCROLL_*` constants. `offset` is the
+    /// 1-based record number for [`CT_FIND_SCROLL_ABSOLUTE`](crate::CT_FIND_SCROLL_ABSOLUTE),
+    /// a signed distance for [`CT_FIND_SCROLL_RELATIVE`](crate::CT_FIND_SCROLL_RELATIVE)
+    /// and ignored otherwise. The query is executed first if iteration has not
+    /// started yet.
+    ///
+    /// Returns `Ok(None)`, leaving the position unchanged, if the target lies
+    /// outside the result set or the result set is empty. After a successful
+    /// scroll, iteration continues with the following record.
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `mode` is not a scroll mode
+    /// * [`CtApiError::CursorExpired`] - The server discarded the cursor
+    /// * [`CtApiError::InvalidHandle`] - The client was closed
+    /// * [`CtApiError::System`] - System call failed
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CtClient, CT_FIND_SCROLL_ABSOLUTE, CT_FIND_SCROLL_RELATIVE};
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// let mut find = client.find_first("Tag", "", None);
+    /// if let Some(outcome) = find.scroll(CT_FIND_SCROLL_ABSOLUTE, 10)? {
+    ///     println!("record {}: {}", outcome.position, outcome.object.get_property("TAG")?);
+    /// }
+    /// let back = find.scroll(CT_FIND_SCROLL_RELATIVE, -5)?;
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn scroll(&mut self, mode: u32, offset: i32) -> Result<Option<ScrollOutcome<'a>>> {
+        use crate::constants::{CT_FIND_SCROLL_NEXT, CT_FIND_SCROLL_RELATIVE};
+
+        if !(CT_FIND_SCROLL_NEXT..=CT_FIND_SCROLL_RELATIVE).contains(&mode) {
+            return Err(CtApiError::InvalidParameter {
+                param: "mode".to_string(),
+                value: mode.to_string(),
+            });
+        }
+        self.client.ensure_open()?;
+        if self.handle.is_null() {
+            if self.is_end || self.find_first()?.is_none() {
+                self.is_end = true;
+                return Ok(None);
+            }
+            // The query has been executed but its first record not yielded
+            self.position = 1;
+        }
+
+        let mut find_object = std::ptr::null_mut();
+        // SAFETY: self.handle is a live find handle from ctFindFirst(Ex).
+        // find_object is a local stack variable.
+        let record = unsafe { ctFindScroll(self.handle, mode, offset, &mut find_object) };
+        self.record_io(0);
+        let Some(position) =
+            interpret_scroll(record, self.position, std::io::Error::last_os_error)?
+        else {
+            return Ok(None);
+        };
+        self.position = position as usize;
+        self.is_end = false;
+        Ok(Some(ScrollOutcome {
+            position,
+            object: self.object(find_object),
+        }))
+    }
+
+    /// Read every remaining record into memory
+    ///
+    /// Repeated strings, such as field names, units and cluster names, share
+    /// one allocation across the result (up to
+    /// [`DEFAULT_INTERN_CAPACITY`](crate::intern::DEFAULT_INTERN_CAPACITY)
+    /// distinct strings).
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`] - The cursor expired and the search is not resumable
+    /// * [`CtApiError::System`] - System call failed
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// let records = client.find_first("Tag", "CLUSTER=Cluster1", None).into_records()?;
+    /// assert!(records.iter().any(|record| record.get("TAG") == Some("Temperature")));
+    /// for record in &records {
+    ///     println!("{:?} {:?}", record.get("TAG"), record.get("UNITS"));
+    /// }
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn into_records(self) -> Result<Vec<Record>> {
+        materialize(self)
+    }
+
+    /// Read every remaining record, interning strings through `interner`
+    ///
+    /// Use [`StringInterner::new`] to choose the maximum number of distinct
+    /// strings kept; `0` disables interning.
+    pub fn into_records_with(self, interner: &mut StringInterner) -> Result<Vec<Record>> {
+        materialize_with(self, interner)
+    }
+
+    /// Take the error that ended the iteration, if any
+    pub fn take_error(&mut self) -> Option<CtApiError> {
+        self.error.take()
+    }
+
+    /// Fetch the next record, reporting failures instead of ending silently
+    ///
+    /// Returns `Ok(None)` once all records have been returned.
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`] - The cursor expired and the search is not resumable
+    /// * [`CtApiError::InvalidHandle`] - The client was closed
+    /// * [`CtApiError::System`] - System call failed
+    pub fn try_next(&mut self) -> Result<Option<FindObject<'a>>> {
+        if self.is_end {
+            return Ok(None);
+        }
+        crate::blocking::check("CtFind iteration", crate::blocking::IN_BLOCKING_SECTION);
+        let result = if let Err(error) = self.client.ensure_open() {
+            Err(error)
+        } else if self.handle.is_null() {
+            self.find_first()
+        } else {
+            self.find_next()
+        };
+        match result {
+            Ok(Some(object)) => {
+                self.position += 1;
+                Ok(Some(object))
+            }
+            Ok(None) => {
+                self.is_end = true;
+                Ok(None)
+            }
+            Err(e) => {
+                self.is_end = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// Fetch the next record as an object borrowing the search
+    ///
+    /// Like [`try_next`](Self::try_next), but the object cannot be kept
+    /// once the search advances or is dropped. Use [`FindObject::detach`]
+    /// to keep its data beyond that.
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`] - The cursor expired and the search is not resumable
+    /// * [`CtApiError::System`] - System call failed
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// let mut find = client.find_first("Tag", "", None);
+    /// let mut kept = Vec::new();
+    /// while let Some(object) = find.next_object()? {
+    ///     println!("{}", object.get_property("TAG")?);
+    ///     kept.push(object.detach()?);
+    /// }
+    /// drop(find);
+    /// assert!(kept.iter().any(|record| record.get("TAG") == Some("Temperature")));
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn next_object(&mut self) -> Result<Option<FindObject<'_>>> {
+        self.try_next()
+    }
+
+    /// Execute the query, returning the first record
+    ///
+    /// An empty result, which CtAPI reports as a failure with a no-record
+    /// error, is `Ok(None)`; any other failure is returned.
+    fn find_first(&mut self) -> Result<Option<FindObject<'a>>> {
+        match self.open() {
+            Ok(object) => Ok(Some(object)),
+            Err(error) if is_no_record(&error) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Whether the query matches any record
+    pub(crate) fn any(mut self) -> Result<bool> {
+        self.client.ensure_open()?;
+        Ok(self.find_first()?.is_some())
+    }
+
+    /// Call `ctFindFirst`, with the reason in `GetLastError` on failure
+    fn open(&mut self) -> std::result::Result<FindObject<'a>, std::io::Error> {
+        let mut find_object = std::ptr::null_mut();
+        let calls = self.client.call_log();
+        let started = calls.start();
         // SAFETY: The CtAPI handle and CString pointers are valid for the
         // lifetime of `self`. find_object is a local stack variable whose
-        // address is valid for the duration of each FFI call.
-        unsafe {
-            if self.is_end {
-                return None;
+        // address is valid for the duration of the FFI call.
+        self.handle = unsafe {
+            match &self.cluster {
+                Some(cluster) => ctFindFirstEx(
+                    self.client.handle(),
+                    self.table_name.as_ptr(),
+                    self.filter.as_ptr(),
+                    cluster.as_ptr(),
+                    &mut find_object,
+                    0,
+                ),
+                None => ctFindFirst(
+                    self.client.handle(),
+                    self.table_name.as_ptr(),
+                    self.filter.as_ptr(),
+                    &mut find_object,
+                    0,
+                ),
             }
-            let mut find_object = std::ptr::null_mut();
-            if self.handle.is_null() {
-                match &self.cluster {
-                    Some(cluster) => {
-                        self.handle = ctFindFirstEx(
-                            self.client.handle(),
-                            self.table_name.as_ptr(),
-                            self.filter.as_ptr(),
-                            cluster.as_ptr(),
-                            &mut find_object,
-                            0,
-                        );
-                        if self.handle.is_null() {
-                            self.is_end = true;
-                            None
-                        } else {
-                            Some(FindObject(find_object))
-                        }
-                    }
-                    None => {
-                        self.handle = ctFindFirst(
-                            self.client.handle(),
-                            self.table_name.as_ptr(),
-                            self.filter.as_ptr(),
-                            &mut find_object,
-                            0,
-                        );
-                        if self.handle.is_null() {
-                            self.is_end = true;
-                            None
-                        } else {
-                            Some(FindObject(find_object))
-                        }
-                    }
-                }
-            } else if ctFindNext(self.handle, &mut find_object) {
-                Some(FindObject(find_object))
-            } else {
-                self.is_end = true;
+        };
+        let error = self.handle.is_null().then(std::io::Error::last_os_error);
+        let op = if self.cluster.is_some() {
+            "ctFindFirstEx"
+        } else {
+            "ctFindFirst"
+        };
+        calls.finish(started, op, self.table_name.as_bytes(), error.is_none());
+        let mut request_bytes = self.table_name.as_bytes().len() + self.filter.as_bytes().len();
+        request_bytes += self
+            .cluster
+            .as_ref()
+            .map_or(0, |cluster| cluster.as_bytes().len());
+        self.record_io(request_bytes);
+        match error {
+            Some(error) => Err(error),
+            None => Ok(self.object(find_object)),
+        }
+    }
+
+    /// Advance the open cursor, resuming it if it has expired
+    fn find_next(&mut self) -> Result<Option<FindObject<'a>>> {
+        let mut find_object = std::ptr::null_mut();
+        let calls = self.client.call_log();
+        let started = calls.start();
+        // SAFETY: self.handle is a live find handle from ctFindFirst(Ex).
+        // find_object is a local stack variable.
+        let found = unsafe { ctFindNext(self.handle, &mut find_object) };
+        calls.finish(started, "ctFindNext", self.table_name.as_bytes(), found);
+        self.record_io(0);
+        if found {
+            return Ok(Some(self.object(find_object)));
+        }
+        let error = std::io::Error::last_os_error();
+        if is_no_record(&error) {
+            // End of records
+            return Ok(None);
+        }
+        if !is_cursor_expired(&error) {
+            return Err(error.into());
+        }
+        if !self.resumable {
+            return Err(CtApiError::CursorExpired {
+                position: self.position,
+            });
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            position = self.position,
+            "find cursor expired, re-executing query"
+        );
+        self.resume()
+    }
+
+    /// Re-execute the query and scroll to the record after the last one yielded
+    fn resume(&mut self) -> Result<Option<FindObject<'a>>> {
+        // SAFETY: the expired handle is still ours to close; errors are ignored
+        // since the server has already discarded the cursor.
+        unsafe { ctFindClose(self.handle) };
+        self.handle = std::ptr::null_mut();
+
+        if self.find_first()?.is_none() {
+            return Ok(None);
+        }
+        let mut find_object = std::ptr::null_mut();
+        // SAFETY: self.handle is the fresh find handle. Record numbers passed
+        // to CT_FIND_SCROLL_ABSOLUTE are 1-based, so position + 1 is the next
+        // record not yet yielded.
+        let record = unsafe {
+            ctFindScroll(
+                self.handle,
+                crate::constants::CT_FIND_SCROLL_ABSOLUTE,
+                (self.position + 1) as i32,
+                &mut find_object,
+            )
+        };
+        self.record_io(0);
+        Ok(
+            interpret_scroll(record, self.position, std::io::Error::last_os_error)?
+                .map(|_| self.object(find_object)),
+        )
+    }
+
+    /// Wrap an object handle returned by the cursor
+    fn object(&self, handle: RawHandle) -> FindObject<'a> {
+        FindObject {
+            handle,
+            io: Arc::clone(self.client.io_counters()),
+            calls: Arc::clone(self.client.call_log()),
+            issued: self
+                .strict
+                .then(|| (Rc::clone(&self.moves), self.moves.get())),
+            search: PhantomData,
+            #[cfg(test)]
+            live: tests::LiveObject::new(),
+        }
+    }
+
+    /// Count a cursor call sending `request_bytes`, superseding earlier objects
+    fn record_io(&self, request_bytes: usize) {
+        self.moves.set(self.moves.get() + 1);
+        self.client
+            .io_counters()
+            .record(IoKind::Find, request_bytes, 0);
+    }
+}
+
+impl<'a> Iterator for CtFind<'a> {
+    type Item = FindObject<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.try_next() {
+            Ok(object) => object,
+            Err(e) => {
+                self.error = Some(e);
                 None
             }
         }
@@ -101,6 +606,7 @@ impl Iterator for CtFind<'_> {
 
 impl Drop for CtFind<'_> {
     fn drop(&mut self) {
+        self.moves.set(self.moves.get() + 1);
         // SAFETY: Safe to call ctFindClose on a valid handle.
         // The null check prevents double-free or invalid handle access.
         // Since CtFind is not Send/Sync, it cannot be accessed from multiple threads.
@@ -113,11 +619,163 @@ impl Drop for CtFind<'_> {
     }
 }
 
+/// Streaming reader over the records of a search, returned by [`CtFind::records`]
+///
+/// Reads up to the prefetch window of records at a time, releasing each
+/// object handle before the cursor advances. Errors end the stream after the
+/// records read before them.
+#[derive(Debug)]
+pub struct FindRecords<'a> {
+    find: CtFind<'a>,
+    interner: StringInterner,
+    prefetch: usize,
+    pending: VecDeque<Record>,
+    error: Option<CtApiError>,
+}
+
+impl FindRecords<'_> {
+    /// Read up to `records` records ahead (at least one)
+    pub fn with_prefetch(mut self, records: usize) -> Self {
+        self.prefetch = records.max(1);
+        self
+    }
+
+    /// Use `interner` to share repeated strings between records
+    pub fn with_interner(mut self, interner: StringInterner) -> Self {
+        self.interner = interner;
+        self
+    }
+
+    /// Read records until the window is full or the search ends
+    fn fill(&mut self) {
+        while self.pending.len() < self.prefetch {
+            let record = match self.find.try_next() {
+                Ok(Some(object)) => Record::read_interned(&object, &mut self.interner),
+                Ok(None) => return,
+                Err(e) => Err(e),
+            };
+            match record {
+                Ok(record) => self.pending.push_back(record),
+                Err(e) => {
+                    self.find.is_end = true;
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for FindRecords<'_> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            self.fill();
+        }
+        match self.pending.pop_front() {
+            Some(record) => Some(Ok(record)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
 /// Wrapper struct containing object handle returned by search function
+///
+/// The handle belongs to its search and is only valid until the search moves
+/// to another record (`ctFindNext`, `ctFindScroll`) or is closed. Read the
+/// properties you need before advancing, [`detach`](Self::detach) the
+/// object, or use [`CtFind::records`].
+///
+/// `'find` is the borrow of the search for objects from
+/// [`CtFind::next_object`], which therefore cannot outlive it:
+///
+/// ```compile_fail,E0597
+/// use ctapi_rs::CtClient;
+///
+/// let client = CtClient::open_mock()?;
+/// let object = {
+///     let mut find = client.find_first("Tag", "", None);
+///     find.next_object()?.unwrap()
+/// };
+/// object.get_property("TAG")?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+///
+/// nor be read after the search has advanced:
+///
+/// ```compile_fail,E0499
+/// use ctapi_rs::CtClient;
+///
+/// let client = CtClient::open_mock()?;
+/// let mut find = client.find_first("Tag", "", None);
+/// let first = find.next_object()?.unwrap();
+/// let _second = find.next_object()?;
+/// first.get_property("TAG")?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+///
+/// For the other [`CtFind`] methods it is the borrow of the client.
 #[derive(Debug)]
-pub struct FindObject(RawHandle);
+pub struct FindObject<'find> {
+    handle: RawHandle,
+    io: Arc<IoCounters>,
+    calls: Arc<CallLog>,
+    /// Move counter of a strict search and its value when this object was yielded
+    issued: Option<(Rc<Cell<u64>>, u64)>,
+    search: PhantomData<&'find ()>,
+    #[cfg(test)]
+    live: tests::LiveObject,
+}
+
+impl FindObject<'_> {
+    /// Copy every field of the record out of CtAPI
+    ///
+    /// The returned [`Record`] owns its data and stays usable after the
+    /// search has moved on or been closed.
+    ///
+    /// # Errors
+    /// * [`CtApiError::StaleFindObject`] - A [`strict`](CtFind::strict)
+    ///   search has moved past this object
+    /// * [`CtApiError::System`] - System call failed
+    pub fn detach(&self) -> Result<Record> {
+        Record::read(self)
+    }
+
+    /// Name, type and size of every field of the record
+    ///
+    /// # Errors
+    /// * [`CtApiError::StaleFindObject`] - A [`strict`](CtFind::strict)
+    ///   search has moved past this object
+    /// * [`CtApiError::System`] - System call failed
+    pub fn columns(&self) -> Result<Vec<ColumnInfo>> {
+        let invalid = |property: String, value: String| CtApiError::Other {
+            code: 0,
+            message: format!("invalid {property}: {value}"),
+        };
+        let count = self.get_property("object.fields.count")?;
+        let count: usize = count
+            .trim()
+            .parse()
+            .map_err(|_| invalid("field count".to_string(), count.clone()))?;
+        (1..=count)
+            .map(|n| {
+                let name = self.get_property(format!("object.fields({n}).name"))?;
+                let property = format!("object.fields({n}).type");
+                let db_type = self.get_property(&property)?;
+                let db_type = DbType::parse(&db_type).ok_or_else(|| invalid(property, db_type))?;
+                let property = format!("object.fields({n}).actualsize");
+                let size = self.get_property(&property)?;
+                let actual_size = size.trim().parse().map_err(|_| invalid(property, size))?;
+                Ok(ColumnInfo {
+                    name,
+                    db_type,
+                    actual_size,
+                })
+            })
+            .collect()
+    }
 
-impl FindObject {
     /// Retrieve object properties or metadata
     ///
     /// Use this function in conjunction with ctFindFirst() and ctFindNext() functions.
@@ -130,28 +788,55 @@ impl FindObject {
     /// - object.fields(n).type - Type of nth field in record
     /// - object.fields(n).actualsize - Actual size of nth field in record
     pub fn get_property<T: AsRef<str>>(&self, name: T) -> Result<String> {
-        let mut buffer = [0u8; 256];
-        let mut len: u32 = 0;
-        let name = CString::new(GBK.encode(name.as_ref()).0)?;
-        // SAFETY: self.0 is a valid FindObject handle from ctFindFirst/ctFindNext.
-        // name is a GBK-encoded CString. buffer is a fixed-size stack array.
-        // len is a local stack variable.
-        unsafe {
-            if !ctGetProperty(
-                self.0,
-                name.as_ptr(),
-                buffer.as_mut_ptr() as *mut c_void,
-                256,
-                &mut len,
-                DBTYPEENUM::DBTYPE_STR,
-            ) {
-                return Err(std::io::Error::last_os_error().into());
-            }
-            Ok(GBK
-                .decode(std::slice::from_raw_parts(buffer.as_ptr(), len as usize))
-                .0
-                .to_string())
+        match self.get_property_as(name, DBTYPEENUM::DBTYPE_STR)? {
+            PropertyValue::Str(s) => Ok(s),
+            other => Ok(other.to_string()),
+        }
+    }
+
+    /// Retrieve an object property as a typed value
+    ///
+    /// Like [`get_property`](Self::get_property), but asks CtAPI to convert the
+    /// property to `ty` and decodes it into the matching [`PropertyValue`] variant.
+    ///
+    /// # Errors
+    /// * [`CtApiError::UnsupportedOperation`](crate::CtApiError::UnsupportedOperation) - `ty` is not supported
+    /// * [`CtApiError::StaleFindObject`](crate::CtApiError::StaleFindObject) - A
+    ///   [`strict`](CtFind::strict) search has moved past this object
+    /// * [`CtApiError::System`](crate::CtApiError::System) - System call failed
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CtClient, DBTYPEENUM};
+    ///
+    /// let client = CtClient::open_mock()?;
+    /// for object in client.find_first("Tag", "CLUSTER=Cluster1", None) {
+    ///     let count = object.get_property_as("object.fields.count", DBTYPEENUM::DBTYPE_I4)?;
+    ///     println!("{count}");
+    /// }
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn get_property_as<T: AsRef<str>>(&self, name: T, ty: DBTYPEENUM) -> Result<PropertyValue> {
+        if let Some((moves, issued)) = &self.issued
+            && moves.get() != *issued
+        {
+            return Err(CtApiError::StaleFindObject);
         }
+        let name = CString::new(GBK.encode(name.as_ref()).0)?;
+        DbBuffer::fetch(ty, |data, len, result_len| {
+            let started = self.calls.start();
+            // SAFETY: self.handle is a FindObject handle from ctFindFirst/ctFindNext.
+            // name is a GBK-encoded CString. data points to a DbBuffer of `len`
+            // bytes sized for `ty`. result_len is a local out-parameter.
+            let ok =
+                unsafe { ctGetProperty(self.handle, name.as_ptr(), data, len, result_len, ty) };
+            self.calls
+                .finish(started, "ctGetProperty", name.as_bytes(), ok);
+            let response_bytes = if ok { *result_len as usize } else { 0 };
+            self.io
+                .record(IoKind::Find, name.as_bytes().len(), response_bytes);
+            ok
+        })
     }
 }
 
@@ -159,10 +844,54 @@ impl FindObject {
 mod tests {
     use super::*;
 
+    thread_local! {
+        /// Find objects yielded on this thread that are still alive, and the
+        /// most that were alive at once
+        static LIVE_OBJECTS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    }
+
+    /// Counts a [`FindObject`] in [`LIVE_OBJECTS`] while it is alive
+    #[derive(Debug)]
+    pub(super) struct LiveObject;
+
+    impl LiveObject {
+        pub(super) fn new() -> Self {
+            LIVE_OBJECTS.with(|counts| {
+                let (live, peak) = counts.get();
+                counts.set((live + 1, peak.max(live + 1)));
+            });
+            Self
+        }
+    }
+
+    impl Drop for LiveObject {
+        fn drop(&mut self) {
+            LIVE_OBJECTS.with(|counts| {
+                let (live, peak) = counts.get();
+                counts.set((live - 1, peak));
+            });
+        }
+    }
+
+    /// Most find objects alive at once on this thread since the last call
+    fn take_peak_live_objects() -> usize {
+        LIVE_OBJECTS.with(|counts| {
+            let (live, _) = counts.get();
+            counts.replace((live, live)).1
+        })
+    }
+
     #[test]
     fn test_find_object_debug() {
         let handle = 0x12345678 as *mut std::ffi::c_void;
-        let find_object = FindObject(handle);
+        let find_object = FindObject {
+            handle,
+            io: Arc::default(),
+            calls: Arc::default(),
+            issued: None,
+            search: PhantomData,
+            live: LiveObject::new(),
+        };
 
         // Test Debug implementation
         let debug_string = format!("{:?}", find_object);
@@ -172,12 +901,224 @@ mod tests {
     #[test]
     fn test_find_object_property_access() {
         let handle = std::ptr::null_mut();
-        let find_object = FindObject(handle);
+        let find_object = FindObject {
+            handle,
+            io: Arc::default(),
+            calls: Arc::default(),
+            issued: None,
+            search: PhantomData,
+            live: LiveObject::new(),
+        };
 
         // Test null handle case
         // Note: Don't test actual property retrieval here as it requires real CtAPI connection
         // Only test basic functionality of struct
-        assert_eq!(find_object.0, std::ptr::null_mut());
+        assert_eq!(find_object.handle, std::ptr::null_mut());
+    }
+
+    #[test]
+    fn test_cursor_expired_classification() {
+        let expired = std::io::Error::from_raw_os_error(ERROR_INVALID_HANDLE);
+        assert!(is_cursor_expired(&expired));
+
+        // ERROR_NO_MORE_FILES marks a normal end of records
+        let end = std::io::Error::from_raw_os_error(18);
+        assert!(!is_cursor_expired(&end));
+    }
+
+    #[test]
+    fn test_interpret_scroll() {
+        let unused = || -> std::io::Error { unreachable!("GetLastError read on success") };
+        assert_eq!(interpret_scroll(7, 3, unused).unwrap(), Some(7));
+
+        // Past the end, before the start and empty result sets all yield no record
+        for code in [0, 18, 259] {
+            let outcome = interpret_scroll(0, 3, || std::io::Error::from_raw_os_error(code));
+            assert_eq!(outcome.unwrap(), None);
+        }
+
+        let expired = interpret_scroll(0, 3, || std::io::Error::from_raw_os_error(6));
+        assert!(matches!(
+            expired,
+            Err(CtApiError::CursorExpired { position: 3 })
+        ));
+
+        let failed = interpret_scroll(0, 3, || std::io::Error::from_raw_os_error(1722));
+        assert!(matches!(failed, Err(CtApiError::System(..))));
+    }
+
+    #[test]
+    fn test_scroll_rejects_unknown_mode() {
+        let client = crate::CtClient::from_handle(std::ptr::null_mut());
+        let mut find = CtFind::new(
+            &client,
+            CString::new("Tag").unwrap(),
+            CString::new("").unwrap(),
+            None,
+        );
+        for mode in [0, 7] {
+            assert!(matches!(
+                find.scroll(mode, 1),
+                Err(CtApiError::InvalidParameter { .. })
+            ));
+        }
+        assert_eq!(find.position(), 0);
+    }
+
+    #[test]
+    fn test_cursor_expired_error() {
+        let error = CtApiError::CursorExpired { position: 42 };
+        assert_eq!(
+            error.to_string(),
+            "[E3003] Find cursor expired after 42 records; restart the query"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    fn many_records(count: usize) -> crate::CtClient {
+        let server = (0..count).fold(crate::mock::MockServer::new(), |server, n| {
+            server.with_record("Tag", &[("TAG", &format!("Tag{n}")), ("UNITS", "%")])
+        });
+        crate::CtClient::open_mock_with(server).unwrap()
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_records_stream_with_one_open_object() {
+        let client = many_records(200);
+        take_peak_live_objects();
+        let mut records = client
+            .find_first("Tag", "", None)
+            .records()
+            .with_prefetch(8);
+        let first = records.next().unwrap().unwrap();
+        assert_eq!(first.get("TAG"), Some("Tag0"));
+        assert_eq!(records.pending.len(), 7);
+        assert_eq!(records.count(), 199);
+        assert_eq!(take_peak_live_objects(), 1);
+
+        let all = client.find_first("Tag", "", None).into_records().unwrap();
+        assert_eq!(all.len(), 200);
+        assert_eq!(take_peak_live_objects(), 1);
+
+        // Keeping the objects themselves holds one per record
+        let objects: Vec<_> = client.find_first("Tag", "", None).take(8).collect();
+        assert_eq!(take_peak_live_objects(), 8);
+        drop(objects);
+        assert_eq!(take_peak_live_objects(), 0);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_expired_cursor_resumes_after_last_record() {
+        use crate::mock::MockServer;
+
+        let server = (0..5).fold(MockServer::new().with_cursor_lifetime(2), |server, n| {
+            server.with_record("Tag", &[("TAG", &format!("Tag{n}"))])
+        });
+        let client = crate::CtClient::open_mock_with(server).unwrap();
+        let tag = |object: FindObject| object.get_property("TAG").unwrap();
+
+        let mut find = client.find_first("Tag", "", None);
+        let tags: Vec<_> = find.by_ref().map(tag).collect();
+        assert_eq!(tags, ["Tag0", "Tag1", "Tag2"]);
+        assert!(matches!(
+            find.take_error(),
+            Some(CtApiError::CursorExpired { position: 3 })
+        ));
+
+        let find = client.find_first("Tag", "", None).resumable();
+        let tags: Vec<_> = find.map(tag).collect();
+        assert_eq!(tags, ["Tag0", "Tag1", "Tag2", "Tag3", "Tag4"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_failures_are_not_the_end_of_records() {
+        use crate::mock::{self, MockServer};
+        use crate::reconnect::is_connection_down;
+
+        let client = many_records(3);
+        let empty = client.find_first("Tag", "TAG=None", None).try_next();
+        assert!(empty.unwrap().is_none());
+        let missing = || client.find_first("NoSuchTable", "", None);
+        assert!(missing().try_next().is_err());
+        assert!(missing().into_records().is_err());
+
+        // A connection lost mid-search is reported, not a truncated result
+        let mut find = client.find_first("Tag", "", None);
+        assert!(find.try_next().unwrap().is_some());
+        mock::reload(&client, MockServer::new().unreachable());
+        assert!(is_connection_down(&find.try_next().unwrap_err()));
+
+        // So is a resumed search whose query fails
+        let server = (0..5).fold(MockServer::new().with_cursor_lifetime(1), |server, n| {
+            server.with_record("Tag", &[("TAG", &format!("Tag{n}"))])
+        });
+        let client = crate::CtClient::open_mock_with(server).unwrap();
+        let mut find = client.find_first("Tag", "", None).resumable();
+        assert!(find.try_next().unwrap().is_some());
+        assert!(find.try_next().unwrap().is_some());
+        mock::reload(&client, MockServer::new().unreachable());
+        assert!(is_connection_down(&find.try_next().unwrap_err()));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_schema_does_not_move_the_search() {
+        use crate::property::ColumnKind;
+
+        let client = many_records(3);
+        let mut find = client.find_first("Tag", "", None);
+        assert_eq!(find.next().unwrap().get_property("TAG").unwrap(), "Tag0");
+
+        let schema = find.schema().unwrap();
+        let names: Vec<_> = schema.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, ["TAG", "UNITS"]);
+        assert_eq!(schema[0].db_type.known(), Some(DBTYPEENUM::DBTYPE_STR));
+        assert_eq!(schema[0].db_type.column_kind(), ColumnKind::Text);
+        assert_eq!(schema[0].actual_size, 4);
+        assert_eq!(find.position(), 1);
+        assert_eq!(find.next().unwrap().get_property("TAG").unwrap(), "Tag1");
+        assert_eq!(find.schema().unwrap(), schema);
+
+        assert!(
+            client
+                .find_first("Tag", "TAG=None", None)
+                .schema()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_advancing_supersedes_previous_object() {
+        let client = many_records(3);
+
+        let mut find = client.find_first("Tag", "", None);
+        let first = find.next().unwrap();
+        let second = find.next().unwrap();
+        assert!(first.get_property("TAG").is_err());
+        assert_eq!(second.get_property("TAG").unwrap(), "Tag1");
+
+        let mut find = client.find_first("Tag", "", None).strict();
+        let first = find.next().unwrap();
+        assert_eq!(first.get_property("TAG").unwrap(), "Tag0");
+        let outcome = find
+            .scroll(crate::constants::CT_FIND_SCROLL_LAST, 0)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            first.get_property("TAG"),
+            Err(CtApiError::StaleFindObject)
+        ));
+        assert_eq!(outcome.object.get_property("TAG").unwrap(), "Tag2");
+        drop(find);
+        assert!(matches!(
+            outcome.object.get_property("TAG"),
+            Err(CtApiError::StaleFindObject)
+        ));
     }
 
     #[test]
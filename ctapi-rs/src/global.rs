@@ -0,0 +1,212 @@
+//! Process-wide default client for scripts
+//!
+//! Throwaway scripts and interactive exploration often need exactly one
+//! connection. [`init`] opens it once and the free functions in this module
+//! use it, so no client has to be passed around:
+//!
+//! ```no_run
+//! use ctapi_rs::global::{self, GlobalConfig};
+//!
+//! global::init(GlobalConfig::default())?;
+//! println!("{}", global::tag_read("Temperature")?);
+//! global::tag_write("Setpoint", "25.5")?;
+//! global::shutdown()?;
+//! # Ok::<(), ctapi_rs::CtApiError>(())
+//! ```
+//!
+//! Every function returns [`CtApiError::GlobalNotInitialized`] until [`init`]
+//! has succeeded and again after [`shutdown`]. A new client can be installed
+//! after a shutdown, which lets test harnesses reconnect between cases.
+//!
+//! Hidden global state makes ownership and reconnection hard to reason
+//! about; long-running services should own a [`CtClient`] instead.
+
+use crate::CtClient;
+use crate::cicode::CicodeWindow;
+use crate::error::{CtApiError, Result};
+use crate::secret::SecretString;
+use std::sync::{Arc, RwLock};
+
+/// The installed client. A plain `RwLock` rather than a `OnceLock` so that
+/// [`shutdown`] can clear it for a later [`init`].
+static GLOBAL: RwLock<Option<Arc<CtClient>>> = RwLock::new(None);
+
+/// Connection settings for [`init`]
+///
+/// The default connects to the local computer with the default credentials.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalConfig {
+    /// Computer name or IP address, `None` for the local computer
+    pub computer: Option<String>,
+    /// User name
+    pub user: Option<String>,
+    /// Password, redacted in `Debug` output
+    pub password: Option<SecretString>,
+    /// `CT_OPEN_*` flags
+    pub mode: u32,
+}
+
+/// Open the global client
+///
+/// # Errors
+/// * [`CtApiError::GlobalAlreadyInitialized`] - A client is already installed;
+///   call [`shutdown`] first
+/// * Any error from [`CtClient::open`]
+pub fn init(config: GlobalConfig) -> Result<()> {
+    install_with(|| {
+        CtClient::open(
+            config.computer.as_deref(),
+            config.user.as_deref(),
+            config.password.as_ref().map(SecretString::expose),
+            config.mode,
+        )
+    })
+}
+
+/// Install the client returned by `open` unless one is already installed
+///
+/// `open` runs under the lock, so concurrent callers never open more than
+/// one connection.
+fn install_with(open: impl FnOnce() -> Result<CtClient>) -> Result<()> {
+    let mut global = GLOBAL.write().unwrap_or_else(|e| e.into_inner());
+    if global.is_some() {
+        return Err(CtApiError::GlobalAlreadyInitialized);
+    }
+    *global = Some(Arc::new(open()?));
+    Ok(())
+}
+
+/// The global client
+///
+/// Useful for calls without a free-function shortcut. The returned client
+/// stays open until it is dropped, even across [`shutdown`].
+pub fn client() -> Result<Arc<CtClient>> {
+    GLOBAL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or(CtApiError::GlobalNotInitialized)
+}
+
+/// Read a tag with the global client, see [`CtClient::tag_read`]
+pub fn tag_read<T: AsRef<str>>(tag: T) -> Result<String> {
+    client()?.tag_read(tag)
+}
+
+/// Write a tag with the global client, see [`CtClient::tag_write_str`]
+pub fn tag_write<T: AsRef<str>>(tag: T, value: &str) -> Result<()> {
+    client()?.tag_write_str(tag, value)
+}
+
+/// Run a Cicode command with the global client, see [`CtClient::cicode`]
+pub fn cicode(cmd: &str) -> Result<String> {
+    client()?.cicode(cmd, CicodeWindow::NONE, 0)
+}
+
+/// Remove the global client
+///
+/// The connection is closed once clones obtained from [`client`] are gone.
+pub fn shutdown() -> Result<()> {
+    GLOBAL
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .map(drop)
+        .ok_or(CtApiError::GlobalNotInitialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serializes tests that touch the process-wide client
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn fake_client() -> Result<CtClient> {
+        Ok(CtClient::from_handle(std::ptr::null_mut()))
+    }
+
+    #[test]
+    fn test_uninitialized() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(matches!(client(), Err(CtApiError::GlobalNotInitialized)));
+        assert!(matches!(
+            tag_read("Tag1"),
+            Err(CtApiError::GlobalNotInitialized)
+        ));
+        assert!(matches!(
+            tag_write("Tag1", "1"),
+            Err(CtApiError::GlobalNotInitialized)
+        ));
+        assert!(matches!(
+            cicode("Time(1)"),
+            Err(CtApiError::GlobalNotInitialized)
+        ));
+        assert!(matches!(shutdown(), Err(CtApiError::GlobalNotInitialized)));
+    }
+
+    #[test]
+    fn test_init_shutdown_reinit() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        install_with(fake_client).unwrap();
+        let first = client().unwrap();
+        assert!(matches!(
+            install_with(fake_client),
+            Err(CtApiError::GlobalAlreadyInitialized)
+        ));
+
+        shutdown().unwrap();
+        assert!(matches!(client(), Err(CtApiError::GlobalNotInitialized)));
+
+        install_with(fake_client).unwrap();
+        let second = client().unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_failed_init_leaves_uninitialized() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let result = install_with(|| Err(CtApiError::Timeout));
+        assert!(matches!(result, Err(CtApiError::Timeout)));
+        assert!(matches!(client(), Err(CtApiError::GlobalNotInitialized)));
+    }
+
+    #[test]
+    fn test_concurrent_first_use() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let opened = AtomicUsize::new(0);
+        let installed = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        install_with(|| {
+                            opened.fetch_add(1, Ordering::SeqCst);
+                            fake_client()
+                        })
+                        .is_ok()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .filter(|&ok| ok)
+                .count()
+        });
+        assert_eq!(installed, 1);
+        assert_eq!(opened.load(Ordering::SeqCst), 1);
+        shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_config_debug_redacts_password() {
+        let config = GlobalConfig {
+            password: Some(SecretString::from("Citect")),
+            ..Default::default()
+        };
+        assert!(!format!("{config:?}").contains("Citect"));
+    }
+}
@@ -0,0 +1,336 @@
+//! Alarm and trend history queries
+//!
+//! Citect SCADA serves alarm summaries and trend samples through find queries
+//! whose table name is a comma separated command: `ALMQUERY,...` and
+//! `TRNQUERY,...`. [`AlarmQuery`] and [`TrendQuery`] build these table names
+//! and run the search.
+//!
+//! Both commands embed a tag name, and servers disagree on where the cluster
+//! of a tag goes: some expect it in front of the tag (`Cluster1.Pump1`),
+//! others expect a bare tag and the cluster as the cluster argument of
+//! `ctFindFirstEx`. [`ClusterStyle`] selects the behaviour of the server. A
+//! query without a cluster uses the client's
+//! [`default_cluster`](crate::CtClient::default_cluster).
+
+use crate::client::CtClient;
+use crate::error::Result;
+use crate::find::CtFind;
+use crate::query::{Record, materialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where a history query places the cluster of its tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClusterStyle {
+    /// Qualify the tag in the table name, as `Cluster.Tag`
+    #[default]
+    TagPrefix,
+    /// Pass the cluster as the cluster argument of the search
+    FindArgument,
+}
+
+/// Table-name tag and search cluster for `tag` in `cluster`
+fn place_cluster(
+    tag: &str,
+    cluster: Option<String>,
+    style: ClusterStyle,
+) -> (String, Option<String>) {
+    match (cluster, style) {
+        (Some(cluster), ClusterStyle::TagPrefix) => (format!("{cluster}.{tag}"), None),
+        (cluster, _) => (tag.to_string(), cluster),
+    }
+}
+
+/// Seconds and milliseconds since the Unix epoch, zero for earlier times
+fn unix_parts(time: SystemTime) -> (u64, u32) {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since.as_secs(), since.subsec_millis())
+}
+
+/// Search of the alarm summary history of a tag (`ALMQUERY`)
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::history::{AlarmQuery, ClusterStyle};
+/// use std::time::{Duration, SystemTime};
+///
+/// let client = CtClient::open_mock()?;
+/// let end = SystemTime::now();
+/// let query = AlarmQuery::new("AlarmDB", "Pump1_Fault", end - Duration::from_secs(3600), end)
+///     .cluster("Cluster1")
+///     .cluster_style(ClusterStyle::FindArgument);
+/// for record in query.records(&client)? {
+///     println!("{:?}", record.get("COMMENT"));
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlarmQuery {
+    database: String,
+    tag: String,
+    start: SystemTime,
+    end: SystemTime,
+    period: Duration,
+    cluster: Option<String>,
+    style: ClusterStyle,
+}
+
+impl AlarmQuery {
+    /// Query the alarms of `tag` in `database` between `start` and `end`
+    pub fn new(database: &str, tag: &str, start: SystemTime, end: SystemTime) -> Self {
+        Self {
+            database: database.to_string(),
+            tag: tag.to_string(),
+            start,
+            end,
+            period: Duration::ZERO,
+            cluster: None,
+            style: ClusterStyle::default(),
+        }
+    }
+
+    /// Period argument of the query, in seconds with a fractional part
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Cluster of the tag, instead of the client's default cluster
+    pub fn cluster(mut self, cluster: &str) -> Self {
+        self.cluster = Some(cluster.to_string());
+        self
+    }
+
+    /// Where the cluster is placed, see [`ClusterStyle`]
+    pub fn cluster_style(mut self, style: ClusterStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Table name and search cluster of the query, given the client's default cluster
+    fn target(&self, default_cluster: Option<String>) -> (String, Option<String>) {
+        let cluster = self.cluster.clone().or(default_cluster);
+        let (tag, cluster) = place_cluster(&self.tag, cluster, self.style);
+        let (start, start_ms) = unix_parts(self.start);
+        let (end, end_ms) = unix_parts(self.end);
+        let table = format!(
+            "ALMQUERY,{},{tag},{start},{start_ms},{end},{end_ms},{}",
+            self.database,
+            self.period.as_secs_f64()
+        );
+        (table, cluster)
+    }
+
+    /// Start the search on `client`
+    pub fn find<'a>(&self, client: &'a CtClient) -> CtFind<'a> {
+        let (table, cluster) = self.target(client.default_cluster());
+        client.find_first(&table, "", cluster.as_deref())
+    }
+
+    /// Run the search on `client` and read every record
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`](crate::CtApiError::CursorExpired) - The server discarded the search cursor
+    /// * [`CtApiError::System`](crate::CtApiError::System) - System call failed
+    pub fn records(&self, client: &CtClient) -> Result<Vec<Record>> {
+        materialize(self.find(client))
+    }
+}
+
+/// Search of the trend samples of a tag (`TRNQUERY`)
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::history::TrendQuery;
+/// use std::time::{Duration, SystemTime};
+///
+/// let client = CtClient::open_mock()?;
+/// client.set_default_cluster(Some("Cluster1"));
+/// let query = TrendQuery::new("Flow1", SystemTime::now(), Duration::from_secs(60), 100);
+/// for sample in query.records(&client)? {
+///     println!("{:?} {:?}", sample.get("DATETIME"), sample.get("VALUE"));
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrendQuery {
+    tag: String,
+    end: SystemTime,
+    period: Duration,
+    samples: u32,
+    display_mode: u32,
+    data_mode: u32,
+    cluster: Option<String>,
+    style: ClusterStyle,
+}
+
+impl TrendQuery {
+    /// Query `samples` samples of `tag`, `period` apart, ending at `end`
+    pub fn new(tag: &str, end: SystemTime, period: Duration, samples: u32) -> Self {
+        Self {
+            tag: tag.to_string(),
+            end,
+            period,
+            samples,
+            display_mode: 0,
+            data_mode: 0,
+            cluster: None,
+            style: ClusterStyle::default(),
+        }
+    }
+
+    /// Display mode argument, as for the Cicode `TrnGetTable` function
+    pub fn display_mode(mut self, mode: u32) -> Self {
+        self.display_mode = mode;
+        self
+    }
+
+    /// Data mode argument: `0` for values only, `1` to include timestamps
+    pub fn data_mode(mut self, mode: u32) -> Self {
+        self.data_mode = mode;
+        self
+    }
+
+    /// Cluster of the tag, instead of the client's default cluster
+    pub fn cluster(mut self, cluster: &str) -> Self {
+        self.cluster = Some(cluster.to_string());
+        self
+    }
+
+    /// Where the cluster is placed, see [`ClusterStyle`]
+    pub fn cluster_style(mut self, style: ClusterStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Table name and search cluster of the query, given the client's default cluster
+    fn target(&self, default_cluster: Option<String>) -> (String, Option<String>) {
+        let cluster = self.cluster.clone().or(default_cluster);
+        let (tag, cluster) = place_cluster(&self.tag, cluster, self.style);
+        let (end, end_ms) = unix_parts(self.end);
+        let table = format!(
+            "TRNQUERY,{end},{end_ms},{},{},{tag},{},{}",
+            self.period.as_secs_f64(),
+            self.samples,
+            self.display_mode,
+            self.data_mode
+        );
+        (table, cluster)
+    }
+
+    /// Start the search on `client`
+    pub fn find<'a>(&self, client: &'a CtClient) -> CtFind<'a> {
+        let (table, cluster) = self.target(client.default_cluster());
+        client.find_first(&table, "", cluster.as_deref())
+    }
+
+    /// Run the search on `client` and read every record
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`](crate::CtApiError::CursorExpired) - The server discarded the search cursor
+    /// * [`CtApiError::System`](crate::CtApiError::System) - System call failed
+    pub fn records(&self, client: &CtClient) -> Result<Vec<Record>> {
+        materialize(self.find(client))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockServer};
+
+    fn at(secs: u64, ms: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(secs * 1000 + ms)
+    }
+
+    #[test]
+    fn test_alarm_query_tag_prefix() {
+        let table = "ALMQUERY,AlarmDB,Cluster1.Pump1,1000,250,2000,0,60";
+        let server = MockServer::new().with_record(table, &[("COMMENT", "Tripped")]);
+        let client = CtClient::open_mock_with(server).unwrap();
+        let query = AlarmQuery::new("AlarmDB", "Pump1", at(1000, 250), at(2000, 0))
+            .period(Duration::from_secs(60))
+            .cluster("Cluster1");
+
+        let records = query.records(&client).unwrap();
+        assert_eq!(records[0].get("COMMENT"), Some("Tripped"));
+        assert_eq!(mock::last_find(&client), Some((table.to_string(), None)));
+    }
+
+    #[test]
+    fn test_alarm_query_find_argument() {
+        let table = "ALMQUERY,AlarmDB,Pump1,1000,0,2000,0,0";
+        let server = MockServer::new().with_cluster_record(
+            "Cluster2",
+            table,
+            &[("COMMENT", "Cluster2 trip")],
+        );
+        let client = CtClient::open_mock_with(server).unwrap();
+        let query = AlarmQuery::new("AlarmDB", "Pump1", at(1000, 0), at(2000, 0))
+            .cluster("Cluster2")
+            .cluster_style(ClusterStyle::FindArgument);
+
+        let records = query.records(&client).unwrap();
+        assert_eq!(records[0].get("COMMENT"), Some("Cluster2 trip"));
+        assert_eq!(
+            mock::last_find(&client),
+            Some((table.to_string(), Some("Cluster2".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_alarm_query_sub_second_period() {
+        let table = "ALMQUERY,AlarmDB,Pump1,1000,0,1000,500,0.001";
+        let client = CtClient::open_mock_with(MockServer::new()).unwrap();
+        let query = AlarmQuery::new("AlarmDB", "Pump1", at(1000, 0), at(1000, 500))
+            .period(Duration::from_millis(1));
+        query.find(&client).count();
+        assert_eq!(mock::last_find(&client), Some((table.to_string(), None)));
+    }
+
+    #[test]
+    fn test_trend_query_uses_default_cluster() {
+        let client = CtClient::open_mock_with(MockServer::new()).unwrap();
+        client.set_default_cluster(Some("Cluster1"));
+        let query =
+            TrendQuery::new("Flow1", at(5000, 500), Duration::from_secs(10), 3).data_mode(1);
+
+        assert_eq!(query.find(&client).count(), 0);
+        assert_eq!(
+            mock::last_find(&client),
+            Some((
+                "TRNQUERY,5000,500,10,3,Cluster1.Flow1,0,1".to_string(),
+                None
+            ))
+        );
+
+        let query = query.cluster_style(ClusterStyle::FindArgument);
+        assert_eq!(query.find(&client).count(), 0);
+        assert_eq!(
+            mock::last_find(&client),
+            Some((
+                "TRNQUERY,5000,500,10,3,Flow1,0,1".to_string(),
+                Some("Cluster1".to_string())
+            ))
+        );
+
+        let query = query.cluster("Cluster2");
+        query.find(&client).count();
+        assert_eq!(
+            mock::last_find(&client).unwrap().1.as_deref(),
+            Some("Cluster2")
+        );
+    }
+
+    #[test]
+    fn test_no_cluster() {
+        let client = CtClient::open_mock_with(MockServer::new()).unwrap();
+        let query = TrendQuery::new("Flow1", at(1, 0), Duration::from_millis(500), 2);
+        query.find(&client).count();
+        assert_eq!(
+            mock::last_find(&client),
+            Some(("TRNQUERY,1,0,0.5,2,Flow1,0,0".to_string(), None))
+        );
+    }
+}
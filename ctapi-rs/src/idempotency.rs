@@ -0,0 +1,150 @@
+//! Which operations are safe to repeat after an ambiguous failure
+//!
+//! A call that fails because the connection dropped while the request was
+//! in flight may or may not have been carried out by the server. Repeating
+//! a read is harmless, but repeating a Cicode command that toggles a pump
+//! may undo the first one. [`ReconnectGate`](crate::ReconnectGate) therefore
+//! only repeats such a call when it is marked [`Idempotency::Idempotent`];
+//! otherwise it fails with [`CtApiError::AmbiguousOutcome`] and leaves the
+//! decision to the caller.
+//!
+//! Failures where the request never reached the server, such as a refused
+//! connection or a server that is not running, are not ambiguous and are
+//! retried for every operation.
+//!
+//! # Classification
+//!
+//! | Operation | Class | Reason |
+//! |-----------|-------|--------|
+//! | [`Open`](Operation::Open) | Idempotent | Opens a new session |
+//! | [`TagRead`](Operation::TagRead) | Idempotent | No side effects |
+//! | [`ListRead`](Operation::ListRead) | Idempotent | No side effects |
+//! | [`Find`](Operation::Find) | Idempotent | No side effects |
+//! | [`TagWrite`](Operation::TagWrite) | Idempotent | Writes an absolute value |
+//! | [`ListWrite`](Operation::ListWrite) | Idempotent | Writes an absolute value |
+//! | [`Cicode`](Operation::Cicode) | Non-idempotent | May have any side effect |
+//! | [`Toggle`](Operation::Toggle) | Non-idempotent | Result depends on the current value |
+//!
+//! A write whose value is derived from the value read before it, such as an
+//! increment, is a toggle in this sense. Calls that are not classified are
+//! treated as non-idempotent.
+
+use crate::error::CtApiError;
+
+/// Win32 error codes meaning the connection dropped while a call was in flight
+const AMBIGUOUS_ERRORS: &[i32] = &[
+    64,   // ERROR_NETNAME_DELETED
+    109,  // ERROR_BROKEN_PIPE
+    232,  // ERROR_NO_DATA (pipe closing)
+    1236, // ERROR_CONNECTION_ABORTED
+    1726, // RPC_S_CALL_FAILED
+];
+
+/// Whether an operation may be repeated without changing its effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Idempotency {
+    /// Repeating the operation has the same effect as running it once
+    Idempotent,
+    /// Repeating the operation may apply its effect twice
+    #[default]
+    NonIdempotent,
+}
+
+impl Idempotency {
+    /// Whether the operation may be repeated after an ambiguous failure
+    pub const fn is_idempotent(self) -> bool {
+        matches!(self, Idempotency::Idempotent)
+    }
+}
+
+/// Operations of the crate, for looking up their [`Idempotency`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `ctOpen`
+    Open,
+    /// Single tag read, including `tag_read_ex` and quality reads
+    TagRead,
+    /// List read and the values read from the list
+    ListRead,
+    /// Find queries and their iteration
+    Find,
+    /// Single tag write of an absolute value
+    TagWrite,
+    /// List write of an absolute value
+    ListWrite,
+    /// Cicode function call
+    Cicode,
+    /// Write derived from the current value, such as a toggle or increment
+    Toggle,
+}
+
+impl Operation {
+    /// Canonical idempotency class of the operation
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{Idempotency, Operation};
+    ///
+    /// assert_eq!(Operation::TagWrite.idempotency(), Idempotency::Idempotent);
+    /// assert_eq!(Operation::Cicode.idempotency(), Idempotency::NonIdempotent);
+    /// ```
+    pub const fn idempotency(self) -> Idempotency {
+        match self {
+            Operation::Open
+            | Operation::TagRead
+            | Operation::ListRead
+            | Operation::Find
+            | Operation::TagWrite
+            | Operation::ListWrite => Idempotency::Idempotent,
+            Operation::Cicode | Operation::Toggle => Idempotency::NonIdempotent,
+        }
+    }
+}
+
+impl From<Operation> for Idempotency {
+    fn from(operation: Operation) -> Self {
+        operation.idempotency()
+    }
+}
+
+/// Whether `error` leaves it unknown if the server carried out the call
+///
+/// True for timeouts and for connections dropped mid-call. A refused or
+/// missing connection is not ambiguous: the request never reached the server.
+pub fn is_ambiguous_failure(error: &CtApiError) -> bool {
+    match error.root() {
+        CtApiError::System(e, ..) => e
+            .raw_os_error()
+            .is_some_and(|code| AMBIGUOUS_ERRORS.contains(&code)),
+        CtApiError::Timeout => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_ambiguous_failures() {
+        let os = |code| CtApiError::from(io::Error::from_raw_os_error(code));
+        assert!(is_ambiguous_failure(&os(1236)));
+        assert!(is_ambiguous_failure(
+            &CtApiError::Timeout.context("writing Pump")
+        ));
+        // RPC_S_SERVER_UNAVAILABLE, ERROR_CONNECTION_REFUSED
+        assert!(!is_ambiguous_failure(&os(1722)));
+        assert!(!is_ambiguous_failure(&os(1225)));
+        assert!(!is_ambiguous_failure(&CtApiError::ConnectionFailed {
+            message: "refused".into()
+        }));
+    }
+
+    #[test]
+    fn test_default_is_conservative() {
+        assert_eq!(Idempotency::default(), Idempotency::NonIdempotent);
+        assert!(Idempotency::from(Operation::ListRead).is_idempotent());
+        assert!(!Idempotency::from(Operation::Toggle).is_idempotent());
+    }
+}
@@ -0,0 +1,247 @@
+//! String interning for materialised query results
+//!
+//! Tag and alarm tables repeat the same few strings on thousands of rows:
+//! the engineering unit, the cluster name, every field name. A
+//! [`StringInterner`] hands out one shared allocation per distinct string so
+//! that a large export holds each of them once. [`SharedStr`] is the
+//! resulting value type: it derefs to `str` and compares, hashes and orders
+//! exactly like the string it holds.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Default maximum number of distinct strings a [`StringInterner`] keeps
+pub const DEFAULT_INTERN_CAPACITY: usize = 4096;
+
+/// Immutable, cheaply cloneable string
+///
+/// Clones share one allocation. Behaves like the `str` it holds for equality,
+/// hashing, ordering and formatting.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SharedStr(Arc<str>);
+
+impl SharedStr {
+    /// Borrow the string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether two values share one allocation
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Default for SharedStr {
+    fn default() -> Self {
+        Self::from("")
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for SharedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SharedStr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(s: &str) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(s: String) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<SharedStr> for String {
+    fn from(s: SharedStr) -> Self {
+        s.0.to_string()
+    }
+}
+
+impl PartialEq<str> for SharedStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SharedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for SharedStr {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+/// Deduplicates strings into shared [`SharedStr`] allocations
+///
+/// Keeps at most `max_unique` distinct strings. Once full, strings it has not
+/// seen yet are returned in their own allocation without being remembered,
+/// so a column of unique values (descriptions, timestamps) cannot grow the
+/// table without bound.
+#[derive(Debug)]
+pub struct StringInterner {
+    strings: HashSet<SharedStr>,
+    max_unique: usize,
+    passed_through: usize,
+}
+
+impl Default for StringInterner {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERN_CAPACITY)
+    }
+}
+
+impl StringInterner {
+    /// Create an interner remembering at most `max_unique` strings
+    pub fn new(max_unique: usize) -> Self {
+        Self {
+            strings: HashSet::new(),
+            max_unique,
+            passed_through: 0,
+        }
+    }
+
+    /// Shared copy of `s`
+    pub fn intern(&mut self, s: &str) -> SharedStr {
+        if let Some(shared) = self.strings.get(s) {
+            return shared.clone();
+        }
+        let shared = SharedStr::from(s);
+        if self.strings.len() < self.max_unique {
+            self.strings.insert(shared.clone());
+        } else {
+            self.passed_through += 1;
+        }
+        shared
+    }
+
+    /// Number of distinct strings held
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether no string is held
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Strings returned uninterned because the interner was full
+    pub fn passed_through(&self) -> usize {
+        self.passed_through
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_repeated_strings_share_allocation() {
+        let mut interner = StringInterner::default();
+        let a = interner.intern("°C");
+        let b = interner.intern("°C");
+        assert!(SharedStr::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+        assert!(!SharedStr::ptr_eq(&a, &interner.intern("bar")));
+    }
+
+    #[test]
+    fn test_cap_passes_through() {
+        let mut interner = StringInterner::new(2);
+        interner.intern("a");
+        interner.intern("b");
+        let c1 = interner.intern("c");
+        let c2 = interner.intern("c");
+        assert_eq!(c1, c2);
+        assert!(!SharedStr::ptr_eq(&c1, &c2));
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.passed_through(), 2);
+        // Strings seen before the cap was reached are still shared
+        assert!(SharedStr::ptr_eq(
+            &interner.intern("a"),
+            &interner.intern("a")
+        ));
+    }
+
+    #[test]
+    fn test_behaves_like_str() {
+        let shared = SharedStr::from("Pump1");
+        assert_eq!(shared, "Pump1");
+        assert_eq!(shared, "Pump1".to_string());
+        assert_eq!(shared.len(), 5);
+        assert_eq!(format!("{shared}"), "Pump1");
+        assert_eq!(format!("{shared:?}"), format!("{:?}", "Pump1"));
+        assert_eq!(String::from(shared.clone()), "Pump1");
+        let mut sorted = vec![SharedStr::from("b"), SharedStr::from("a")];
+        sorted.sort();
+        assert_eq!(sorted, ["a", "b"]);
+
+        #[cfg(all(feature = "serde", feature = "json"))]
+        assert_eq!(serde_json::to_string(&shared).unwrap(), r#""Pump1""#);
+
+        let mut map = HashMap::new();
+        map.insert(shared, 1);
+        assert_eq!(map.get("Pump1"), Some(&1));
+    }
+
+    #[test]
+    fn test_large_table_holds_each_value_once() {
+        let mut interner = StringInterner::default();
+        let rows: Vec<_> = (0..100_000)
+            .map(|i| {
+                let unit = ["°C", "bar", "m3/h", "%"][i % 4];
+                let cluster = interner.intern("Cluster1");
+                (interner.intern("UNIT"), interner.intern(unit), cluster)
+            })
+            .collect();
+        assert_eq!(interner.len(), 6);
+        let first = &rows[0];
+        assert!(rows.iter().all(|row| SharedStr::ptr_eq(&row.0, &first.0)));
+        assert!(rows.iter().all(|row| SharedStr::ptr_eq(&row.2, &first.2)));
+    }
+}
@@ -0,0 +1,7 @@
+//! Adapters for code ported from other CtAPI bindings
+//!
+//! Each submodule mirrors the surface of one family of vendor wrappers as
+//! thin functions over the regular API, so that ported code can be moved
+//! over first and made idiomatic later.
+
+pub mod dotnet;
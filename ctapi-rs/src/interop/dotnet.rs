@@ -0,0 +1,476 @@
+//! Porting aids for code written against the vendor's .NET CtAPI wrapper
+//!
+//! The .NET wrapper returns a `VariantQuality` from its `TagReadEx`
+//! overloads, reports failures as HRESULTs and names list modes with
+//! enums. This module provides the same shapes over the regular API:
+//!
+//! | .NET | ctapi-rs |
+//! |------|----------|
+//! | `TagReadEx(tag)` returning `VariantQuality` | [`tag_read_ex_compat`] returning [`VariantQuality`] |
+//! | `HResult` of a thrown exception | [`CtApiError::to_hresult`] |
+//! | `Marshal.GetExceptionForHR` | [`CtApiError::from_hresult`] |
+//! | list mode and list data enums | [`list_mode`], [`list_data`], [`list_event`] |
+//!
+//! # HRESULT Mapping
+//!
+//! System errors carry their Win32 error code as `HRESULT_FROM_WIN32`, or
+//! their HRESULT unchanged if the operating system reported one. The other
+//! errors use customer-defined facilities, so they never collide with
+//! Windows HRESULTs:
+//!
+//! | HRESULT | Error |
+//! |---------|-------|
+//! | `0x8007xxxx` | [`System`](CtApiError::System) with Win32 error `xxxx` |
+//! | `0xA100xxxx` | error with [code](CtApiError::code) `xxxx`, see the [registry](crate::error#error-codes) |
+//! | `0xA101xxxx` | [`Citect`](CtApiError::Citect) or [`Other`](CtApiError::Other) with Citect error code `xxxx`, read back as `Other` |
+//!
+//! Converting an error to an HRESULT and back keeps its code for Win32 and
+//! Citect error codes up to `0xFFFF` and every code of the registry. Only
+//! the code survives: the fields of a reconstructed error are empty.
+
+use crate::client::CtClient;
+use crate::error::{CtApiError, Result};
+use crate::quality::{CitectError, Quality};
+use crate::tag_path::TagNameError;
+use ctapi_sys::CtTagValueItems;
+use std::ffi::{CStr, CString};
+use std::io;
+
+/// Facility of HRESULTs wrapping a Win32 error code
+pub const FACILITY_WIN32: u32 = 0x007;
+/// Customer facility of HRESULTs carrying a ctapi-rs error code
+pub const FACILITY_CTAPI: u32 = 0x100;
+/// Customer facility of HRESULTs carrying a Citect error code
+pub const FACILITY_CITECT: u32 = 0x101;
+
+/// `E_FAIL`, reported for system errors without an operating system code
+pub const E_FAIL: i32 = 0x8000_4005_u32 as i32;
+
+const SEVERITY_ERROR: u32 = 0x8000_0000;
+const CUSTOMER: u32 = 0x2000_0000;
+
+/// Failure HRESULT of `code` in `facility`
+const fn make_hresult(customer: bool, facility: u32, code: u32) -> i32 {
+    let customer = if customer { CUSTOMER } else { 0 };
+    (SEVERITY_ERROR | customer | ((facility & 0x7FF) << 16) | (code & 0xFFFF)) as i32
+}
+
+/// `HRESULT_FROM_WIN32`: HRESULTs and `0` are returned unchanged
+pub const fn hresult_from_win32(code: i32) -> i32 {
+    if code <= 0 {
+        code
+    } else {
+        make_hresult(false, FACILITY_WIN32, code as u32)
+    }
+}
+
+impl CtApiError {
+    /// HRESULT the .NET wrapper would report for this error
+    ///
+    /// See the [module documentation](crate::interop::dotnet#hresult-mapping)
+    /// for the mapping. [`Context`](CtApiError::Context) layers report the
+    /// HRESULT of the error they wrap.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtApiError;
+    ///
+    /// assert_eq!(CtApiError::Timeout.to_hresult() as u32, 0xA100_0FA1);
+    /// let not_found = CtApiError::from(std::io::Error::from_raw_os_error(1168));
+    /// assert_eq!(not_found.to_hresult() as u32, 0x8007_0490);
+    /// ```
+    pub fn to_hresult(&self) -> i32 {
+        match self.root() {
+            CtApiError::System(error, ..) => {
+                error.raw_os_error().map_or(E_FAIL, hresult_from_win32)
+            }
+            CtApiError::Citect { code, .. } | CtApiError::Other { code, .. } => {
+                make_hresult(true, FACILITY_CITECT, *code)
+            }
+            error => make_hresult(true, FACILITY_CTAPI, error.code()),
+        }
+    }
+
+    /// Error for an HRESULT reported by the .NET wrapper, `None` on success
+    ///
+    /// HRESULTs of other facilities become [`System`](CtApiError::System)
+    /// errors holding the HRESULT.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtApiError;
+    ///
+    /// let error = CtApiError::from_hresult(0xA100_0FA1_u32 as i32).unwrap();
+    /// assert!(matches!(error, CtApiError::Timeout));
+    /// assert!(CtApiError::from_hresult(0).is_none());
+    /// ```
+    pub fn from_hresult(hresult: i32) -> Option<CtApiError> {
+        if hresult >= 0 {
+            return None;
+        }
+        let bits = hresult as u32;
+        let customer = bits & CUSTOMER != 0;
+        let facility = (bits >> 16) & 0x7FF;
+        let code = bits & 0xFFFF;
+        let error = match (customer, facility) {
+            (true, FACILITY_CTAPI) => from_code(code),
+            (true, FACILITY_CITECT) => Some(CtApiError::from_error_code(code)),
+            (false, FACILITY_WIN32) => Some(io::Error::from_raw_os_error(code as i32).into()),
+            _ => None,
+        };
+        Some(error.unwrap_or_else(|| io::Error::from_raw_os_error(hresult).into()))
+    }
+}
+
+/// Error with the registry `code` and empty fields
+fn from_code(code: u32) -> Option<CtApiError> {
+    let empty = String::new;
+    let error = match code {
+        1001 => CStr::from_bytes_until_nul(b"").unwrap_err().into(),
+        1002 => CString::new("\0").unwrap_err().into(),
+        1003 => CtApiError::Encoding { text: empty() },
+        1004 => CtApiError::TextTooLong {
+            field: empty(),
+            len: 0,
+            max: 0,
+        },
+        1005 => CtApiError::Truncated { needed: 0 },
+        2001 => CtApiError::ConnectionFailed { message: empty() },
+        2002 => CtApiError::GlobalNotInitialized,
+        2003 => CtApiError::GlobalAlreadyInitialized,
+        2004 => CtApiError::VersionSkew {
+            client: empty(),
+            server: empty(),
+        },
+        2005 => CtApiError::AmbiguousOutcome {
+            source: Box::new(CtApiError::ConnectionFailed { message: empty() }),
+        },
+        2006 => CtApiError::SessionClosed,
+        2007 => CtApiError::ConnectionLost { generation: 0 },
+        2008 => CtApiError::TooManyPendingOps { limit: 0 },
+        2009 => CtApiError::InvalidHandle,
+        3001 => CtApiError::TagNotFound { tag: empty() },
+        3002 => CtApiError::InvalidParameter {
+            param: empty(),
+            value: empty(),
+        },
+        3003 => CtApiError::CursorExpired { position: 0 },
+        3004 => CtApiError::StaleFindObject,
+        3005 => CtApiError::UnexpectedCicodeResult {
+            function: empty(),
+            result: empty(),
+        },
+        3006 => CtApiError::UnsupportedOperation { operation: empty() },
+        3007 => CtApiError::BadQuality {
+            tag: empty(),
+            quality: Quality::default(),
+        },
+        3008 => CtApiError::WriteNotVerified {
+            tag: empty(),
+            expected: empty(),
+            actual: empty(),
+        },
+        3009 => CtApiError::CannotCancel,
+        3010 => CtApiError::WriteNotPermitted { reason: empty() },
+        3011 => CtApiError::InvalidPageToken { reason: empty() },
+        3012 => CtApiError::ParseError {
+            tag: empty(),
+            raw: empty(),
+            target_type: empty(),
+        },
+        3013 => CtApiError::InvalidTagName {
+            name: empty(),
+            reason: TagNameError::Empty,
+        },
+        3014 => CtApiError::DuplicateTag { tag: empty() },
+        4001 => CtApiError::Timeout,
+        _ => return None,
+    };
+    Some(error)
+}
+
+/// Result of a `TagReadEx` call, shaped like the .NET `VariantQuality`
+///
+/// Timestamps are FILETIME values as reported by CtAPI.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VariantQuality {
+    /// Value as returned by CtAPI
+    pub value: String,
+    /// Timestamp of the reading
+    pub timestamp: u64,
+    /// Timestamp of the value
+    pub value_timestamp: u64,
+    /// Timestamp of the quality
+    pub quality_timestamp: u64,
+    /// General quality
+    pub quality_general: u8,
+    /// Quality substatus
+    pub quality_substatus: u8,
+    /// Quality limit
+    pub quality_limit: u8,
+    /// Extended quality substatus
+    pub quality_extended_substatus: u8,
+    /// Error reported by the data source, `0` for none
+    pub quality_datasource_error: u32,
+    /// Whether the value is overridden
+    pub quality_override: bool,
+    /// Whether the tag is in control mode
+    pub quality_control_mode: bool,
+}
+
+impl VariantQuality {
+    /// Combine a value with the metadata returned by `ctTagReadEx`
+    pub fn from_items(value: String, items: &CtTagValueItems) -> Self {
+        Self {
+            value,
+            timestamp: items.timestamp,
+            value_timestamp: items.value_timestamp,
+            quality_timestamp: items.quality_timestamp,
+            quality_general: items.quality_general,
+            quality_substatus: items.quality_substatus,
+            quality_limit: items.quality_limit,
+            quality_extended_substatus: items.quality_extended_substatus,
+            quality_datasource_error: items.quality_datasource_error,
+            quality_override: items.boverride,
+            quality_control_mode: items.control_mode,
+        }
+    }
+
+    /// Quality of the value
+    pub fn quality(&self) -> Quality {
+        Quality {
+            general: self.quality_general,
+            substatus: self.quality_substatus,
+            limit: self.quality_limit,
+            extended_substatus: self.quality_extended_substatus,
+            datasource_error: CitectError::from_code(self.quality_datasource_error),
+            timestamp: self.quality_timestamp,
+            overridden: self.quality_override,
+            control_mode: self.quality_control_mode,
+        }
+    }
+
+    /// Object with the property names the .NET wrapper serializes
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::interop::dotnet::VariantQuality;
+    ///
+    /// let reading = VariantQuality {
+    ///     value: "1.2".to_string(),
+    ///     quality_general: 3,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(reading.to_json()["QualityGeneral"], 3);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let fields: [(&str, serde_json::Value); 11] = [
+            ("Value", self.value.as_str().into()),
+            ("Timestamp", self.timestamp.into()),
+            ("ValueTimestamp", self.value_timestamp.into()),
+            ("QualityTimestamp", self.quality_timestamp.into()),
+            ("QualityGeneral", self.quality_general.into()),
+            ("QualitySubstatus", self.quality_substatus.into()),
+            ("QualityLimit", self.quality_limit.into()),
+            (
+                "QualityExtendedSubstatus",
+                self.quality_extended_substatus.into(),
+            ),
+            (
+                "QualityDatasourceError",
+                self.quality_datasource_error.into(),
+            ),
+            ("QualityOverride", self.quality_override.into()),
+            ("QualityControlMode", self.quality_control_mode.into()),
+        ];
+        let mut object = serde_json::Map::new();
+        for (name, value) in fields {
+            object.insert(name.to_string(), value);
+        }
+        serde_json::Value::Object(object)
+    }
+}
+
+/// Read a tag like the .NET `TagReadEx` overload returning `VariantQuality`
+///
+/// Calls [`CtClient::tag_read_ex`] with the client's negotiated
+/// [`tag_value_items`](CtClient::tag_value_items).
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::interop::dotnet::tag_read_ex_compat;
+///
+/// let client = CtClient::open_mock()?;
+/// let reading = tag_read_ex_compat(&client, "Pressure")?;
+/// assert_eq!(reading.value, "1.2");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn tag_read_ex_compat<T: AsRef<str>>(client: &CtClient, tag: T) -> Result<VariantQuality> {
+    let mut items = client.tag_value_items();
+    let value = client.tag_read_ex(tag, &mut items)?;
+    Ok(VariantQuality::from_items(value, &items))
+}
+
+/// List modes under the names of the .NET enum members
+pub mod list_mode {
+    use crate::constants::*;
+
+    /// `Event`, [`CT_LIST_EVENT`]
+    pub const EVENT: u32 = CT_LIST_EVENT;
+    /// `LightweightMode`, [`CT_LIST_LIGHTWEIGHT_MODE`]
+    pub const LIGHTWEIGHT_MODE: u32 = CT_LIST_LIGHTWEIGHT_MODE;
+}
+
+/// List event flags under the names of the .NET enum members
+pub mod list_event {
+    use crate::constants::*;
+
+    /// `New`, [`CT_LIST_EVENT_NEW`]
+    pub const NEW: u32 = CT_LIST_EVENT_NEW;
+    /// `Status`, [`CT_LIST_EVENT_STATUS`]
+    pub const STATUS: u32 = CT_LIST_EVENT_STATUS;
+}
+
+/// List data items under the names of the .NET enum members
+pub mod list_data {
+    use crate::constants::*;
+
+    /// `Value`, [`CT_LIST_VALUE`]
+    pub const VALUE: u32 = CT_LIST_VALUE;
+    /// `Timestamp`, [`CT_LIST_TIMESTAMP`]
+    pub const TIMESTAMP: u32 = CT_LIST_TIMESTAMP;
+    /// `ValueTimestamp`, [`CT_LIST_VALUE_TIMESTAMP`]
+    pub const VALUE_TIMESTAMP: u32 = CT_LIST_VALUE_TIMESTAMP;
+    /// `QualityTimestamp`, [`CT_LIST_QUALITY_TIMESTAMP`]
+    pub const QUALITY_TIMESTAMP: u32 = CT_LIST_QUALITY_TIMESTAMP;
+    /// `QualityGeneral`, [`CT_LIST_QUALITY_GENERAL`]
+    pub const QUALITY_GENERAL: u32 = CT_LIST_QUALITY_GENERAL;
+    /// `QualitySubstatus`, [`CT_LIST_QUALITY_SUBSTATUS`]
+    pub const QUALITY_SUBSTATUS: u32 = CT_LIST_QUALITY_SUBSTATUS;
+    /// `QualityLimit`, [`CT_LIST_QUALITY_LIMIT`]
+    pub const QUALITY_LIMIT: u32 = CT_LIST_QUALITY_LIMIT;
+    /// `QualityExtendedSubstatus`, [`CT_LIST_QUALITY_EXTENDED_SUBSTATUS`]
+    pub const QUALITY_EXTENDED_SUBSTATUS: u32 = CT_LIST_QUALITY_EXTENDED_SUBSTATUS;
+    /// `QualityDatasourceError`, [`CT_LIST_QUALITY_DATASOURCE_ERROR`]
+    pub const QUALITY_DATASOURCE_ERROR: u32 = CT_LIST_QUALITY_DATASOURCE_ERROR;
+    /// `QualityOverride`, [`CT_LIST_QUALITY_OVERRIDE`]
+    pub const QUALITY_OVERRIDE: u32 = CT_LIST_QUALITY_OVERRIDE;
+    /// `QualityControlMode`, [`CT_LIST_QUALITY_CONTROL_MODE`]
+    pub const QUALITY_CONTROL_MODE: u32 = CT_LIST_QUALITY_CONTROL_MODE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Codes of the error registry
+    const CODES: &[u32] = &[
+        1001, 1002, 1003, 1004, 1005, 2001, 2002, 2003, 2004, 2005, 2006, 2007, 2008, 2009, 3001,
+        3002, 3003, 3004, 3005, 3006, 3007, 3008, 3009, 3010, 3011, 3012, 3013, 3014, 4001,
+    ];
+
+    fn round_trip(error: &CtApiError) -> CtApiError {
+        CtApiError::from_hresult(error.to_hresult()).unwrap()
+    }
+
+    #[test]
+    fn test_registry_codes_round_trip() {
+        for &code in CODES {
+            let error = from_code(code).unwrap();
+            assert_eq!(error.code(), code);
+            assert_eq!(round_trip(&error).code(), code);
+            assert_eq!(error.to_hresult() as u32, 0xA100_0000 | code);
+        }
+        let wrapped = CtApiError::Timeout.context("reading Temperature");
+        assert_eq!(wrapped.to_hresult(), CtApiError::Timeout.to_hresult());
+    }
+
+    #[test]
+    fn test_citect_and_win32_codes_round_trip() {
+        for code in [0, 1, 256, 999, 1000, 0xFFFF] {
+            let error = CtApiError::from_error_code(code);
+            assert_eq!(round_trip(&error).code(), error.code());
+        }
+        for code in [1, 2, 87, 1168, 0xFFFF] {
+            let error = CtApiError::from(io::Error::from_raw_os_error(code));
+            assert_eq!(error.to_hresult(), hresult_from_win32(code));
+            let CtApiError::System(back, ..) = round_trip(&error) else {
+                panic!("not a system error");
+            };
+            assert_eq!(back.raw_os_error(), Some(code));
+        }
+    }
+
+    #[test]
+    fn test_foreign_hresults_are_kept() {
+        // E_ACCESSDENIED and an unused customer facility
+        for hresult in [
+            0x8007_0005_u32 as i32,
+            0x8000_FFFF_u32 as i32,
+            0xA200_0001_u32 as i32,
+        ] {
+            let error = CtApiError::from_hresult(hresult).unwrap();
+            assert_eq!(error.to_hresult(), hresult);
+        }
+        let error = CtApiError::from(io::Error::other("no code"));
+        assert_eq!(error.to_hresult(), E_FAIL);
+        assert!(CtApiError::from_hresult(1).is_none());
+        assert!(from_code(5001).is_none());
+    }
+
+    #[test]
+    fn test_list_aliases() {
+        assert_eq!(list_mode::EVENT | list_mode::LIGHTWEIGHT_MODE, 3);
+        assert_eq!(list_data::QUALITY_CONTROL_MODE, 0x0B);
+        assert_eq!(list_event::STATUS, crate::constants::CT_LIST_EVENT_STATUS);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_variant_quality_json_shape() {
+        let items = CtTagValueItems {
+            timestamp: 133_500_000_000_000_000,
+            value_timestamp: 133_500_000_000_000_001,
+            quality_timestamp: 133_500_000_000_000_002,
+            quality_general: 3,
+            quality_substatus: 1,
+            quality_datasource_error: 7,
+            boverride: true,
+            ..CtTagValueItems::default()
+        };
+        let reading = VariantQuality::from_items("泵已启动".to_string(), &items);
+        assert_eq!(reading.quality().datasource_error.unwrap().code(), 7);
+        // As serialized by System.Text.Json from the .NET wrapper
+        let dotnet = r#"{
+            "Value": "泵已启动",
+            "Timestamp": 133500000000000000,
+            "ValueTimestamp": 133500000000000001,
+            "QualityTimestamp": 133500000000000002,
+            "QualityGeneral": 3,
+            "QualitySubstatus": 1,
+            "QualityLimit": 0,
+            "QualityExtendedSubstatus": 0,
+            "QualityDatasourceError": 7,
+            "QualityOverride": true,
+            "QualityControlMode": false
+        }"#;
+        let expected: serde_json::Value = serde_json::from_str(dotnet).unwrap();
+        assert_eq!(reading.to_json(), expected);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_tag_read_ex_compat() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::seeded().with_tag_quality("Pressure", 1, 4);
+        let client = CtClient::open_mock_with(server).unwrap();
+        let reading = tag_read_ex_compat(&client, "Pressure").unwrap();
+        assert_eq!(reading.value, "1.2");
+        assert_eq!(reading.quality_general, 1);
+        assert_eq!(reading.quality_datasource_error, 4);
+        assert!(tag_read_ex_compat(&client, "Missing").is_err());
+    }
+}
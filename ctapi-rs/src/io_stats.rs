@@ -0,0 +1,263 @@
+//! Per-client I/O accounting
+//!
+//! Every [`CtClient`](crate::CtClient) counts the CtAPI calls it makes and
+//! roughly how many bytes each one moves, so that remote links can be sized
+//! from real traffic. Counters are shared with clones of the client and can
+//! be read with [`io_stats`](crate::CtClient::io_stats) and cleared with
+//! [`reset_io_stats`](crate::CtClient::reset_io_stats).
+//!
+//! Sizes are payload sizes, not protocol frames:
+//!
+//! - the request size is the GBK-encoded length of the tag names, values,
+//!   Cicode commands, tables and filters sent, without terminating NULs
+//! - the response size is the length of the returned string, the length
+//!   CtAPI reports for a found object's property, or the size of the buffer
+//!   filled with a tag property
+//!
+//! Failed calls are counted with an empty response. Tag lists and the
+//! asynchronous operations in [`async_ops`](crate::async_ops) are not counted.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Category of a counted call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoKind {
+    /// Tag and tag property reads
+    Read,
+    /// Tag writes
+    Write,
+    /// Cicode calls
+    Cicode,
+    /// Search calls: `ctFindFirst`, `ctFindNext`, `ctFindScroll` and
+    /// property reads on found objects
+    Find,
+}
+
+impl IoKind {
+    const ALL: [IoKind; 4] = [IoKind::Read, IoKind::Write, IoKind::Cicode, IoKind::Find];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Totals for one [`IoKind`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoCounts {
+    /// Number of calls
+    pub calls: u64,
+    /// Bytes sent
+    pub request_bytes: u64,
+    /// Bytes received
+    pub response_bytes: u64,
+}
+
+impl std::ops::Add for IoCounts {
+    type Output = IoCounts;
+
+    fn add(self, other: IoCounts) -> IoCounts {
+        IoCounts {
+            calls: self.calls + other.calls,
+            request_bytes: self.request_bytes + other.request_bytes,
+            response_bytes: self.response_bytes + other.response_bytes,
+        }
+    }
+}
+
+/// Snapshot of a client's I/O counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Tag and tag property reads
+    pub reads: IoCounts,
+    /// Tag writes
+    pub writes: IoCounts,
+    /// Cicode calls
+    pub cicode: IoCounts,
+    /// Search calls
+    pub find: IoCounts,
+}
+
+impl IoStats {
+    /// Counts for one category
+    pub fn get(&self, kind: IoKind) -> IoCounts {
+        match kind {
+            IoKind::Read => self.reads,
+            IoKind::Write => self.writes,
+            IoKind::Cicode => self.cicode,
+            IoKind::Find => self.find,
+        }
+    }
+
+    /// Sum over all categories
+    pub fn total(&self) -> IoCounts {
+        self.reads + self.writes + self.cicode + self.find
+    }
+}
+
+/// One counted call, as passed to [`CtClient::on_io`](crate::CtClient::on_io)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoEvent {
+    /// Category of the call
+    pub kind: IoKind,
+    /// Bytes sent
+    pub request_bytes: u64,
+    /// Bytes received
+    pub response_bytes: u64,
+}
+
+/// Callback invoked for every counted call
+pub type IoCallback = Arc<dyn Fn(&IoEvent) + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct Counter {
+    calls: AtomicU64,
+    request_bytes: AtomicU64,
+    response_bytes: AtomicU64,
+}
+
+impl Counter {
+    fn load(&self) -> IoCounts {
+        IoCounts {
+            calls: self.calls.load(Ordering::Relaxed),
+            request_bytes: self.request_bytes.load(Ordering::Relaxed),
+            response_bytes: self.response_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.request_bytes.store(0, Ordering::Relaxed);
+        self.response_bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Counters of one client plus the callbacks fed from them
+#[derive(Default)]
+pub(crate) struct IoCounters {
+    counters: [Counter; 4],
+    callbacks: Mutex<Vec<IoCallback>>,
+}
+
+impl std::fmt::Debug for IoCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoCounters")
+            .field("stats", &self.snapshot())
+            .finish_non_exhaustive()
+    }
+}
+
+impl IoCounters {
+    /// Count one call of `kind`
+    pub(crate) fn record(&self, kind: IoKind, request_bytes: usize, response_bytes: usize) {
+        let event = IoEvent {
+            kind,
+            request_bytes: request_bytes as u64,
+            response_bytes: response_bytes as u64,
+        };
+        let counter = &self.counters[kind.index()];
+        counter.calls.fetch_add(1, Ordering::Relaxed);
+        counter
+            .request_bytes
+            .fetch_add(event.request_bytes, Ordering::Relaxed);
+        counter
+            .response_bytes
+            .fetch_add(event.response_bytes, Ordering::Relaxed);
+
+        let callbacks = {
+            let callbacks = self.callbacks.lock().unwrap_or_else(|e| e.into_inner());
+            if callbacks.is_empty() {
+                return;
+            }
+            callbacks.clone()
+        };
+        // Callers read the OS error of the counted call after recording it
+        let last_error = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        for callback in callbacks {
+            callback(&event);
+        }
+        // SAFETY: SetLastError only stores the code for the calling thread.
+        unsafe { windows_sys::Win32::Foundation::SetLastError(last_error as u32) };
+    }
+
+    /// Current totals
+    pub(crate) fn snapshot(&self) -> IoStats {
+        let [reads, writes, cicode, find] =
+            IoKind::ALL.map(|kind| self.counters[kind.index()].load());
+        IoStats {
+            reads,
+            writes,
+            cicode,
+            find,
+        }
+    }
+
+    /// Zero all counters
+    pub(crate) fn reset(&self) {
+        for counter in &self.counters {
+            counter.reset();
+        }
+    }
+
+    /// Register a callback for future calls
+    pub(crate) fn on_io(&self, callback: IoCallback) {
+        self.callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_snapshot_and_reset() {
+        let counters = IoCounters::default();
+        counters.record(IoKind::Read, 11, 4);
+        counters.record(IoKind::Read, 8, 3);
+        counters.record(IoKind::Cicode, 7, 8);
+
+        let stats = counters.snapshot();
+        assert_eq!(
+            stats.reads,
+            IoCounts {
+                calls: 2,
+                request_bytes: 19,
+                response_bytes: 7,
+            }
+        );
+        assert_eq!(stats.get(IoKind::Cicode).response_bytes, 8);
+        assert_eq!(stats.writes, IoCounts::default());
+        assert_eq!(stats.total().request_bytes, 26);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), IoStats::default());
+    }
+
+    #[test]
+    fn test_callbacks_see_every_call() {
+        let counters = IoCounters::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        counters.on_io(Arc::new(move |event: &IoEvent| {
+            sink.lock().unwrap().push(*event)
+        }));
+
+        counters.record(IoKind::Write, 12, 0);
+        counters.record(IoKind::Find, 10, 0);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(
+            seen[0],
+            IoEvent {
+                kind: IoKind::Write,
+                request_bytes: 12,
+                response_bytes: 0,
+            }
+        );
+        assert_eq!(seen[1].kind, IoKind::Find);
+    }
+}
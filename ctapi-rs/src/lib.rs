@@ -4,40 +4,177 @@
 //!
 //! This module provides a safe Rust interface for interacting with Citect SCADA system CtAPI.
 //! Main features include:
-//! - Client connection management
-//! - Tag read/write operations
-//! - Object search and property retrieval
-//! - Tag list management
+//! - Client connection management, with credentials read from the
+//!   environment or prompted for (`cli` feature)
+//! - Typed server details: version, project, clusters and logged-in user
+//! - Tag read/write operations, including array elements and element fields
+//! - Object search and property retrieval, with shared cached queries and
+//!   stable pagination
+//! - Alarm and trend history queries
+//! - Tag list management, including staggered multi-list polling and
+//!   time-synchronized snapshots
 //! - Engineering units and raw value conversion
+//! - Polling tag watcher with bounded value history
+//! - Rollover-corrected totals and rates of counter tags
+//! - Pools of parallel connections to one server
+//! - Connection state notifications and reconnect gating, with pluggable
+//!   backoff strategies
+//! - Reads from redundant primary and standby servers, with failover, racing
+//!   or comparison of their answers
+//! - Change feed producing ready-to-publish MQTT messages
+//! - Per-client call and byte counters for capacity planning, and a ring
+//!   buffer of the most recent CtAPI calls for post-mortem analysis, also
+//!   reported as `tracing` events (`tracing` feature)
+//! - Crash-safe local audit journal
+//! - Write permits for commissioning windows, remote enable tags and operator
+//!   acknowledgement
 //! - Asynchronous operations with OVERLAPPED I/O
+//! - Optional C ABI for non-Rust hosts (`capi` feature)
+//! - Tag references checked at compile time (`macros` feature)
+//! - Porting aids for code written against the vendor's .NET wrapper
+//! - `CitectApi` trait for unit testing application code against an
+//!   in-memory client (`mock` feature)
 
+pub mod api;
 pub mod async_ops;
+pub mod audit;
+pub mod backoff;
+mod blocking;
+pub mod bridge;
+pub mod builder;
+pub mod call_log;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cicode;
+pub mod citect_code;
 pub mod client;
+mod clock;
 pub mod constants;
+pub mod credentials;
+#[cfg(feature = "demos")]
+pub mod demos;
+pub mod discovery;
 pub mod error;
+mod ffi;
+pub mod filetime;
+pub mod filter;
 pub mod find;
+pub mod global;
+pub mod history;
+pub mod idempotency;
+pub mod intern;
+pub mod interop;
+pub mod io_stats;
 pub mod list;
+pub mod metadata;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod paging;
+pub mod pending;
+pub mod permit;
+pub mod pool;
+pub mod property;
+pub mod quality;
+pub mod query;
+pub mod read_only;
+pub mod reconnect;
+pub mod redundant;
+pub mod retry;
 pub mod scaling;
+pub mod secret;
+pub mod server_info;
+pub mod snapshot;
+pub mod staggered;
+pub mod state;
+pub mod support;
+mod sync;
+pub mod tag_path;
+pub mod totalizer;
 mod util;
+pub mod version;
+pub mod watcher;
+pub mod write;
+pub mod write_outcome;
 
+#[cfg(feature = "tokio-support")]
+pub mod session;
 #[cfg(feature = "tokio-support")]
 pub mod tokio_async;
 
-pub use crate::async_ops::{AsyncCtClient, AsyncOperation, CtApiFuture, FutureCtClient};
-pub use crate::client::{ct_client_create, ct_client_destroy, CtClient};
+pub use crate::api::{CitectApi, CitectList};
+pub use crate::async_ops::{AsyncCtClient, AsyncOperation, CtApiFuture, FutureCtClient, Started};
+pub use crate::audit::{AuditRecord, AuditSink, JournalAuditSink};
+pub use crate::backoff::{Backoff, Decorrelated, Exponential, Fibonacci, Fixed};
+pub use crate::builder::CtClientBuilder;
+pub use crate::call_log::CallRecord;
+pub use crate::cicode::{CicodeCall, CicodeResult, CicodeWindow};
+pub use crate::citect_code::CitectCode;
+pub use crate::client::{
+    ConnectionInfo, CtClient, DEFAULT_READ_BUFFER_SIZE, ct_client_create, ct_client_destroy,
+};
 pub use crate::constants::*;
+pub use crate::credentials::{CredentialSource, EnvCredentials};
 pub use crate::error::CtApiError;
-pub use crate::find::{CtFind, FindObject};
-pub use crate::list::CtList;
-pub use crate::scaling::{ct_eng_to_raw, ct_raw_to_eng};
+pub use crate::find::{
+    ColumnInfo, CtFind, DEFAULT_FIND_PREFETCH, FindObject, FindRecords, ScrollOutcome,
+};
+pub use crate::history::{AlarmQuery, ClusterStyle, TrendQuery};
+pub use crate::idempotency::{Idempotency, Operation};
+pub use crate::intern::{SharedStr, StringInterner};
+pub use crate::io_stats::{IoCounts, IoEvent, IoKind, IoStats};
+pub use crate::list::{
+    CtList, Generation, ListEvent, ListEventKind, ListItemValue, ListReading, ListReadings,
+    ModeChangeReport, ResubscribeReport, TagEntry, TagOptions,
+};
+pub use crate::metadata::{MetadataCache, MetadataStats, TagMetadata};
+pub use crate::paging::{Page, PagedQuery};
+pub use crate::pending::{Overflow, PendingLimit};
+pub use crate::permit::{WritePermit, WriteRequest};
+pub use crate::pool::{CtClientPool, PooledClient};
+pub use crate::property::{ColumnKind, DbType, PropertyValue};
+pub use crate::quality::{
+    CitectError, Quality, QualityLimit, QualityPartition, QualityStatus, QualitySubstatus,
+    QualityThreshold, TagReading,
+};
+pub use crate::query::{QueryCache, QueryStats, Record};
+pub use crate::read_only::{ReadOnlyCtClient, ReadOnlyCtList};
+pub use crate::reconnect::{CallOptions, ReconnectGate};
+pub use crate::redundant::{ReadPolicy, RedundantReader, RedundantReading};
+pub use crate::retry::{OpenAttempt, RetryPolicy};
+pub use crate::scaling::{
+    ConversionReport, ct_eng_to_raw, ct_raw_to_eng, eng_to_raw_slice, raw_to_eng_slice,
+};
+pub use crate::secret::SecretString;
+pub use crate::server_info::ServerInfo;
+pub use crate::snapshot::{SnapshotValue, SyncSnapshot, snapshot_synchronized};
+pub use crate::staggered::{StaggeredList, TagSpec};
+pub use crate::state::{ConnectionState, ConnectionStatus, StateChange};
+pub use crate::tag_path::{AddressingForm, ReadItem, TagField, TagNameError, TagPath};
+pub use crate::totalizer::Totalizer;
+pub use crate::version::{CitectVersion, VersionInfo};
+pub use crate::watcher::{PollGap, PollStats, TagChange, TagWatcher, TimedValue, WatcherSnapshot};
+pub use crate::write::{
+    TagValue, TransactionOptions, TransactionReport, TransactionWrite, WriteStrategy,
+};
+pub use crate::write_outcome::WriteOutcome;
+
+#[cfg(feature = "mock")]
+pub use crate::api::{MockCitectClient, MockCitectList};
 
 #[cfg(feature = "tokio-support")]
-pub use crate::tokio_async::{TokioCtClient, TokioCtList};
+pub use crate::session::{DrainReport, TokioCtSession};
+#[cfg(feature = "tokio-support")]
+pub use crate::tokio_async::{TokioCtClient, TokioCtList, blocking_section};
+
+#[cfg(feature = "macros")]
+pub use ctapi_macros::{tag, tag_checked};
 
 // re-export commonly used types from ctapi_sys
+pub use ctapi_sys::CtApiVersion;
 pub use ctapi_sys::CtHScale;
 pub use ctapi_sys::CtScale;
 pub use ctapi_sys::CtTagValueItems;
+pub use ctapi_sys::DBTYPEENUM;
 
 #[cfg(test)]
 mod tests {
@@ -46,13 +183,17 @@ mod tests {
     use std::sync::Arc;
     use std::{thread::sleep, time::Duration};
 
-    /// Helper function to get connection parameters from environment variables
-    /// Returns None for each parameter if the corresponding environment variable is not set
+    /// Connection parameters from the `CTAPI_*` environment variables,
+    /// see [`EnvCredentials`]
     fn get_connection_params() -> (Option<String>, Option<String>, Option<String>) {
-        let computer = std::env::var("CITECT_COMPUTER").ok();
-        let user = std::env::var("CITECT_USER").ok();
-        let password = std::env::var("CITECT_PASSWORD").ok();
-        (computer, user, password)
+        let credentials = EnvCredentials::load().unwrap();
+        (
+            credentials.computer().map(str::to_string),
+            credentials.user().map(str::to_string),
+            credentials
+                .password()
+                .map(|password| password.expose().to_string()),
+        )
     }
 
     fn is_send<T: Send>(_t: T) {}
@@ -199,6 +340,42 @@ mod tests {
         // Arc<CtClient> is dropped here, after all threads finish and all CtFind objects are dropped
     }
 
+    /// Writes exercised under every [`WriteStrategy`]
+    fn write_suite(client: &CtClient) {
+        client.tag_write("BIT_1", 1).unwrap();
+        assert_eq!(client.tag_read("BIT_1").unwrap(), "1");
+        client.tag_write_str("BIT_1", "0").unwrap();
+        assert_eq!(client.tag_read("BIT_1").unwrap(), "0");
+        assert!(client.tag_write_str("NO_SUCH_TAG_XYZ", "1").is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn write_strategy_parity_test() {
+        let client = CtClient::open_mock().unwrap();
+        for strategy in [WriteStrategy::Blocking, WriteStrategy::Overlapped] {
+            client.set_write_strategy(strategy);
+            write_suite(&client);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn overlapped_write_timeout_test() {
+        let server = mock::MockServer::seeded().with_stalled_tag("BIT_1");
+        let client = CtClient::open_mock_with(server).unwrap();
+        client.set_write_strategy(WriteStrategy::Overlapped);
+        client.set_write_timeout(Some(Duration::ZERO));
+        // A zero timeout expires before the device can acknowledge the write.
+        let written = client.tag_write("BIT_1", 1);
+        assert!(matches!(written.unwrap_err().root(), CtApiError::Timeout));
+        assert_eq!(client.pending_ops(), 0);
+
+        // The blocking strategy ignores the timeout.
+        client.set_write_strategy(WriteStrategy::Blocking);
+        client.tag_write("BIT_1", 0).unwrap();
+    }
+
     #[test]
     #[ignore = "Requires actual Citect SCADA connection"]
     fn client_find_alarm_test() {
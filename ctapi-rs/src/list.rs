@@ -1,30 +1,700 @@
 //! Tag list operation related implementation
 use super::CtClient;
+use crate::call_log::JoinedSubject;
+use crate::client::{ERROR_IO_PENDING, clear_last_error};
+use crate::constants::{CT_LIST_EVENT, CT_LIST_EVENT_NEW};
 use crate::error::{CtApiError, Result};
-use ctapi_sys::*;
+use crate::ffi::*;
+use crate::filetime;
+use crate::quality::{CitectError, Quality, QualityPartition, QualityThreshold, TagReading};
+use crate::sync::{AtomicU64, Ordering, RwLock};
+use crate::tag_path::{AddressingForm, ReadItem, TagPath};
+use crate::util::{decode_gbk_until_nul, parse_value};
+use crate::write_outcome::{WriteOutcome, WriteTracker};
+use chrono::{DateTime, Utc};
 use encoding_rs::*;
-use std::collections::HashMap;
-use std::ffi::{CStr, CString};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::CString;
+use std::marker::PhantomData;
 use std::os::windows::io::RawHandle;
 use std::os::windows::raw::HANDLE;
-use std::sync::{Arc, RwLock};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 
 const NULL: HANDLE = 0 as HANDLE;
 
+/// Tags re-added per exclusive lock of the tag map by [`CtList::set_raw_mode`]
+const MODE_CHANGE_BATCH: usize = 64;
+
+/// Win32 `ERROR_NO_MORE_ITEMS`, reported by `ctListEvent` when no tag changed
+const ERROR_NO_MORE_ITEMS: i32 = 259;
+
 /// Opaque CtAPI tag/list handle, explicitly made [`Send`] + [`Sync`].
 ///
 /// # Safety
 ///
 /// The handle is an opaque identifier obtained from `ctListNew` / `ctListAdd`
 /// that is only ever passed back to CtAPI functions.  Concurrent access is
-/// controlled by the enclosing [`CtList`] through its `RwLock`, so no data
-/// race is possible.
+/// controlled by the enclosing [`CtList`] through its [`TagTable`], so no
+/// data race is possible.
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 struct ListHandle(RawHandle);
 unsafe impl Send for ListHandle {}
 unsafe impl Sync for ListHandle {}
 
+/// Polling options a tag was added with by [`CtList::add_tag_ex`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagOptions {
+    /// Whether the raw value is read instead of the engineering value
+    pub raw: bool,
+    /// Polling period in milliseconds
+    pub poll_period: i32,
+    /// Deadband in percent of the engineering range
+    pub deadband: f64,
+}
+
+impl Default for TagOptions {
+    /// Options of a tag added with [`CtList::add_tag`]: engineering value,
+    /// 500 ms polling period and no deadband
+    fn default() -> Self {
+        Self {
+            raw: false,
+            poll_period: 500,
+            deadband: 0.0,
+        }
+    }
+}
+
+/// Outcome of [`CtList::resubscribe`]
+#[derive(Debug, Default)]
+pub struct ResubscribeReport {
+    /// Tags added to the new list handle, in list order
+    pub readded: Vec<String>,
+    /// Tags that could not be added again, now removed from the list
+    pub dropped: Vec<(String, CtApiError)>,
+}
+
+/// Outcome of [`CtList::set_raw_mode`]
+#[derive(Debug, Default)]
+pub struct ModeChangeReport {
+    /// Tags re-added in the requested mode, in list order
+    pub changed: Vec<String>,
+    /// Tags that could not be re-added, still in their previous mode
+    pub failed: Vec<(String, CtApiError)>,
+}
+
+impl ModeChangeReport {
+    /// Whether every tag of the list is now in the requested mode
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Read of a list, numbered from 1 in the order the reads started
+///
+/// [`CtList::read_generation`] is the last read that completed, and each
+/// value of [`CtList::read_all_full`] carries the read it comes from. Reads
+/// that fail keep their number, so generations can skip numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Generation(u64);
+
+impl Generation {
+    /// No read: the list was not read yet, or the tag was not part of any
+    /// completed read
+    pub const NEVER: Generation = Generation(0);
+
+    /// Number of the read, 0 for [`NEVER`](Self::NEVER)
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Generation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NEVER => f.write_str("never"),
+            Self(read) => write!(f, "read {read}"),
+        }
+    }
+}
+
+/// Reads of a list that were started and completed
+///
+/// A read takes the next number when it starts. `ctListRead` does not fetch
+/// tags added while a read of the list is pending, so a tag is stamped with
+/// the first read started after it was added: its value is available once
+/// that read or a later one has completed.
+#[derive(Debug)]
+struct ReadCounter {
+    started: AtomicU64,
+    completed: AtomicU64,
+}
+
+impl ReadCounter {
+    fn new() -> Self {
+        Self {
+            started: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+        }
+    }
+
+    /// Number a read about to call `ctListRead`
+    fn begin(&self) -> Generation {
+        Generation(self.started.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    /// Record `read` as completed; completing out of order keeps the highest
+    fn complete(&self, read: Generation) {
+        self.completed.fetch_max(read.0, Ordering::SeqCst);
+    }
+
+    fn completed(&self) -> Generation {
+        Generation(self.completed.load(Ordering::SeqCst))
+    }
+
+    /// Stamp of a tag `ctListAdd` has just returned
+    ///
+    /// A read numbered after this has called `ctListRead` after the tag was
+    /// added, so it fetches the tag.
+    fn stamp(&self) -> ReadStamp {
+        ReadStamp {
+            added_in: self.completed(),
+            first_read: Generation(self.started.load(Ordering::SeqCst) + 1),
+        }
+    }
+
+    /// Last completed read that fetched a tag with `stamp`
+    fn last_read(&self, stamp: ReadStamp) -> Generation {
+        let completed = self.completed();
+        if completed >= stamp.first_read {
+            completed
+        } else {
+            Generation::NEVER
+        }
+    }
+}
+
+/// Reads around the time a tag got its current handle
+#[derive(Debug, Clone, Copy)]
+struct ReadStamp {
+    /// Last read completed when the tag was added
+    added_in: Generation,
+    /// First read that fetches the tag
+    first_read: Generation,
+}
+
+/// Handle and options of a tag on a list
+#[derive(Clone)]
+struct ListTag {
+    handle: ListHandle,
+    /// `None` for tags added with [`CtList::add_tag`]
+    options: Option<TagOptions>,
+    /// Name the tag was added to the server as, when the client's
+    /// [default cluster](CtClient::default_cluster) qualified its key
+    address: Option<String>,
+    /// Reads the tag was added between
+    stamp: ReadStamp,
+}
+
+impl ListTag {
+    fn raw(&self) -> RawHandle {
+        self.handle.0
+    }
+
+    /// Name to add the tag under key `name` to the server as
+    fn address<'a>(&'a self, name: &'a str) -> &'a str {
+        self.address.as_deref().unwrap_or(name)
+    }
+
+    /// Options the tag is polled with, the defaults for tags added with
+    /// [`CtList::add_tag`]
+    fn effective_options(&self) -> TagOptions {
+        self.options.unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for ListTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListTag")
+            .field("handle", &self.handle.0)
+            .field("options", &self.options)
+            .field("address", &self.address)
+            .field("stamp", &self.stamp)
+            .finish()
+    }
+}
+
+/// Routes `ctListEvent` results to the event lists of a connection
+///
+/// CtAPI queues the events of all event lists of a connection together, so
+/// the list calling [`next_event`](CtList::next_event) may be handed a tag of
+/// another list. The router queues such an event for the list the tag is on,
+/// which gets it on its next call. Shared by a client and its clones.
+#[derive(Default)]
+pub(crate) struct EventRouter {
+    lists: Mutex<Vec<RoutedList>>,
+    next_id: AtomicU64,
+}
+
+/// Event list known to an [`EventRouter`]
+struct RoutedList {
+    id: u64,
+    tags: Weak<TagTable<ListTag>>,
+    /// Tag handles of events fetched by other lists, oldest first
+    queued: VecDeque<usize>,
+}
+
+impl EventRouter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<RoutedList>> {
+        self.lists.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start routing events of the tags in `tags`, returning the list's id
+    fn register(&self, tags: &Arc<TagTable<ListTag>>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().push(RoutedList {
+            id,
+            tags: Arc::downgrade(tags),
+            queued: VecDeque::new(),
+        });
+        id
+    }
+
+    /// Stop routing events to list `id`, dropping those queued for it
+    fn unregister(&self, id: u64) {
+        self.lock().retain(|list| list.id != id);
+    }
+
+    /// Tag handle of the next event of list `id`, fetching events with
+    /// `fetch` until one is for that list
+    ///
+    /// Events of tags on other lists are queued for them; events of tags no
+    /// list has any more are dropped.
+    fn next(
+        &self,
+        id: Option<u64>,
+        mut fetch: impl FnMut() -> Result<Option<RawHandle>>,
+    ) -> Result<Option<RawHandle>> {
+        let mut lists = self.lock();
+        let own = lists.iter_mut().find(|list| Some(list.id) == id);
+        if let Some(queued) = own.and_then(|list| list.queued.pop_front()) {
+            return Ok(Some(queued as RawHandle));
+        }
+        while let Some(raw) = fetch()? {
+            let owner = lists.iter_mut().find(|list| {
+                let tags = list.tags.upgrade();
+                tags.is_some_and(|tags| tags.owns(raw))
+            });
+            match owner {
+                Some(list) if Some(list.id) == id => return Ok(Some(raw)),
+                Some(list) => list.queued.push_back(raw as usize),
+                None => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl std::fmt::Debug for EventRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventRouter")
+            .field("lists", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Tag name → per-tag handle returned by `ctListAdd`
+///
+/// Handles are only used under the table's lock: shared while a tag is read
+/// or written, exclusive while tags are added or deleted. A read running
+/// concurrently with [`remove`](Self::remove) therefore either finishes with
+/// the handle before `ctListDelete` releases it or does not find the tag.
+///
+/// `RwLock` instead of `Mutex` because tag reads vastly outnumber
+/// tag additions / removals in typical usage.
+struct TagTable<H> {
+    map: RwLock<Tags<H>>,
+}
+
+impl TagTable<ListTag> {
+    /// Whether `raw` is the handle of one of the tags
+    fn owns(&self, raw: RawHandle) -> bool {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        map.handles.values().any(|(_, tag)| tag.raw() == raw)
+    }
+}
+
+/// Handles of a [`TagTable`] with the sequence number of their insertion
+struct Tags<H> {
+    handles: HashMap<String, (u64, H)>,
+    next: u64,
+}
+
+impl<H> TagTable<H> {
+    fn new() -> Self {
+        Self {
+            map: RwLock::new(Tags {
+                handles: HashMap::new(),
+                next: 0,
+            }),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.map
+            .read()
+            .expect("CtList tag_map RwLock poisoned")
+            .handles
+            .len()
+    }
+
+    /// Names of the tags, sorted
+    fn names(&self) -> Vec<String> {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        let mut names: Vec<String> = map.handles.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Call `f` with each tag name, sorted, under the shared lock
+    fn for_each_name(&self, mut f: impl FnMut(&str)) {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        let mut names: Vec<&str> = map.handles.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names.into_iter().for_each(|name| f(name));
+    }
+
+    /// Names and handles of the tags, in insertion order
+    fn entries(&self) -> Vec<(String, H)>
+    where
+        H: Clone,
+    {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        let mut entries: Vec<_> = map.handles.iter().collect();
+        entries.sort_by_key(|(_, (seq, _))| *seq);
+        entries
+            .into_iter()
+            .map(|(name, (_, handle))| (name.clone(), handle.clone()))
+            .collect()
+    }
+
+    /// Run `f` with the handle of `tag` under the shared lock
+    fn with<R>(&self, tag: &str, f: impl FnOnce(&H) -> Result<R>) -> Result<R> {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        match map.handles.get(tag) {
+            Some((_, handle)) => f(handle),
+            None => Err(CtApiError::TagNotFound {
+                tag: tag.to_string(),
+            }),
+        }
+    }
+
+    /// Every tag with what `f` returns for it, under one shared lock, in
+    /// insertion order
+    fn map_all<R>(&self, mut f: impl FnMut(&H) -> R) -> Vec<(String, R)> {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        let mut entries: Vec<_> = map.handles.iter().collect();
+        entries.sort_by_key(|(_, (seq, _))| *seq);
+        entries
+            .into_iter()
+            .map(|(name, (_, handle))| (name.clone(), f(handle)))
+            .collect()
+    }
+
+    /// First `Some` `f` returns for a tag, under the shared lock
+    fn find_map<R>(&self, mut f: impl FnMut(&str, &H) -> Option<R>) -> Option<R> {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        map.handles
+            .iter()
+            .find_map(|(name, (_, handle))| f(name, handle))
+    }
+
+    fn contains(&self, tag: &str) -> bool {
+        let map = self.map.read().expect("CtList tag_map RwLock poisoned");
+        map.handles.contains_key(tag)
+    }
+
+    /// Add `tag` with the handle created by `add`, under the exclusive lock
+    ///
+    /// A tag already in the table fails with [`CtApiError::DuplicateTag`]
+    /// without calling `add`, so its handle is neither replaced nor leaked.
+    fn insert(&self, tag: &str, add: impl FnOnce() -> Result<H>) -> Result<()> {
+        let mut map = self.map.write().expect("CtList tag_map RwLock poisoned");
+        if map.handles.contains_key(tag) {
+            return Err(CtApiError::DuplicateTag {
+                tag: tag.to_string(),
+            });
+        }
+        let handle = add()?;
+        let seq = map.next;
+        map.next += 1;
+        map.handles.insert(tag.to_owned(), (seq, handle));
+        Ok(())
+    }
+
+    /// Replace the handles of `tags` under one exclusive lock
+    ///
+    /// `replace` returns the new handle of a tag, or `None` to keep the
+    /// current one. Tags keep their insertion order. An error from `replace`
+    /// stops the batch; tags before it keep their new handle.
+    fn replace(
+        &self,
+        tags: &[String],
+        mut replace: impl FnMut(&str, &H) -> Result<Option<H>>,
+    ) -> Result<()> {
+        let mut map = self.map.write().expect("CtList tag_map RwLock poisoned");
+        for tag in tags {
+            if let Some((_, handle)) = map.handles.get_mut(tag.as_str())
+                && let Some(new) = replace(tag, handle)?
+            {
+                *handle = new;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild every handle under the exclusive lock, in insertion order
+    ///
+    /// `rebuild` returns the new handle of a tag, or `None` to remove the
+    /// tag. An error from `rebuild` leaves the table unchanged.
+    fn rebuild(&self, mut rebuild: impl FnMut(&str, &H) -> Result<Option<H>>) -> Result<()> {
+        let mut map = self.map.write().expect("CtList tag_map RwLock poisoned");
+        let mut order: Vec<_> = map
+            .handles
+            .iter()
+            .map(|(name, (seq, _))| (*seq, name.clone()))
+            .collect();
+        order.sort();
+        let mut rebuilt = HashMap::with_capacity(order.len());
+        for (seq, name) in order {
+            let (_, handle) = &map.handles[&name];
+            if let Some(new) = rebuild(&name, handle)? {
+                rebuilt.insert(name, (seq, new));
+            }
+        }
+        map.handles = rebuilt;
+        Ok(())
+    }
+
+    /// Remove `tag` once `delete` has released its handle, under the exclusive lock
+    fn remove(&self, tag: &str, delete: impl FnOnce(&H) -> Result<()>) -> Result<()> {
+        let mut map = self.map.write().expect("CtList tag_map RwLock poisoned");
+        match map.handles.get(tag) {
+            Some((_, handle)) => {
+                delete(handle)?;
+                map.handles.remove(tag);
+                Ok(())
+            }
+            None => Err(CtApiError::TagNotFound {
+                tag: tag.to_string(),
+            }),
+        }
+    }
+}
+
+/// A tag of a [`CtList`], returned by [`CtList::entries`]
+///
+/// The entry is a snapshot taken when [`entries`](CtList::entries) was
+/// called; the tag may have been deleted from the list since.
+#[derive(Debug, Clone)]
+pub struct TagEntry<'a> {
+    name: String,
+    tag: ListTag,
+    last_read: Generation,
+    list: PhantomData<&'a CtList>,
+}
+
+impl TagEntry<'_> {
+    /// Tag name as passed to [`add_tag`](CtList::add_tag)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Options of a tag added with [`add_tag_ex`](CtList::add_tag_ex), `None`
+    /// for tags added with [`add_tag`](CtList::add_tag)
+    pub fn options(&self) -> Option<TagOptions> {
+        self.tag.options
+    }
+
+    /// [Read generation](CtList::read_generation) of the list when the tag
+    /// was added
+    ///
+    /// [`set_raw_mode`](CtList::set_raw_mode) and
+    /// [`resubscribe`](CtList::resubscribe) add tags again, which restamps
+    /// them.
+    pub fn added_in(&self) -> Generation {
+        self.tag.stamp.added_in
+    }
+
+    /// Last completed read of the list that fetched the tag, or
+    /// [`Generation::NEVER`] if none has yet
+    ///
+    /// A tag added while a read is pending is not fetched by that read, only
+    /// by the next one.
+    pub fn last_read(&self) -> Generation {
+        self.last_read
+    }
+
+    /// Tag handle returned by `ctListAdd`, for CtAPI functions not wrapped by this crate
+    ///
+    /// Prefer [`CtList::with_raw_handle`], which holds the tag on the list
+    /// while the handle is used.
+    ///
+    /// # Safety
+    /// The handle is only valid while the tag remains on the list. Once
+    /// [`delete_tag`](CtList::delete_tag) has removed it, possibly from
+    /// another thread, the handle is released and must not be passed to
+    /// CtAPI.
+    pub unsafe fn raw_handle(&self) -> RawHandle {
+        self.tag.raw()
+    }
+}
+
+/// A value of [`CtList::read_all_full`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListReading {
+    /// Tag name as passed to [`add_tag`](CtList::add_tag)
+    pub tag: String,
+    /// Value and quality of the tag
+    pub reading: TagReading,
+    /// Read the value comes from, [`Generation::NEVER`] if the tag was not
+    /// fetched by any completed read
+    pub freshness: Generation,
+}
+
+/// An item of a tag from the last list read, see [`CtList::read_tag_item`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListItemValue {
+    /// [`ReadItem::Value`]
+    Value(String),
+    /// [`ReadItem::Timestamp`], [`ReadItem::ValueTimestamp`] or
+    /// [`ReadItem::QualityTimestamp`] as FILETIME ticks, `0` if not set
+    Timestamp(u64),
+    /// [`ReadItem::QualityGeneral`], [`ReadItem::QualitySubstatus`],
+    /// [`ReadItem::QualityLimit`] or [`ReadItem::QualityExtendedSubstatus`];
+    /// see [`QualityStatus`](crate::QualityStatus),
+    /// [`QualitySubstatus`](crate::QualitySubstatus) and
+    /// [`QualityLimit`](crate::QualityLimit) for their meaning
+    Quality(u8),
+    /// [`ReadItem::DatasourceError`], `None` if the I/O driver reported no
+    /// error
+    DatasourceError(Option<CitectError>),
+    /// [`ReadItem::Override`] or [`ReadItem::ControlMode`]
+    Flag(bool),
+}
+
+impl ListItemValue {
+    /// Typed `text` of `item` of `tag`
+    fn parse(tag: &str, item: ReadItem, text: String) -> Result<Self> {
+        Ok(match item {
+            ReadItem::Value => ListItemValue::Value(text),
+            ReadItem::Timestamp | ReadItem::ValueTimestamp | ReadItem::QualityTimestamp => {
+                ListItemValue::Timestamp(parse_item(tag, item, &text)?)
+            }
+            ReadItem::QualityGeneral
+            | ReadItem::QualitySubstatus
+            | ReadItem::QualityLimit
+            | ReadItem::QualityExtendedSubstatus => {
+                ListItemValue::Quality(parse_item(tag, item, &text)?)
+            }
+            ReadItem::DatasourceError => {
+                ListItemValue::DatasourceError(CitectError::parse_item(&text))
+            }
+            ReadItem::Override | ReadItem::ControlMode => {
+                ListItemValue::Flag(parse_item::<u8>(tag, item, &text)? != 0)
+            }
+        })
+    }
+
+    /// Time of a [`Timestamp`](Self::Timestamp), `None` for other items and
+    /// timestamps that are not set
+    pub fn to_utc(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ListItemValue::Timestamp(ticks) => filetime::to_utc(*ticks),
+            _ => None,
+        }
+    }
+}
+
+/// Why [`CtList::next_event`] reported a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListEventKind {
+    /// The tag was added to the list; only reported when
+    /// [`CT_LIST_EVENT_NEW`] is passed
+    New,
+    /// The value or, with
+    /// [`CT_LIST_EVENT_STATUS`](crate::constants::CT_LIST_EVENT_STATUS),
+    /// the status of the tag changed
+    Status,
+}
+
+/// A tag reported by [`CtList::next_event`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEvent {
+    /// Tag name as passed to [`add_tag`](CtList::add_tag)
+    pub tag: String,
+    /// Value of the tag after the change
+    pub value: String,
+    /// Why the tag was reported
+    pub kind: ListEventKind,
+}
+
+/// Values of a list returned by [`CtList::read_all_full`], sorted by tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListReadings {
+    generation: Generation,
+    readings: Vec<ListReading>,
+}
+
+impl ListReadings {
+    /// Generation of the read that returned these values
+    pub fn generation(&self) -> Generation {
+        self.generation
+    }
+
+    /// Keep the values fetched by this read or a later one, dropping tags
+    /// added while it was pending
+    pub fn fresh_only(mut self) -> Self {
+        let generation = self.generation;
+        self.readings.retain(|value| value.freshness >= generation);
+        self
+    }
+
+    /// Take the values out
+    pub fn into_vec(self) -> Vec<ListReading> {
+        self.readings
+    }
+}
+
+impl std::ops::Deref for ListReadings {
+    type Target = [ListReading];
+
+    fn deref(&self) -> &[ListReading] {
+        &self.readings
+    }
+}
+
+impl IntoIterator for ListReadings {
+    type Item = ListReading;
+    type IntoIter = std::vec::IntoIter<ListReading>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.readings.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ListReadings {
+    type Item = &'a ListReading;
+    type IntoIter = std::slice::Iter<'a, ListReading>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.readings.iter()
+    }
+}
+
 /// Wrapper struct containing a CtAPI list handle.
 ///
 /// # Thread Safety
@@ -35,11 +705,13 @@ unsafe impl Sync for ListHandle {}
 ///
 /// | Field      | Synchronization | Rationale |
 /// |------------|-----------------|-----------|
-/// | `handle`   | **None** (immutable after `new`) | The list handle from `ctListNew` never changes; direct access is safe from any thread. |
-/// | `tag_map`  | **[`RwLock`]**  | Tag lookups (`read_tag`, `write_tag`) vastly outnumber structural changes (`add_tag`, `delete_tag`). A `RwLock` lets multiple readers proceed in parallel while writes remain exclusive. |
+/// | `handle`   | **None** (only replaced by `resubscribe`, which takes `&mut self`) | The list handle from `ctListNew` does not change while the list is shared; direct access is safe from any thread. |
+/// | `tag_map`  | **[`RwLock`](std::sync::RwLock)**  | Tag lookups (`read_tag`, `write_tag`) vastly outnumber structural changes (`add_tag`, `delete_tag`). A `RwLock` lets multiple readers proceed in parallel while writes remain exclusive. |
+/// | `writes`   | **[`Mutex`]** | Held only to update the [write outcome](crate::write_outcome) bookkeeping, never across a CtAPI call. |
 ///
 /// As a result:
-/// - `read()` / `read_async()` are **completely lock-free**.
+/// - `read()` / `read_async()` are **lock-free**; `read()` only checks the
+///   tags written recently for [rejected writes](Self::pending_write_outcomes).
 /// - `read_tag()` / `write_tag()` acquire a **shared read lock** — multiple
 ///   threads can call them simultaneously.
 /// - `add_tag()` / `delete_tag()` acquire an **exclusive write lock** — they
@@ -57,11 +729,11 @@ unsafe impl Sync for ListHandle {}
 ///
 /// # Examples
 ///
-/// ```no_run
+/// ```
 /// use ctapi_rs::CtClient;
 /// use std::sync::Arc;
 ///
-/// let client = Arc::new(CtClient::open(None, None, None, 0)?);
+/// let client = Arc::new(CtClient::open_mock()?);
 /// let list = Arc::new(Arc::clone(&client).list_new(0)?);
 /// list.add_tag("Temperature")?;
 /// list.add_tag("Pressure")?;
@@ -70,41 +742,184 @@ unsafe impl Sync for ListHandle {}
 /// // Multiple threads can call read_tag concurrently.
 /// let list2 = Arc::clone(&list);
 /// let t = std::thread::spawn(move || list2.read_tag("Temperature", 0).unwrap());
-/// println!("Pressure: {}", list.read_tag("Pressure", 0)?);
-/// println!("Temperature: {}", t.join().unwrap());
+/// assert_eq!(list.read_tag("Pressure", 0)?, "1.2");
+/// assert_eq!(t.join().unwrap(), "25.5");
 /// # Ok::<(), anyhow::Error>(())
 /// ```
 pub struct CtList {
     client: Arc<CtClient>,
     /// The CtAPI list handle returned by `ctListNew`.
-    /// Immutable after construction — no lock required.
+    /// Only replaced through `&mut self` — no lock required.
     handle: ListHandle,
+    /// Mode passed to `ctListNew`
+    mode: u32,
+    /// Reconnect generation of the client when `handle` was created
+    generation: u64,
     /// Tag name → per-tag handle returned by `ctListAdd`.
-    ///
-    /// `RwLock` instead of `Mutex` because tag reads vastly outnumber
-    /// tag additions / removals in typical usage.
-    tag_map: RwLock<HashMap<String, ListHandle>>,
+    /// Shared with the client's [`EventRouter`] for event lists.
+    tag_map: Arc<TagTable<ListTag>>,
+    /// Id of an event list in the client's [`EventRouter`]
+    route: Option<u64>,
+    /// Writes whose outcome the next reads may reveal
+    writes: Mutex<WriteTracker>,
+    /// Reads started and completed, to stamp tags and values
+    reads: ReadCounter,
+    /// Tags [`next_event`](Self::next_event) has reported
+    announced: Mutex<HashSet<String>>,
 }
 
 impl std::fmt::Debug for CtList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let tag_map = self.tag_map.read().expect("CtList tag_map RwLock poisoned");
         f.debug_struct("CtList")
             .field("handle", &self.handle.0)
-            .field("tag_count", &tag_map.len())
+            .field("tag_count", &self.tag_map.len())
             .finish()
     }
 }
 
 impl CtList {
-    pub(super) fn new(client: Arc<CtClient>, handle: RawHandle) -> Self {
+    pub(super) fn new(client: Arc<CtClient>, handle: RawHandle, mode: u32) -> Self {
+        let tag_map = Arc::new(TagTable::new());
+        let events = mode & CT_LIST_EVENT != 0;
+        let route = events.then(|| client.events().register(&tag_map));
         Self {
+            generation: client.reconnect_generation(),
             client,
             handle: ListHandle(handle),
-            tag_map: RwLock::new(HashMap::new()),
+            mode,
+            tag_map,
+            route,
+            writes: Mutex::new(WriteTracker::new()),
+            reads: ReadCounter::new(),
+            announced: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Client this list belongs to (internal use)
+    pub(crate) fn client(&self) -> &Arc<CtClient> {
+        &self.client
+    }
+
+    /// Fail with [`CtApiError::InvalidHandle`] if the list has no handle or
+    /// its client was closed
+    fn ensure_open(&self) -> Result<()> {
+        if self.handle.0.is_null() {
+            return Err(CtApiError::InvalidHandle);
+        }
+        self.client.ensure_open()
+    }
+
+    /// Names of the tags in the list, sorted
+    ///
+    /// Acquires a **shared read lock** on the tag map. To look at the names
+    /// without copying them, use [`for_each_tag`](Self::for_each_tag).
+    pub fn tags(&self) -> Vec<String> {
+        self.tag_map.names()
+    }
+
+    /// Call `f` with the name of each tag in the list, sorted
+    ///
+    /// The names are borrowed from the list, which holds a **shared read
+    /// lock** on the tag map until `f` has seen every tag. Adding or
+    /// deleting tags of the same list from `f` deadlocks.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::CtClient;
+    /// use std::sync::Arc;
+    ///
+    /// let list = Arc::new(CtClient::open_mock()?).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// list.add_tag("Pressure")?;
+    ///
+    /// let mut longest = 0;
+    /// list.for_each_tag(|tag| longest = longest.max(tag.len()));
+    /// assert_eq!(longest, "Temperature".len());
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn for_each_tag(&self, f: impl FnMut(&str)) {
+        self.tag_map.for_each_name(f)
+    }
+
+    /// Number of tags in the list
+    pub fn len(&self) -> usize {
+        self.tag_map.len()
+    }
+
+    /// Whether the list has no tags
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether `tag` is in the list, by the name it was added under
+    pub fn contains_tag(&self, tag: &str) -> bool {
+        self.tag_map.contains(tag)
+    }
+
+    /// Tags of the list with their handles, in the order they were added
+    ///
+    /// Meant for calling CtAPI functions this crate does not wrap. The
+    /// entries are a snapshot taken under a **shared read lock**; tags
+    /// deleted afterwards are still returned.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// list.add_tag_ex("Pressure", false, 1000, 0.5)?;
+    /// for entry in list.entries() {
+    ///     println!("{}: {:?}", entry.name(), entry.options());
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn entries(&self) -> impl Iterator<Item = TagEntry<'_>> {
+        self.tag_map
+            .entries()
+            .into_iter()
+            .map(|(name, tag)| TagEntry {
+                name,
+                last_read: self.reads.last_read(tag.stamp),
+                tag,
+                list: PhantomData,
+            })
+    }
+
+    /// Name `tag` is added to the server as, if the client's default
+    /// cluster qualifies it
+    fn qualified(&self, tag: &str) -> Option<String> {
+        match self.client.qualify(tag) {
+            Cow::Owned(address) => Some(address),
+            Cow::Borrowed(_) => None,
         }
     }
 
+    /// Run `f` with the handle `ctListAdd` returned for `tag`
+    ///
+    /// Holds a **shared read lock** on the tag map while `f` runs, so the tag
+    /// cannot be deleted and its handle stays valid. `f` must not add or
+    /// delete tags of this list, which would deadlock.
+    ///
+    /// # Errors
+    /// * [`CtApiError::TagNotFound`] - The tag is not on the list
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// let valid = list.with_raw_handle("Temperature", |handle| !handle.is_null())?;
+    /// assert!(valid);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_raw_handle<R>(&self, tag: &str, f: impl FnOnce(RawHandle) -> R) -> Result<R> {
+        self.tag_map.with(tag, |handle| Ok(f(handle.raw())))
+    }
+
     /// Add tag or tag element to list
     ///
     /// Once tags are added to the list, they can be read using ctListRead() and
@@ -112,23 +927,35 @@ impl CtList {
     /// not be read until next call to ctListRead().  ctListWrite() can be
     /// called immediately after ctListAdd() completes.
     ///
+    /// A name without a `.` is added in the client's
+    /// [default cluster](CtClient::default_cluster), if one is set, but is
+    /// still read and written by the name given here.
+    ///
+    /// Unless the client's [name checks](CtClient::set_validate_tag_names)
+    /// are off, a malformed name fails with [`CtApiError::InvalidTagName`]
+    /// before CtAPI is called. A tag already in the list fails with
+    /// [`CtApiError::DuplicateTag`]; delete it first to add it again.
+    ///
     /// Acquires an **exclusive write lock** on the tag map.
     pub fn add_tag<T: AsRef<str>>(&self, tag: T) -> Result<()> {
-        let ctag = CString::new(GBK.encode(tag.as_ref()).0)?;
-        let mut tag_map = self
-            .tag_map
-            .write()
-            .expect("CtList tag_map RwLock poisoned");
-        // SAFETY: self.handle.0 is a valid CtAPI list handle. ctag is a
-        // GBK-encoded CString whose pointer is valid for this call.
-        unsafe {
-            let handle = ctListAdd(self.handle.0, ctag.as_ptr());
+        self.ensure_open()?;
+        self.client.check_tag_name(tag.as_ref())?;
+        let address = self.qualified(tag.as_ref());
+        let ctag = CString::new(GBK.encode(address.as_deref().unwrap_or(tag.as_ref())).0)?;
+        self.tag_map.insert(tag.as_ref(), || {
+            // SAFETY: self.handle.0 is a valid CtAPI list handle. ctag is a
+            // GBK-encoded CString whose pointer is valid for this call.
+            let handle = unsafe { ctListAdd(self.handle.0, ctag.as_ptr()) };
             if handle.is_null() {
                 return Err(std::io::Error::last_os_error().into());
             }
-            tag_map.insert(tag.as_ref().to_owned(), ListHandle(handle));
-        }
-        Ok(())
+            Ok(ListTag {
+                handle: ListHandle(handle),
+                options: None,
+                address,
+                stamp: self.reads.stamp(),
+            })
+        })
     }
 
     /// Add tag (extended version with more parameters)
@@ -137,6 +964,8 @@ impl CtList {
     /// polling period and deadband.  If using ctListAdd, default polling
     /// period is 500ms, raw value flag defaults to engineering value FALSE.
     ///
+    /// Name checks and duplicates fail as in [`add_tag`](Self::add_tag).
+    ///
     /// Acquires an **exclusive write lock** on the tag map.
     pub fn add_tag_ex<T: AsRef<str>>(
         &self,
@@ -145,172 +974,751 @@ impl CtList {
         poll_period: i32,
         deadband: f64,
     ) -> Result<()> {
-        let ctag = CString::new(GBK.encode(tag.as_ref()).0)?;
-        let mut tag_map = self
+        self.ensure_open()?;
+        self.client.check_tag_name(tag.as_ref())?;
+        let address = self.qualified(tag.as_ref());
+        let ctag = CString::new(GBK.encode(address.as_deref().unwrap_or(tag.as_ref())).0)?;
+        self.tag_map.insert(tag.as_ref(), || {
+            // SAFETY: self.handle.0 is a valid CtAPI list handle. ctag is a
+            // GBK-encoded CString. raw, poll_period, deadband are primitive
+            // values matching the CtAPI parameter types.
+            let handle =
+                unsafe { ctListAddEx(self.handle.0, ctag.as_ptr(), raw, poll_period, deadband) };
+            if handle.is_null() {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            Ok(ListTag {
+                handle: ListHandle(handle),
+                options: Some(TagOptions {
+                    raw,
+                    poll_period,
+                    deadband,
+                }),
+                address,
+                stamp: self.reads.stamp(),
+            })
+        })
+    }
+
+    /// Options `tag` is polled with
+    ///
+    /// Tags added with [`add_tag`](Self::add_tag) report the
+    /// [defaults](TagOptions::default) of `ctListAdd`.
+    ///
+    /// # Errors
+    /// * [`CtApiError::TagNotFound`] - The tag is not on the list
+    pub fn tag_options<T: AsRef<str>>(&self, tag: T) -> Result<TagOptions> {
+        self.tag_map
+            .with(tag.as_ref(), |handle| Ok(handle.effective_options()))
+    }
+
+    /// Switch every tag of the list between raw and engineering values
+    ///
+    /// CtAPI fixes the raw flag when a tag is added, so each tag not yet in
+    /// the requested mode is added again with the flag changed and its
+    /// polling period and deadband kept, then its previous handle is
+    /// deleted. Tags are re-added in batches under an **exclusive write
+    /// lock** and keep their place in [`entries`](Self::entries). Values of
+    /// re-added tags are available after the next [`read`](Self::read);
+    /// taking `&mut self` ensures no read of this list is in progress.
+    ///
+    /// A tag that cannot be re-added keeps its previous mode and handle and
+    /// is reported in [`ModeChangeReport::failed`].
+    ///
+    /// # Errors
+    /// Losing the connection
+    /// ([`is_connection_down`](crate::reconnect::is_connection_down)) stops
+    /// the switch with that error. Tags switched until then keep their new
+    /// mode, so calling `set_raw_mode` again resumes with the remaining tags.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let mut list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// list.add_tag_ex("Pressure", false, 1000, 0.5)?;
+    ///
+    /// let report = list.set_raw_mode(true)?;
+    /// assert!(report.is_complete());
+    /// assert!(list.tag_options("Pressure")?.raw);
+    /// assert_eq!(list.tag_options("Pressure")?.poll_period, 1000);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_raw_mode(&mut self, raw: bool) -> Result<ModeChangeReport> {
+        crate::blocking::check("CtList::set_raw_mode", crate::blocking::IN_BLOCKING_SECTION);
+        self.ensure_open()?;
+        let tags: Vec<String> = self
             .tag_map
-            .write()
-            .expect("CtList tag_map RwLock poisoned");
+            .entries()
+            .into_iter()
+            .filter(|(_, tag)| tag.effective_options().raw != raw)
+            .map(|(name, _)| name)
+            .collect();
+        let mut report = ModeChangeReport::default();
+        let mut switch = |name: &str, tag: &ListTag| match self.readd(name, tag, raw) {
+            Ok(new) => {
+                report.changed.push(name.to_string());
+                Ok(Some(new))
+            }
+            Err(error) if crate::reconnect::is_connection_down(&error) => Err(error),
+            Err(error) => {
+                report.failed.push((name.to_string(), error));
+                Ok(None)
+            }
+        };
+        for batch in tags.chunks(MODE_CHANGE_BATCH) {
+            self.tag_map.replace(batch, &mut switch)?;
+        }
+        Ok(report)
+    }
+
+    /// Add `tag` again with the raw flag set to `raw`, then delete its
+    /// previous handle
+    ///
+    /// On error the previous handle stays valid.
+    fn readd(&self, name: &str, tag: &ListTag, raw: bool) -> Result<ListTag> {
+        let options = TagOptions {
+            raw,
+            ..tag.effective_options()
+        };
+        let ctag = CString::new(GBK.encode(tag.address(name)).0)?;
         // SAFETY: self.handle.0 is a valid CtAPI list handle. ctag is a
-        // GBK-encoded CString. raw, poll_period, deadband are primitive
-        // values matching the CtAPI parameter types.
+        // GBK-encoded CString. The options are primitive values matching the
+        // CtAPI parameter types.
+        let handle = unsafe {
+            ctListAddEx(
+                self.handle.0,
+                ctag.as_ptr(),
+                options.raw,
+                options.poll_period,
+                options.deadband,
+            )
+        };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: tag.raw() is a valid tag handle from ctListAdd/ctListAddEx,
+        // and handle was just returned by ctListAddEx. The caller holds the
+        // write lock on tag_map.
         unsafe {
-            let handle = ctListAddEx(self.handle.0, ctag.as_ptr(), raw, poll_period, deadband);
-            if handle.is_null() {
-                return Err(std::io::Error::last_os_error().into());
+            if !ctListDelete(tag.raw()) {
+                let error = std::io::Error::last_os_error();
+                ctListDelete(handle);
+                return Err(error.into());
             }
-            tag_map.insert(tag.as_ref().to_owned(), ListHandle(handle));
         }
+        Ok(ListTag {
+            handle: ListHandle(handle),
+            options: Some(options),
+            address: tag.address.clone(),
+            stamp: self.reads.stamp(),
+        })
+    }
+
+    /// [Reconnect generation](CtClient::reconnect_generation) of the client
+    /// when the list handle was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Last read of the list that completed, [`Generation::NEVER`] before
+    /// the first
+    ///
+    /// [`read`](Self::read) and [`TokioCtList::read_tokio`](crate::TokioCtList::read_tokio)
+    /// complete a read when they return successfully. A read started with
+    /// [`read_async`](Self::read_async) takes a number but is never counted
+    /// as completed, since the list does not see it finish.
+    pub fn read_generation(&self) -> Generation {
+        self.reads.completed()
+    }
+
+    /// Recreate the list on a fresh `ctListNew` handle with all its tags
+    ///
+    /// CtAPI discards the lists of a connection when it re-establishes it,
+    /// after which [`read`](Self::read) fails with
+    /// [`CtApiError::ConnectionLost`]. This adds every tag again, in list
+    /// order and with its [options](Self::tag_options), to a new list handle
+    /// and frees the previous one. Values are available after the next
+    /// [`read`](Self::read); taking `&mut self` ensures no call on the
+    /// previous handle is in progress.
+    ///
+    /// A tag that cannot be added again is removed from the list and
+    /// reported in [`ResubscribeReport::dropped`].
+    ///
+    /// # Errors
+    /// Creating the list or losing the connection
+    /// ([`is_connection_down`](crate::reconnect::is_connection_down)) stops
+    /// the resubscription with that error and leaves the list unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::{CtApiError, CtClient};
+    /// # use ctapi_rs::mock;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let mut list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    ///
+    /// // The connection drops and comes back
+    /// mock::simulate_reconnect(&client);
+    /// assert!(matches!(list.read(), Err(CtApiError::ConnectionLost { generation: 1 })));
+    ///
+    /// let report = list.resubscribe()?;
+    /// assert_eq!(report.readded, ["Temperature"]);
+    /// list.read()?;
+    /// assert_eq!(list.read_tag("Temperature", 0)?, "25.5");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn resubscribe(&mut self) -> Result<ResubscribeReport> {
+        crate::blocking::check("CtList::resubscribe", crate::blocking::IN_BLOCKING_SECTION);
+        self.client.ensure_open()?;
+        let generation = self.client.reconnect_generation();
+        // SAFETY: the client handle is a valid CtAPI connection handle. mode
+        // is the DWORD flag value the list was created with.
+        let handle = unsafe { ctListNew(self.client.handle(), self.mode) };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let mut report = ResubscribeReport::default();
+        let mut add = |name: &str, tag: &ListTag| match add_to(handle, name, tag) {
+            Ok(new) => {
+                report.readded.push(name.to_string());
+                Ok(Some(ListTag {
+                    handle: new,
+                    options: tag.options,
+                    address: tag.address.clone(),
+                    stamp: self.reads.stamp(),
+                }))
+            }
+            Err(error) if crate::reconnect::is_connection_down(&error) => Err(error),
+            Err(error) => {
+                report.dropped.push((name.to_string(), error));
+                Ok(None)
+            }
+        };
+        if let Err(error) = self.tag_map.rebuild(&mut add) {
+            // SAFETY: handle was just returned by ctListNew; freeing it also
+            // releases the tags added to it.
+            unsafe { ctListFree(handle) };
+            return Err(error);
+        }
+
+        // SAFETY: the previous handle came from ctListNew and no tag of the
+        // table refers to it any more. Freeing a list CtAPI already discarded
+        // fails harmlessly.
+        unsafe { ctListFree(self.handle.0) };
+        self.handle = ListHandle(handle);
+        self.generation = generation;
+        Ok(report)
+    }
+
+    /// `error`, or [`CtApiError::ConnectionLost`] if the client reconnected
+    /// since the list handle was created
+    fn stale_or(&self, error: CtApiError) -> CtApiError {
+        let generation = self.client.reconnect_generation();
+        if generation != self.generation {
+            return CtApiError::ConnectionLost { generation };
+        }
+        error
+    }
+
+    /// Delete tag created with ctListAdd
+    ///
+    /// Program can call ctListDelete() while there are pending reads or writes
+    /// in another thread.  ctListWrite() and ctListRead() will return after
+    /// tag deletion.
+    ///
+    /// Acquires an **exclusive write lock** on the tag map.
+    pub fn delete_tag<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        self.ensure_open()?;
+        self.tag_map.remove(tag.as_ref(), |handle| {
+            // SAFETY: handle.raw() is a valid tag handle from ctListAdd/ctListAddEx.
+            // The write lock on tag_map prevents concurrent access.
+            if !unsafe { ctListDelete(handle.raw()) } {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            Ok(())
+        })?;
+        self.announced().remove(tag.as_ref());
         Ok(())
     }
 
-    /// Delete tag created with ctListAdd
+    /// Read tags in list
+    ///
+    /// This function will read tags attached to the list.  Once data is read
+    /// from I/O device, ctListData() can be called to get tag values.  If
+    /// reading is not successful, ctListData() will return errors for tags
+    /// that cannot be read.
+    ///
+    /// Tags can be added and removed from list while ctListRead() is pending.
+    ///
+    /// **Lock-free**: accesses the immutable list handle directly.
+    ///
+    /// # Errors
+    /// * [`CtApiError::ConnectionLost`] - The read failed and the client has
+    ///   reconnected since the list was created; see
+    ///   [`resubscribe`](Self::resubscribe)
+    /// * [`CtApiError::System`] - The read failed
+    pub fn read(&self) -> Result<()> {
+        self.read_counted().map(drop)
+    }
+
+    /// [`read`](Self::read), returning the generation of the read
+    fn read_counted(&self) -> Result<Generation> {
+        crate::blocking::check("CtList::read", "TokioCtList::read_tokio");
+        self.ensure_open()?;
+        let read = self.reads.begin();
+        let calls = self.client.call_log();
+        let started = calls.start();
+        // Names are only joined for a call that is logged or traced
+        let mut subject = JoinedSubject::new();
+        if started.is_some() {
+            self.for_each_tag(|tag| subject.push(tag));
+        }
+        // SAFETY: self.handle.0 is a valid CtAPI list handle. NULL OVERLAPPED
+        // pointer means synchronous (blocking) read.
+        let ok = unsafe { ctListRead(self.handle.0, NULL as *mut OVERLAPPED) };
+        calls.finish(started, "ctListRead", subject.as_bytes(), ok);
+        if !ok {
+            return Err(self.stale_or(std::io::Error::last_os_error().into()));
+        }
+        self.reads.complete(read);
+        self.correlate_writes();
+        Ok(read)
+    }
+
+    /// Read tags in list asynchronously
+    ///
+    /// Non-blocking version of [`read`].  The read operation starts and
+    /// completes in the background.  Use [`AsyncOperation::get_result`] or
+    /// poll for completion.
+    ///
+    /// Tags added while the read is pending are only fetched by the next
+    /// one. The read is not counted in [`read_generation`](Self::read_generation).
+    ///
+    /// **Lock-free**: accesses the immutable list handle directly.
+    ///
+    /// # Parameters
+    /// * `async_op` - [`AsyncOperation`](crate::AsyncOperation) to track this operation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::{CtClient, AsyncOperation};
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Tag1")?;
+    ///
+    /// let mut async_op = AsyncOperation::new();
+    /// list.read_async(&mut async_op)?;
+    ///
+    /// while !async_op.is_complete() {
+    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    /// }
+    ///
+    /// let value = list.read_tag("Tag1", 0)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_async(&self, async_op: &mut crate::AsyncOperation) -> Result<()> {
+        self.start_read(async_op).map(drop)
+    }
+
+    /// [`read_async`](Self::read_async), returning the generation the read
+    /// takes once passed to [`complete_read`](Self::complete_read) (internal use)
+    pub(crate) fn start_read(&self, async_op: &mut crate::AsyncOperation) -> Result<Generation> {
+        self.ensure_open()?;
+        async_op.begin(self.client.pending())?;
+        let read = self.reads.begin();
+        // SAFETY: self.handle.0 is a valid CtAPI list handle. async_op.overlapped_mut()
+        // returns a valid OVERLAPPED pointer that tracks async completion.
+        unsafe {
+            if !ctListRead(self.handle.0, async_op.overlapped_mut()) {
+                let error = std::io::Error::last_os_error();
+                if error.raw_os_error() != Some(ERROR_IO_PENDING) {
+                    return Err(self.stale_or(error.into()));
+                }
+            }
+            Ok(read)
+        }
+    }
+
+    /// Record a read started with [`start_read`](Self::start_read) as
+    /// completed (internal use)
+    pub(crate) fn complete_read(&self, read: Generation) {
+        self.reads.complete(read);
+    }
+
+    /// Get values of tags in list
+    ///
+    /// Call this function after [`read`] completes for added tags.
+    ///
+    /// Acquires a **shared read lock** on the tag map — multiple threads may
+    /// call `read_tag` concurrently without blocking each other.
+    pub fn read_tag<T: AsRef<str>>(&self, tag: T, mode: u32) -> Result<String> {
+        self.ensure_open()?;
+        self.tag_map
+            .with(tag.as_ref(), |handle| list_data(handle.raw(), mode))
+    }
+
+    /// Value of every tag from the last list read, by tag name
+    ///
+    /// Reads each tag like [`read_tag`](Self::read_tag), under one shared
+    /// lock of the tag map. A tag whose value cannot be read gets its own
+    /// error without failing the others.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// list.add_tag("Pressure")?;
+    /// list.read()?;
+    /// let values = list.read_all(0);
+    /// assert_eq!(values["Pressure"].as_deref().ok(), Some("1.2"));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_all(&self, mode: u32) -> HashMap<String, Result<String>> {
+        self.read_all_ordered(mode).into_iter().collect()
+    }
+
+    /// Value of every tag from the last list read, in the order the tags
+    /// were added
+    ///
+    /// Like [`read_all`](Self::read_all).
+    pub fn read_all_ordered(&self, mode: u32) -> Vec<(String, Result<String>)> {
+        if self.ensure_open().is_err() {
+            return self.tag_map.map_all(|_| Err(CtApiError::InvalidHandle));
+        }
+        self.tag_map.map_all(|handle| list_data(handle.raw(), mode))
+    }
+
+    /// Read the list, then return the value of every tag
+    ///
+    /// [`read`](Self::read) followed by [`read_all`](Self::read_all).
+    ///
+    /// # Errors
+    /// Any error of [`read`](Self::read); tags whose value cannot be read
+    /// get their error in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// for (tag, value) in list.snapshot(0)? {
+    ///     println!("{tag} = {value:?}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn snapshot(&self, mode: u32) -> Result<HashMap<String, Result<String>>> {
+        self.read()?;
+        Ok(self.read_all(mode))
+    }
+
+    /// Value of a tag from the last list read, parsed as a `V`
+    ///
+    /// Reads the `mode` item like [`read_tag`](Self::read_tag) and parses it
+    /// like [`CtClient::tag_read_as`].
+    ///
+    /// # Errors
+    /// * [`CtApiError::ParseError`] - The value is not a valid `V`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Pump1")?;
+    /// list.read()?;
+    /// assert!(list.read_tag_as::<bool, _>("Pump1", 0)?);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_tag_as<V: FromStr, T: AsRef<str>>(&self, tag: T, mode: u32) -> Result<V> {
+        let tag = tag.as_ref();
+        parse_value(tag, self.read_tag(tag, mode)?)
+    }
+
+    /// An item of a tag from the last list read, typed by `item`
+    ///
+    /// Reads the item with `ctListItem`; `mode` is passed through and is
+    /// normally 0. Items the API does not report for the addressing form of
+    /// `tag` (see [`tag_path`](crate::tag_path)) fail.
+    ///
+    /// # Errors
+    /// * [`CtApiError::TagNotFound`] - The tag is not on the list
+    /// * [`CtApiError::System`] - The API did not report the item
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// use ctapi_rs::ReadItem;
+    /// use ctapi_rs::list::ListItemValue;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Pressure")?;
+    /// list.read()?;
+    /// let value = list.read_tag_item("Pressure", ReadItem::Value, 0)?;
+    /// assert_eq!(value, ListItemValue::Value("1.2".to_string()));
+    /// let sampled = list.read_tag_item("Pressure", ReadItem::Timestamp, 0)?;
+    /// println!("sampled at {:?}", sampled.to_utc());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_tag_item<T: AsRef<str>>(
+        &self,
+        tag: T,
+        item: ReadItem,
+        mode: u32,
+    ) -> Result<ListItemValue> {
+        self.ensure_open()?;
+        let tag = tag.as_ref();
+        let text = self
+            .tag_map
+            .with(tag, |handle| item_text(handle.raw(), item, mode))?;
+        ListItemValue::parse(tag, item, text)
+    }
+
+    /// Value, quality and timestamps of a tag from the last list read
+    ///
+    /// Reads every item the API reports for the addressing form of `tag`
+    /// with `ctListItem`, under one shared lock of the tag map. Element
+    /// fields are returned with [`Quality::NOT_APPLICABLE`], like
+    /// [`read_all_full`](Self::read_all_full) does.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Pressure")?;
+    /// list.read()?;
+    /// let reading = list.read_tag_full("Pressure")?;
+    /// println!("{} ({}) at {:?}", reading.value, reading.quality, reading.timestamp);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_tag_full<T: AsRef<str>>(&self, tag: T) -> Result<TagReading> {
+        self.ensure_open()?;
+        let tag = tag.as_ref();
+        self.tag_map.with(tag, |entry| read_full(entry.raw(), tag))
+    }
+
+    /// Data source error of a tag from the last list read
     ///
-    /// Program can call ctListDelete() while there are pending reads or writes
-    /// in another thread.  ctListWrite() and ctListRead() will return after
-    /// tag deletion.
+    /// Reads the `CT_LIST_QUALITY_DATASOURCE_ERROR` item, returning `None` if
+    /// the I/O driver reported no error.
     ///
-    /// Acquires an **exclusive write lock** on the tag map.
-    pub fn delete_tag<T: AsRef<str>>(&self, tag: T) -> Result<()> {
-        let mut tag_map = self
-            .tag_map
-            .write()
-            .expect("CtList tag_map RwLock poisoned");
-        match tag_map.get(tag.as_ref()) {
-            Some(handle) =>
-            // SAFETY: handle.0 is a valid tag handle from ctListAdd/ctListAddEx.
-            // The write lock on tag_map prevents concurrent access.
-            unsafe {
-                if !ctListDelete(handle.0) {
-                    return Err(std::io::Error::last_os_error().into());
-                }
-                tag_map.remove(tag.as_ref());
-                Ok(())
-            },
-            None => Err(CtApiError::TagNotFound {
-                tag: tag.as_ref().to_string(),
-            }),
-        }
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Tag1")?;
+    /// list.read()?;
+    /// if let Some(error) = list.datasource_error("Tag1")? {
+    ///     println!("Tag1: {error}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn datasource_error<T: AsRef<str>>(&self, tag: T) -> Result<Option<CitectError>> {
+        let item = self.read_tag(tag, crate::constants::CT_LIST_QUALITY_DATASOURCE_ERROR)?;
+        Ok(CitectError::parse_item(&item))
     }
 
-    /// Read tags in list
-    ///
-    /// This function will read tags attached to the list.  Once data is read
-    /// from I/O device, ctListData() can be called to get tag values.  If
-    /// reading is not successful, ctListData() will return errors for tags
-    /// that cannot be read.
+    /// Read the list and split its values by quality
     ///
-    /// Tags can be added and removed from list while ctListRead() is pending.
+    /// Values whose general quality passes `threshold` are returned as good;
+    /// the others are rejected with their general quality and data source
+    /// error. Other quality fields are not read.
     ///
-    /// **Lock-free**: accesses the immutable list handle directly.
-    pub fn read(&self) -> Result<()> {
-        // SAFETY: self.handle.0 is a valid CtAPI list handle. NULL OVERLAPPED
-        // pointer means synchronous (blocking) read.
-        unsafe {
-            if !ctListRead(self.handle.0, NULL as *mut OVERLAPPED) {
-                Err(std::io::Error::last_os_error().into())
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::{CtClient, QualityThreshold};
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Temperature")?;
+    /// list.add_tag("Pressure")?;
+    /// let readings = list.read_all_good(QualityThreshold::GoodOrUncertain)?;
+    /// for (tag, value) in &readings.good {
+    ///     println!("{tag} = {value}");
+    /// }
+    /// for (tag, quality) in &readings.rejected {
+    ///     eprintln!("{tag}: {quality}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn read_all_good(&self, threshold: QualityThreshold) -> Result<QualityPartition> {
+        use crate::constants::{CT_LIST_QUALITY_GENERAL, CT_LIST_VALUE};
+
+        self.read()?;
+        let mut partition = QualityPartition::default();
+        for tag in self.tags() {
+            let general = self.read_tag(&tag, CT_LIST_QUALITY_GENERAL)?;
+            let general = general.trim().parse().map_err(|_| CtApiError::Other {
+                code: 0,
+                message: format!("invalid quality of {tag}: {general}"),
+            })?;
+            let mut quality = Quality {
+                general,
+                ..Quality::default()
+            };
+            if threshold.accepts(&quality) {
+                let value = self.read_tag(&tag, CT_LIST_VALUE)?;
+                partition.good.push((tag, value));
             } else {
-                Ok(())
+                quality.datasource_error = self.datasource_error(&tag)?;
+                partition.rejected.push((tag, quality));
             }
         }
+        Ok(partition)
     }
 
-    /// Read tags in list asynchronously
-    ///
-    /// Non-blocking version of [`read`].  The read operation starts and
-    /// completes in the background.  Use [`AsyncOperation::get_result`] or
-    /// poll for completion.
+    /// Read the list and return every value with its quality
     ///
-    /// **Lock-free**: accesses the immutable list handle directly.
+    /// Only the quality items the API reports for the addressing form of a
+    /// tag are read (see [`tag_path`](crate::tag_path)): element fields are
+    /// returned with [`Quality::NOT_APPLICABLE`] instead of failing. Readings
+    /// are sorted by tag.
     ///
-    /// # Parameters
-    /// * `async_op` - [`AsyncOperation`](crate::AsyncOperation) to track this operation.
+    /// Each value carries the last completed read that fetched its tag. A tag
+    /// added while this read was pending has not been fetched yet and is
+    /// returned with an empty value, [`Quality::NOT_APPLICABLE`] and
+    /// [`Generation::NEVER`]; [`ListReadings::fresh_only`] keeps the values
+    /// of this read.
     ///
     /// # Examples
-    /// ```no_run
-    /// # use ctapi_rs::{CtClient, AsyncOperation};
+    /// ```
+    /// # use ctapi_rs::CtClient;
     /// # use std::sync::Arc;
-    /// let client = Arc::new(CtClient::open(None, None, None, 0)?);
+    /// let client = Arc::new(CtClient::open_mock()?);
     /// let list = Arc::clone(&client).list_new(0)?;
-    /// list.add_tag("Tag1")?;
-    ///
-    /// let mut async_op = AsyncOperation::new();
-    /// list.read_async(&mut async_op)?;
-    ///
-    /// while !async_op.is_complete() {
-    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    /// list.add_tag("Pressure")?;
+    /// list.add_tag("Pressure.V")?;
+    /// for value in list.read_all_full()?.fresh_only() {
+    ///     println!("{} = {} ({})", value.tag, value.reading, value.freshness);
     /// }
-    ///
-    /// let value = list.read_tag("Tag1", 0)?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn read_async(&self, async_op: &mut crate::AsyncOperation) -> Result<()> {
-        // SAFETY: self.handle.0 is a valid CtAPI list handle. async_op.overlapped_mut()
-        // returns a valid OVERLAPPED pointer that tracks async completion.
-        unsafe {
-            if !ctListRead(self.handle.0, async_op.overlapped_mut()) {
-                let error = std::io::Error::last_os_error();
-                if error.raw_os_error() != Some(997) {
-                    return Err(error.into());
-                }
-            }
-            Ok(())
+    pub fn read_all_full(&self) -> Result<ListReadings> {
+        let generation = self.read_counted()?;
+        let mut entries: Vec<_> = self.entries().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut readings = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let freshness = entry.last_read;
+            let reading = if freshness == Generation::NEVER {
+                TagReading::new(String::new(), Quality::NOT_APPLICABLE)
+            } else {
+                self.read_tag_full(&entry.name)?
+            };
+            readings.push(ListReading {
+                tag: entry.name,
+                reading,
+                freshness,
+            });
         }
+        Ok(ListReadings {
+            generation,
+            readings,
+        })
     }
 
-    /// Get values of tags in list
+    /// Next tag of an event list whose value or status changed, `None` if
+    /// none is waiting
     ///
-    /// Call this function after [`read`] completes for added tags.
+    /// Wraps `ctListEvent` for lists created with
+    /// [`CtClient::list_new_event`]. `mode` selects the notifications:
+    /// [`CT_LIST_EVENT_NEW`] also reports tags as they are added, and
+    /// [`CT_LIST_EVENT_STATUS`](crate::constants::CT_LIST_EVENT_STATUS)
+    /// status changes. The first event of a tag with `CT_LIST_EVENT_NEW` is
+    /// a [`ListEventKind::New`], any other a [`ListEventKind::Status`].
     ///
-    /// Acquires a **shared read lock** on the tag map — multiple threads may
-    /// call `read_tag` concurrently without blocking each other.
-    pub fn read_tag<T: AsRef<str>>(&self, tag: T, mode: u32) -> Result<String> {
-        let tag_map = self.tag_map.read().expect("CtList tag_map RwLock poisoned");
-        match tag_map.get(tag.as_ref()) {
-            Some(handle) =>
-            // SAFETY: handle.0 is a valid tag handle from ctListAdd. buffer is a
-            // fixed-size stack array. mode is a valid DWORD flag.
-            unsafe {
-                let mut buffer = [0u8; 256];
-                if !ctListData(
-                    handle.0,
-                    buffer.as_mut_ptr().cast(),
-                    buffer.len() as DWORD,
-                    mode,
-                ) {
-                    return Err(std::io::Error::last_os_error().into());
-                }
-                Ok(GBK
-                    .decode(CStr::from_bytes_until_nul(buffer.as_ref())?.to_bytes())
-                    .0
-                    .to_string())
-            },
-            None => Err(CtApiError::TagNotFound {
-                tag: tag.as_ref().to_string(),
-            }),
+    /// CtAPI queues the events of all event lists of a connection together.
+    /// An event of a tag on another event list of the same client is kept
+    /// for that list and returned by its next call.
+    ///
+    /// Acquires a **shared read lock** on the tag map.
+    ///
+    /// # Errors
+    /// * [`CtApiError::ConnectionLost`] - The client has reconnected since the
+    ///   list was created
+    /// * [`CtApiError::System`] - `ctListEvent` failed
+    pub fn next_event(&self, mode: u32) -> Result<Option<ListEvent>> {
+        self.ensure_open()?;
+        let events = self.client.events();
+        loop {
+            let Some(raw) = events.next(self.route, || self.fetch_event(mode))? else {
+                return Ok(None);
+            };
+            let event = self.tag_map.find_map(|name, tag| {
+                let value = (tag.raw() == raw).then(|| item_text(raw, ReadItem::Value, 0))?;
+                Some(value.map(|value| (name.to_string(), value)))
+            });
+            let Some((tag, value)) = event.transpose()? else {
+                continue;
+            };
+            let first = self.announced().insert(tag.clone());
+            let kind = if first && mode & CT_LIST_EVENT_NEW != 0 {
+                ListEventKind::New
+            } else {
+                ListEventKind::Status
+            };
+            return Ok(Some(ListEvent { tag, value, kind }));
+        }
+    }
+
+    /// Tag handle `ctListEvent` returns for the connection, `None` if no
+    /// event is waiting
+    fn fetch_event(&self, mode: u32) -> Result<Option<RawHandle>> {
+        clear_last_error();
+        // SAFETY: the client handle is a valid CtAPI connection handle and
+        // mode a DWORD flag value.
+        let raw = unsafe { ctListEvent(self.client.handle(), mode) };
+        if !raw.is_null() {
+            return Ok(Some(raw));
+        }
+        let error = std::io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(0 | ERROR_NO_MORE_ITEMS) => Ok(None),
+            _ => Err(self.stale_or(error.into())),
         }
     }
 
+    fn announced(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.announced.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// Write single tag in list
     ///
     /// Acquires a **shared read lock** on the tag map — multiple threads may
     /// call `write_tag` concurrently without blocking each other.
     pub fn write_tag<T: AsRef<str>>(&self, tag: T, value: T) -> Result<()> {
-        let tag_map = self.tag_map.read().expect("CtList tag_map RwLock poisoned");
-        if let Some(handle) = tag_map.get(tag.as_ref()) {
+        self.ensure_open()?;
+        self.client
+            .check_write_permit("list_write", tag.as_ref(), value.as_ref())?;
+        self.tag_map.with(tag.as_ref(), |handle| {
             let cvalue = CString::new(GBK.encode(value.as_ref()).0)?;
-            // SAFETY: handle.0 is a valid tag handle. cvalue is a GBK-encoded
+            // SAFETY: handle.raw() is a valid tag handle. cvalue is a GBK-encoded
             // CString. NULL OVERLAPPED means synchronous write.
             unsafe {
-                if !ctListWrite(handle.0, cvalue.as_ptr(), NULL as *mut OVERLAPPED) {
+                if !ctListWrite(handle.raw(), cvalue.as_ptr(), NULL as *mut OVERLAPPED) {
                     return Err(std::io::Error::last_os_error().into());
                 }
             }
             Ok(())
-        } else {
-            Err(CtApiError::TagNotFound {
-                tag: tag.as_ref().to_string(),
-            })
-        }
+        })?;
+        self.track_write(tag.as_ref());
+        Ok(())
     }
 
     /// Write single tag in list asynchronously
@@ -327,10 +1735,10 @@ impl CtList {
     /// * `async_op` - [`AsyncOperation`](crate::AsyncOperation) to track this operation.
     ///
     /// # Examples
-    /// ```no_run
+    /// ```
     /// # use ctapi_rs::{CtClient, AsyncOperation};
     /// # use std::sync::Arc;
-    /// let client = Arc::new(CtClient::open(None, None, None, 0)?);
+    /// let client = Arc::new(CtClient::open_mock()?);
     /// let list = Arc::clone(&client).list_new(0)?;
     /// list.add_tag("Tag1")?;
     ///
@@ -348,30 +1756,214 @@ impl CtList {
         value: T,
         async_op: &mut crate::AsyncOperation,
     ) -> Result<()> {
-        let tag_map = self.tag_map.read().expect("CtList tag_map RwLock poisoned");
-        if let Some(handle) = tag_map.get(tag.as_ref()) {
+        self.ensure_open()?;
+        self.client
+            .check_write_permit("list_write", tag.as_ref(), value.as_ref())?;
+        self.tag_map.with(tag.as_ref(), |handle| {
             let cvalue = CString::new(GBK.encode(value.as_ref()).0)?;
-            // SAFETY: handle.0 is a valid tag handle. cvalue is a GBK-encoded
+            async_op.begin(self.client.pending())?;
+            // SAFETY: handle.raw() is a valid tag handle. cvalue is a GBK-encoded
             // CString. async_op.overlapped_mut() returns a valid OVERLAPPED pointer.
             unsafe {
-                if !ctListWrite(handle.0, cvalue.as_ptr(), async_op.overlapped_mut()) {
+                if !ctListWrite(handle.raw(), cvalue.as_ptr(), async_op.overlapped_mut()) {
                     let error = std::io::Error::last_os_error();
-                    if error.raw_os_error() != Some(997) {
+                    if error.raw_os_error() != Some(ERROR_IO_PENDING) {
                         return Err(error.into());
                     }
                 }
             }
             Ok(())
-        } else {
-            Err(CtApiError::TagNotFound {
-                tag: tag.as_ref().to_string(),
-            })
+        })?;
+        self.track_write(tag.as_ref());
+        Ok(())
+    }
+
+    /// Writes that the device likely rejected after accepting them
+    ///
+    /// Returns the outcomes found by the reads since the last call, oldest
+    /// first; each write is reported at most once. Writes made with
+    /// [`write_tag`](Self::write_tag) and
+    /// [`write_tag_async`](Self::write_tag_async) are checked by every
+    /// [`read`](Self::read) within the
+    /// [correlation window](Self::set_write_correlation_window), and by this
+    /// call against the values of the last read, which covers
+    /// [`read_async`](Self::read_async).
+    ///
+    /// The outcomes are a heuristic, see [`write_outcome`](crate::write_outcome).
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::CtClient;
+    /// # use ctapi_rs::write_outcome::WriteOutcome;
+    /// # use std::sync::Arc;
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::clone(&client).list_new(0)?;
+    /// list.add_tag("Setpoint")?;
+    /// list.write_tag("Setpoint", "25")?;
+    /// list.read()?;
+    /// for outcome in list.pending_write_outcomes() {
+    ///     let WriteOutcome::LikelyRejected { tag, datasource_error, .. } = outcome;
+    ///     eprintln!("{tag} was likely rejected: {datasource_error}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn pending_write_outcomes(&self) -> Vec<WriteOutcome> {
+        self.correlate_writes();
+        self.writes().drain()
+    }
+
+    /// Time after a write in which a new data source error of the tag is
+    /// blamed on it
+    pub fn write_correlation_window(&self) -> Duration {
+        self.writes().window()
+    }
+
+    /// Change the [correlation window](Self::pending_write_outcomes) of
+    /// writes, [`DEFAULT_CORRELATION_WINDOW`](crate::write_outcome::DEFAULT_CORRELATION_WINDOW)
+    /// by default
+    ///
+    /// Applies to the writes already tracked as well.
+    pub fn set_write_correlation_window(&self, window: Duration) {
+        self.writes().set_window(window);
+    }
+
+    fn writes(&self) -> std::sync::MutexGuard<'_, WriteTracker> {
+        self.writes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Track an accepted write with the data source error `tag` had when it
+    /// was last read
+    fn track_write(&self, tag: &str) {
+        let baseline = self.datasource_error(tag).ok().flatten();
+        self.writes().record(tag, baseline);
+    }
+
+    /// Check the tracked writes against the data source errors of the last read
+    fn correlate_writes(&self) {
+        let tracked = self.writes().tracked();
+        for tag in tracked {
+            // A tag deleted since it was written has nothing to report
+            let error = self.datasource_error(&tag).ok().flatten();
+            self.writes().observe(&tag, error);
+        }
+    }
+}
+
+/// Add `tag`, kept under key `name`, to `list` with `ctListAddEx`, or
+/// `ctListAdd` without options
+/// Value of a list tag from the last read, through `ctListData`
+fn list_data(handle: RawHandle, mode: u32) -> Result<String> {
+    let mut buffer = [0u8; 256];
+    let (data, length) = (buffer.as_mut_ptr().cast(), buffer.len() as DWORD);
+    // SAFETY: handle is a valid tag handle from ctListAdd. data points to a
+    // fixed-size stack array of length bytes. mode is a valid DWORD flag.
+    if !unsafe { ctListData(handle, data, length, mode) } {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    decode_gbk_until_nul(&buffer)
+}
+
+/// Text of `item` of a list tag from the last read
+fn item_text(handle: RawHandle, item: ReadItem, mode: u32) -> Result<String> {
+    let mut buffer = [0u8; 256];
+    let (data, length) = (buffer.as_mut_ptr().cast(), buffer.len() as DWORD);
+    // SAFETY: handle is a valid tag handle from ctListAdd. data points to a
+    // fixed-size stack array of length bytes.
+    let ok = unsafe { ctListItem(handle, item.list_mode(), data, length, mode) };
+    if !ok {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    decode_gbk_until_nul(&buffer)
+}
+
+/// `text` of `item` of `tag` as a number
+fn parse_item<N: FromStr>(tag: &str, item: ReadItem, text: &str) -> Result<N> {
+    text.trim().parse().map_err(|_| CtApiError::Other {
+        code: 0,
+        message: format!("invalid {item:?} of {tag}: {text}"),
+    })
+}
+
+/// Value, quality and timestamps of the list tag `handle`
+fn read_full(handle: RawHandle, tag: &str) -> Result<TagReading> {
+    let form = TagPath::form_of(tag);
+    let value = item_text(handle, ReadItem::Value, 0)?;
+    if !form.has_quality() {
+        return Ok(TagReading::new(value, Quality::NOT_APPLICABLE));
+    }
+    let quality = read_quality(handle, tag, form)?;
+    let timestamp = read_number(handle, tag, form, ReadItem::Timestamp)?;
+    let value_timestamp = read_number(handle, tag, form, ReadItem::ValueTimestamp)?;
+    Ok(TagReading {
+        value,
+        timestamp: filetime::to_utc(timestamp),
+        value_timestamp: filetime::to_utc(value_timestamp),
+        quality_timestamp: filetime::to_utc(quality.timestamp),
+        quality,
+    })
+}
+
+/// Quality items of the list tag `handle`, as far as `form` has them
+fn read_quality(handle: RawHandle, tag: &str, form: AddressingForm) -> Result<Quality> {
+    let datasource_error = if form.supports(ReadItem::DatasourceError) {
+        CitectError::parse_item(&item_text(handle, ReadItem::DatasourceError, 0)?)
+    } else {
+        None
+    };
+    let number = |item| read_number::<u8>(handle, tag, form, item);
+    Ok(Quality {
+        general: number(ReadItem::QualityGeneral)?,
+        substatus: number(ReadItem::QualitySubstatus)?,
+        limit: number(ReadItem::QualityLimit)?,
+        extended_substatus: number(ReadItem::QualityExtendedSubstatus)?,
+        datasource_error,
+        timestamp: read_number(handle, tag, form, ReadItem::QualityTimestamp)?,
+        overridden: number(ReadItem::Override)? != 0,
+        control_mode: number(ReadItem::ControlMode)? != 0,
+    })
+}
+
+/// Numeric item of the list tag `handle`, zero if `form` does not have it
+fn read_number<N: FromStr + Default>(
+    handle: RawHandle,
+    tag: &str,
+    form: AddressingForm,
+    item: ReadItem,
+) -> Result<N> {
+    if !form.supports(item) {
+        return Ok(N::default());
+    }
+    parse_item(tag, item, &item_text(handle, item, 0)?)
+}
+
+fn add_to(list: RawHandle, name: &str, tag: &ListTag) -> Result<ListHandle> {
+    let ctag = CString::new(GBK.encode(tag.address(name)).0)?;
+    // SAFETY: list is a valid CtAPI list handle. ctag is a GBK-encoded
+    // CString. The options are primitive values matching the CtAPI parameter
+    // types.
+    let handle = unsafe {
+        match tag.options {
+            Some(options) => ctListAddEx(
+                list,
+                ctag.as_ptr(),
+                options.raw,
+                options.poll_period,
+                options.deadband,
+            ),
+            None => ctListAdd(list, ctag.as_ptr()),
         }
+    };
+    if handle.is_null() {
+        return Err(std::io::Error::last_os_error().into());
     }
+    Ok(ListHandle(handle))
 }
 
 impl Drop for CtList {
     fn drop(&mut self) {
+        if let Some(route) = self.route {
+            self.client.events().unregister(route);
+        }
         if !self.handle.0.is_null() {
             // Safety: the handle was created by ctListNew and is valid.
             // `handle` is a plain field — no lock needed in Drop.
@@ -392,4 +1984,633 @@ mod tests {
         assert_send::<super::CtList>();
         assert_sync::<super::CtList>();
     }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_entries_in_insertion_order() {
+        use super::TagOptions;
+        use crate::CtClient;
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        for tag in ["Temperature", "Pressure", "Flow1"] {
+            list.add_tag(tag).unwrap();
+        }
+        list.add_tag_ex("Counter", true, 250, 1.0).unwrap();
+        list.delete_tag("Pressure").unwrap();
+
+        let entries: Vec<_> = list.entries().collect();
+        let names: Vec<_> = entries.iter().map(|entry| entry.name()).collect();
+        assert_eq!(names, ["Temperature", "Flow1", "Counter"]);
+        assert_eq!(entries[0].options(), None);
+        let options = TagOptions {
+            raw: true,
+            poll_period: 250,
+            deadband: 1.0,
+        };
+        assert_eq!(entries[2].options(), Some(options));
+
+        let handle = list.with_raw_handle("Flow1", |handle| handle).unwrap();
+        // SAFETY: Flow1 is still on the list.
+        assert_eq!(unsafe { entries[1].raw_handle() }, handle);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_set_raw_mode_keeps_options_and_reports_failures() {
+        use super::TagOptions;
+        use crate::CtClient;
+        use crate::mock::{self, MockServer};
+        use std::sync::Arc;
+
+        let server = MockServer::seeded().with_raw_unsupported("Status");
+        let client = Arc::new(CtClient::open_mock_with(server.clone()).unwrap());
+        let mut list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Temperature").unwrap();
+        list.add_tag_ex("Status", false, 1000, 0.0).unwrap();
+        list.add_tag_ex("Pressure", false, 250, 1.5).unwrap();
+        list.add_tag_ex("Counter", true, 2000, 0.0).unwrap();
+
+        let report = list.set_raw_mode(true).unwrap();
+        assert_eq!(report.changed, ["Temperature", "Pressure"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "Status");
+        assert!(!report.is_complete());
+
+        let names: Vec<_> = list
+            .entries()
+            .map(|entry| entry.name().to_string())
+            .collect();
+        assert_eq!(names, ["Temperature", "Status", "Pressure", "Counter"]);
+        let options = TagOptions {
+            raw: true,
+            poll_period: 250,
+            deadband: 1.5,
+        };
+        assert_eq!(list.tag_options("Pressure").unwrap(), options);
+        assert!(list.tag_options("Temperature").unwrap().raw);
+        assert!(!list.tag_options("Status").unwrap().raw);
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Pressure", 0).unwrap(), "1.2");
+
+        // Losing the connection stops the switch, which resumes once it is back
+        mock::reload(&client, server.clone().unreachable());
+        assert!(list.set_raw_mode(false).is_err());
+        mock::reload(&client, server);
+        let report = list.set_raw_mode(false).unwrap();
+        assert_eq!(report.changed, ["Temperature", "Pressure", "Counter"]);
+        assert!(report.is_complete());
+        assert_eq!(list.tag_options("Counter").unwrap().poll_period, 2000);
+        assert!(list.set_raw_mode(false).unwrap().changed.is_empty());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_resubscribe_after_reconnect() {
+        use crate::mock::{self, MockServer};
+        use crate::{CtApiError, CtClient};
+        use std::sync::Arc;
+
+        let server = MockServer::seeded();
+        let client = Arc::new(CtClient::open_mock_with(server.clone()).unwrap());
+        let mut list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Temperature").unwrap();
+        list.add_tag_ex("Status", true, 1000, 0.0).unwrap();
+        list.add_tag_ex("Pressure", false, 250, 1.5).unwrap();
+        list.read().unwrap();
+        assert_eq!(list.generation(), 0);
+
+        mock::simulate_reconnect(&client);
+        assert_eq!(client.reconnect_generation(), 1);
+        let err = list.read().unwrap_err();
+        assert!(matches!(err, CtApiError::ConnectionLost { generation: 1 }));
+        assert!(err.is_connection_error());
+
+        // Losing the connection again leaves the list as it was
+        mock::reload(&client, server.clone().unreachable());
+        assert!(list.resubscribe().is_err());
+        assert_eq!(list.generation(), 0);
+        assert_eq!(list.tags().len(), 3);
+
+        mock::reload(&client, server.with_raw_unsupported("Status"));
+        let report = list.resubscribe().unwrap();
+        assert_eq!(report.readded, ["Temperature", "Pressure"]);
+        assert_eq!(report.dropped.len(), 1);
+        assert_eq!(report.dropped[0].0, "Status");
+        assert_eq!(list.generation(), 1);
+        assert_eq!(list.tag_options("Pressure").unwrap().poll_period, 250);
+        let names: Vec<_> = list
+            .entries()
+            .map(|entry| entry.name().to_string())
+            .collect();
+        assert_eq!(names, ["Temperature", "Pressure"]);
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Pressure", 0).unwrap(), "1.2");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_tags_added_in_default_cluster() {
+        use crate::CtClient;
+        use crate::mock;
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let mut list = Arc::clone(&client).list_new(0).unwrap();
+        client.set_default_cluster(Some("Cluster1"));
+        list.add_tag("Temperature").unwrap();
+        list.add_tag("Cluster1.Setpoint").unwrap();
+        client.set_default_cluster(Some("Cluster3"));
+        list.add_tag_ex("Pressure", false, 250, 0.0).unwrap();
+
+        // Tags keep the name they were added under
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Temperature", 0).unwrap(), "25.5");
+        assert_eq!(list.read_tag("Cluster1.Setpoint", 0).unwrap(), "20");
+        assert!(list.read_tag("Pressure", 0).is_err());
+
+        // and are added under their first address again, whatever the
+        // default cluster is by then
+        mock::simulate_reconnect(&client);
+        client.set_default_cluster(None);
+        list.resubscribe().unwrap();
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Temperature", 0).unwrap(), "25.5");
+        assert!(list.read_tag("Pressure", 0).is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_with_raw_handle_denies_deleted_tags() {
+        use crate::CtClient;
+        use crate::error::CtApiError;
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Temperature").unwrap();
+        assert!(list.with_raw_handle("Temperature", |_| ()).is_ok());
+
+        list.delete_tag("Temperature").unwrap();
+        let result = list.with_raw_handle("Temperature", |_| panic!("handle of a deleted tag"));
+        assert!(matches!(result, Err(CtApiError::TagNotFound { .. })));
+        assert_eq!(list.entries().count(), 0);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_all_good_partitions_by_quality() {
+        use crate::CtClient;
+        use crate::mock::MockServer;
+        use crate::quality::{QUAL_BAD, QUAL_UNCERTAIN, QualityThreshold};
+        use std::sync::Arc;
+
+        let server = MockServer::new()
+            .with_tag("Good", "1")
+            .with_tag("Uncertain", "2")
+            .with_tag_quality("Uncertain", QUAL_UNCERTAIN, 0)
+            .with_tag("Offline", "3")
+            .with_tag_quality("Offline", QUAL_BAD, 2);
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        for tag in ["Good", "Uncertain", "Offline"] {
+            list.add_tag(tag).unwrap();
+        }
+
+        let strict = list.read_all_good(QualityThreshold::GoodOnly).unwrap();
+        assert_eq!(strict.good, [("Good".to_string(), "1".to_string())]);
+        let rejected: Vec<_> = strict
+            .rejected
+            .iter()
+            .map(|(tag, _)| tag.as_str())
+            .collect();
+        assert_eq!(rejected, ["Offline", "Uncertain"]);
+        let offline = strict.rejected[0].1;
+        assert!(offline.is_bad());
+        assert_eq!(offline.datasource_error.unwrap().code(), 2);
+
+        let lenient = list
+            .read_all_good(QualityThreshold::GoodOrUncertain)
+            .unwrap();
+        assert_eq!(lenient.good.len(), 2);
+        assert_eq!(lenient.good[1], ("Uncertain".to_string(), "2".to_string()));
+        assert_eq!(lenient.rejected.len(), 1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_rejected_write_reported_once() {
+        use crate::CtClient;
+        use crate::mock::{self, MockServer};
+        use crate::quality::{CitectError, QUAL_BAD};
+        use crate::write_outcome::WriteOutcome;
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Setpoint").unwrap();
+        list.add_tag("Pressure").unwrap();
+        list.read().unwrap();
+        list.write_tag("Pressure", "1.5").unwrap();
+        list.write_tag("Setpoint", "25").unwrap();
+
+        // The device takes the write, then refuses it
+        let rejected = MockServer::seeded().with_tag_quality("Setpoint", QUAL_BAD, 4);
+        mock::reload(&client, rejected);
+        assert!(list.pending_write_outcomes().is_empty());
+        list.read().unwrap();
+        list.read().unwrap();
+        assert_eq!(
+            list.pending_write_outcomes(),
+            [WriteOutcome::LikelyRejected {
+                tag: "Setpoint".to_string(),
+                write_seq: 2,
+                datasource_error: CitectError::from_code(4).unwrap(),
+            }]
+        );
+        list.read().unwrap();
+        assert!(list.pending_write_outcomes().is_empty());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_all_full_per_addressing_form() {
+        use crate::CtClient;
+        use crate::mock::MockServer;
+        use crate::quality::{QUAL_BAD, QUAL_GOOD, Quality, QualityThreshold};
+        use std::sync::Arc;
+
+        let server = MockServer::new()
+            .with_tag("Level", "42")
+            .with_tag("Flow[2]", "7.5")
+            .with_tag_quality("Flow[2]", QUAL_BAD, 2);
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        for tag in ["Level", "Flow[2]", "Level.V", "Flow[2].Q"] {
+            list.add_tag(tag).unwrap();
+        }
+        // Quality items of element fields are refused by the API
+        assert!(list.read_all_good(QualityThreshold::GoodOnly).is_err());
+
+        let readings = list.read_all_full().unwrap();
+        let summary: Vec<_> = readings
+            .iter()
+            .map(|r| {
+                (
+                    r.tag.as_str(),
+                    r.reading.value.as_str(),
+                    r.reading.quality.general,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Flow[2]", "7.5", QUAL_BAD),
+                ("Flow[2].Q", "0", Quality::NOT_APPLICABLE.general),
+                ("Level", "42", QUAL_GOOD),
+                ("Level.V", "42", Quality::NOT_APPLICABLE.general),
+            ]
+        );
+        let datasource_error = readings[0].reading.quality.datasource_error;
+        assert_eq!(datasource_error.unwrap().code(), 2);
+        assert!(readings[2].reading.quality.timestamp > 0);
+        assert!(readings[2].reading.value_timestamp.is_some());
+        assert_eq!(readings[3].reading.value_timestamp, None);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_all_keeps_per_tag_errors() {
+        use crate::{CtApiError, CtClient};
+        use std::sync::Arc;
+
+        let mut client = CtClient::open_mock().unwrap();
+        let list = Arc::new(client.clone()).list_new(0).unwrap();
+        for tag in ["Temperature", "Missing", "Pressure"] {
+            list.add_tag(tag).unwrap();
+        }
+        let values = list.snapshot(0).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["Temperature"].as_deref().ok(), Some("25.5"));
+        assert!(values["Missing"].is_err());
+
+        let ordered = list.read_all_ordered(0);
+        let tags: Vec<_> = ordered.iter().map(|(tag, _)| tag.as_str()).collect();
+        assert_eq!(tags, ["Temperature", "Missing", "Pressure"]);
+        assert_eq!(ordered[2].1.as_deref().ok(), Some("1.2"));
+
+        client.close_ex(false).unwrap();
+        let closed = list.read_all(0);
+        assert!(closed.values().all(|value| value.is_err()));
+        assert!(matches!(closed["Pressure"], Err(CtApiError::InvalidHandle)));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_duplicate_tags_keep_their_handle() {
+        use crate::{CtApiError, CtClient};
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = client.list_new(0).unwrap();
+        assert!(list.is_empty());
+        list.add_tag("Temperature").unwrap();
+        let handle = list.with_raw_handle("Temperature", |h| h as usize).unwrap();
+
+        let duplicate = list.add_tag_ex("Temperature", true, 250, 0.0);
+        assert!(matches!(duplicate, Err(CtApiError::DuplicateTag { tag }) if tag == "Temperature"));
+        let duplicate = list.add_tag("Temperature");
+        assert!(matches!(duplicate, Err(CtApiError::DuplicateTag { .. })));
+        assert_eq!(list.len(), 1);
+        assert!(list.contains_tag("Temperature"));
+        assert!(!list.contains_tag("Pressure"));
+        list.add_tag("Pressure").unwrap();
+        let mut seen = Vec::new();
+        list.for_each_tag(|tag| seen.push(tag.to_string()));
+        assert_eq!(seen, list.tags());
+        assert_eq!(seen, ["Pressure", "Temperature"]);
+        let kept = list.with_raw_handle("Temperature", |h| h as usize).unwrap();
+        assert_eq!(kept, handle);
+        assert!(!list.tag_options("Temperature").unwrap().raw);
+
+        // A deleted tag can be added again
+        list.delete_tag("Temperature").unwrap();
+        assert!(!list.contains_tag("Temperature"));
+        list.add_tag("Temperature").unwrap();
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Temperature", 0).unwrap(), "25.5");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_tag_item_types_each_item() {
+        use super::ListItemValue;
+        use crate::mock::MockServer;
+        use crate::quality::{QUAL_BAD, Quality, QualityStatus};
+        use crate::{CtApiError, CtClient, ReadItem};
+        use std::sync::Arc;
+
+        let server = MockServer::new()
+            .with_tag("Level", "42")
+            .with_tag("Flow[2]", "7.5")
+            .with_tag_quality("Flow[2]", QUAL_BAD, 2);
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        for tag in ["Level", "Flow[2]", "Level.V"] {
+            list.add_tag(tag).unwrap();
+        }
+        list.read().unwrap();
+
+        let read = |tag: &str, item| list.read_tag_item(tag, item, 0).unwrap();
+        assert_eq!(
+            read("Level", ReadItem::Value),
+            ListItemValue::Value("42".to_string())
+        );
+        assert!(read("Level", ReadItem::Timestamp).to_utc().is_some());
+        assert_eq!(
+            read("Flow[2]", ReadItem::QualityGeneral),
+            ListItemValue::Quality(QUAL_BAD)
+        );
+        let error = read("Flow[2]", ReadItem::DatasourceError);
+        let ListItemValue::DatasourceError(Some(error)) = error else {
+            panic!("no data source error: {error:?}");
+        };
+        assert_eq!(error.code(), 2);
+        assert_eq!(
+            read("Level", ReadItem::Override),
+            ListItemValue::Flag(false)
+        );
+        assert_eq!(read("Level.V", ReadItem::Value).to_utc(), None);
+        let general = list.read_tag_item("Level.V", ReadItem::QualityGeneral, 0);
+        assert!(general.is_err());
+        let missing = list.read_tag_item("Missing", ReadItem::Value, 0);
+        assert!(matches!(missing, Err(CtApiError::TagNotFound { .. })));
+
+        let flow = list.read_tag_full("Flow[2]").unwrap();
+        assert_eq!(flow.value, "7.5");
+        assert_eq!(flow.quality.status(), QualityStatus::Bad);
+        assert!(flow.timestamp.is_some());
+        let field = list.read_tag_full("Level.V").unwrap();
+        assert_eq!(field.quality, Quality::NOT_APPLICABLE);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_event_list_reports_changes_until_dropped() {
+        use super::{ListEvent, ListEventKind};
+        use crate::CtClient;
+        use crate::constants::{CT_LIST_EVENT_NEW, CT_LIST_EVENT_STATUS};
+        use crate::mock::MockServer;
+        use std::sync::{Arc, mpsc};
+        use std::time::Duration;
+
+        let server = MockServer::new()
+            .with_tag("Level", "42")
+            .with_tag("Flow", "7.5");
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let list = Arc::new(Arc::clone(&client).list_new_event(0).unwrap());
+        list.add_tag("Level").unwrap();
+        list.add_tag("Flow").unwrap();
+
+        // Read events until the list is dropped
+        let (sender, events) = mpsc::channel();
+        let watched = Arc::downgrade(&list);
+        let consumer = std::thread::spawn(move || {
+            let mode = CT_LIST_EVENT_NEW | CT_LIST_EVENT_STATUS;
+            while let Some(list) = watched.upgrade() {
+                match list.next_event(mode).unwrap() {
+                    Some(event) => sender.send(event).unwrap(),
+                    None => std::thread::sleep(Duration::from_millis(5)),
+                }
+            }
+        });
+        let next = || events.recv_timeout(Duration::from_secs(5)).unwrap();
+        let event = |tag: &str, value: &str, kind| ListEvent {
+            tag: tag.to_string(),
+            value: value.to_string(),
+            kind,
+        };
+
+        let mut added = [next(), next()];
+        added.sort_by(|a, b| a.tag.cmp(&b.tag));
+        assert_eq!(
+            added,
+            [
+                event("Flow", "7.5", ListEventKind::New),
+                event("Level", "42", ListEventKind::New),
+            ]
+        );
+        client.tag_write("Level", 43).unwrap();
+        assert_eq!(next(), event("Level", "43", ListEventKind::Status));
+        assert_eq!(list.read_tag("Level", 0).unwrap(), "43");
+
+        drop(list);
+        consumer.join().unwrap();
+        assert!(events.try_recv().is_err());
+
+        // Lists created without CT_LIST_EVENT report nothing
+        let polled = Arc::clone(&client).list_new(0).unwrap();
+        polled.add_tag("Level").unwrap();
+        assert_eq!(polled.next_event(CT_LIST_EVENT_NEW).unwrap(), None);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_event_lists_of_one_client_get_their_own_events() {
+        use crate::CtClient;
+        use crate::constants::CT_LIST_EVENT_NEW;
+        use crate::mock::MockServer;
+        use std::sync::Arc;
+
+        let server = MockServer::new()
+            .with_tag("Level", "42")
+            .with_tag("Flow", "7.5");
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+        let levels = Arc::clone(&client).list_new_event(0).unwrap();
+        levels.add_tag("Level").unwrap();
+        let flows = Arc::clone(&client).list_new_event(0).unwrap();
+        flows.add_tag("Flow").unwrap();
+        let tag = |list: &CtList, mode| list.next_event(mode).unwrap().map(|event| event.tag);
+
+        // CtAPI hands out the Level event first; the Flow list keeps it for
+        // the Level list instead of dropping it
+        assert_eq!(tag(&flows, CT_LIST_EVENT_NEW).as_deref(), Some("Flow"));
+        assert_eq!(tag(&flows, CT_LIST_EVENT_NEW), None);
+        assert_eq!(tag(&levels, CT_LIST_EVENT_NEW).as_deref(), Some("Level"));
+        assert_eq!(tag(&levels, CT_LIST_EVENT_NEW), None);
+
+        client.tag_write("Level", 43).unwrap();
+        client.tag_write("Flow", 8).unwrap();
+        assert_eq!(tag(&flows, 0).as_deref(), Some("Flow"));
+        drop(flows);
+        assert_eq!(tag(&levels, 0).as_deref(), Some("Level"));
+        assert_eq!(tag(&levels, 0), None);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_generations_around_pending_read() {
+        use crate::{AsyncOperation, CtClient};
+        use std::sync::Arc;
+
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Temperature").unwrap();
+        list.add_tag("Setpoint").unwrap();
+        assert_eq!(list.read_generation(), Generation::NEVER);
+        list.read().unwrap();
+        assert_eq!(list.read_generation(), Generation(1));
+
+        // Read 2 is pending: Pressure is not part of it, and Setpoint added
+        // again gets a handle that only read 3 fetches
+        let mut op = AsyncOperation::new();
+        list.read_async(&mut op).unwrap();
+        list.add_tag("Pressure").unwrap();
+        list.delete_tag("Setpoint").unwrap();
+        list.add_tag("Setpoint").unwrap();
+        op.get_result(&client).unwrap();
+        let stamps: Vec<_> = list
+            .entries()
+            .map(|e| (e.name().to_string(), e.added_in(), e.last_read()))
+            .collect();
+        assert_eq!(
+            stamps,
+            [
+                ("Temperature".to_string(), Generation::NEVER, Generation(1)),
+                ("Pressure".to_string(), Generation(1), Generation::NEVER),
+                ("Setpoint".to_string(), Generation(1), Generation::NEVER),
+            ]
+        );
+        assert_eq!(list.read_generation(), Generation(1));
+        assert!(list.read_tag("Pressure", 0).is_err());
+
+        list.delete_tag("Temperature").unwrap();
+        let readings = list.read_all_full().unwrap();
+        assert_eq!(readings.generation(), Generation(3));
+        let fresh: Vec<_> = readings
+            .fresh_only()
+            .into_iter()
+            .map(|r| (r.tag, r.reading.value, r.freshness))
+            .collect();
+        assert_eq!(
+            fresh,
+            [
+                ("Pressure".to_string(), "1.2".to_string(), Generation(3)),
+                ("Setpoint".to_string(), "20".to_string(), Generation(3)),
+            ]
+        );
+        assert_eq!(list.entries().next().unwrap().last_read(), Generation(3));
+    }
+
+    #[test]
+    fn test_fresh_only_drops_tags_not_in_read() {
+        let reading = |tag: &str, freshness| ListReading {
+            tag: tag.to_string(),
+            reading: TagReading::new(String::new(), Quality::NOT_APPLICABLE),
+            freshness,
+        };
+        let readings = ListReadings {
+            generation: Generation(4),
+            readings: vec![
+                reading("Flow", Generation(4)),
+                reading("Level", Generation::NEVER),
+                reading("Pump", Generation(5)),
+            ],
+        };
+        assert_eq!(readings.len(), 3);
+        let fresh: Vec<_> = readings
+            .fresh_only()
+            .iter()
+            .map(|r| r.tag.clone())
+            .collect();
+        assert_eq!(fresh, ["Flow", "Pump"]);
+        assert_eq!(Generation::NEVER.to_string(), "never");
+        assert_eq!(Generation(4).to_string(), "read 4");
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::sync::atomic::{AtomicBool, Ordering};
+    use loom::thread;
+
+    /// A tag handle that records being released by `ctListDelete`
+    #[derive(Default)]
+    struct Handle {
+        released: AtomicBool,
+    }
+
+    #[test]
+    fn loom_read_never_sees_deleted_handle() {
+        loom::model(|| {
+            let table = Arc::new(TagTable::new());
+            table.insert("Level", || Ok(Handle::default())).unwrap();
+
+            let reader = {
+                let table = table.clone();
+                thread::spawn(move || {
+                    table.with("Level", |handle| {
+                        assert!(!handle.released.load(Ordering::SeqCst), "use after delete");
+                        Ok(())
+                    })
+                })
+            };
+            table
+                .remove("Level", |handle| {
+                    handle.released.store(true, Ordering::SeqCst);
+                    Ok(())
+                })
+                .unwrap();
+
+            match reader.join().unwrap() {
+                Ok(()) | Err(CtApiError::TagNotFound { .. }) => {}
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+            assert_eq!(table.len(), 0);
+        });
+    }
 }
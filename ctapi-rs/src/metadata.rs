@@ -0,0 +1,377 @@
+//! Cached tag metadata
+//!
+//! Engineering units and ranges of a tag rarely change while a project runs,
+//! but they do when the Citect project is recompiled and reloaded.
+//! [`CtClient::tag_metadata`](crate::CtClient::tag_metadata) serves them from a
+//! per-client [`MetadataCache`] whose entries expire after a TTL and which can
+//! be cleared with [`invalidate`](MetadataCache::invalidate).
+//!
+//! A cache can also watch a [`ReloadIndicator`], such as a Cicode expression
+//! returning the project modification time or a tag holding the project
+//! version. The indicator is checked lazily, at most once per TTL, when
+//! metadata is requested; if its value changed since the last check, every
+//! cached entry is dropped before the request is served.
+
+use crate::CtClient;
+use crate::clock::{SharedClock, system_clock};
+use crate::error::{CtApiError, Result};
+use ctapi_sys::DBTYPEENUM;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Default lifetime of cached metadata
+pub const DEFAULT_METADATA_TTL: Duration = Duration::from_secs(300);
+
+/// Engineering metadata of a tag
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TagMetadata {
+    /// Engineering units (`EngUnits`)
+    pub units: String,
+    /// Engineering value at the bottom of the scale (`EngZero`)
+    pub eng_zero: f64,
+    /// Engineering value at the top of the scale (`EngFull`)
+    pub eng_full: f64,
+}
+
+impl TagMetadata {
+    /// Read the metadata of `tag` from the server
+    pub(crate) fn read(client: &CtClient, tag: &str) -> Result<Self> {
+        let number = |property: &str| {
+            let value = client.tag_get_property(tag, property, DBTYPEENUM::DBTYPE_R8)?;
+            value.as_f64().ok_or_else(|| CtApiError::Other {
+                code: 0,
+                message: format!("{tag}.{property} is not a number: {value}"),
+            })
+        };
+        Ok(Self {
+            units: client
+                .tag_get_property(tag, "EngUnits", DBTYPEENUM::DBTYPE_STR)?
+                .to_string(),
+            eng_zero: number("EngZero")?,
+            eng_full: number("EngFull")?,
+        })
+    }
+}
+
+/// Value that changes when the Citect project is reloaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadIndicator {
+    /// Result of a Cicode expression, for example one returning the project
+    /// modification time
+    Cicode(String),
+    /// Value of a tag, for example one holding the project version
+    Tag(String),
+}
+
+/// Counters of a [`MetadataCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetadataStats {
+    /// Requests answered from the cache
+    pub hits: u64,
+    /// Requests that read metadata from the server
+    pub misses: u64,
+    /// Misses caused by an entry older than the TTL
+    pub expired: u64,
+    /// Project reloads detected through the reload indicator
+    pub reloads: u64,
+    /// Entries dropped because a reload was detected
+    pub stale_entries: u64,
+    /// Calls to [`MetadataCache::invalidate`]
+    pub invalidations: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    ttl: Duration,
+    indicator: Option<ReloadIndicator>,
+    /// Last value of the indicator and when it was read
+    marker: Option<(String, Instant)>,
+    entries: HashMap<String, (TagMetadata, Instant)>,
+}
+
+/// Cache of tag metadata with a TTL and project reload detection
+#[derive(Debug)]
+pub struct MetadataCache {
+    clock: SharedClock,
+    state: Mutex<State>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    expired: AtomicU64,
+    reloads: AtomicU64,
+    stale_entries: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
+
+impl MetadataCache {
+    pub(crate) fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            state: Mutex::new(State {
+                ttl: DEFAULT_METADATA_TTL,
+                indicator: None,
+                marker: None,
+                entries: HashMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+            reloads: AtomicU64::new(0),
+            stale_entries: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// Lifetime of cached entries and interval between reload checks
+    pub fn ttl(&self) -> Duration {
+        self.lock().ttl
+    }
+
+    /// Change the lifetime of cached entries and the reload check interval
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.lock().ttl = ttl;
+    }
+
+    /// Watch `indicator` for project reloads, or stop watching with `None`
+    pub fn set_reload_indicator(&self, indicator: Option<ReloadIndicator>) {
+        let mut state = self.lock();
+        state.indicator = indicator;
+        state.marker = None;
+    }
+
+    /// Drop every cached entry
+    ///
+    /// Call this when the project is known to have been reloaded.
+    pub fn invalidate(&self) {
+        self.lock().entries.clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counters since creation
+    pub fn stats(&self) -> MetadataStats {
+        MetadataStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            reloads: self.reloads.load(Ordering::Relaxed),
+            stale_entries: self.stale_entries.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of cached entries, including expired ones not yet replaced
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Return the metadata of `tag`, running `fetch` if it is not cached
+    ///
+    /// If the reload indicator is due for a check, `probe` reads its current
+    /// value first; a changed value drops every entry.
+    pub(crate) fn get_or_fetch<P, F>(&self, tag: &str, probe: P, fetch: F) -> Result<TagMetadata>
+    where
+        P: FnOnce(&ReloadIndicator) -> Result<String>,
+        F: FnOnce() -> Result<TagMetadata>,
+    {
+        self.check_reload(probe)?;
+
+        let key = tag.to_ascii_lowercase();
+        {
+            let state = self.lock();
+            if let Some((metadata, fetched_at)) = state.entries.get(&key) {
+                if self.clock.now().duration_since(*fetched_at) < state.ttl {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(metadata.clone());
+                }
+                self.expired.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let metadata = fetch()?;
+        self.lock()
+            .entries
+            .insert(key, (metadata.clone(), self.clock.now()));
+        Ok(metadata)
+    }
+
+    /// Read the reload indicator if it is due and drop all entries if it changed
+    fn check_reload<P>(&self, probe: P) -> Result<()>
+    where
+        P: FnOnce(&ReloadIndicator) -> Result<String>,
+    {
+        let now = self.clock.now();
+        let indicator = {
+            let state = self.lock();
+            match (&state.indicator, &state.marker) {
+                (None, _) => return Ok(()),
+                (Some(_), Some((_, checked_at))) if now.duration_since(*checked_at) < state.ttl => {
+                    return Ok(());
+                }
+                (Some(indicator), _) => indicator.clone(),
+            }
+        };
+        let value = probe(&indicator)?;
+
+        let mut state = self.lock();
+        if state.indicator.as_ref() != Some(&indicator) {
+            // Replaced while probing; the new indicator starts from scratch
+            return Ok(());
+        }
+        let changed = state
+            .marker
+            .as_ref()
+            .is_some_and(|(previous, _)| *previous != value);
+        if changed {
+            let dropped = state.entries.len() as u64;
+            state.entries.clear();
+            self.reloads.fetch_add(1, Ordering::Relaxed);
+            self.stale_entries.fetch_add(dropped, Ordering::Relaxed);
+        }
+        state.marker = Some((value, now));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    fn metadata(units: &str) -> TagMetadata {
+        TagMetadata {
+            units: units.to_string(),
+            eng_zero: 0.0,
+            eng_full: 100.0,
+        }
+    }
+
+    fn no_probe(_: &ReloadIndicator) -> Result<String> {
+        panic!("no indicator configured")
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let clock = MockClock::new();
+        let cache = MetadataCache::with_clock(clock.clone());
+        cache.set_ttl(Duration::from_secs(60));
+
+        let first = cache
+            .get_or_fetch("Level", no_probe, || Ok(metadata("%")))
+            .unwrap();
+        assert_eq!(first.units, "%");
+        let cached = cache
+            .get_or_fetch("LEVEL", no_probe, || panic!("cached"))
+            .unwrap();
+        assert_eq!(cached, first);
+
+        clock.advance(Duration::from_secs(60));
+        let refreshed = cache
+            .get_or_fetch("Level", no_probe, || Ok(metadata("m")))
+            .unwrap();
+        assert_eq!(refreshed.units, "m");
+        assert_eq!(
+            cache.stats(),
+            MetadataStats {
+                hits: 1,
+                misses: 2,
+                expired: 1,
+                ..MetadataStats::default()
+            }
+        );
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_invalidate_and_failed_fetch() {
+        let cache = MetadataCache::default();
+        cache
+            .get_or_fetch("Level", no_probe, || Ok(metadata("%")))
+            .unwrap();
+        cache.invalidate();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().invalidations, 1);
+
+        let error = cache.get_or_fetch("Level", no_probe, || Err(CtApiError::Timeout));
+        assert!(matches!(error, Err(CtApiError::Timeout)));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_reload_indicator_checked_once_per_ttl() {
+        let clock = MockClock::new();
+        let cache = MetadataCache::with_clock(clock.clone());
+        cache.set_ttl(Duration::from_secs(60));
+        cache.set_reload_indicator(Some(ReloadIndicator::Tag("ProjectVersion".to_string())));
+
+        let version = Arc::new(Mutex::new("1".to_string()));
+        let probes = Cell::new(0);
+        let probe = |indicator: &ReloadIndicator| {
+            assert_eq!(
+                indicator,
+                &ReloadIndicator::Tag("ProjectVersion".to_string())
+            );
+            probes.set(probes.get() + 1);
+            Ok(version.lock().unwrap().clone())
+        };
+
+        cache
+            .get_or_fetch("Level", probe, || Ok(metadata("%")))
+            .unwrap();
+        cache
+            .get_or_fetch("Flow", probe, || Ok(metadata("l/s")))
+            .unwrap();
+        assert_eq!(probes.get(), 1);
+
+        // A reload between checks goes unnoticed until the next check is due
+        *version.lock().unwrap() = "2".to_string();
+        clock.advance(Duration::from_secs(30));
+        cache
+            .get_or_fetch("Level", probe, || panic!("cached"))
+            .unwrap();
+        assert_eq!(probes.get(), 1);
+
+        clock.advance(Duration::from_secs(30));
+        let fresh = cache
+            .get_or_fetch("Level", probe, || Ok(metadata("m")))
+            .unwrap();
+        assert_eq!(fresh.units, "m");
+        assert_eq!(probes.get(), 2);
+        assert_eq!(cache.len(), 1);
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.reloads, stats.stale_entries, stats.expired),
+            (1, 2, 0)
+        );
+    }
+
+    #[test]
+    fn test_probe_failure_is_reported() {
+        let cache = MetadataCache::default();
+        cache.set_reload_indicator(Some(ReloadIndicator::Cicode("ProjectTime()".to_string())));
+        let result = cache.get_or_fetch(
+            "Level",
+            |_| Err(CtApiError::Timeout),
+            || panic!("not fetched without a reload check"),
+        );
+        assert!(matches!(result, Err(CtApiError::Timeout)));
+    }
+}
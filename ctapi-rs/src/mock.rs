@@ -0,0 +1,2084 @@
+//! In-process CtAPI backend for examples and tests
+//!
+//! With the `mock` feature, [`CtClient::open_mock`] returns a client that
+//! talks to a [`MockServer`] in this process instead of Citect SCADA. Tag
+//! reads and writes, lists, finds and Cicode calls behave like the real API
+//! closely enough for the documentation examples to run on any machine:
+//!
+//! ```
+//! use ctapi_rs::CtClient;
+//!
+//! let client = CtClient::open_mock()?;
+//! client.tag_write_str("Setpoint", "30")?;
+//! assert_eq!(client.tag_read("Setpoint")?, "30");
+//! # Ok::<(), ctapi_rs::CtApiError>(())
+//! ```
+//!
+//! Clients opened with [`CtClient::open`] still use CtAPI.dll; only handles
+//! created by the mock are routed to it. Every mock client has its own copy
+//! of the server it was opened with.
+//!
+//! [`CtClient::open_mock`]: crate::CtClient::open_mock
+//! [`CtClient::open`]: crate::CtClient::open
+
+#![allow(non_snake_case)]
+
+use crate::constants::{
+    CT_FIND_SCROLL_ABSOLUTE, CT_FIND_SCROLL_FIRST, CT_FIND_SCROLL_LAST, CT_FIND_SCROLL_NEXT,
+    CT_FIND_SCROLL_PREV, CT_FIND_SCROLL_RELATIVE, CT_LIST_EVENT, CT_LIST_EVENT_NEW,
+    CT_LIST_EVENT_STATUS, CT_LIST_QUALITY_DATASOURCE_ERROR, CT_LIST_QUALITY_GENERAL,
+    CT_LIST_QUALITY_TIMESTAMP, CT_LIST_TIMESTAMP, CT_LIST_VALUE, CT_LIST_VALUE_TIMESTAMP,
+    CT_OPEN_RECONNECT,
+};
+use crate::filetime::FILETIME_UNIX_EPOCH;
+use crate::quality::QUAL_GOOD;
+use crate::tag_path::{AddressingForm, TagField, TagPath};
+use ctapi_sys::{CtApiVersion, CtTagValueItems, DBTYPEENUM, DWORD, LPCSTR, LPSTR, OVERLAPPED};
+use encoding_rs::GBK;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{CStr, c_void};
+use std::os::windows::io::RawHandle;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Win32 `ERROR_INVALID_FUNCTION`, reported for Cicode the server does not know
+const ERROR_INVALID_FUNCTION: u32 = 1;
+/// Win32 `ERROR_ACCESS_DENIED`, reported for writes a tag rejects
+const ERROR_ACCESS_DENIED: u32 = 5;
+/// Win32 `ERROR_INVALID_HANDLE`
+const ERROR_INVALID_HANDLE: u32 = 6;
+/// Win32 `ERROR_INVALID_DATA`, reported when a value does not fit the requested type
+const ERROR_INVALID_DATA: u32 = 13;
+/// Win32 `ERROR_NOT_SUPPORTED`, reported for quality items of element fields
+/// and for raw values of tags that have none
+///
+/// The code CtAPI uses to refuse quality items of fields is assumed, see the
+/// [`tag_path`](crate::tag_path) module documentation.
+const ERROR_NOT_SUPPORTED: u32 = 50;
+/// Win32 `ERROR_INVALID_PARAMETER`, reported for tag value items the DLL does not know
+const ERROR_INVALID_PARAMETER: u32 = 87;
+/// Win32 `ERROR_NO_MORE_ITEMS`, reported at the end of a find and when no
+/// list event is waiting
+const ERROR_NO_MORE_ITEMS: u32 = 259;
+/// Win32 `ERROR_OPERATION_ABORTED`, reported for cancelled overlapped calls
+const ERROR_OPERATION_ABORTED: u32 = 995;
+/// Win32 `ERROR_IO_PENDING`, reported for overlapped calls that have not completed
+const ERROR_IO_PENDING: u32 = 997;
+/// Win32 `ERROR_NOT_FOUND`, reported for unknown tags, tables and properties
+const ERROR_NOT_FOUND: u32 = 1168;
+/// `STATUS_PENDING`, the OVERLAPPED status of a call in progress
+const STATUS_PENDING: DWORD = 0x103;
+/// `RPC_S_SERVER_UNAVAILABLE`, reported when connecting to an unreachable server
+const RPC_S_SERVER_UNAVAILABLE: u32 = 1722;
+
+/// A tag of a [`MockServer`]
+#[derive(Debug, Clone, Default)]
+struct MockTag {
+    value: String,
+    timestamp: u64,
+    properties: BTreeMap<String, String>,
+    /// General quality and data source error; good without error if `None`
+    quality: Option<(u8, u32)>,
+    /// Writes accepted before further writes are rejected; unlimited if `None`
+    writes_left: Option<usize>,
+    /// Whether adding the tag to a list for raw values fails
+    raw_unsupported: bool,
+}
+
+impl MockTag {
+    fn quality(&self) -> (u8, u32) {
+        self.quality.unwrap_or((QUAL_GOOD, 0))
+    }
+}
+
+/// Tags, tables and Cicode results served by a mock client
+///
+/// Tag and field names are matched case-insensitively, as Citect SCADA does.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::mock::MockServer;
+///
+/// let server = MockServer::new()
+///     .with_tag("Level", "42")
+///     .with_record("Tag", &[("TAG", "Level"), ("UNITS", "%")])
+///     .with_cicode("Version(0)", "8.20.0.0");
+/// let client = CtClient::open_mock_with(server)?;
+/// assert_eq!(client.tag_read("level")?, "42");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockServer {
+    tags: BTreeMap<String, MockTag>,
+    tables: BTreeMap<String, Vec<Vec<(String, String)>>>,
+    /// Tables only visible to searches naming their cluster, keyed by cluster and table
+    cluster_tables: BTreeMap<(String, String), Vec<Vec<(String, String)>>>,
+    cicode: HashMap<String, String>,
+    /// Table name and cluster of the most recent search
+    last_find: Option<(String, Option<String>)>,
+    /// `ctFindNext` calls a find cursor answers before it expires; unlimited if `None`
+    cursor_lifetime: Option<usize>,
+    /// Tag value items revision of the emulated DLL, and whether longer ones are rejected
+    items_version: (CtApiVersion, bool),
+    /// Time every tag read takes
+    read_latency: Duration,
+    /// Whether `ctOpenEx` fails as if the server were down
+    unreachable: bool,
+    /// Time `ctOpenEx` takes to connect
+    connect_latency: Duration,
+    /// Whether a `ctOpenEx` is in progress, and whether it was cancelled
+    connecting: (bool, bool),
+    /// Cicode commands whose overlapped calls stay pending until waited for
+    pending_cicode: HashSet<String>,
+    /// Cicode commands whose overlapped calls only complete when cancelled
+    hanging_cicode: HashSet<String>,
+    /// Tags whose overlapped list reads and writes stay pending until waited for
+    stalled: HashSet<String>,
+    /// Pending overlapped calls by the address of their OVERLAPPED
+    in_flight: HashMap<usize, InFlightCall>,
+}
+
+/// An overlapped call started but not yet collected
+#[derive(Debug, Clone)]
+struct InFlightCall {
+    /// Address and length of the caller's result buffer, 0 for calls without one
+    buffer: usize,
+    len: DWORD,
+    /// Result to deliver, `None` once cancelled
+    result: Option<String>,
+    /// Whether waiting for the call blocks until it is cancelled
+    hangs: bool,
+}
+
+impl InFlightCall {
+    /// A call completing without a result, such as a write or list read
+    fn empty() -> Self {
+        Self {
+            buffer: 0,
+            len: 0,
+            result: Some(String::new()),
+            hangs: false,
+        }
+    }
+}
+
+impl MockServer {
+    /// An empty server
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The server used by [`CtClient::open_mock`](crate::CtClient::open_mock)
+    ///
+    /// Holds the tags used throughout the documentation (`Temperature`,
+    /// `Pressure`, `Setpoint`, `Tag1`, ...), a `Tag` table describing them in
+    /// cluster `Cluster1`, one active alarm in the `Alarm` table and the
+    /// results of `Time(1)`, `Date(4)` and the functions read by
+    /// [`CtClient::server_info`](crate::CtClient::server_info): project
+    /// `Example`, user `Manager` and the single cluster `Cluster1`.
+    pub fn seeded() -> Self {
+        const TAGS: &[(&str, &str, &str)] = &[
+            ("Temperature", "25.5", "°C"),
+            ("Pressure", "1.2", "bar"),
+            ("Setpoint", "20", "°C"),
+            ("Counter", "0", ""),
+            ("Status", "Stopped", ""),
+            ("Pump_Start", "0", ""),
+            ("Tag1", "0", ""),
+            ("Flow1", "12.5", "m3/h"),
+            ("Flow2", "11.8", "m3/h"),
+            ("FlowRate", "24.3", "m3/h"),
+            ("Tank1_Level", "63.0", "%"),
+            ("Pump1", "1", ""),
+            ("Pump2", "0", ""),
+            ("BIT_1", "1", ""),
+            ("BIT_2", "0", ""),
+        ];
+        let mut server = Self::new();
+        for &(tag, value, units) in TAGS {
+            server = server.with_tag(tag, value).with_record(
+                "Tag",
+                &[("TAG", tag), ("CLUSTER", "Cluster1"), ("UNITS", units)],
+            );
+        }
+        server
+            .with_tag_property("Temperature", "EngUnits", "°C")
+            .with_tag_property("Temperature", "EngZero", "0")
+            .with_tag_property("Temperature", "EngFull", "100")
+            .with_record(
+                "Alarm",
+                &[
+                    ("TAG", "Pump1_Fault"),
+                    ("CLUSTER", "Cluster1"),
+                    ("STATE", "ON"),
+                ],
+            )
+            .with_cicode("Time(1)", "10:30:00")
+            .with_cicode("Date(4)", "2024-01-15")
+            .with_cicode("Version(0)", "8.20.0.0")
+            .with_cicode("ProjectInfo(0)", "Example")
+            .with_cicode("Name()", "Manager")
+            .with_cicode("ClusterFirst()", "Cluster1")
+            .with_cicode("ClusterNext(\"Cluster1\")", "")
+    }
+
+    /// Add a tag, or replace its value
+    pub fn with_tag(mut self, name: &str, value: &str) -> Self {
+        let tag = self.tags.entry(name.to_ascii_lowercase()).or_default();
+        tag.value = value.to_string();
+        tag.timestamp = now_ticks();
+        self
+    }
+
+    /// Set the quality reported for a tag, adding the tag if needed
+    ///
+    /// `general` is one of the `QUAL_*` constants and `datasource_error` the
+    /// I/O driver error code, `0` for none. Tags are good by default.
+    pub fn with_tag_quality(mut self, name: &str, general: u8, datasource_error: u32) -> Self {
+        self.tags
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .quality = Some((general, datasource_error));
+        self
+    }
+
+    /// Reject writes to a tag after the next `writes` writes, adding the tag if needed
+    ///
+    /// Rejected writes fail with `ERROR_ACCESS_DENIED` and leave the value
+    /// unchanged. A limit of `0` makes the tag read-only.
+    pub fn with_write_limit(mut self, name: &str, writes: usize) -> Self {
+        self.tags
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .writes_left = Some(writes);
+        self
+    }
+
+    /// Fail adding a tag to a list for raw values with `ERROR_NOT_SUPPORTED`,
+    /// adding the tag if needed
+    pub fn with_raw_unsupported(mut self, name: &str) -> Self {
+        self.tags
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .raw_unsupported = true;
+        self
+    }
+
+    /// Set a property read by `CtClient::tag_get_property`, adding the tag if needed
+    pub fn with_tag_property(mut self, tag: &str, property: &str, value: &str) -> Self {
+        self.tags
+            .entry(tag.to_ascii_lowercase())
+            .or_default()
+            .properties
+            .insert(property.to_ascii_lowercase(), value.to_string());
+        self
+    }
+
+    /// Append a record to a table searched by `CtClient::find_first`
+    pub fn with_record(mut self, table: &str, fields: &[(&str, &str)]) -> Self {
+        let record = fields
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.tables
+            .entry(table.to_ascii_lowercase())
+            .or_default()
+            .push(record);
+        self
+    }
+
+    /// Append a record to a table only searched when `cluster` is passed to
+    /// `CtClient::find_first`
+    ///
+    /// Records added with [`with_record`](Self::with_record) are found in
+    /// every cluster.
+    pub fn with_cluster_record(
+        mut self,
+        cluster: &str,
+        table: &str,
+        fields: &[(&str, &str)],
+    ) -> Self {
+        let record = fields
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.cluster_tables
+            .entry((cluster.to_ascii_lowercase(), table.to_ascii_lowercase()))
+            .or_default()
+            .push(record);
+        self
+    }
+
+    /// Expire every find cursor after `moves` calls of `ctFindNext`
+    ///
+    /// The next `ctFindNext` fails with `ERROR_INVALID_HANDLE`, as it does
+    /// once the server has discarded an idle cursor. Scrolling does not count
+    /// towards the lifetime.
+    pub fn with_cursor_lifetime(mut self, moves: usize) -> Self {
+        self.cursor_lifetime = Some(moves);
+        self
+    }
+
+    /// Answer the Cicode command `cmd` with `result`
+    ///
+    /// Commands are matched exactly; unknown commands fail.
+    pub fn with_cicode(mut self, cmd: &str, result: &str) -> Self {
+        self.cicode.insert(cmd.to_string(), result.to_string());
+        self
+    }
+
+    /// Like [`with_cicode`](Self::with_cicode), but overlapped calls of `cmd`
+    /// report `ERROR_IO_PENDING` and only complete once their result is
+    /// waited for
+    ///
+    /// Polling the result without waiting keeps reporting the call as
+    /// pending, and `ctCancelIO` makes it complete with
+    /// `ERROR_OPERATION_ABORTED`.
+    pub fn with_pending_cicode(mut self, cmd: &str, result: &str) -> Self {
+        self.pending_cicode.insert(cmd.to_string());
+        self.with_cicode(cmd, result)
+    }
+
+    /// Like [`with_pending_cicode`](Self::with_pending_cicode), but waiting
+    /// for an overlapped call of `cmd` blocks until `ctCancelIO` makes it
+    /// complete with `ERROR_OPERATION_ABORTED`
+    pub fn with_hanging_cicode(mut self, cmd: &str) -> Self {
+        self.hanging_cicode.insert(cmd.to_string());
+        self.with_cicode(cmd, "")
+    }
+
+    /// Make the I/O device of tag `name` stop answering
+    ///
+    /// Overlapped reads of a list holding the tag and overlapped writes to it
+    /// report `ERROR_IO_PENDING` and only complete once their result is
+    /// waited for; `ctCancelIO` makes them complete with
+    /// `ERROR_OPERATION_ABORTED`. Blocking reads and writes still answer.
+    pub fn with_stalled_tag(mut self, name: &str) -> Self {
+        self.stalled.insert(name.to_string());
+        self
+    }
+
+    /// Whether `name` refers to a tag set up by [`with_stalled_tag`](Self::with_stalled_tag)
+    fn is_stalled(&self, name: &str) -> bool {
+        let key = self.tag_key(name);
+        self.stalled.iter().any(|tag| self.tag_key(tag) == key)
+    }
+
+    fn tag(&self, name: &str) -> Option<&MockTag> {
+        self.tags.get(&self.tag_key(name))
+    }
+
+    /// Key of the tag `name`, without the prefix of a known cluster
+    fn tag_key(&self, name: &str) -> String {
+        let key = name.to_ascii_lowercase();
+        match key.split_once('.') {
+            Some((cluster, tag)) if self.tags.contains_key(tag) && self.is_cluster(cluster) => {
+                tag.to_string()
+            }
+            _ => key,
+        }
+    }
+
+    /// Whether `name` is named by a cluster record or the `CLUSTER` field of
+    /// a `Tag` record
+    fn is_cluster(&self, name: &str) -> bool {
+        let in_tag_table = self.tables.get("tag").is_some_and(|records| {
+            records.iter().flatten().any(|(field, value)| {
+                field.eq_ignore_ascii_case("CLUSTER") && value.eq_ignore_ascii_case(name)
+            })
+        });
+        let mut clusters = self.cluster_tables.keys().map(|(cluster, _)| cluster);
+        in_tag_table || clusters.any(|cluster| cluster == name)
+    }
+
+    /// Emulate a CtAPI.dll that only knows the `version` tag value items
+    ///
+    /// Longer structures passed to `ctTagReadEx` are filled up to the length
+    /// of `version`, which is echoed back in their `length` field.
+    pub fn with_items_version(mut self, version: CtApiVersion) -> Self {
+        self.items_version = (version, false);
+        self
+    }
+
+    /// Like [`with_items_version`](Self::with_items_version), but reject
+    /// longer structures with `ERROR_INVALID_PARAMETER`
+    pub fn with_strict_items_version(mut self, version: CtApiVersion) -> Self {
+        self.items_version = (version, true);
+        self
+    }
+
+    /// Fail [`CtClient::connect`](crate::CtClient::connect), Cicode calls,
+    /// list tag additions and searches with `RPC_S_SERVER_UNAVAILABLE`, as a
+    /// server that is not running would
+    ///
+    /// With `CT_OPEN_RECONNECT`, `ctOpenEx` succeeds and only reports the
+    /// failure through `GetLastError`, leaving the connection to CtAPI's
+    /// reconnect thread like the real library does.
+    pub fn unreachable(mut self) -> Self {
+        self.unreachable = true;
+        self
+    }
+
+    /// Make `ctOpenEx` take `latency` to connect
+    ///
+    /// `ctCancelIO` without an OVERLAPPED aborts a connect in progress with
+    /// `ERROR_OPERATION_ABORTED`.
+    pub fn with_connect_latency(mut self, latency: Duration) -> Self {
+        self.connect_latency = latency;
+        self
+    }
+
+    /// Make every tag read block for `latency` before it answers
+    pub fn with_read_latency(mut self, latency: Duration) -> Self {
+        self.read_latency = latency;
+        self
+    }
+
+    /// The tag read by the reference `name`
+    ///
+    /// Fields `V`, `Q` and `T` are derived from the tag or element they
+    /// qualify; array elements and other fields must be added as tags.
+    fn resolve(&self, name: &str) -> Option<MockTag> {
+        if let Some(tag) = self.tag(name) {
+            return Some(tag.clone());
+        }
+        let path = TagPath::parse(name).ok()?;
+        let base = self.tag(name.rsplit_once('.')?.0)?;
+        let value = match path.element_field()? {
+            TagField::Value => base.value.clone(),
+            TagField::Quality => base.quality().0.to_string(),
+            TagField::Timestamp => base.timestamp.to_string(),
+            _ => return None,
+        };
+        Some(MockTag {
+            value,
+            timestamp: base.timestamp,
+            ..MockTag::default()
+        })
+    }
+
+    /// Write a tag, returning the Win32 error code of a rejected write
+    ///
+    /// Fields `V`, `Q` and `T` write the value, general quality and
+    /// timestamp of the tag they qualify.
+    fn write(&mut self, name: &str, value: &str) -> Result<(), u32> {
+        let key = self.tag_key(name);
+        let Some(tag) = self.tags.get_mut(&key) else {
+            return self.write_field(name, value);
+        };
+        if let Some(writes_left) = &mut tag.writes_left {
+            *writes_left = writes_left.checked_sub(1).ok_or(ERROR_ACCESS_DENIED)?;
+        }
+        tag.value = value.to_string();
+        tag.timestamp = now_ticks();
+        Ok(())
+    }
+
+    fn write_field(&mut self, name: &str, value: &str) -> Result<(), u32> {
+        let path = TagPath::parse(name).map_err(|_| ERROR_NOT_FOUND)?;
+        let (base, _) = name.rsplit_once('.').ok_or(ERROR_NOT_FOUND)?;
+        if path.element_field() == Some(&TagField::Value) {
+            return self.write(base, value);
+        }
+        let key = self.tag_key(base);
+        let tag = self.tags.get_mut(&key).ok_or(ERROR_NOT_FOUND)?;
+        match path.element_field() {
+            Some(TagField::Quality) => {
+                let general = value.parse().map_err(|_| ERROR_INVALID_DATA)?;
+                tag.quality = Some((general, tag.quality().1));
+            }
+            Some(TagField::Timestamp) => {
+                tag.timestamp = value.parse().map_err(|_| ERROR_INVALID_DATA)?;
+            }
+            _ => return Err(ERROR_NOT_FOUND),
+        }
+        Ok(())
+    }
+
+    fn find(
+        &self,
+        table: &str,
+        filter: &str,
+        cluster: Option<&str>,
+    ) -> Option<Vec<Vec<(String, String)>>> {
+        let filter = parse_filter(filter);
+        let table = table.to_ascii_lowercase();
+        let records = cluster
+            .and_then(|cluster| {
+                self.cluster_tables
+                    .get(&(cluster.to_ascii_lowercase(), table.clone()))
+            })
+            .or_else(|| self.tables.get(&table))?;
+        Some(
+            records
+                .iter()
+                .filter(|record| matches_filter(record, &filter))
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// Current time in FILETIME ticks
+fn now_ticks() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    FILETIME_UNIX_EPOCH + (since_epoch.as_nanos() / 100) as u64
+}
+
+/// Alternatives of `field=value` criteria that must all match
+pub(crate) type Filter = Vec<Vec<(String, String)>>;
+
+/// Parse `A=1 B=2 OR A=3` into `[[(A, 1), (B, 2)], [(A, 3)]]`
+///
+/// Values may be quoted, with `^` escaping the next character.
+pub(crate) fn parse_filter(filter: &str) -> Filter {
+    let mut alternatives = vec![Vec::new()];
+    let mut chars = filter.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut token = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '^' if quoted => token.extend(chars.next()),
+                c if c.is_whitespace() && !quoted => break,
+                c => token.push(c),
+            }
+        }
+        if token.is_empty() {
+            break;
+        }
+        match token.split_once('=') {
+            Some((field, value)) => alternatives
+                .last_mut()
+                .expect("at least one alternative")
+                .push((field.to_string(), value.to_string())),
+            None if token.eq_ignore_ascii_case("OR") => alternatives.push(Vec::new()),
+            None => {}
+        }
+    }
+    alternatives
+}
+
+pub(crate) fn matches_filter(record: &[(String, String)], filter: &Filter) -> bool {
+    filter.iter().any(|criteria| {
+        criteria.iter().all(|(field, pattern)| {
+            record
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(field))
+                .is_some_and(|(_, value)| wildcard_match(pattern, value))
+        })
+    })
+}
+
+/// Case-insensitive match supporting `*` and `?`
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// ───────────────────────────────────────────────
+// Handle registry
+// ───────────────────────────────────────────────
+
+type SharedServer = Arc<Mutex<MockServer>>;
+
+#[derive(Debug)]
+enum Resource {
+    Connection(SharedServer),
+    /// A connection closed with `ctCloseEx(handle, false)`, reopened by `ctOpenEx`
+    Disconnected(SharedServer),
+    /// A list and the mode it was created with
+    List(SharedServer, DWORD),
+    ListTag {
+        list: usize,
+        server: SharedServer,
+        name: String,
+        read: Option<MockTag>,
+    },
+    Find {
+        records: Vec<Vec<(String, String)>>,
+        /// 1-based position of the current record, 0 before the first
+        position: usize,
+        /// Object handle of the current record
+        objects: Vec<usize>,
+        /// `ctFindNext` calls left before the cursor expires; unlimited if `None`
+        moves_left: Option<usize>,
+        /// Server answering `ctFindNext`
+        server: SharedServer,
+    },
+    FindObject(Vec<(String, String)>),
+    /// A released handle. It stays registered so that it fails instead of
+    /// being forwarded to CtAPI.dll.
+    Closed,
+}
+
+/// A registered object. Its handle is the address of `anchor`, so mock
+/// handles never collide with each other or with handles from CtAPI.dll.
+#[derive(Debug)]
+struct Entry {
+    anchor: Box<u8>,
+    object: Resource,
+}
+
+static OBJECTS: LazyLock<Mutex<HashMap<usize, Entry>>> = LazyLock::new(Default::default);
+
+fn objects() -> std::sync::MutexGuard<'static, HashMap<usize, Entry>> {
+    OBJECTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn register(objects: &mut HashMap<usize, Entry>, object: Resource) -> usize {
+    let anchor = Box::new(0u8);
+    let id = &*anchor as *const u8 as usize;
+    objects.insert(id, Entry { anchor, object });
+    id
+}
+
+fn handle(id: usize) -> RawHandle {
+    id as RawHandle
+}
+
+/// Whether `handle` was created by the mock
+fn is_mock(handle: RawHandle) -> bool {
+    objects().contains_key(&(handle as usize))
+}
+
+/// Mark `id` as closed
+fn release(objects: &mut HashMap<usize, Entry>, id: usize) {
+    if let Some(entry) = objects.get_mut(&id) {
+        entry.object = Resource::Closed;
+    }
+}
+
+/// Register the object handle of a found record
+fn register_find_object(
+    objects: &mut HashMap<usize, Entry>,
+    record: Vec<(String, String)>,
+) -> usize {
+    register(objects, Resource::FindObject(record))
+}
+
+/// Lists of a mock client that have not been freed
+#[cfg(test)]
+pub(crate) fn open_lists(client: &crate::CtClient) -> usize {
+    let objects = objects();
+    let shared = server_of(&objects, client.handle()).expect("not a mock client");
+    objects
+        .values()
+        .filter(|entry| match &entry.object {
+            Resource::List(server, _) => Arc::ptr_eq(server, &shared),
+            _ => false,
+        })
+        .count()
+}
+
+/// Overlapped calls of a mock client started but not yet collected
+#[cfg(test)]
+pub(crate) fn in_flight_calls(client: &crate::CtClient) -> usize {
+    let shared = server_of(&objects(), client.handle()).expect("not a mock client");
+    lock(&shared).in_flight.len()
+}
+
+/// Register a connection to `server` and return its handle
+pub(crate) fn connect(server: MockServer) -> RawHandle {
+    let mut objects = objects();
+    handle(register(
+        &mut objects,
+        Resource::Connection(Arc::new(Mutex::new(server))),
+    ))
+}
+
+/// Replace the project served to a mock client, as a project reload would
+///
+/// Tags, tables and Cicode results of `client`'s server are replaced by those
+/// of `server`. Open lists keep the values of their last read.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::mock::{self, MockServer};
+///
+/// let client = CtClient::open_mock()?;
+/// mock::reload(&client, MockServer::new().with_tag("Level", "7"));
+/// assert_eq!(client.tag_read("Level")?, "7");
+/// assert!(client.tag_read("Temperature").is_err());
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+///
+/// # Panics
+/// If `client` is not connected to a mock server.
+pub fn reload(client: &crate::CtClient, server: MockServer) {
+    let shared = server_of(&objects(), client.handle()).expect("not a mock client");
+    *lock(&shared) = server;
+}
+
+/// Let a mock client lose its connection and get it back, as CtAPI does for
+/// clients opened with `CT_OPEN_RECONNECT`
+///
+/// The lists of the client are discarded, so that calls on them fail with
+/// `ERROR_INVALID_HANDLE`, and the client's connection state goes through
+/// [`Reconnecting`](crate::ConnectionState::Reconnecting) back to
+/// [`Connected`](crate::ConnectionState::Connected), advancing its
+/// [reconnect generation](crate::CtClient::reconnect_generation).
+///
+/// # Panics
+/// If `client` is not connected to a mock server.
+pub fn simulate_reconnect(client: &crate::CtClient) {
+    {
+        let mut objects = objects();
+        let shared = server_of(&objects, client.handle()).expect("not a mock client");
+        let lists: HashSet<usize> = objects
+            .iter()
+            .filter(|(_, entry)| match &entry.object {
+                Resource::List(server, _) => Arc::ptr_eq(server, &shared),
+                _ => false,
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for entry in objects.values_mut() {
+            let discarded = match &entry.object {
+                Resource::ListTag { list, .. } => lists.contains(list),
+                Resource::List(server, _) => Arc::ptr_eq(server, &shared),
+                _ => false,
+            };
+            if discarded {
+                entry.object = Resource::Closed;
+            }
+        }
+    }
+    let state = client.state_tracker();
+    state.set(crate::ConnectionState::Reconnecting, None);
+    state.set(crate::ConnectionState::Connected, None);
+}
+
+/// Table name and cluster passed to the most recent search of a mock client
+///
+/// The cluster is `None` if the search did not name one.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::mock;
+///
+/// let client = CtClient::open_mock()?;
+/// client.find_first("Alarm", "STATE=ON", Some("Cluster1")).count();
+/// assert_eq!(
+///     mock::last_find(&client),
+///     Some(("Alarm".to_string(), Some("Cluster1".to_string())))
+/// );
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+///
+/// # Panics
+/// If `client` is not connected to a mock server.
+pub fn last_find(client: &crate::CtClient) -> Option<(String, Option<String>)> {
+    let shared = server_of(&objects(), client.handle()).expect("not a mock client");
+    lock(&shared).last_find.clone()
+}
+
+fn fail<T>(code: u32, result: T) -> T {
+    // SAFETY: SetLastError only stores the code for the calling thread.
+    unsafe { windows_sys::Win32::Foundation::SetLastError(code) };
+    result
+}
+
+fn server_of(objects: &HashMap<usize, Entry>, handle: RawHandle) -> Option<SharedServer> {
+    match &objects.get(&(handle as usize))?.object {
+        Resource::Connection(server) | Resource::List(server, _) => Some(Arc::clone(server)),
+        _ => None,
+    }
+}
+
+fn lock(server: &SharedServer) -> std::sync::MutexGuard<'_, MockServer> {
+    server.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Decode a NUL-terminated GBK argument
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn arg(s: LPCSTR) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    // SAFETY: guaranteed by the caller.
+    let bytes = unsafe { CStr::from_ptr(s) }.to_bytes();
+    GBK.decode(bytes).0.into_owned()
+}
+
+/// Copy `value` GBK-encoded and NUL-terminated into `buffer`, truncating if
+/// needed, and return its full encoded length
+///
+/// # Safety
+/// `buffer` must be writable for `len` bytes.
+unsafe fn put_str(value: &str, buffer: *mut c_void, len: DWORD) -> usize {
+    let encoded = GBK.encode(value).0;
+    if !buffer.is_null() && len > 0 {
+        let n = encoded.len().min(len as usize - 1);
+        // SAFETY: n + 1 <= len bytes are written, as guaranteed by the caller.
+        unsafe {
+            std::ptr::copy_nonoverlapping(encoded.as_ptr(), buffer.cast::<u8>(), n);
+            *buffer.cast::<u8>().add(n) = 0;
+        }
+    }
+    encoded.len()
+}
+
+/// Write `value` converted to `ty` into `buffer`, returning the result length
+///
+/// # Safety
+/// `buffer` must be writable for `len` bytes.
+unsafe fn put_typed(value: &str, ty: u32, buffer: *mut c_void, len: DWORD) -> Option<usize> {
+    use DBTYPEENUM::*;
+    let number = || value.trim().parse::<f64>().ok();
+    let bytes: Vec<u8> = match ty {
+        t if t == DBTYPE_STR as u32 => {
+            // SAFETY: guaranteed by the caller.
+            return Some(unsafe { put_str(value, buffer, len) });
+        }
+        t if t == DBTYPE_I1 as u32 => (number()? as i8).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_UI1 as u32 => (number()? as u8).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_I2 as u32 => (number()? as i16).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_UI2 as u32 => (number()? as u16).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_BOOL as u32 => (-i16::from(number()? != 0.0)).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_I4 as u32 => (number()? as i32).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_UI4 as u32 => (number()? as u32).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_R4 as u32 => (number()? as f32).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_I8 as u32 => (number()? as i64).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_UI8 as u32 => (number()? as u64).to_ne_bytes().to_vec(),
+        t if t == DBTYPE_R8 as u32 => number()?.to_ne_bytes().to_vec(),
+        _ => return None,
+    };
+    if buffer.is_null() || (len as usize) < bytes.len() {
+        return None;
+    }
+    // SAFETY: bytes.len() <= len bytes are written, as guaranteed by the caller.
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.cast::<u8>(), bytes.len()) };
+    Some(bytes.len())
+}
+
+/// Complete an overlapped operation immediately
+///
+/// # Safety
+/// `overlapped` must be null or point to a live OVERLAPPED.
+unsafe fn complete(overlapped: *mut OVERLAPPED, bytes: usize) -> bool {
+    if !overlapped.is_null() {
+        // SAFETY: guaranteed by the caller. Packed fields are written through
+        // the raw pointer without creating references.
+        unsafe {
+            (*overlapped).dwStatus = 0;
+            (*overlapped).dwLength = bytes as DWORD;
+            let event = (*overlapped).hEvent;
+            if !event.is_null() {
+                windows_sys::Win32::System::Threading::SetEvent(event);
+            }
+        }
+    }
+    true
+}
+
+/// Leave `call` pending until it is waited for, reporting `ERROR_IO_PENDING`
+///
+/// # Safety
+/// `overlapped` must be live and stay in place until the call is collected.
+unsafe fn park(server: &SharedServer, overlapped: *mut OVERLAPPED, call: InFlightCall) -> bool {
+    lock(server).in_flight.insert(overlapped as usize, call);
+    // SAFETY: guaranteed by the caller.
+    unsafe { (*overlapped).dwStatus = STATUS_PENDING };
+    fail(ERROR_IO_PENDING, false)
+}
+
+/// Value of `field` in a find record, including the `object.fields` metadata
+fn record_property(record: &[(String, String)], name: &str) -> Option<String> {
+    if name.eq_ignore_ascii_case("object.fields.count") {
+        return Some(record.len().to_string());
+    }
+    if let Some((index, metadata)) = name
+        .strip_prefix("object.fields(")
+        .and_then(|rest| rest.split_once(")."))
+    {
+        let index: usize = index.trim().parse().ok()?;
+        let (field, value) = record.get(index.checked_sub(1)?)?;
+        // Every field is a string, as in most CtAPI tables
+        return match metadata.to_ascii_lowercase().as_str() {
+            "name" => Some(field.clone()),
+            "type" => Some((DBTYPEENUM::DBTYPE_STR as u32).to_string()),
+            "actualsize" => Some(GBK.encode(value).0.len().to_string()),
+            _ => None,
+        };
+    }
+    record
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+// ───────────────────────────────────────────────
+// CtAPI entry points
+//
+// Same signatures as ctapi-sys. Calls on handles the mock did not create are
+// forwarded to CtAPI.dll.
+// ───────────────────────────────────────────────
+
+pub(crate) unsafe fn ctClose(hCTAPI: RawHandle) -> bool {
+    let mut objects = objects();
+    match objects.get(&(hCTAPI as usize)).map(|entry| &entry.object) {
+        Some(Resource::Connection(_)) => {
+            release(&mut objects, hCTAPI as usize);
+            true
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctClose(hCTAPI) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctCloseEx(hCTAPI: RawHandle, bDestroy: bool) -> bool {
+    let mut objects = objects();
+    let Some(entry) = objects.get_mut(&(hCTAPI as usize)) else {
+        drop(objects);
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctCloseEx(hCTAPI, bDestroy) };
+    };
+    match &entry.object {
+        Resource::Connection(_) if bDestroy => release(&mut objects, hCTAPI as usize),
+        Resource::Connection(server) => {
+            entry.object = Resource::Disconnected(Arc::clone(server));
+        }
+        _ => return fail(ERROR_INVALID_HANDLE, false),
+    }
+    true
+}
+
+pub(crate) unsafe fn ctClientDestroy(hCTAPI: RawHandle) -> bool {
+    let mut objects = objects();
+    match objects.get(&(hCTAPI as usize)).map(|entry| &entry.object) {
+        Some(Resource::Connection(_) | Resource::Disconnected(_)) => {
+            release(&mut objects, hCTAPI as usize);
+            true
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctClientDestroy(hCTAPI) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctOpenEx(
+    sComputer: LPCSTR,
+    sUser: LPCSTR,
+    sPassword: LPCSTR,
+    nMode: DWORD,
+    hCTAPI: RawHandle,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctOpenEx(sComputer, sUser, sPassword, nMode, hCTAPI) };
+    }
+    let mut objects = objects();
+    let Some(entry) = objects.get_mut(&(hCTAPI as usize)) else {
+        return fail(ERROR_INVALID_HANDLE, false);
+    };
+    let server = match &entry.object {
+        Resource::Connection(server) | Resource::Disconnected(server) => Arc::clone(server),
+        _ => return fail(ERROR_INVALID_HANDLE, false),
+    };
+    if lock(&server).unreachable {
+        if nMode & CT_OPEN_RECONNECT == 0 {
+            return fail(RPC_S_SERVER_UNAVAILABLE, false);
+        }
+        // The handle is open and CtAPI keeps trying in the background
+        entry.object = Resource::Connection(server);
+        return fail(RPC_S_SERVER_UNAVAILABLE, true);
+    }
+    let latency = {
+        let mut server = lock(&server);
+        server.connecting = (true, false);
+        server.connect_latency
+    };
+    drop(objects);
+    let deadline = std::time::Instant::now() + latency;
+    loop {
+        let cancelled = lock(&server).connecting.1;
+        let now = std::time::Instant::now();
+        if cancelled || now >= deadline {
+            lock(&server).connecting = (false, false);
+            if cancelled {
+                return fail(ERROR_OPERATION_ABORTED, false);
+            }
+            break;
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(5)));
+    }
+    match objects().get_mut(&(hCTAPI as usize)) {
+        Some(entry) => {
+            entry.object = Resource::Connection(server);
+            true
+        }
+        None => fail(ERROR_INVALID_HANDLE, false),
+    }
+}
+
+pub(crate) unsafe fn ctCancelIO(hCTAPI: RawHandle, pctOverlapped: *mut OVERLAPPED) -> bool {
+    if is_mock(hCTAPI) {
+        if let Some(server) = server_of(&objects(), hCTAPI) {
+            let mut server = lock(&server);
+            if pctOverlapped.is_null() {
+                for call in server.in_flight.values_mut() {
+                    call.result = None;
+                }
+                if server.connecting.0 {
+                    server.connecting.1 = true;
+                }
+            } else if let Some(call) = server.in_flight.get_mut(&(pctOverlapped as usize)) {
+                call.result = None;
+                return true;
+            }
+        }
+        // Other mock operations complete before they return, so they can
+        // never be cancelled and cancelling everything has nothing more to do
+        return pctOverlapped.is_null() || fail(ERROR_NOT_FOUND, false);
+    }
+    // SAFETY: not a mock handle; passed through unchanged.
+    unsafe { ctapi_sys::ctCancelIO(hCTAPI, pctOverlapped) }
+}
+
+pub(crate) unsafe fn ctGetOverlappedResult(
+    hCTAPI: RawHandle,
+    lpctOverlapped: *mut OVERLAPPED,
+    pBytes: *mut DWORD,
+    bWait: bool,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctGetOverlappedResult(hCTAPI, lpctOverlapped, pBytes, bWait) };
+    }
+    let key = lpctOverlapped as usize;
+    let call = match server_of(&objects(), hCTAPI) {
+        Some(server) if bWait => loop {
+            let mut server = lock(&server);
+            match server.in_flight.get(&key) {
+                Some(call) if call.hangs && call.result.is_some() => {
+                    drop(server);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                _ => break server.in_flight.remove(&key),
+            }
+        },
+        Some(server) if lock(&server).in_flight.contains_key(&key) => {
+            return fail(ERROR_IO_PENDING, false);
+        }
+        _ => None,
+    };
+    if let Some(call) = call {
+        // SAFETY: the OVERLAPPED and result buffer were handed to ctCicode,
+        // which the caller keeps alive until the call completes.
+        unsafe {
+            let Some(result) = call.result else {
+                (*lpctOverlapped).dwStatus = ERROR_OPERATION_ABORTED;
+                return fail(ERROR_OPERATION_ABORTED, false);
+            };
+            let len = put_str(&result, call.buffer as *mut c_void, call.len);
+            complete(lpctOverlapped, (len + 1).min(call.len as usize));
+        }
+    }
+    // SAFETY: the caller passes the OVERLAPPED of an operation completed by
+    // `complete` and a writable byte count.
+    unsafe {
+        if !pBytes.is_null() {
+            *pBytes = if lpctOverlapped.is_null() {
+                0
+            } else {
+                (*lpctOverlapped).dwLength
+            };
+        }
+    }
+    true
+}
+
+pub(crate) unsafe fn ctCicode(
+    hCTAPI: RawHandle,
+    sCmd: LPCSTR,
+    vhWin: DWORD,
+    nMode: DWORD,
+    sResult: LPSTR,
+    dwLength: DWORD,
+    pctOverlapped: *mut OVERLAPPED,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe {
+            ctapi_sys::ctCicode(hCTAPI, sCmd, vhWin, nMode, sResult, dwLength, pctOverlapped)
+        };
+    }
+    let Some(server) = server_of(&objects(), hCTAPI) else {
+        return fail(ERROR_INVALID_HANDLE, false);
+    };
+    // SAFETY: the caller passes a NUL-terminated command.
+    let cmd = unsafe { arg(sCmd) };
+    let (result, pending, hangs) = {
+        let server = lock(&server);
+        if server.unreachable {
+            return fail(RPC_S_SERVER_UNAVAILABLE, false);
+        }
+        let cmd = cmd.trim();
+        let hangs = server.hanging_cicode.contains(cmd);
+        let pending = hangs || server.pending_cicode.contains(cmd);
+        (server.cicode.get(cmd).cloned(), pending, hangs)
+    };
+    match result {
+        Some(result) if pending && !pctOverlapped.is_null() => {
+            let call = InFlightCall {
+                buffer: sResult as usize,
+                len: dwLength,
+                result: Some(result),
+                hangs,
+            };
+            // SAFETY: the caller passes a live OVERLAPPED that stays in place
+            // until the call is collected.
+            unsafe { park(&server, pctOverlapped, call) }
+        }
+        // SAFETY: the caller passes a result buffer of dwLength bytes and a
+        // null or live OVERLAPPED.
+        Some(result) => unsafe {
+            let len = put_str(&result, sResult.cast(), dwLength);
+            complete(pctOverlapped, (len + 1).min(dwLength as usize))
+        },
+        None => fail(ERROR_INVALID_FUNCTION, false),
+    }
+}
+
+pub(crate) unsafe fn ctTagRead(
+    hCTAPI: RawHandle,
+    sTag: LPCSTR,
+    sValue: LPSTR,
+    dwLength: DWORD,
+) -> bool {
+    // SAFETY: forwarded to ctTagReadEx with the caller's guarantees.
+    unsafe { ctTagReadEx(hCTAPI, sTag, sValue, dwLength, std::ptr::null_mut()) }
+}
+
+pub(crate) unsafe fn ctTagReadEx(
+    hCTAPI: RawHandle,
+    sTag: LPCSTR,
+    sValue: LPSTR,
+    dwLength: DWORD,
+    pctTagvalueItems: *mut CtTagValueItems,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe {
+            if pctTagvalueItems.is_null() {
+                ctapi_sys::ctTagRead(hCTAPI, sTag, sValue, dwLength)
+            } else {
+                ctapi_sys::ctTagReadEx(hCTAPI, sTag, sValue, dwLength, pctTagvalueItems)
+            }
+        };
+    }
+    let Some(server) = server_of(&objects(), hCTAPI) else {
+        return fail(ERROR_INVALID_HANDLE, false);
+    };
+    // SAFETY: the caller passes a NUL-terminated tag name.
+    let name = unsafe { arg(sTag) };
+    // Like CtAPI, refuse quality items for element fields
+    if !pctTagvalueItems.is_null() && !TagPath::form_of(&name).has_quality() {
+        return fail(ERROR_NOT_SUPPORTED, false);
+    }
+    let (tag, (version, strict), latency) = {
+        let server = lock(&server);
+        (
+            server.resolve(&name),
+            server.items_version,
+            server.read_latency,
+        )
+    };
+    if !latency.is_zero() {
+        std::thread::sleep(latency);
+    }
+    let Some(tag) = tag else {
+        return fail(ERROR_NOT_FOUND, false);
+    };
+    let supported = version.items_len();
+    // SAFETY: the caller passes a null or writable CtTagValueItems, whose
+    // length field is read without creating a reference to the packed struct.
+    let requested = (!pctTagvalueItems.is_null())
+        .then(|| unsafe { std::ptr::addr_of!((*pctTagvalueItems).length).read_unaligned() });
+    if strict && requested.is_some_and(|len| len > supported) {
+        return fail(ERROR_INVALID_PARAMETER, false);
+    }
+
+    let (general, datasource_error) = tag.quality();
+    let items = CtTagValueItems {
+        length: 0,
+        timestamp: tag.timestamp,
+        value_timestamp: tag.timestamp,
+        quality_timestamp: tag.timestamp,
+        quality_general: general,
+        quality_datasource_error: datasource_error,
+        ..CtTagValueItems::default()
+    };
+    // SAFETY: the caller passes a value buffer of dwLength bytes. Only the
+    // fields within the caller's length, and known to the emulated DLL, are
+    // copied after the length field.
+    unsafe {
+        put_str(&tag.value, sValue.cast(), dwLength);
+        if let Some(requested) = requested {
+            let len = requested.min(supported).min(CtTagValueItems::LEN) as usize;
+            let start = size_of::<u32>();
+            if len > start {
+                let source = std::ptr::addr_of!(items).cast::<u8>().add(start);
+                let target = pctTagvalueItems.cast::<u8>().add(start);
+                std::ptr::copy_nonoverlapping(source, target, len - start);
+            }
+            std::ptr::addr_of_mut!((*pctTagvalueItems).length).write_unaligned(len as u32);
+        }
+    }
+    true
+}
+
+pub(crate) unsafe fn ctTagWrite(hCTAPI: RawHandle, sTag: LPCSTR, sValue: LPCSTR) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctTagWrite(hCTAPI, sTag, sValue) };
+    }
+    // SAFETY: forwarded with the caller's guarantees.
+    unsafe { ctTagWriteEx(hCTAPI, sTag, sValue, std::ptr::null_mut()) }
+}
+
+pub(crate) unsafe fn ctTagWriteEx(
+    hCTAPI: RawHandle,
+    sTag: LPCSTR,
+    sValue: LPCSTR,
+    pctOverlapped: *mut OVERLAPPED,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctTagWriteEx(hCTAPI, sTag, sValue, pctOverlapped) };
+    }
+    let Some(server) = server_of(&objects(), hCTAPI) else {
+        return fail(ERROR_INVALID_HANDLE, false);
+    };
+    // SAFETY: the caller passes NUL-terminated strings.
+    let (name, value) = unsafe { (arg(sTag), arg(sValue)) };
+    let stalled = {
+        let mut server = lock(&server);
+        if let Err(code) = server.write(&name, &value) {
+            return fail(code, false);
+        }
+        server.is_stalled(&name)
+    };
+    if stalled && !pctOverlapped.is_null() {
+        // SAFETY: the caller passes a live OVERLAPPED that stays in place
+        // until the call is collected.
+        return unsafe { park(&server, pctOverlapped, InFlightCall::empty()) };
+    }
+    // SAFETY: the caller passes a null or live OVERLAPPED.
+    unsafe { complete(pctOverlapped, 0) }
+}
+
+pub(crate) unsafe fn ctTagGetProperty(
+    hCTAPI: RawHandle,
+    szTagName: LPCSTR,
+    szProperty: LPCSTR,
+    pData: *mut c_void,
+    dwBufferLength: DWORD,
+    dwType: DWORD,
+) -> bool {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe {
+            ctapi_sys::ctTagGetProperty(
+                hCTAPI,
+                szTagName,
+                szProperty,
+                pData,
+                dwBufferLength,
+                dwType,
+            )
+        };
+    }
+    let Some(server) = server_of(&objects(), hCTAPI) else {
+        return fail(ERROR_INVALID_HANDLE, false);
+    };
+    // SAFETY: the caller passes NUL-terminated strings.
+    let (name, property) = unsafe { (arg(szTagName), arg(szProperty)) };
+    let value = lock(&server)
+        .tag(&name)
+        .and_then(|tag| tag.properties.get(&property.to_ascii_lowercase()).cloned());
+    let Some(value) = value else {
+        return fail(ERROR_NOT_FOUND, false);
+    };
+    // SAFETY: the caller passes a buffer of dwBufferLength bytes.
+    match unsafe { put_typed(&value, dwType, pData, dwBufferLength) } {
+        Some(_) => true,
+        None => fail(ERROR_INVALID_DATA, false),
+    }
+}
+
+pub(crate) unsafe fn ctListNew(hCTAPI: RawHandle, dwMode: DWORD) -> RawHandle {
+    let mut objects = objects();
+    match &objects.get(&(hCTAPI as usize)).map(|entry| &entry.object) {
+        Some(Resource::Connection(server)) => {
+            let list = Resource::List(Arc::clone(server), dwMode);
+            handle(register(&mut objects, list))
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, std::ptr::null_mut()),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctListNew(hCTAPI, dwMode) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctListFree(hList: RawHandle) -> bool {
+    let mut objects = objects();
+    let list = hList as usize;
+    match objects.get(&list).map(|entry| &entry.object) {
+        Some(Resource::List(..)) => {
+            release(&mut objects, list);
+            for entry in objects.values_mut() {
+                if matches!(entry.object, Resource::ListTag { list: owner, .. } if owner == list) {
+                    entry.object = Resource::Closed;
+                }
+            }
+            true
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctListFree(hList) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctListAdd(hList: RawHandle, sTag: LPCSTR) -> RawHandle {
+    if !is_mock(hList) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctListAdd(hList, sTag) };
+    }
+    // SAFETY: forwarded with the caller's guarantees.
+    unsafe { ctListAddEx(hList, sTag, false, 0, 0.0) }
+}
+
+pub(crate) unsafe fn ctListAddEx(
+    hList: RawHandle,
+    sTag: LPCSTR,
+    bRaw: bool,
+    nPollPeriodMS: i32,
+    dDeadband: f64,
+) -> RawHandle {
+    let mut objects = objects();
+    let list = hList as usize;
+    match objects.get(&list).map(|entry| &entry.object) {
+        Some(Resource::List(server, _)) => {
+            // SAFETY: the caller passes a NUL-terminated tag name.
+            let name = unsafe { arg(sTag) };
+            {
+                let server = lock(server);
+                if server.unreachable {
+                    return fail(RPC_S_SERVER_UNAVAILABLE, std::ptr::null_mut());
+                }
+                if bRaw && server.tag(&name).is_some_and(|tag| tag.raw_unsupported) {
+                    return fail(ERROR_NOT_SUPPORTED, std::ptr::null_mut());
+                }
+            }
+            let tag = Resource::ListTag {
+                list,
+                server: Arc::clone(server),
+                name,
+                read: None,
+            };
+            handle(register(&mut objects, tag))
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, std::ptr::null_mut()),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctListAddEx(hList, sTag, bRaw, nPollPeriodMS, dDeadband) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctListDelete(hTag: RawHandle) -> bool {
+    let mut objects = objects();
+    match objects.get(&(hTag as usize)).map(|entry| &entry.object) {
+        Some(Resource::ListTag { .. }) => {
+            release(&mut objects, hTag as usize);
+            true
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctListDelete(hTag) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctListRead(hList: RawHandle, pctOverlapped: *mut OVERLAPPED) -> bool {
+    let mut objects = objects();
+    let list = hList as usize;
+    let list_server = match objects.get(&list).map(|entry| &entry.object) {
+        Some(Resource::List(server, _)) => Arc::clone(server),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctListRead(hList, pctOverlapped) };
+        }
+    };
+    let mut stalled = false;
+    for entry in objects.values_mut() {
+        if let Resource::ListTag {
+            list: owner,
+            server,
+            name,
+            read,
+        } = &mut entry.object
+            && *owner == list
+        {
+            let server = lock(server);
+            *read = server.resolve(name);
+            stalled |= server.is_stalled(name);
+        }
+    }
+    drop(objects);
+    if stalled && !pctOverlapped.is_null() {
+        // SAFETY: the caller passes a live OVERLAPPED that stays in place
+        // until the call is collected.
+        return unsafe { park(&list_server, pctOverlapped, InFlightCall::empty()) };
+    }
+    // SAFETY: the caller passes a null or live OVERLAPPED.
+    unsafe { complete(pctOverlapped, 0) }
+}
+
+pub(crate) unsafe fn ctListData(
+    hTag: RawHandle,
+    pBuffer: *mut c_void,
+    dwLength: DWORD,
+    dwMode: DWORD,
+) -> bool {
+    let objects = objects();
+    let (read, form) = match objects.get(&(hTag as usize)).map(|entry| &entry.object) {
+        Some(Resource::ListTag { read, name, .. }) => (read.clone(), TagPath::form_of(name)),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctListData(hTag, pBuffer, dwLength, dwMode) };
+        }
+    };
+    drop(objects);
+    let Some(tag) = read else {
+        return fail(ERROR_NOT_FOUND, false);
+    };
+    if form == AddressingForm::Field && !matches!(dwMode, 0 | CT_LIST_VALUE) {
+        return fail(ERROR_NOT_SUPPORTED, false);
+    }
+    let item = match dwMode {
+        0 | CT_LIST_VALUE => tag.value,
+        CT_LIST_TIMESTAMP | CT_LIST_VALUE_TIMESTAMP | CT_LIST_QUALITY_TIMESTAMP => {
+            tag.timestamp.to_string()
+        }
+        CT_LIST_QUALITY_GENERAL => tag.quality().0.to_string(),
+        CT_LIST_QUALITY_DATASOURCE_ERROR => tag.quality().1.to_string(),
+        _ => "0".to_string(),
+    };
+    // SAFETY: the caller passes a buffer of dwLength bytes.
+    unsafe { put_str(&item, pBuffer, dwLength) };
+    true
+}
+
+pub(crate) unsafe fn ctListItem(
+    hTag: RawHandle,
+    dwItem: DWORD,
+    pBuffer: *mut c_void,
+    dwLength: DWORD,
+    dwMode: DWORD,
+) -> bool {
+    if !objects().contains_key(&(hTag as usize)) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctListItem(hTag, dwItem, pBuffer, dwLength, dwMode) };
+    }
+    // Mock tags serve the same items through either call
+    // SAFETY: the caller passes a buffer of dwLength bytes.
+    unsafe { ctListData(hTag, pBuffer, dwLength, dwItem) }
+}
+
+/// Tags of the event lists of the connection whose value changed since
+/// they were last reported, in handle order
+///
+/// A tag is first reported once it resolves, with `CT_LIST_EVENT_NEW`; without
+/// it the first value is taken silently. Quality changes are only reported
+/// with `CT_LIST_EVENT_STATUS`. A reported tag serves its new value through
+/// `ctListData` and `ctListItem` without a `ctListRead`.
+pub(crate) unsafe fn ctListEvent(hCTAPI: RawHandle, dwMode: DWORD) -> RawHandle {
+    let mut objects = objects();
+    let shared = match objects.get(&(hCTAPI as usize)).map(|entry| &entry.object) {
+        Some(Resource::Connection(server)) => Arc::clone(server),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, std::ptr::null_mut()),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctListEvent(hCTAPI, dwMode) };
+        }
+    };
+    let server = lock(&shared);
+    if server.unreachable {
+        return fail(RPC_S_SERVER_UNAVAILABLE, std::ptr::null_mut());
+    }
+    let lists: HashSet<usize> = objects
+        .iter()
+        .filter(|(_, entry)| match &entry.object {
+            Resource::List(owner, mode) => mode & CT_LIST_EVENT != 0 && Arc::ptr_eq(owner, &shared),
+            _ => false,
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    let mut tags: Vec<usize> = objects.keys().copied().collect();
+    tags.sort_unstable();
+    for id in tags {
+        let Some(Resource::ListTag {
+            list, name, read, ..
+        }) = objects.get_mut(&id).map(|entry| &mut entry.object)
+        else {
+            continue;
+        };
+        if !lists.contains(&*list) {
+            continue;
+        }
+        let Some(current) = server.resolve(name) else {
+            continue;
+        };
+        let reported = match read {
+            None => dwMode & CT_LIST_EVENT_NEW != 0,
+            Some(last) => {
+                last.value != current.value
+                    || (dwMode & CT_LIST_EVENT_STATUS != 0 && last.quality() != current.quality())
+            }
+        };
+        if read.is_none() || reported {
+            *read = Some(current);
+        }
+        if reported {
+            return handle(id);
+        }
+    }
+    fail(ERROR_NO_MORE_ITEMS, std::ptr::null_mut())
+}
+
+pub(crate) unsafe fn ctListWrite(
+    hTag: RawHandle,
+    sValue: LPCSTR,
+    pctOverlapped: *mut OVERLAPPED,
+) -> bool {
+    let objects = objects();
+    let (server, name) = match objects.get(&(hTag as usize)).map(|entry| &entry.object) {
+        Some(Resource::ListTag { server, name, .. }) => (Arc::clone(server), name.clone()),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctListWrite(hTag, sValue, pctOverlapped) };
+        }
+    };
+    drop(objects);
+    // SAFETY: the caller passes a NUL-terminated value.
+    let value = unsafe { arg(sValue) };
+    let stalled = {
+        let mut server = lock(&server);
+        if let Err(code) = server.write(&name, &value) {
+            return fail(code, false);
+        }
+        server.is_stalled(&name)
+    };
+    if stalled && !pctOverlapped.is_null() {
+        // SAFETY: the caller passes a live OVERLAPPED that stays in place
+        // until the call is collected.
+        return unsafe { park(&server, pctOverlapped, InFlightCall::empty()) };
+    }
+    // SAFETY: the caller passes a null or live OVERLAPPED.
+    unsafe { complete(pctOverlapped, 0) }
+}
+
+pub(crate) unsafe fn ctFindFirst(
+    hCTAPI: RawHandle,
+    szTableName: LPCSTR,
+    szFilter: LPCSTR,
+    pObjHnd: *mut RawHandle,
+    dwFlags: DWORD,
+) -> RawHandle {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe { ctapi_sys::ctFindFirst(hCTAPI, szTableName, szFilter, pObjHnd, dwFlags) };
+    }
+    // SAFETY: forwarded with the caller's guarantees; no cluster is named.
+    unsafe {
+        ctFindFirstEx(
+            hCTAPI,
+            szTableName,
+            szFilter,
+            std::ptr::null(),
+            pObjHnd,
+            dwFlags,
+        )
+    }
+}
+
+pub(crate) unsafe fn ctFindFirstEx(
+    hCTAPI: RawHandle,
+    szTableName: LPCSTR,
+    szFilter: LPCSTR,
+    szCluster: LPCSTR,
+    pObjHnd: *mut RawHandle,
+    dwFlags: DWORD,
+) -> RawHandle {
+    if !is_mock(hCTAPI) {
+        // SAFETY: not a mock handle; passed through unchanged.
+        return unsafe {
+            ctapi_sys::ctFindFirstEx(hCTAPI, szTableName, szFilter, szCluster, pObjHnd, dwFlags)
+        };
+    }
+    let mut objects = objects();
+    let Some(server) = server_of(&objects, hCTAPI) else {
+        return fail(ERROR_INVALID_HANDLE, std::ptr::null_mut());
+    };
+    // SAFETY: the caller passes NUL-terminated strings or, for the cluster, NULL.
+    let (table, filter, cluster) = unsafe { (arg(szTableName), arg(szFilter), arg(szCluster)) };
+    let cluster = Some(cluster).filter(|cluster| !cluster.is_empty());
+    let mut guard = lock(&server);
+    guard.last_find = Some((table.clone(), cluster.clone()));
+    if guard.unreachable {
+        return fail(RPC_S_SERVER_UNAVAILABLE, std::ptr::null_mut());
+    }
+    let records = match guard.find(&table, &filter, cluster.as_deref()) {
+        Some(records) if !records.is_empty() => records,
+        Some(_) => return fail(ERROR_NO_MORE_ITEMS, std::ptr::null_mut()),
+        None => return fail(ERROR_NOT_FOUND, std::ptr::null_mut()),
+    };
+    let moves_left = guard.cursor_lifetime;
+    drop(guard);
+    let object = register_find_object(&mut objects, records[0].clone());
+    let find = Resource::Find {
+        records,
+        position: 1,
+        objects: vec![object],
+        moves_left,
+        server,
+    };
+    let find = register(&mut objects, find);
+    // SAFETY: the caller passes a writable object handle.
+    unsafe { *pObjHnd = handle(object) };
+    handle(find)
+}
+
+/// Move a mock find to the 1-based `target` record and return its object handle
+///
+/// Like CtAPI, the object handle of the previous record is released.
+fn seek(objects: &mut HashMap<usize, Entry>, find: usize, target: i64) -> Option<RawHandle> {
+    let record = match &mut objects.get_mut(&find)?.object {
+        Resource::Find {
+            records, position, ..
+        } => {
+            let index = usize::try_from(target).ok()?.checked_sub(1)?;
+            let record = records.get(index)?.clone();
+            *position = index + 1;
+            record
+        }
+        _ => return None,
+    };
+    let previous = match objects.get_mut(&find) {
+        Some(Entry {
+            object: Resource::Find { objects, .. },
+            ..
+        }) => std::mem::take(objects),
+        _ => Vec::new(),
+    };
+    for previous in previous {
+        release(objects, previous);
+    }
+    let object = register_find_object(objects, record);
+    if let Some(Entry {
+        object: Resource::Find { objects, .. },
+        ..
+    }) = objects.get_mut(&find)
+    {
+        objects.push(object);
+    }
+    Some(handle(object))
+}
+
+pub(crate) unsafe fn ctFindNext(hnd: RawHandle, pObjHnd: *mut RawHandle) -> bool {
+    let mut objects = objects();
+    let find = objects.get_mut(&(hnd as usize));
+    let position = match find.map(|entry| &mut entry.object) {
+        Some(Resource::Find {
+            moves_left: Some(0),
+            ..
+        }) => return fail(ERROR_INVALID_HANDLE, false),
+        Some(Resource::Find {
+            position,
+            moves_left,
+            server,
+            ..
+        }) => {
+            if lock(server).unreachable {
+                return fail(RPC_S_SERVER_UNAVAILABLE, false);
+            }
+            if let Some(moves) = moves_left {
+                *moves -= 1;
+            }
+            *position
+        }
+        Some(_) => return fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctFindNext(hnd, pObjHnd) };
+        }
+    };
+    match seek(&mut objects, hnd as usize, position as i64 + 1) {
+        // SAFETY: the caller passes a writable object handle.
+        Some(object) => unsafe {
+            *pObjHnd = object;
+            true
+        },
+        None => fail(ERROR_NO_MORE_ITEMS, false),
+    }
+}
+
+pub(crate) unsafe fn ctFindScroll(
+    hnd: RawHandle,
+    dwMode: DWORD,
+    dwOffset: i32,
+    pObjHnd: *mut RawHandle,
+) -> DWORD {
+    let mut objects = objects();
+    let (position, count) = match objects.get(&(hnd as usize)).map(|entry| &entry.object) {
+        Some(Resource::Find {
+            position, records, ..
+        }) => (*position as i64, records.len() as i64),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, 0),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe { ctapi_sys::ctFindScroll(hnd, dwMode, dwOffset, pObjHnd) };
+        }
+    };
+    let target = match dwMode {
+        CT_FIND_SCROLL_NEXT => position + 1,
+        CT_FIND_SCROLL_PREV => position - 1,
+        CT_FIND_SCROLL_FIRST => 1,
+        CT_FIND_SCROLL_LAST => count,
+        CT_FIND_SCROLL_ABSOLUTE => i64::from(dwOffset),
+        CT_FIND_SCROLL_RELATIVE => position + i64::from(dwOffset),
+        _ => return fail(ERROR_INVALID_DATA, 0),
+    };
+    match seek(&mut objects, hnd as usize, target) {
+        // SAFETY: the caller passes a writable object handle.
+        Some(object) => unsafe {
+            *pObjHnd = object;
+            target as DWORD
+        },
+        None => fail(ERROR_NO_MORE_ITEMS, 0),
+    }
+}
+
+pub(crate) unsafe fn ctFindClose(hnd: RawHandle) -> bool {
+    let mut objects = objects();
+    match objects.get(&(hnd as usize)).map(|entry| &entry.object) {
+        Some(Resource::Find {
+            objects: records, ..
+        }) => {
+            for object in records.clone() {
+                release(&mut objects, object);
+            }
+            release(&mut objects, hnd as usize);
+            true
+        }
+        Some(_) => fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            unsafe { ctapi_sys::ctFindClose(hnd) }
+        }
+    }
+}
+
+pub(crate) unsafe fn ctGetProperty(
+    hnd: RawHandle,
+    szName: LPCSTR,
+    pData: *mut c_void,
+    dwBufferLength: DWORD,
+    dwResultLength: *mut DWORD,
+    dwType: DBTYPEENUM,
+) -> bool {
+    let objects = objects();
+    let record = match objects.get(&(hnd as usize)).map(|entry| &entry.object) {
+        Some(Resource::FindObject(record)) => record.clone(),
+        Some(_) => return fail(ERROR_INVALID_HANDLE, false),
+        None => {
+            drop(objects);
+            // SAFETY: not a mock handle; passed through unchanged.
+            return unsafe {
+                ctapi_sys::ctGetProperty(hnd, szName, pData, dwBufferLength, dwResultLength, dwType)
+            };
+        }
+    };
+    drop(objects);
+    // SAFETY: the caller passes a NUL-terminated property name.
+    let name = unsafe { arg(szName) };
+    let Some(value) = record_property(&record, &name) else {
+        return fail(ERROR_NOT_FOUND, false);
+    };
+    // SAFETY: the caller passes a buffer of dwBufferLength bytes and a null
+    // or writable result length.
+    unsafe {
+        match put_typed(&value, dwType as u32, pData, dwBufferLength) {
+            Some(len) => {
+                if !dwResultLength.is_null() {
+                    *dwResultLength = len as DWORD;
+                }
+                true
+            }
+            None => fail(ERROR_INVALID_DATA, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CT_LIST_QUALITY_DATASOURCE_ERROR;
+    use crate::{AsyncCtClient, AsyncOperation, CicodeWindow, CtApiError, CtClient};
+
+    #[test]
+    fn test_filter_parsing() {
+        assert_eq!(parse_filter(""), vec![Vec::<(String, String)>::new()]);
+        let filter = parse_filter(r#"TAG="A ^"B^"" CLUSTER=C1 OR TAG=X*"#);
+        assert_eq!(
+            filter,
+            vec![
+                vec![
+                    ("TAG".to_string(), r#"A "B""#.to_string()),
+                    ("CLUSTER".to_string(), "C1".to_string())
+                ],
+                vec![("TAG".to_string(), "X*".to_string())],
+            ]
+        );
+        assert!(wildcard_match("pump*", "Pump12"));
+        assert!(wildcard_match("P?mp*2", "Pump12"));
+        assert!(!wildcard_match("Pump?", "Pump12"));
+        assert!(wildcard_match("*", ""));
+    }
+
+    #[test]
+    fn test_tags_round_trip() {
+        let client = CtClient::open_mock().unwrap();
+        assert_eq!(client.tag_read("Temperature").unwrap(), "25.5");
+        client.tag_write("Temperature", 30.5).unwrap();
+        assert_eq!(client.tag_read("TEMPERATURE").unwrap(), "30.5");
+        assert!(client.tag_read("Missing").is_err());
+        assert!(client.tag_write_str("Missing", "1").is_err());
+
+        let reading = client.tag_read_with_quality("Pressure").unwrap();
+        assert!(reading.quality.is_good());
+
+        // Every mock client has its own server
+        let other = CtClient::open_mock().unwrap();
+        assert_eq!(other.tag_read("Temperature").unwrap(), "25.5");
+    }
+
+    #[test]
+    fn test_cicode_and_async() {
+        let server = MockServer::seeded().with_cicode("Double(21)", "42");
+        let client = CtClient::open_mock_with(server).unwrap();
+        assert_eq!(
+            client.cicode("Double(21)", CicodeWindow::NONE, 0).unwrap(),
+            "42"
+        );
+        assert!(client.cicode("Unknown()", CicodeWindow::NONE, 0).is_err());
+
+        let mut op = AsyncOperation::new();
+        client
+            .cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut op)
+            .unwrap();
+        assert!(op.is_complete());
+        assert_eq!(op.get_result(&client).unwrap(), "10:30:00");
+    }
+
+    #[test]
+    fn test_cicode_async_start_outcomes() {
+        use crate::Started;
+
+        let server = MockServer::seeded().with_pending_cicode("Slow()", "done");
+        let client = CtClient::open_mock_with(server).unwrap();
+        let mut op = AsyncOperation::new();
+        let start = |cmd: &str, op: &mut AsyncOperation| {
+            client.cicode_async(cmd, CicodeWindow::NONE, 0, op)
+        };
+
+        // Completed before ctCicode returned
+        assert_eq!(start("Time(1)", &mut op).unwrap(), Started::Completed);
+        assert_eq!(op.get_result(&client).unwrap(), "10:30:00");
+
+        // Failed to start: reported, and the operation is free for reuse
+        let err = start("Unknown()", &mut op).unwrap_err();
+        assert!(matches!(err, CtApiError::System(..)));
+        assert_eq!(start("Time(1)", &mut op).unwrap(), Started::Completed);
+        op.get_result(&client).unwrap();
+
+        // Queued: the operation stays in use until its result is collected
+        assert_eq!(start("Slow()", &mut op).unwrap(), Started::Pending);
+        assert!(!op.is_complete());
+        assert!(op.try_get_result(&client).is_none());
+        assert!(matches!(
+            start("Time(1)", &mut op),
+            Err(CtApiError::InvalidParameter { .. })
+        ));
+        assert_eq!(op.get_result(&client).unwrap(), "done");
+
+        // Cancelled while queued
+        assert_eq!(start("Slow()", &mut op).unwrap(), Started::Pending);
+        op.cancel(&client).unwrap();
+        assert!(op.get_result(&client).is_err());
+        assert_eq!(start("Slow()", &mut op).unwrap(), Started::Pending);
+        assert_eq!(op.get_result(&client).unwrap(), "done");
+
+        let calls = client.recent_calls();
+        assert!(calls.iter().any(|call| call.op == "ctCicode"));
+    }
+
+    #[test]
+    fn test_lists() {
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let list = Arc::clone(&client).list_new(0).unwrap();
+        list.add_tag("Flow1").unwrap();
+        list.add_tag("Missing").unwrap();
+        assert!(list.read_tag("Flow1", CT_LIST_VALUE).is_err());
+
+        list.read().unwrap();
+        assert_eq!(list.read_tag("Flow1", CT_LIST_VALUE).unwrap(), "12.5");
+        let timestamp = list.read_tag("Flow1", CT_LIST_VALUE_TIMESTAMP).unwrap();
+        assert!(timestamp.parse::<u64>().unwrap() > FILETIME_UNIX_EPOCH);
+        assert_eq!(list.datasource_error("Flow1").unwrap(), None);
+        assert!(
+            list.read_tag("Missing", CT_LIST_QUALITY_DATASOURCE_ERROR)
+                .is_err()
+        );
+
+        list.write_tag("Flow1", "13.0").unwrap();
+        assert_eq!(list.read_tag("Flow1", CT_LIST_VALUE).unwrap(), "12.5");
+        let mut op = AsyncOperation::new();
+        list.read_async(&mut op).unwrap();
+        op.get_result(&client).unwrap();
+        assert_eq!(list.read_tag("Flow1", CT_LIST_VALUE).unwrap(), "13.0");
+        assert_eq!(client.tag_read("Flow1").unwrap(), "13.0");
+    }
+
+    #[test]
+    fn test_find_and_scroll() {
+        let client = CtClient::open_mock().unwrap();
+        let records = client
+            .find_first("Tag", "TAG=Flow*", None)
+            .into_records()
+            .unwrap();
+        let tags: Vec<_> = records.iter().map(|r| r.get("TAG").unwrap()).collect();
+        assert_eq!(tags, ["Flow1", "Flow2", "FlowRate"]);
+        assert_eq!(records[0].get("units"), Some("m3/h"));
+
+        let mut find = client.find_first("Tag", "TAG=Flow*", None);
+        let last = find.scroll(CT_FIND_SCROLL_LAST, 0).unwrap().unwrap();
+        assert_eq!(last.position, 3);
+        assert_eq!(last.object.get_property("TAG").unwrap(), "FlowRate");
+        assert!(find.scroll(CT_FIND_SCROLL_RELATIVE, 5).unwrap().is_none());
+
+        assert_eq!(client.find_first("Tag", "TAG=None", None).count(), 0);
+        assert_eq!(client.find_first("NoSuchTable", "", None).count(), 0);
+        let alarms = client.find_first("Alarm", "STATE=ON", None).count();
+        assert_eq!(alarms, 1);
+    }
+
+    #[test]
+    fn test_properties_and_handles() {
+        let client = CtClient::open_mock().unwrap();
+        let full = client
+            .tag_get_property("Temperature", "EngFull", DBTYPEENUM::DBTYPE_R8)
+            .unwrap();
+        assert_eq!(full, crate::PropertyValue::R8(100.0));
+        let units = client
+            .tag_get_property("Temperature", "EngUnits", DBTYPEENUM::DBTYPE_STR)
+            .unwrap();
+        assert_eq!(units, crate::PropertyValue::Str("°C".to_string()));
+        let error = client
+            .tag_get_property("Temperature", "EngUnits", DBTYPEENUM::DBTYPE_R8)
+            .unwrap_err();
+        assert!(matches!(error.root(), CtApiError::System(..)));
+    }
+
+    fn is_closed(handle: RawHandle) -> bool {
+        let objects = objects();
+        let entry = objects.get(&(handle as usize));
+        matches!(entry.map(|entry| &entry.object), Some(Resource::Closed))
+    }
+
+    #[test]
+    fn test_handles_released_with_owners() {
+        let client = CtClient::open_mock().unwrap();
+        // SAFETY: every handle passed is a live mock handle or null.
+        unsafe {
+            let list = ctListNew(client.handle(), 0);
+            let tag = ctListAddEx(list, c"Tag1".as_ptr(), false, 0, 0.0);
+            assert!(is_mock(list) && is_mock(tag));
+            assert!(ctListFree(list));
+            assert!(is_closed(list) && is_closed(tag));
+            assert!(!ctListFree(list));
+
+            let mut object = std::ptr::null_mut();
+            let find = ctFindFirst(
+                client.handle(),
+                c"Tag".as_ptr(),
+                c"".as_ptr(),
+                &mut object,
+                0,
+            );
+            assert!(is_mock(find) && is_mock(object));
+            assert!(ctFindClose(find));
+            assert!(is_closed(find) && is_closed(object));
+        }
+        let handle = client.handle();
+        drop(client);
+        assert!(is_closed(handle));
+        // SAFETY: closed mock handles are rejected, not forwarded
+        assert!(!unsafe { ctClose(handle) });
+    }
+}
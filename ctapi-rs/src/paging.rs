@@ -0,0 +1,679 @@
+//! Deterministic ordering and continuation tokens for find queries
+//!
+//! CtAPI does not guarantee that a find query returns its records in the same
+//! order every time, so serving a large result page by page from repeated
+//! executions can repeat or skip records. A [`PagedQuery`] sorts the complete
+//! result by a chosen field before cutting a page, comparing values by the
+//! [`DbType`] of the field, and breaks ties between equal keys by a
+//! fingerprint of the whole record.
+//!
+//! Each [`Page`] except the last carries an opaque continuation token holding
+//! a hash of the query and the sort key of the last record served.
+//! [`PagedQuery::continue_query`] re-executes the query and resumes after that
+//! key. Tokens are signed with SipHash-2-4 under a secret key: a modified
+//! token, or one issued for a different query, is rejected with
+//! [`CtApiError::InvalidPageToken`].
+//!
+//! # Consistency
+//!
+//! Paging is best effort. Because a page resumes after a sort key rather than
+//! at a record count, records deleted since the previous page do not shift
+//! later records into the gap, and records inserted after the last served key
+//! are returned on later pages. Records inserted before it are not returned,
+//! and a record whose sort field changed can be served twice or not at all.
+
+use crate::client::CtClient;
+use crate::error::{CtApiError, Result};
+use crate::property::{ColumnKind, DbType};
+use crate::query::Record;
+use ctapi_sys::DBTYPEENUM;
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Default number of records per page
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Version of the token layout
+const TOKEN_VERSION: &str = "1";
+
+/// Signing key used by queries without an explicit [`secret`](PagedQuery::secret)
+static PROCESS_SECRET: LazyLock<[u8; 16]> = LazyLock::new(|| {
+    let mut secret = [0; 16];
+    for half in secret.chunks_exact_mut(8) {
+        half.copy_from_slice(&RandomState::new().build_hasher().finish().to_le_bytes());
+    }
+    secret
+});
+
+/// Find query served in pages of a stable order
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::paging::PagedQuery;
+///
+/// let client = CtClient::open_mock()?;
+/// let query = PagedQuery::new("Tag", "CLUSTER=Cluster1", "TAG").page_size(10);
+/// let mut page = query.first_page(&client)?;
+/// loop {
+///     for record in &page.records {
+///         println!("{:?}", record.get("TAG"));
+///     }
+///     // A web API hands the token to the client and gets it back with the
+///     // request for the next page
+///     match page.next {
+///         Some(token) => page = query.continue_query(&client, &token)?,
+///         None => break,
+///     }
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PagedQuery {
+    table: String,
+    filter: String,
+    cluster: Option<String>,
+    sort_field: String,
+    sort_type: Option<DbType>,
+    descending: bool,
+    page_size: usize,
+    max_age: Duration,
+    secret: Option<[u8; 16]>,
+}
+
+/// One page of a [`PagedQuery`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// Records of this page, in sort order
+    pub records: Vec<Record>,
+    /// Token for the next page, `None` on the last page
+    pub next: Option<String>,
+}
+
+impl PagedQuery {
+    /// Page through `table` records matching `filter`, sorted by `sort_field`
+    pub fn new(table: &str, filter: &str, sort_field: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            filter: filter.to_string(),
+            cluster: None,
+            sort_field: sort_field.to_string(),
+            sort_type: None,
+            descending: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            max_age: Duration::ZERO,
+            secret: None,
+        }
+    }
+
+    /// Search in `cluster`
+    pub fn cluster(mut self, cluster: &str) -> Self {
+        self.cluster = Some(cluster.to_string());
+        self
+    }
+
+    /// Compare sort values as `db_type` instead of the column type
+    ///
+    /// Without this, the type is read from the
+    /// [schema](crate::CtFind::schema) of the result, which executes the
+    /// query once more. Fields the schema does not list are compared as text.
+    pub fn sort_type(mut self, db_type: DbType) -> Self {
+        self.sort_type = Some(db_type);
+        self
+    }
+
+    /// Sort in descending order
+    pub fn descending(mut self, descending: bool) -> Self {
+        self.descending = descending;
+        self
+    }
+
+    /// Maximum number of records per page, at least 1
+    ///
+    /// The page size is not part of the token, so it can change between pages.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Serve the result from the client's [query cache](crate::QueryCache)
+    /// if it is younger than `max_age`
+    ///
+    /// Defaults to zero: every page executes the query, joining identical
+    /// executions already running.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sign tokens with `secret`
+    ///
+    /// By default tokens are signed with a key chosen at random when the
+    /// process starts, so they are only accepted by the process that issued
+    /// them. Servers behind a load balancer, or tokens that must survive a
+    /// restart, need a shared secret.
+    pub fn secret(mut self, secret: [u8; 16]) -> Self {
+        self.secret = Some(secret);
+        self
+    }
+
+    /// Execute the query and return its first page
+    ///
+    /// # Errors
+    /// * [`CtApiError::CursorExpired`] - The server discarded the search cursor
+    /// * [`CtApiError::System`] - System call failed
+    pub fn first_page(&self, client: &CtClient) -> Result<Page> {
+        let (records, kind) = self.fetch(client)?;
+        Ok(self.page(records, kind, None))
+    }
+
+    /// Execute the query again and return the page after `token`
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidPageToken`] - The token is malformed, was
+    ///   modified, or was issued for a different query or secret
+    /// * [`CtApiError::CursorExpired`] - The server discarded the search cursor
+    /// * [`CtApiError::System`] - System call failed
+    pub fn continue_query(&self, client: &CtClient, token: &str) -> Result<Page> {
+        let position = self.decode_token(token)?;
+        let (records, kind) = self.fetch(client)?;
+        Ok(self.page(records, kind, Some(&position)))
+    }
+
+    fn fetch(&self, client: &CtClient) -> Result<(Vec<Record>, ColumnKind)> {
+        let cluster = self.cluster.as_deref();
+        let records = client.shared_query(&self.table, &self.filter, cluster, self.max_age)?;
+        let db_type = match self.sort_type {
+            Some(db_type) => db_type,
+            None => client
+                .find_first(&self.table, &self.filter, cluster)
+                .schema()?
+                .into_iter()
+                .find(|column| column.name.eq_ignore_ascii_case(&self.sort_field))
+                .map_or(DbType::from_code(DBTYPEENUM::DBTYPE_STR as u32), |c| {
+                    c.db_type
+                }),
+        };
+        Ok((records.as_ref().clone(), db_type.column_kind()))
+    }
+
+    /// Sort `records` and cut the page after `after`
+    fn page(&self, records: Vec<Record>, kind: ColumnKind, after: Option<&Position>) -> Page {
+        let mut keyed: Vec<_> = records
+            .into_iter()
+            .map(|record| {
+                let key = SortKey::of(&record, &self.sort_field, kind);
+                (key, fingerprint(&record), record)
+            })
+            .collect();
+        // Stable, so identical records keep the order the server returned
+        keyed.sort_by(|a, b| self.compare((&a.0, a.1), (&b.0, b.1)));
+
+        let start = match after {
+            Some(after) => {
+                let last = (&after.key, after.fingerprint);
+                let first_equal =
+                    keyed.partition_point(|r| self.compare((&r.0, r.1), last).is_lt());
+                let equal = keyed[first_equal..]
+                    .iter()
+                    .take_while(|r| self.compare((&r.0, r.1), last).is_eq())
+                    .count();
+                first_equal + equal.min(after.seen)
+            }
+            None => 0,
+        };
+        let end = keyed.len().min(start + self.page_size);
+        let next = (end < keyed.len() && end > start).then(|| {
+            let (key, fingerprint, _) = &keyed[end - 1];
+            let last = (key, *fingerprint);
+            let seen = keyed[..end]
+                .iter()
+                .rev()
+                .take_while(|r| self.compare((&r.0, r.1), last).is_eq())
+                .count();
+            self.encode_token(&Position {
+                key: key.clone(),
+                fingerprint: *fingerprint,
+                seen,
+            })
+        });
+        Page {
+            records: keyed
+                .drain(start..end)
+                .map(|(_, _, record)| record)
+                .collect(),
+            next,
+        }
+    }
+
+    /// Order of two records by sort key, then fingerprint
+    fn compare(&self, a: (&SortKey, u64), b: (&SortKey, u64)) -> Ordering {
+        let by_key = a.0.cmp(b.0);
+        let by_key = if self.descending {
+            by_key.reverse()
+        } else {
+            by_key
+        };
+        by_key.then(a.1.cmp(&b.1))
+    }
+
+    /// Hash of everything that determines the order of the result
+    fn query_hash(&self) -> u64 {
+        let mut data = Vec::new();
+        for part in [
+            self.table.as_str(),
+            self.filter.as_str(),
+            self.cluster.as_deref().unwrap_or("\u{1}"),
+            &self.sort_field.to_ascii_lowercase(),
+            if self.descending { "desc" } else { "asc" },
+            &self
+                .sort_type
+                .map(|t| t.code().to_string())
+                .unwrap_or_default(),
+        ] {
+            data.extend_from_slice(part.as_bytes());
+            data.push(0);
+        }
+        siphash([0; 16], &data)
+    }
+
+    fn key(&self) -> [u8; 16] {
+        self.secret.unwrap_or(*PROCESS_SECRET)
+    }
+
+    fn encode_token(&self, position: &Position) -> String {
+        let payload = format!(
+            "{TOKEN_VERSION}.{:016x}.{}.{:016x}.{}",
+            self.query_hash(),
+            position.key.encode(),
+            position.fingerprint,
+            position.seen
+        );
+        let mac = siphash(self.key(), payload.as_bytes());
+        format!("{payload}.{mac:016x}")
+    }
+
+    fn decode_token(&self, token: &str) -> Result<Position> {
+        let invalid = |reason: &str| CtApiError::InvalidPageToken {
+            reason: reason.to_string(),
+        };
+        let (payload, mac) = token.rsplit_once('.').ok_or_else(|| invalid("malformed"))?;
+        let mac = u64::from_str_radix(mac, 16).map_err(|_| invalid("malformed"))?;
+        if mac != siphash(self.key(), payload.as_bytes()) {
+            return Err(invalid("signature mismatch"));
+        }
+        let parts: Vec<&str> = payload.split('.').collect();
+        let [version, query, key, fingerprint, seen] = parts[..] else {
+            return Err(invalid("malformed"));
+        };
+        if version != TOKEN_VERSION {
+            return Err(invalid("unsupported version"));
+        }
+        if u64::from_str_radix(query, 16).ok() != Some(self.query_hash()) {
+            return Err(invalid("issued for a different query"));
+        }
+        Ok(Position {
+            key: SortKey::decode(key).ok_or_else(|| invalid("malformed"))?,
+            fingerprint: u64::from_str_radix(fingerprint, 16).map_err(|_| invalid("malformed"))?,
+            seen: seen.parse().map_err(|_| invalid("malformed"))?,
+        })
+    }
+}
+
+/// Where a page ended: the last key served and how many records with that
+/// exact key and fingerprint were served
+#[derive(Debug, Clone, PartialEq)]
+struct Position {
+    key: SortKey,
+    fingerprint: u64,
+    seen: usize,
+}
+
+/// Value of the sort field, typed by the column kind
+///
+/// Missing values sort first, then numbers, then text; values that do not
+/// parse as the column type are compared as text.
+#[derive(Debug, Clone, PartialEq)]
+enum SortKey {
+    Missing,
+    Int(i128),
+    Float(f64),
+    Text(String),
+}
+
+impl SortKey {
+    fn of(record: &Record, field: &str, kind: ColumnKind) -> Self {
+        let Some(value) = record.get(field) else {
+            return SortKey::Missing;
+        };
+        let trimmed = value.trim();
+        let typed = match kind {
+            ColumnKind::Integer => trimmed.parse().ok().map(SortKey::Int),
+            ColumnKind::Float | ColumnKind::Timestamp => trimmed.parse().ok().map(SortKey::Float),
+            ColumnKind::Boolean => match trimmed.to_ascii_lowercase().as_str() {
+                "0" | "false" | "off" => Some(SortKey::Int(0)),
+                "1" | "-1" | "true" | "on" => Some(SortKey::Int(1)),
+                _ => None,
+            },
+            ColumnKind::Text => None,
+        };
+        typed.unwrap_or_else(|| SortKey::Text(value.to_string()))
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Missing => 0,
+            SortKey::Int(_) | SortKey::Float(_) => 1,
+            SortKey::Text(_) => 2,
+        }
+    }
+
+    /// URL-safe encoding for tokens
+    fn encode(&self) -> String {
+        match self {
+            SortKey::Missing => "M".to_string(),
+            SortKey::Int(v) => format!("I{v}"),
+            SortKey::Float(v) => format!("F{:016x}", v.to_bits()),
+            SortKey::Text(v) => v.bytes().fold("T".to_string(), |mut s, b| {
+                let _ = write!(s, "{b:02x}");
+                s
+            }),
+        }
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let (tag, rest) = text.split_at_checked(1)?;
+        Some(match tag {
+            "M" if rest.is_empty() => SortKey::Missing,
+            "I" => SortKey::Int(rest.parse().ok()?),
+            "F" => SortKey::Float(f64::from_bits(u64::from_str_radix(rest, 16).ok()?)),
+            "T" if rest.len() % 2 == 0 => {
+                let bytes = (0..rest.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(rest.get(i..i + 2)?, 16).ok())
+                    .collect::<Option<Vec<u8>>>()?;
+                SortKey::Text(String::from_utf8(bytes).ok()?)
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl Eq for SortKey {}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Int(a), SortKey::Int(b)) => a.cmp(b),
+            (SortKey::Float(a), SortKey::Float(b)) => a.total_cmp(b),
+            (SortKey::Int(a), SortKey::Float(b)) => (*a as f64).total_cmp(b),
+            (SortKey::Float(a), SortKey::Int(b)) => a.total_cmp(&(*b as f64)),
+            (SortKey::Text(a), SortKey::Text(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+/// Hash of every field of a record, breaking ties between equal sort keys
+fn fingerprint(record: &Record) -> u64 {
+    let mut data = Vec::new();
+    for (name, value) in record.fields() {
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(value.as_bytes());
+        data.push(0);
+    }
+    siphash([0; 16], &data)
+}
+
+/// SipHash-2-4 of `data` under `key`
+fn siphash(key: [u8; 16], data: &[u8]) -> u64 {
+    fn rounds(v: &mut [u64; 4], n: usize) {
+        for _ in 0..n {
+            v[0] = v[0].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(13) ^ v[0];
+            v[0] = v[0].rotate_left(32);
+            v[2] = v[2].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(16) ^ v[2];
+            v[0] = v[0].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(21) ^ v[0];
+            v[2] = v[2].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(17) ^ v[2];
+            v[2] = v[2].rotate_left(32);
+        }
+    }
+
+    let word = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+    let (k0, k1) = (word(&key[..8]), word(&key[8..]));
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let chunks = data.chunks_exact(8);
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    for m in chunks.map(word).chain([u64::from_le_bytes(last)]) {
+        v[3] ^= m;
+        rounds(&mut v, 2);
+        v[0] ^= m;
+    }
+    v[2] ^= 0xff;
+    rounds(&mut v, 4);
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 16] = [7; 16];
+
+    fn record(fields: &[(&str, &str)]) -> Record {
+        Record::new(
+            fields
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    fn tags(values: &[(&str, &str)]) -> Vec<Record> {
+        values
+            .iter()
+            .map(|&(tag, level)| record(&[("TAG", tag), ("LEVEL", level)]))
+            .collect()
+    }
+
+    fn names(page: &Page) -> Vec<&str> {
+        page.records.iter().map(|r| r.get("TAG").unwrap()).collect()
+    }
+
+    fn query() -> PagedQuery {
+        PagedQuery::new("Tag", "", "LEVEL")
+            .page_size(2)
+            .secret(SECRET)
+    }
+
+    /// Page through `records` the way a client would, refetching every page
+    fn all_pages(query: &PagedQuery, records: &[Record], kind: ColumnKind) -> Vec<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut position = None;
+        loop {
+            let page = query.page(records.to_vec(), kind, position.as_ref());
+            pages.push(names(&page).iter().map(|s| s.to_string()).collect());
+            match page.next {
+                Some(token) => position = Some(query.decode_token(&token).unwrap()),
+                None => return pages,
+            }
+        }
+    }
+
+    #[test]
+    fn test_siphash_reference_vectors() {
+        let key: [u8; 16] = std::array::from_fn(|i| i as u8);
+        let message: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash(key, &[]), 0x726f_db47_dd0e_0e31);
+        assert_eq!(siphash(key, &message[..8]), 0x93f5_f579_9a93_2462);
+        assert_eq!(siphash(key, &message), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn test_typed_sort() {
+        let records = tags(&[
+            ("A", "10"),
+            ("B", "9"),
+            ("C", ""),
+            ("D", "x"),
+            ("E", "-2.5"),
+        ]);
+        let numeric = query().page_size(10);
+        let page = numeric.page(records.clone(), ColumnKind::Float, None);
+        assert_eq!(names(&page), ["E", "B", "A", "C", "D"]);
+        let page = numeric.page(records.clone(), ColumnKind::Text, None);
+        assert_eq!(names(&page), ["C", "E", "A", "B", "D"]);
+        let page = numeric
+            .descending(true)
+            .page(records, ColumnKind::Float, None);
+        assert_eq!(names(&page), ["D", "C", "A", "B", "E"]);
+    }
+
+    #[test]
+    fn test_sort_is_stable_across_fetch_orders() {
+        let records = tags(&[("A", "1"), ("B", "1"), ("C", "1"), ("D", "0"), ("E", "1")]);
+        let mut reversed = records.clone();
+        reversed.reverse();
+        let forward = all_pages(&query(), &records, ColumnKind::Integer);
+        assert_eq!(forward, all_pages(&query(), &reversed, ColumnKind::Integer));
+        assert_eq!(forward.len(), 3);
+        let mut served: Vec<_> = forward.concat();
+        assert_eq!(served[0], "D");
+        served.sort();
+        assert_eq!(served, ["A", "B", "C", "D", "E"]);
+    }
+
+    #[test]
+    fn test_identical_records_split_across_pages() {
+        let records = tags(&[("A", "1"), ("A", "1"), ("A", "1"), ("B", "2")]);
+        let pages = all_pages(&query(), &records, ColumnKind::Integer);
+        assert_eq!(pages, [vec!["A", "A"], vec!["A", "B"]]);
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let query = query();
+        for key in [
+            SortKey::Missing,
+            SortKey::Int(-42),
+            SortKey::Float(2.5),
+            SortKey::Text("泵.A 1".to_string()),
+        ] {
+            let position = Position {
+                key,
+                fingerprint: 0xdead_beef,
+                seen: 3,
+            };
+            let token = query.encode_token(&position);
+            assert!(
+                token
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"-.".contains(&b))
+            );
+            assert_eq!(query.decode_token(&token).unwrap(), position);
+        }
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        let query = query();
+        let records = tags(&[("A", "1"), ("B", "2"), ("C", "3")]);
+        let token = query.page(records, ColumnKind::Integer, None).next.unwrap();
+
+        let reason = |token: &str, query: &PagedQuery| match query.decode_token(token) {
+            Err(CtApiError::InvalidPageToken { reason }) => reason,
+            other => panic!("token accepted: {other:?}"),
+        };
+        let forged = token.replacen(".I2.", ".I1.", 1);
+        assert_ne!(forged, token);
+        assert_eq!(reason(&forged, &query), "signature mismatch");
+        assert_eq!(reason("", &query), "malformed");
+        assert_eq!(
+            reason(&token[..token.len() - 1], &query),
+            "signature mismatch"
+        );
+        assert_eq!(
+            reason(&token, &query.clone().secret([8; 16])),
+            "signature mismatch"
+        );
+
+        let changed = PagedQuery::new("Tag", "CLUSTER=Cluster2", "LEVEL").secret(SECRET);
+        assert_eq!(reason(&token, &changed), "issued for a different query");
+        assert_eq!(
+            reason(&token, &query.clone().descending(true)),
+            "issued for a different query"
+        );
+        // The page size may change between pages
+        assert!(query.clone().page_size(50).decode_token(&token).is_ok());
+        let error = CtApiError::InvalidPageToken {
+            reason: String::new(),
+        };
+        assert_eq!(error.code(), 3011);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_pages_against_mock() {
+        use crate::mock::{self, MockServer};
+
+        let server = |tags: &[&str]| {
+            tags.iter().fold(MockServer::new(), |server, tag| {
+                server.with_record("Tag", &[("TAG", tag)])
+            })
+        };
+        let client = CtClient::open_mock_with(server(&["Pump3", "Pump1", "Pump2"])).unwrap();
+        let query = PagedQuery::new("Tag", "", "TAG").page_size(2);
+        let first = query.first_page(&client).unwrap();
+        assert_eq!(names(&first), ["Pump1", "Pump2"]);
+        let token = first.next.unwrap();
+
+        mock::reload(&client, server(&["Pump4", "Pump2", "Pump3", "Pump0"]));
+        let second = query.continue_query(&client, &token).unwrap();
+        assert_eq!(names(&second), ["Pump3", "Pump4"]);
+        assert!(second.next.is_none());
+
+        let changed = PagedQuery::new("Tag", "TAG=Pump*", "TAG");
+        let err = changed.continue_query(&client, &token).unwrap_err();
+        assert!(matches!(err, CtApiError::InvalidPageToken { .. }));
+    }
+
+    #[test]
+    fn test_resume_after_insertion_and_deletion() {
+        let query = query();
+        let before = tags(&[("A", "1"), ("B", "2"), ("C", "3"), ("D", "4")]);
+        let first = query.page(before, ColumnKind::Float, None);
+        assert_eq!(names(&first), ["A", "B"]);
+        let position = query.decode_token(&first.next.unwrap()).unwrap();
+
+        // A was deleted, one record inserted before the last key and one after
+        let after = tags(&[
+            ("B", "2"),
+            ("AB", "1.5"),
+            ("C", "3"),
+            ("CD", "3.5"),
+            ("D", "4"),
+        ]);
+        let second = query.page(after, ColumnKind::Float, Some(&position));
+        assert_eq!(names(&second), ["C", "CD"]);
+    }
+}
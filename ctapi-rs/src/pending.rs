@@ -0,0 +1,292 @@
+//! Limit on the overlapped operations outstanding on a client
+//!
+//! CtAPI servers reject or slow down connections that have too many
+//! overlapped operations in progress. Every overlapped call made through a
+//! [`CtClient`](crate::CtClient) or its lists takes a slot from the client's
+//! limiter when it starts and returns it when its result is collected, its
+//! [`AsyncOperation`](crate::AsyncOperation) is reset or dropped, or it fails
+//! to start. The async clients, futures and the tokio session all start
+//! their operations this way, so they share the limit.
+//!
+//! When every slot is taken, [`Overflow::FailFast`] fails the new call with
+//! [`CtApiError::TooManyPendingOps`], and [`Overflow::Queue`] makes it wait
+//! for a slot in first-come, first-served order.
+
+use crate::error::{CtApiError, Result};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Overlapped operations a client allows by default
+pub const DEFAULT_MAX_PENDING: usize = 64;
+
+/// What happens to a call started while every slot is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Fail the call with [`CtApiError::TooManyPendingOps`]
+    FailFast,
+    /// Wait for a slot, behind the calls already waiting
+    Queue {
+        /// Calls that may wait at once; further calls fail with
+        /// [`CtApiError::TooManyPendingOps`]
+        capacity: usize,
+        /// Time a call may wait before failing with [`CtApiError::Timeout`]
+        timeout: Duration,
+    },
+}
+
+/// Maximum number of outstanding overlapped operations of a client
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::pending::PendingLimit;
+/// use std::time::Duration;
+///
+/// let client = CtClient::open_mock()?;
+/// client.set_pending_limit(PendingLimit::new(16).queue(1_000, Duration::from_secs(5)));
+/// assert_eq!(client.pending_limit().max(), 16);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingLimit {
+    max: usize,
+    overflow: Overflow,
+}
+
+impl PendingLimit {
+    /// Allow `max` outstanding operations, failing further calls
+    ///
+    /// A `max` of 0 is raised to 1.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            overflow: Overflow::FailFast,
+        }
+    }
+
+    /// Let up to `capacity` calls wait at most `timeout` each for a slot
+    pub fn queue(mut self, capacity: usize, timeout: Duration) -> Self {
+        self.overflow = Overflow::Queue { capacity, timeout };
+        self
+    }
+
+    /// Fail calls made while every slot is taken
+    pub fn fail_fast(mut self) -> Self {
+        self.overflow = Overflow::FailFast;
+        self
+    }
+
+    /// Maximum number of outstanding operations
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// What happens to calls made while every slot is taken
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+}
+
+impl Default for PendingLimit {
+    /// [`DEFAULT_MAX_PENDING`] operations, failing further calls
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PENDING)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Slots {
+    limit: PendingLimit,
+    taken: usize,
+    /// Tickets of the waiting calls, oldest first
+    waiting: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+/// Slots of one client, shared with its clones and lists (internal use)
+#[derive(Debug, Default)]
+pub(crate) struct PendingOps {
+    slots: Mutex<Slots>,
+    released: Condvar,
+}
+
+impl PendingOps {
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn limit(&self) -> PendingLimit {
+        self.lock().limit
+    }
+
+    /// Apply `limit`; operations already started keep their slots
+    pub(crate) fn set_limit(&self, limit: PendingLimit) {
+        self.lock().limit = limit;
+        self.released.notify_all();
+    }
+
+    /// Slots taken
+    pub(crate) fn outstanding(&self) -> usize {
+        self.lock().taken
+    }
+
+    /// Take a slot for an operation about to start
+    ///
+    /// # Errors
+    /// * [`CtApiError::TooManyPendingOps`] - Every slot is taken and the
+    ///   limit fails fast, or the queue is full
+    /// * [`CtApiError::Timeout`] - No slot became free while queued
+    pub(crate) fn acquire(self: &Arc<Self>) -> Result<PendingPermit> {
+        let mut slots = self.lock();
+        if slots.waiting.is_empty() && slots.taken < slots.limit.max {
+            slots.taken += 1;
+            return Ok(PendingPermit(Arc::clone(self)));
+        }
+        let full = CtApiError::TooManyPendingOps {
+            limit: slots.limit.max,
+        };
+        let Overflow::Queue { capacity, timeout } = slots.limit.overflow else {
+            return Err(full);
+        };
+        if slots.waiting.len() >= capacity {
+            return Err(full);
+        }
+
+        let ticket = slots.next_ticket;
+        slots.next_ticket += 1;
+        slots.waiting.push_back(ticket);
+        let deadline = Instant::now() + timeout;
+        loop {
+            if slots.waiting.front() == Some(&ticket) && slots.taken < slots.limit.max {
+                slots.waiting.pop_front();
+                slots.taken += 1;
+                // The next caller in line may find a slot as well
+                self.released.notify_all();
+                return Ok(PendingPermit(Arc::clone(self)));
+            }
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                slots.waiting.retain(|waiting| *waiting != ticket);
+                self.released.notify_all();
+                return Err(CtApiError::Timeout);
+            };
+            slots = self
+                .released
+                .wait_timeout(slots, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn release(&self) {
+        self.lock().taken -= 1;
+        self.released.notify_all();
+    }
+}
+
+/// A slot of a [`PendingOps`], returned when dropped (internal use)
+#[derive(Debug)]
+pub(crate) struct PendingPermit(Arc<PendingOps>);
+
+impl Drop for PendingPermit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn limiter(limit: PendingLimit) -> Arc<PendingOps> {
+        let pending = Arc::new(PendingOps::default());
+        pending.set_limit(limit);
+        pending
+    }
+
+    #[test]
+    fn test_fail_fast_beyond_limit() {
+        let pending = limiter(PendingLimit::new(2));
+        let first = pending.acquire().unwrap();
+        let _second = pending.acquire().unwrap();
+        let err = pending.acquire().unwrap_err();
+        assert!(matches!(err, CtApiError::TooManyPendingOps { limit: 2 }));
+        assert_eq!(pending.outstanding(), 2);
+
+        drop(first);
+        assert_eq!(pending.outstanding(), 1);
+        assert!(pending.acquire().is_ok());
+        assert_eq!(PendingLimit::new(0).max(), 1);
+    }
+
+    #[test]
+    fn test_queue_is_bounded_and_times_out() {
+        let pending = limiter(PendingLimit::new(1).queue(1, Duration::from_millis(20)));
+        let _held = pending.acquire().unwrap();
+
+        let waiter = Arc::clone(&pending);
+        let queued = std::thread::spawn(move || waiter.acquire().map(drop));
+        while pending.lock().waiting.is_empty() {
+            std::thread::yield_now();
+        }
+        let err = pending.acquire().unwrap_err();
+        assert!(matches!(err, CtApiError::TooManyPendingOps { limit: 1 }));
+        assert!(matches!(queued.join().unwrap(), Err(CtApiError::Timeout)));
+        assert!(pending.lock().waiting.is_empty());
+    }
+
+    #[test]
+    fn test_queued_calls_take_slots_in_order() {
+        let pending = limiter(PendingLimit::new(1).queue(16, Duration::from_secs(10)));
+        let held = pending.acquire().unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut waiters = Vec::new();
+        for i in 0..8 {
+            let waiter = Arc::clone(&pending);
+            let order = Arc::clone(&order);
+            waiters.push(std::thread::spawn(move || {
+                let _permit = waiter.acquire().unwrap();
+                order.lock().unwrap().push(i);
+            }));
+            // Queue the waiters one after the other
+            while pending.lock().waiting.len() < i + 1 {
+                std::thread::yield_now();
+            }
+        }
+        drop(held);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), (0..8).collect::<Vec<_>>());
+        assert_eq!(pending.outstanding(), 0);
+    }
+
+    #[test]
+    fn test_limit_never_exceeded_under_load() {
+        let pending = limiter(PendingLimit::new(4).queue(64, Duration::from_secs(10)));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let workers: Vec<_> = (0..16)
+            .map(|_| {
+                let (pending, active, peak) =
+                    (Arc::clone(&pending), Arc::clone(&active), Arc::clone(&peak));
+                std::thread::spawn(move || {
+                    for _ in 0..200 {
+                        let _permit = pending.acquire().unwrap();
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::yield_now();
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+        assert_eq!(pending.outstanding(), 0);
+    }
+}
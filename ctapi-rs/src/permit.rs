@@ -0,0 +1,664 @@
+//! Conditions checked before every tag write
+//!
+//! Plant procedures often only allow remote writes during a commissioning
+//! window, while a "remote enable" tag is set, or once an operator has agreed.
+//! A [`WritePermit`] installed with
+//! [`CtClient::set_write_permit`](crate::CtClient::set_write_permit) is
+//! evaluated before every tag write of the client and its clones, including
+//! overlapped, future and list writes. A denied write fails with
+//! [`CtApiError::WriteNotPermitted`] without calling CtAPI and is recorded in
+//! the client's [audit sink](crate::CtClient::set_audit_sink).
+//!
+//! A permit is a closure or one of the rules of this module:
+//!
+//! - [`TimeWindow`] allows writes between two points in time
+//! - [`GatingTag`] allows writes while a tag holds a given value
+//! - [`OperatorAck`] allows writes while an operator acknowledgement is set
+//!
+//! Rules are combined with [`All`], [`Any`] and [`Not`]:
+//!
+//! ```
+//! use ctapi_rs::permit::{All, Any, GatingTag, OperatorAck, TimeWindow};
+//! use ctapi_rs::{CtApiError, CtClient};
+//! use std::sync::Arc;
+//! use std::time::{Duration, SystemTime};
+//!
+//! let client = CtClient::open_mock()?;
+//! let ack = OperatorAck::new();
+//! let commissioning = TimeWindow::new(SystemTime::now(), Duration::from_secs(3600));
+//! let permit = All::new()
+//!     .with(Any::new().with(commissioning).with(GatingTag::new("Pump_Start", "1")))
+//!     .with(ack.clone());
+//! client.set_write_permit(Some(Arc::new(permit)));
+//!
+//! let denied = client.tag_write("Setpoint", 30);
+//! assert!(matches!(denied, Err(CtApiError::WriteNotPermitted { .. })));
+//! ack.acknowledge();
+//! client.tag_write("Setpoint", 30)?;
+//! # Ok::<(), ctapi_rs::CtApiError>(())
+//! ```
+//!
+//! Tags a permit reads with [`WriteRequest::read_tag`] are read directly and
+//! do not go through the permit. A write made on the same thread while the
+//! permit is being evaluated, for example by a closure holding a clone of the
+//! client, is denied instead of evaluating the permit again.
+
+use crate::audit::{AuditRecord, AuditSink};
+use crate::client::CtClient;
+use crate::error::{CtApiError, Result};
+use crate::write::values_match;
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default time a [`GatingTag`] reuses the value it read
+pub const DEFAULT_GATING_MAX_AGE: Duration = Duration::from_secs(1);
+
+thread_local! {
+    static EVALUATING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A tag write about to be made, as seen by a [`WritePermit`]
+#[derive(Clone, Copy)]
+pub struct WriteRequest<'a> {
+    /// Tag to be written
+    pub tag: &'a str,
+    /// Value to be written
+    pub value: &'a str,
+    /// When the write was requested
+    pub time: SystemTime,
+    client: Option<&'a CtClient>,
+}
+
+impl<'a> WriteRequest<'a> {
+    /// Request to write `value` to `tag`, made now, for testing permits
+    ///
+    /// [`read_tag`](Self::read_tag) fails on such a request; the client
+    /// passes requests that read through it.
+    pub fn new(tag: &'a str, value: &'a str) -> Self {
+        Self {
+            tag,
+            value,
+            time: SystemTime::now(),
+            client: None,
+        }
+    }
+
+    /// Same request made at `time`
+    pub fn at(mut self, time: SystemTime) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub(crate) fn with_client(mut self, client: &'a CtClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Read a tag on the client making the write
+    ///
+    /// # Errors
+    /// * [`CtApiError::UnsupportedOperation`] - Request not made by a client
+    /// * Any error of [`CtClient::tag_read`]
+    pub fn read_tag(&self, tag: &str) -> Result<String> {
+        let client = self
+            .client
+            .ok_or_else(|| CtApiError::UnsupportedOperation {
+                operation: "reading tags from a request without client".to_string(),
+            })?;
+        client.tag_read(tag)
+    }
+}
+
+impl fmt::Debug for WriteRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteRequest")
+            .field("tag", &self.tag)
+            .field("value", &self.value)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decides whether a tag write may be made
+///
+/// Implemented by closures taking a [`WriteRequest`]:
+///
+/// ```
+/// use ctapi_rs::permit::{WritePermit, WriteRequest};
+///
+/// let no_setpoints = |request: &WriteRequest<'_>| {
+///     if request.tag.starts_with("Setpoint") {
+///         return Err("setpoints are changed locally".to_string());
+///     }
+///     Ok(())
+/// };
+/// assert!(no_setpoints.check(&WriteRequest::new("Setpoint", "30")).is_err());
+/// assert!(no_setpoints.check(&WriteRequest::new("Pump_Start", "1")).is_ok());
+/// ```
+pub trait WritePermit: Send + Sync {
+    /// Allow the write, or deny it with the reason
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String>;
+}
+
+impl<F> WritePermit for F
+where
+    F: Fn(&WriteRequest<'_>) -> std::result::Result<(), String> + Send + Sync,
+{
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        self(request)
+    }
+}
+
+impl<P: WritePermit + ?Sized> WritePermit for Arc<P> {
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        (**self).check(request)
+    }
+}
+
+/// Allows writes requested in `[start, start + duration)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    start: SystemTime,
+    end: SystemTime,
+}
+
+impl TimeWindow {
+    /// Window opening at `start` and lasting `duration`
+    pub fn new(start: SystemTime, duration: Duration) -> Self {
+        let end = start.checked_add(duration).unwrap_or(start);
+        Self { start, end }
+    }
+
+    /// Whether writes requested at `time` are allowed
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl WritePermit for TimeWindow {
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        if self.contains(request.time) {
+            Ok(())
+        } else {
+            Err("outside the write window".to_string())
+        }
+    }
+}
+
+/// Allows writes while a tag, such as a "remote enable" flag, holds a value
+///
+/// The value read is reused for [`max_age`](Self::max_age) so that a burst
+/// of writes reads the tag once. Values are compared like
+/// [write verification](crate::TransactionOptions::verify) does: numerically
+/// when both parse as numbers. A gating tag that cannot be read denies.
+#[derive(Debug)]
+pub struct GatingTag {
+    tag: String,
+    expected: String,
+    max_age: Duration,
+    cache: Mutex<Option<(Instant, String)>>,
+}
+
+impl GatingTag {
+    /// Allow writes while `tag` reads `expected`
+    pub fn new(tag: &str, expected: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            expected: expected.to_string(),
+            max_age: DEFAULT_GATING_MAX_AGE,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// How long a value read is reused, [`Duration::ZERO`] to read every time
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<(Instant, String)>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn current(&self, request: &WriteRequest<'_>) -> Result<String> {
+        if let Some((read_at, value)) = &*self.lock() {
+            if read_at.elapsed() < self.max_age {
+                return Ok(value.clone());
+            }
+        }
+        let value = request.read_tag(&self.tag)?;
+        *self.lock() = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+}
+
+impl WritePermit for GatingTag {
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        let actual = self
+            .current(request)
+            .map_err(|e| format!("gating tag '{}' unavailable: {e}", self.tag))?;
+        if values_match(&self.expected, &actual) {
+            Ok(())
+        } else {
+            Err(format!(
+                "gating tag '{}' is {actual:?}, requires {:?}",
+                self.tag, self.expected
+            ))
+        }
+    }
+}
+
+/// Allows writes while an operator acknowledgement is set
+///
+/// Clones share the flag: keep one to [`acknowledge`](Self::acknowledge) and
+/// [`revoke`](Self::revoke) from the operator interface and install another
+/// in the permit.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorAck {
+    acknowledged: Arc<AtomicBool>,
+}
+
+impl OperatorAck {
+    /// Flag that is not acknowledged yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow writes
+    pub fn acknowledge(&self) {
+        self.acknowledged.store(true, Ordering::Release);
+    }
+
+    /// Deny writes again
+    pub fn revoke(&self) {
+        self.acknowledged.store(false, Ordering::Release);
+    }
+
+    /// Whether writes are allowed
+    pub fn is_acknowledged(&self) -> bool {
+        self.acknowledged.load(Ordering::Acquire)
+    }
+}
+
+impl WritePermit for OperatorAck {
+    fn check(&self, _request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        if self.is_acknowledged() {
+            Ok(())
+        } else {
+            Err("not acknowledged by an operator".to_string())
+        }
+    }
+}
+
+/// Allows a write only if every permit allows it; allows everything if empty
+#[derive(Default)]
+pub struct All {
+    permits: Vec<Box<dyn WritePermit>>,
+}
+
+impl All {
+    /// Combination without permits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also require `permit`
+    pub fn with(mut self, permit: impl WritePermit + 'static) -> Self {
+        self.permits.push(Box::new(permit));
+        self
+    }
+}
+
+impl WritePermit for All {
+    /// Denies with the reason of the first permit that denies
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        self.permits.iter().try_for_each(|p| p.check(request))
+    }
+}
+
+impl fmt::Debug for All {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("All")
+            .field("permits", &self.permits.len())
+            .finish()
+    }
+}
+
+/// Allows a write if one of the permits allows it; denies everything if empty
+#[derive(Default)]
+pub struct Any {
+    permits: Vec<Box<dyn WritePermit>>,
+}
+
+impl Any {
+    /// Combination without permits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also accept `permit`
+    pub fn with(mut self, permit: impl WritePermit + 'static) -> Self {
+        self.permits.push(Box::new(permit));
+        self
+    }
+}
+
+impl WritePermit for Any {
+    /// Denies with the reasons of all permits, separated by `; `
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        let mut reasons = Vec::with_capacity(self.permits.len());
+        for permit in &self.permits {
+            match permit.check(request) {
+                Ok(()) => return Ok(()),
+                Err(reason) => reasons.push(reason),
+            }
+        }
+        if reasons.is_empty() {
+            return Err("no write permitted".to_string());
+        }
+        Err(reasons.join("; "))
+    }
+}
+
+impl fmt::Debug for Any {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Any")
+            .field("permits", &self.permits.len())
+            .finish()
+    }
+}
+
+/// Allows a write if the permit denies it
+pub struct Not {
+    permit: Box<dyn WritePermit>,
+    reason: String,
+}
+
+impl Not {
+    /// Invert `permit`, denying with `reason` when it allows
+    pub fn new(permit: impl WritePermit + 'static, reason: &str) -> Self {
+        Self {
+            permit: Box::new(permit),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl WritePermit for Not {
+    fn check(&self, request: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        match self.permit.check(request) {
+            Ok(()) => Err(self.reason.clone()),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for Not {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Not")
+            .field("reason", &self.reason)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Write permit and audit sink shared by clones of a client
+#[derive(Default)]
+pub(crate) struct WriteGuard {
+    permit: Mutex<Option<Arc<dyn WritePermit>>>,
+    audit: Mutex<Option<Arc<dyn AuditSink>>>,
+}
+
+impl fmt::Debug for WriteGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteGuard")
+            .field("permit", &self.permit().is_some())
+            .field("audit", &self.audit().is_some())
+            .finish()
+    }
+}
+
+impl WriteGuard {
+    pub(crate) fn permit(&self) -> Option<Arc<dyn WritePermit>> {
+        self.permit
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_permit(&self, permit: Option<Arc<dyn WritePermit>>) {
+        *self.permit.lock().unwrap_or_else(|e| e.into_inner()) = permit;
+    }
+
+    pub(crate) fn audit(&self) -> Option<Arc<dyn AuditSink>> {
+        self.audit.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub(crate) fn set_audit(&self, sink: Option<Arc<dyn AuditSink>>) {
+        *self.audit.lock().unwrap_or_else(|e| e.into_inner()) = sink;
+    }
+
+    /// Evaluate the permit for a write by `operation`, auditing a denial
+    pub(crate) fn check(
+        &self,
+        client: &CtClient,
+        operation: &str,
+        tag: &str,
+        value: &str,
+    ) -> Result<()> {
+        let Some(permit) = self.permit() else {
+            return Ok(());
+        };
+        let request = WriteRequest::new(tag, value).with_client(client);
+        let Err(reason) = evaluate(&*permit, &request) else {
+            return Ok(());
+        };
+        let error = CtApiError::WriteNotPermitted { reason };
+        if let Some(sink) = self.audit() {
+            let record = AuditRecord::new(operation, tag).value(value).error(&error);
+            let audited = sink.record(&record);
+            #[cfg(feature = "tracing")]
+            if let Err(e) = audited {
+                tracing::warn!(tag, error = %e, "failed to audit denied write");
+            }
+            #[cfg(not(feature = "tracing"))]
+            let _ = audited;
+        }
+        Err(error)
+    }
+}
+
+/// Run `permit`, denying writes made from within it instead of recursing
+fn evaluate(
+    permit: &dyn WritePermit,
+    request: &WriteRequest<'_>,
+) -> std::result::Result<(), String> {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            EVALUATING.set(false);
+        }
+    }
+    if EVALUATING.replace(true) {
+        return Err("write attempted while evaluating the write permit".to_string());
+    }
+    let _reset = Reset;
+    permit.check(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn allow(_: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
+    fn deny(_: &WriteRequest<'_>) -> std::result::Result<(), String> {
+        Err("denied".to_string())
+    }
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_time_window_edges() {
+        let window = TimeWindow::new(at(100), Duration::from_secs(60));
+        let check = |secs| window.check(&WriteRequest::new("Setpoint", "1").at(at(secs)));
+        assert!(check(99).is_err());
+        assert!(check(100).is_ok());
+        assert!(check(159).is_ok());
+        assert_eq!(check(160).unwrap_err(), "outside the write window");
+        assert!(!TimeWindow::new(at(100), Duration::ZERO).contains(at(100)));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let request = WriteRequest::new("Setpoint", "1");
+        assert!(All::new().check(&request).is_ok());
+        assert!(All::new().with(allow).with(deny).check(&request).is_err());
+        assert!(Any::new().check(&request).is_err());
+        assert!(Any::new().with(deny).with(allow).check(&request).is_ok());
+        assert_eq!(
+            Any::new()
+                .with(deny)
+                .with(deny)
+                .check(&request)
+                .unwrap_err(),
+            "denied; denied"
+        );
+        assert_eq!(
+            Not::new(allow, "locked").check(&request).unwrap_err(),
+            "locked"
+        );
+        assert!(Not::new(deny, "locked").check(&request).is_ok());
+    }
+
+    #[test]
+    fn test_operator_ack_shared_by_clones() {
+        let ack = OperatorAck::new();
+        let permit = ack.clone();
+        let request = WriteRequest::new("Setpoint", "1");
+        assert!(permit.check(&request).is_err());
+        ack.acknowledge();
+        assert!(permit.check(&request).is_ok());
+        ack.revoke();
+        assert!(!permit.is_acknowledged());
+    }
+
+    #[test]
+    fn test_gating_tag_without_client_denies() {
+        let gate = GatingTag::new("Remote_Enable", "1");
+        let reason = gate.check(&WriteRequest::new("Setpoint", "1")).unwrap_err();
+        assert!(
+            reason.starts_with("gating tag 'Remote_Enable' unavailable"),
+            "{reason}"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_gating_tag_against_mock() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::seeded().with_tag("Remote_Enable", "0");
+        let client = CtClient::open_mock_with(server).unwrap();
+        client.set_write_permit(Some(Arc::new(
+            GatingTag::new("Remote_Enable", "1").max_age(Duration::ZERO),
+        )));
+
+        let err = client.tag_write("Setpoint", 30).unwrap_err();
+        assert!(matches!(err, CtApiError::WriteNotPermitted { .. }));
+        assert_eq!(err.code(), 3010);
+        assert_eq!(client.tag_read("Setpoint").unwrap(), "20");
+
+        // The gating tag is read directly, not through the permit
+        client.set_write_permit(None);
+        client.tag_write("Remote_Enable", "1.0").unwrap();
+        client.set_write_permit(Some(Arc::new(GatingTag::new("Remote_Enable", "1"))));
+        client.tag_write("Setpoint", 30).unwrap();
+        assert_eq!(client.tag_read("Setpoint").unwrap(), "30");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_gating_tag_cached() {
+        let client = CtClient::open_mock().unwrap();
+        let request = WriteRequest::new("Setpoint", "21").with_client(&client);
+        let cached = GatingTag::new("Pump_Start", "0").max_age(Duration::from_secs(60));
+        cached.check(&request).unwrap();
+
+        // Still permitted on the value read before the change
+        client.tag_write("Pump_Start", 1).unwrap();
+        cached.check(&request).unwrap();
+        let fresh = GatingTag::new("Pump_Start", "0").max_age(Duration::ZERO);
+        assert!(fresh.check(&request).is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_gating_tag_unavailable() {
+        let client = CtClient::open_mock().unwrap();
+        client.set_write_permit(Some(Arc::new(GatingTag::new("Missing_Enable", "1"))));
+        let err = client.tag_write("Setpoint", 30).unwrap_err();
+        let CtApiError::WriteNotPermitted { reason } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert!(reason.contains("'Missing_Enable' unavailable"), "{reason}");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_denial_is_audited() {
+        let client = CtClient::open_mock().unwrap();
+        let sink = Arc::new(MemorySink::default());
+        client.set_audit_sink(Some(sink.clone()));
+        client.set_write_permit(Some(Arc::new(OperatorAck::new())));
+
+        assert!(client.tag_write("Setpoint", 30).is_err());
+        let mut op = crate::AsyncOperation::new();
+        assert!(client.tag_write_ex("Setpoint", 31, Some(&mut op)).is_err());
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].operation, "tag_write");
+        assert_eq!(records[0].target, "Setpoint");
+        assert_eq!(records[0].value.as_deref(), Some("30"));
+        let error = records[0].error.as_deref().unwrap();
+        assert!(error.contains("not acknowledged by an operator"), "{error}");
+        assert_eq!(records[1].operation, "tag_write_ex");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_write_from_permit_does_not_recurse() {
+        let client = CtClient::open_mock().unwrap();
+        let inner = client.clone();
+        client.set_write_permit(Some(Arc::new(move |_: &WriteRequest<'_>| {
+            inner.tag_write("Counter", 1).map_err(|e| e.to_string())
+        })));
+        let err = client.tag_write("Setpoint", 30).unwrap_err();
+        let CtApiError::WriteNotPermitted { reason } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert!(
+            reason.contains("while evaluating the write permit"),
+            "{reason}"
+        );
+        assert!(!EVALUATING.get());
+    }
+}
@@ -0,0 +1,349 @@
+//! Pool of connections to one server
+//!
+//! CtAPI serializes some operations per connection, so a collector issuing
+//! many calls in parallel gets more throughput from a few connections than
+//! from one. [`CtClientPool`] keeps up to `size` clients opened the same way
+//! and lends them out through [`get`](CtClientPool::get); the
+//! [`PooledClient`] guard puts its client back when dropped.
+//!
+//! A client that was idle longer than [`probe_after`](CtClientPool::probe_after),
+//! or whose last known state is not connected, is probed with
+//! [`CtClient::ping`] before it is lent out. One that does not answer is
+//! dropped and a new connection is opened in its place; a connection that
+//! cannot be opened leaves its place empty for the next checkout to retry.
+
+use crate::client::{ConnectionInfo, CtClient};
+use crate::error::{CtApiError, Result};
+use crate::secret::SecretString;
+use crate::state::ConnectionStatus;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Time [`CtClientPool::get`] waits for a client by default
+pub const DEFAULT_POOL_MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// Idle time after which a client is probed before it is lent out by default
+pub const DEFAULT_PROBE_AFTER: Duration = Duration::from_secs(30);
+
+type Opener = dyn Fn() -> Result<CtClient> + Send + Sync;
+
+/// A client waiting in the pool
+struct Idle {
+    client: CtClient,
+    since: Instant,
+}
+
+#[derive(Default)]
+struct Slots {
+    idle: Vec<Idle>,
+    /// Clients idle or lent out, plus connections being opened
+    open: usize,
+}
+
+struct Shared {
+    open: Box<Opener>,
+    size: usize,
+    slots: Mutex<Slots>,
+    returned: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Give up a place, so that a waiting checkout opens a new connection
+    fn vacate(&self) {
+        self.lock().open -= 1;
+        self.returned.notify_one();
+    }
+}
+
+/// Fixed-size pool of clients opened with the same parameters
+///
+/// The pool is `Send + Sync`: share it in an [`Arc`] and check clients out
+/// from any thread. [`get`](Self::get) blocks, so from async code call it
+/// through `tokio::task::spawn_blocking`.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::pool::CtClientPool;
+/// use std::time::Duration;
+///
+/// let pool = CtClientPool::new(3, CtClient::open_mock)?.max_wait(Duration::from_secs(5));
+/// let client = pool.get()?;
+/// assert_eq!(client.tag_read("Temperature")?, "25.5");
+/// drop(client); // back in the pool
+/// assert_eq!(pool.idle_count(), 3);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub struct CtClientPool {
+    shared: Arc<Shared>,
+    max_wait: Duration,
+    probe_after: Duration,
+}
+
+impl CtClientPool {
+    /// Open `size` clients with `open`, which is called again to replace
+    /// clients that went bad
+    ///
+    /// A `size` of 0 is raised to 1.
+    ///
+    /// # Errors
+    /// Returns the error of the first client that could not be opened.
+    pub fn new<F>(size: usize, open: F) -> Result<Self>
+    where
+        F: Fn() -> Result<CtClient> + Send + Sync + 'static,
+    {
+        let size = size.max(1);
+        let mut idle = Vec::with_capacity(size);
+        for _ in 0..size {
+            idle.push(Idle {
+                client: open()?,
+                since: Instant::now(),
+            });
+        }
+        let slots = Slots { idle, open: size };
+        Ok(Self {
+            shared: Arc::new(Shared {
+                open: Box::new(open),
+                size,
+                slots: Mutex::new(slots),
+                returned: Condvar::new(),
+            }),
+            max_wait: DEFAULT_POOL_MAX_WAIT,
+            probe_after: DEFAULT_PROBE_AFTER,
+        })
+    }
+
+    /// Open `size` connections with [`CtClient::open`]
+    ///
+    /// # Errors
+    /// * [`CtApiError::System`] - A connection failed
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use ctapi_rs::pool::CtClientPool;
+    ///
+    /// let pool = CtClientPool::open(Some("scada1"), Some("Manager"), Some("pw"), 0, 4)?;
+    /// println!("{}", pool.get()?.tag_read("Temperature")?);
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn open(
+        computer: Option<&str>,
+        user: Option<&str>,
+        password: Option<&str>,
+        mode: u32,
+        size: usize,
+    ) -> Result<Self> {
+        let info = ConnectionInfo {
+            computer: computer.map(str::to_string),
+            user: user.map(str::to_string),
+            password: password.map(SecretString::from),
+            mode,
+        };
+        Self::new(size, move || {
+            CtClient::open(
+                info.computer.as_deref(),
+                info.user.as_deref(),
+                info.password.as_ref().map(SecretString::expose),
+                info.mode,
+            )
+        })
+    }
+
+    /// Fail [`get`](Self::get) with [`CtApiError::Timeout`] after waiting
+    /// `max_wait` for a client, [`DEFAULT_POOL_MAX_WAIT`] by default
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Probe clients idle for longer than `idle` before lending them out,
+    /// [`DEFAULT_PROBE_AFTER`] by default
+    ///
+    /// [`Duration::ZERO`] probes every checkout.
+    pub fn probe_after(mut self, idle: Duration) -> Self {
+        self.probe_after = idle;
+        self
+    }
+
+    /// Number of clients the pool holds when none went bad
+    pub fn size(&self) -> usize {
+        self.shared.size
+    }
+
+    /// Number of clients waiting in the pool
+    pub fn idle_count(&self) -> usize {
+        self.shared.lock().idle.len()
+    }
+
+    /// Check a client out, waiting for one to be returned if all are lent
+    ///
+    /// # Errors
+    /// * [`CtApiError::Timeout`] - No client became free within
+    ///   [`max_wait`](Self::max_wait)
+    /// * Any error of the opener, if a client had to be opened and could not be
+    pub fn get(&self) -> Result<PooledClient> {
+        crate::blocking::check("CtClientPool::get", crate::blocking::IN_BLOCKING_SECTION);
+        let deadline = Instant::now() + self.max_wait;
+        let mut slots = self.shared.lock();
+        let idle = loop {
+            if let Some(idle) = slots.idle.pop() {
+                break Some(idle);
+            }
+            if slots.open < self.shared.size {
+                slots.open += 1;
+                break None;
+            }
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return Err(CtApiError::Timeout);
+            };
+            slots = self
+                .shared
+                .returned
+                .wait_timeout(slots, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        };
+        drop(slots);
+
+        let client = match idle {
+            Some(idle) if self.is_healthy(&idle) => idle.client,
+            // A client that does not answer is dropped and replaced
+            _ => match (self.shared.open)() {
+                Ok(client) => client,
+                Err(error) => {
+                    self.shared.vacate();
+                    return Err(error);
+                }
+            },
+        };
+        Ok(PooledClient {
+            client: Some(client),
+            pool: Arc::clone(&self.shared),
+        })
+    }
+
+    /// Whether `idle` can be lent out, probing it if it may have gone bad
+    fn is_healthy(&self, idle: &Idle) -> bool {
+        if idle.client.is_connected() && idle.since.elapsed() < self.probe_after {
+            return true;
+        }
+        matches!(idle.client.ping(), Ok(ConnectionStatus::Connected))
+    }
+}
+
+impl fmt::Debug for CtClientPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CtClientPool")
+            .field("size", &self.shared.size)
+            .field("idle", &self.idle_count())
+            .field("max_wait", &self.max_wait)
+            .field("probe_after", &self.probe_after)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Client checked out of a [`CtClientPool`], returned to it when dropped
+pub struct PooledClient {
+    client: Option<CtClient>,
+    pool: Arc<Shared>,
+}
+
+impl PooledClient {
+    /// Close the client instead of returning it
+    ///
+    /// For a connection known to be broken; the next checkout that finds no
+    /// idle client opens a new one in its place.
+    pub fn discard(mut self) {
+        self.client = None;
+        self.pool.vacate();
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = CtClient;
+
+    fn deref(&self) -> &CtClient {
+        self.client.as_ref().expect("client is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut CtClient {
+        self.client.as_mut().expect("client is only taken on drop")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let since = Instant::now();
+            self.pool.lock().idle.push(Idle { client, since });
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+impl fmt::Debug for PooledClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledClient")
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_get_gives_up_after_max_wait() {
+        let pool = CtClientPool::new(1, CtClient::open_mock).unwrap();
+        let pool = pool.max_wait(Duration::from_millis(20));
+        let held = pool.get().unwrap();
+        assert!(matches!(pool.get(), Err(CtApiError::Timeout)));
+
+        drop(held);
+        assert_eq!(pool.get().unwrap().tag_read("Temperature").unwrap(), "25.5");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_bad_clients_are_reopened_lazily() {
+        use crate::mock::{self, MockServer};
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        let opened = Arc::new(AtomicUsize::new(0));
+        let failing = Arc::new(AtomicBool::new(false));
+        let (counter, fail) = (Arc::clone(&opened), Arc::clone(&failing));
+        let open = move || {
+            if fail.load(Ordering::SeqCst) {
+                return Err(CtApiError::Timeout);
+            }
+            counter.fetch_add(1, Ordering::SeqCst);
+            CtClient::open_mock()
+        };
+        let pool = CtClientPool::new(1, open).unwrap();
+        let pool = pool.probe_after(Duration::ZERO);
+
+        let client = pool.get().unwrap();
+        mock::reload(&client, MockServer::seeded().unreachable());
+        drop(client);
+        assert_eq!(pool.get().unwrap().tag_read("Pressure").unwrap(), "1.2");
+        assert_eq!(opened.load(Ordering::SeqCst), 2);
+
+        // A connection that cannot be opened is retried by the next checkout
+        pool.get().unwrap().discard();
+        failing.store(true, Ordering::SeqCst);
+        assert!(pool.get().is_err());
+        failing.store(false, Ordering::SeqCst);
+        assert!(pool.get().unwrap().is_connected());
+        assert_eq!(opened.load(Ordering::SeqCst), 3);
+    }
+}
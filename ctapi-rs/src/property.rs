@@ -0,0 +1,590 @@
+//! Typed property values and DBTYPE-aware FFI buffers
+//!
+//! `ctGetProperty` and `ctTagGetProperty` write the property into a caller
+//! supplied buffer whose required size depends on the requested `DBTYPEENUM`.
+//! [`DbBuffer`] owns that scratch space, sizes it correctly for each type and
+//! decodes the result into a [`PropertyValue`].
+//!
+//! Find results describe the type of each column with the numeric
+//! `object.fields(n).type` property, decoded by [`DbType`].
+
+use crate::error::{CtApiError, Result};
+use ctapi_sys::DBTYPEENUM;
+use encoding_rs::GBK;
+use std::ffi::c_void;
+use std::fmt;
+
+/// Initial buffer size for variable-length (string) properties
+const INITIAL_STR_LEN: usize = 256;
+
+/// Upper bound for variable-length properties, guards against runaway growth
+const MAX_STR_LEN: usize = 64 * 1024;
+
+/// Typed value of an object or tag property
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    /// Signed 8-bit integer (`DBTYPE_I1`)
+    I1(i8),
+    /// Signed 16-bit integer (`DBTYPE_I2`)
+    I2(i16),
+    /// Signed 32-bit integer (`DBTYPE_I4`)
+    I4(i32),
+    /// Signed 64-bit integer (`DBTYPE_I8`)
+    I8(i64),
+    /// Unsigned 8-bit integer (`DBTYPE_UI1`)
+    UI1(u8),
+    /// Unsigned 16-bit integer (`DBTYPE_UI2`)
+    UI2(u16),
+    /// Unsigned 32-bit integer (`DBTYPE_UI4`)
+    UI4(u32),
+    /// Unsigned 64-bit integer (`DBTYPE_UI8`)
+    UI8(u64),
+    /// Single precision float (`DBTYPE_R4`)
+    R4(f32),
+    /// Double precision float (`DBTYPE_R8`)
+    R8(f64),
+    /// Boolean (`DBTYPE_BOOL`)
+    Bool(bool),
+    /// String (`DBTYPE_STR`), decoded from GBK
+    Str(String),
+}
+
+impl PropertyValue {
+    /// Numeric value as `f64`, `None` for strings
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PropertyValue::I1(v) => Some(v as f64),
+            PropertyValue::I2(v) => Some(v as f64),
+            PropertyValue::I4(v) => Some(v as f64),
+            PropertyValue::I8(v) => Some(v as f64),
+            PropertyValue::UI1(v) => Some(v as f64),
+            PropertyValue::UI2(v) => Some(v as f64),
+            PropertyValue::UI4(v) => Some(v as f64),
+            PropertyValue::UI8(v) => Some(v as f64),
+            PropertyValue::R4(v) => Some(v as f64),
+            PropertyValue::R8(v) => Some(v),
+            PropertyValue::Bool(v) => Some(if v { 1.0 } else { 0.0 }),
+            PropertyValue::Str(_) => None,
+        }
+    }
+
+    /// String value, `None` for numeric types
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::I1(v) => write!(f, "{v}"),
+            PropertyValue::I2(v) => write!(f, "{v}"),
+            PropertyValue::I4(v) => write!(f, "{v}"),
+            PropertyValue::I8(v) => write!(f, "{v}"),
+            PropertyValue::UI1(v) => write!(f, "{v}"),
+            PropertyValue::UI2(v) => write!(f, "{v}"),
+            PropertyValue::UI4(v) => write!(f, "{v}"),
+            PropertyValue::UI8(v) => write!(f, "{v}"),
+            PropertyValue::R4(v) => write!(f, "{v}"),
+            PropertyValue::R8(v) => write!(f, "{v}"),
+            PropertyValue::Bool(v) => write!(f, "{v}"),
+            PropertyValue::Str(v) => f.write_str(v),
+        }
+    }
+}
+
+/// Column type reported by `object.fields(n).type`
+///
+/// Wraps the numeric DBTYPE code, which can be one CtAPI does not declare in
+/// [`DBTYPEENUM`] or carry the array/byref/vector modifier flags.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::property::{ColumnKind, DbType};
+/// use ctapi_rs::DBTYPEENUM;
+///
+/// let ty = DbType::parse("5").unwrap();
+/// assert_eq!(ty.known(), Some(DBTYPEENUM::DBTYPE_R8));
+/// assert_eq!(ty.column_kind(), ColumnKind::Float);
+/// assert_eq!(DbType::from_code(999).column_kind(), ColumnKind::Text);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DbType(u32);
+
+/// Modifier flags of a DBTYPE code (`DBTYPE_VECTOR`, `DBTYPE_ARRAY`,
+/// `DBTYPE_BYREF` and `DBTYPE_RESERVED`)
+const DBTYPE_MODIFIERS: u32 = 0xF000;
+
+impl DbType {
+    /// Wrap a numeric DBTYPE code
+    pub fn from_code(code: u32) -> Self {
+        Self(code)
+    }
+
+    /// Parse the string value of an `object.fields(n).type` property
+    pub fn parse(text: &str) -> Option<Self> {
+        text.trim().parse().ok().map(Self)
+    }
+
+    /// Numeric DBTYPE code
+    pub fn code(self) -> u32 {
+        self.0
+    }
+
+    /// The declared type, `None` for unknown codes and codes with modifier flags
+    pub fn known(self) -> Option<DBTYPEENUM> {
+        use DBTYPEENUM::*;
+        Some(match self.0 {
+            0 => DBTYPE_EMPTY,
+            1 => DBTYPE_NULL,
+            2 => DBTYPE_I2,
+            3 => DBTYPE_I4,
+            4 => DBTYPE_R4,
+            5 => DBTYPE_R8,
+            6 => DBTYPE_CY,
+            7 => DBTYPE_DATE,
+            8 => DBTYPE_BSTR,
+            9 => DBTYPE_IDISPATCH,
+            10 => DBTYPE_ERROR,
+            11 => DBTYPE_BOOL,
+            12 => DBTYPE_VARIANT,
+            13 => DBTYPE_IUNKNOWN,
+            14 => DBTYPE_DECIMAL,
+            16 => DBTYPE_I1,
+            17 => DBTYPE_UI1,
+            18 => DBTYPE_UI2,
+            19 => DBTYPE_UI4,
+            20 => DBTYPE_I8,
+            21 => DBTYPE_UI8,
+            72 => DBTYPE_GUID,
+            128 => DBTYPE_BYTES,
+            129 => DBTYPE_STR,
+            130 => DBTYPE_WSTR,
+            131 => DBTYPE_NUMERIC,
+            132 => DBTYPE_UDT,
+            133 => DBTYPE_DBDATE,
+            134 => DBTYPE_DBTIME,
+            135 => DBTYPE_DBTIMESTAMP,
+            _ => return None,
+        })
+    }
+
+    /// How a typed consumer should store the column
+    ///
+    /// Unknown codes and codes with modifier flags are [`ColumnKind::Text`].
+    pub fn column_kind(self) -> ColumnKind {
+        use DBTYPEENUM::*;
+        if self.0 & DBTYPE_MODIFIERS != 0 {
+            return ColumnKind::Text;
+        }
+        match self.known() {
+            Some(DBTYPE_I1 | DBTYPE_I2 | DBTYPE_I4 | DBTYPE_I8) => ColumnKind::Integer,
+            Some(DBTYPE_UI1 | DBTYPE_UI2 | DBTYPE_UI4 | DBTYPE_UI8) => ColumnKind::Integer,
+            Some(DBTYPE_R4 | DBTYPE_R8 | DBTYPE_CY | DBTYPE_DECIMAL | DBTYPE_NUMERIC) => {
+                ColumnKind::Float
+            }
+            Some(DBTYPE_BOOL) => ColumnKind::Boolean,
+            Some(DBTYPE_DATE | DBTYPE_DBDATE | DBTYPE_DBTIME | DBTYPE_DBTIMESTAMP) => {
+                ColumnKind::Timestamp
+            }
+            _ => ColumnKind::Text,
+        }
+    }
+}
+
+impl fmt::Display for DbType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.known() {
+            Some(ty) => write!(f, "{ty:?}"),
+            None => write!(f, "DBTYPE({})", self.0),
+        }
+    }
+}
+
+/// Storage class of a find result column, derived from its [`DbType`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    /// Signed or unsigned integer
+    Integer,
+    /// Floating point, currency or decimal
+    Float,
+    /// Boolean
+    Boolean,
+    /// Date, time or timestamp
+    Timestamp,
+    /// String, or any type without a better mapping
+    Text,
+}
+
+/// Fixed size in bytes of a DBTYPE, `None` for variable-length types
+fn fixed_size(ty: DBTYPEENUM) -> Result<Option<usize>> {
+    use DBTYPEENUM::*;
+    match ty {
+        DBTYPE_I1 | DBTYPE_UI1 => Ok(Some(1)),
+        DBTYPE_I2 | DBTYPE_UI2 | DBTYPE_BOOL => Ok(Some(2)),
+        DBTYPE_I4 | DBTYPE_UI4 | DBTYPE_R4 => Ok(Some(4)),
+        DBTYPE_I8 | DBTYPE_UI8 | DBTYPE_R8 => Ok(Some(8)),
+        DBTYPE_STR => Ok(None),
+        other => Err(CtApiError::UnsupportedOperation {
+            operation: format!("property type {other:?}"),
+        }),
+    }
+}
+
+/// Copy the first `N` bytes of `bytes` into an array
+///
+/// Going through a byte array rather than casting the pointer keeps numeric
+/// reads valid regardless of the alignment of the source buffer.
+fn read_array<const N: usize>(bytes: &[u8]) -> Result<[u8; N]> {
+    bytes
+        .get(..N)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| CtApiError::Other {
+            code: 0,
+            message: format!("property buffer too short: {} < {N}", bytes.len()),
+        })
+}
+
+/// Decode raw property bytes of the given type
+pub(crate) fn decode_bytes(ty: DBTYPEENUM, bytes: &[u8]) -> Result<PropertyValue> {
+    use DBTYPEENUM::*;
+    Ok(match ty {
+        DBTYPE_I1 => PropertyValue::I1(i8::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_UI1 => PropertyValue::UI1(u8::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_I2 => PropertyValue::I2(i16::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_UI2 => PropertyValue::UI2(u16::from_ne_bytes(read_array(bytes)?)),
+        // VARIANT_BOOL: 0 is false, anything else (normally -1) is true
+        DBTYPE_BOOL => PropertyValue::Bool(i16::from_ne_bytes(read_array(bytes)?) != 0),
+        DBTYPE_I4 => PropertyValue::I4(i32::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_UI4 => PropertyValue::UI4(u32::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_R4 => PropertyValue::R4(f32::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_I8 => PropertyValue::I8(i64::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_UI8 => PropertyValue::UI8(u64::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_R8 => PropertyValue::R8(f64::from_ne_bytes(read_array(bytes)?)),
+        DBTYPE_STR => {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            PropertyValue::Str(GBK.decode(&bytes[..end]).0.into_owned())
+        }
+        other => {
+            return Err(CtApiError::UnsupportedOperation {
+                operation: format!("property type {other:?}"),
+            });
+        }
+    })
+}
+
+/// Scratch buffer sized for a given DBTYPE
+#[derive(Debug)]
+pub(crate) struct DbBuffer {
+    ty: DBTYPEENUM,
+    bytes: Vec<u8>,
+}
+
+impl DbBuffer {
+    /// Allocate a buffer for `ty`
+    ///
+    /// # Errors
+    /// * [`CtApiError::UnsupportedOperation`] - The type is not supported
+    pub(crate) fn new(ty: DBTYPEENUM) -> Result<Self> {
+        let len = fixed_size(ty)?.unwrap_or(INITIAL_STR_LEN);
+        Ok(Self {
+            ty,
+            bytes: vec![0; len],
+        })
+    }
+
+    /// Requested DBTYPE
+    pub(crate) fn db_type(&self) -> DBTYPEENUM {
+        self.ty
+    }
+
+    /// Pointer to pass as the FFI data buffer
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut c_void {
+        self.bytes.as_mut_ptr() as *mut c_void
+    }
+
+    /// Buffer length to pass alongside [`as_mut_ptr`](Self::as_mut_ptr)
+    pub(crate) fn len(&self) -> u32 {
+        self.bytes.len() as u32
+    }
+
+    /// Decode the buffer contents, using `result_len` bytes if reported
+    pub(crate) fn decode(&self, result_len: u32) -> Result<PropertyValue> {
+        let used = match result_len as usize {
+            0 => self.bytes.len(),
+            n => n.min(self.bytes.len()),
+        };
+        decode_bytes(self.ty, &self.bytes[..used])
+    }
+
+    /// Whether a variable-length result did not fit and must be fetched again
+    ///
+    /// `result_len` is the length reported by the API, or `0` if the API does
+    /// not report one, in which case a missing terminator means truncation.
+    fn needs_grow(&self, result_len: u32) -> Option<usize> {
+        if fixed_size(self.ty).ok().flatten().is_some() {
+            return None;
+        }
+        let len = self.bytes.len();
+        if result_len as usize >= len {
+            Some(result_len as usize + 1)
+        } else if result_len == 0 && !self.bytes.contains(&0) {
+            Some(len * 2)
+        } else {
+            None
+        }
+    }
+
+    /// Run an FFI property call with a correctly sized buffer and decode the result
+    ///
+    /// `call` receives the buffer pointer and length and an out-parameter for
+    /// the result length, and returns the FFI success flag. For variable-length
+    /// types the call is repeated with a larger buffer if the first result did
+    /// not fit.
+    pub(crate) fn fetch<F>(ty: DBTYPEENUM, mut call: F) -> Result<PropertyValue>
+    where
+        F: FnMut(*mut c_void, u32, &mut u32) -> bool,
+    {
+        let mut buffer = Self::new(ty)?;
+        loop {
+            let mut result_len = 0u32;
+            let len = buffer.len();
+            if !call(buffer.as_mut_ptr(), len, &mut result_len) {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            match buffer.needs_grow(result_len) {
+                Some(new_len) if new_len <= MAX_STR_LEN => {
+                    buffer.bytes = vec![0; new_len];
+                }
+                Some(new_len) => {
+                    return Err(CtApiError::TextTooLong {
+                        field: "property".to_string(),
+                        len: new_len,
+                        max: MAX_STR_LEN,
+                    });
+                }
+                None => return buffer.decode(result_len),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DBTYPEENUM::*;
+
+    #[test]
+    fn test_buffer_sizes() {
+        assert_eq!(DbBuffer::new(DBTYPE_I1).unwrap().len(), 1);
+        assert_eq!(DbBuffer::new(DBTYPE_UI1).unwrap().len(), 1);
+        assert_eq!(DbBuffer::new(DBTYPE_I2).unwrap().len(), 2);
+        assert_eq!(DbBuffer::new(DBTYPE_UI2).unwrap().len(), 2);
+        assert_eq!(DbBuffer::new(DBTYPE_BOOL).unwrap().len(), 2);
+        assert_eq!(DbBuffer::new(DBTYPE_I4).unwrap().len(), 4);
+        assert_eq!(DbBuffer::new(DBTYPE_UI4).unwrap().len(), 4);
+        assert_eq!(DbBuffer::new(DBTYPE_R4).unwrap().len(), 4);
+        assert_eq!(DbBuffer::new(DBTYPE_I8).unwrap().len(), 8);
+        assert_eq!(DbBuffer::new(DBTYPE_UI8).unwrap().len(), 8);
+        assert_eq!(DbBuffer::new(DBTYPE_R8).unwrap().len(), 8);
+        assert_eq!(
+            DbBuffer::new(DBTYPE_STR).unwrap().len() as usize,
+            INITIAL_STR_LEN
+        );
+        assert!(matches!(
+            DbBuffer::new(DBTYPE_VARIANT),
+            Err(CtApiError::UnsupportedOperation { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_numeric_types() {
+        assert_eq!(
+            decode_bytes(DBTYPE_I1, &(-5i8).to_ne_bytes()).unwrap(),
+            PropertyValue::I1(-5)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_UI1, &[200]).unwrap(),
+            PropertyValue::UI1(200)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_I2, &(-300i16).to_ne_bytes()).unwrap(),
+            PropertyValue::I2(-300)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_UI2, &60000u16.to_ne_bytes()).unwrap(),
+            PropertyValue::UI2(60000)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_I4, &(-70000i32).to_ne_bytes()).unwrap(),
+            PropertyValue::I4(-70000)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_UI4, &4_000_000_000u32.to_ne_bytes()).unwrap(),
+            PropertyValue::UI4(4_000_000_000)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_R4, &1.5f32.to_ne_bytes()).unwrap(),
+            PropertyValue::R4(1.5)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_I8, &(-1i64 << 40).to_ne_bytes()).unwrap(),
+            PropertyValue::I8(-1 << 40)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_UI8, &(1u64 << 63).to_ne_bytes()).unwrap(),
+            PropertyValue::UI8(1 << 63)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_R8, &123.25f64.to_ne_bytes()).unwrap(),
+            PropertyValue::R8(123.25)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_BOOL, &(-1i16).to_ne_bytes()).unwrap(),
+            PropertyValue::Bool(true)
+        );
+        assert_eq!(
+            decode_bytes(DBTYPE_BOOL, &0i16.to_ne_bytes()).unwrap(),
+            PropertyValue::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_decode_misaligned() {
+        // Place each numeric value at an odd offset so a pointer cast would be misaligned
+        let mut raw = [0u8; 9];
+        raw[1..9].copy_from_slice(&6.5f64.to_ne_bytes());
+        assert_eq!(
+            decode_bytes(DBTYPE_R8, &raw[1..]).unwrap(),
+            PropertyValue::R8(6.5)
+        );
+
+        raw[1..9].copy_from_slice(&(-42i64).to_ne_bytes());
+        assert_eq!(
+            decode_bytes(DBTYPE_I8, &raw[1..]).unwrap(),
+            PropertyValue::I8(-42)
+        );
+
+        raw[1..5].copy_from_slice(&0.25f32.to_ne_bytes());
+        assert_eq!(
+            decode_bytes(DBTYPE_R4, &raw[1..5]).unwrap(),
+            PropertyValue::R4(0.25)
+        );
+
+        raw[1..3].copy_from_slice(&1234i16.to_ne_bytes());
+        assert_eq!(
+            decode_bytes(DBTYPE_I2, &raw[1..3]).unwrap(),
+            PropertyValue::I2(1234)
+        );
+    }
+
+    #[test]
+    fn test_db_type_codes() {
+        let cases = [
+            ("2", Some(DBTYPE_I2), ColumnKind::Integer),
+            ("3", Some(DBTYPE_I4), ColumnKind::Integer),
+            ("19", Some(DBTYPE_UI4), ColumnKind::Integer),
+            ("20", Some(DBTYPE_I8), ColumnKind::Integer),
+            ("4", Some(DBTYPE_R4), ColumnKind::Float),
+            (" 5 ", Some(DBTYPE_R8), ColumnKind::Float),
+            ("6", Some(DBTYPE_CY), ColumnKind::Float),
+            ("11", Some(DBTYPE_BOOL), ColumnKind::Boolean),
+            ("7", Some(DBTYPE_DATE), ColumnKind::Timestamp),
+            ("135", Some(DBTYPE_DBTIMESTAMP), ColumnKind::Timestamp),
+            ("129", Some(DBTYPE_STR), ColumnKind::Text),
+            ("130", Some(DBTYPE_WSTR), ColumnKind::Text),
+            ("12", Some(DBTYPE_VARIANT), ColumnKind::Text),
+            ("999", None, ColumnKind::Text),
+        ];
+        for (text, known, kind) in cases {
+            let ty = DbType::parse(text).unwrap();
+            assert_eq!(ty.known(), known, "{text}");
+            assert_eq!(ty.column_kind(), kind, "{text}");
+        }
+        assert_eq!(DbType::parse("R8"), None);
+
+        let array_of_i4 = DbType::from_code(0x2000 | 3);
+        assert_eq!(array_of_i4.known(), None);
+        assert_eq!(array_of_i4.column_kind(), ColumnKind::Text);
+        assert_eq!(array_of_i4.to_string(), "DBTYPE(8195)");
+        assert_eq!(DbType::from_code(5).to_string(), "DBTYPE_R8");
+    }
+
+    #[test]
+    fn test_decode_short_buffer() {
+        assert!(decode_bytes(DBTYPE_R8, &[0u8; 4]).is_err());
+        assert!(decode_bytes(DBTYPE_I2, &[0u8; 1]).is_err());
+    }
+
+    #[test]
+    fn test_decode_str() {
+        let (gbk, _, _) = GBK.encode("温度");
+        let mut raw = gbk.into_owned();
+        raw.extend_from_slice(&[0, b'x', b'y']);
+        assert_eq!(
+            decode_bytes(DBTYPE_STR, &raw).unwrap(),
+            PropertyValue::Str("温度".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fetch_fixed_size_calls_once() {
+        let mut calls = 0;
+        let value = DbBuffer::fetch(DBTYPE_I4, |ptr, len, _| {
+            calls += 1;
+            assert_eq!(len, 4);
+            let bytes = 77i32.to_ne_bytes();
+            // SAFETY: ptr points to a buffer of `len` bytes owned by DbBuffer
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, 4) };
+            true
+        })
+        .unwrap();
+        assert_eq!(value, PropertyValue::I4(77));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_fetch_str_size_then_fetch() {
+        let long = "A".repeat(INITIAL_STR_LEN + 40);
+        let mut lens = vec![];
+        let value = DbBuffer::fetch(DBTYPE_STR, |ptr, len, result_len| {
+            lens.push(len);
+            let n = long.len().min(len as usize);
+            // SAFETY: ptr points to a buffer of `len` bytes owned by DbBuffer
+            unsafe { std::ptr::copy_nonoverlapping(long.as_ptr(), ptr as *mut u8, n) };
+            *result_len = long.len() as u32;
+            true
+        })
+        .unwrap();
+        assert_eq!(value, PropertyValue::Str(long.clone()));
+        assert_eq!(lens, vec![INITIAL_STR_LEN as u32, long.len() as u32 + 1]);
+    }
+
+    #[test]
+    fn test_fetch_str_without_reported_length() {
+        let long = "B".repeat(INITIAL_STR_LEN * 3);
+        let mut calls = 0;
+        let value = DbBuffer::fetch(DBTYPE_STR, |ptr, len, _| {
+            calls += 1;
+            let n = long.len().min(len as usize);
+            // SAFETY: ptr points to a zeroed buffer of `len` bytes owned by DbBuffer
+            unsafe { std::ptr::copy_nonoverlapping(long.as_ptr(), ptr as *mut u8, n) };
+            true
+        })
+        .unwrap();
+        assert_eq!(value.as_str(), Some(long.as_str()));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_property_value_accessors() {
+        assert_eq!(PropertyValue::I2(3).as_f64(), Some(3.0));
+        assert_eq!(PropertyValue::Bool(true).as_f64(), Some(1.0));
+        assert_eq!(PropertyValue::Str("x".into()).as_f64(), None);
+        assert_eq!(PropertyValue::Str("x".into()).as_str(), Some("x"));
+        assert_eq!(PropertyValue::R8(1.5).to_string(), "1.5");
+    }
+}
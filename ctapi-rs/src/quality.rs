@@ -0,0 +1,634 @@
+//! Tag quality decoding
+//!
+//! Citect SCADA reports the quality of every tag value next to the value
+//! itself (see [`CtTagValueItems`]). [`Quality`] unpacks those fields, and
+//! [`CitectError`] turns the data source error code into a readable
+//! description, so that diagnostics can say what the I/O driver reported
+//! instead of printing a bare number.
+//!
+//! Formatting a [`Quality`] or [`QualityStatus`] writes static strings
+//! straight into the formatter and never allocates, so they can go on every
+//! log record. The strings are part of the log format and do not change:
+//!
+//! | [`QualityStatus`] | Text |
+//! |-------------------|------|
+//! | `Good` | `good` |
+//! | `Uncertain` | `uncertain` |
+//! | `Bad` | `bad` |
+//! | `NotApplicable` | `not applicable` |
+//! | `Unknown` | `unknown`; a [`Quality`] shows `quality <n>` |
+//!
+//! With the `serde` feature the same strings are serialized as unit variants.
+
+use crate::filetime;
+use chrono::{DateTime, Utc};
+use ctapi_sys::CtTagValueItems;
+use std::fmt;
+
+/// `quality_general` value of a bad reading
+pub const QUAL_BAD: u8 = 0;
+/// `quality_general` value of an uncertain reading
+pub const QUAL_UNCERTAIN: u8 = 1;
+/// `quality_general` value of a good reading
+pub const QUAL_GOOD: u8 = 3;
+/// General quality of a reading that has no quality, see [`Quality::NOT_APPLICABLE`]
+pub const QUAL_NOT_APPLICABLE: u8 = u8::MAX;
+
+/// Generic I/O driver errors shared by the Citect protocol drivers
+///
+/// Assumed, not taken from a published table: the CtAPI reference only
+/// documents the field as the driver's error code. The descriptions are best
+/// effort and the code is always shown next to them, so check it against
+/// the driver's documentation. Driver-specific codes are not listed and are
+/// reported by number.
+const DATASOURCE_ERRORS: &[(u32, &str)] = &[
+    (1, "General driver error"),
+    (2, "Channel offline"),
+    (3, "Unit offline"),
+    (4, "Invalid address"),
+    (5, "Address out of range"),
+    (6, "Invalid data type"),
+    (7, "Write protected"),
+    (8, "No response from device"),
+    (9, "Invalid response from device"),
+    (10, "Device busy"),
+];
+
+/// Error reported by the data source (I/O driver) of a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CitectError {
+    code: u32,
+}
+
+impl CitectError {
+    /// Wrap a data source error code, `None` for `0` (no error)
+    pub fn from_code(code: u32) -> Option<Self> {
+        (code != 0).then_some(Self { code })
+    }
+
+    /// Parse the value of the `CT_LIST_QUALITY_DATASOURCE_ERROR` list item
+    pub fn parse_item(item: &str) -> Option<Self> {
+        item.trim().parse().ok().and_then(Self::from_code)
+    }
+
+    /// Raw error code
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    /// Description of a known code, `None` for driver-specific codes
+    pub fn description(&self) -> Option<&'static str> {
+        DATASOURCE_ERRORS
+            .iter()
+            .find(|(code, _)| *code == self.code)
+            .map(|(_, description)| *description)
+    }
+}
+
+impl fmt::Display for CitectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{description} (driver error {})", self.code),
+            None => write!(f, "driver error {}", self.code),
+        }
+    }
+}
+
+/// Class of the general quality of a reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityStatus {
+    /// [`QUAL_GOOD`]
+    Good,
+    /// [`QUAL_UNCERTAIN`]
+    Uncertain,
+    /// [`QUAL_BAD`]
+    Bad,
+    /// [`QUAL_NOT_APPLICABLE`]
+    NotApplicable,
+    /// Any other value
+    Unknown,
+}
+
+impl QualityStatus {
+    /// Class of the `quality_general` value `general`
+    pub fn from_general(general: u8) -> Self {
+        match general {
+            QUAL_GOOD => QualityStatus::Good,
+            QUAL_UNCERTAIN => QualityStatus::Uncertain,
+            QUAL_BAD => QualityStatus::Bad,
+            QUAL_NOT_APPLICABLE => QualityStatus::NotApplicable,
+            _ => QualityStatus::Unknown,
+        }
+    }
+
+    /// Text of the status, see the [module documentation](self)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QualityStatus::Good => "good",
+            QualityStatus::Uncertain => "uncertain",
+            QualityStatus::Bad => "bad",
+            QualityStatus::NotApplicable => "not applicable",
+            QualityStatus::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for QualityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Meaning of the quality substatus, which depends on the general quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualitySubstatus {
+    /// No specific reason given
+    NonSpecific,
+    /// Good: the value was overridden locally
+    LocalOverride,
+    /// Uncertain: the source stopped updating, the value is the last usable one
+    LastUsableValue,
+    /// Uncertain: the sensor is out of calibration or at a limit
+    SensorNotAccurate,
+    /// Uncertain: the value is outside the engineering unit range
+    EngineeringUnitsExceeded,
+    /// Uncertain: the value is derived from fewer sources than required
+    SubNormal,
+    /// Bad: the tag or device is misconfigured
+    ConfigurationError,
+    /// Bad: the input is not connected
+    NotConnected,
+    /// Bad: the device failed
+    DeviceFailure,
+    /// Bad: the sensor failed
+    SensorFailure,
+    /// Bad: communication failed, the value is the last known one
+    LastKnownValue,
+    /// Bad: communication failed and no value is known
+    CommFailure,
+    /// Bad: the tag or device is out of service
+    OutOfService,
+    /// Any other combination, or a reading without quality
+    Unknown,
+}
+
+impl QualitySubstatus {
+    /// Meaning of the `quality_substatus` value `substatus` of a `general` quality
+    pub fn from_parts(general: u8, substatus: u8) -> Self {
+        use QualitySubstatus::*;
+        match (general, substatus) {
+            (QUAL_GOOD | QUAL_UNCERTAIN | QUAL_BAD, 0) => NonSpecific,
+            (QUAL_GOOD, 6) => LocalOverride,
+            (QUAL_UNCERTAIN, 1) => LastUsableValue,
+            (QUAL_UNCERTAIN, 4) => SensorNotAccurate,
+            (QUAL_UNCERTAIN, 5) => EngineeringUnitsExceeded,
+            (QUAL_UNCERTAIN, 6) => SubNormal,
+            (QUAL_BAD, 1) => ConfigurationError,
+            (QUAL_BAD, 2) => NotConnected,
+            (QUAL_BAD, 3) => DeviceFailure,
+            (QUAL_BAD, 4) => SensorFailure,
+            (QUAL_BAD, 5) => LastKnownValue,
+            (QUAL_BAD, 6) => CommFailure,
+            (QUAL_BAD, 7) => OutOfService,
+            _ => Unknown,
+        }
+    }
+}
+
+/// Limit the value of a reading is held at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityLimit {
+    /// The value can move freely
+    NotLimited,
+    /// The value is at its low limit
+    Low,
+    /// The value is at its high limit
+    High,
+    /// The value cannot move
+    Constant,
+    /// Any other value
+    Unknown,
+}
+
+impl QualityLimit {
+    /// Meaning of the `quality_limit` value `limit`
+    pub fn from_limit(limit: u8) -> Self {
+        match limit {
+            0 => QualityLimit::NotLimited,
+            1 => QualityLimit::Low,
+            2 => QualityLimit::High,
+            3 => QualityLimit::Constant,
+            _ => QualityLimit::Unknown,
+        }
+    }
+}
+
+/// Quality fields of a tag reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quality {
+    /// General quality (`QUAL_GOOD`, `QUAL_UNCERTAIN` or `QUAL_BAD`)
+    pub general: u8,
+    /// Quality substatus
+    pub substatus: u8,
+    /// Quality limit
+    pub limit: u8,
+    /// Extended substatus, specific to the data source
+    pub extended_substatus: u8,
+    /// Error reported by the data source, if any
+    pub datasource_error: Option<CitectError>,
+    /// Quality timestamp as reported by CtAPI
+    pub timestamp: u64,
+    /// Whether the value is overridden
+    pub overridden: bool,
+    /// Whether the tag is in control mode
+    pub control_mode: bool,
+}
+
+impl Quality {
+    /// Quality of a read for which the API reports none, such as an element field
+    ///
+    /// See [`tag_path`](crate::tag_path) for the items available per
+    /// addressing form.
+    pub const NOT_APPLICABLE: Quality = Quality {
+        general: QUAL_NOT_APPLICABLE,
+        substatus: 0,
+        limit: 0,
+        extended_substatus: 0,
+        datasource_error: None,
+        timestamp: 0,
+        overridden: false,
+        control_mode: false,
+    };
+
+    /// Unpack the quality fields of `items`
+    ///
+    /// Items whose length stops before the quality fields give
+    /// [`NOT_APPLICABLE`](Self::NOT_APPLICABLE), and the flags are only read
+    /// from items of the [current](ctapi_sys::CtApiVersion::Current) revision.
+    pub fn from_items(items: &CtTagValueItems) -> Self {
+        if !items.has_quality() {
+            return Self::NOT_APPLICABLE;
+        }
+        // Copy out of the packed struct before use
+        let CtTagValueItems {
+            quality_general,
+            quality_substatus,
+            quality_limit,
+            quality_extended_substatus,
+            quality_datasource_error,
+            quality_timestamp,
+            boverride,
+            control_mode,
+            ..
+        } = *items;
+        Self {
+            general: quality_general,
+            substatus: quality_substatus,
+            limit: quality_limit,
+            extended_substatus: quality_extended_substatus,
+            datasource_error: CitectError::from_code(quality_datasource_error),
+            timestamp: quality_timestamp,
+            overridden: items.has_modes() && boverride,
+            control_mode: items.has_modes() && control_mode,
+        }
+    }
+
+    /// Class of the general quality
+    pub fn status(&self) -> QualityStatus {
+        QualityStatus::from_general(self.general)
+    }
+
+    /// Meaning of the substatus
+    pub fn substatus_kind(&self) -> QualitySubstatus {
+        QualitySubstatus::from_parts(self.general, self.substatus)
+    }
+
+    /// Meaning of the limit
+    pub fn limit_kind(&self) -> QualityLimit {
+        QualityLimit::from_limit(self.limit)
+    }
+
+    /// Whether the reading is good
+    pub fn is_good(&self) -> bool {
+        self.general == QUAL_GOOD
+    }
+
+    /// Whether the reading is bad
+    pub fn is_bad(&self) -> bool {
+        self.general == QUAL_BAD
+    }
+
+    /// Whether the API reported a quality for the reading
+    pub fn is_applicable(&self) -> bool {
+        self.general != QUAL_NOT_APPLICABLE
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status() {
+            QualityStatus::Unknown => write!(f, "quality {}", self.general)?,
+            status => f.write_str(status.as_str())?,
+        }
+        if let Some(error) = self.datasource_error {
+            write!(f, ": {error}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for QualityStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit_variant("QualityStatus", *self as u32, self.as_str())
+    }
+}
+
+/// The error code
+#[cfg(feature = "serde")]
+impl serde::Serialize for CitectError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.code)
+    }
+}
+
+/// The fields with the [`status`](Quality::status) of the general quality
+#[cfg(feature = "serde")]
+impl serde::Serialize for Quality {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut quality = serializer.serialize_struct("Quality", 9)?;
+        quality.serialize_field("status", &self.status())?;
+        quality.serialize_field("general", &self.general)?;
+        quality.serialize_field("substatus", &self.substatus)?;
+        quality.serialize_field("limit", &self.limit)?;
+        quality.serialize_field("extended_substatus", &self.extended_substatus)?;
+        quality.serialize_field("datasource_error", &self.datasource_error)?;
+        quality.serialize_field("timestamp", &self.timestamp)?;
+        quality.serialize_field("overridden", &self.overridden)?;
+        quality.serialize_field("control_mode", &self.control_mode)?;
+        quality.end()
+    }
+}
+
+/// Lowest quality a quality-filtered read accepts
+///
+/// Used by [`CtClient::tag_read_good`](crate::CtClient::tag_read_good) and
+/// [`CtList::read_all_good`](crate::CtList::read_all_good).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QualityThreshold {
+    /// Accept good readings only
+    #[default]
+    GoodOnly,
+    /// Accept good and uncertain readings
+    GoodOrUncertain,
+}
+
+impl QualityThreshold {
+    /// Whether a reading of `quality` passes
+    pub fn accepts(&self, quality: &Quality) -> bool {
+        match self {
+            QualityThreshold::GoodOnly => quality.general == QUAL_GOOD,
+            QualityThreshold::GoodOrUncertain => {
+                matches!(quality.general, QUAL_GOOD | QUAL_UNCERTAIN)
+            }
+        }
+    }
+}
+
+/// Values of a list read split by quality
+///
+/// Returned by [`CtList::read_all_good`](crate::CtList::read_all_good).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QualityPartition {
+    /// `(tag, value)` of accepted readings, sorted by tag
+    pub good: Vec<(String, String)>,
+    /// `(tag, quality)` of rejected readings, sorted by tag
+    pub rejected: Vec<(String, Quality)>,
+}
+
+/// A tag value together with its quality and timestamps
+///
+/// Timestamps the server did not report are `None`, as are all timestamps
+/// of a reading whose quality is [`NOT_APPLICABLE`](Quality::NOT_APPLICABLE).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagReading {
+    /// Value as returned by CtAPI
+    pub value: String,
+    /// Quality of the value
+    pub quality: Quality,
+    /// Time of the reading
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Time the value last changed
+    pub value_timestamp: Option<DateTime<Utc>>,
+    /// Time the quality last changed
+    pub quality_timestamp: Option<DateTime<Utc>>,
+}
+
+impl TagReading {
+    /// Reading of `value` with `quality` and no timestamps
+    pub fn new(value: String, quality: Quality) -> Self {
+        Self {
+            value,
+            quality,
+            timestamp: None,
+            value_timestamp: None,
+            quality_timestamp: None,
+        }
+    }
+
+    /// Combine a value with the metadata returned by `ctTagReadEx`
+    pub fn from_items(value: String, items: &CtTagValueItems) -> Self {
+        // Copy out of the packed struct before use
+        let CtTagValueItems {
+            timestamp,
+            value_timestamp,
+            quality_timestamp,
+            ..
+        } = *items;
+        Self {
+            value,
+            quality: Quality::from_items(items),
+            timestamp: filetime::to_utc(timestamp),
+            value_timestamp: filetime::to_utc(value_timestamp),
+            quality_timestamp: filetime::to_utc(quality_timestamp),
+        }
+    }
+}
+
+impl fmt::Display for TagReading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.quality.is_good() || !self.quality.is_applicable() {
+            f.write_str(&self.value)
+        } else {
+            write!(f, "{} ({})", self.value, self.quality)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(general: u8, datasource_error: u32) -> CtTagValueItems {
+        CtTagValueItems {
+            quality_general: general,
+            quality_datasource_error: datasource_error,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_known_codes() {
+        for (code, description) in [
+            (2, "Channel offline"),
+            (3, "Unit offline"),
+            (8, "No response from device"),
+        ] {
+            let quality = Quality::from_items(&items(QUAL_BAD, code));
+            let error = quality.datasource_error.unwrap();
+            assert_eq!(error.code(), code);
+            assert_eq!(error.description(), Some(description));
+            assert_eq!(
+                error.to_string(),
+                format!("{description} (driver error {code})")
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_code_kept_verbatim() {
+        let quality = Quality::from_items(&items(QUAL_BAD, 0x8004_1234));
+        let error = quality.datasource_error.unwrap();
+        assert_eq!(error.code(), 0x8004_1234);
+        assert_eq!(error.description(), None);
+        assert_eq!(
+            error.to_string(),
+            format!("driver error {}", 0x8004_1234u32)
+        );
+    }
+
+    #[test]
+    fn test_no_error() {
+        let quality = Quality::from_items(&items(QUAL_GOOD, 0));
+        assert!(quality.is_good());
+        assert_eq!(quality.datasource_error, None);
+        assert_eq!(CitectError::parse_item("0"), None);
+        assert_eq!(CitectError::parse_item(" 3 ").unwrap().code(), 3);
+        assert_eq!(CitectError::parse_item("garbage"), None);
+    }
+
+    #[test]
+    fn test_threshold_per_quality_class() {
+        let cases = [
+            (QUAL_GOOD, true, true),
+            (QUAL_UNCERTAIN, false, true),
+            (QUAL_BAD, false, false),
+            // Values outside the documented classes are never accepted
+            (2, false, false),
+        ];
+        for (general, good_only, good_or_uncertain) in cases {
+            let quality = Quality::from_items(&items(general, 0));
+            assert_eq!(
+                QualityThreshold::GoodOnly.accepts(&quality),
+                good_only,
+                "{general}"
+            );
+            assert_eq!(
+                QualityThreshold::GoodOrUncertain.accepts(&quality),
+                good_or_uncertain,
+                "{general}"
+            );
+        }
+        assert_eq!(QualityThreshold::default(), QualityThreshold::GoodOnly);
+    }
+
+    #[test]
+    fn test_reading_display() {
+        let good = TagReading::from_items("42.5".to_string(), &items(QUAL_GOOD, 0));
+        assert_eq!(good.to_string(), "42.5");
+
+        let bad = TagReading::from_items("0".to_string(), &items(QUAL_BAD, 2));
+        assert_eq!(bad.to_string(), "0 (bad: Channel offline (driver error 2))");
+
+        let uncertain = TagReading::from_items("7".to_string(), &items(QUAL_UNCERTAIN, 0));
+        assert_eq!(uncertain.to_string(), "7 (uncertain)");
+
+        let field = TagReading::new("1".to_string(), Quality::NOT_APPLICABLE);
+        assert_eq!(field.to_string(), "1");
+        assert_eq!(field.quality.to_string(), "not applicable");
+        assert!(!field.quality.is_good() && !field.quality.is_bad());
+    }
+
+    #[test]
+    fn test_reading_timestamps() {
+        // 2024-03-01 12:00:00 UTC and one second later
+        let mut items = items(QUAL_GOOD, 0);
+        items.timestamp = 133_537_680_010_000_000;
+        items.value_timestamp = 133_537_680_000_000_000;
+        let reading = TagReading::from_items("1".to_string(), &items);
+        let changed = reading.value_timestamp.unwrap();
+        assert_eq!(changed.to_rfc3339(), "2024-03-01T12:00:00+00:00");
+        assert_eq!((reading.timestamp.unwrap() - changed).num_seconds(), 1);
+        assert_eq!(reading.quality_timestamp, None);
+    }
+
+    #[test]
+    fn test_decoded_parts() {
+        let mut items = items(QUAL_BAD, 0);
+        items.quality_substatus = 6;
+        items.quality_limit = 2;
+        items.boverride = true;
+        let quality = Quality::from_items(&items);
+        assert_eq!(quality.substatus_kind(), QualitySubstatus::CommFailure);
+        assert_eq!(quality.limit_kind(), QualityLimit::High);
+        assert!(quality.overridden && !quality.control_mode);
+
+        // The same substatus means something else for another general quality
+        let good = QualitySubstatus::from_parts(QUAL_GOOD, 6);
+        assert_eq!(good, QualitySubstatus::LocalOverride);
+        let not_applicable = Quality::NOT_APPLICABLE.substatus_kind();
+        assert_eq!(not_applicable, QualitySubstatus::Unknown);
+
+        // Items of the older revision do not carry the flags
+        items.length = CtTagValueItems::QUALITY_LEN;
+        assert!(!Quality::from_items(&items).overridden);
+    }
+
+    #[test]
+    fn test_status_strings_are_stable() {
+        use QualityStatus::*;
+        let cases = [
+            (QUAL_GOOD, Good, "good"),
+            (QUAL_UNCERTAIN, Uncertain, "uncertain"),
+            (QUAL_BAD, Bad, "bad"),
+            (QUAL_NOT_APPLICABLE, NotApplicable, "not applicable"),
+            (2, Unknown, "unknown"),
+        ];
+        for (general, status, text) in cases {
+            let quality = Quality::from_items(&items(general, 0));
+            assert_eq!(quality.status(), status);
+            assert_eq!(status.as_str(), text);
+            assert_eq!(status.to_string(), text);
+            if status != Unknown {
+                assert_eq!(quality.to_string(), text);
+            }
+        }
+        assert_eq!(Quality::from_items(&items(2, 0)).to_string(), "quality 2");
+        let offline = Quality::from_items(&items(QUAL_BAD, 3));
+        assert_eq!(offline.to_string(), "bad: Unit offline (driver error 3)");
+    }
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    #[test]
+    fn test_serialized_strings_are_stable() {
+        let offline = Quality::from_items(&items(QUAL_BAD, 3));
+        assert_eq!(
+            serde_json::to_string(&offline).unwrap(),
+            r#"{"status":"bad","general":0,"substatus":0,"limit":0,"extended_substatus":0,"datasource_error":3,"timestamp":0,"overridden":false,"control_mode":false}"#
+        );
+        let json = serde_json::to_string(&QualityStatus::NotApplicable).unwrap();
+        assert_eq!(json, r#""not applicable""#);
+    }
+}
@@ -0,0 +1,589 @@
+//! Shared, cached find queries
+//!
+//! Dashboards often run the same query (for example "all active alarms") from
+//! several places every few seconds. [`CtClient::shared_query`](crate::CtClient::shared_query)
+//! serves such queries from a per-client [`QueryCache`]: a result younger than
+//! the requested maximum age is returned as is, and concurrent requests for a
+//! query that is already running wait for that execution instead of opening
+//! their own find handle.
+
+use crate::clock::{SharedClock, system_clock};
+use crate::error::{CtApiError, Result};
+use crate::find::{CtFind, FindObject};
+use crate::intern::{SharedStr, StringInterner};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Default maximum number of cached queries per client
+pub const DEFAULT_QUERY_CACHE_CAPACITY: usize = 64;
+
+/// One materialised record of a find query
+///
+/// Holds every field of the record as a string, in table order. Names and
+/// values are [`SharedStr`]s; records read together through one
+/// [`StringInterner`] share the allocations of repeated strings.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Record {
+    fields: Vec<(SharedStr, SharedStr)>,
+}
+
+impl Record {
+    /// Create a record from `(name, value)` pairs
+    pub fn new(fields: Vec<(String, String)>) -> Self {
+        Self {
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    /// Read every field of a find object
+    ///
+    /// Field names come from the `object.fields.count` and
+    /// `object.fields(n).name` metadata properties.
+    pub fn read(object: &FindObject<'_>) -> Result<Self> {
+        Self::read_interned(object, &mut StringInterner::new(0))
+    }
+
+    /// Read every field of a find object, sharing repeated strings through `interner`
+    pub fn read_interned(object: &FindObject<'_>, interner: &mut StringInterner) -> Result<Self> {
+        let count = object.get_property("object.fields.count")?;
+        let count: usize = count.trim().parse().map_err(|_| CtApiError::Other {
+            code: 0,
+            message: format!("invalid field count: {count}"),
+        })?;
+        let fields = (1..=count)
+            .map(|n| {
+                let name = object.get_property(format!("object.fields({n}).name"))?;
+                let value = object.get_property(&name)?;
+                Ok((interner.intern(&name), interner.intern(&value)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { fields })
+    }
+
+    /// Value of the field `name`, compared case-insensitively
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// All `(name, value)` pairs in table order
+    pub fn fields(&self) -> &[(SharedStr, SharedStr)] {
+        &self.fields
+    }
+}
+
+/// Read every remaining record of a search, interning repeated strings
+pub(crate) fn materialize(find: CtFind<'_>) -> Result<Vec<Record>> {
+    materialize_with(find, &mut StringInterner::default())
+}
+
+/// Read every remaining record of a search through `interner`
+pub(crate) fn materialize_with(
+    mut find: CtFind<'_>,
+    interner: &mut StringInterner,
+) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    // Each object is read and dropped before the cursor moves on, so only one
+    // object handle is open at a time
+    while let Some(object) = find.try_next()? {
+        records.push(Record::read_interned(&object, interner)?);
+    }
+    Ok(records)
+}
+
+/// Identity of a query: table, filter and cluster
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct QueryKey {
+    table: String,
+    filter: String,
+    cluster: Option<String>,
+}
+
+impl QueryKey {
+    pub(crate) fn new(table: &str, filter: &str, cluster: Option<&str>) -> Self {
+        Self {
+            table: table.to_string(),
+            filter: filter.to_string(),
+            cluster: cluster.map(str::to_string),
+        }
+    }
+}
+
+/// Outcome of an in-flight execution; `Some(None)` means it failed
+type InFlightSlot = Arc<(Mutex<Option<Option<Arc<Vec<Record>>>>>, Condvar)>;
+
+#[derive(Debug)]
+enum Entry {
+    Ready {
+        records: Arc<Vec<Record>>,
+        fetched_at: Instant,
+        max_age: Duration,
+        last_used: u64,
+    },
+    InFlight(InFlightSlot),
+}
+
+/// Hit and miss counters of a [`QueryCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryStats {
+    /// Requests answered from a cached result
+    pub hits: u64,
+    /// Requests that waited for another caller's execution
+    pub joined: u64,
+    /// Requests that executed the query
+    pub misses: u64,
+}
+
+/// Cache of recent find query results
+///
+/// Entries are dropped once they are older than the maximum age they were
+/// last requested with, and the least recently used entries are evicted when
+/// more than [`capacity`](Self::capacity) queries are cached.
+#[derive(Debug)]
+pub struct QueryCache {
+    clock: SharedClock,
+    entries: Mutex<HashMap<QueryKey, Entry>>,
+    capacity: AtomicUsize,
+    tick: AtomicU64,
+    hits: AtomicU64,
+    joined: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
+
+impl QueryCache {
+    pub(crate) fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            entries: Mutex::new(HashMap::new()),
+            capacity: AtomicUsize::new(DEFAULT_QUERY_CACHE_CAPACITY),
+            tick: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            joined: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Maximum number of cached queries
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Change the maximum number of cached queries
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut entries = self.lock();
+        self.evict(&mut entries);
+    }
+
+    /// Hit and miss counters since creation
+    pub fn stats(&self) -> QueryStats {
+        QueryStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            joined: self.joined.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of cached results
+    pub fn len(&self) -> usize {
+        self.lock()
+            .values()
+            .filter(|entry| matches!(entry, Entry::Ready { .. }))
+            .count()
+    }
+
+    /// Whether no results are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop all cached results
+    pub fn clear(&self) {
+        self.lock()
+            .retain(|_, entry| matches!(entry, Entry::InFlight(_)));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<QueryKey, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Drop expired results, then the least recently used ones above capacity
+    fn evict(&self, entries: &mut HashMap<QueryKey, Entry>) {
+        let now = self.clock.now();
+        entries.retain(|_, entry| match entry {
+            Entry::Ready {
+                fetched_at,
+                max_age,
+                ..
+            } => now.duration_since(*fetched_at) <= *max_age,
+            Entry::InFlight(_) => true,
+        });
+        let capacity = self.capacity();
+        loop {
+            let ready = entries
+                .values()
+                .filter(|e| matches!(e, Entry::Ready { .. }));
+            if ready.count() <= capacity {
+                break;
+            }
+            let oldest = entries
+                .iter()
+                .filter_map(|(key, entry)| match entry {
+                    Entry::Ready { last_used, .. } => Some((*last_used, key.clone())),
+                    Entry::InFlight(_) => None,
+                })
+                .min_by_key(|(last_used, _)| *last_used);
+            match oldest {
+                Some((_, key)) => entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Return a result for `key` no older than `max_age`, running `fetch` at most once
+    ///
+    /// Callers arriving while another caller runs `fetch` for the same key
+    /// wait for its result. If that execution fails, one of the waiters runs
+    /// `fetch` itself.
+    pub(crate) fn get_or_fetch<F>(
+        &self,
+        key: QueryKey,
+        max_age: Duration,
+        fetch: F,
+    ) -> Result<Arc<Vec<Record>>>
+    where
+        F: FnOnce() -> Result<Vec<Record>>,
+    {
+        let slot = loop {
+            let mut entries = self.lock();
+            self.evict(&mut entries);
+            let now = self.clock.now();
+            let tick = self.tick.fetch_add(1, Ordering::Relaxed);
+            let waiting = match entries.get_mut(&key) {
+                Some(Entry::Ready {
+                    records,
+                    fetched_at,
+                    max_age: entry_age,
+                    last_used,
+                }) if now.duration_since(*fetched_at) <= max_age => {
+                    *last_used = tick;
+                    *entry_age = max_age;
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Arc::clone(records));
+                }
+                Some(Entry::InFlight(slot)) => Arc::clone(slot),
+                _ => {
+                    let slot: InFlightSlot = Arc::new((Mutex::new(None), Condvar::new()));
+                    entries.insert(key.clone(), Entry::InFlight(Arc::clone(&slot)));
+                    break slot;
+                }
+            };
+            drop(entries);
+
+            let (outcome, done) = &*waiting;
+            let mut outcome = outcome.lock().unwrap_or_else(|e| e.into_inner());
+            while outcome.is_none() {
+                outcome = done.wait(outcome).unwrap_or_else(|e| e.into_inner());
+            }
+            if let Some(Some(records)) = outcome.as_ref() {
+                self.joined.fetch_add(1, Ordering::Relaxed);
+                return Ok(Arc::clone(records));
+            }
+            // The execution we waited for failed; try again ourselves
+        };
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let mut fetching = Fetching {
+            cache: self,
+            key,
+            slot,
+            max_age,
+            resolved: false,
+        };
+        let result = fetch().map(Arc::new);
+        fetching.resolve(result.as_ref().ok());
+        result
+    }
+}
+
+/// An execution of a query that waiters are blocked on
+///
+/// Resolving it stores or drops the result and wakes the waiters. If `fetch`
+/// panics, dropping it resolves the execution as failed, so that the waiters
+/// do not block forever.
+struct Fetching<'a> {
+    cache: &'a QueryCache,
+    key: QueryKey,
+    slot: InFlightSlot,
+    max_age: Duration,
+    resolved: bool,
+}
+
+impl Fetching<'_> {
+    fn resolve(&mut self, records: Option<&Arc<Vec<Record>>>) {
+        self.resolved = true;
+        let cache = self.cache;
+        let mut entries = cache.lock();
+        match records {
+            Some(records) => {
+                entries.insert(
+                    self.key.clone(),
+                    Entry::Ready {
+                        records: Arc::clone(records),
+                        fetched_at: cache.clock.now(),
+                        max_age: self.max_age,
+                        last_used: cache.tick.fetch_add(1, Ordering::Relaxed),
+                    },
+                );
+                cache.evict(&mut entries);
+            }
+            None => {
+                entries.remove(&self.key);
+            }
+        }
+        drop(entries);
+
+        let (outcome, done) = &*self.slot;
+        *outcome.lock().unwrap_or_else(|e| e.into_inner()) = Some(records.cloned());
+        done.notify_all();
+    }
+}
+
+impl Drop for Fetching<'_> {
+    fn drop(&mut self) {
+        if !self.resolved {
+            self.resolve(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::Barrier;
+    use std::thread;
+
+    fn records(values: &[&str]) -> Vec<Record> {
+        values
+            .iter()
+            .map(|v| Record::new(vec![("TAG".to_string(), v.to_string())]))
+            .collect()
+    }
+
+    fn key(filter: &str) -> QueryKey {
+        QueryKey::new("Alarm", filter, None)
+    }
+
+    #[test]
+    fn test_record_lookup() {
+        let record = Record::new(vec![
+            ("TAG".to_string(), "Pump1".to_string()),
+            ("STATE".to_string(), "ON".to_string()),
+        ]);
+        assert_eq!(record.get("tag"), Some("Pump1"));
+        assert_eq!(record.get("STATE"), Some("ON"));
+        assert_eq!(record.get("DESC"), None);
+        assert_eq!(record.fields().len(), 2);
+    }
+
+    #[test]
+    fn test_record_equality_ignores_sharing() {
+        let mut interner = StringInterner::default();
+        let shared = Record {
+            fields: vec![(interner.intern("UNIT"), interner.intern("°C"))],
+        };
+        let plain = Record::new(vec![("UNIT".to_string(), "°C".to_string())]);
+        assert_eq!(shared, plain);
+        assert_eq!(format!("{shared:?}"), format!("{plain:?}"));
+        assert_eq!(plain.fields()[0].1, "°C");
+    }
+
+    #[test]
+    fn test_cached_until_max_age() {
+        let clock = MockClock::new();
+        let cache = QueryCache::with_clock(clock.clone());
+        let max_age = Duration::from_secs(5);
+
+        let first = cache
+            .get_or_fetch(key("A"), max_age, || Ok(records(&["x"])))
+            .unwrap();
+        clock.advance(Duration::from_secs(4));
+        let second = cache
+            .get_or_fetch(key("A"), max_age, || panic!("served from cache"))
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        clock.advance(Duration::from_secs(2));
+        let third = cache
+            .get_or_fetch(key("A"), max_age, || Ok(records(&["y"])))
+            .unwrap();
+        assert_eq!(third[0].get("TAG"), Some("y"));
+        assert_eq!(
+            cache.stats(),
+            QueryStats {
+                hits: 1,
+                joined: 0,
+                misses: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let cache = QueryCache::with_clock(MockClock::new());
+        let age = Duration::from_secs(60);
+        cache
+            .get_or_fetch(key("A"), age, || Ok(records(&["a"])))
+            .unwrap();
+        let b = cache
+            .get_or_fetch(key("B"), age, || Ok(records(&["b"])))
+            .unwrap();
+        assert_eq!(b[0].get("TAG"), Some("b"));
+        let clustered = QueryKey::new("Alarm", "A", Some("Cluster1"));
+        let c = cache
+            .get_or_fetch(clustered, age, || Ok(records(&["c"])))
+            .unwrap();
+        assert_eq!(c[0].get("TAG"), Some("c"));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_lru_capacity() {
+        let cache = QueryCache::with_clock(MockClock::new());
+        cache.set_capacity(2);
+        let age = Duration::from_secs(60);
+        cache.get_or_fetch(key("A"), age, || Ok(vec![])).unwrap();
+        cache.get_or_fetch(key("B"), age, || Ok(vec![])).unwrap();
+        // Touch A so that B is the least recently used
+        cache
+            .get_or_fetch(key("A"), age, || panic!("cached"))
+            .unwrap();
+        cache.get_or_fetch(key("C"), age, || Ok(vec![])).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        cache
+            .get_or_fetch(key("A"), age, || panic!("A was evicted"))
+            .unwrap();
+        let mut refetched = false;
+        cache
+            .get_or_fetch(key("B"), age, || {
+                refetched = true;
+                Ok(vec![])
+            })
+            .unwrap();
+        assert!(refetched);
+    }
+
+    #[test]
+    fn test_expired_entries_evicted() {
+        let clock = MockClock::new();
+        let cache = QueryCache::with_clock(clock.clone());
+        cache
+            .get_or_fetch(key("A"), Duration::from_secs(1), || Ok(vec![]))
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+        clock.advance(Duration::from_secs(2));
+        cache
+            .get_or_fetch(key("B"), Duration::from_secs(1), || Ok(vec![]))
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_burst_executes_once() {
+        let cache = Arc::new(QueryCache::default());
+        let executions = Arc::new(AtomicU64::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let callers: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                let executions = Arc::clone(&executions);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    cache.get_or_fetch(key("ACTIVE"), Duration::from_secs(10), || {
+                        executions.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(50));
+                        Ok(records(&["alarm"]))
+                    })
+                })
+            })
+            .collect();
+
+        for caller in callers {
+            assert_eq!(caller.join().unwrap().unwrap().len(), 1);
+        }
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits + stats.joined, 7);
+    }
+
+    #[test]
+    fn test_panicking_fetch_releases_waiters() {
+        let cache = Arc::new(QueryCache::with_clock(MockClock::new()));
+        let age = Duration::from_secs(10);
+        let (started, fetching) = std::sync::mpsc::channel();
+        let (unblock, blocked) = std::sync::mpsc::channel::<()>();
+
+        let panicking = {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                cache.get_or_fetch(key("A"), age, || {
+                    started.send(()).unwrap();
+                    blocked.recv().unwrap();
+                    panic!("fetch failed");
+                })
+            })
+        };
+        fetching.recv().unwrap();
+        let waiter = {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || cache.get_or_fetch(key("A"), age, || Ok(records(&["x"]))))
+        };
+        // Held by the cache entry, the running execution and the waiter
+        let waiting = || match cache.lock().get(&key("A")) {
+            Some(Entry::InFlight(slot)) => Arc::strong_count(slot) == 3,
+            _ => false,
+        };
+        while !waiting() {
+            thread::yield_now();
+        }
+
+        unblock.send(()).unwrap();
+        assert!(panicking.join().is_err());
+        let records = waiter.join().unwrap().unwrap();
+        assert_eq!(records[0].get("TAG"), Some("x"));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_failed_execution_not_cached() {
+        let cache = QueryCache::with_clock(MockClock::new());
+        let age = Duration::from_secs(10);
+        let err = cache
+            .get_or_fetch(key("A"), age, || Err(CtApiError::Timeout))
+            .unwrap_err();
+        assert!(matches!(err, CtApiError::Timeout));
+        assert!(cache.is_empty());
+        let ok = cache
+            .get_or_fetch(key("A"), age, || Ok(records(&["x"])))
+            .unwrap();
+        assert_eq!(ok.len(), 1);
+    }
+}
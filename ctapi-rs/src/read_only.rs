@@ -0,0 +1,270 @@
+//! Read-only view of a client
+//!
+//! [`ReadOnlyCtClient`] is a client that can only read: it has no tag write
+//! methods at all, so code handed one (a plugin, a report generator) cannot
+//! write tags even by mistake. Cicode can change the plant too, so
+//! [`cicode`](ReadOnlyCtClient::cicode) only exists on a view whose owner
+//! opted in with [`allow_cicode`](ReadOnlyCtClient::allow_cicode).
+//!
+//! Get one from [`CtClient::open_read_only`], which also opens the connection
+//! with [`CT_OPEN_READ_ONLY`](crate::constants::CT_OPEN_READ_ONLY), or from
+//! an open client with [`CtClient::as_read_only`].
+//!
+//! ```compile_fail
+//! use ctapi_rs::CtClient;
+//! use std::sync::Arc;
+//!
+//! let client = Arc::new(CtClient::open_mock()?).as_read_only();
+//! client.tag_write("Setpoint", 25.5)?; // no such method
+//! # Ok::<(), ctapi_rs::CtApiError>(())
+//! ```
+//!
+//! ```compile_fail
+//! use ctapi_rs::{CicodeWindow, CtClient};
+//! use std::sync::Arc;
+//!
+//! let client = Arc::new(CtClient::open_mock()?).as_read_only();
+//! client.cicode("Time(1)", CicodeWindow::NONE, 0)?; // not allowed yet
+//! # Ok::<(), ctapi_rs::CtApiError>(())
+//! ```
+
+use crate::cicode::{CicodeCall, CicodeWindow};
+use crate::error::Result;
+use crate::{
+    AsyncOperation, CtClient, CtFind, CtList, CtTagValueItems, ListItemValue, ListReadings,
+    ReadItem, TagReading,
+};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Marker of a [`ReadOnlyCtClient`] that cannot run Cicode
+#[derive(Debug)]
+pub enum NoCicode {}
+
+/// Marker of a [`ReadOnlyCtClient`] that can run Cicode
+#[derive(Debug)]
+pub enum WithCicode {}
+
+/// Client that can read tags, search and run read-only lists, but not write
+///
+/// Shares the connection of the [`CtClient`] it was made from; the
+/// connection stays open while either is alive.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::{CicodeWindow, CtClient};
+/// use std::sync::Arc;
+///
+/// let client = Arc::new(CtClient::open_mock()?);
+/// let plugin_view = client.as_read_only();
+/// assert_eq!(plugin_view.tag_read("Temperature")?, "25.5");
+///
+/// let list = plugin_view.list_new(0)?;
+/// list.add_tag("Temperature")?;
+/// list.read()?;
+/// assert_eq!(list.read_tag("Temperature", 0)?, "25.5");
+///
+/// let trusted_view = plugin_view.allow_cicode();
+/// assert_eq!(trusted_view.cicode("Time(1)", CicodeWindow::NONE, 0)?, "10:30:00");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub struct ReadOnlyCtClient<C = NoCicode> {
+    client: Arc<CtClient>,
+    cicode: PhantomData<C>,
+}
+
+impl<C> ReadOnlyCtClient<C> {
+    pub(crate) fn new(client: Arc<CtClient>) -> Self {
+        Self {
+            client,
+            cicode: PhantomData,
+        }
+    }
+
+    /// Read a tag, see [`CtClient::tag_read`]
+    pub fn tag_read<T: AsRef<str>>(&self, tag: T) -> Result<String> {
+        self.client.tag_read(tag)
+    }
+
+    /// Read a tag and parse its value, see [`CtClient::tag_read_as`]
+    pub fn tag_read_as<V: FromStr, T: AsRef<str>>(&self, tag: T) -> Result<V> {
+        self.client.tag_read_as(tag)
+    }
+
+    /// Read several tags in one round trip, see [`CtClient::tag_read_many`]
+    pub fn tag_read_many(&self, tags: &[&str]) -> Vec<(String, Result<String>)> {
+        self.client.tag_read_many(tags)
+    }
+
+    /// Read a tag with its timestamp and quality, see [`CtClient::tag_read_ex`]
+    pub fn tag_read_ex<T: AsRef<str>>(
+        &self,
+        tag: T,
+        tagvalue_items: &mut CtTagValueItems,
+    ) -> Result<String> {
+        self.client.tag_read_ex(tag, tagvalue_items)
+    }
+
+    /// Check whether a tag is configured, see [`CtClient::tag_exists`]
+    pub fn tag_exists(&self, tag: &str) -> Result<bool> {
+        self.client.tag_exists(tag)
+    }
+
+    /// Search a table, see [`CtClient::find_first`]
+    pub fn find_first(&self, table_name: &str, filter: &str, cluster: Option<&str>) -> CtFind<'_> {
+        self.client.find_first(table_name, filter, cluster)
+    }
+
+    /// Create a list that can only be read, see [`CtClient::list_new`]
+    ///
+    /// # Errors
+    /// * [`CtApiError::System`](crate::CtApiError::System) - The list could
+    ///   not be created
+    pub fn list_new(&self, mode: u32) -> Result<ReadOnlyCtList> {
+        Arc::clone(&self.client).list_new(mode).map(ReadOnlyCtList)
+    }
+
+    /// Whether the connection is up, see [`CtClient::is_connected`]
+    pub fn is_connected(&self) -> bool {
+        self.client.is_connected()
+    }
+}
+
+impl ReadOnlyCtClient<NoCicode> {
+    /// View of the same connection that can also run Cicode
+    ///
+    /// Cicode functions can write tags and act on the plant, so only hand
+    /// this view to code trusted to run them.
+    pub fn allow_cicode(self) -> ReadOnlyCtClient<WithCicode> {
+        ReadOnlyCtClient::new(self.client)
+    }
+}
+
+impl ReadOnlyCtClient<WithCicode> {
+    /// Run a Cicode function, see [`CtClient::cicode`]
+    pub fn cicode(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String> {
+        self.client.cicode(cmd, vh_win, mode)
+    }
+
+    /// Run a Cicode call built with [`CicodeCall`], see [`CtClient::cicode_call`]
+    pub fn cicode_call(&self, call: &CicodeCall) -> Result<String> {
+        self.client.cicode_call(call)
+    }
+}
+
+impl<C> Clone for ReadOnlyCtClient<C> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.client))
+    }
+}
+
+impl<C> std::fmt::Debug for ReadOnlyCtClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOnlyCtClient")
+            .field("client", &self.client)
+            .field("cicode", &std::any::type_name::<C>())
+            .finish()
+    }
+}
+
+/// [`CtList`] that can only be read
+///
+/// Created by [`ReadOnlyCtClient::list_new`]. Tags can be added and removed,
+/// but the list has no write methods.
+#[derive(Debug)]
+pub struct ReadOnlyCtList(CtList);
+
+impl ReadOnlyCtList {
+    /// Names of the tags in the list, see [`CtList::tags`]
+    pub fn tags(&self) -> Vec<String> {
+        self.0.tags()
+    }
+
+    /// Call `f` with the name of each tag, see [`CtList::for_each_tag`]
+    pub fn for_each_tag(&self, f: impl FnMut(&str)) {
+        self.0.for_each_tag(f)
+    }
+
+    /// Number of tags in the list, see [`CtList::len`]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the list has no tags, see [`CtList::is_empty`]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether `tag` is in the list, see [`CtList::contains_tag`]
+    pub fn contains_tag(&self, tag: &str) -> bool {
+        self.0.contains_tag(tag)
+    }
+
+    /// Add a tag, see [`CtList::add_tag`]
+    pub fn add_tag<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        self.0.add_tag(tag)
+    }
+
+    /// Remove a tag, see [`CtList::delete_tag`]
+    pub fn delete_tag<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        self.0.delete_tag(tag)
+    }
+
+    /// Read every tag in the list, see [`CtList::read`]
+    pub fn read(&self) -> Result<()> {
+        self.0.read()
+    }
+
+    /// Start reading every tag in the list, see [`CtList::read_async`]
+    pub fn read_async(&self, async_op: &mut AsyncOperation) -> Result<()> {
+        self.0.read_async(async_op)
+    }
+
+    /// Value of a tag from the last read, see [`CtList::read_tag`]
+    pub fn read_tag<T: AsRef<str>>(&self, tag: T, mode: u32) -> Result<String> {
+        self.0.read_tag(tag, mode)
+    }
+
+    /// Parsed value of a tag from the last read, see [`CtList::read_tag_as`]
+    pub fn read_tag_as<V: FromStr, T: AsRef<str>>(&self, tag: T, mode: u32) -> Result<V> {
+        self.0.read_tag_as(tag, mode)
+    }
+
+    /// Value of every tag from the last read, see [`CtList::read_all`]
+    pub fn read_all(&self, mode: u32) -> HashMap<String, Result<String>> {
+        self.0.read_all(mode)
+    }
+
+    /// Value of every tag from the last read in the order they were added,
+    /// see [`CtList::read_all_ordered`]
+    pub fn read_all_ordered(&self, mode: u32) -> Vec<(String, Result<String>)> {
+        self.0.read_all_ordered(mode)
+    }
+
+    /// Read the list and return the value of every tag, see [`CtList::snapshot`]
+    pub fn snapshot(&self, mode: u32) -> Result<HashMap<String, Result<String>>> {
+        self.0.snapshot(mode)
+    }
+
+    /// An item of a tag from the last read, see [`CtList::read_tag_item`]
+    pub fn read_tag_item<T: AsRef<str>>(
+        &self,
+        tag: T,
+        item: ReadItem,
+        mode: u32,
+    ) -> Result<ListItemValue> {
+        self.0.read_tag_item(tag, item, mode)
+    }
+
+    /// Value and quality of a tag from the last read, see [`CtList::read_tag_full`]
+    pub fn read_tag_full<T: AsRef<str>>(&self, tag: T) -> Result<TagReading> {
+        self.0.read_tag_full(tag)
+    }
+
+    /// Every tag with its quality from the last read, see [`CtList::read_all_full`]
+    pub fn read_all_full(&self) -> Result<ListReadings> {
+        self.0.read_all_full()
+    }
+}
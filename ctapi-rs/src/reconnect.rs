@@ -0,0 +1,746 @@
+//! Gate that holds calls back while CtAPI reconnects
+//!
+//! A client opened with [`CT_OPEN_RECONNECT`](crate::CT_OPEN_RECONNECT) keeps
+//! its handle while the server is unreachable, but every call on it fails
+//! immediately until the DLL has reconnected. Retrying such calls in a loop
+//! spins the CPU and floods the DLL.
+//!
+//! [`ReconnectGate`] wraps calls instead: the first call that fails with a
+//! "connection down" error closes the gate. Calls made while the gate is
+//! closed wait on a condition variable rather than calling the DLL, and one
+//! of the waiters probes the connection at a backoff interval. When a probe
+//! succeeds the gate opens and all waiters proceed. When the [`Backoff`]
+//! strategy gives up, the gate fails calls until
+//! [`ReconnectGate::resume_probing`] is called.
+//!
+//! A call is only run again after a failure that left its outcome unknown,
+//! such as a connection aborted mid-call, if it is marked idempotent through
+//! [`CallOptions`]; see [`idempotency`](crate::idempotency).
+//!
+//! A gate attached to a client with [`ReconnectGate::track_state`] reports
+//! its transitions through the client's connection state notifications.
+
+use crate::CtClient;
+use crate::backoff::Backoff;
+use crate::clock::{SharedClock, system_clock};
+use crate::error::{CtApiError, Result};
+use crate::idempotency::{Idempotency, Operation, is_ambiguous_failure};
+use crate::retry::RetryPolicy;
+use crate::state::{ConnectionState, StateTracker};
+use crate::sync::{Condvar, Mutex, MutexGuard};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Win32 error codes that mean the connection to the server is down
+const CONNECTION_DOWN_ERRORS: &[i32] = &[
+    64,   // ERROR_NETNAME_DELETED
+    109,  // ERROR_BROKEN_PIPE
+    232,  // ERROR_NO_DATA (pipe closing)
+    233,  // ERROR_PIPE_NOT_CONNECTED
+    1229, // ERROR_CONNECTION_INVALID
+    1236, // ERROR_CONNECTION_ABORTED
+    1722, // RPC_S_SERVER_UNAVAILABLE
+    1726, // RPC_S_CALL_FAILED
+    2250, // ERROR_NOT_CONNECTED
+];
+
+/// Whether `error` means the connection to the server is down
+///
+/// Such errors close a [`ReconnectGate`]; any other error is returned to the
+/// caller unchanged.
+pub fn is_connection_down(error: &CtApiError) -> bool {
+    match error.root() {
+        CtApiError::System(e, ..) => e
+            .raw_os_error()
+            .is_some_and(|code| CONNECTION_DOWN_ERRORS.contains(&code)),
+        CtApiError::ConnectionFailed { .. } => true,
+        _ => false,
+    }
+}
+
+/// Options for a single [`ReconnectGate::call_with`]
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::{CallOptions, Operation};
+/// use std::time::Duration;
+///
+/// let options = CallOptions::from(Operation::TagWrite).timeout(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallOptions {
+    idempotency: Idempotency,
+    timeout: Option<Duration>,
+}
+
+impl CallOptions {
+    /// Options for a non-idempotent call with the gate's default timeout
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the call may be run again after an ambiguous failure
+    pub fn idempotency(mut self, idempotency: impl Into<Idempotency>) -> Self {
+        self.idempotency = idempotency.into();
+        self
+    }
+
+    /// Time the call may wait for the connection to come back
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl From<Operation> for CallOptions {
+    fn from(operation: Operation) -> Self {
+        Self::new().idempotency(operation)
+    }
+}
+
+/// Probe used by [`ReconnectGate`] to test whether the connection is back
+pub type ReconnectProbe = Box<dyn Fn() -> Result<()> + Send + Sync>;
+
+#[derive(Debug)]
+struct GateState {
+    down: bool,
+    probing: bool,
+    given_up: bool,
+    failed_probes: u32,
+    next_probe: Instant,
+    backoff: Box<dyn Backoff>,
+}
+
+impl GateState {
+    fn new(backoff: Box<dyn Backoff>, now: Instant) -> Mutex<Self> {
+        Mutex::new(Self {
+            down: false,
+            probing: false,
+            given_up: false,
+            failed_probes: 0,
+            next_probe: now,
+            backoff,
+        })
+    }
+
+    /// Error returned by calls once probing has given up
+    fn gave_up(&self) -> CtApiError {
+        CtApiError::ConnectionFailed {
+            message: format!(
+                "gave up reconnecting after {} failed probes",
+                self.failed_probes
+            ),
+        }
+    }
+}
+
+/// Holds calls back while the connection is down
+///
+/// # Examples
+/// ```no_run
+/// use ctapi_rs::{CT_OPEN_RECONNECT, CicodeWindow, CtClient, Operation, ReconnectGate};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let client = Arc::new(CtClient::open(None, None, None, CT_OPEN_RECONNECT)?);
+/// let probe_client = Arc::clone(&client);
+/// let gate = ReconnectGate::new(move || probe_client.cicode("Version(0)", CicodeWindow::NONE, 0).map(|_| ()))
+///     .call_timeout(Duration::from_secs(10));
+///
+/// let value = gate.call_with(Operation::TagRead, || client.tag_read("Temperature"))?;
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub struct ReconnectGate {
+    probe: ReconnectProbe,
+    call_timeout: Duration,
+    state: Mutex<GateState>,
+    reopened: Condvar,
+    tracker: Option<Arc<StateTracker>>,
+    clock: SharedClock,
+}
+
+impl std::fmt::Debug for ReconnectGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconnectGate")
+            .field("call_timeout", &self.call_timeout)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReconnectGate {
+    /// Create an open gate that uses `probe` to test the connection
+    ///
+    /// The probe should be a cheap call on the same client, for example a
+    /// trivial Cicode function. Probes start 500 ms after the outage is
+    /// detected and back off up to 30 s; calls wait at most 30 s.
+    pub fn new<F>(probe: F) -> Self
+    where
+        F: Fn() -> Result<()> + Send + Sync + 'static,
+    {
+        let clock = system_clock();
+        Self {
+            probe: Box::new(probe),
+            call_timeout: Duration::from_secs(30),
+            state: GateState::new(RetryPolicy::default().strategy(), clock.now()),
+            reopened: Condvar::new(),
+            tracker: None,
+            clock,
+        }
+    }
+
+    /// Measure call timeouts and probe intervals with `clock`
+    pub(crate) fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.lock().next_probe = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// Report transitions through `client`'s connection state notifications
+    ///
+    /// Closing the gate moves the client to [`ConnectionState::Reconnecting`],
+    /// a call timing out while the gate is closed moves it to
+    /// [`ConnectionState::Down`], and a successful probe moves it back to
+    /// [`ConnectionState::Connected`].
+    pub fn track_state(mut self, client: &CtClient) -> Self {
+        self.tracker = Some(Arc::clone(client.state_tracker()));
+        self
+    }
+
+    /// Interval between probes
+    ///
+    /// Only the backoff settings of `policy` are used, its deadline is
+    /// ignored; with the default exponential strategy probing continues
+    /// until it succeeds.
+    pub fn probe_backoff(mut self, policy: RetryPolicy) -> Self {
+        self.state = GateState::new(policy.strategy(), self.clock.now());
+        self
+    }
+
+    /// Strategy choosing the interval between probes
+    ///
+    /// The strategy is reset each time the gate closes. Once it returns
+    /// `None` the gate stops probing: waiting and later calls fail with
+    /// [`CtApiError::ConnectionFailed`] until [`resume_probing`](Self::resume_probing).
+    pub fn probe_strategy<B: Backoff + 'static>(mut self, backoff: B) -> Self {
+        self.state = GateState::new(Box::new(backoff), self.clock.now());
+        self
+    }
+
+    /// Default time a call may wait for the connection to come back
+    pub fn call_timeout(mut self, timeout: Duration) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+
+    /// Whether the gate is currently closed
+    pub fn is_down(&self) -> bool {
+        self.lock().down
+    }
+
+    /// Whether the probe strategy has given up on the connection
+    pub fn has_given_up(&self) -> bool {
+        self.lock().given_up
+    }
+
+    /// Start probing again after the probe strategy gave up
+    ///
+    /// The strategy is reset and the next call probes at once. Does nothing
+    /// if probing has not given up.
+    pub fn resume_probing(&self) {
+        let mut state = self.lock();
+        if state.given_up {
+            state.given_up = false;
+            state.failed_probes = 0;
+            state.backoff.reset();
+            state.next_probe = self.clock.now();
+            drop(state);
+            self.report(ConnectionState::Reconnecting, None);
+        }
+    }
+
+    /// Run `op`, waiting up to the default call timeout while the connection is down
+    ///
+    /// `op` is treated as non-idempotent, see [`call_with`](Self::call_with).
+    ///
+    /// # Errors
+    /// * [`CtApiError::Timeout`] - Connection did not come back in time
+    /// * [`CtApiError::ConnectionFailed`] - The probe strategy gave up
+    /// * [`CtApiError::AmbiguousOutcome`] - The connection dropped mid-call
+    /// * Any error returned by `op` other than a connection failure
+    pub fn call<T, F>(&self, op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        self.call_with(CallOptions::new(), op)
+    }
+
+    /// Run `op`, waiting up to `timeout` while the connection is down
+    ///
+    /// `op` is treated as non-idempotent, see [`call_with`](Self::call_with).
+    pub fn call_with_timeout<T, F>(&self, timeout: Duration, op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        self.call_with(CallOptions::new().timeout(timeout), op)
+    }
+
+    /// Run `op` with per-call options
+    ///
+    /// If `op` fails with a connection error the gate closes and the call
+    /// waits for the gate to reopen before running `op` again. When the
+    /// failure leaves the outcome unknown and `op` is not idempotent, the
+    /// gate still closes but the call fails with
+    /// [`CtApiError::AmbiguousOutcome`] instead of running `op` again.
+    ///
+    /// # Errors
+    /// As [`call`](Self::call).
+    pub fn call_with<T, F>(&self, options: impl Into<CallOptions>, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let options = options.into();
+        let deadline = self.clock.now() + options.timeout.unwrap_or(self.call_timeout);
+        loop {
+            self.wait_open(deadline)?;
+            match op() {
+                Err(error) if is_connection_down(&error) => {
+                    if is_ambiguous_failure(&error) && !options.idempotency.is_idempotent() {
+                        self.trip(CtApiError::ConnectionFailed {
+                            message: error.to_string(),
+                        });
+                        return Err(CtApiError::AmbiguousOutcome {
+                            source: Box::new(error),
+                        });
+                    }
+                    self.trip(error);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Forward a transition to the attached client, if any
+    fn report(&self, state: ConnectionState, error: Option<CtApiError>) {
+        if let Some(tracker) = &self.tracker {
+            tracker.set(state, error);
+        }
+    }
+
+    /// Close the gate after a connection failure
+    fn trip(&self, error: CtApiError) {
+        let mut state = self.lock();
+        if !state.down {
+            state.down = true;
+            state.failed_probes = 0;
+            state.backoff.reset();
+            match state.backoff.next_delay(1, &error) {
+                Some(delay) => {
+                    state.next_probe = self.clock.now() + delay;
+                    drop(state);
+                    self.report(ConnectionState::Reconnecting, Some(error));
+                }
+                None => {
+                    state.given_up = true;
+                    drop(state);
+                    self.report(ConnectionState::Down, Some(error));
+                }
+            }
+        }
+    }
+
+    /// Block until the gate is open, probing when a probe is due
+    fn wait_open(&self, deadline: Instant) -> Result<()> {
+        let mut state = self.lock();
+        while state.down {
+            if state.given_up {
+                return Err(state.gave_up());
+            }
+            let now = self.clock.now();
+            if !state.probing && now >= state.next_probe {
+                state.probing = true;
+                drop(state);
+                let probe = (self.probe)();
+                state = self.lock();
+                state.probing = false;
+                let error = match probe {
+                    Ok(()) => {
+                        state.down = false;
+                        self.reopened.notify_all();
+                        drop(state);
+                        self.report(ConnectionState::Connected, None);
+                        return Ok(());
+                    }
+                    Err(error) => error,
+                };
+                state.failed_probes += 1;
+                let attempt = state.failed_probes + 1;
+                match state.backoff.next_delay(attempt, &error) {
+                    Some(delay) => state.next_probe = self.clock.now() + delay,
+                    None => {
+                        state.given_up = true;
+                        let gave_up = state.gave_up();
+                        self.reopened.notify_all();
+                        drop(state);
+                        self.report(ConnectionState::Down, Some(error));
+                        return Err(gave_up);
+                    }
+                }
+                // Let another waiter pick up the next probe if it is due first
+                self.reopened.notify_all();
+                continue;
+            }
+            if now >= deadline {
+                drop(state);
+                self.report(ConnectionState::Down, Some(CtApiError::Timeout));
+                return Err(CtApiError::Timeout);
+            }
+            if state.probing {
+                // Woken when the probe in progress has finished
+                state = self
+                    .reopened
+                    .wait_timeout(state, deadline - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+                continue;
+            }
+            // Claim the next probe and wait for it on the clock; the other
+            // waiters are woken when this one is done
+            state.probing = true;
+            let wake = state.next_probe.min(deadline);
+            drop(state);
+            self.clock.sleep_until(wake);
+            state = self.lock();
+            state.probing = false;
+            self.reopened.notify_all();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backoff::Fixed;
+    use crate::clock::MockClock;
+    use crate::state::StateChange;
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::thread;
+
+    fn connection_lost() -> CtApiError {
+        io::Error::from_raw_os_error(1722).into()
+    }
+
+    /// Simulated server whose availability the test controls
+    #[derive(Default)]
+    struct Server {
+        up: AtomicBool,
+        calls: AtomicU32,
+        probes: AtomicU32,
+        /// Number of the probe that finds the server back up, 0 for never
+        recovers_at: u32,
+    }
+
+    impl Server {
+        /// Server that is down until `failing` probes have failed
+        fn recovering(failing: u32) -> Arc<Self> {
+            Arc::new(Self {
+                recovers_at: failing + 1,
+                ..Self::default()
+            })
+        }
+
+        fn read(&self) -> Result<&'static str> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.up.load(Ordering::SeqCst) {
+                Ok("42")
+            } else {
+                Err(connection_lost())
+            }
+        }
+
+        fn probe(&self) -> Result<()> {
+            let probe = self.probes.fetch_add(1, Ordering::SeqCst) + 1;
+            if probe == self.recovers_at {
+                self.up.store(true, Ordering::SeqCst);
+            }
+            if self.up.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(connection_lost())
+            }
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .initial_delay(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(40))
+            .jitter(0.0)
+    }
+
+    /// Time the gate waits before probes `1..=probes`
+    fn probe_delays(probes: u32) -> Duration {
+        let delay = |attempt| policy().delay(attempt, 0.0);
+        (1..=probes).map(delay).sum()
+    }
+
+    fn gate(server: &Arc<Server>, clock: &Arc<MockClock>) -> ReconnectGate {
+        let probe_server = Arc::clone(server);
+        ReconnectGate::new(move || probe_server.probe())
+            .with_clock(clock.clone())
+            .probe_backoff(policy())
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(is_connection_down(&connection_lost()));
+        assert!(is_connection_down(&CtApiError::ConnectionFailed {
+            message: "lost".into()
+        }));
+        assert!(!is_connection_down(&CtApiError::Timeout));
+        assert!(!is_connection_down(&CtApiError::TagNotFound {
+            tag: "x".into()
+        }));
+    }
+
+    #[test]
+    fn test_open_gate_passes_through() {
+        let server = Arc::new(Server::default());
+        server.up.store(true, Ordering::SeqCst);
+        let gate = gate(&server, &MockClock::new());
+
+        assert_eq!(gate.call(|| server.read()).unwrap(), "42");
+        let err = gate
+            .call(|| -> Result<()> { Err(CtApiError::TagNotFound { tag: "x".into() }) })
+            .unwrap_err();
+        assert!(matches!(err, CtApiError::TagNotFound { .. }));
+        assert!(!gate.is_down());
+        assert_eq!(server.probes.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_calls_during_outage_wait_and_succeed() {
+        let server = Server::recovering(3);
+        let clock = MockClock::new();
+        let gate = Arc::new(gate(&server, &clock).call_timeout(Duration::from_secs(5)));
+
+        let callers: Vec<_> = (0..4)
+            .map(|_| {
+                let gate = Arc::clone(&gate);
+                let server = Arc::clone(&server);
+                thread::spawn(move || gate.call(|| server.read()))
+            })
+            .collect();
+        for caller in callers {
+            assert_eq!(caller.join().unwrap().unwrap(), "42");
+        }
+
+        assert!(!gate.is_down());
+        // Each caller hit the DLL at most once before the gate closed and
+        // once after it reopened
+        let calls = server.calls.load(Ordering::SeqCst);
+        assert!(calls <= 8, "{calls} calls");
+        // One probe at a time, backing off between the failed ones
+        assert_eq!(server.probes.load(Ordering::SeqCst), 4);
+        assert_eq!(clock.elapsed(), probe_delays(4));
+    }
+
+    #[test]
+    fn test_call_times_out_without_spinning() {
+        let server = Arc::new(Server::default());
+        let clock = MockClock::new();
+        let gate = gate(&server, &clock);
+
+        let err = gate
+            .call_with_timeout(Duration::from_millis(100), || server.read())
+            .unwrap_err();
+
+        assert!(matches!(err, CtApiError::Timeout));
+        assert_eq!(clock.elapsed(), Duration::from_millis(100));
+        assert_eq!(server.calls.load(Ordering::SeqCst), 1);
+        // Probes after 10, 30 and 70 ms; the next one would be after the deadline
+        assert_eq!(server.probes.load(Ordering::SeqCst), 3);
+        assert!(gate.is_down());
+    }
+
+    #[test]
+    fn test_transitions_reported_to_client() {
+        let server = Arc::new(Server::default());
+        server.up.store(true, Ordering::SeqCst);
+        let client = CtClient::from_handle(std::ptr::null_mut());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        client.on_state_change(move |change: &StateChange| {
+            sink.lock()
+                .unwrap()
+                .push((change.state, change.error.is_some()))
+        });
+        let gate = gate(&server, &MockClock::new()).track_state(&client);
+
+        gate.call(|| server.read()).unwrap();
+        server.up.store(false, Ordering::SeqCst);
+        let err = gate
+            .call_with_timeout(Duration::from_millis(50), || server.read())
+            .unwrap_err();
+        assert!(matches!(err, CtApiError::Timeout));
+        assert_eq!(client.connection_state(), ConnectionState::Down);
+
+        server.up.store(true, Ordering::SeqCst);
+        gate.call(|| server.read()).unwrap();
+        assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+        use ConnectionState::*;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [(Reconnecting, true), (Down, true), (Connected, false)]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_failure_retried_only_when_idempotent() {
+        let server = Arc::new(Server::default());
+        server.up.store(true, Ordering::SeqCst);
+        let gate = gate(&server, &MockClock::new()).call_timeout(Duration::from_secs(5));
+        let writes = AtomicU32::new(0);
+        // ERROR_CONNECTION_ABORTED on the first attempt, after the server applied the write
+        let write = || -> Result<()> {
+            if writes.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(io::Error::from_raw_os_error(1236).into())
+            } else {
+                Ok(())
+            }
+        };
+
+        let err = gate.call_with(Operation::Toggle, write).unwrap_err();
+        let CtApiError::AmbiguousOutcome { source } = &err else {
+            panic!("{err:?}");
+        };
+        assert!(matches!(**source, CtApiError::System(..)));
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert!(gate.is_down());
+
+        writes.store(0, Ordering::SeqCst);
+        gate.call_with(Operation::TagWrite, write).unwrap();
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_clean_connection_failure_retried_for_any_operation() {
+        let server = Arc::new(Server::default());
+        server.up.store(true, Ordering::SeqCst);
+        let gate = gate(&server, &MockClock::new()).call_timeout(Duration::from_secs(5));
+        let writes = AtomicU32::new(0);
+        // The request never reached the server
+        let write = || -> Result<()> {
+            if writes.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(CtApiError::ConnectionFailed {
+                    message: "connection refused".into(),
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        gate.call(write).unwrap();
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_probe_strategy_gives_up() {
+        let server = Arc::new(Server::default());
+        let probe_server = Arc::clone(&server);
+        let clock = MockClock::new();
+        let gate = ReconnectGate::new(move || probe_server.probe())
+            .with_clock(clock.clone())
+            .probe_strategy(Fixed::new(Duration::from_millis(5)).max_attempts(3));
+
+        let err = gate
+            .call_with_timeout(Duration::from_secs(5), || server.read())
+            .unwrap_err();
+        let CtApiError::ConnectionFailed { message } = &err else {
+            panic!("{err:?}");
+        };
+        assert!(message.contains("after 2 failed probes"), "{message}");
+        assert_eq!(clock.elapsed(), Duration::from_millis(10));
+        assert!(gate.has_given_up());
+        assert_eq!(server.probes.load(Ordering::SeqCst), 2);
+
+        // Later calls fail at once without probing or calling the DLL
+        let err = gate.call(|| server.read()).unwrap_err();
+        assert!(matches!(err, CtApiError::ConnectionFailed { .. }));
+        assert_eq!(server.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(server.probes.load(Ordering::SeqCst), 2);
+
+        server.up.store(true, Ordering::SeqCst);
+        gate.resume_probing();
+        assert!(!gate.has_given_up());
+        assert_eq!(gate.call(|| server.read()).unwrap(), "42");
+        assert!(!gate.is_down());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use loom::thread;
+
+    /// Callers released together must be served by a single probe at a time
+    #[test]
+    fn loom_release_storm_probes_one_at_a_time() {
+        loom::model(|| {
+            let up = Arc::new(AtomicBool::new(false));
+            let probing = Arc::new(AtomicUsize::new(0));
+            let probes = Arc::new(AtomicUsize::new(0));
+            let gate = {
+                let (up, probing, probes) = (up.clone(), probing.clone(), probes.clone());
+                ReconnectGate::new(move || {
+                    assert_eq!(
+                        probing.fetch_add(1, Ordering::SeqCst),
+                        0,
+                        "concurrent probes"
+                    );
+                    probes.fetch_add(1, Ordering::SeqCst);
+                    up.store(true, Ordering::SeqCst);
+                    probing.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                })
+                .probe_backoff(
+                    RetryPolicy::default()
+                        .initial_delay(Duration::ZERO)
+                        .jitter(0.0),
+                )
+            };
+            let gate = Arc::new(gate);
+            let read = |up: &AtomicBool| {
+                if up.load(Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err(CtApiError::ConnectionFailed {
+                        message: "down".into(),
+                    })
+                }
+            };
+
+            let callers: Vec<_> = (0..2)
+                .map(|_| {
+                    let (gate, up) = (gate.clone(), up.clone());
+                    thread::spawn(move || gate.call(|| read(&up)))
+                })
+                .collect();
+            for caller in callers {
+                caller.join().unwrap().unwrap();
+            }
+            assert!(!gate.is_down());
+            assert!((1..=2).contains(&probes.load(Ordering::SeqCst)));
+        });
+    }
+}
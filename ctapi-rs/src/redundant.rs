@@ -0,0 +1,441 @@
+//! Reads from a redundant server pair
+//!
+//! Citect SCADA runs I/O servers in primary and standby pairs. For
+//! safety-relevant displays a [`RedundantReader`] reads critical tags through
+//! a client connected to each server and reports which one answered. The
+//! [`ReadPolicy`] decides how the two are used:
+//!
+//! - [`PreferPrimary`](ReadPolicy::PreferPrimary) reads the primary and falls
+//!   back to the standby when the read fails
+//! - [`Fastest`](ReadPolicy::Fastest) reads both at once and returns the first
+//!   good answer
+//! - [`Compare`](ReadPolicy::Compare) reads both at once and flags a
+//!   [`Disagreement`] when the values or qualities differ
+//!
+//! In `Fastest` mode both servers are read with an overlapped `ctListRead`
+//! on a temporary list. Once one has answered with good quality the other
+//! read is cancelled with `ctCancelIO`, and the call returns after CtAPI has
+//! completed the cancellation, so no read outlives it.
+
+use crate::async_ops::AsyncOperation;
+use crate::client::CtClient;
+use crate::error::{CtApiError, Result};
+use crate::list::CtList;
+use crate::quality::TagReading;
+use std::sync::Arc;
+use std::thread;
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Threading::WaitForMultipleObjects;
+
+/// `INFINITE` timeout for `WaitForMultipleObjects`
+const INFINITE: u32 = u32::MAX;
+
+/// Server of a redundant pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServerRole {
+    /// Primary server
+    Primary,
+    /// Standby server
+    Standby,
+}
+
+/// How a [`RedundantReader`] uses the two servers
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReadPolicy {
+    /// Read the primary, and the standby if that fails
+    #[default]
+    PreferPrimary,
+    /// Read both, the first answer with good quality wins
+    ///
+    /// Without a good answer, the primary's reading is returned, then the
+    /// standby's, then the primary's error.
+    Fastest,
+    /// Read both and return the primary's answer, flagging differences
+    ///
+    /// Numeric values disagree when they are more than `tolerance` apart,
+    /// other values when they differ. If one server fails, the other's
+    /// answer is returned with [`Disagreement::Unavailable`].
+    Compare {
+        /// Largest difference between numeric values that still agrees
+        tolerance: f64,
+    },
+}
+
+/// Difference between the answers of the two servers, see [`ReadPolicy::Compare`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Disagreement {
+    /// The other server reported a different general quality
+    Quality {
+        /// Reading of the other server
+        other: TagReading,
+    },
+    /// The other server's value differs beyond the tolerance
+    Value {
+        /// Reading of the other server
+        other: TagReading,
+    },
+    /// The other server could not be read
+    Unavailable {
+        /// Error the read failed with
+        error: String,
+    },
+}
+
+/// Answer of a [`RedundantReader`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedundantReading {
+    /// Value and quality returned
+    pub reading: TagReading,
+    /// Server that returned `reading`
+    pub server: ServerRole,
+    /// How the other server's answer differs, always `None` unless comparing
+    pub disagreement: Option<Disagreement>,
+}
+
+impl RedundantReading {
+    fn new(server: ServerRole, reading: TagReading) -> Self {
+        Self {
+            reading,
+            server,
+            disagreement: None,
+        }
+    }
+
+    /// Whether both servers agreed, or only one was read
+    pub fn agrees(&self) -> bool {
+        self.disagreement.is_none()
+    }
+}
+
+/// Reads tags from a primary and a standby server
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::redundant::{ReadPolicy, RedundantReader, ServerRole};
+/// use std::sync::Arc;
+///
+/// let primary = Arc::new(CtClient::open_mock()?);
+/// let standby = Arc::new(CtClient::open_mock()?);
+/// let reader = RedundantReader::new(primary, standby);
+///
+/// let answer = reader.read("Pressure", ReadPolicy::Compare { tolerance: 0.01 })?;
+/// assert_eq!(answer.server, ServerRole::Primary);
+/// assert!(answer.agrees());
+/// assert_eq!(answer.reading.value, "1.2");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedundantReader {
+    primary: Arc<CtClient>,
+    standby: Arc<CtClient>,
+}
+
+/// Overlapped read of one server in a [`ReadPolicy::Fastest`] race
+///
+/// Dropped before its read completed, it cancels the read and waits for
+/// the cancellation before the OVERLAPPED and the list are freed.
+struct Race {
+    server: ServerRole,
+    client: Arc<CtClient>,
+    /// Boxed so the OVERLAPPED stays in place when the race is moved
+    op: Box<AsyncOperation>,
+    list: CtList,
+}
+
+impl Race {
+    fn start(server: ServerRole, client: &Arc<CtClient>, tag: &str) -> Result<Self> {
+        let list = Arc::clone(client).list_new(0)?;
+        list.add_tag(tag)?;
+        let mut op = Box::new(AsyncOperation::new());
+        list.read_async(op.as_mut())?;
+        Ok(Self {
+            server,
+            client: Arc::clone(client),
+            op,
+            list,
+        })
+    }
+
+    /// Event signalled once the read has completed
+    fn event(&self) -> HANDLE {
+        self.op.win_event_handle()
+    }
+
+    /// Collect the completed read and the reading of `tag`
+    fn finish(&mut self, tag: &str) -> Result<TagReading> {
+        self.op.get_result(&self.client)?;
+        self.list.read_tag_full(tag)
+    }
+}
+
+impl Drop for Race {
+    fn drop(&mut self) {
+        if !self.op.is_complete() {
+            // A read completing before it is cancelled is collected all the same
+            let _ = self.client.cancel_io(Some(self.op.as_mut()));
+            let _ = self.op.get_result(&self.client);
+        }
+    }
+}
+
+/// Index of the first race whose read has completed, waiting for one
+fn first_completed(races: &[Race]) -> Result<usize> {
+    let events: Vec<_> = races.iter().map(Race::event).collect();
+    let count = events.len() as u32;
+    // SAFETY: the events belong to the operations of `races`, which stay
+    // alive for the duration of the wait.
+    let waited = unsafe { WaitForMultipleObjects(count, events.as_ptr(), 0, INFINITE) };
+    // WAIT_OBJECT_0 is 0, so a signalled event is reported by its index
+    let index = waited as usize;
+    if index < races.len() {
+        return Ok(index);
+    }
+    Err(std::io::Error::last_os_error().into())
+}
+
+impl RedundantReader {
+    /// Reader using a client connected to each server of the pair
+    pub fn new(primary: Arc<CtClient>, standby: Arc<CtClient>) -> Self {
+        Self { primary, standby }
+    }
+
+    /// Client connected to `server`
+    pub fn client(&self, server: ServerRole) -> &Arc<CtClient> {
+        match server {
+            ServerRole::Primary => &self.primary,
+            ServerRole::Standby => &self.standby,
+        }
+    }
+
+    /// Read `tag` with its quality according to `policy`
+    ///
+    /// # Errors
+    /// The primary's error if no server could be read; any error of
+    /// [`CtClient::tag_read_with_quality`].
+    pub fn read(&self, tag: &str, policy: ReadPolicy) -> Result<RedundantReading> {
+        match policy {
+            ReadPolicy::PreferPrimary => self.prefer_primary(tag),
+            ReadPolicy::Fastest => self.fastest(tag),
+            ReadPolicy::Compare { tolerance } => self.compare(tag, tolerance),
+        }
+    }
+
+    fn prefer_primary(&self, tag: &str) -> Result<RedundantReading> {
+        match self.primary.tag_read_with_quality(tag) {
+            Ok(reading) => Ok(RedundantReading::new(ServerRole::Primary, reading)),
+            Err(primary_error) => match self.standby.tag_read_with_quality(tag) {
+                Ok(reading) => Ok(RedundantReading::new(ServerRole::Standby, reading)),
+                Err(_) => Err(primary_error),
+            },
+        }
+    }
+
+    fn fastest(&self, tag: &str) -> Result<RedundantReading> {
+        let (mut primary, mut standby) = (None, None);
+        let mut races = Vec::with_capacity(2);
+        for server in [ServerRole::Primary, ServerRole::Standby] {
+            match Race::start(server, self.client(server), tag) {
+                Ok(race) => races.push(race),
+                Err(e) if server == ServerRole::Primary => primary = Some(Err(e)),
+                Err(e) => standby = Some(Err(e)),
+            }
+        }
+        // Returning drops the races left, cancelling the slower read
+        while !races.is_empty() {
+            let mut race = races.swap_remove(first_completed(&races)?);
+            match race.finish(tag) {
+                Ok(reading) if reading.quality.is_good() => {
+                    return Ok(RedundantReading::new(race.server, reading));
+                }
+                result if race.server == ServerRole::Primary => primary = Some(result),
+                result => standby = Some(result),
+            }
+        }
+        match (primary, standby) {
+            (Some(Ok(reading)), _) => Ok(RedundantReading::new(ServerRole::Primary, reading)),
+            (_, Some(Ok(reading))) => Ok(RedundantReading::new(ServerRole::Standby, reading)),
+            (Some(Err(e)), _) | (None, Some(Err(e))) => Err(e),
+            (None, None) => unreachable!("both servers are read"),
+        }
+    }
+
+    fn compare(&self, tag: &str, tolerance: f64) -> Result<RedundantReading> {
+        let (primary, standby) = thread::scope(|scope| {
+            let standby = scope.spawn(|| self.standby.tag_read_with_quality(tag));
+            let primary = self.primary.tag_read_with_quality(tag);
+            let standby = standby
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            (primary, standby)
+        });
+        match (primary, standby) {
+            (Ok(primary), Ok(standby)) => {
+                let disagreement = if primary.quality.general != standby.quality.general {
+                    Some(Disagreement::Quality { other: standby })
+                } else if !values_agree(&primary.value, &standby.value, tolerance) {
+                    Some(Disagreement::Value { other: standby })
+                } else {
+                    None
+                };
+                Ok(RedundantReading {
+                    disagreement,
+                    ..RedundantReading::new(ServerRole::Primary, primary)
+                })
+            }
+            (Ok(reading), Err(e)) => Ok(unavailable(ServerRole::Primary, reading, e)),
+            (Err(e), Ok(reading)) => Ok(unavailable(ServerRole::Standby, reading, e)),
+            (Err(e), Err(_)) => Err(e),
+        }
+    }
+}
+
+/// Answer of `server` while the other server failed with `error`
+fn unavailable(server: ServerRole, reading: TagReading, error: CtApiError) -> RedundantReading {
+    RedundantReading {
+        disagreement: Some(Disagreement::Unavailable {
+            error: error.to_string(),
+        }),
+        ..RedundantReading::new(server, reading)
+    }
+}
+
+/// Whether two values agree, numerically within `tolerance` if both are numbers
+fn values_agree(a: &str, b: &str, tolerance: f64) -> bool {
+    let (a, b) = (a.trim(), b.trim());
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= tolerance,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_agree() {
+        assert!(values_agree("1.20", "1.2", 0.0));
+        assert!(values_agree("10.0", "10.05", 0.1));
+        assert!(!values_agree("10.0", "10.2", 0.1));
+        assert!(values_agree("Running", " Running", 0.1));
+        assert!(!values_agree("Running", "Stopped", 1e9));
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use super::super::*;
+        use crate::error::CtApiError;
+        use crate::mock::{MockServer, in_flight_calls};
+        use std::time::Duration;
+
+        fn pair(primary: MockServer, standby: MockServer) -> RedundantReader {
+            RedundantReader::new(
+                Arc::new(CtClient::open_mock_with(primary).unwrap()),
+                Arc::new(CtClient::open_mock_with(standby).unwrap()),
+            )
+        }
+
+        fn server(level: &str) -> MockServer {
+            MockServer::new().with_tag("Level", level)
+        }
+
+        #[test]
+        fn test_prefer_primary() {
+            let reader = pair(server("1"), server("2"));
+            let answer = reader.read("Level", ReadPolicy::PreferPrimary).unwrap();
+            assert_eq!(
+                (answer.server, answer.reading.value.as_str()),
+                (ServerRole::Primary, "1")
+            );
+
+            let reader = pair(MockServer::new(), server("2"));
+            let answer = reader.read("Level", ReadPolicy::PreferPrimary).unwrap();
+            assert_eq!(
+                (answer.server, answer.reading.value.as_str()),
+                (ServerRole::Standby, "2")
+            );
+            assert!(answer.agrees());
+
+            let err = reader
+                .read("Missing", ReadPolicy::PreferPrimary)
+                .unwrap_err();
+            assert!(matches!(err, CtApiError::System(..)));
+        }
+
+        #[test]
+        fn test_fastest_returns_first_good_answer() {
+            let stalled = server("1").with_stalled_tag("Level");
+            let reader = pair(stalled, server("2"));
+            let answer = reader.read("Level", ReadPolicy::Fastest).unwrap();
+            assert_eq!(
+                (answer.server, answer.reading.value.as_str()),
+                (ServerRole::Standby, "2")
+            );
+            // The primary's read was cancelled, not left running
+            assert_eq!(in_flight_calls(reader.client(ServerRole::Primary)), 0);
+
+            let stalled = server("2").with_stalled_tag("Level");
+            let reader = pair(server("1"), stalled);
+            let answer = reader.read("Level", ReadPolicy::Fastest).unwrap();
+            assert_eq!(answer.server, ServerRole::Primary);
+            assert_eq!(in_flight_calls(reader.client(ServerRole::Standby)), 0);
+        }
+
+        #[test]
+        fn test_fastest_skips_bad_quality_and_failures() {
+            let bad = server("1").with_tag_quality("Level", crate::quality::QUAL_BAD, 0);
+            let reader = pair(bad, server("2"));
+            let answer = reader.read("Level", ReadPolicy::Fastest).unwrap();
+            assert_eq!(answer.server, ServerRole::Standby);
+
+            let reader = pair(MockServer::new(), server("2"));
+            let answer = reader.read("Level", ReadPolicy::Fastest).unwrap();
+            assert_eq!(answer.server, ServerRole::Standby);
+
+            // Without a good answer the primary's reading is preferred
+            let bad = server("1").with_tag_quality("Level", crate::quality::QUAL_BAD, 0);
+            let reader = pair(bad, MockServer::new());
+            let answer = reader.read("Level", ReadPolicy::Fastest).unwrap();
+            assert_eq!(answer.server, ServerRole::Primary);
+            assert!(answer.reading.quality.is_bad());
+
+            let reader = pair(MockServer::new(), MockServer::new());
+            assert!(reader.read("Level", ReadPolicy::Fastest).is_err());
+        }
+
+        #[test]
+        fn test_compare() {
+            let compare = ReadPolicy::Compare { tolerance: 0.5 };
+            let latency = Duration::from_millis(20);
+            let reader = pair(server("10.0"), server("10.4").with_read_latency(latency));
+            let answer = reader.read("Level", compare).unwrap();
+            assert_eq!(answer.server, ServerRole::Primary);
+            assert!(answer.agrees());
+
+            let reader = pair(server("10.0").with_read_latency(latency), server("11"));
+            let answer = reader.read("Level", compare).unwrap();
+            assert_eq!(answer.reading.value, "10.0");
+            let Some(Disagreement::Value { other }) = answer.disagreement else {
+                panic!("expected a value disagreement: {answer:?}");
+            };
+            assert_eq!(other.value, "11");
+
+            let bad = server("10.0").with_tag_quality("Level", crate::quality::QUAL_BAD, 0);
+            let answer = pair(server("10.0"), bad).read("Level", compare).unwrap();
+            assert!(matches!(
+                answer.disagreement,
+                Some(Disagreement::Quality { .. })
+            ));
+
+            let answer = pair(MockServer::new(), server("3"))
+                .read("Level", compare)
+                .unwrap();
+            assert_eq!(answer.server, ServerRole::Standby);
+            assert!(matches!(
+                answer.disagreement,
+                Some(Disagreement::Unavailable { .. })
+            ));
+        }
+    }
+}
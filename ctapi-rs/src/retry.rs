@@ -0,0 +1,400 @@
+//! Retry policy for establishing connections
+//!
+//! [`RetryPolicy`] describes how [`CtClient::open_with_retry`](crate::CtClient::open_with_retry)
+//! retries a failed `ctOpen`: exponential backoff with jitter by default, or
+//! any [`Backoff`] strategy, bounded by a total deadline. Only failures that
+//! indicate the server is not reachable yet are retried; authentication
+//! failures are returned immediately.
+
+use crate::backoff::{Backoff, Exponential};
+use crate::clock::Clock;
+use crate::error::{CtApiError, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Win32 error codes returned by `ctOpen` when the credentials are rejected
+const FATAL_OPEN_ERRORS: &[i32] = &[
+    5,    // ERROR_ACCESS_DENIED
+    86,   // ERROR_INVALID_PASSWORD
+    1326, // ERROR_LOGON_FAILURE
+    1327, // ERROR_ACCOUNT_RESTRICTION
+    1331, // ERROR_ACCOUNT_DISABLED
+];
+
+/// Information about a failed open attempt, passed to [`RetryPolicy::on_attempt`]
+#[derive(Debug)]
+pub struct OpenAttempt<'a> {
+    /// 1-based attempt number
+    pub attempt: u32,
+    /// Error returned by this attempt
+    pub error: &'a CtApiError,
+    /// Delay before the next attempt, `None` if no further attempt will be made
+    pub next_delay: Option<Duration>,
+}
+
+/// Callback invoked after every failed open attempt
+pub type OpenAttemptCallback = Arc<dyn Fn(&OpenAttempt<'_>) + Send + Sync>;
+
+/// Creates a fresh backoff strategy for each series of attempts
+pub(crate) type BackoffFactory = Arc<dyn Fn() -> Box<dyn Backoff> + Send + Sync>;
+
+/// Backoff policy for [`CtClient::open_with_retry`](crate::CtClient::open_with_retry)
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::default()
+///     .initial_delay(Duration::from_secs(1))
+///     .max_delay(Duration::from_secs(20))
+///     .deadline(Duration::from_secs(600))
+///     .on_attempt(|a| eprintln!("open attempt {} failed: {}", a.attempt, a.error));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: f64,
+    deadline: Duration,
+    backoff: Option<BackoffFactory>,
+    on_attempt: Option<OpenAttemptCallback>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            deadline: Duration::from_secs(300),
+            backoff: None,
+            on_attempt: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("initial_delay", &self.initial_delay)
+            .field("max_delay", &self.max_delay)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("deadline", &self.deadline)
+            .field("backoff", &self.backoff.is_some())
+            .field("on_attempt", &self.on_attempt.is_some())
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the second attempt
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Upper bound for a single delay
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Factor applied to the delay after each attempt (at least `1.0`)
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Fraction of each delay that is randomised, between `0.0` and `1.0`
+    ///
+    /// With jitter `j` a delay `d` becomes a random value in `[d * (1 - j), d]`,
+    /// so that many clients restarted together do not retry in lockstep.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Total time after which no further attempt is made
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Strategy choosing the delays, instead of the exponential settings above
+    ///
+    /// Each run starts from a copy of `backoff`. The run ends when the
+    /// strategy returns `None`, an error is not retryable or the deadline
+    /// passes, whichever comes first.
+    pub fn backoff<B>(mut self, backoff: B) -> Self
+    where
+        B: Backoff + Clone + Sync + 'static,
+    {
+        self.backoff = Some(Arc::new(move || Box::new(backoff.clone())));
+        self
+    }
+
+    /// Callback invoked after every failed attempt
+    pub fn on_attempt<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&OpenAttempt<'_>) + Send + Sync + 'static,
+    {
+        self.on_attempt = Some(Arc::new(f));
+        self
+    }
+
+    /// Exponential strategy described by the delay settings
+    fn exponential(&self) -> Exponential {
+        Exponential::new(self.initial_delay, self.max_delay)
+            .multiplier(self.multiplier)
+            .jitter(self.jitter)
+    }
+
+    /// New instance of the strategy choosing the delays
+    pub(crate) fn strategy(&self) -> Box<dyn Backoff> {
+        match &self.backoff {
+            Some(factory) => factory(),
+            None => Box::new(self.exponential()),
+        }
+    }
+
+    /// Backoff delay before attempt `attempt + 1`, without jitter
+    fn base_delay(&self, attempt: u32) -> Duration {
+        self.exponential().base_delay(attempt)
+    }
+
+    /// Backoff delay with jitter applied, `random` in `[0, 1)`
+    pub(crate) fn delay(&self, attempt: u32, random: f64) -> Duration {
+        self.exponential().delay(attempt, random)
+    }
+
+    /// Run `open` until it succeeds, fails fatally or the deadline passes
+    pub(crate) fn run<T, F>(&self, clock: &dyn Clock, mut open: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let deadline = clock.now() + self.deadline;
+        let mut backoff = self.strategy();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let error = match open() {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            let remaining = deadline.saturating_duration_since(clock.now());
+            let next_delay = if is_retryable_open_error(&error) && !remaining.is_zero() {
+                backoff
+                    .next_delay(attempt, &error)
+                    .map(|delay| delay.min(remaining))
+            } else {
+                None
+            };
+
+            if let Some(callback) = &self.on_attempt {
+                callback(&OpenAttempt {
+                    attempt,
+                    error: &error,
+                    next_delay,
+                });
+            }
+
+            match next_delay {
+                Some(delay) => clock.sleep(delay),
+                None => return Err(error),
+            }
+        }
+    }
+}
+
+/// Whether an open failure is worth retrying
+///
+/// Credential and permission failures are fatal; anything else (server not
+/// running, RPC unavailable, network errors) is assumed to be transient.
+pub fn is_retryable_open_error(error: &CtApiError) -> bool {
+    match error.root() {
+        CtApiError::System(e, ..) => e
+            .raw_os_error()
+            .is_none_or(|code| !FATAL_OPEN_ERRORS.contains(&code)),
+        CtApiError::ConnectionFailed { .. } | CtApiError::Timeout | CtApiError::Citect { .. } => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether `ctOpen` failed because the server rejected the credentials
+pub(crate) fn is_credentials_error(error: &CtApiError) -> bool {
+    match error.root() {
+        CtApiError::System(e, ..) => e
+            .raw_os_error()
+            .is_some_and(|code| FATAL_OPEN_ERRORS.contains(&code)),
+        _ => false,
+    }
+}
+
+/// Random value in `[0, 1)` from the standard library's randomly seeded hasher
+pub(crate) fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backoff::{Fibonacci, Fixed};
+    use crate::clock::MockClock;
+    use std::io;
+    use std::sync::Mutex;
+
+    fn server_down() -> CtApiError {
+        // RPC_S_SERVER_UNAVAILABLE
+        io::Error::from_raw_os_error(1722).into()
+    }
+
+    fn bad_password() -> CtApiError {
+        io::Error::from_raw_os_error(1326).into()
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(8))
+            .multiplier(2.0)
+            .jitter(0.0)
+            .deadline(Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(is_retryable_open_error(&server_down()));
+        assert!(!is_retryable_open_error(&bad_password()));
+        assert!(!is_retryable_open_error(&CtApiError::TagNotFound {
+            tag: "x".into()
+        }));
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let p = policy();
+        let delays: Vec<_> = (1..=6).map(|n| p.base_delay(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 8, 8]);
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let p = policy().jitter(0.5);
+        assert_eq!(p.delay(3, 0.0), Duration::from_secs(4));
+        assert_eq!(p.delay(3, 1.0), Duration::from_secs(2));
+        for _ in 0..100 {
+            let r = random_unit();
+            assert!((0.0..1.0).contains(&r));
+        }
+    }
+
+    #[test]
+    fn test_succeeds_after_transient_failures() {
+        let clock = MockClock::new();
+        let attempts = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&attempts);
+        let p = policy().on_attempt(move |a| seen.lock().unwrap().push((a.attempt, a.next_delay)));
+
+        let mut failures = 3;
+        let result = p.run(&*clock, || {
+            if failures > 0 {
+                failures -= 1;
+                Err(server_down())
+            } else {
+                Ok("connected")
+            }
+        });
+
+        assert_eq!(result.unwrap(), "connected");
+        assert_eq!(clock.elapsed(), Duration::from_secs(1 + 2 + 4));
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            *attempts,
+            [
+                (1, Some(Duration::from_secs(1))),
+                (2, Some(Duration::from_secs(2))),
+                (3, Some(Duration::from_secs(4))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fatal_error_fails_immediately() {
+        let clock = MockClock::new();
+        let mut calls = 0;
+        let result: Result<()> = policy().run(&*clock, || {
+            calls += 1;
+            Err(bad_password())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_deadline_bounds_total_time() {
+        let clock = MockClock::new();
+        let mut calls = 0;
+        let result: Result<()> = policy().deadline(Duration::from_secs(20)).run(&*clock, || {
+            calls += 1;
+            Err(server_down())
+        });
+        assert!(matches!(result, Err(CtApiError::System(..))));
+        // 1 + 2 + 4 + 8 = 15 s, then the final wait is clipped to the remaining 5 s
+        assert_eq!(clock.elapsed(), Duration::from_secs(20));
+        assert_eq!(calls, 6);
+    }
+
+    #[test]
+    fn test_custom_backoff() {
+        let clock = MockClock::new();
+        let unit = Duration::from_secs(1);
+        let p = policy().backoff(Fibonacci::new(unit, Duration::from_secs(10)));
+        let mut failures = 5;
+        let result = p.run(&*clock, || {
+            if failures > 0 {
+                failures -= 1;
+                Err(server_down())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(clock.elapsed(), Duration::from_secs(1 + 1 + 2 + 3 + 5));
+    }
+
+    #[test]
+    fn test_backoff_none_ends_run() {
+        let clock = MockClock::new();
+        let attempts = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&attempts);
+        let p = policy()
+            .backoff(Fixed::new(Duration::from_secs(2)).max_attempts(3))
+            .on_attempt(move |a| seen.lock().unwrap().push(a.next_delay));
+
+        let mut calls = 0;
+        for _ in 0..2 {
+            let result: Result<()> = p.run(&*clock, || {
+                calls += 1;
+                Err(server_down())
+            });
+            assert!(matches!(result, Err(CtApiError::System(..))));
+        }
+
+        // Each run starts from a fresh strategy
+        assert_eq!(calls, 6);
+        assert_eq!(clock.elapsed(), Duration::from_secs(8));
+        let two = Some(Duration::from_secs(2));
+        assert_eq!(*attempts.lock().unwrap(), [two, two, None, two, two, None]);
+    }
+}
@@ -1,6 +1,7 @@
 //! Engineering units and raw value conversion related implementation
-use crate::error::Result;
-use ctapi_sys::*;
+use crate::constants::{CT_SCALE_CLAMP_LIMIT, CT_SCALE_NOISE_FACTOR, CT_SCALE_RANGE_CHECK};
+use crate::error::{CtApiError, Result};
+use crate::ffi::*;
 use std::io::Error;
 
 /// Convert engineering scale variable to raw I/O device scale
@@ -61,6 +62,155 @@ pub fn ct_raw_to_eng(value: f64, scale: &CtScale, mode: u32) -> Result<f64> {
     Ok(result)
 }
 
+/// Outcome of a slice conversion
+///
+/// Problem samples are counted rather than failing the whole batch. Samples
+/// counted in [`out_of_range`](Self::out_of_range) or
+/// [`non_finite`](Self::non_finite) are left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConversionReport {
+    /// Samples converted, including clamped ones
+    pub converted: usize,
+    /// Samples clamped to the target scale by [`CT_SCALE_CLAMP_LIMIT`]
+    pub clamped: usize,
+    /// Samples rejected by [`CT_SCALE_RANGE_CHECK`]
+    pub out_of_range: usize,
+    /// NaN or infinite samples
+    pub non_finite: usize,
+}
+
+impl ConversionReport {
+    /// Whether every sample was converted without clamping
+    pub fn is_clean(&self) -> bool {
+        self.clamped == 0 && self.out_of_range == 0 && self.non_finite == 0
+    }
+}
+
+/// Convert raw I/O device values to engineering values in place
+///
+/// Equivalent to calling [`ct_raw_to_eng`] on each element, but computed in
+/// Rust without crossing into CtAPI, except under [`CT_SCALE_NOISE_FACTOR`]
+/// whose tolerance CtAPI does not document. `mode` flags apply per sample:
+///
+/// * no flag - values outside the raw scale are extrapolated
+/// * [`CT_SCALE_RANGE_CHECK`] - values outside the raw scale are left
+///   unchanged and counted as out of range
+/// * [`CT_SCALE_CLAMP_LIMIT`] - results are clamped to the engineering scale
+///   and counted as clamped; takes precedence over the range check
+///
+/// # Errors
+/// * [`CtApiError::InvalidParameter`] - The raw scale has zero width
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::scaling::raw_to_eng_slice;
+/// use ctapi_rs::{CT_SCALE_CLAMP_LIMIT, CtScale};
+/// use ctapi_sys::CtHScale;
+///
+/// let scale = CtScale::new(CtHScale::new(0.0, 32000.0), CtHScale::new(0.0, 100.0));
+/// let mut samples = [0.0, 16000.0, 40000.0, f64::NAN];
+/// let report = raw_to_eng_slice(&mut samples, &scale, CT_SCALE_CLAMP_LIMIT)?;
+/// assert_eq!(samples[..3], [0.0, 50.0, 100.0]);
+/// assert_eq!((report.converted, report.clamped, report.non_finite), (3, 1, 1));
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn raw_to_eng_slice(
+    values: &mut [f64],
+    scale: &CtScale,
+    mode: u32,
+) -> Result<ConversionReport> {
+    convert_slice(values, scale.raw(), scale.eng(), mode, |value| {
+        ct_raw_to_eng(value, scale, mode)
+    })
+}
+
+/// Convert engineering values to raw I/O device values in place
+///
+/// The inverse of [`raw_to_eng_slice`], with the same handling of `mode`
+/// relative to the engineering scale (input) and raw scale (output).
+///
+/// # Errors
+/// * [`CtApiError::InvalidParameter`] - The engineering scale has zero width
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::scaling::eng_to_raw_slice;
+/// use ctapi_rs::{CT_SCALE_RANGE_CHECK, CtScale};
+/// use ctapi_sys::CtHScale;
+///
+/// let scale = CtScale::new(CtHScale::new(0.0, 32000.0), CtHScale::new(0.0, 100.0));
+/// let mut samples = [50.0, 120.0];
+/// let report = eng_to_raw_slice(&mut samples, &scale, CT_SCALE_RANGE_CHECK)?;
+/// assert_eq!(samples, [16000.0, 120.0]);
+/// assert_eq!(report.out_of_range, 1);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn eng_to_raw_slice(
+    values: &mut [f64],
+    scale: &CtScale,
+    mode: u32,
+) -> Result<ConversionReport> {
+    convert_slice(values, scale.eng(), scale.raw(), mode, |value| {
+        ct_eng_to_raw(value, scale, mode)
+    })
+}
+
+/// Map `values` from the `from` scale to the `to` scale
+///
+/// `ffi` converts a single sample through CtAPI when `mode` asks for noise
+/// factor handling; CtAPI only fails such a conversion for a range error.
+fn convert_slice(
+    values: &mut [f64],
+    from: CtHScale,
+    to: CtHScale,
+    mode: u32,
+    ffi: impl Fn(f64) -> Result<f64>,
+) -> Result<ConversionReport> {
+    let span = from.full() - from.zero();
+    if span == 0.0 || !span.is_finite() {
+        return Err(CtApiError::InvalidParameter {
+            param: "scale".to_string(),
+            value: format!("{} to {}", from.zero(), from.full()),
+        });
+    }
+    let factor = (to.full() - to.zero()) / span;
+    let (low, high) = (from.zero().min(from.full()), from.zero().max(from.full()));
+    let (to_low, to_high) = (to.zero().min(to.full()), to.zero().max(to.full()));
+    let noise_factor = mode & CT_SCALE_NOISE_FACTOR != 0;
+    let clamp = mode & CT_SCALE_CLAMP_LIMIT != 0;
+    let range_check = mode & CT_SCALE_RANGE_CHECK != 0;
+
+    let mut report = ConversionReport::default();
+    for value in values.iter_mut() {
+        if !value.is_finite() {
+            report.non_finite += 1;
+            continue;
+        }
+        if noise_factor {
+            match ffi(*value) {
+                Ok(converted) => {
+                    *value = converted;
+                    report.converted += 1;
+                }
+                Err(_) => report.out_of_range += 1,
+            }
+            continue;
+        }
+        let converted = to.zero() + (*value - from.zero()) * factor;
+        if clamp && !(to_low..=to_high).contains(&converted) {
+            *value = converted.clamp(to_low, to_high);
+            report.clamped += 1;
+        } else if range_check && !(low..=high).contains(value) {
+            report.out_of_range += 1;
+            continue;
+        } else {
+            *value = converted;
+        }
+        report.converted += 1;
+    }
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,4 +244,80 @@ mod tests {
         let eng_value = result.unwrap();
         assert!((eng_value - 50.0).abs() < 0.1); // Allow small floating point error
     }
+
+    fn mixed_batch() -> [f64; 6] {
+        [-8000.0, 0.0, 16000.0, 32000.0, 40000.0, f64::NAN]
+    }
+
+    #[test]
+    fn test_raw_to_eng_slice_without_flags() {
+        let scale = CtScale::new(CtHScale::new(0.0, 32000.0), CtHScale::new(0.0, 100.0));
+        let mut values = mixed_batch();
+        let report = raw_to_eng_slice(&mut values, &scale, 0).unwrap();
+        assert_eq!(values[..5], [-25.0, 0.0, 50.0, 100.0, 125.0]);
+        assert!(values[5].is_nan());
+        assert_eq!(
+            report,
+            ConversionReport {
+                converted: 5,
+                non_finite: 1,
+                ..ConversionReport::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_raw_to_eng_slice_range_check() {
+        let scale = CtScale::new(CtHScale::new(0.0, 32000.0), CtHScale::new(0.0, 100.0));
+        let mut values = mixed_batch();
+        let report = raw_to_eng_slice(&mut values, &scale, CT_SCALE_RANGE_CHECK).unwrap();
+        assert_eq!(values[..5], [-8000.0, 0.0, 50.0, 100.0, 40000.0]);
+        assert_eq!(
+            (report.converted, report.out_of_range, report.non_finite),
+            (3, 2, 1)
+        );
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_raw_to_eng_slice_clamp_limit() {
+        let scale = CtScale::new(CtHScale::new(0.0, 32000.0), CtHScale::new(0.0, 100.0));
+        for mode in [
+            CT_SCALE_CLAMP_LIMIT,
+            CT_SCALE_CLAMP_LIMIT | CT_SCALE_RANGE_CHECK,
+        ] {
+            let mut values = mixed_batch();
+            let report = raw_to_eng_slice(&mut values, &scale, mode).unwrap();
+            assert_eq!(values[..5], [0.0, 0.0, 50.0, 100.0, 100.0]);
+            assert_eq!(
+                (report.converted, report.clamped, report.out_of_range),
+                (5, 2, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_eng_to_raw_slice_inverted_scale() {
+        // Reverse-acting instrument: raw 4000 at 100 %, raw 20000 at 0 %
+        let scale = CtScale::new(CtHScale::new(20000.0, 4000.0), CtHScale::new(0.0, 100.0));
+        let mut values = [0.0, 25.0, 100.0, 110.0, f64::INFINITY];
+        let mode = CT_SCALE_RANGE_CHECK;
+        let report = eng_to_raw_slice(&mut values, &scale, mode).unwrap();
+        assert_eq!(values[..4], [20000.0, 16000.0, 4000.0, 110.0]);
+        assert_eq!(
+            (report.converted, report.out_of_range, report.non_finite),
+            (3, 1, 1)
+        );
+
+        let mut values = [110.0];
+        let report = eng_to_raw_slice(&mut values, &scale, CT_SCALE_CLAMP_LIMIT).unwrap();
+        assert_eq!((values[0], report.clamped), (4000.0, 1));
+    }
+
+    #[test]
+    fn test_zero_width_scale_rejected() {
+        let scale = CtScale::new(CtHScale::new(5.0, 5.0), CtHScale::new(0.0, 100.0));
+        let result = raw_to_eng_slice(&mut [1.0], &scale, 0);
+        assert!(matches!(result, Err(CtApiError::InvalidParameter { .. })));
+    }
 }
@@ -0,0 +1,109 @@
+//! Secret values that must not leak into logs
+//!
+//! [`SecretString`] wraps passwords and similar credentials. Its `Debug` and
+//! `Display` implementations print `***`, so structs holding one can derive
+//! `Debug` safely. With the `zeroize` feature the contents are wiped from
+//! memory when the value is dropped.
+
+use std::fmt;
+
+/// Placeholder printed instead of a secret value
+pub const REDACTED: &str = "***";
+
+/// A string whose contents are never printed
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::SecretString;
+///
+/// let password = SecretString::from("Citect");
+/// assert_eq!(format!("{password:?}"), "***");
+/// assert_eq!(password.expose(), "Citect");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a secret value
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// Access the secret value
+    ///
+    /// Keep the returned reference short-lived and never log it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the secret is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_never_formatted() {
+        let secret = SecretString::new("hunter2");
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(format!("{secret}"), REDACTED);
+        assert_eq!(format!("{secret:#?}"), REDACTED);
+
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Login {
+            user: String,
+            password: SecretString,
+        }
+        let login = Login {
+            user: "Engineer".to_string(),
+            password: secret.clone(),
+        };
+        let debug = format!("{login:?}");
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("password: ***"));
+    }
+
+    #[test]
+    fn test_secret_expose() {
+        let secret = SecretString::from("Citect".to_string());
+        assert_eq!(secret.expose(), "Citect");
+        assert!(!secret.is_empty());
+        assert!(SecretString::default().is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Typed details of the connected server
+//!
+//! [`CtClient::server_info`](crate::CtClient::server_info) gathers what
+//! dashboards usually ask the server for with separate Cicode calls - the
+//! Citect SCADA version, the project name, the clusters and the logged-in
+//! user - into one [`ServerInfo`].
+//!
+//! Each item is read on its own. An item the server cannot supply, for
+//! example because its Citect SCADA version lacks the Cicode function, is
+//! `None` rather than an error; only a lost connection fails the call.
+//!
+//! With the `serde` feature a [`ServerInfo`] serializes with the version
+//! split into its parts.
+
+use crate::CtClient;
+use crate::cicode::{CicodeCall, CicodeWindow};
+use crate::error::Result;
+use crate::reconnect::is_connection_down;
+use crate::version::{self, CitectVersion};
+
+/// Cicode command returning the project name
+const PROJECT_COMMAND: &str = "ProjectInfo(0)";
+
+/// Cicode command returning the name of the logged-in user
+const USER_COMMAND: &str = "Name()";
+
+/// Cicode command returning the first cluster name
+const FIRST_CLUSTER_COMMAND: &str = "ClusterFirst()";
+
+/// Clusters listed at most, in case a server keeps repeating a name
+const MAX_CLUSTERS: usize = 256;
+
+/// Version, project, clusters and user of the connected server
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServerInfo {
+    /// Result of the Cicode `Version()` call
+    pub version_text: Option<String>,
+    /// Parsed [`version_text`](Self::version_text)
+    pub version: Option<CitectVersion>,
+    /// Project name, from `ProjectInfo(0)`
+    pub project: Option<String>,
+    /// Cluster names in the order `ClusterFirst()` and `ClusterNext()`
+    /// report them, `None` for servers without clusters
+    pub clusters: Option<Vec<String>>,
+    /// Name of the user logged in to the server, from `Name()`, `None`
+    /// if nobody is
+    pub user: Option<String>,
+}
+
+impl ServerInfo {
+    /// Ask the server
+    pub(crate) fn read(client: &CtClient) -> Result<Self> {
+        let version_text = optional(client, version::SERVER_VERSION_COMMAND)?;
+        Ok(Self {
+            version: version_text.as_deref().and_then(CitectVersion::parse),
+            version_text,
+            project: optional(client, PROJECT_COMMAND)?,
+            clusters: clusters(client)?,
+            user: optional(client, USER_COMMAND)?,
+        })
+    }
+}
+
+/// Trimmed result of `cmd`, `None` if it is empty or the call failed for
+/// another reason than a lost connection
+fn optional(client: &CtClient, cmd: &str) -> Result<Option<String>> {
+    match client.cicode(cmd, CicodeWindow::NONE, 0) {
+        Ok(result) => {
+            let result = result.trim();
+            Ok((!result.is_empty()).then(|| result.to_string()))
+        }
+        Err(error) if is_connection_down(&error) => Err(error),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Walk the clusters with `ClusterFirst()` and `ClusterNext()`
+fn clusters(client: &CtClient) -> Result<Option<Vec<String>>> {
+    let Some(first) = optional(client, FIRST_CLUSTER_COMMAND)? else {
+        return Ok(None);
+    };
+    let mut clusters = vec![first];
+    while clusters.len() < MAX_CLUSTERS {
+        let last = clusters.last().expect("at least one cluster");
+        let cmd = CicodeCall::new("ClusterNext").arg_str(last).command()?;
+        match optional(client, &cmd)? {
+            Some(next) if !clusters.contains(&next) => clusters.push(next),
+            _ => break,
+        }
+    }
+    Ok(Some(clusters))
+}
+
+/// The fields, with the version as its parts
+#[cfg(feature = "serde")]
+impl serde::Serialize for ServerInfo {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut info = serializer.serialize_struct("ServerInfo", 5)?;
+        info.serialize_field("version_text", &self.version_text)?;
+        info.serialize_field("version", &self.version)?;
+        info.serialize_field("project", &self.project)?;
+        info.serialize_field("clusters", &self.clusters)?;
+        info.serialize_field("user", &self.user)?;
+        info.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_server_info_from_mock() {
+        use crate::mock::MockServer;
+
+        let client = CtClient::open_mock().unwrap();
+        let info = client.server_info().unwrap();
+        assert_eq!(info.version_text.as_deref(), Some("8.20.0.0"));
+        let version = info.version.unwrap();
+        assert_eq!((version.major, version.minor), (8, 20));
+        assert_eq!(info.project.as_deref(), Some("Example"));
+        assert_eq!(info.clusters, Some(vec!["Cluster1".to_string()]));
+        assert_eq!(info.user.as_deref(), Some("Manager"));
+
+        // An older server without these functions still answers
+        let client = CtClient::open_mock_with(MockServer::new()).unwrap();
+        assert_eq!(client.server_info().unwrap(), ServerInfo::default());
+
+        let client = CtClient::open_mock_with(MockServer::seeded().unreachable()).unwrap();
+        assert!(client.server_info().is_err());
+    }
+
+    #[cfg(all(feature = "serde", feature = "json", feature = "mock"))]
+    #[test]
+    fn test_serialized_server_info() {
+        let info = CtClient::open_mock().unwrap().server_info().unwrap();
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"version_text":"8.20.0.0","version":{"major":8,"minor":20,"patch":0,"build":0},"project":"Example","clusters":["Cluster1"],"user":"Manager"}"#
+        );
+    }
+}
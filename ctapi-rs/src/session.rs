@@ -0,0 +1,477 @@
+//! Actor running the CtAPI calls of a client for async callers
+//!
+//! [`TokioCtSession`] owns a [`CtClient`] and runs its calls one at a time,
+//! in the order they were made, on a dedicated thread. Async callers queue
+//! a command and await its reply, so slow SCADA calls occupy neither the
+//! runtime nor its blocking pool.
+//!
+//! Requires the `tokio-support` feature.
+//!
+//! # Shutdown
+//!
+//! [`TokioCtSession::shutdown`] stops accepting commands and drains the
+//! queue within a deadline: queued commands keep running until the deadline
+//! passes, the remaining ones are answered with
+//! [`CtApiError::SessionClosed`], and a Cicode call or write still in flight
+//! at the deadline is cancelled with `ctCancelIO`. Only the session's own
+//! operation is cancelled; I/O started on clones of the client is left
+//! alone. Every caller has its reply before the client is closed.
+//!
+//! Dropping the session without a shutdown drains it without waiting:
+//! queued commands are answered with `SessionClosed` right away, in-flight
+//! work is cancelled, and the client is closed by the session thread once
+//! the call in flight has returned.
+
+use crate::error::{CtApiError, Result};
+use crate::ffi::{OVERLAPPED, ctCancelIO};
+use crate::write::TagValue;
+use crate::{AsyncCtClient, AsyncOperation, CicodeWindow, CtClient};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+
+/// A call run by the session thread
+#[derive(Debug)]
+enum Request {
+    TagRead { tag: String },
+    TagWrite { tag: String, value: String },
+    Cicode { cmd: String, mode: u32 },
+}
+
+impl Request {
+    /// Run the call; writes and Cicode calls are overlapped so that
+    /// `ctCancelIO` can abort them through `in_flight`
+    fn run(self, client: &CtClient, in_flight: &InFlight) -> Result<String> {
+        match self {
+            Request::TagRead { tag } => client.tag_read(&tag),
+            Request::TagWrite { tag, value } => {
+                let mut op = AsyncOperation::new();
+                client.tag_write_ex(&tag, value, Some(&mut op))?;
+                in_flight.wait(client, &mut op)
+            }
+            Request::Cicode { cmd, mode } => {
+                let mut op = AsyncOperation::new();
+                client.cicode_async(&cmd, CicodeWindow::NONE, mode, &mut op)?;
+                in_flight.wait(client, &mut op)
+            }
+        }
+    }
+}
+
+/// Overlapped operation the session thread is waiting for
+#[derive(Debug, Default)]
+struct InFlight {
+    slot: Mutex<InFlightSlot>,
+}
+
+#[derive(Debug, Default)]
+struct InFlightSlot {
+    /// Address of the operation's OVERLAPPED structure
+    overlapped: Option<usize>,
+    /// Whether the session cancelled; later operations are cancelled as
+    /// soon as they start
+    cancelled: bool,
+    /// Whether an operation was cancelled
+    aborted: bool,
+}
+
+impl InFlight {
+    fn lock(&self) -> MutexGuard<'_, InFlightSlot> {
+        self.slot.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait for `op`, which can be cancelled with [`cancel`](Self::cancel)
+    /// meanwhile
+    fn wait(&self, client: &CtClient, op: &mut AsyncOperation) -> Result<String> {
+        {
+            let mut slot = self.lock();
+            // SAFETY: only the address is taken; CtAPI identifies the
+            // operation by it.
+            let overlapped = unsafe { op.overlapped_mut() };
+            if slot.cancelled {
+                // SAFETY: the operation was started on the client's handle
+                // and has not been collected.
+                slot.aborted |= unsafe { ctCancelIO(client.handle(), overlapped) };
+            } else {
+                slot.overlapped = Some(overlapped as usize);
+            }
+        }
+        let result = op.get_result(client);
+        // Waits for a cancel in progress before the operation is released
+        self.lock().overlapped = None;
+        result
+    }
+
+    /// Cancel the operation waited for, and any started after it
+    fn cancel(&self, client: &CtClient) {
+        let mut slot = self.lock();
+        slot.cancelled = true;
+        if let Some(overlapped) = slot.overlapped {
+            // SAFETY: the operation was started on the client's handle, and
+            // `wait` clears the slot under this lock before it is released.
+            slot.aborted |= unsafe { ctCancelIO(client.handle(), overlapped as *mut OVERLAPPED) };
+        }
+    }
+}
+
+/// A queued call and the channel its caller awaits the reply on
+#[derive(Debug)]
+struct Command {
+    request: Request,
+    reply: oneshot::Sender<Result<String>>,
+}
+
+/// Drain progress shared by a session and its thread
+#[derive(Debug, Default)]
+struct Drain {
+    /// Commands dequeued after this instant are answered with `SessionClosed`
+    deadline: Mutex<Option<Instant>>,
+    completed: AtomicUsize,
+    closed: AtomicUsize,
+}
+
+impl Drain {
+    fn lock(&self) -> MutexGuard<'_, Option<Instant>> {
+        self.deadline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Move the deadline to `deadline` unless it is already earlier
+    fn close_at(&self, deadline: Instant) {
+        let mut current = self.lock();
+        *current = Some(current.map_or(deadline, |current| current.min(deadline)));
+    }
+
+    fn expired(&self) -> bool {
+        self.lock()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Outcome of [`TokioCtSession::shutdown`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrainReport {
+    /// Commands run over the life of the session, including failed ones
+    pub completed: usize,
+    /// Queued commands answered with [`CtApiError::SessionClosed`]
+    pub closed: usize,
+    /// Whether work still in flight at the deadline was cancelled
+    pub cancelled: bool,
+}
+
+/// A client driven by a dedicated thread, see the [module](self) documentation
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::{CtClient, TokioCtSession};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let session = TokioCtSession::new(CtClient::open_mock()?)?;
+///     session.tag_write("Setpoint", 21.5).await?;
+///     println!("Temperature: {}", session.tag_read("Temperature").await?);
+///
+///     let report = session.shutdown(Duration::from_secs(5)).await;
+///     assert_eq!(report.closed, 0);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TokioCtSession {
+    /// Queue of the session thread, `None` once commands are refused
+    commands: Mutex<Option<mpsc::UnboundedSender<Command>>>,
+    /// Signalled by the session thread after it has closed the client
+    finished: Mutex<Option<oneshot::Receiver<()>>>,
+    /// The client, owned by the session thread, for cancelling its I/O
+    client: Weak<CtClient>,
+    /// Operation of the session thread, for cancelling it
+    in_flight: Arc<InFlight>,
+    drain: Arc<Drain>,
+}
+
+impl TokioCtSession {
+    /// Start a session thread owning `client`
+    ///
+    /// # Errors
+    /// * [`CtApiError::System`] - The thread could not be started
+    pub fn new(client: CtClient) -> Result<Self> {
+        let client = Arc::new(client);
+        let weak = Arc::downgrade(&client);
+        let (commands, queue) = mpsc::unbounded_channel();
+        let (done, finished) = oneshot::channel();
+        let drain = Arc::new(Drain::default());
+        let in_flight = Arc::new(InFlight::default());
+        let (shared, waited) = (Arc::clone(&drain), Arc::clone(&in_flight));
+        std::thread::Builder::new()
+            .name("ctapi-session".to_string())
+            .spawn(move || {
+                run(client, queue, &shared, &waited);
+                let _ = done.send(());
+            })?;
+        Ok(Self {
+            commands: Mutex::new(Some(commands)),
+            finished: Mutex::new(Some(finished)),
+            client: weak,
+            in_flight,
+            drain,
+        })
+    }
+
+    /// Read a tag, see [`CtClient::tag_read`]
+    ///
+    /// # Errors
+    /// * [`CtApiError::SessionClosed`] - The session was shut down before
+    ///   the read ran
+    pub async fn tag_read(&self, tag: &str) -> Result<String> {
+        let tag = tag.to_string();
+        self.call(Request::TagRead { tag }).await
+    }
+
+    /// Write a tag, see [`CtClient::tag_write_ex`]
+    ///
+    /// # Errors
+    /// * [`CtApiError::SessionClosed`] - The session was shut down before
+    ///   the write ran
+    pub async fn tag_write(&self, tag: &str, value: impl TagValue) -> Result<()> {
+        let (tag, value) = (tag.to_string(), value.to_tag_value().into_owned());
+        self.call(Request::TagWrite { tag, value })
+            .await
+            .map(|_| ())
+    }
+
+    /// Run a Cicode function, see [`CtClient::cicode`]
+    ///
+    /// # Errors
+    /// * [`CtApiError::SessionClosed`] - The session was shut down before
+    ///   the call ran
+    pub async fn cicode(&self, cmd: &str, mode: u32) -> Result<String> {
+        let cmd = cmd.to_string();
+        self.call(Request::Cicode { cmd, mode }).await
+    }
+
+    /// Stop accepting commands and drain the queue within `deadline`
+    ///
+    /// Returns once every queued caller has its reply and the client is
+    /// closed. A tag read in flight at the deadline cannot be cancelled and
+    /// is waited for. Calling `shutdown` again returns the counts so far
+    /// without waiting.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ctapi_rs::{CtApiError, CtClient, TokioCtSession};
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let session = TokioCtSession::new(CtClient::open_mock()?)?;
+    /// session.shutdown(Duration::from_secs(1)).await;
+    /// let refused = session.tag_read("Temperature").await;
+    /// assert!(matches!(refused, Err(CtApiError::SessionClosed)));
+    /// # Ok(()) }
+    /// ```
+    pub async fn shutdown(&self, deadline: Duration) -> DrainReport {
+        self.drain.close_at(Instant::now() + deadline);
+        drop(self.sender().take());
+        let finished = self
+            .finished
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(mut finished) = finished
+            && tokio::time::timeout(deadline, &mut finished).await.is_err()
+        {
+            self.cancel_in_flight();
+            let _ = finished.await;
+        }
+        DrainReport {
+            completed: self.drain.completed.load(Ordering::Acquire),
+            closed: self.drain.closed.load(Ordering::Acquire),
+            cancelled: self.in_flight.lock().aborted,
+        }
+    }
+
+    fn sender(&self) -> MutexGuard<'_, Option<mpsc::UnboundedSender<Command>>> {
+        self.commands.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `request`, returning the channel its reply arrives on
+    fn send(&self, request: Request) -> Result<oneshot::Receiver<Result<String>>> {
+        let (reply, answer) = oneshot::channel();
+        let command = Command { request, reply };
+        match self.sender().as_ref() {
+            Some(commands) if commands.send(command).is_ok() => Ok(answer),
+            _ => Err(CtApiError::SessionClosed),
+        }
+    }
+
+    async fn call(&self, request: Request) -> Result<String> {
+        let answer = self.send(request)?;
+        // The thread only drops a reply unanswered if it panicked
+        answer.await.unwrap_or(Err(CtApiError::SessionClosed))
+    }
+
+    /// Cancel the operation of the session thread, if the client is still open
+    fn cancel_in_flight(&self) {
+        if let Some(client) = self.client.upgrade() {
+            self.in_flight.cancel(&client);
+        }
+    }
+}
+
+impl Drop for TokioCtSession {
+    fn drop(&mut self) {
+        self.drain.close_at(Instant::now());
+        if self.sender().take().is_some() {
+            self.cancel_in_flight();
+        }
+    }
+}
+
+/// Body of the session thread: run commands until the queue is closed and
+/// empty, then close the client
+fn run(
+    client: Arc<CtClient>,
+    mut queue: mpsc::UnboundedReceiver<Command>,
+    drain: &Drain,
+    in_flight: &InFlight,
+) {
+    while let Some(Command { request, reply }) = queue.blocking_recv() {
+        // The caller has stopped waiting
+        if reply.is_closed() {
+            continue;
+        }
+        let result = if drain.expired() {
+            drain.closed.fetch_add(1, Ordering::AcqRel);
+            Err(CtApiError::SessionClosed)
+        } else {
+            let result = request.run(&client, in_flight);
+            drain.completed.fetch_add(1, Ordering::AcqRel);
+            result
+        };
+        let _ = reply.send(result);
+    }
+    drop(client);
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+
+    /// Session whose `Hang()` calls run until they are cancelled
+    fn hanging_session() -> (TokioCtSession, CtClient) {
+        let server = MockServer::seeded().with_hanging_cicode("Hang()");
+        let client = CtClient::open_mock_with(server).unwrap();
+        (TokioCtSession::new(client.clone()).unwrap(), client)
+    }
+
+    fn queue_read(session: &TokioCtSession) -> oneshot::Receiver<Result<String>> {
+        let tag = "Temperature".to_string();
+        session.send(Request::TagRead { tag }).unwrap()
+    }
+
+    fn queue_hang(session: &TokioCtSession) -> oneshot::Receiver<Result<String>> {
+        let cmd = "Hang()".to_string();
+        session.send(Request::Cicode { cmd, mode: 0 }).unwrap()
+    }
+
+    fn is_aborted(result: &Result<String>) -> bool {
+        matches!(result, Err(CtApiError::System(e, ..)) if e.raw_os_error() == Some(995))
+    }
+
+    /// Wait until `client` has `count` overlapped calls in flight
+    async fn in_flight(client: &CtClient, count: usize) {
+        while crate::mock::in_flight_calls(client) < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown_replies_to_every_caller() {
+        let (session, client) = hanging_session();
+        let first = queue_read(&session);
+        let hang = queue_hang(&session);
+        let queued: Vec<_> = (0..5).map(|_| queue_read(&session)).collect();
+        assert_eq!(first.await.unwrap().unwrap(), "25.5");
+        in_flight(&client, 1).await;
+
+        // The Cicode call is still in flight at the deadline and cancelled;
+        // the reads queued behind it are answered without running
+        let report = session.shutdown(Duration::ZERO).await;
+        assert!(is_aborted(&hang.await.unwrap()));
+        for answer in queued {
+            let reply = answer.await.unwrap();
+            assert!(matches!(reply, Err(CtApiError::SessionClosed)));
+        }
+        let expected = DrainReport {
+            completed: 2,
+            closed: 5,
+            cancelled: true,
+        };
+        assert_eq!(report, expected);
+
+        let refused = session.cicode("Time(1)", 0).await;
+        assert!(matches!(refused, Err(CtApiError::SessionClosed)));
+        assert_eq!(session.shutdown(Duration::ZERO).await, report);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_drop_answers_queued_commands() {
+        let (session, client) = hanging_session();
+        let hang = queue_hang(&session);
+        let queued: Vec<_> = (0..4).map(|_| queue_read(&session)).collect();
+        in_flight(&client, 1).await;
+        drop(session);
+
+        assert!(is_aborted(&hang.await.unwrap()));
+        for answer in queued {
+            let reply = answer.await.unwrap();
+            assert!(matches!(reply, Err(CtApiError::SessionClosed)));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancel_leaves_io_of_clones_alone() {
+        let server = MockServer::seeded()
+            .with_hanging_cicode("Hang()")
+            .with_pending_cicode("Slow()", "done");
+        let client = CtClient::open_mock_with(server).unwrap();
+        let mut held = AsyncOperation::new();
+        client
+            .cicode_async("Slow()", CicodeWindow::NONE, 0, &mut held)
+            .unwrap();
+
+        let session = TokioCtSession::new(client.clone()).unwrap();
+        let hang = queue_hang(&session);
+        in_flight(&client, 2).await;
+        let report = session.shutdown(Duration::ZERO).await;
+        assert!(report.cancelled);
+        assert!(is_aborted(&hang.await.unwrap()));
+        assert_eq!(held.get_result(&client).unwrap(), "done");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_session_shares_pending_limit_with_client() {
+        use crate::AsyncCtClient;
+        use crate::pending::PendingLimit;
+
+        let server = MockServer::seeded().with_pending_cicode("Slow()", "done");
+        let client = CtClient::open_mock_with(server).unwrap();
+        client.set_pending_limit(PendingLimit::new(1));
+        let mut held = AsyncOperation::new();
+        client
+            .cicode_async("Slow()", CicodeWindow::NONE, 0, &mut held)
+            .unwrap();
+
+        let session = TokioCtSession::new(client.clone()).unwrap();
+        let err = session.cicode("Time(1)", 0).await.unwrap_err();
+        assert!(matches!(err, CtApiError::TooManyPendingOps { limit: 1 }));
+        let err = session.tag_write("Setpoint", 1).await.unwrap_err();
+        assert!(matches!(err, CtApiError::TooManyPendingOps { .. }));
+
+        assert_eq!(held.get_result(&client).unwrap(), "done");
+        assert!(session.cicode("Time(1)", 0).await.is_ok());
+        assert_eq!(client.pending_ops(), 0);
+        session.shutdown(Duration::from_millis(100)).await;
+    }
+}
@@ -0,0 +1,288 @@
+//! Time-synchronized snapshots across several lists
+//!
+//! Comparing values from different lists, or from different servers, only
+//! makes sense if they were sampled at about the same moment. A
+//! [`SyncSnapshot`] is taken by starting an overlapped read on every list at
+//! once, waiting for all of them, and checking the spread between the
+//! earliest and the latest value timestamp. A spread above the tolerance is
+//! retried; if no attempt gets within it, the closest one is returned and
+//! reports the skew it achieved.
+
+use crate::async_ops::AsyncOperation;
+use crate::constants::{CT_LIST_VALUE, CT_LIST_VALUE_TIMESTAMP};
+use crate::error::Result;
+use crate::list::CtList;
+use std::time::Duration;
+
+/// Number of attempts made by [`snapshot_synchronized`]
+pub const DEFAULT_SNAPSHOT_ATTEMPTS: u32 = 3;
+
+/// CtAPI timestamp ticks, of 100 ns, in a second
+const TICKS_PER_SEC: u64 = 10_000_000;
+
+/// One tag value of a [`SyncSnapshot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotValue {
+    /// Label of the connection the value was read from
+    pub source: String,
+    /// Tag name
+    pub tag: String,
+    /// Value as returned by `ctListData`
+    pub value: String,
+    /// Value timestamp in 100 ns ticks as reported by CtAPI, `None` if the
+    /// server did not supply one
+    pub timestamp: Option<u64>,
+}
+
+/// Values read from several lists at about the same moment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncSnapshot {
+    /// Every tag of every list, in list order
+    pub values: Vec<SnapshotValue>,
+    /// Spread between the earliest and the latest value timestamp
+    pub skew: Duration,
+    /// Whether `skew` is within the requested tolerance
+    pub within_tolerance: bool,
+    /// Number of read rounds taken
+    pub attempts: u32,
+}
+
+impl SyncSnapshot {
+    /// Value of `tag` read from `source`
+    pub fn get(&self, source: &str, tag: &str) -> Option<&SnapshotValue> {
+        self.values
+            .iter()
+            .find(|value| value.source == source && value.tag == tag)
+    }
+}
+
+/// Read all `sources` together until their value timestamps are within
+/// `max_skew`, making up to [`DEFAULT_SNAPSHOT_ATTEMPTS`] attempts
+///
+/// Each source is a connection label and a list; the label is recorded in
+/// [`SnapshotValue::source`]. Values without a timestamp do not count
+/// towards the skew.
+///
+/// # Errors
+/// Any error from starting or completing a list read or from reading a tag.
+/// A skew that stays above `max_skew` is not an error; check
+/// [`SyncSnapshot::within_tolerance`].
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+/// # use ctapi_rs::mock::MockServer;
+/// use ctapi_rs::snapshot::snapshot_synchronized;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// # let open_a = || CtClient::open_mock_with(MockServer::new().with_tag("Flow_Out", "12.5"));
+/// # let open_b = || CtClient::open_mock_with(MockServer::new().with_tag("Flow_In", "12.4"));
+/// let plant_a = Arc::new(open_a()?);
+/// let plant_b = Arc::new(open_b()?);
+/// let list_a = Arc::clone(&plant_a).list_new(0)?;
+/// let list_b = Arc::clone(&plant_b).list_new(0)?;
+/// list_a.add_tag("Flow_Out")?;
+/// list_b.add_tag("Flow_In")?;
+///
+/// let sources = [("ScadaA", &list_a), ("ScadaB", &list_b)];
+/// let snapshot = snapshot_synchronized(&sources, Duration::from_millis(500))?;
+/// if snapshot.within_tolerance {
+///     println!("{:?}", snapshot.get("ScadaA", "Flow_Out"));
+/// }
+/// assert_eq!(snapshot.get("ScadaB", "Flow_In").unwrap().value, "12.4");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn snapshot_synchronized(
+    sources: &[(&str, &CtList)],
+    max_skew: Duration,
+) -> Result<SyncSnapshot> {
+    snapshot_synchronized_with(sources, max_skew, DEFAULT_SNAPSHOT_ATTEMPTS)
+}
+
+/// [`snapshot_synchronized`] with an explicit number of attempts
+///
+/// At least one attempt is always made.
+pub fn snapshot_synchronized_with(
+    sources: &[(&str, &CtList)],
+    max_skew: Duration,
+    attempts: u32,
+) -> Result<SyncSnapshot> {
+    capture(max_skew, attempts, || read_round(sources))
+}
+
+/// Run `round` until its values are within `max_skew`
+///
+/// Returns the first round within tolerance, otherwise the round with the
+/// smallest skew.
+fn capture<F>(max_skew: Duration, attempts: u32, mut round: F) -> Result<SyncSnapshot>
+where
+    F: FnMut() -> Result<Vec<SnapshotValue>>,
+{
+    let attempts = attempts.max(1);
+    let mut best: Option<SyncSnapshot> = None;
+    for attempt in 1..=attempts {
+        let values = round()?;
+        let skew = skew_of(&values);
+        if skew <= max_skew {
+            return Ok(SyncSnapshot {
+                values,
+                skew,
+                within_tolerance: true,
+                attempts: attempt,
+            });
+        }
+        if best.as_ref().is_none_or(|best| skew < best.skew) {
+            best = Some(SyncSnapshot {
+                values,
+                skew,
+                within_tolerance: false,
+                attempts,
+            });
+        }
+    }
+    Ok(best.expect("at least one attempt is made"))
+}
+
+/// Spread between the earliest and latest timestamp
+fn skew_of(values: &[SnapshotValue]) -> Duration {
+    let mut timestamps = values.iter().filter_map(|value| value.timestamp);
+    let Some(first) = timestamps.next() else {
+        return Duration::ZERO;
+    };
+    let (min, max) = timestamps.fold((first, first), |(min, max), t| (min.min(t), max.max(t)));
+    let ticks = max - min;
+    let nanos = (ticks % TICKS_PER_SEC) * 100;
+    Duration::new(ticks / TICKS_PER_SEC, nanos as u32)
+}
+
+/// Read every list with overlapped reads started together
+fn read_round(sources: &[(&str, &CtList)]) -> Result<Vec<SnapshotValue>> {
+    // Not resized after this point: the OVERLAPPED structures must not move
+    // while their reads are pending.
+    let mut ops: Vec<AsyncOperation> = sources.iter().map(|_| AsyncOperation::new()).collect();
+
+    let mut started = 0;
+    let mut start_error = None;
+    for ((_, list), op) in sources.iter().zip(ops.iter_mut()) {
+        if let Err(error) = list.read_async(op) {
+            start_error = Some(error);
+            break;
+        }
+        started += 1;
+    }
+
+    // Wait for every started read, even after a failure, before the
+    // operations are dropped.
+    let mut wait_error = None;
+    for ((_, list), op) in sources.iter().zip(ops.iter_mut()).take(started) {
+        if let Err(error) = op.get_result(list.client()) {
+            wait_error.get_or_insert(error);
+        }
+    }
+    if let Some(error) = start_error.or(wait_error) {
+        return Err(error);
+    }
+
+    let mut values = Vec::new();
+    for (source, list) in sources {
+        for tag in list.tags() {
+            let value = list.read_tag(&tag, CT_LIST_VALUE)?;
+            let timestamp = list.read_tag(&tag, CT_LIST_VALUE_TIMESTAMP)?;
+            values.push(SnapshotValue {
+                source: source.to_string(),
+                tag,
+                value,
+                timestamp: timestamp.trim().parse().ok(),
+            });
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CtApiError;
+
+    /// One round of values from two servers with the given timestamps
+    fn round(a: u64, b: u64) -> Vec<SnapshotValue> {
+        let value = |source: &str, tag: &str, timestamp| SnapshotValue {
+            source: source.to_string(),
+            tag: tag.to_string(),
+            value: "1.5".to_string(),
+            timestamp: Some(timestamp),
+        };
+        vec![value("A", "Flow", a), value("B", "Flow", b)]
+    }
+
+    /// Ticks in `ms` milliseconds
+    fn ms(ms: u64) -> u64 {
+        ms * 10_000
+    }
+
+    #[test]
+    fn test_pass_first_attempt() {
+        let mut rounds = 0;
+        let snapshot = capture(Duration::from_millis(100), 3, || {
+            rounds += 1;
+            Ok(round(ms(1_000), ms(1_050)))
+        })
+        .unwrap();
+        assert_eq!(rounds, 1);
+        assert!(snapshot.within_tolerance);
+        assert_eq!(snapshot.attempts, 1);
+        assert_eq!(snapshot.skew, Duration::from_millis(50));
+        assert_eq!(
+            snapshot.get("B", "Flow").unwrap().timestamp,
+            Some(ms(1_050))
+        );
+        assert!(snapshot.get("C", "Flow").is_none());
+    }
+
+    #[test]
+    fn test_retry_until_within_tolerance() {
+        let mut rounds = vec![round(ms(0), ms(500)), round(ms(0), ms(80))].into_iter();
+        let next = || Ok(rounds.next().unwrap());
+        let snapshot = capture(Duration::from_millis(100), 3, next).unwrap();
+        assert!(snapshot.within_tolerance);
+        assert_eq!(snapshot.attempts, 2);
+        assert_eq!(snapshot.skew, Duration::from_millis(80));
+        assert_eq!(rounds.len(), 0);
+    }
+
+    #[test]
+    fn test_give_up_returns_closest_attempt() {
+        let mut rounds = vec![
+            round(ms(0), ms(400)),
+            round(ms(0), ms(200)),
+            round(ms(300), ms(0)),
+        ]
+        .into_iter();
+        let next = || Ok(rounds.next().unwrap());
+        let snapshot = capture(Duration::from_millis(100), 3, next).unwrap();
+        assert!(!snapshot.within_tolerance);
+        assert_eq!(snapshot.attempts, 3);
+        assert_eq!(snapshot.skew, Duration::from_millis(200));
+
+        // Zero attempts still reads once
+        let snapshot = capture(Duration::ZERO, 0, || Ok(round(ms(0), ms(1)))).unwrap();
+        assert_eq!(snapshot.attempts, 1);
+    }
+
+    #[test]
+    fn test_missing_timestamps_and_errors() {
+        let mut values = round(ms(0), ms(900));
+        values[1].timestamp = None;
+        assert_eq!(skew_of(&values), Duration::ZERO);
+        assert_eq!(skew_of(&[]), Duration::ZERO);
+
+        // Skews past u32::MAX ticks, about 7 minutes, are not cut short
+        let values = round(0, ms(3_600_000) + 1);
+        let hour = Duration::from_secs(3600);
+        assert_eq!(skew_of(&values), hour + Duration::from_nanos(100));
+
+        let failed = capture(Duration::ZERO, 3, || Err(CtApiError::Timeout));
+        assert!(matches!(failed, Err(CtApiError::Timeout)));
+    }
+}
@@ -0,0 +1,460 @@
+//! Large tag sets split into staggered list groups
+//!
+//! Reading one list of thousands of tags makes every I/O device busy at the
+//! same moment on each poll. [`StaggeredList`] instead keeps one [`CtList`]
+//! per group, each with its own poll period, and offsets the groups' read
+//! times evenly across the shortest period so that device traffic is spread
+//! out. Tags are looked up by name regardless of the group they are in.
+//!
+//! Reads are scheduled against absolute deadlines (`start + offset + n *
+//! period`), so a slow read does not shift later reads.
+
+use crate::CtClient;
+use crate::clock::{SharedClock, system_clock};
+use crate::error::{CtApiError, Result};
+use crate::list::CtList;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// A tag to add to a [`StaggeredList`] group
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSpec {
+    /// Tag name
+    pub name: String,
+    /// Read raw values instead of engineering values
+    pub raw: bool,
+    /// Deadband passed to `ctListAddEx`
+    pub deadband: f64,
+}
+
+impl TagSpec {
+    /// Engineering-value tag without deadband
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            raw: false,
+            deadband: 0.0,
+        }
+    }
+
+    /// Read raw values
+    pub fn raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    /// Set the deadband
+    pub fn deadband(mut self, deadband: f64) -> Self {
+        self.deadband = deadband;
+        self
+    }
+}
+
+impl From<&str> for TagSpec {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+/// Offsets that spread `periods.len()` groups evenly across the shortest period
+pub(crate) fn stagger_offsets(periods: &[Duration]) -> Vec<Duration> {
+    let Some(shortest) = periods.iter().min() else {
+        return Vec::new();
+    };
+    let count = periods.len() as u32;
+    (0..count).map(|i| *shortest * i / count).collect()
+}
+
+/// Absolute read deadlines of each group
+#[derive(Debug)]
+pub(crate) struct PollSchedule {
+    periods: Vec<Duration>,
+    next: Vec<Instant>,
+}
+
+impl PollSchedule {
+    pub(crate) fn new(start: Instant, periods: Vec<Duration>) -> Self {
+        let next = stagger_offsets(&periods)
+            .into_iter()
+            .map(|offset| start + offset)
+            .collect();
+        Self { periods, next }
+    }
+
+    /// Earliest pending deadline
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.next.iter().min().copied()
+    }
+
+    /// Groups due at `now`, advancing each past `now`
+    ///
+    /// A group that fell behind by several periods is read once and its
+    /// missed deadlines are skipped, keeping it on its original grid.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<usize> {
+        let mut due = Vec::new();
+        for (group, next) in self.next.iter_mut().enumerate() {
+            if *next > now {
+                continue;
+            }
+            due.push(group);
+            let period = self.periods[group];
+            if period.is_zero() {
+                *next = now;
+                continue;
+            }
+            let missed = (now - *next).as_nanos() / period.as_nanos() + 1;
+            let skip = period.as_nanos().saturating_mul(missed);
+            let skip = Duration::from_nanos(u64::try_from(skip).unwrap_or(u64::MAX));
+            *next = next.checked_add(skip).unwrap_or(now + period);
+        }
+        due
+    }
+}
+
+/// Which group each tag belongs to, and how it was added
+#[derive(Debug, Default)]
+pub(crate) struct Membership {
+    tags: HashMap<String, (usize, TagSpec)>,
+}
+
+impl Membership {
+    pub(crate) fn insert(&mut self, group: usize, spec: TagSpec) -> Result<()> {
+        if self.tags.contains_key(&spec.name) {
+            return Err(CtApiError::InvalidParameter {
+                param: "tag".to_string(),
+                value: spec.name,
+            });
+        }
+        self.tags.insert(spec.name.clone(), (group, spec));
+        Ok(())
+    }
+
+    pub(crate) fn group_of(&self, tag: &str) -> Result<usize> {
+        self.tags
+            .get(tag)
+            .map(|(group, _)| *group)
+            .ok_or_else(|| CtApiError::TagNotFound {
+                tag: tag.to_string(),
+            })
+    }
+
+    pub(crate) fn spec(&self, tag: &str) -> Option<&TagSpec> {
+        self.tags.get(tag).map(|(_, spec)| spec)
+    }
+
+    pub(crate) fn set_group(&mut self, tag: &str, group: usize) {
+        if let Some(entry) = self.tags.get_mut(tag) {
+            entry.0 = group;
+        }
+    }
+
+    pub(crate) fn tags_in(&self, group: usize) -> Vec<String> {
+        let mut tags: Vec<_> = self
+            .tags
+            .iter()
+            .filter(|(_, (g, _))| *g == group)
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        tags.sort();
+        tags
+    }
+}
+
+#[derive(Debug)]
+struct Group {
+    list: CtList,
+    period: Duration,
+}
+
+/// Tags split across several lists whose reads are staggered
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::{CtClient, StaggeredList, TagSpec};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let client = Arc::new(CtClient::open_mock()?);
+/// let fast: Vec<TagSpec> = ["Flow1", "Flow2"].into_iter().map(TagSpec::from).collect();
+/// let slow = vec![TagSpec::new("Tank1_Level").deadband(0.5)];
+/// let list = StaggeredList::new(
+///     client,
+///     vec![(fast, Duration::from_millis(500)), (slow, Duration::from_secs(5))],
+/// )?;
+///
+/// list.read_all()?;
+/// assert_eq!(list.read_tag("Tank1_Level", 0)?, "63.0");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct StaggeredList {
+    groups: Vec<Group>,
+    membership: RwLock<Membership>,
+    schedule: Mutex<PollSchedule>,
+    clock: SharedClock,
+}
+
+impl StaggeredList {
+    /// Create one list per `(tags, poll period)` group
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - A tag appears in more than one group
+    /// * [`CtApiError::System`] - A list could not be created or a tag added
+    pub fn new(client: Arc<CtClient>, groups: Vec<(Vec<TagSpec>, Duration)>) -> Result<Self> {
+        Self::with_clock(client, groups, system_clock())
+    }
+
+    pub(crate) fn with_clock(
+        client: Arc<CtClient>,
+        groups: Vec<(Vec<TagSpec>, Duration)>,
+        clock: SharedClock,
+    ) -> Result<Self> {
+        let mut membership = Membership::default();
+        let mut lists = Vec::with_capacity(groups.len());
+        for (index, (specs, period)) in groups.into_iter().enumerate() {
+            let list = Arc::clone(&client).list_new(0)?;
+            for spec in specs {
+                let name = spec.name.clone();
+                membership.insert(index, spec)?;
+                let spec = membership.spec(&name).expect("just inserted");
+                add_spec(&list, spec, period)?;
+            }
+            lists.push(Group { list, period });
+        }
+        let periods = lists.iter().map(|group| group.period).collect();
+        Ok(Self {
+            schedule: Mutex::new(PollSchedule::new(clock.now(), periods)),
+            groups: lists,
+            membership: RwLock::new(membership),
+            clock,
+        })
+    }
+
+    /// Number of groups
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Tags currently in `group`, sorted by name
+    pub fn tags_in(&self, group: usize) -> Vec<String> {
+        self.membership
+            .read()
+            .expect("StaggeredList membership RwLock poisoned")
+            .tags_in(group)
+    }
+
+    /// Read every group once, immediately
+    pub fn read_all(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|group| group.list.read())
+    }
+
+    /// Read a tag's value from whichever group holds it
+    ///
+    /// `mode` is passed to [`CtList::read_tag`].
+    pub fn read_tag(&self, tag: &str, mode: u32) -> Result<String> {
+        let group = self
+            .membership
+            .read()
+            .expect("StaggeredList membership RwLock poisoned")
+            .group_of(tag)?;
+        self.groups[group].list.read_tag(tag, mode)
+    }
+
+    /// Move a tag to another group, adopting that group's poll period
+    ///
+    /// # Errors
+    /// * [`CtApiError::TagNotFound`] - The tag is not in any group
+    /// * [`CtApiError::InvalidParameter`] - `group` does not exist
+    pub fn move_tag(&self, tag: &str, group: usize) -> Result<()> {
+        let target = self.groups.get(group).ok_or(CtApiError::InvalidParameter {
+            param: "group".to_string(),
+            value: group.to_string(),
+        })?;
+        let mut membership = self
+            .membership
+            .write()
+            .expect("StaggeredList membership RwLock poisoned");
+        let current = membership.group_of(tag)?;
+        if current == group {
+            return Ok(());
+        }
+        let spec = membership.spec(tag).cloned().expect("tag has a group");
+        add_spec(&target.list, &spec, target.period)?;
+        if let Err(e) = self.groups[current].list.delete_tag(tag) {
+            let _ = target.list.delete_tag(tag);
+            return Err(e);
+        }
+        membership.set_group(tag, group);
+        Ok(())
+    }
+
+    /// Read the groups whose deadline has passed, returning their indices
+    pub fn poll_due(&self) -> Result<Vec<usize>> {
+        let due = self
+            .schedule
+            .lock()
+            .expect("StaggeredList schedule Mutex poisoned")
+            .take_due(self.clock.now());
+        for &group in &due {
+            self.groups[group].list.read()?;
+        }
+        Ok(due)
+    }
+
+    /// Read groups on schedule until `stop` is set
+    ///
+    /// Sleeps until the next group deadline, reads the due groups and
+    /// repeats. The first read error stops the loop and is returned.
+    pub fn run(&self, stop: &AtomicBool) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            let next = self
+                .schedule
+                .lock()
+                .expect("StaggeredList schedule Mutex poisoned")
+                .next_deadline();
+            let Some(next) = next else {
+                return Ok(());
+            };
+            self.clock.sleep_until(next);
+            self.poll_due()?;
+        }
+        Ok(())
+    }
+}
+
+/// Add `spec` to `list` with the group's poll period
+fn add_spec(list: &CtList, spec: &TagSpec, period: Duration) -> Result<()> {
+    let period_ms = i32::try_from(period.as_millis()).unwrap_or(i32::MAX);
+    list.add_tag_ex(spec.name.as_str(), spec.raw, period_ms, spec.deadband)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_offsets_spread_across_shortest_period() {
+        assert_eq!(
+            stagger_offsets(&[ms(1000), ms(400), ms(5000), ms(2000)]),
+            [ms(0), ms(100), ms(200), ms(300)]
+        );
+        assert_eq!(stagger_offsets(&[ms(500)]), [ms(0)]);
+        assert!(stagger_offsets(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_schedule_staggers_and_repeats() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut schedule = PollSchedule::new(start, vec![ms(300), ms(600), ms(300)]);
+        assert_eq!(schedule.next_deadline(), Some(start));
+
+        let mut reads = Vec::new();
+        for _ in 0..12 {
+            clock.advance(ms(50));
+            let now = clock.now();
+            for group in schedule.take_due(now) {
+                reads.push(((now - start).as_millis(), group));
+            }
+        }
+        // Offsets are 0, 100 and 200 ms; groups 0 and 2 repeat every 300 ms,
+        // group 1 every 600 ms.
+        assert_eq!(
+            reads,
+            [(50, 0), (100, 1), (200, 2), (300, 0), (500, 2), (600, 0)]
+        );
+    }
+
+    #[test]
+    fn test_schedule_skips_missed_deadlines_without_drift() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut schedule = PollSchedule::new(start, vec![ms(100)]);
+        assert_eq!(schedule.take_due(start), [0]);
+
+        // A read that overran by 2.5 periods triggers one catch-up read
+        clock.advance(ms(350));
+        assert_eq!(schedule.take_due(clock.now()), [0]);
+        assert_eq!(schedule.next_deadline(), Some(start + ms(400)));
+        assert!(schedule.take_due(start + ms(399)).is_empty());
+    }
+
+    #[test]
+    fn test_schedule_skips_more_than_u32_max_periods() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut schedule = PollSchedule::new(start, vec![Duration::from_nanos(1)]);
+        assert_eq!(schedule.take_due(start), [0]);
+
+        // 5 billion missed periods do not fit in a u32 multiplier
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(schedule.take_due(clock.now()), [0]);
+        let next = start + Duration::from_secs(5) + Duration::from_nanos(1);
+        assert_eq!(schedule.next_deadline(), Some(next));
+    }
+
+    #[test]
+    fn test_membership_lookup_and_move() {
+        let mut membership = Membership::default();
+        membership.insert(0, TagSpec::new("Flow1")).unwrap();
+        membership.insert(0, TagSpec::new("Flow2")).unwrap();
+        membership
+            .insert(1, TagSpec::new("Level").deadband(0.5))
+            .unwrap();
+
+        assert_eq!(membership.group_of("Flow2").unwrap(), 0);
+        assert_eq!(membership.group_of("Level").unwrap(), 1);
+        assert!(matches!(
+            membership.group_of("Missing"),
+            Err(CtApiError::TagNotFound { .. })
+        ));
+        assert!(membership.insert(1, TagSpec::new("Flow1")).is_err());
+
+        membership.set_group("Flow1", 1);
+        assert_eq!(membership.tags_in(0), ["Flow2"]);
+        assert_eq!(membership.tags_in(1), ["Flow1", "Level"]);
+        assert_eq!(membership.spec("Level").unwrap().deadband, 0.5);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_read_tag_across_groups() {
+        let client = Arc::new(CtClient::open_mock().unwrap());
+        let clock = MockClock::new();
+        let list = StaggeredList::with_clock(
+            client,
+            vec![
+                (vec!["Flow1".into(), "Flow2".into()], ms(100)),
+                (vec![TagSpec::new("Tank1_Level").raw(true)], ms(400)),
+            ],
+            clock.clone(),
+        )
+        .unwrap();
+
+        // Each group is read on its own deadline; its tags are then
+        // readable by name alongside the other group's.
+        assert_eq!(list.poll_due().unwrap(), [0]);
+        assert_eq!(list.read_tag("Flow2", 0).unwrap(), "11.8");
+        clock.advance(ms(50));
+        assert_eq!(list.poll_due().unwrap(), [1]);
+        assert_eq!(list.read_tag("Tank1_Level", 0).unwrap(), "63.0");
+        assert_eq!(list.read_tag("Flow1", 0).unwrap(), "12.5");
+        assert!(matches!(
+            list.read_tag("Pump1", 0),
+            Err(CtApiError::TagNotFound { .. })
+        ));
+
+        // A moved tag is read from its new group after that group's next read
+        list.move_tag("Flow1", 1).unwrap();
+        assert_eq!(list.tags_in(1), ["Flow1", "Tank1_Level"]);
+        list.read_all().unwrap();
+        assert_eq!(list.read_tag("Flow1", 0).unwrap(), "12.5");
+    }
+}
@@ -0,0 +1,367 @@
+//! Connection state notifications
+//!
+//! Every [`CtClient`](crate::CtClient) tracks whether its connection is up and
+//! reports changes to subscribers, so that user interfaces can show a live
+//! connection indicator without polling. Transitions come from opening and
+//! closing the client, from [`CtClient::ping`](crate::CtClient::ping) and
+//! from a [`ReconnectGate`](crate::ReconnectGate) attached to it.
+//!
+//! Repeated reports of the same state are collapsed: subscribers only see
+//! actual changes. A transition to [`ConnectionState::Down`] carries the
+//! client's [recent calls](crate::call_log) for post-mortem analysis.
+//!
+//! Every return to [`ConnectionState::Connected`] from another state
+//! advances the client's
+//! [reconnect generation](crate::CtClient::reconnect_generation).
+
+use crate::call_log::{CallLog, CallRecord};
+use crate::citect_code::CitectCode;
+use crate::error::CtApiError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Coarse state of a connection
+///
+/// Displayed and serialized (with the `serde` feature) as `connected`,
+/// `reconnecting` or `down`, without allocating. These strings are part of
+/// the log format and do not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// Calls reach the server
+    Connected,
+    /// The connection was lost and is being re-established
+    Reconnecting,
+    /// The connection is lost or the client has been closed
+    Down,
+}
+
+impl ConnectionState {
+    /// Text of the state
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionState::Connected => "connected",
+            ConnectionState::Reconnecting => "reconnecting",
+            ConnectionState::Down => "down",
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConnectionState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit_variant("ConnectionState", *self as u32, self.as_str())
+    }
+}
+
+/// Outcome of a [`CtClient::ping`](crate::CtClient::ping)
+#[derive(Debug, Clone)]
+pub enum ConnectionStatus {
+    /// The server answered the probe
+    Connected,
+    /// The probe failed with a connection error and CtAPI is re-establishing
+    /// the connection, as it does for clients opened with `CT_OPEN_RECONNECT`
+    Reconnecting {
+        /// Error of the probe
+        error: Arc<CtApiError>,
+    },
+    /// The probe failed with a connection error, or the client is closed
+    Disconnected {
+        /// Error of the probe
+        error: Arc<CtApiError>,
+    },
+}
+
+impl ConnectionStatus {
+    /// Whether the server answered
+    pub fn is_connected(&self) -> bool {
+        matches!(self, ConnectionStatus::Connected)
+    }
+
+    /// Error of the failed probe
+    pub fn error(&self) -> Option<&Arc<CtApiError>> {
+        match self {
+            ConnectionStatus::Connected => None,
+            ConnectionStatus::Reconnecting { error } | ConnectionStatus::Disconnected { error } => {
+                Some(error)
+            }
+        }
+    }
+
+    /// Win32 error code of the failed probe, if the system reported one
+    pub fn os_error(&self) -> Option<i32> {
+        match self.error()?.root() {
+            CtApiError::System(error, ..) => error.raw_os_error(),
+            CtApiError::Citect { code, .. } => Some(CitectCode::new(*code).os_error()),
+            _ => None,
+        }
+    }
+
+    /// Connection state this status is recorded as
+    pub fn state(&self) -> ConnectionState {
+        match self {
+            ConnectionStatus::Connected => ConnectionState::Connected,
+            ConnectionStatus::Reconnecting { .. } => ConnectionState::Reconnecting,
+            ConnectionStatus::Disconnected { .. } => ConnectionState::Down,
+        }
+    }
+}
+
+/// A connection state transition
+#[derive(Debug, Clone)]
+pub struct StateChange {
+    /// New state
+    pub state: ConnectionState,
+    /// When the transition happened
+    pub at: SystemTime,
+    /// Error that caused the transition, if any
+    pub error: Option<Arc<CtApiError>>,
+    /// Calls the client made before a transition to [`ConnectionState::Down`],
+    /// oldest first; empty for other transitions
+    pub recent_calls: Arc<[CallRecord]>,
+}
+
+impl StateChange {
+    fn new(state: ConnectionState, error: Option<Arc<CtApiError>>) -> Self {
+        Self {
+            state,
+            at: SystemTime::now(),
+            error,
+            recent_calls: Arc::new([]),
+        }
+    }
+}
+
+/// Callback invoked on every connection state transition
+pub type StateCallback = Arc<dyn Fn(&StateChange) + Send + Sync>;
+
+/// Current state of one connection plus its subscribers
+pub(crate) struct StateTracker {
+    current: Mutex<StateChange>,
+    /// Returns to [`ConnectionState::Connected`], advanced under `current`
+    generation: AtomicU64,
+    callbacks: Mutex<Vec<StateCallback>>,
+    calls: Option<Arc<CallLog>>,
+    #[cfg(feature = "tokio-support")]
+    sender: tokio::sync::watch::Sender<StateChange>,
+}
+
+impl std::fmt::Debug for StateTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateTracker")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl StateTracker {
+    /// Create a tracker starting in `state`
+    pub(crate) fn new(state: ConnectionState) -> Self {
+        let initial = StateChange::new(state, None);
+        Self {
+            #[cfg(feature = "tokio-support")]
+            sender: tokio::sync::watch::channel(initial.clone()).0,
+            current: Mutex::new(initial),
+            generation: AtomicU64::new(0),
+            callbacks: Mutex::new(Vec::new()),
+            calls: None,
+        }
+    }
+
+    /// Attach `calls` to transitions to [`ConnectionState::Down`]
+    pub(crate) fn with_call_log(mut self, calls: Arc<CallLog>) -> Self {
+        self.calls = Some(calls);
+        self
+    }
+
+    fn lock_current(&self) -> MutexGuard<'_, StateChange> {
+        self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current state
+    pub(crate) fn state(&self) -> ConnectionState {
+        self.lock_current().state
+    }
+
+    /// Number of returns to [`ConnectionState::Connected`] from another state
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Last transition, or the initial state
+    pub(crate) fn last_change(&self) -> StateChange {
+        self.lock_current().clone()
+    }
+
+    /// Move to `state`, notifying subscribers if it differs from the current one
+    ///
+    /// Returns whether a transition happened.
+    pub(crate) fn set(&self, state: ConnectionState, error: Option<CtApiError>) -> bool {
+        self.set_shared(state, error.map(Arc::new))
+    }
+
+    /// Like [`set`](Self::set), with an error that is also reported elsewhere
+    pub(crate) fn set_shared(
+        &self,
+        state: ConnectionState,
+        error: Option<Arc<CtApiError>>,
+    ) -> bool {
+        let change = {
+            let mut current = self.lock_current();
+            if current.state == state {
+                return false;
+            }
+            if state == ConnectionState::Connected {
+                self.generation.fetch_add(1, Ordering::AcqRel);
+            }
+            *current = StateChange::new(state, error);
+            if let (ConnectionState::Down, Some(calls)) = (state, &self.calls) {
+                current.recent_calls = calls.recent().into();
+            }
+            current.clone()
+        };
+
+        #[cfg(feature = "tokio-support")]
+        self.sender.send_replace(change.clone());
+
+        let callbacks = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for callback in callbacks {
+            callback(&change);
+        }
+        true
+    }
+
+    /// Register a callback for future transitions
+    pub(crate) fn on_change(&self, callback: StateCallback) {
+        self.callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(callback);
+    }
+
+    /// Receiver that always holds the latest transition
+    #[cfg(feature = "tokio-support")]
+    pub(crate) fn subscribe(&self) -> tokio::sync::watch::Receiver<StateChange> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(tracker: &StateTracker) -> Arc<Mutex<Vec<ConnectionState>>> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        tracker.on_change(Arc::new(move |change: &StateChange| {
+            sink.lock().unwrap().push(change.state)
+        }));
+        seen
+    }
+
+    #[test]
+    fn test_state_strings_are_stable() {
+        use ConnectionState::*;
+        for (state, text) in [
+            (Connected, "connected"),
+            (Reconnecting, "reconnecting"),
+            (Down, "down"),
+        ] {
+            assert_eq!(state.as_str(), text);
+            assert_eq!(state.to_string(), text);
+        }
+        #[cfg(all(feature = "serde", feature = "json"))]
+        assert_eq!(
+            serde_json::to_string(&Reconnecting).unwrap(),
+            r#""reconnecting""#
+        );
+    }
+
+    #[test]
+    fn test_transitions_are_deduplicated() {
+        let tracker = StateTracker::new(ConnectionState::Connected);
+        let seen = recorder(&tracker);
+
+        assert!(!tracker.set(ConnectionState::Connected, None));
+        assert!(tracker.set(ConnectionState::Reconnecting, None));
+        assert!(!tracker.set(ConnectionState::Reconnecting, None));
+        assert!(tracker.set(ConnectionState::Down, Some(CtApiError::Timeout)));
+        assert!(!tracker.set(ConnectionState::Down, None));
+        assert!(tracker.set(ConnectionState::Connected, None));
+
+        use ConnectionState::*;
+        assert_eq!(*seen.lock().unwrap(), [Reconnecting, Down, Connected]);
+        assert_eq!(tracker.state(), Connected);
+        assert_eq!(tracker.generation(), 1);
+    }
+
+    #[test]
+    fn test_down_carries_error_and_timestamp() {
+        let tracker = StateTracker::new(ConnectionState::Connected);
+        let before = SystemTime::now();
+        tracker.set(ConnectionState::Down, Some(CtApiError::Timeout));
+
+        let change = tracker.last_change();
+        assert_eq!(change.state, ConnectionState::Down);
+        assert!(change.at >= before);
+        assert!(matches!(change.error.as_deref(), Some(CtApiError::Timeout)));
+
+        tracker.set(ConnectionState::Connected, None);
+        assert!(tracker.last_change().error.is_none());
+    }
+
+    #[test]
+    fn test_down_carries_recent_calls() {
+        let calls = Arc::new(CallLog::new(2));
+        let tracker =
+            StateTracker::new(ConnectionState::Connected).with_call_log(Arc::clone(&calls));
+        for (op, ok) in [
+            ("ctTagRead", true),
+            ("ctCicode", true),
+            ("ctTagWrite", false),
+        ] {
+            let started = calls.start();
+            calls.finish(started, op, b"Pump1", ok);
+        }
+
+        tracker.set(ConnectionState::Reconnecting, None);
+        assert!(tracker.last_change().recent_calls.is_empty());
+        tracker.set(ConnectionState::Down, Some(CtApiError::Timeout));
+        let change = tracker.last_change();
+        let ops: Vec<_> = change.recent_calls.iter().map(|call| call.op).collect();
+        assert_eq!(ops, ["ctCicode", "ctTagWrite"]);
+        assert!(!change.recent_calls[1].is_ok());
+    }
+
+    #[cfg(feature = "tokio-support")]
+    #[test]
+    fn test_watch_receiver_sees_transitions() {
+        let tracker = StateTracker::new(ConnectionState::Connected);
+        let mut rx = tracker.subscribe();
+        assert_eq!(rx.borrow().state, ConnectionState::Connected);
+        assert!(!rx.has_changed().unwrap());
+
+        // Duplicate reports do not wake the receiver
+        tracker.set(ConnectionState::Connected, None);
+        assert!(!rx.has_changed().unwrap());
+
+        tracker.set(ConnectionState::Reconnecting, None);
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(rx.borrow_and_update().state, ConnectionState::Reconnecting);
+
+        tracker.set(ConnectionState::Down, Some(CtApiError::Timeout));
+        let change = rx.borrow_and_update().clone();
+        assert_eq!(change.state, ConnectionState::Down);
+        assert!(change.error.is_some());
+    }
+}
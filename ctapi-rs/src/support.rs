@@ -0,0 +1,349 @@
+//! Support bundles for bug reports
+//!
+//! [`collect`] gathers what is needed to diagnose most issues in one value:
+//! crate and platform details, the loaded CtAPI.dll version and, when a
+//! client is given, its connection parameters, state, counters and
+//! [recent calls](crate::call_log). Nothing
+//! is read from the server, so a bundle can be collected while the
+//! connection is down.
+//!
+//! With the `json` feature a bundle renders as pretty JSON for attaching to
+//! an issue. Passwords are [`SecretString`](crate::SecretString)s and appear
+//! as `***`.
+
+use crate::CtClient;
+use crate::call_log::CallRecord;
+use crate::client::ConnectionInfo;
+use crate::io_stats::IoStats;
+use crate::metadata::MetadataStats;
+use crate::query::QueryStats;
+use crate::state::StateChange;
+use crate::version::{self, VersionInfo};
+
+/// Diagnostic snapshot returned by [`collect`]
+#[derive(Debug, Clone)]
+pub struct SupportBundle {
+    /// Version of this crate
+    pub crate_version: &'static str,
+    /// Operating system, as in [`std::env::consts::OS`]
+    pub os: &'static str,
+    /// CPU architecture, as in [`std::env::consts::ARCH`]
+    pub arch: &'static str,
+    /// Bitness of this process
+    pub pointer_width: u32,
+    /// Crate features enabled in this build
+    pub features: Vec<&'static str>,
+    /// File version of the loaded CtAPI.dll, `None` if it is not loaded
+    pub ctapi_dll_version: Option<String>,
+    /// Details of the client passed to [`collect`]
+    pub client: Option<ClientReport>,
+}
+
+/// Client section of a [`SupportBundle`]
+#[derive(Debug, Clone)]
+pub struct ClientReport {
+    /// Parameters the client was opened with
+    pub connection: Option<ConnectionInfo>,
+    /// Last connection state transition, including the error that caused it
+    pub state: StateChange,
+    /// Versions recorded by the last version check
+    pub versions: Option<VersionInfo>,
+    /// I/O counters
+    pub io: IoStats,
+    /// Query cache counters
+    pub queries: QueryStats,
+    /// Metadata cache counters
+    pub metadata: MetadataStats,
+    /// Most recent CtAPI calls, oldest first
+    pub recent_calls: Vec<CallRecord>,
+}
+
+impl ClientReport {
+    fn new(client: &CtClient) -> Self {
+        Self {
+            connection: client.connection_info().cloned(),
+            state: client.state_tracker().last_change(),
+            versions: client.version_info(),
+            io: client.io_stats(),
+            queries: client.query_cache().stats(),
+            metadata: client.metadata_cache().stats(),
+            recent_calls: client.recent_calls(),
+        }
+    }
+}
+
+/// Every crate feature, with whether it is enabled in this build
+const FEATURES: &[(&str, bool)] = &[
+    ("backtrace", cfg!(feature = "backtrace")),
+    ("capi", cfg!(feature = "capi")),
+    ("cli", cfg!(feature = "cli")),
+    ("demos", cfg!(feature = "demos")),
+    ("json", cfg!(feature = "json")),
+    ("macros", cfg!(feature = "macros")),
+    ("mock", cfg!(feature = "mock")),
+    ("serde", cfg!(feature = "serde")),
+    ("tokio-support", cfg!(feature = "tokio-support")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("zeroize", cfg!(feature = "zeroize")),
+];
+
+/// Crate features enabled in this build
+fn enabled_features() -> Vec<&'static str> {
+    FEATURES
+        .iter()
+        .filter_map(|&(name, enabled)| enabled.then_some(name))
+        .collect()
+}
+
+/// Collect a support bundle, optionally including the state of `client`
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+///
+/// let client = CtClient::open_mock()?;
+/// let bundle = ctapi_rs::support::collect(Some(&client));
+/// assert_eq!(bundle.crate_version, env!("CARGO_PKG_VERSION"));
+/// assert!(bundle.client.is_some());
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub fn collect(client: Option<&CtClient>) -> SupportBundle {
+    SupportBundle {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        pointer_width: usize::BITS,
+        features: enabled_features(),
+        ctapi_dll_version: version::local_dll_version(),
+        client: client.map(ClientReport::new),
+    }
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use crate::io_stats::IoCounts;
+    use crate::secret::REDACTED;
+    use serde_json::{Map, Value};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+        let mut object = Map::new();
+        for (name, value) in fields {
+            object.insert(name.to_string(), value);
+        }
+        Value::Object(object)
+    }
+
+    fn unix_millis(at: SystemTime) -> u64 {
+        at.duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+
+    fn connection(info: &ConnectionInfo) -> Value {
+        object([
+            ("computer", info.computer.clone().into()),
+            ("user", info.user.clone().into()),
+            ("password", info.password.as_ref().map(|_| REDACTED).into()),
+            ("mode", info.mode.into()),
+        ])
+    }
+
+    fn counts(counts: &IoCounts) -> Value {
+        object([
+            ("calls", counts.calls.into()),
+            ("request_bytes", counts.request_bytes.into()),
+            ("response_bytes", counts.response_bytes.into()),
+        ])
+    }
+
+    fn call(call: &CallRecord) -> Value {
+        object([
+            ("op", call.op.into()),
+            ("subject", call.subject().into()),
+            ("at_unix_ms", unix_millis(call.at).into()),
+            ("duration_us", (call.duration.as_micros() as u64).into()),
+            ("error", call.error.into()),
+        ])
+    }
+
+    fn client(report: &ClientReport) -> Value {
+        let error = report.state.error.as_ref().map(|error| {
+            object([
+                ("code", error.code().into()),
+                ("message", error.to_string().into()),
+            ])
+        });
+        let versions = report.versions.as_ref().map(|versions| {
+            object([
+                ("client", versions.client.clone().into()),
+                ("server", versions.server.clone().into()),
+            ])
+        });
+        let MetadataStats {
+            hits,
+            misses,
+            expired,
+            reloads,
+            stale_entries,
+            invalidations,
+        } = report.metadata;
+        object([
+            (
+                "connection",
+                report.connection.as_ref().map(connection).into(),
+            ),
+            (
+                "state",
+                object([
+                    ("state", format!("{:?}", report.state.state).into()),
+                    ("since_unix_ms", unix_millis(report.state.at).into()),
+                    ("error", error.into()),
+                ]),
+            ),
+            ("versions", versions.into()),
+            (
+                "recent_calls",
+                report
+                    .recent_calls
+                    .iter()
+                    .map(call)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            (
+                "io",
+                object([
+                    ("reads", counts(&report.io.reads)),
+                    ("writes", counts(&report.io.writes)),
+                    ("cicode", counts(&report.io.cicode)),
+                    ("find", counts(&report.io.find)),
+                ]),
+            ),
+            (
+                "queries",
+                object([
+                    ("hits", report.queries.hits.into()),
+                    ("joined", report.queries.joined.into()),
+                    ("misses", report.queries.misses.into()),
+                ]),
+            ),
+            (
+                "metadata",
+                object([
+                    ("hits", hits.into()),
+                    ("misses", misses.into()),
+                    ("expired", expired.into()),
+                    ("reloads", reloads.into()),
+                    ("stale_entries", stale_entries.into()),
+                    ("invalidations", invalidations.into()),
+                ]),
+            ),
+        ])
+    }
+
+    impl SupportBundle {
+        /// The bundle as a JSON value
+        pub fn to_json(&self) -> Value {
+            object([
+                ("crate_version", self.crate_version.into()),
+                ("os", self.os.into()),
+                ("arch", self.arch.into()),
+                ("pointer_width", self.pointer_width.into()),
+                ("features", self.features.clone().into()),
+                ("ctapi_dll_version", self.ctapi_dll_version.clone().into()),
+                ("client", self.client.as_ref().map(client).into()),
+            ])
+        }
+
+        /// The bundle as indented JSON, ready to attach to an issue
+        pub fn to_pretty_json(&self) -> String {
+            serde_json::to_string_pretty(&self.to_json()).unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json", feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::CtApiError;
+    use crate::state::ConnectionState;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// A bundle with every platform-dependent value pinned
+    fn pinned(client: Option<&CtClient>) -> SupportBundle {
+        let mut bundle = collect(client);
+        bundle.crate_version = "0.0.0";
+        bundle.os = "windows";
+        bundle.arch = "x86_64";
+        bundle.pointer_width = 64;
+        bundle.features = vec!["json", "mock"];
+        bundle.ctapi_dll_version = Some("8.20.0.0".to_string());
+        if let Some(report) = &mut bundle.client {
+            report.state.at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_000);
+            for call in &mut report.recent_calls {
+                call.at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_000);
+                call.duration = Duration::from_micros(250);
+            }
+        }
+        bundle
+    }
+
+    #[test]
+    fn test_features_cover_the_manifest() {
+        let manifest = include_str!("../Cargo.toml");
+        let table = manifest.split("[features]").nth(1).unwrap();
+        let table = table.split("\n[").next().unwrap();
+        let mut declared: Vec<_> = table
+            .lines()
+            .filter_map(|line| line.split_once('=').map(|(name, _)| name.trim()))
+            .filter(|&name| name != "default")
+            .collect();
+        declared.sort_unstable();
+        let listed: Vec<_> = FEATURES.iter().map(|&(name, _)| name).collect();
+        assert_eq!(listed, declared);
+    }
+
+    #[test]
+    fn test_json_matches_golden_file() {
+        let client = CtClient::open_mock()
+            .unwrap()
+            .with_connection_info(ConnectionInfo {
+                computer: Some("scada01".to_string()),
+                user: Some("Manager".to_string()),
+                password: Some("s3cr3t-pw".into()),
+                mode: 0,
+            });
+        client.tag_read("Temperature").unwrap();
+        client.state_tracker().set(
+            ConnectionState::Reconnecting,
+            Some(CtApiError::ConnectionFailed {
+                message: "server not responding".to_string(),
+            }),
+        );
+
+        let json = pinned(Some(&client)).to_pretty_json();
+        let golden = include_str!("../tests/golden/support_bundle.json");
+        assert_eq!(json, golden.trim_end());
+    }
+
+    #[test]
+    fn test_secrets_never_appear() {
+        let client = CtClient::open_mock()
+            .unwrap()
+            .with_connection_info(ConnectionInfo {
+                password: Some("s3cr3t-pw".into()),
+                ..ConnectionInfo::default()
+            });
+        let bundle = collect(Some(&client));
+        assert!(!bundle.to_pretty_json().contains("s3cr3t-pw"));
+        assert!(!format!("{bundle:?}").contains("s3cr3t-pw"));
+    }
+
+    #[test]
+    fn test_without_client() {
+        let bundle = pinned(None);
+        assert!(bundle.client.is_none());
+        assert!(bundle.to_json().to_string().contains("\"client\":null"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Synchronization primitives of the internal state machines
+//!
+//! The state shared between threads by the reconnect gate, the list tag
+//! table, async operations and their futures is guarded by the primitives
+//! re-exported here. They are the `std` ones, except when the crate is built
+//! with `--cfg loom`: they are then [loom](https://docs.rs/loom)'s, and the
+//! `loom` tests of those modules explore every interleaving of their
+//! threads:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test -p ctapi-rs --lib --release loom
+//! ```
+//!
+//! Only the model tests can run in that configuration, since loom
+//! primitives panic when used outside `loom::model`.
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(loom)]
+pub(crate) use loom::sync::{Condvar, Mutex, MutexGuard, RwLock};
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Condvar, Mutex, MutexGuard, RwLock};
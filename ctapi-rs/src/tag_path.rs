@@ -0,0 +1,621 @@
+//! Tag addressing: plain tags, array elements and element fields
+//!
+//! Besides a plain tag name, Citect SCADA accepts an element of an array tag
+//! (`Tag[3]`) and a field of a tag element (`Tag.V`, `Tag.Q`, ...) wherever
+//! a tag is read. [`TagPath`] builds and parses these references, with an
+//! optional cluster prefix (`Cluster.Tag.Field`).
+//!
+//! The three addressing forms are read through the same `ctTagRead` and
+//! list calls, but the API does not report the same items for each of them:
+//!
+//! | Item                          | `Tag` | `Tag[n]` | `Tag.Field` |
+//! |-------------------------------|:-----:|:--------:|:-----------:|
+//! | Value                         |  yes  |   yes    |     yes     |
+//! | Value and quality timestamps  |  yes  |   yes    |     no      |
+//! | General quality               |  yes  |   yes    |     no      |
+//! | Quality substatus and limit   |  yes  |   yes    |     no      |
+//! | Extended substatus            |  yes  |   yes    |     no      |
+//! | Data source error             |  yes  |   yes    |     no      |
+//! | Override and control mode     |  yes  |   yes    |     no      |
+//!
+//! A field read returns the field itself as its value, so its quality is
+//! reported as [`Quality::NOT_APPLICABLE`](crate::Quality::NOT_APPLICABLE)
+//! by [`CtClient::tag_read_full`](crate::CtClient::tag_read_full) and
+//! [`CtList::read_all_full`](crate::CtList::read_all_full) rather than
+//! failing. [`AddressingForm::supports`] answers from the table above.
+//!
+//! The table is an assumption, not taken from the CtAPI reference, which
+//! does not say which items each form reports. It follows from a field
+//! being a single item of its tag (`Tag.Q` *is* the quality) that carries
+//! no quality of its own; check it against the server in use.
+//!
+//! [`validate_tag_name`] rejects references Citect SCADA never accepts, such
+//! as names with spaces or an embedded NUL, before they reach the DLL.
+//! [`CtClient`](crate::CtClient) runs it on the names it reads, writes and
+//! adds to lists unless
+//! [`set_validate_tag_names`](crate::CtClient::set_validate_tag_names)
+//! turns it off.
+
+use crate::constants::{
+    CT_LIST_QUALITY_CONTROL_MODE, CT_LIST_QUALITY_DATASOURCE_ERROR,
+    CT_LIST_QUALITY_EXTENDED_SUBSTATUS, CT_LIST_QUALITY_GENERAL, CT_LIST_QUALITY_LIMIT,
+    CT_LIST_QUALITY_OVERRIDE, CT_LIST_QUALITY_SUBSTATUS, CT_LIST_QUALITY_TIMESTAMP,
+    CT_LIST_TIMESTAMP, CT_LIST_VALUE, CT_LIST_VALUE_TIMESTAMP,
+};
+use crate::error::{CtApiError, Result};
+use ctapi_tag_grammar as grammar;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Longest tag reference, in characters, that [`validate_tag_name`],
+/// [`TagPath::parse`] and `tag!` accept
+pub const MAX_TAG_NAME_LEN: usize = grammar::MAX_TAG_NAME_LEN;
+
+/// How a [`TagPath`] addresses its tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressingForm {
+    /// A whole tag, `Tag`
+    Plain,
+    /// An element of an array tag, `Tag[n]`
+    Element,
+    /// A field of a tag element, `Tag.Field` or `Tag[n].Field`
+    Field,
+}
+
+/// An item reported next to a tag value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadItem {
+    /// The value itself
+    Value,
+    /// Timestamp of the reading
+    Timestamp,
+    /// Time the value last changed
+    ValueTimestamp,
+    /// Quality timestamp
+    QualityTimestamp,
+    /// General quality
+    QualityGeneral,
+    /// Quality substatus
+    QualitySubstatus,
+    /// Quality limit
+    QualityLimit,
+    /// Extended quality substatus
+    QualityExtendedSubstatus,
+    /// Data source error
+    DatasourceError,
+    /// Override flag
+    Override,
+    /// Control mode flag
+    ControlMode,
+}
+
+impl ReadItem {
+    /// `ctListData` mode reading this item
+    pub fn list_mode(self) -> u32 {
+        match self {
+            ReadItem::Value => CT_LIST_VALUE,
+            ReadItem::Timestamp => CT_LIST_TIMESTAMP,
+            ReadItem::ValueTimestamp => CT_LIST_VALUE_TIMESTAMP,
+            ReadItem::QualityTimestamp => CT_LIST_QUALITY_TIMESTAMP,
+            ReadItem::QualityGeneral => CT_LIST_QUALITY_GENERAL,
+            ReadItem::QualitySubstatus => CT_LIST_QUALITY_SUBSTATUS,
+            ReadItem::QualityLimit => CT_LIST_QUALITY_LIMIT,
+            ReadItem::QualityExtendedSubstatus => CT_LIST_QUALITY_EXTENDED_SUBSTATUS,
+            ReadItem::DatasourceError => CT_LIST_QUALITY_DATASOURCE_ERROR,
+            ReadItem::Override => CT_LIST_QUALITY_OVERRIDE,
+            ReadItem::ControlMode => CT_LIST_QUALITY_CONTROL_MODE,
+        }
+    }
+}
+
+/// Availability of each item for `[Plain, Element, Field]`, see the module documentation
+const AVAILABILITY: &[(ReadItem, [bool; 3])] = &[
+    (ReadItem::Value, [true, true, true]),
+    (ReadItem::Timestamp, [true, true, false]),
+    (ReadItem::ValueTimestamp, [true, true, false]),
+    (ReadItem::QualityTimestamp, [true, true, false]),
+    (ReadItem::QualityGeneral, [true, true, false]),
+    (ReadItem::QualitySubstatus, [true, true, false]),
+    (ReadItem::QualityLimit, [true, true, false]),
+    (ReadItem::QualityExtendedSubstatus, [true, true, false]),
+    (ReadItem::DatasourceError, [true, true, false]),
+    (ReadItem::Override, [true, true, false]),
+    (ReadItem::ControlMode, [true, true, false]),
+];
+
+impl AddressingForm {
+    /// Whether the API reports `item` for tags addressed this way
+    pub fn supports(self, item: ReadItem) -> bool {
+        let column = match self {
+            AddressingForm::Plain => 0,
+            AddressingForm::Element => 1,
+            AddressingForm::Field => 2,
+        };
+        AVAILABILITY
+            .iter()
+            .find(|(known, _)| *known == item)
+            .is_some_and(|(_, forms)| forms[column])
+    }
+
+    /// Whether the API reports the quality of tags addressed this way
+    pub fn has_quality(self) -> bool {
+        self.supports(ReadItem::QualityGeneral)
+    }
+}
+
+/// Field of a tag element
+///
+/// The documented fields are recognised case-insensitively; any other valid
+/// identifier is passed to the server as a custom field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TagField {
+    /// `V`, the value
+    Value,
+    /// `Q`, the quality
+    Quality,
+    /// `T`, the timestamp
+    Timestamp,
+    /// `VT`, the value timestamp
+    ValueTimestamp,
+    /// `QT`, the quality timestamp
+    QualityTimestamp,
+    /// Any other field, kept as written
+    Custom(Cow<'static, str>),
+}
+
+/// Documented fields and their names, as listed by the shared grammar
+const KNOWN_FIELDS: &[(&str, TagField)] = &[
+    ("V", TagField::Value),
+    ("Q", TagField::Quality),
+    ("T", TagField::Timestamp),
+    ("VT", TagField::ValueTimestamp),
+    ("QT", TagField::QualityTimestamp),
+];
+
+impl TagField {
+    /// The documented field called `name`, if any
+    pub fn known(name: &str) -> Option<Self> {
+        KNOWN_FIELDS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, field)| field.clone())
+    }
+
+    /// The field called `name`, which the grammar has accepted
+    fn named(name: &str) -> Self {
+        Self::known(name).unwrap_or_else(|| TagField::Custom(Cow::Owned(name.to_string())))
+    }
+
+    /// Field name as sent to the server
+    pub fn as_str(&self) -> &str {
+        match self {
+            TagField::Custom(name) => name,
+            field => KNOWN_FIELDS
+                .iter()
+                .find(|(_, known)| known == field)
+                .map_or("", |(name, _)| *name),
+        }
+    }
+}
+
+impl FromStr for TagField {
+    type Err = CtApiError;
+
+    /// Parse a field name: a letter or `_` followed by letters, digits or `_`
+    fn from_str(name: &str) -> Result<Self> {
+        grammar::check_field(0, name).map_err(|_| invalid("field", name))?;
+        Ok(Self::named(name))
+    }
+}
+
+impl fmt::Display for TagField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn invalid(param: &str, value: &str) -> CtApiError {
+    CtApiError::InvalidParameter {
+        param: param.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Check a tag or cluster name, which is made of letters, digits and `_`
+fn check_name(param: &str, name: &str) -> Result<()> {
+    grammar::check_name(param, 0, name).map_err(|_| invalid(param, name))
+}
+
+/// Reference to a tag, an array element or an element field
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::tag_path::{AddressingForm, TagPath};
+///
+/// let path = TagPath::new("Flow")?.cluster("Cluster1")?.index(2).field("V")?;
+/// assert_eq!(path.to_string(), "Cluster1.Flow[2].V");
+/// assert_eq!(path.form(), AddressingForm::Field);
+///
+/// let parsed: TagPath = "Flow[2]".parse()?;
+/// assert_eq!(parsed.form(), AddressingForm::Element);
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TagPath {
+    cluster: Option<Cow<'static, str>>,
+    tag: Cow<'static, str>,
+    index: Option<u32>,
+    field: Option<TagField>,
+}
+
+impl TagPath {
+    /// Path of the whole tag `tag`
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `tag` is empty or not made of
+    ///   letters, digits and `_`
+    pub fn new(tag: &str) -> Result<Self> {
+        check_name("tag", tag)?;
+        Ok(Self {
+            cluster: None,
+            tag: Cow::Owned(tag.to_string()),
+            index: None,
+            field: None,
+        })
+    }
+
+    /// Qualify the tag with its cluster
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `cluster` is empty or not made of
+    ///   letters, digits and `_`
+    pub fn cluster(mut self, cluster: &str) -> Result<Self> {
+        check_name("cluster", cluster)?;
+        self.cluster = Some(Cow::Owned(cluster.to_string()));
+        Ok(self)
+    }
+
+    /// Address element `index` of an array tag
+    pub fn index(mut self, index: u32) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Address a field of the tag element
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `field` is not a valid identifier
+    pub fn field(mut self, field: &str) -> Result<Self> {
+        self.field = Some(field.parse()?);
+        Ok(self)
+    }
+
+    /// Parse a tag reference
+    ///
+    /// `A.B` is read as a tag field when `B` is a documented field
+    /// ([`TagField::known`]) and as a cluster qualified tag otherwise; a
+    /// custom field therefore needs the cluster (`Cluster.Tag.Field`) or
+    /// [`TagPath::field`]. With the `macros` feature, `tag!` checks a
+    /// literal reference against the same grammar at compile time and
+    /// builds the path as a constant.
+    ///
+    /// # Errors
+    /// * [`CtApiError::InvalidParameter`] - `path` is not a valid tag reference
+    pub fn parse(path: &str) -> Result<Self> {
+        let parts = grammar::parse(path).map_err(|_| invalid("tag", path))?;
+        Ok(Self {
+            cluster: parts.cluster.map(|cluster| Cow::Owned(cluster.to_string())),
+            tag: Cow::Owned(parts.tag.to_string()),
+            index: parts.index,
+            field: parts.field.map(TagField::named),
+        })
+    }
+
+    /// Expansion of the `tag!` macro, from the parts it has already checked
+    #[doc(hidden)]
+    pub const fn __from_parts(
+        cluster: Option<&'static str>,
+        tag: &'static str,
+        index: Option<u32>,
+        field: Option<TagField>,
+    ) -> Self {
+        Self {
+            cluster: match cluster {
+                Some(cluster) => Some(Cow::Borrowed(cluster)),
+                None => None,
+            },
+            tag: Cow::Borrowed(tag),
+            index,
+            field,
+        }
+    }
+
+    /// Cluster of the tag, if qualified
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.cluster.as_deref()
+    }
+
+    /// Tag name without cluster, index or field
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Array element index, if any
+    pub fn element(&self) -> Option<u32> {
+        self.index
+    }
+
+    /// Element field, if any
+    pub fn element_field(&self) -> Option<&TagField> {
+        self.field.as_ref()
+    }
+
+    /// How the tag is addressed
+    pub fn form(&self) -> AddressingForm {
+        match (&self.field, self.index) {
+            (Some(_), _) => AddressingForm::Field,
+            (None, Some(_)) => AddressingForm::Element,
+            (None, None) => AddressingForm::Plain,
+        }
+    }
+
+    /// Addressing form of the tag reference `tag`, plain if it does not parse
+    pub(crate) fn form_of(tag: &str) -> AddressingForm {
+        Self::parse(tag).map_or(AddressingForm::Plain, |path| path.form())
+    }
+}
+
+impl FromStr for TagPath {
+    type Err = CtApiError;
+
+    fn from_str(path: &str) -> Result<Self> {
+        Self::parse(path)
+    }
+}
+
+impl fmt::Display for TagPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(cluster) = &self.cluster {
+            write!(f, "{cluster}.")?;
+        }
+        f.write_str(&self.tag)?;
+        if let Some(index) = self.index {
+            write!(f, "[{index}]")?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, ".{field}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Why [`validate_tag_name`] rejected a tag reference
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TagNameError {
+    /// The name is empty
+    #[error("name is empty")]
+    Empty,
+    /// The name is longer than [`MAX_TAG_NAME_LEN`] characters
+    #[error("{len} characters long, maximum is {MAX_TAG_NAME_LEN}")]
+    TooLong {
+        /// Length in characters
+        len: usize,
+    },
+    /// A character that cannot appear where it does
+    #[error("{character:?} not allowed at position {position}")]
+    InvalidChar {
+        /// Offending character
+        character: char,
+        /// 0-based position in characters
+        position: usize,
+    },
+}
+
+impl TagNameError {
+    fn invalid_char(character: char, position: usize) -> Self {
+        TagNameError::InvalidChar {
+            character,
+            position,
+        }
+    }
+}
+
+/// Check that `name` is a tag reference Citect SCADA can accept
+///
+/// Applies the grammar of [`TagPath::parse`] and `tag!`: names of letters,
+/// digits and `_`, in any script, separated by the `.` of a cluster prefix
+/// or element field, the tag optionally followed by an array index `[n]`,
+/// and at most [`MAX_TAG_NAME_LEN`] characters in all. Spaces, control
+/// characters and other punctuation are rejected with their position.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::tag_path::{TagNameError, validate_tag_name};
+///
+/// assert!(validate_tag_name("Cluster1.Flow[2].V").is_ok());
+/// assert!(validate_tag_name("温度").is_ok());
+/// assert_eq!(
+///     validate_tag_name("Flow rate"),
+///     Err(TagNameError::InvalidChar { character: ' ', position: 4 })
+/// );
+/// ```
+pub fn validate_tag_name(name: &str) -> std::result::Result<(), TagNameError> {
+    let invalid = match grammar::parse(name) {
+        Ok(_) => return Ok(()),
+        Err(invalid) => invalid,
+    };
+    let len = name.chars().count();
+    if len > MAX_TAG_NAME_LEN {
+        return Err(TagNameError::TooLong { len });
+    }
+    match invalid.culprit(name) {
+        Some((position, character)) => Err(TagNameError::invalid_char(character, position)),
+        None => Err(TagNameError::Empty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forms() {
+        let cases = [
+            ("Pump1", None, "Pump1", None, None, AddressingForm::Plain),
+            (
+                "Flow[3]",
+                None,
+                "Flow",
+                Some(3),
+                None,
+                AddressingForm::Element,
+            ),
+            (
+                "Pump1.v",
+                None,
+                "Pump1",
+                None,
+                Some(TagField::Value),
+                AddressingForm::Field,
+            ),
+            (
+                "Cluster1.Pump1",
+                Some("Cluster1"),
+                "Pump1",
+                None,
+                None,
+                AddressingForm::Plain,
+            ),
+            (
+                "Cluster1.Flow[0].Scale",
+                Some("Cluster1"),
+                "Flow",
+                Some(0),
+                Some(TagField::Custom("Scale".into())),
+                AddressingForm::Field,
+            ),
+        ];
+        for (text, cluster, tag, index, field, form) in cases {
+            let path = TagPath::parse(text).unwrap();
+            assert_eq!(path.cluster_name(), cluster, "{text}");
+            assert_eq!(path.tag(), tag, "{text}");
+            assert_eq!(path.element(), index, "{text}");
+            assert_eq!(path.element_field(), field.as_ref(), "{text}");
+            assert_eq!(path.form(), form, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_known_fields_match_the_grammar() {
+        assert_eq!(KNOWN_FIELDS.len(), grammar::KNOWN_FIELDS.len());
+        for (name, variant) in grammar::KNOWN_FIELDS {
+            let field = TagField::known(name).unwrap();
+            assert_eq!(field.as_str(), *name);
+            assert_eq!(format!("{field:?}"), *variant);
+        }
+    }
+
+    #[test]
+    fn test_from_parts_matches_parse() {
+        const FLOW: TagPath =
+            TagPath::__from_parts(Some("Cluster1"), "Flow", Some(2), Some(TagField::Value));
+        assert_eq!(FLOW, TagPath::parse("Cluster1.Flow[2].V").unwrap());
+        let custom = Some(TagField::Custom(Cow::Borrowed("Scale")));
+        let scale = TagPath::__from_parts(None, "Flow", None, custom);
+        assert_eq!(scale, TagPath::new("Flow").unwrap().field("Scale").unwrap());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let path = TagPath::new("Flow").unwrap().index(2).field("qt").unwrap();
+        assert_eq!(path.to_string(), "Flow[2].QT");
+        assert_eq!(path.to_string().parse::<TagPath>().unwrap(), path);
+
+        let custom = TagPath::new("Pump1").unwrap().field("Mode_2").unwrap();
+        assert_eq!(custom.to_string(), "Pump1.Mode_2");
+        let qualified = custom.cluster("Site").unwrap();
+        assert_eq!(qualified.to_string().parse::<TagPath>().unwrap(), qualified);
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        for text in [
+            "",
+            "A.B.C.D",
+            "Flow[x]",
+            "Flow[1",
+            "Pump1.V.",
+            ".Pump1",
+            "A.B.1x",
+            "Boiler1\\Temp",
+            "Flow rate",
+        ] {
+            assert!(
+                matches!(
+                    TagPath::parse(text),
+                    Err(CtApiError::InvalidParameter { .. })
+                ),
+                "{text}"
+            );
+        }
+        assert!(TagPath::new("A.B").is_err());
+        assert!(TagPath::new("Flow rate").is_err());
+        assert!(TagPath::new("Pump1").unwrap().field("").is_err());
+        assert!(TagPath::new("Pump1").unwrap().field("V-1").is_err());
+        assert!(TagPath::new("Pump1").unwrap().cluster("C[1]").is_err());
+    }
+
+    #[test]
+    fn test_validate_tag_name() {
+        for name in [
+            "Pump1",
+            "Cluster1.Flow[12].QT",
+            "Tag_1.v",
+            "_Local",
+            "温度.V",
+        ] {
+            assert_eq!(validate_tag_name(name), Ok(()), "{name}");
+        }
+        let invalid = |character, position| Err(TagNameError::invalid_char(character, position));
+        assert_eq!(validate_tag_name("Tag\01"), invalid('\0', 3));
+        assert_eq!(validate_tag_name("Flow rate"), invalid(' ', 4));
+        assert_eq!(validate_tag_name("Pump-1"), invalid('-', 4));
+        assert_eq!(validate_tag_name(".Pump1"), invalid('.', 0));
+        assert_eq!(validate_tag_name("Pump1..V"), invalid('.', 6));
+        assert_eq!(validate_tag_name("Pump1."), invalid('.', 5));
+        assert_eq!(validate_tag_name("Flow[]"), invalid(']', 5));
+        assert_eq!(validate_tag_name("Flow[x]"), invalid('x', 5));
+        assert_eq!(validate_tag_name("Flow[2"), invalid('[', 4));
+        assert_eq!(validate_tag_name("Flow[2]x"), invalid('[', 4));
+        assert_eq!(validate_tag_name("Flow[+3]"), invalid('+', 5));
+        assert_eq!(validate_tag_name("A.B.C.D"), invalid('.', 5));
+        assert_eq!(validate_tag_name(""), Err(TagNameError::Empty));
+
+        let longest = "T".repeat(MAX_TAG_NAME_LEN);
+        assert_eq!(validate_tag_name(&longest), Ok(()));
+        let len = MAX_TAG_NAME_LEN + 1;
+        assert_eq!(
+            validate_tag_name(&"T".repeat(len)),
+            Err(TagNameError::TooLong { len })
+        );
+    }
+
+    #[test]
+    fn test_availability_matrix() {
+        use AddressingForm::*;
+        for (item, _) in AVAILABILITY {
+            assert!(Plain.supports(*item), "{item:?}");
+            assert!(Element.supports(*item), "{item:?}");
+        }
+        assert!(Field.supports(ReadItem::Value));
+        assert!(!Field.supports(ReadItem::QualityGeneral));
+        assert!(!Field.supports(ReadItem::Timestamp));
+        assert!(Plain.has_quality() && Element.has_quality() && !Field.has_quality());
+        assert_eq!(
+            ReadItem::DatasourceError.list_mode(),
+            CT_LIST_QUALITY_DATASOURCE_ERROR
+        );
+    }
+}
@@ -18,21 +18,34 @@
 //! offloads the blocking call to Tokio's blocking-thread pool via
 //! `spawn_blocking`, leaving the async runtime free to drive other tasks.
 //!
-//! For operations that natively support Windows OVERLAPPED I/O (e.g. Cicode),
-//! consider [`FutureCtClient`](crate::FutureCtClient) instead — it avoids a
-//! dedicated thread entirely.
+//! Operations that natively support Windows OVERLAPPED I/O (Cicode, tag
+//! writes and list reads/writes) are driven through
+//! [`CtApiFuture`](crate::CtApiFuture) instead, so they do not occupy a
+//! blocking-pool thread at all.
+//!
+//! # Cancellation
+//!
+//! Dropping a future returned by [`TokioCtClient::cicode_tokio`],
+//! [`TokioCtClient::tag_write_tokio`] or the [`TokioCtList`] methods on
+//! `CtList` — for example when [`tokio::time::timeout`] fires or a
+//! `tokio::select!` branch loses — cancels the SCADA-side operation with
+//! `ctCancelIO`, waits for CtAPI to complete the cancellation and then
+//! releases its buffer. The `spawn_blocking` based reads (`tag_read_tokio`,
+//! `tag_read_ex_tokio` and the `Arc<CtList>` methods) cannot be
+//! interrupted: the blocking call runs to completion and its result is
+//! discarded.
 //!
 //! # Examples
 //!
-//! ```no_run
-//! use ctapi_rs::{CtClient, TokioCtClient};
+//! ```
+//! use ctapi_rs::{CicodeWindow, CtClient, TokioCtClient};
 //! use std::sync::Arc;
 //!
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
-//!     let client = Arc::new(CtClient::open(None, None, None, 0)?);
+//!     let client = Arc::new(CtClient::open_mock()?);
 //!
-//!     let time = client.cicode_tokio("Time(1)", 0, 0).await?;
+//!     let time = client.cicode_tokio("Time(1)", CicodeWindow::NONE, 0).await?;
 //!     println!("Server time: {}", time);
 //!
 //!     let temp = client.tag_read_tokio("Temperature").await?;
@@ -43,9 +56,10 @@
 //! ```
 
 use crate::error::Result;
-use crate::{AsyncOperation, CtClient, CtList, CtTagValueItems};
+use crate::{
+    AsyncOperation, CicodeWindow, CtApiFuture, CtClient, CtList, CtTagValueItems, FutureCtClient,
+};
 use std::sync::Arc;
-use windows_sys::Win32::System::Threading::WaitForSingleObject;
 
 // ───────────────────────────────────────────────
 // TokioCtClient
@@ -54,9 +68,10 @@ use windows_sys::Win32::System::Threading::WaitForSingleObject;
 /// Extension trait providing `async`/`await`-compatible methods for
 /// [`CtClient`].
 ///
-/// All methods offload the blocking CtAPI call to Tokio's blocking-thread
-/// pool via [`tokio::task::spawn_blocking`], so the async runtime is never
-/// stalled.
+/// Cicode calls and tag writes use OVERLAPPED I/O and are cancelled when
+/// the future is dropped. Tag reads offload the blocking CtAPI call to
+/// Tokio's blocking-thread pool via [`tokio::task::spawn_blocking`], so the
+/// async runtime is never stalled.
 ///
 /// # Implementations
 ///
@@ -66,13 +81,13 @@ use windows_sys::Win32::System::Threading::WaitForSingleObject;
 ///
 /// # Examples
 ///
-/// ```no_run
+/// ```
 /// use ctapi_rs::{CtClient, TokioCtClient};
 /// use std::sync::Arc;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let client = Arc::new(CtClient::open(None, None, None, 0)?);
+///     let client = Arc::new(CtClient::open_mock()?);
 ///
 ///     // Concurrent reads — spawn multiple Tokio tasks
 ///     let c1 = Arc::clone(&client);
@@ -90,23 +105,24 @@ pub trait TokioCtClient {
     /// Execute a Cicode function asynchronously.
     ///
     /// Equivalent to [`CtClient::cicode`] but non-blocking in async contexts.
+    /// Dropping the future before it resolves cancels the call on the server.
     ///
     /// # Parameters
     /// * `cmd`    - Cicode command string (e.g. `"Time(1)"`).
-    /// * `vh_win` - Window handle, usually `0`.
+    /// * `vh_win` - Window to run the function in, usually `0` ([`CicodeWindow::NONE`]).
     /// * `mode`   - Execution mode flag.
     ///
     /// # Examples
-    /// ```no_run
-    /// # use ctapi_rs::{CtClient, TokioCtClient};
+    /// ```
+    /// # use ctapi_rs::{CicodeWindow, CtClient, TokioCtClient};
     /// # #[tokio::main]
     /// # async fn main() -> anyhow::Result<()> {
-    /// let client = CtClient::open(None, None, None, 0)?;
-    /// let result = client.cicode_tokio("Time(1)", 0, 0).await?;
+    /// let client = CtClient::open_mock()?;
+    /// let result = client.cicode_tokio("Time(1)", CicodeWindow::NONE, 0).await?;
     /// println!("Server time: {}", result);
     /// # Ok(()) }
     /// ```
-    async fn cicode_tokio(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<String>;
+    async fn cicode_tokio(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String>;
 
     /// Read a tag value asynchronously.
     ///
@@ -114,11 +130,11 @@ pub trait TokioCtClient {
     /// * `tag` - Tag name.
     ///
     /// # Examples
-    /// ```no_run
+    /// ```
     /// # use ctapi_rs::{CtClient, TokioCtClient};
     /// # #[tokio::main]
     /// # async fn main() -> anyhow::Result<()> {
-    /// let client = CtClient::open(None, None, None, 0)?;
+    /// let client = CtClient::open_mock()?;
     /// let value = client.tag_read_tokio("Temperature").await?;
     /// println!("Temperature: {}", value);
     /// # Ok(()) }
@@ -134,11 +150,11 @@ pub trait TokioCtClient {
     /// Returns a tuple of `(value_string, CtTagValueItems)`.
     ///
     /// # Examples
-    /// ```no_run
+    /// ```
     /// # use ctapi_rs::{CtClient, TokioCtClient};
     /// # #[tokio::main]
     /// # async fn main() -> anyhow::Result<()> {
-    /// let client = CtClient::open(None, None, None, 0)?;
+    /// let client = CtClient::open_mock()?;
     /// let (value, meta) = client.tag_read_ex_tokio("Pressure").await?;
     /// println!("Pressure: {}  quality: {}", value, meta.quality_general);
     /// # Ok(()) }
@@ -171,10 +187,8 @@ pub trait TokioCtClient {
 // ── impl for CtClient ────────────────────────────────────────────────────────
 
 impl TokioCtClient for CtClient {
-    async fn cicode_tokio(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<String> {
-        let client = self.clone();
-        let cmd = cmd.to_string();
-        spawn_blocking_result(move || client.cicode(&cmd, vh_win, mode)).await
+    async fn cicode_tokio(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String> {
+        self.cicode_future(cmd, vh_win, mode)?.await
     }
 
     async fn tag_read_tokio(&self, tag: &str) -> Result<String> {
@@ -195,20 +209,15 @@ impl TokioCtClient for CtClient {
     }
 
     async fn tag_write_tokio(&self, tag: &str, value: &str) -> Result<()> {
-        let client = self.clone();
-        let tag = tag.to_string();
-        let value = value.to_string();
-        spawn_blocking_result(move || client.tag_write_str(&tag, &value)).await
+        self.tag_write_future(tag, value)?.await.map(|_| ())
     }
 }
 
 // ── impl for Arc<CtClient> ───────────────────────────────────────────────────
 
 impl TokioCtClient for Arc<CtClient> {
-    async fn cicode_tokio(&self, cmd: &str, vh_win: u32, mode: u32) -> Result<String> {
-        let client = Arc::clone(self);
-        let cmd = cmd.to_string();
-        spawn_blocking_result(move || client.cicode(&cmd, vh_win, mode)).await
+    async fn cicode_tokio(&self, cmd: &str, vh_win: CicodeWindow, mode: u32) -> Result<String> {
+        self.cicode_future(cmd, vh_win, mode)?.await
     }
 
     async fn tag_read_tokio(&self, tag: &str) -> Result<String> {
@@ -229,10 +238,7 @@ impl TokioCtClient for Arc<CtClient> {
     }
 
     async fn tag_write_tokio(&self, tag: &str, value: &str) -> Result<()> {
-        let client = Arc::clone(self);
-        let tag = tag.to_string();
-        let value = value.to_string();
-        spawn_blocking_result(move || client.tag_write_str(&tag, &value)).await
+        self.tag_write_future(tag, value)?.await.map(|_| ())
     }
 }
 
@@ -248,22 +254,22 @@ impl TokioCtClient for Arc<CtClient> {
 /// [`CtList`] is `Send + Sync` and can be safely shared across threads via
 /// `Arc<CtList>`.  Two implementations are provided:
 ///
-/// - **`impl TokioCtList for CtList`** — uses Windows OVERLAPPED I/O with
-///   polling; best for single-task usage where the list is owned by one async
-///   context.
+/// - **`impl TokioCtList for CtList`** — uses Windows OVERLAPPED I/O and is
+///   cancelled when the future is dropped; best for single-task usage where
+///   the list is owned by one async context.
 /// - **`impl TokioCtList for Arc<CtList>`** — offloads the blocking call to
 ///   Tokio's blocking-thread pool via [`tokio::task::spawn_blocking`]; best
 ///   when the same list is shared across multiple Tokio tasks.
 ///
 /// # Examples
 ///
-/// ```no_run
+/// ```
 /// use ctapi_rs::{CtClient, TokioCtList};
 /// use std::sync::Arc;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let client = Arc::new(CtClient::open(None, None, None, 0)?);
+///     let client = Arc::new(CtClient::open_mock()?);
 ///
 ///     // Single-task usage (OVERLAPPED I/O, no extra thread)
 ///     let list = Arc::clone(&client).list_new(0)?;
@@ -300,10 +306,9 @@ pub trait TokioCtList {
 
 /// OVERLAPPED-based implementation for owned/borrowed `CtList`.
 ///
-/// Uses Windows OVERLAPPED I/O with event-driven wake via the OVERLAPPED
-/// event handle. A single Tokio blocking thread waits on the event and
-/// returns as soon as the operation completes — no polling latency.
-/// Suitable for single-task contexts.
+/// Drives the OVERLAPPED operation through a [`CtApiFuture`], so no
+/// blocking-pool thread is held while waiting and dropping the future cancels
+/// the operation with `ctCancelIO`. Suitable for single-task contexts.
 impl TokioCtList for CtList {
     async fn read_tokio(&self) -> Result<()> {
         // Box the AsyncOperation before starting so the OVERLAPPED struct
@@ -311,40 +316,16 @@ impl TokioCtList for CtList {
         // and writes completion data there — moving `op` after read_async
         // would leave CtAPI with a dangling pointer.
         let mut op = Box::new(AsyncOperation::new());
-        self.read_async(&mut op)
-            .map_err(|e| crate::error::CtApiError::Other {
-                code: 0,
-                message: e.to_string(),
-            })?;
-
-        tokio::task::spawn_blocking(move || {
-            // SAFETY: op owns the WinEvent handle. WaitForSingleObject with
-            // INFINITE blocks until the OVERLAPPED operation signals the event.
-            unsafe { WaitForSingleObject(op.win_event_handle(), u32::MAX) };
-        })
-        .await
-        .map_err(|e| crate::error::CtApiError::Other {
-            code: 0,
-            message: e.to_string(),
-        })
+        let read = self.start_read(&mut op)?;
+        CtApiFuture::from_boxed(self.client(), op).await?;
+        self.complete_read(read);
+        Ok(())
     }
 
     async fn write_tag_tokio(&self, tag: &str, value: &str) -> Result<()> {
         let mut op = Box::new(AsyncOperation::new());
-        self.write_tag_async(tag, value, &mut op)
-            .map_err(|e| crate::error::CtApiError::Other {
-                code: 0,
-                message: e.to_string(),
-            })?;
-
-        tokio::task::spawn_blocking(move || {
-            unsafe { WaitForSingleObject(op.win_event_handle(), u32::MAX) };
-        })
-        .await
-        .map_err(|e| crate::error::CtApiError::Other {
-            code: 0,
-            message: e.to_string(),
-        })
+        self.write_tag_async(tag, value, &mut op)?;
+        CtApiFuture::from_boxed(self.client(), op).await.map(|_| ())
     }
 }
 
@@ -386,12 +367,42 @@ impl TokioCtList for Arc<CtList> {
 
 /// Run `f` on Tokio's blocking thread pool and map a `JoinError` into
 /// [`CtApiError::Other`].
+/// Run blocking CtAPI calls inside a [`tokio::task::spawn_blocking`] closure
+///
+/// In debug builds the synchronous methods report calls made on a Tokio
+/// runtime thread (see the `CTAPI_RS_STRICT_ASYNC` variable). Blocking-pool
+/// threads cannot be told apart from runtime workers, so closures passed to
+/// `spawn_blocking` mark their calls as intended with this function. It
+/// simply calls `f` in release builds.
+///
+/// # Examples
+///
+/// ```
+/// use ctapi_rs::CtClient;
+/// use ctapi_rs::blocking_section;
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let client = Arc::new(CtClient::open_mock()?);
+///     let names = tokio::task::spawn_blocking(move || {
+///         blocking_section(|| client.find_first("Tag", "", None).count())
+///     })
+///     .await?;
+///     assert!(names > 0);
+///     Ok(())
+/// }
+/// ```
+pub fn blocking_section<R>(f: impl FnOnce() -> R) -> R {
+    crate::blocking::allow(f)
+}
+
 async fn spawn_blocking_result<F, T>(f: F) -> Result<T>
 where
     F: FnOnce() -> Result<T> + Send + 'static,
     T: Send + 'static,
 {
-    tokio::task::spawn_blocking(f)
+    tokio::task::spawn_blocking(|| blocking_section(f))
         .await
         .map_err(|e| crate::error::CtApiError::Other {
             code: 0,
@@ -407,6 +418,15 @@ where
 mod tests {
     use super::*;
 
+    /// Client connected with the `CTAPI_*` environment variables
+    fn open_live() -> CtClient {
+        let credentials = crate::EnvCredentials::load().unwrap();
+        CtClient::builder()
+            .credentials(credentials)
+            .build()
+            .unwrap()
+    }
+
     /// Ensure the module compiles and basic trait bounds are satisfied.
     #[tokio::test]
     async fn test_tokio_integration_compiles() {
@@ -417,13 +437,33 @@ mod tests {
         assert_send::<crate::CtApiFuture>();
     }
 
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_tag_write_tokio_passes_values_through() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::new()
+            .with_tag("Counter", "0")
+            .with_tag("Pump_Start", "0")
+            .with_tag("BatchID", "");
+        let client = Arc::new(CtClient::open_mock_with(server).unwrap());
+
+        for (tag, value) in [
+            ("Counter", "42"),
+            ("Pump_Start", "1"),
+            ("BatchID", "LOT-2024-001"),
+        ] {
+            client.tag_write_tokio(tag, value).await.unwrap();
+            assert_eq!(client.tag_read_tokio(tag).await.unwrap(), value);
+            (*client).tag_write_tokio(tag, value).await.unwrap();
+        }
+    }
+
     /// Verify that TokioCtClient is object-safe enough to use via trait references.
     #[tokio::test]
     #[ignore = "Requires actual Citect SCADA connection"]
     async fn test_arc_client_trait() {
-        let client = Arc::new(
-            CtClient::open(Some("127.0.0.1"), Some("Engineer"), Some("Citect"), 0).unwrap(),
-        );
+        let client = Arc::new(open_live());
 
         // Both Arc<CtClient> and CtClient impl TokioCtClient
         let _v1 = client.tag_read_tokio("BIT_1").await.unwrap();
@@ -433,9 +473,7 @@ mod tests {
     #[tokio::test]
     #[ignore = "Requires actual Citect SCADA connection"]
     async fn test_concurrent_reads() {
-        let client = Arc::new(
-            CtClient::open(Some("127.0.0.1"), Some("Engineer"), Some("Citect"), 0).unwrap(),
-        );
+        let client = Arc::new(open_live());
 
         let tags = ["BIT_1", "BIT_2", "BIT_3"];
         let mut handles = vec![];
@@ -455,23 +493,47 @@ mod tests {
     #[tokio::test]
     #[ignore = "Requires actual Citect SCADA connection"]
     async fn test_tag_read_ex_tokio() {
-        let client =
-            CtClient::open(Some("127.0.0.1"), Some("Engineer"), Some("Citect"), 0).unwrap();
+        let client = open_live();
         let (value, meta) = client.tag_read_ex_tokio("BIT_1").await.unwrap();
         println!("value={} quality={}", value, meta.quality_general);
     }
 
+    #[tokio::test]
+    #[ignore = "Requires actual Citect SCADA connection"]
+    async fn test_cicode_tokio_timeout_cancels() {
+        let client = Arc::new(open_live());
+
+        // Sleep(30) would hold the call for 30 s; the timeout drops the future,
+        // which must cancel the operation rather than wait for it.
+        let start = std::time::Instant::now();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            client.cicode_tokio("Sleep(30)", CicodeWindow::NONE, 0),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+
+        // The connection is still usable after the cancellation.
+        client
+            .cicode_tokio("Time(1)", CicodeWindow::NONE, 0)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     #[ignore = "Requires actual Citect SCADA connection"]
     async fn test_future_client_with_tokio() {
         use crate::FutureCtClient;
 
-        let client =
-            CtClient::open(Some("127.0.0.1"), Some("Engineer"), Some("Citect"), 0).unwrap();
+        let client = open_live();
 
         // FutureCtClient uses OVERLAPPED — compare result with spawn_blocking approach.
-        let future_result = client.cicode_future("Time(1)", 0, 0).unwrap().await;
-        let blocking_result = client.cicode_tokio("Time(1)", 0, 0).await;
+        let future_result = client
+            .cicode_future("Time(1)", CicodeWindow::NONE, 0)
+            .unwrap()
+            .await;
+        let blocking_result = client.cicode_tokio("Time(1)", CicodeWindow::NONE, 0).await;
 
         println!("future:   {:?}", future_result);
         println!("blocking: {:?}", blocking_result);
@@ -0,0 +1,492 @@
+//! Rollover-corrected totals of counter tags
+//!
+//! Totalizer and pulse counter tags count up to a device-specific limit and
+//! wrap around to zero. A [`Totalizer`] follows the successive readings of
+//! such a tag and accumulates the increments between them, so that totals,
+//! [deltas](Totalizer::delta_since) and [rates](Totalizer::rate) stay correct
+//! across rollovers.
+//!
+//! A reading below the previous one is a rollover if the increment it implies
+//! (up to the limit and on from zero) is plausible, that is at most
+//! [`max_step`](Totalizer::max_step). Otherwise the counter was reset, for
+//! example by a device restart, and a [`TotalizerEvent::Reset`] is reported
+//! instead of a huge increment; [`ResetHandling`] decides what is counted.
+//!
+//! Readings come from explicit [`update`](Totalizer::update) calls or from a
+//! [`TagWatcher`](crate::TagWatcher), through its
+//! [change events](crate::TagWatcher::change_events) or
+//! [latest reading](crate::TagWatcher::latest). Readings that are not of good
+//! quality or not numeric are skipped; the increment over such a gap is
+//! counted at the next good reading. Rollovers cannot be told apart within a
+//! gap, so a counter that may wrap more than once between two good readings
+//! needs a faster poll.
+//!
+//! ```
+//! use ctapi_rs::totalizer::{Totalizer, TotalizerEvent};
+//! use std::time::{Duration, Instant};
+//!
+//! // 16-bit pulse counter: 65535 is followed by 0
+//! let mut flow = Totalizer::new("FT101_Pulses", 65536.0);
+//! let start = Instant::now();
+//! flow.update(65000.0, start);
+//! let event = flow.update(500.0, start + Duration::from_secs(10));
+//! assert!(matches!(event, Some(TotalizerEvent::Rollover { .. })));
+//! assert_eq!(flow.total(), 1036.0);
+//! assert_eq!(flow.rate(), Some(103.6));
+//! ```
+
+use crate::quality::QUAL_GOOD;
+use crate::watcher::{TagChange, TagWatcher, TimedValue};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Readings a new totalizer keeps for [`Totalizer::delta_since`]
+pub const DEFAULT_TOTALIZER_HISTORY: usize = 1024;
+
+/// What a [`Totalizer`] counts when the counter was reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResetHandling {
+    /// Assume the counter restarted from zero and count its new value
+    #[default]
+    CountFromZero,
+    /// Count nothing for the reading after the reset
+    Skip,
+}
+
+/// Discontinuity of the counter detected by [`Totalizer::update`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TotalizerEvent {
+    /// The counter wrapped around its limit
+    Rollover {
+        /// Reading before the rollover
+        previous: f64,
+        /// Reading after the rollover
+        current: f64,
+    },
+    /// The counter dropped without a plausible rollover
+    Reset {
+        /// Reading before the reset
+        previous: f64,
+        /// Reading after the reset
+        current: f64,
+    },
+}
+
+/// Rollover-corrected total of a counter tag, see the [module docs](self)
+#[derive(Debug, Clone)]
+pub struct Totalizer {
+    tag: String,
+    rollover_at: f64,
+    max_step: f64,
+    reset_handling: ResetHandling,
+    capacity: usize,
+    /// Last good reading
+    last: Option<(Instant, f64)>,
+    total: f64,
+    /// Time and total after each counted reading, oldest first
+    samples: VecDeque<(Instant, f64)>,
+    /// Whether readings were dropped from `samples`
+    trimmed: bool,
+    rollovers: u64,
+    resets: u64,
+}
+
+impl Totalizer {
+    /// Track `tag`, a counter that wraps to zero on reaching `rollover_at`
+    ///
+    /// `rollover_at` is the number of distinct counter values: 65536 for a
+    /// 16-bit counter whose last value is 65535, 1e6 for a counter whose
+    /// last value is 999999. Increments of up to half of it between two
+    /// readings are taken as plausible.
+    pub fn new(tag: &str, rollover_at: f64) -> Self {
+        Self {
+            tag: tag.to_string(),
+            rollover_at,
+            max_step: rollover_at / 2.0,
+            reset_handling: ResetHandling::default(),
+            capacity: DEFAULT_TOTALIZER_HISTORY,
+            last: None,
+            total: 0.0,
+            samples: VecDeque::new(),
+            trimmed: false,
+            rollovers: 0,
+            resets: 0,
+        }
+    }
+
+    /// Largest increment between two readings still taken as a rollover
+    pub fn max_step(mut self, max_step: f64) -> Self {
+        self.max_step = max_step;
+        self
+    }
+
+    /// What to count when the counter was reset
+    pub fn reset_handling(mut self, handling: ResetHandling) -> Self {
+        self.reset_handling = handling;
+        self
+    }
+
+    /// Number of readings kept for [`delta_since`](Self::delta_since), at least 2
+    pub fn history(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(2);
+        self
+    }
+
+    /// Tag tracked
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Account for a good reading of `value` taken at `timestamp`
+    ///
+    /// Readings not later than the previous one are ignored, as are values
+    /// that are not finite.
+    pub fn update(&mut self, value: f64, timestamp: Instant) -> Option<TotalizerEvent> {
+        if !value.is_finite() {
+            return None;
+        }
+        let Some((last_time, previous)) = self.last else {
+            self.last = Some((timestamp, value));
+            self.push(timestamp);
+            return None;
+        };
+        if timestamp <= last_time {
+            return None;
+        }
+        self.last = Some((timestamp, value));
+
+        let (increment, event) = if value >= previous {
+            (value - previous, None)
+        } else {
+            let wrapped = self.rollover_at - previous + value;
+            if wrapped > 0.0 && wrapped <= self.max_step {
+                self.rollovers += 1;
+                (
+                    wrapped,
+                    Some(TotalizerEvent::Rollover {
+                        previous,
+                        current: value,
+                    }),
+                )
+            } else {
+                self.resets += 1;
+                let increment = match self.reset_handling {
+                    ResetHandling::CountFromZero => value.max(0.0),
+                    ResetHandling::Skip => 0.0,
+                };
+                (
+                    increment,
+                    Some(TotalizerEvent::Reset {
+                        previous,
+                        current: value,
+                    }),
+                )
+            }
+        };
+        self.total += increment;
+        self.push(timestamp);
+        event
+    }
+
+    /// Account for a watcher reading, skipping bad quality and non-numeric values
+    pub fn update_reading(&mut self, reading: &TimedValue) -> Option<TotalizerEvent> {
+        if reading.quality != QUAL_GOOD {
+            return None;
+        }
+        self.update(reading.numeric()?, reading.timestamp)
+    }
+
+    /// Account for a change event of the tracked tag, ignoring other tags
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::totalizer::Totalizer;
+    /// use ctapi_rs::{CtClient, TagWatcher};
+    /// use std::sync::Arc;
+    ///
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let watcher = TagWatcher::new(Arc::new(client.list_new(0)?));
+    /// watcher.watch("Counter")?;
+    /// let changes = watcher.change_events();
+    /// let mut total = Totalizer::new("Counter", 65536.0);
+    ///
+    /// watcher.poll()?;
+    /// for change in changes.try_iter() {
+    ///     total.observe(&change);
+    /// }
+    /// assert_eq!(total.total(), 0.0);
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn observe(&mut self, change: &TagChange) -> Option<TotalizerEvent> {
+        if change.tag != self.tag {
+            return None;
+        }
+        self.update_reading(&change.current)
+    }
+
+    /// Account for the latest reading of the tracked tag in `watcher`
+    ///
+    /// A reading already accounted for is not counted again, so this can be
+    /// called after every poll.
+    pub fn update_from(&mut self, watcher: &TagWatcher) -> Option<TotalizerEvent> {
+        self.update_reading(&watcher.latest(&self.tag)?)
+    }
+
+    fn push(&mut self, timestamp: Instant) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+            self.trimmed = true;
+        }
+        self.samples.push_back((timestamp, self.total));
+    }
+
+    /// Increments counted since the first reading
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// Increments counted after `since`
+    ///
+    /// Measured from the last reading at or before `since`, or from the first
+    /// reading if `since` precedes it. `None` if that reading is no longer in
+    /// the [history](Self::history) or there is no reading yet.
+    pub fn delta_since(&self, since: Instant) -> Option<f64> {
+        let (first_time, _) = *self.samples.front()?;
+        if since < first_time {
+            return (!self.trimmed).then_some(self.total);
+        }
+        let at = self.samples.partition_point(|&(time, _)| time <= since);
+        let (_, total_then) = self.samples[at - 1];
+        Some(self.total - total_then)
+    }
+
+    /// Increments per second between the last two good readings
+    pub fn rate(&self) -> Option<f64> {
+        let mut recent = self.samples.iter().rev();
+        let (time, total) = *recent.next()?;
+        let (previous_time, previous_total) = *recent.next()?;
+        let seconds = (time - previous_time).as_secs_f64();
+        Some((total - previous_total) / seconds)
+    }
+
+    /// Last good reading and when it was taken
+    pub fn last(&self) -> Option<(Instant, f64)> {
+        self.last
+    }
+
+    /// Number of rollovers detected
+    pub fn rollovers(&self) -> u64 {
+        self.rollovers
+    }
+
+    /// Number of counter resets detected
+    pub fn resets(&self) -> u64 {
+        self.resets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct Feed {
+        start: Instant,
+        totalizer: Totalizer,
+    }
+
+    impl Feed {
+        fn new(totalizer: Totalizer) -> Self {
+            Self {
+                start: Instant::now(),
+                totalizer,
+            }
+        }
+
+        fn at(&self, secs: u64) -> Instant {
+            self.start + Duration::from_secs(secs)
+        }
+
+        fn update(&mut self, secs: u64, value: f64) -> Option<TotalizerEvent> {
+            let at = self.at(secs);
+            self.totalizer.update(value, at)
+        }
+
+        fn reading(&mut self, secs: u64, value: &str, quality: u8) -> Option<TotalizerEvent> {
+            let reading = TimedValue {
+                value: value.to_string(),
+                timestamp: self.at(secs),
+                quality,
+                datasource_error: None,
+            };
+            self.totalizer.update_reading(&reading)
+        }
+    }
+
+    #[test]
+    fn test_counts_increments() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 65536.0));
+        assert_eq!(feed.totalizer.total(), 0.0);
+        assert_eq!(feed.totalizer.rate(), None);
+        assert_eq!(feed.update(0, 100.0), None);
+        assert_eq!(feed.update(10, 150.0), None);
+        assert_eq!(feed.update(20, 150.0), None);
+        assert_eq!(feed.totalizer.total(), 50.0);
+        assert_eq!(feed.totalizer.rate(), Some(0.0));
+        assert_eq!(feed.totalizer.last(), Some((feed.at(20), 150.0)));
+    }
+
+    #[test]
+    fn test_rollover() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 65536.0));
+        feed.update(0, 65530.0);
+        let event = feed.update(1, 4.0);
+        assert_eq!(
+            event,
+            Some(TotalizerEvent::Rollover {
+                previous: 65530.0,
+                current: 4.0
+            })
+        );
+        assert_eq!(feed.totalizer.total(), 10.0);
+        // Rolling over again keeps accumulating
+        feed.update(2, 65000.0);
+        feed.update(3, 1000.0);
+        assert_eq!(feed.totalizer.total(), 10.0 + 64996.0 + 1536.0);
+        assert_eq!(
+            (feed.totalizer.rollovers(), feed.totalizer.resets()),
+            (2, 0)
+        );
+    }
+
+    #[test]
+    fn test_rollover_to_exactly_zero() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 1e6));
+        feed.update(0, 999_999.0);
+        assert!(matches!(
+            feed.update(1, 0.0),
+            Some(TotalizerEvent::Rollover { .. })
+        ));
+        assert_eq!(feed.totalizer.total(), 1.0);
+    }
+
+    #[test]
+    fn test_reset_is_not_a_rollover() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 65536.0));
+        feed.update(0, 20000.0);
+        feed.update(1, 20100.0);
+        // Wrapping would mean an increment of 45536, more than half the range
+        let event = feed.update(2, 100.0);
+        assert_eq!(
+            event,
+            Some(TotalizerEvent::Reset {
+                previous: 20100.0,
+                current: 100.0
+            })
+        );
+        assert_eq!(feed.totalizer.total(), 200.0);
+        assert_eq!(feed.totalizer.resets(), 1);
+        feed.update(3, 130.0);
+        assert_eq!(feed.totalizer.total(), 230.0);
+    }
+
+    #[test]
+    fn test_reset_skipped() {
+        let totalizer = Totalizer::new("FT101", 65536.0).reset_handling(ResetHandling::Skip);
+        let mut feed = Feed::new(totalizer);
+        feed.update(0, 20000.0);
+        assert!(matches!(
+            feed.update(1, 100.0),
+            Some(TotalizerEvent::Reset { .. })
+        ));
+        assert_eq!(feed.totalizer.total(), 0.0);
+        feed.update(2, 150.0);
+        assert_eq!(feed.totalizer.total(), 50.0);
+    }
+
+    #[test]
+    fn test_max_step() {
+        let totalizer = Totalizer::new("FT101", 65536.0).max_step(1000.0);
+        let mut feed = Feed::new(totalizer);
+        feed.update(0, 65000.0);
+        assert!(matches!(
+            feed.update(1, 400.0),
+            Some(TotalizerEvent::Rollover { .. })
+        ));
+        feed.update(2, 65000.0);
+        assert!(matches!(
+            feed.update(3, 600.0),
+            Some(TotalizerEvent::Reset { .. })
+        ));
+    }
+
+    #[test]
+    fn test_bad_quality_gap() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 65536.0));
+        feed.reading(0, "65500", QUAL_GOOD);
+        // Bad and non-numeric readings are excluded ...
+        assert_eq!(feed.reading(10, "0", crate::quality::QUAL_BAD), None);
+        assert_eq!(feed.reading(20, "#COM", QUAL_GOOD), None);
+        assert_eq!(feed.totalizer.total(), 0.0);
+        // ... and the increment over the gap is counted at the next good one
+        assert!(matches!(
+            feed.reading(30, "64", QUAL_GOOD),
+            Some(TotalizerEvent::Rollover { .. })
+        ));
+        assert_eq!(feed.totalizer.total(), 100.0);
+        assert_eq!(feed.totalizer.rate(), Some(100.0 / 30.0));
+    }
+
+    #[test]
+    fn test_stale_and_invalid_readings_ignored() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 65536.0));
+        feed.update(10, 100.0);
+        assert_eq!(feed.update(10, 50.0), None);
+        assert_eq!(feed.update(5, 50.0), None);
+        assert_eq!(feed.update(20, f64::NAN), None);
+        feed.update(20, 110.0);
+        assert_eq!(feed.totalizer.total(), 10.0);
+        assert_eq!(feed.totalizer.resets(), 0);
+    }
+
+    #[test]
+    fn test_delta_since() {
+        let mut feed = Feed::new(Totalizer::new("FT101", 1000.0).history(3));
+        assert_eq!(feed.totalizer.delta_since(feed.at(0)), None);
+        feed.update(10, 0.0);
+        feed.update(20, 300.0);
+        feed.update(30, 700.0);
+        assert_eq!(feed.totalizer.delta_since(feed.at(0)), Some(700.0));
+        assert_eq!(feed.totalizer.delta_since(feed.at(20)), Some(400.0));
+        assert_eq!(feed.totalizer.delta_since(feed.at(25)), Some(400.0));
+        assert_eq!(feed.totalizer.delta_since(feed.at(30)), Some(0.0));
+
+        feed.update(40, 100.0);
+        assert_eq!(feed.totalizer.total(), 1100.0);
+        assert_eq!(feed.totalizer.delta_since(feed.at(20)), Some(800.0));
+        // The reading at 10 s has left the history
+        assert_eq!(feed.totalizer.delta_since(feed.at(0)), None);
+        assert_eq!(feed.totalizer.delta_since(feed.at(15)), None);
+    }
+
+    #[test]
+    fn test_observe_filters_tag() {
+        let mut totalizer = Totalizer::new("FT101", 65536.0);
+        let start = Instant::now();
+        let change = |tag: &str, value: &str, secs| TagChange {
+            tag: tag.to_string(),
+            previous: None,
+            current: TimedValue {
+                value: value.to_string(),
+                timestamp: start + Duration::from_secs(secs),
+                quality: QUAL_GOOD,
+                datasource_error: None,
+            },
+            at: std::time::SystemTime::now(),
+        };
+        totalizer.observe(&change("FT101", "10", 0));
+        totalizer.observe(&change("FT102", "500", 1));
+        totalizer.observe(&change("FT101", "15", 2));
+        assert_eq!(totalizer.total(), 5.0);
+    }
+}
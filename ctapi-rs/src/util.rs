@@ -1,11 +1,143 @@
 //! Internal utilities shared across modules.
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::str::FromStr;
 
 use encoding_rs::GBK;
 
+use crate::error::{CtApiError, Result};
+
 /// Encode a Rust string as a GBK-encoded, null-terminated C string.
 pub(crate) fn encode_to_gbk_cstring(s: &str) -> std::result::Result<CString, std::ffi::NulError> {
     let (encoded, _, _) = GBK.encode(s);
     CString::new(encoded)
 }
+
+/// Encode a Rust string as GBK, failing if any character has no GBK mapping.
+///
+/// `encode_to_gbk_cstring` silently substitutes unmappable characters, which is
+/// acceptable for lookups but not for text that ends up in an audit record.
+pub(crate) fn encode_to_gbk_strict(s: &str) -> Result<Vec<u8>> {
+    let (encoded, _, had_errors) = GBK.encode(s);
+    if had_errors {
+        return Err(CtApiError::Encoding {
+            text: s.to_string(),
+        });
+    }
+    Ok(encoded.into_owned())
+}
+
+/// View a C `char` buffer as bytes.
+///
+/// This is the only place in the crate that reinterprets `i8` buffers filled
+/// by CtAPI; every decoding site goes through it.
+pub(crate) fn as_bytes(buffer: &[i8]) -> &[u8] {
+    // SAFETY: i8 and u8 have identical size and alignment and every bit
+    // pattern is valid for both. The pointer and length come from a live
+    // slice, and the returned slice borrows it for the same lifetime.
+    unsafe { std::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), buffer.len()) }
+}
+
+/// Length of the NUL-terminated string at the start of `buffer`, or of the
+/// whole buffer if it contains no NUL.
+pub(crate) fn nul_terminated_len(buffer: &[i8]) -> usize {
+    buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len())
+}
+
+/// Decode the NUL-terminated GBK string at the start of `bytes`.
+///
+/// Fails with [`CtApiError::FromBytesUntilNul`] if `bytes` contains no NUL.
+/// A NUL at the start is an empty string, not an error: a STRING tag or
+/// Cicode function can legitimately return `""`.
+pub(crate) fn decode_gbk_until_nul(bytes: &[u8]) -> Result<String> {
+    let cstr = CStr::from_bytes_until_nul(bytes)?;
+    Ok(GBK.decode(cstr.to_bytes()).0.into_owned())
+}
+
+/// Parse the value `raw` read from `tag` as a `T`.
+///
+/// Surrounding whitespace and padding are ignored. Digital tags read as
+/// `"0"`/`"1"`; when `T` does not accept those, they are retried as
+/// `"false"`/`"true"` so that they parse as `bool`.
+pub(crate) fn parse_value<T: FromStr>(tag: &str, raw: String) -> Result<T> {
+    let text = raw.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    if let Ok(value) = text.parse() {
+        return Ok(value);
+    }
+    let digital = match text {
+        "0" => "false".parse().ok(),
+        "1" => "true".parse().ok(),
+        _ => None,
+    };
+    digital.ok_or_else(|| CtApiError::ParseError {
+        tag: tag.to_string(),
+        raw,
+        target_type: std::any::type_name::<T>().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_bytes() {
+        assert!(as_bytes(&[]).is_empty());
+        assert_eq!(as_bytes(&[0x41, -1, 0]), [0x41, 0xff, 0]);
+    }
+
+    #[test]
+    fn test_nul_terminated_len() {
+        assert_eq!(nul_terminated_len(&[]), 0);
+        assert_eq!(nul_terminated_len(&[0x41, 0x42, 0, 0x43]), 2);
+        assert_eq!(nul_terminated_len(&[0x41, -1]), 2);
+    }
+
+    #[test]
+    fn test_decode_gbk_until_nul() {
+        assert_eq!(decode_gbk_until_nul(b"abc\0").unwrap(), "abc");
+        assert_eq!(decode_gbk_until_nul(b"\0").unwrap(), "");
+
+        let mut bytes = GBK.encode("温度").0.into_owned();
+        bytes.push(0);
+        assert_eq!(decode_gbk_until_nul(&bytes).unwrap(), "温度");
+    }
+
+    #[test]
+    fn test_decode_empty_and_unterminated() {
+        assert!(matches!(
+            decode_gbk_until_nul(&[]),
+            Err(CtApiError::FromBytesUntilNul(_))
+        ));
+        assert!(matches!(
+            decode_gbk_until_nul(b"abc"),
+            Err(CtApiError::FromBytesUntilNul(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_stops_at_interior_nul() {
+        assert_eq!(decode_gbk_until_nul(b"ab\0cd\0").unwrap(), "ab");
+        let buffer = [0x68i8, 0x69, 0, 0x78, 0];
+        assert_eq!(decode_gbk_until_nul(as_bytes(&buffer)).unwrap(), "hi");
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let counter: i32 = parse_value("Counter", " 42 \0".into()).unwrap();
+        assert_eq!(counter, 42);
+        let level: f64 = parse_value("Level", "12.5  ".into()).unwrap();
+        assert_eq!(level, 12.5);
+        assert!(parse_value::<bool>("Pump", "1".into()).unwrap());
+        assert!(!parse_value::<bool>("Pump", "0 ".into()).unwrap());
+        assert!(parse_value::<bool>("Pump", "true".into()).unwrap());
+        assert_eq!(parse_value::<u8>("Mode", "1".into()).unwrap(), 1);
+
+        let err = parse_value::<f64>("Level", "#COM ".into()).unwrap_err();
+        assert!(matches!(
+            err,
+            CtApiError::ParseError { ref tag, ref raw, ref target_type }
+                if tag == "Level" && raw == "#COM " && target_type == "f64"
+        ));
+    }
+}
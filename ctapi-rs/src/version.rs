@@ -0,0 +1,262 @@
+//! Client/server version checks
+//!
+//! A local CtAPI.dll much older than the Citect SCADA server it talks to
+//! makes some calls fail in obscure ways. [`CtClient::check_versions`]
+//! compares the file version of the loaded CtAPI.dll with the version
+//! reported by the server's Cicode `Version()` function and keeps both in a
+//! [`VersionInfo`], which is also handy for support bundles.
+//!
+//! [`CtClient::check_versions`]: crate::CtClient::check_versions
+
+use crate::error::{CtApiError, Result};
+use std::fmt;
+
+/// Cicode command returning the server version
+pub(crate) const SERVER_VERSION_COMMAND: &str = "Version(0)";
+
+/// First server version whose tag quality and timestamp elements can be
+/// written, used by [`CtClient::tag_write_full`](crate::CtClient::tag_write_full)
+pub const QUALITY_WRITE_VERSION: CitectVersion = CitectVersion {
+    major: 7,
+    minor: 50,
+    patch: 0,
+    build: 0,
+};
+
+/// A dotted Citect SCADA version number such as `8.20.0.123`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CitectVersion {
+    /// Major version
+    pub major: u32,
+    /// Minor version
+    pub minor: u32,
+    /// Patch level
+    pub patch: u32,
+    /// Build number
+    pub build: u32,
+}
+
+impl CitectVersion {
+    /// Extract a version number from text such as `8.20.0.123`, `v7.40` or
+    /// `Citect SCADA 2018 R2 (8.20)`
+    ///
+    /// The first dotted number wins; a lone integer is only used when the
+    /// text has no dotted number, so product years do not shadow the real
+    /// version.
+    pub fn parse(text: &str) -> Option<Self> {
+        let tokens = || {
+            text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .map(|token| token.trim_matches('.'))
+                .filter(|token| !token.is_empty())
+        };
+        let token = tokens()
+            .find(|token| token.contains('.'))
+            .or_else(|| tokens().next())?;
+
+        let mut parts = token.split('.').map(|part| part.parse::<u32>().ok());
+        let major = parts.next().flatten()?;
+        let mut next = || parts.next().flatten().unwrap_or(0);
+        Some(Self {
+            major,
+            minor: next(),
+            patch: next(),
+            build: next(),
+        })
+    }
+}
+
+/// The parts as a struct
+#[cfg(feature = "serde")]
+impl serde::Serialize for CitectVersion {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut version = serializer.serialize_struct("CitectVersion", 4)?;
+        version.serialize_field("major", &self.major)?;
+        version.serialize_field("minor", &self.minor)?;
+        version.serialize_field("patch", &self.patch)?;
+        version.serialize_field("build", &self.build)?;
+        version.end()
+    }
+}
+
+impl fmt::Display for CitectVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.patch, self.build
+        )
+    }
+}
+
+/// Versions of the local CtAPI.dll and of the connected server
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VersionInfo {
+    /// File version of the loaded CtAPI.dll, if it could be read
+    pub client: Option<String>,
+    /// Result of the server's Cicode `Version()` call, if it succeeded
+    pub server: Option<String>,
+}
+
+impl VersionInfo {
+    /// Parsed client version
+    pub fn client_version(&self) -> Option<CitectVersion> {
+        self.client.as_deref().and_then(CitectVersion::parse)
+    }
+
+    /// Parsed server version
+    pub fn server_version(&self) -> Option<CitectVersion> {
+        self.server.as_deref().and_then(CitectVersion::parse)
+    }
+
+    /// Whether both versions are known and their major versions differ
+    pub fn is_skewed(&self) -> bool {
+        match (self.client_version(), self.server_version()) {
+            (Some(client), Some(server)) => client.major != server.major,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "client {}, server {}",
+            self.client.as_deref().unwrap_or("unknown"),
+            self.server.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+/// Fail with [`CtApiError::VersionSkew`] if `strict` and the versions are skewed
+pub(crate) fn enforce(info: &VersionInfo, strict: bool) -> Result<()> {
+    if strict && info.is_skewed() {
+        return Err(CtApiError::VersionSkew {
+            client: info.client.clone().unwrap_or_default(),
+            server: info.server.clone().unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+/// File version of the CtAPI.dll loaded into this process
+pub(crate) fn local_dll_version() -> Option<String> {
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileVersionInfoSizeW, GetFileVersionInfoW, VS_FIXEDFILEINFO, VerQueryValueW,
+    };
+    use windows_sys::Win32::System::LibraryLoader::{GetModuleFileNameW, GetModuleHandleA};
+
+    // SAFETY: the module name is a NUL-terminated literal. The returned handle
+    // is not owned and needs no cleanup.
+    let module = unsafe { GetModuleHandleA(c"CtApi.dll".as_ptr().cast()) };
+    if module.is_null() {
+        return None;
+    }
+
+    let mut path = [0u16; 1024];
+    // SAFETY: path is a writable buffer of the length passed.
+    let len = unsafe { GetModuleFileNameW(module, path.as_mut_ptr(), path.len() as u32) };
+    if len == 0 || len as usize >= path.len() {
+        return None;
+    }
+
+    let mut ignored = 0;
+    // SAFETY: path is NUL-terminated by GetModuleFileNameW.
+    let size = unsafe { GetFileVersionInfoSizeW(path.as_ptr(), &mut ignored) };
+    if size == 0 {
+        return None;
+    }
+    let mut data = vec![0u8; size as usize];
+    // SAFETY: data is a writable buffer of `size` bytes.
+    if unsafe { GetFileVersionInfoW(path.as_ptr(), 0, size, data.as_mut_ptr().cast()) } == 0 {
+        return None;
+    }
+
+    let root = [u16::from(b'\\'), 0];
+    let mut info: *mut std::ffi::c_void = std::ptr::null_mut();
+    let mut info_len = 0;
+    // SAFETY: data holds the version resource; info receives a pointer into it.
+    let found = unsafe {
+        VerQueryValueW(
+            data.as_ptr().cast(),
+            root.as_ptr(),
+            &mut info,
+            &mut info_len,
+        )
+    };
+    if found == 0 || info.is_null() || (info_len as usize) < size_of::<VS_FIXEDFILEINFO>() {
+        return None;
+    }
+    // SAFETY: VerQueryValueW returned a pointer to a VS_FIXEDFILEINFO inside
+    // data, which is still alive. The resource data is not guaranteed to be
+    // aligned, so read it unaligned.
+    let fixed = unsafe { info.cast::<VS_FIXEDFILEINFO>().read_unaligned() };
+    Some(format!(
+        "{}.{}.{}.{}",
+        fixed.dwFileVersionMS >> 16,
+        fixed.dwFileVersionMS & 0xffff,
+        fixed.dwFileVersionLS >> 16,
+        fixed.dwFileVersionLS & 0xffff
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(major: u32, minor: u32, patch: u32, build: u32) -> CitectVersion {
+        CitectVersion {
+            major,
+            minor,
+            patch,
+            build,
+        }
+    }
+
+    #[test]
+    fn test_parse_version_strings() {
+        assert_eq!(CitectVersion::parse("8.20.0.123"), Some(v(8, 20, 0, 123)));
+        assert_eq!(CitectVersion::parse("v7.40"), Some(v(7, 40, 0, 0)));
+        assert_eq!(CitectVersion::parse("7.20 SP4"), Some(v(7, 20, 0, 0)));
+        assert_eq!(
+            CitectVersion::parse("Citect SCADA 2018 R2 (8.20)"),
+            Some(v(8, 20, 0, 0))
+        );
+        assert_eq!(CitectVersion::parse("Version 8"), Some(v(8, 0, 0, 0)));
+        assert_eq!(CitectVersion::parse("unknown"), None);
+        assert_eq!(CitectVersion::parse(""), None);
+        assert_eq!(v(8, 20, 0, 123).to_string(), "8.20.0.123");
+    }
+
+    #[test]
+    fn test_skew_compares_major_versions() {
+        let info = |client: &str, server: &str| VersionInfo {
+            client: Some(client.to_string()),
+            server: Some(server.to_string()),
+        };
+        assert!(!info("8.20.0.100", "8.40").is_skewed());
+        assert!(info("7.40.0.0", "8.20").is_skewed());
+        assert!(info("8.10.0.0", "Plant SCADA 2023 (10.0)").is_skewed());
+
+        let partial = VersionInfo {
+            client: Some("7.40.0.0".to_string()),
+            server: None,
+        };
+        assert!(!partial.is_skewed());
+        assert!(enforce(&partial, true).is_ok());
+
+        let skewed = info("7.40.0.0", "8.20");
+        assert!(enforce(&skewed, false).is_ok());
+        let error = enforce(&skewed, true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[E2004] CtAPI version skew: client 7.40.0.0, server 8.20"
+        );
+        assert_eq!(partial.to_string(), "client 7.40.0.0, server unknown");
+    }
+}
@@ -0,0 +1,875 @@
+//! Polling tag watcher with per-tag value history
+//!
+//! [`TagWatcher`] owns a [`CtList`], reads it on each [`poll`](TagWatcher::poll)
+//! and keeps the latest reading of every watched tag. When history is enabled
+//! the last N readings of each tag are kept in a fixed-size ring buffer, from
+//! which simple rate-of-change and moving-average figures can be computed.
+//!
+//! With a [poll period](TagWatcher::with_poll_period) the watcher also checks
+//! the spacing of consecutive polls. A poll arriving more than 1.5 periods
+//! after the previous one means cycles were skipped (a stalled process or a
+//! reconnect, for example); each such gap is reported as a [`PollGap`] to the
+//! receivers returned by [`TagWatcher::gap_events`] and counted in
+//! [`TagWatcher::stats`], so that consumers such as historians can flag the
+//! missing data.
+//!
+//! Readings whose value or quality differ from the previous one are also
+//! published as [`TagChange`]s to the receivers returned by
+//! [`TagWatcher::change_events`], for consumers that forward deltas only.
+//!
+//! The latest readings are also published once per poll as an immutable
+//! [`WatcherSnapshot`]. [`TagWatcher::latest`] and
+//! [`TagWatcher::latest_all`] read that snapshot without taking a lock, so
+//! they never wait for a poll in progress nor hold the poller up, and always
+//! see the readings of one complete poll.
+
+use crate::clock::{SharedClock, system_clock};
+use crate::constants::CT_LIST_QUALITY_GENERAL;
+use crate::error::Result;
+use crate::list::CtList;
+use crate::quality::{CitectError, QUAL_GOOD};
+use arc_swap::ArcSwap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A single reading of a tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedValue {
+    /// Value as returned by CtAPI
+    pub value: String,
+    /// Time the reading was taken
+    pub timestamp: Instant,
+    /// General quality (`quality_general`) of the reading
+    pub quality: u8,
+    /// Error reported by the I/O driver when the reading is not good
+    pub datasource_error: Option<CitectError>,
+}
+
+impl TimedValue {
+    /// Value parsed as a number, `None` for non-numeric values
+    pub fn numeric(&self) -> Option<f64> {
+        self.value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+    }
+}
+
+/// Latest reading and bounded history of one tag
+#[derive(Debug)]
+struct TagHistory {
+    latest: Option<TimedValue>,
+    samples: VecDeque<TimedValue>,
+    capacity: usize,
+}
+
+impl TagHistory {
+    fn new(capacity: usize) -> Self {
+        Self {
+            latest: None,
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The change from the latest reading to `reading`, if it is one
+    fn change_to(&self, tag: &str, reading: &TimedValue, at: SystemTime) -> Option<TagChange> {
+        let unchanged = self.latest.as_ref().is_some_and(|latest| {
+            latest.value == reading.value && latest.quality == reading.quality
+        });
+        (!unchanged).then(|| TagChange {
+            tag: tag.to_string(),
+            previous: self.latest.clone(),
+            current: reading.clone(),
+            at,
+        })
+    }
+
+    fn record(&mut self, sample: TimedValue) {
+        if self.capacity > 0 {
+            if self.samples.len() == self.capacity {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(sample.clone());
+        }
+        self.latest = Some(sample);
+    }
+
+    /// Numeric samples taken no earlier than `now - window`
+    fn numeric_window(&self, now: Instant, window: Duration) -> Vec<(Instant, f64)> {
+        let since = now.checked_sub(window);
+        self.samples
+            .iter()
+            .filter(|s| since.is_none_or(|since| s.timestamp >= since))
+            .filter_map(|s| s.numeric().map(|v| (s.timestamp, v)))
+            .collect()
+    }
+
+    fn rate_of_change(&self, now: Instant, window: Duration) -> Option<f64> {
+        let samples = self.numeric_window(now, window);
+        let (t0, v0) = *samples.first()?;
+        let (t1, v1) = *samples.last()?;
+        let dt = t1.duration_since(t0).as_secs_f64();
+        if dt > 0.0 { Some((v1 - v0) / dt) } else { None }
+    }
+
+    fn moving_average(&self, now: Instant, window: Duration) -> Option<f64> {
+        let samples = self.numeric_window(now, window);
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().map(|(_, v)| v).sum::<f64>() / samples.len() as f64)
+    }
+}
+
+/// A reading that differs from the previous one in value or quality
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagChange {
+    /// Tag name
+    pub tag: String,
+    /// Previous reading, `None` for the first reading of the tag
+    pub previous: Option<TimedValue>,
+    /// New reading
+    pub current: TimedValue,
+    /// Wall-clock time of the poll that produced the reading
+    pub at: SystemTime,
+}
+
+/// Latest readings of the watched tags as of one completed poll
+///
+/// Returned by [`TagWatcher::latest_all`]. A tag that could not be read in
+/// a poll keeps its reading from an earlier poll; a watched tag without any
+/// reading yet is absent.
+#[derive(Debug, Clone, Default)]
+pub struct WatcherSnapshot {
+    polls: u64,
+    readings: HashMap<String, TimedValue>,
+}
+
+impl WatcherSnapshot {
+    /// Polls completed when the snapshot was taken, see [`PollStats::polls`]
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Latest reading of `tag`
+    pub fn get(&self, tag: &str) -> Option<&TimedValue> {
+        self.readings.get(tag)
+    }
+
+    /// Tags and their latest readings, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TimedValue)> {
+        self.readings
+            .iter()
+            .map(|(tag, reading)| (tag.as_str(), reading))
+    }
+
+    /// Number of tags with a reading
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    /// Whether no tag has a reading yet
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+}
+
+/// Skipped poll cycles detected between two consecutive polls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollGap {
+    /// Number of poll cycles that did not run
+    pub missed: u32,
+    /// Time of the last poll before the gap
+    pub from: Instant,
+    /// Time of the first poll after the gap
+    pub to: Instant,
+}
+
+/// Summary of the polls made by a [`TagWatcher`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PollStats {
+    /// Completed polls; also the sequence number of the latest poll
+    pub polls: u64,
+    /// Gaps detected
+    pub gaps: u64,
+    /// Poll cycles missed across all gaps
+    pub missed: u64,
+    /// Largest difference between a poll's scheduled and actual time
+    pub worst_jitter: Duration,
+}
+
+/// Sequence and timing of completed polls
+#[derive(Debug, Default)]
+struct PollTracker {
+    period: Option<Duration>,
+    last: Option<Instant>,
+    stats: PollStats,
+}
+
+impl PollTracker {
+    /// Record a poll completed at `now`, returning the gap before it if any
+    fn record(&mut self, now: Instant) -> Option<PollGap> {
+        self.stats.polls += 1;
+        let last = self.last.replace(now)?;
+        let period = self.period.filter(|p| !p.is_zero())?;
+
+        let scheduled = last + period;
+        let jitter = now.max(scheduled) - now.min(scheduled);
+        self.stats.worst_jitter = self.stats.worst_jitter.max(jitter);
+
+        let elapsed = now.saturating_duration_since(last);
+        if elapsed.as_secs_f64() <= period.as_secs_f64() * 1.5 {
+            return None;
+        }
+        let cycles = (elapsed.as_secs_f64() / period.as_secs_f64()).round();
+        let missed = (cycles as u32).saturating_sub(1).max(1);
+        self.stats.gaps += 1;
+        self.stats.missed += u64::from(missed);
+        Some(PollGap {
+            missed,
+            from: last,
+            to: now,
+        })
+    }
+}
+
+/// Polling watcher over a [`CtList`]
+///
+/// # Thread Safety
+///
+/// `TagWatcher` is [`Send`] + [`Sync`]; one thread can drive [`poll`](Self::poll)
+/// while others query [`latest`](Self::latest) and [`history`](Self::history).
+/// [`latest`](Self::latest) and [`latest_all`](Self::latest_all) are
+/// lock-free; the other queries briefly share a lock with the poller.
+///
+/// # Memory
+///
+/// History is disabled by default. With [`with_history`](Self::with_history)
+/// each watched tag keeps at most `capacity` readings; older readings are
+/// discarded as new ones arrive.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ctapi_rs::{CtClient, TagWatcher};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let client = Arc::new(CtClient::open(None, None, None, 0)?);
+/// let list = Arc::new(Arc::clone(&client).list_new(0)?);
+/// let watcher = TagWatcher::new(list).with_history(60);
+/// watcher.watch("Temperature")?;
+///
+/// loop {
+///     watcher.poll()?;
+///     if let Some(rate) = watcher.rate_of_change("Temperature", Duration::from_secs(30)) {
+///         println!("Temperature rising at {rate:.2}/s");
+///     }
+///     std::thread::sleep(Duration::from_secs(1));
+/// }
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct TagWatcher {
+    list: Arc<CtList>,
+    clock: SharedClock,
+    history_capacity: usize,
+    tags: RwLock<HashMap<String, TagHistory>>,
+    /// Latest readings, replaced under the write lock of `tags`
+    snapshot: ArcSwap<WatcherSnapshot>,
+    tracker: Mutex<PollTracker>,
+    gap_senders: Mutex<Vec<Sender<PollGap>>>,
+    change_senders: Mutex<Vec<Sender<TagChange>>>,
+}
+
+impl TagWatcher {
+    /// Create a watcher reading from `list`
+    pub fn new(list: Arc<CtList>) -> Self {
+        Self {
+            list,
+            clock: system_clock(),
+            history_capacity: 0,
+            tags: RwLock::new(HashMap::new()),
+            snapshot: ArcSwap::from_pointee(WatcherSnapshot::default()),
+            tracker: Mutex::new(PollTracker::default()),
+            gap_senders: Mutex::new(Vec::new()),
+            change_senders: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Keep the last `capacity` readings of each tag (`0` disables history)
+    ///
+    /// Applies to tags watched after this call.
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Expected interval between polls, enabling gap and jitter tracking
+    ///
+    /// The watcher does not schedule polls itself; `period` should match the
+    /// interval at which the caller invokes [`poll`](Self::poll).
+    pub fn with_poll_period(self, period: Duration) -> Self {
+        self.lock_tracker().period = Some(period);
+        self
+    }
+
+    /// List the watcher reads from (internal use)
+    pub(crate) fn list(&self) -> &Arc<CtList> {
+        &self.list
+    }
+
+    /// Replace the time source (internal use and tests)
+    pub(crate) fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Start watching a tag
+    ///
+    /// Adds the tag to the underlying list. Watching a tag twice is a no-op.
+    pub fn watch<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        let tag = tag.as_ref();
+        let mut tags = self.tags.write().expect("TagWatcher tags RwLock poisoned");
+        if tags.contains_key(tag) {
+            return Ok(());
+        }
+        self.list.add_tag(tag)?;
+        tags.insert(tag.to_string(), TagHistory::new(self.history_capacity));
+        Ok(())
+    }
+
+    /// Stop watching a tag and discard its history
+    pub fn unwatch<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        let tag = tag.as_ref();
+        let mut tags = self.tags.write().expect("TagWatcher tags RwLock poisoned");
+        if tags.remove(tag).is_some() {
+            self.publish_snapshot(&tags);
+            self.list.delete_tag(tag)?;
+        }
+        Ok(())
+    }
+
+    /// Read the list once and record a reading for every watched tag
+    ///
+    /// Tags whose value cannot be retrieved in this cycle are skipped.
+    ///
+    /// # Errors
+    /// * [`CtApiError::System`](crate::CtApiError::System) - The list read failed
+    pub fn poll(&self) -> Result<()> {
+        self.list.read()?;
+        let now = self.clock.now();
+        let at = SystemTime::now();
+        self.complete_poll(now);
+        let mut changes = Vec::new();
+        let mut tags = self.tags.write().expect("TagWatcher tags RwLock poisoned");
+        for (tag, history) in tags.iter_mut() {
+            let Ok(value) = self.list.read_tag(tag.as_str(), 0) else {
+                continue;
+            };
+            let quality = self
+                .list
+                .read_tag(tag.as_str(), CT_LIST_QUALITY_GENERAL)
+                .ok()
+                .and_then(|q| q.trim().parse::<u8>().ok())
+                .unwrap_or(0);
+            let datasource_error = if quality == QUAL_GOOD {
+                None
+            } else {
+                self.list.datasource_error(tag.as_str()).ok().flatten()
+            };
+            let reading = TimedValue {
+                value,
+                timestamp: now,
+                quality,
+                datasource_error,
+            };
+            changes.extend(history.change_to(tag, &reading, at));
+            history.record(reading);
+        }
+        self.publish_snapshot(&tags);
+        drop(tags);
+        self.publish_changes(changes);
+        Ok(())
+    }
+
+    /// Replace the snapshot read by [`latest`](Self::latest) with the latest
+    /// readings of `tags`
+    ///
+    /// Called with the write lock of `tags` held, so snapshots are published
+    /// in poll order.
+    fn publish_snapshot(&self, tags: &HashMap<String, TagHistory>) {
+        let readings = tags
+            .iter()
+            .filter_map(|(tag, history)| Some((tag.clone(), history.latest.clone()?)))
+            .collect();
+        self.snapshot.store(Arc::new(WatcherSnapshot {
+            polls: self.lock_tracker().stats.polls,
+            readings,
+        }));
+    }
+
+    /// Send `changes` to the receivers of [`change_events`](Self::change_events)
+    fn publish_changes(&self, changes: Vec<TagChange>) {
+        if changes.is_empty() {
+            return;
+        }
+        let mut senders = self
+            .change_senders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        for change in changes {
+            senders.retain(|sender| sender.send(change.clone()).is_ok());
+        }
+    }
+
+    fn lock_tracker(&self) -> MutexGuard<'_, PollTracker> {
+        self.tracker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count a completed poll and report the gap before it, if any
+    fn complete_poll(&self, now: Instant) {
+        let Some(gap) = self.lock_tracker().record(now) else {
+            return;
+        };
+        let mut senders = self.gap_senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.retain(|sender| sender.send(gap).is_ok());
+    }
+
+    /// Receive a [`PollGap`] for every gap detected from now on
+    ///
+    /// Each call returns an independent receiver. Gaps are only detected when
+    /// a [poll period](Self::with_poll_period) is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctapi_rs::{CtClient, TagWatcher};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::new(Arc::clone(&client).list_new(0)?);
+    /// let watcher = TagWatcher::new(list).with_poll_period(Duration::from_secs(1));
+    /// let gaps = watcher.gap_events();
+    ///
+    /// std::thread::spawn(move || {
+    ///     for gap in gaps {
+    ///         eprintln!("{} poll(s) missed", gap.missed);
+    ///     }
+    /// });
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn gap_events(&self) -> Receiver<PollGap> {
+        let (sender, receiver) = channel();
+        self.gap_senders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        receiver
+    }
+
+    /// Receive a [`TagChange`] for every changed reading from now on
+    ///
+    /// Each call returns an independent receiver. A reading is a change if it
+    /// is the first of its tag or its value or quality differs from the
+    /// previous reading; unchanged readings are not sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctapi_rs::{CtClient, TagWatcher};
+    /// use std::sync::Arc;
+    ///
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::new(Arc::clone(&client).list_new(0)?);
+    /// let watcher = TagWatcher::new(list);
+    /// let changes = watcher.change_events();
+    /// watcher.watch("Temperature")?;
+    ///
+    /// watcher.poll()?;
+    /// watcher.poll()?;
+    /// let change = changes.try_recv().unwrap();
+    /// assert_eq!((change.tag.as_str(), change.current.value.as_str()), ("Temperature", "25.5"));
+    /// assert!(changes.try_recv().is_err());
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn change_events(&self) -> Receiver<TagChange> {
+        let (sender, receiver) = channel();
+        self.change_senders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        receiver
+    }
+
+    /// Poll count, detected gaps and worst jitter so far
+    pub fn stats(&self) -> PollStats {
+        self.lock_tracker().stats
+    }
+
+    /// Record a reading directly, bypassing the list (internal use and tests)
+    pub(crate) fn record(&self, tag: &str, value: &str, quality: u8) {
+        let reading = TimedValue {
+            value: value.to_string(),
+            timestamp: self.clock.now(),
+            quality,
+            datasource_error: None,
+        };
+        let mut tags = self.tags.write().expect("TagWatcher tags RwLock poisoned");
+        let capacity = self.history_capacity;
+        let history = tags
+            .entry(tag.to_string())
+            .or_insert_with(|| TagHistory::new(capacity));
+        let change = history.change_to(tag, &reading, SystemTime::now());
+        history.record(reading);
+        self.publish_snapshot(&tags);
+        drop(tags);
+        self.publish_changes(change.into_iter().collect());
+    }
+
+    /// Names of the watched tags
+    pub fn tags(&self) -> Vec<String> {
+        let tags = self.tags.read().expect("TagWatcher tags RwLock poisoned");
+        tags.keys().cloned().collect()
+    }
+
+    /// Most recent reading of a tag
+    ///
+    /// **Lock-free**: reads the snapshot of the last completed poll.
+    pub fn latest<T: AsRef<str>>(&self, tag: T) -> Option<TimedValue> {
+        self.snapshot.load().get(tag.as_ref()).cloned()
+    }
+
+    /// Most recent readings of all watched tags, from one completed poll
+    ///
+    /// **Lock-free**: returns the published snapshot itself, which later
+    /// polls replace rather than modify.
+    ///
+    /// # Examples
+    /// ```
+    /// use ctapi_rs::{CtClient, TagWatcher};
+    /// use std::sync::Arc;
+    ///
+    /// let client = Arc::new(CtClient::open_mock()?);
+    /// let list = Arc::new(Arc::clone(&client).list_new(0)?);
+    /// let watcher = TagWatcher::new(list);
+    /// watcher.watch("Temperature")?;
+    /// watcher.watch("Pressure")?;
+    /// watcher.poll()?;
+    ///
+    /// let snapshot = watcher.latest_all();
+    /// assert_eq!(snapshot.polls(), 1);
+    /// assert_eq!(snapshot.get("Pressure").unwrap().value, "1.2");
+    /// assert_eq!(snapshot.len(), 2);
+    /// # Ok::<(), ctapi_rs::CtApiError>(())
+    /// ```
+    pub fn latest_all(&self) -> Arc<WatcherSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// Buffered readings of a tag, oldest first
+    ///
+    /// Empty if history is disabled or the tag is not watched.
+    pub fn history<T: AsRef<str>>(&self, tag: T) -> Vec<TimedValue> {
+        let tags = self.tags.read().expect("TagWatcher tags RwLock poisoned");
+        tags.get(tag.as_ref())
+            .map(|h| h.samples.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Rate of change per second over the readings in the last `window`
+    ///
+    /// Computed from the oldest and newest numeric readings in the window;
+    /// non-numeric readings are ignored. Returns `None` if fewer than two
+    /// numeric readings at distinct times are available.
+    pub fn rate_of_change<T: AsRef<str>>(&self, tag: T, window: Duration) -> Option<f64> {
+        let now = self.clock.now();
+        let tags = self.tags.read().expect("TagWatcher tags RwLock poisoned");
+        tags.get(tag.as_ref())?.rate_of_change(now, window)
+    }
+
+    /// Mean of the numeric readings in the last `window`
+    ///
+    /// Non-numeric readings are ignored. Returns `None` if the window holds no
+    /// numeric readings.
+    pub fn moving_average<T: AsRef<str>>(&self, tag: T, window: Duration) -> Option<f64> {
+        let now = self.clock.now();
+        let tags = self.tags.read().expect("TagWatcher tags RwLock poisoned");
+        tags.get(tag.as_ref())?.moving_average(now, window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+
+    fn history(capacity: usize) -> (Arc<MockClock>, TagHistory) {
+        (MockClock::new(), TagHistory::new(capacity))
+    }
+
+    fn sample(clock: &MockClock, value: &str) -> TimedValue {
+        TimedValue {
+            value: value.to_string(),
+            timestamp: clock.now(),
+            quality: 3,
+            datasource_error: None,
+        }
+    }
+
+    #[test]
+    fn test_history_disabled_by_default() {
+        let (clock, mut h) = history(0);
+        h.record(sample(&clock, "1"));
+        assert!(h.samples.is_empty());
+        assert_eq!(h.latest.as_ref().unwrap().value, "1");
+    }
+
+    #[test]
+    fn test_ring_buffer_is_bounded() {
+        let (clock, mut h) = history(3);
+        for i in 0..10 {
+            h.record(sample(&clock, &i.to_string()));
+            clock.advance(Duration::from_secs(1));
+        }
+        let values: Vec<_> = h.samples.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(values, ["7", "8", "9"]);
+        assert_eq!(h.samples.len(), 3);
+    }
+
+    #[test]
+    fn test_rate_of_change_and_average() {
+        let (clock, mut h) = history(10);
+        for v in ["10", "12", "14", "16"] {
+            h.record(sample(&clock, v));
+            clock.advance(Duration::from_secs(2));
+        }
+        let now = clock.now();
+        // Whole history: 10 -> 16 over 6 s
+        assert_eq!(h.rate_of_change(now, Duration::from_secs(60)), Some(1.0));
+        assert_eq!(h.moving_average(now, Duration::from_secs(60)), Some(13.0));
+        // Last 4 s holds only "14" and "16"
+        assert_eq!(h.moving_average(now, Duration::from_secs(4)), Some(15.0));
+        assert_eq!(h.rate_of_change(now, Duration::from_secs(4)), Some(1.0));
+        // A single reading has no rate
+        assert_eq!(h.rate_of_change(now, Duration::from_secs(2)), None);
+    }
+
+    #[test]
+    fn test_non_numeric_excluded_from_math() {
+        let (clock, mut h) = history(10);
+        for v in ["1", "OFF", "3"] {
+            h.record(sample(&clock, v));
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(h.samples.len(), 3);
+        let now = clock.now();
+        assert_eq!(h.moving_average(now, Duration::from_secs(60)), Some(2.0));
+        assert_eq!(h.rate_of_change(now, Duration::from_secs(60)), Some(1.0));
+
+        let (_, mut only_text) = history(4);
+        only_text.record(sample(&clock, "RUNNING"));
+        assert_eq!(only_text.moving_average(now, Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_timed_value_numeric() {
+        let clock = MockClock::new();
+        assert_eq!(sample(&clock, " 2.5 ").numeric(), Some(2.5));
+        assert_eq!(sample(&clock, "NaN").numeric(), None);
+        assert_eq!(sample(&clock, "abc").numeric(), None);
+    }
+
+    fn tracker(period: Duration) -> PollTracker {
+        PollTracker {
+            period: Some(period),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_regular_polls_have_no_gaps() {
+        let clock = MockClock::new();
+        let mut tracker = tracker(Duration::from_secs(1));
+        for _ in 0..5 {
+            assert_eq!(tracker.record(clock.now()), None);
+            clock.advance(Duration::from_millis(1400));
+        }
+        assert_eq!(tracker.stats.polls, 5);
+        assert_eq!(tracker.stats.gaps, 0);
+        assert_eq!(tracker.stats.worst_jitter, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_gap_reports_missed_cycles() {
+        let clock = MockClock::new();
+        let mut tracker = tracker(Duration::from_secs(1));
+        tracker.record(clock.now());
+        let from = clock.now();
+
+        // A stall of 4 s: the polls due at +2 s and +3 s never ran.
+        clock.advance(Duration::from_secs(1));
+        tracker.record(clock.now());
+        let from_stall = clock.now();
+        clock.advance(Duration::from_secs(3));
+        let gap = tracker.record(clock.now()).unwrap();
+        assert_eq!(gap.missed, 2);
+        assert_eq!(gap.from, from_stall);
+        assert_eq!(gap.to, clock.now());
+        assert!(gap.from > from);
+
+        // Just over 1.5 periods rounds to one missed cycle.
+        clock.advance(Duration::from_millis(1600));
+        assert_eq!(tracker.record(clock.now()).unwrap().missed, 1);
+
+        let stats = tracker.stats;
+        assert_eq!((stats.polls, stats.gaps, stats.missed), (4, 2, 3));
+        assert_eq!(stats.worst_jitter, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_no_period_counts_only() {
+        let clock = MockClock::new();
+        let mut tracker = PollTracker::default();
+        tracker.record(clock.now());
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(tracker.record(clock.now()), None);
+        assert_eq!(tracker.stats.polls, 2);
+        assert_eq!(tracker.stats.worst_jitter, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_gap_events_and_stats() {
+        let clock = MockClock::new();
+        let client = Arc::new(crate::CtClient::from_handle(std::ptr::null_mut()));
+        let list = Arc::new(CtList::new(client, std::ptr::null_mut(), 0));
+        let watcher = TagWatcher::new(list)
+            .with_clock(clock.clone())
+            .with_poll_period(Duration::from_secs(1));
+        let gaps = watcher.gap_events();
+        drop(watcher.gap_events());
+
+        watcher.complete_poll(clock.now());
+        clock.advance(Duration::from_secs(5));
+        watcher.complete_poll(clock.now());
+
+        let gap = gaps.try_recv().unwrap();
+        assert_eq!(gap.missed, 4);
+        assert_eq!(gap.to, clock.now());
+        assert!(gaps.try_recv().is_err());
+        assert_eq!(watcher.gap_senders.lock().unwrap().len(), 1);
+
+        let stats = watcher.stats();
+        assert_eq!((stats.polls, stats.gaps, stats.missed), (2, 1, 4));
+        assert_eq!(stats.worst_jitter, Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_change_events_deliver_deltas() {
+        let client = Arc::new(crate::CtClient::from_handle(std::ptr::null_mut()));
+        let list = Arc::new(CtList::new(client, std::ptr::null_mut(), 0));
+        let watcher = TagWatcher::new(list);
+        let changes = watcher.change_events();
+        drop(watcher.change_events());
+
+        watcher.record("Level", "1.0", QUAL_GOOD);
+        watcher.record("Level", "1.0", QUAL_GOOD);
+        watcher.record("Level", "1.5", QUAL_GOOD);
+        watcher.record("Level", "1.5", 0);
+
+        let received: Vec<TagChange> = changes.try_iter().collect();
+        let values: Vec<_> = received
+            .iter()
+            .map(|c| {
+                (
+                    c.previous.as_ref().map(|p| p.value.as_str()),
+                    c.current.value.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            [(None, "1.0"), (Some("1.0"), "1.5"), (Some("1.5"), "1.5")]
+        );
+        assert_eq!(received[2].current.quality, 0);
+        assert_eq!(watcher.change_senders.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_latest_never_sees_torn_polls() {
+        use crate::mock::{self, MockServer};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        const TAGS: [&str; 4] = ["A", "B", "C", "D"];
+        const POLLS: u64 = 200;
+        let project = |poll: u64| {
+            TAGS.iter().fold(MockServer::new(), |server, tag| {
+                server.with_tag(tag, &poll.to_string())
+            })
+        };
+        let client = Arc::new(crate::CtClient::open_mock_with(project(0)).unwrap());
+        let list = Arc::new(Arc::clone(&client).list_new(0).unwrap());
+        let watcher = Arc::new(TagWatcher::new(list));
+        for tag in TAGS {
+            watcher.watch(tag).unwrap();
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let watcher = Arc::clone(&watcher);
+                let done = Arc::clone(&done);
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    while !done.load(Ordering::Acquire) {
+                        // Every reading of a snapshot comes from the poll it names
+                        let snapshot = watcher.latest_all();
+                        for (_, reading) in snapshot.iter() {
+                            assert_eq!(reading.value, snapshot.polls().to_string());
+                        }
+                        assert!(snapshot.polls() >= last);
+                        last = snapshot.polls();
+
+                        let seen: u64 = watcher.latest("C").map_or(0, |r| r.value.parse().unwrap());
+                        assert!(seen >= last);
+                    }
+                })
+            })
+            .collect();
+
+        for poll in 1..=POLLS {
+            mock::reload(&client, project(poll));
+            watcher.poll().unwrap();
+        }
+        done.store(true, Ordering::Release);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(watcher.latest_all().polls(), POLLS);
+
+        // Readers do not wait for the poller's lock
+        let held = watcher.tags.write().unwrap();
+        let reader = Arc::clone(&watcher);
+        let latest = std::thread::spawn(move || reader.latest("D"))
+            .join()
+            .unwrap();
+        assert_eq!(latest.unwrap().value, POLLS.to_string());
+        drop(held);
+
+        watcher.unwatch("D").unwrap();
+        assert!(watcher.latest("D").is_none());
+        assert_eq!(watcher.latest_all().len(), 3);
+    }
+
+    #[test]
+    fn test_watcher_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TagWatcher>();
+    }
+}
@@ -0,0 +1,306 @@
+//! Tag write strategies
+//!
+//! [`CtClient::tag_write`](crate::CtClient::tag_write) and
+//! [`CtClient::tag_write_str`](crate::CtClient::tag_write_str) can reach the
+//! server in two ways. The blocking `ctTagWrite` holds the calling thread for
+//! the full device round trip and cannot be interrupted. `ctTagWriteEx` with
+//! an OVERLAPPED structure behaves the same when waited on immediately, but
+//! the wait can be bounded: when it times out the write is cancelled on the
+//! server with `ctCancelIO`.
+//!
+//! The strategy is chosen per client with
+//! [`CtClient::set_write_strategy`](crate::CtClient::set_write_strategy) and
+//! is shared with clones of the client. The default is
+//! [`WriteStrategy::Blocking`], which matches earlier releases exactly.
+//!
+//! Values passed to [`CtClient::tag_write`](crate::CtClient::tag_write)
+//! implement [`TagValue`], which gives the text CtAPI receives. Numbers and
+//! strings are sent as they display, `bool` as `1` or `0`.
+//!
+//! [`CtClient::write_transaction`](crate::CtClient::write_transaction)
+//! applies several writes and restores the previous values if one of them
+//! fails. It is best effort: Citect SCADA has no multi-tag transactions, so
+//! other clients can observe the intermediate values, and a rollback write
+//! can fail too. The returned [`TransactionReport`] lists the outcome of
+//! every write.
+
+use crate::error::CtApiError;
+use crate::intern::SharedStr;
+use std::borrow::Cow;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// `INFINITE` timeout for `WaitForSingleObject`
+const INFINITE: u32 = u32::MAX;
+
+/// Value that can be written to a tag
+///
+/// CtAPI takes every value as text and converts it to the type of the tag.
+/// Digital tags only accept `1` and `0`, so `bool` is sent that way rather
+/// than as the `true`/`false` of its `Display` implementation.
+///
+/// # Examples
+/// ```
+/// use ctapi_rs::CtClient;
+///
+/// let client = CtClient::open_mock()?;
+/// client.tag_write("Pump_Start", true)?;
+/// assert_eq!(client.tag_read("Pump_Start")?, "1");
+/// # Ok::<(), ctapi_rs::CtApiError>(())
+/// ```
+pub trait TagValue {
+    /// Text sent to CtAPI for this value
+    fn to_tag_value(&self) -> Cow<'_, str>;
+}
+
+impl TagValue for bool {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(if *self { "1" } else { "0" })
+    }
+}
+
+macro_rules! display_tag_value {
+    ($($ty:ty),*) => {
+        $(impl TagValue for $ty {
+            fn to_tag_value(&self) -> Cow<'_, str> {
+                Cow::Owned(self.to_string())
+            }
+        })*
+    };
+}
+
+display_tag_value!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, char
+);
+
+impl TagValue for str {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl TagValue for String {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl TagValue for Cow<'_, str> {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl TagValue for SharedStr {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: TagValue + ?Sized> TagValue for &T {
+    fn to_tag_value(&self) -> Cow<'_, str> {
+        (**self).to_tag_value()
+    }
+}
+
+/// How tag writes reach the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WriteStrategy {
+    /// Synchronous `ctTagWrite`; write timeouts are ignored
+    #[default]
+    Blocking,
+    /// `ctTagWriteEx` with an OVERLAPPED structure and an event wait, bounded
+    /// by the client's write timeout
+    Overlapped,
+}
+
+/// Write settings shared by clones of a client
+#[derive(Debug, Default)]
+pub(crate) struct WriteSettings {
+    inner: Mutex<Settings>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Settings {
+    strategy: WriteStrategy,
+    timeout: Option<Duration>,
+}
+
+impl WriteSettings {
+    fn lock(&self) -> MutexGuard<'_, Settings> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn strategy(&self) -> WriteStrategy {
+        self.lock().strategy
+    }
+
+    pub(crate) fn set_strategy(&self, strategy: WriteStrategy) {
+        self.lock().strategy = strategy;
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.lock().timeout
+    }
+
+    pub(crate) fn set_timeout(&self, timeout: Option<Duration>) {
+        self.lock().timeout = timeout;
+    }
+
+    /// Strategy and timeout of the next write, read under one lock
+    pub(crate) fn current(&self) -> (WriteStrategy, Option<Duration>) {
+        let settings = *self.lock();
+        (settings.strategy, settings.timeout)
+    }
+}
+
+/// Options of [`CtClient::write_transaction`](crate::CtClient::write_transaction)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransactionOptions {
+    verify: bool,
+}
+
+impl TransactionOptions {
+    /// Read every tag back after writing it and treat a differing value as a failure
+    ///
+    /// Values are compared as numbers when both parse as one, so that `"1.50"`
+    /// verifies a write of `"1.5"`, and as trimmed strings otherwise.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Whether written values are read back
+    pub fn verifies(&self) -> bool {
+        self.verify
+    }
+}
+
+/// One write of a transaction or of its rollback
+#[derive(Debug)]
+pub struct TransactionWrite {
+    /// Tag name
+    pub tag: String,
+    /// Value written
+    pub value: String,
+    /// Why the write failed, `None` if it succeeded
+    pub error: Option<CtApiError>,
+}
+
+impl TransactionWrite {
+    pub(crate) fn new(tag: &str, value: &str, result: Result<(), CtApiError>) -> Self {
+        Self {
+            tag: tag.to_string(),
+            value: value.to_string(),
+            error: result.err(),
+        }
+    }
+
+    /// Whether the write succeeded
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Outcome of [`CtClient::write_transaction`](crate::CtClient::write_transaction)
+#[derive(Debug, Default)]
+pub struct TransactionReport {
+    /// Value of every target tag before the transaction, in request order
+    pub previous: Vec<(String, String)>,
+    /// Writes attempted, in request order; the last one failed unless all succeeded
+    pub writes: Vec<TransactionWrite>,
+    /// Writes restoring previous values, most recent tag first
+    pub rollback: Vec<TransactionWrite>,
+}
+
+impl TransactionReport {
+    /// Whether every write succeeded and nothing was rolled back
+    pub fn committed(&self) -> bool {
+        self.writes.len() == self.previous.len() && self.writes.iter().all(TransactionWrite::is_ok)
+    }
+
+    /// Whether every rollback write succeeded
+    ///
+    /// If not, some tags are left at values written by the transaction.
+    pub fn rollback_complete(&self) -> bool {
+        self.rollback.iter().all(TransactionWrite::is_ok)
+    }
+}
+
+/// Whether a value read back after a write matches the value written
+pub(crate) fn values_match(expected: &str, actual: &str) -> bool {
+    let (expected, actual) = (expected.trim(), actual.trim());
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        _ => expected == actual,
+    }
+}
+
+/// Convert a timeout to the milliseconds expected by `WaitForSingleObject`
+///
+/// `None` waits forever. Durations are rounded up so that a sub-millisecond
+/// timeout still waits, and capped just below `INFINITE`.
+pub(crate) fn wait_millis(timeout: Option<Duration>) -> u32 {
+    match timeout {
+        None => INFINITE,
+        Some(timeout) => {
+            let millis = timeout.as_nanos().div_ceil(1_000_000);
+            millis.min(u128::from(INFINITE - 1)) as u32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_blocking() {
+        let settings = WriteSettings::default();
+        assert_eq!(settings.current(), (WriteStrategy::Blocking, None));
+        assert_eq!(WriteStrategy::default(), WriteStrategy::Blocking);
+    }
+
+    #[test]
+    fn test_settings_update() {
+        let settings = WriteSettings::default();
+        settings.set_strategy(WriteStrategy::Overlapped);
+        settings.set_timeout(Some(Duration::from_secs(2)));
+        assert_eq!(settings.strategy(), WriteStrategy::Overlapped);
+        assert_eq!(settings.timeout(), Some(Duration::from_secs(2)));
+
+        settings.set_timeout(None);
+        assert_eq!(settings.current(), (WriteStrategy::Overlapped, None));
+    }
+
+    #[test]
+    fn test_tag_values() {
+        assert_eq!(true.to_tag_value(), "1");
+        assert_eq!(false.to_tag_value(), "0");
+        assert_eq!((-42_i32).to_tag_value(), "-42");
+        assert_eq!(2.5_f64.to_tag_value(), "2.5");
+        assert_eq!("Running".to_tag_value(), "Running");
+        assert_eq!(String::from("LOT-1").to_tag_value(), "LOT-1");
+        assert_eq!((&&true).to_tag_value(), "1");
+    }
+
+    #[test]
+    fn test_values_match() {
+        assert!(values_match("1.5", "1.50"));
+        assert!(values_match("Running", " Running "));
+        assert!(!values_match("1", "0"));
+        assert!(!values_match("On", "on"));
+    }
+
+    #[test]
+    fn test_wait_millis() {
+        assert_eq!(wait_millis(None), INFINITE);
+        assert_eq!(wait_millis(Some(Duration::ZERO)), 0);
+        assert_eq!(wait_millis(Some(Duration::from_micros(1))), 1);
+        assert_eq!(wait_millis(Some(Duration::from_millis(1500))), 1500);
+        assert_eq!(
+            wait_millis(Some(Duration::from_secs(u64::MAX))),
+            INFINITE - 1
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Outcomes of list writes that were accepted but later rejected
+//!
+//! `ctListWrite` succeeds once the request is queued, also when it is
+//! overlapped; a device that refuses the value afterwards only shows it
+//! through the quality of the tag on a later list read. A [`CtList`] tracks
+//! the last write to each of its tags and, when a read within the correlation
+//! window finds a data source error the tag did not have when it was written,
+//! reports [`WriteOutcome::LikelyRejected`] through
+//! [`CtList::pending_write_outcomes`].
+//!
+//! This is a heuristic: the error may have another cause, such as the device
+//! going offline, and a write whose error shows only after the window is not
+//! reported. Only the last write to a tag is tracked, so it is blamed for an
+//! error caused by an earlier one.
+//!
+//! [`CtList`]: crate::CtList
+//! [`CtList::pending_write_outcomes`]: crate::CtList::pending_write_outcomes
+
+use crate::clock::{SharedClock, system_clock};
+use crate::quality::CitectError;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Time after a write in which a new data source error is blamed on it by default
+pub const DEFAULT_CORRELATION_WINDOW: Duration = Duration::from_secs(10);
+
+/// What a list read revealed about an earlier write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The tag got a data source error after the write was accepted
+    LikelyRejected {
+        /// Tag that was written
+        tag: String,
+        /// Sequence number of the write on its list, starting at 1
+        write_seq: u64,
+        /// Error the tag reported
+        datasource_error: CitectError,
+    },
+}
+
+/// A write waiting for the reads that may reveal its outcome
+#[derive(Debug)]
+struct IssuedWrite {
+    seq: u64,
+    at: Instant,
+    /// Data source error of the tag when it was written
+    baseline: Option<CitectError>,
+}
+
+/// Writes of one list and the outcomes found for them (internal use)
+#[derive(Debug)]
+pub(crate) struct WriteTracker {
+    clock: SharedClock,
+    window: Duration,
+    next_seq: u64,
+    issued: HashMap<String, IssuedWrite>,
+    outcomes: Vec<WriteOutcome>,
+}
+
+impl WriteTracker {
+    pub(crate) fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    pub(crate) fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            window: DEFAULT_CORRELATION_WINDOW,
+            next_seq: 1,
+            issued: HashMap::new(),
+            outcomes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub(crate) fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Track a write to `tag` accepted while the tag had `baseline`,
+    /// replacing an earlier write to it, and return its sequence number
+    pub(crate) fn record(&mut self, tag: &str, baseline: Option<CitectError>) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let write = IssuedWrite {
+            seq,
+            at: self.clock.now(),
+            baseline,
+        };
+        self.issued.insert(tag.to_string(), write);
+        seq
+    }
+
+    /// Forget the writes older than the window and return the tags of the others
+    pub(crate) fn tracked(&mut self) -> Vec<String> {
+        let now = self.clock.now();
+        let window = self.window;
+        self.issued
+            .retain(|_, write| now.saturating_duration_since(write.at) <= window);
+        self.issued.keys().cloned().collect()
+    }
+
+    /// Compare the data source error `tag` was read with to the one it was
+    /// written with, recording an outcome if it got a new one
+    pub(crate) fn observe(&mut self, tag: &str, datasource_error: Option<CitectError>) {
+        let Some(write) = self.issued.get(tag) else {
+            return;
+        };
+        let Some(datasource_error) = datasource_error else {
+            return;
+        };
+        if write.baseline == Some(datasource_error) {
+            return;
+        }
+        let write_seq = write.seq;
+        self.issued.remove(tag);
+        self.outcomes.push(WriteOutcome::LikelyRejected {
+            tag: tag.to_string(),
+            write_seq,
+            datasource_error,
+        });
+    }
+
+    /// Outcomes found since the last call, oldest first
+    pub(crate) fn drain(&mut self) -> Vec<WriteOutcome> {
+        std::mem::take(&mut self.outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn error(code: u32) -> Option<CitectError> {
+        CitectError::from_code(code)
+    }
+
+    #[test]
+    fn test_new_error_reported_once() {
+        let mut tracker = WriteTracker::with_clock(MockClock::new());
+        assert_eq!(tracker.record("Setpoint", None), 1);
+        tracker.observe("Setpoint", None);
+        assert!(tracker.drain().is_empty());
+
+        tracker.observe("Setpoint", error(4));
+        tracker.observe("Setpoint", error(4));
+        assert_eq!(
+            tracker.drain(),
+            [WriteOutcome::LikelyRejected {
+                tag: "Setpoint".to_string(),
+                write_seq: 1,
+                datasource_error: CitectError::from_code(4).unwrap(),
+            }]
+        );
+        assert!(tracker.drain().is_empty());
+        assert!(tracker.tracked().is_empty());
+    }
+
+    #[test]
+    fn test_existing_error_is_not_blamed_on_write() {
+        let mut tracker = WriteTracker::with_clock(MockClock::new());
+        tracker.record("Setpoint", error(4));
+        tracker.observe("Setpoint", error(4));
+        assert!(tracker.drain().is_empty());
+
+        tracker.observe("Setpoint", error(9));
+        assert_eq!(tracker.drain().len(), 1);
+    }
+
+    #[test]
+    fn test_writes_expire_after_window() {
+        let clock = MockClock::new();
+        let mut tracker = WriteTracker::with_clock(clock.clone());
+        tracker.set_window(Duration::from_secs(2));
+        tracker.record("Setpoint", None);
+        clock.advance(Duration::from_secs(1));
+        tracker.record("Pump_Start", None);
+        assert_eq!(tracker.tracked().len(), 2);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(tracker.tracked(), ["Pump_Start"]);
+        tracker.observe("Setpoint", error(4));
+        assert!(tracker.drain().is_empty());
+    }
+}
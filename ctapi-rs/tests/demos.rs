@@ -0,0 +1,115 @@
+//! Smoke tests running the example demos
+//!
+//! The mock-backed tests always run. The live ones need `CTAPI_COMPUTER`,
+//! `CTAPI_USER` and `CTAPI_PASSWORD` and run with
+//! `cargo test --features demos -- --ignored`; they only write to
+//! [`DEMO_TAG`] when `CTAPI_DEMO_WRITES=1` is set.
+#![cfg(feature = "demos")]
+
+use ctapi_rs::CtClient;
+use ctapi_rs::demos::*;
+use ctapi_rs::mock::MockServer;
+use std::sync::Arc;
+
+const DEMO_TAG: &str = "TagExt_DemoTag1";
+const DEMO_MIRROR_TAG: &str = "TagExt_DemoTag1_Mirror";
+
+#[test]
+fn demo_config_has_no_defaults() {
+    let config = DemoConfig::default();
+    assert_eq!(config.computer, None);
+    assert_eq!(config.user, None);
+    assert_eq!(config.password, None);
+    assert_eq!(config.write_value("1"), None);
+}
+
+#[test]
+fn demo_config_debug_redacts_password() {
+    let config = DemoConfig {
+        password: Some("s3cr3t-pw".into()),
+        ..DemoConfig::default()
+    };
+    let debug = format!("{config:?}");
+    assert!(!debug.contains("s3cr3t-pw"));
+    assert!(debug.contains("password: Some(***)"));
+}
+
+#[test]
+fn mock_tag_roundtrip() {
+    let client = CtClient::open_mock().unwrap();
+    let read = run_tag_roundtrip(&client, "Setpoint", None).unwrap();
+    assert_eq!([read.before, read.after], ["20", "20"]);
+
+    let written = run_tag_roundtrip(&client, "Setpoint", Some("21")).unwrap();
+    assert_eq!([written.before, written.after], ["20", "21"]);
+    assert!(run_tag_roundtrip(&client, "NoSuchTag", None).is_err());
+}
+
+#[test]
+fn mock_list_cycle_reuses_the_list() {
+    let client = Arc::new(CtClient::open_mock().unwrap());
+    let list = new_demo_list(Arc::clone(&client), &["Temperature", "Setpoint"]).unwrap();
+    let values = run_list_cycle(&list).unwrap();
+    assert_eq!(values[0], ("Temperature".to_string(), "25.5".to_string()));
+    assert_eq!(values[1], ("Setpoint".to_string(), "20".to_string()));
+
+    client.tag_write_str("Setpoint", "22").unwrap();
+    let values = run_list_cycle(&list).unwrap();
+    assert_eq!(values[1].1, "22");
+}
+
+#[test]
+fn mock_async_cicode() {
+    let server = MockServer::seeded().with_pending_cicode("Date(4)", "2024-01-15");
+    let client = CtClient::open_mock_with(server).unwrap();
+    let results = run_async_cicode(&client, &["Time(1)", "Date(4)"]).unwrap();
+    assert_eq!(results, ["10:30:00", "2024-01-15"]);
+}
+
+#[cfg(feature = "tokio-support")]
+#[tokio::test]
+async fn mock_tokio_cycle() {
+    let client = Arc::new(CtClient::open_mock().unwrap());
+    let list = new_demo_list(Arc::clone(&client), &["Pressure"]).unwrap();
+    let (time, values) = run_tokio_cycle(&client, &list).await.unwrap();
+    assert_eq!(time, "10:30:00");
+    assert_eq!(values, [("Pressure".to_string(), "1.2".to_string())]);
+}
+
+#[test]
+#[ignore = "Requires actual Citect SCADA connection"]
+fn demo_tag_roundtrip() {
+    let config = DemoConfig::from_env().unwrap();
+    let client = config.open().unwrap();
+    let value = config.write_value("1");
+    let roundtrip = run_tag_roundtrip(&client, DEMO_TAG, value).unwrap();
+    assert_eq!(roundtrip.after, value.unwrap_or(&roundtrip.before));
+}
+
+#[test]
+#[ignore = "Requires actual Citect SCADA connection"]
+fn demo_list_cycle() {
+    let client = Arc::new(DemoConfig::from_env().unwrap().open().unwrap());
+    let list = new_demo_list(client, &[DEMO_TAG, DEMO_MIRROR_TAG]).unwrap();
+    assert_eq!(run_list_cycle(&list).unwrap().len(), 2);
+    assert_eq!(run_list_cycle(&list).unwrap().len(), 2);
+}
+
+#[test]
+#[ignore = "Requires actual Citect SCADA connection"]
+fn demo_async_cicode() {
+    let client = DemoConfig::from_env().unwrap().open().unwrap();
+    let results = run_async_cicode(&client, &["Time(1)", "Date(4)"]).unwrap();
+    assert_eq!(results.len(), 2);
+}
+
+#[cfg(feature = "tokio-support")]
+#[tokio::test]
+#[ignore = "Requires actual Citect SCADA connection"]
+async fn demo_tokio_cycle() {
+    let client = Arc::new(DemoConfig::from_env().unwrap().open().unwrap());
+    let list = new_demo_list(Arc::clone(&client), &[DEMO_TAG]).unwrap();
+    let (time, values) = run_tokio_cycle(&client, &list).await.unwrap();
+    assert!(!time.is_empty());
+    assert_eq!(values.len(), 1);
+}
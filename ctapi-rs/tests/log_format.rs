@@ -0,0 +1,120 @@
+//! Formatting quality and connection state must not allocate
+//!
+//! They are written on every structured log record, see
+//! [`ctapi_rs::quality`].
+
+use ctapi_rs::quality::{QUAL_BAD, QUAL_GOOD, QUAL_NOT_APPLICABLE, QUAL_UNCERTAIN};
+use ctapi_rs::{ConnectionState, Quality, QualityStatus};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+
+/// System allocator counting the allocations of the current thread
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Fixed-size text sink, standing in for a log line buffer
+struct Line {
+    bytes: [u8; 128],
+    len: usize,
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(std::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(|count| count.get());
+    f();
+    ALLOCATIONS.with(|count| count.get()) - before
+}
+
+fn qualities() -> Vec<Quality> {
+    [QUAL_GOOD, QUAL_UNCERTAIN, QUAL_BAD, QUAL_NOT_APPLICABLE, 2]
+        .into_iter()
+        .flat_map(|general| {
+            [0, 3, 0x8004_1234].map(|code| Quality {
+                general,
+                datasource_error: ctapi_rs::CitectError::from_code(code),
+                ..Quality::default()
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn quality_and_state_format_without_allocating() {
+    let qualities = qualities();
+    let states = [
+        ConnectionState::Connected,
+        ConnectionState::Reconnecting,
+        ConnectionState::Down,
+    ];
+    let count = allocations(|| {
+        for quality in &qualities {
+            for state in &states {
+                let mut line = Line {
+                    bytes: [0; 128],
+                    len: 0,
+                };
+                write!(
+                    line,
+                    "state={state} quality={quality} status={}",
+                    quality.status()
+                )
+                .unwrap();
+                std::hint::black_box(&line.bytes[..line.len]);
+                std::hint::black_box((state.as_str(), quality.status().as_str()));
+            }
+        }
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn line_contents() {
+    let mut line = Line {
+        bytes: [0; 128],
+        len: 0,
+    };
+    let quality = Quality {
+        general: QUAL_BAD,
+        datasource_error: ctapi_rs::CitectError::from_code(2),
+        ..Quality::default()
+    };
+    write!(
+        line,
+        "{} {quality} {}",
+        ConnectionState::Down,
+        QualityStatus::Bad
+    )
+    .unwrap();
+    let text = std::str::from_utf8(&line.bytes[..line.len]).unwrap();
+    assert_eq!(text, "down bad: Channel offline (driver error 2) bad");
+}
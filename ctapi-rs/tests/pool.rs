@@ -0,0 +1,50 @@
+//! Concurrent checkouts from a connection pool
+//!
+//! Runs against the in-process mock server.
+
+use ctapi_rs::CtClient;
+use ctapi_rs::pool::CtClientPool;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const POOL_SIZE: usize = 3;
+const THREADS: usize = 12;
+const CHECKOUTS: usize = 200;
+
+#[test]
+fn concurrent_checkouts_share_the_connections() {
+    let pool = CtClientPool::new(POOL_SIZE, CtClient::open_mock).unwrap();
+    let pool = Arc::new(pool.max_wait(Duration::from_secs(10)));
+    let lent = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let (pool, lent, peak) = (Arc::clone(&pool), Arc::clone(&lent), Arc::clone(&peak));
+            thread::spawn(move || {
+                for _ in 0..CHECKOUTS {
+                    let client = pool.get().unwrap();
+                    let now = lent.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    assert_eq!(client.tag_read("Temperature").unwrap(), "25.5");
+                    lent.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    assert!(peak.load(Ordering::SeqCst) <= POOL_SIZE);
+    assert_eq!(pool.idle_count(), POOL_SIZE);
+}
+
+#[test]
+fn pool_can_be_shared_between_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CtClientPool>();
+    assert_send_sync::<ctapi_rs::pool::PooledClient>();
+}
@@ -40,6 +40,44 @@ impl Default for OVERLAPPED {
 unsafe impl Send for OVERLAPPED {}
 unsafe impl Sync for OVERLAPPED {}
 
+/// Revision of the [`CtTagValueItems`] structure understood by a CtAPI.dll
+///
+/// The DLL reads the `length` field of the structure to learn which revision
+/// the caller passes; fields past that length are neither read nor written.
+///
+/// Only the full structure is documented by the CtAPI reference. The
+/// 36-byte [`Quality`](CtApiVersion::Quality) revision is an assumption
+/// derived from the layout: the structure without its two trailing flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum CtApiVersion {
+    /// Timestamps and quality, without the override and control mode flags
+    Quality,
+    /// Timestamps, quality, override and control mode flags
+    #[default]
+    Current,
+}
+
+impl CtApiVersion {
+    /// `length` of this revision of the structure
+    pub const fn items_len(self) -> u32 {
+        match self {
+            CtApiVersion::Quality => CtTagValueItems::QUALITY_LEN,
+            CtApiVersion::Current => CtTagValueItems::LEN,
+        }
+    }
+
+    /// Largest revision contained in a structure of `length` bytes
+    pub const fn from_items_len(length: u32) -> Option<Self> {
+        if length >= CtTagValueItems::LEN {
+            Some(CtApiVersion::Current)
+        } else if length >= CtTagValueItems::QUALITY_LEN {
+            Some(CtApiVersion::Quality)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C, packed)]
 pub struct CtTagValueItems {
@@ -56,17 +94,19 @@ pub struct CtTagValueItems {
     pub control_mode: bool,
 }
 
+// The layout is fixed by CtAPI: a change here breaks the length negotiation.
+const _: () = assert!(CtTagValueItems::LEN == 38 && CtTagValueItems::QUALITY_LEN == 36);
+
 impl CtTagValueItems {
-    /// Get the ct tag value items's length.
-    pub fn length(&self) -> u32 {
-        self.length
-    }
-}
+    /// `length` of the current revision, the size of this structure
+    pub const LEN: u32 = size_of::<Self>() as u32;
+    /// `length` of the revision without the override and control mode flags
+    pub const QUALITY_LEN: u32 = std::mem::offset_of!(Self, boverride) as u32;
 
-impl Default for CtTagValueItems {
-    fn default() -> Self {
+    /// Zeroed structure announcing the `version` revision to the DLL
+    pub const fn for_version(version: CtApiVersion) -> Self {
         Self {
-            length: 38,
+            length: version.items_len(),
             timestamp: 0,
             value_timestamp: 0,
             quality_timestamp: 0,
@@ -79,6 +119,53 @@ impl Default for CtTagValueItems {
             control_mode: false,
         }
     }
+
+    /// Get the ct tag value items's length.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Revision given by the length, `None` if shorter than any known revision
+    pub fn version(&self) -> Option<CtApiVersion> {
+        CtApiVersion::from_items_len(self.length)
+    }
+
+    /// Whether the quality fields are within the length
+    pub fn has_quality(&self) -> bool {
+        self.length >= Self::QUALITY_LEN
+    }
+
+    /// Whether the override and control mode flags are within the length
+    pub fn has_modes(&self) -> bool {
+        self.length >= Self::LEN
+    }
+
+    /// Zero the fields past the length, which the DLL did not fill
+    ///
+    /// Below the [`Quality`](CtApiVersion::Quality) revision no field is
+    /// trusted, so the timestamps are zeroed as well.
+    pub fn clear_unavailable(&mut self) {
+        if !self.has_modes() {
+            self.boverride = false;
+            self.control_mode = false;
+        }
+        if !self.has_quality() {
+            self.timestamp = 0;
+            self.value_timestamp = 0;
+            self.quality_timestamp = 0;
+            self.quality_general = 0;
+            self.quality_substatus = 0;
+            self.quality_limit = 0;
+            self.quality_extended_substatus = 0;
+            self.quality_datasource_error = 0;
+        }
+    }
+}
+
+impl Default for CtTagValueItems {
+    fn default() -> Self {
+        Self::for_version(CtApiVersion::Current)
+    }
 }
 
 /// A struct reprent the range of value
@@ -163,6 +250,7 @@ impl Default for CtScale {
         Self::new(CtHScale::default(), CtHScale::default())
     }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 #[allow(non_camel_case_types)]
 pub enum DBTYPEENUM {
@@ -0,0 +1,271 @@
+//! Tag reference grammar shared by ctapi-rs and ctapi-macros
+//!
+//! `TagPath::parse` in ctapi-rs and the `tag!` macro of ctapi-macros both
+//! call [`parse`], so a literal the macro accepts is exactly a reference the
+//! parser accepts. This crate is an implementation detail of the two: it is
+//! hidden from their documentation and may change in any release.
+
+/// Longest tag reference, in characters
+pub const MAX_TAG_NAME_LEN: usize = 254;
+
+/// Documented element fields, with the `TagField` variant of each
+///
+/// Names are matched case-insensitively.
+pub const KNOWN_FIELDS: &[(&str, &str)] = &[
+    ("V", "Value"),
+    ("Q", "Quality"),
+    ("T", "Timestamp"),
+    ("VT", "ValueTimestamp"),
+    ("QT", "QualityTimestamp"),
+];
+
+/// Parts of a tag reference, borrowed from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parts<'a> {
+    /// Cluster prefix
+    pub cluster: Option<&'a str>,
+    /// Tag name without cluster, index or field
+    pub tag: &'a str,
+    /// Array element index
+    pub index: Option<u32>,
+    /// Element field, as written
+    pub field: Option<&'a str>,
+}
+
+/// Why a tag reference is rejected, and the byte offset of the culprit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invalid {
+    /// Byte offset of the offending character
+    pub at: usize,
+    /// What is wrong there
+    pub problem: String,
+}
+
+impl Invalid {
+    fn new(at: usize, problem: impl Into<String>) -> Self {
+        Self {
+            at,
+            problem: problem.into(),
+        }
+    }
+
+    /// Problem followed by the reference and a caret under the culprit
+    pub fn render(&self, path: &str) -> String {
+        let column = path[..self.at].chars().count();
+        format!("{}\n  {path}\n  {}^", self.problem, " ".repeat(column))
+    }
+
+    /// Position in characters and the culprit itself
+    ///
+    /// A problem at the end of `path`, such as a trailing `.`, is blamed on
+    /// its last character. `None` for an empty `path`.
+    pub fn culprit(&self, path: &str) -> Option<(usize, char)> {
+        let position = path[..self.at].chars().count();
+        match path[self.at..].chars().next() {
+            Some(c) => Some((position, c)),
+            None => path.chars().last().map(|c| (position - 1, c)),
+        }
+    }
+}
+
+/// The documented field called `name` and its `TagField` variant, if any
+pub fn known_field(name: &str) -> Option<(&'static str, &'static str)> {
+    KNOWN_FIELDS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/// Split a tag reference into its parts
+///
+/// `Tag`, `Tag[n]`, `Tag.Field`, `Cluster.Tag` and `Cluster.Tag[n].Field`.
+/// `A.B` is a tag field when `B` is one of the [`KNOWN_FIELDS`] and a
+/// cluster qualified tag otherwise. A reference is at most
+/// [`MAX_TAG_NAME_LEN`] characters long.
+pub fn parse(path: &str) -> Result<Parts<'_>, Invalid> {
+    if let Some((at, _)) = path.char_indices().nth(MAX_TAG_NAME_LEN) {
+        let len = path.chars().count();
+        let problem =
+            format!("a tag reference is {len} characters long, maximum is {MAX_TAG_NAME_LEN}");
+        return Err(Invalid::new(at, problem));
+    }
+    let mut segments = Vec::new();
+    let mut at = 0;
+    for segment in path.split('.') {
+        segments.push((at, segment));
+        at += segment.len() + 1;
+    }
+    let (cluster, tag, field) = match segments[..] {
+        [tag] => (None, tag, None),
+        [tag, field] if known_field(field.1).is_some() => (None, tag, Some(field)),
+        [cluster, tag] => (Some(cluster), tag, None),
+        [cluster, tag, field] => (Some(cluster), tag, Some(field)),
+        _ => {
+            let problem = "a tag reference has at most three segments, `Cluster.Tag.Field`";
+            return Err(Invalid::new(segments[3].0 - 1, problem));
+        }
+    };
+
+    let (at, tag) = tag;
+    let (name, index) = match tag.strip_suffix(']').and_then(|t| t.split_once('[')) {
+        Some((name, index)) => (name, Some((at + name.len() + 1, index))),
+        None => (tag, None),
+    };
+    check_name("tag", at, name)?;
+    let index = index.map(parse_index).transpose()?;
+    if let Some((at, cluster)) = cluster {
+        check_name("cluster", at, cluster)?;
+    }
+    if let Some((at, field)) = field {
+        check_field(at, field)?;
+    }
+    Ok(Parts {
+        cluster: cluster.map(|(_, cluster)| cluster),
+        tag: name,
+        index,
+        field: field.map(|(_, field)| field),
+    })
+}
+
+/// A tag or cluster name, at offset `at`, is made of letters, digits and `_`
+///
+/// Letters and digits of any script are accepted, so `温度` is a name.
+pub fn check_name(what: &str, at: usize, name: &str) -> Result<(), Invalid> {
+    if name.is_empty() {
+        return Err(Invalid::new(at, format!("empty {what} name")));
+    }
+    let stray = name
+        .char_indices()
+        .find(|&(_, c)| !(c == '_' || c.is_alphanumeric()));
+    let Some((i, c)) = stray else {
+        return Ok(());
+    };
+    let problem = match c {
+        '.' => format!("`.` is not allowed in a {what} name, it separates segments"),
+        '[' | ']' => format!("`{c}` is not allowed in a {what} name, write elements as `Tag[n]`"),
+        c => format!(
+            "`{}` is not allowed in a {what} name, which is letters, digits and `_`",
+            c.escape_default()
+        ),
+    };
+    Err(Invalid::new(at + i, problem))
+}
+
+/// An array index, at offset `at`, is a `u32`
+fn parse_index((at, index): (usize, &str)) -> Result<u32, Invalid> {
+    if index.is_empty() {
+        return Err(Invalid::new(at, "empty array index"));
+    }
+    let stray = index.char_indices().find(|(_, c)| !c.is_ascii_digit());
+    if let Some((i, c)) = stray {
+        let problem =
+            format!("`{c}` is not allowed in an array index, which is an unsigned integer");
+        return Err(Invalid::new(at + i, problem));
+    }
+    index
+        .parse()
+        .map_err(|_| Invalid::new(at, format!("array index `{index}` is not a u32")))
+}
+
+/// A field, at offset `at`, is a letter or `_` followed by letters, digits or `_`
+pub fn check_field(at: usize, field: &str) -> Result<(), Invalid> {
+    let mut chars = field.char_indices();
+    match chars.next() {
+        None => return Err(Invalid::new(at, "empty field name")),
+        Some((_, c)) if !(c.is_ascii_alphabetic() || c == '_') => {
+            let problem = format!("a field name starts with a letter or `_`, not `{c}`");
+            return Err(Invalid::new(at, problem));
+        }
+        Some(_) => {}
+    }
+    match chars.find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_')) {
+        Some((i, c)) => {
+            let problem = format!("`{c}` is not allowed in a field name");
+            Err(Invalid::new(at + i, problem))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts() {
+        let parts = |cluster, tag, index, field| Parts {
+            cluster,
+            tag,
+            index,
+            field,
+        };
+        let cases = [
+            ("Pump1", parts(None, "Pump1", None, None)),
+            ("Flow[3]", parts(None, "Flow", Some(3), None)),
+            ("Pump1.v", parts(None, "Pump1", None, Some("v"))),
+            (
+                "Cluster1.Pump1",
+                parts(Some("Cluster1"), "Pump1", None, None),
+            ),
+            (
+                "Cluster1.Flow[0].Scale",
+                parts(Some("Cluster1"), "Flow", Some(0), Some("Scale")),
+            ),
+            ("温度.V", parts(None, "温度", None, Some("V"))),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(parse(path), Ok(expected), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_points_at_offending_character() {
+        let cases = [
+            ("", 0),
+            ("A.B.C.D", 5),
+            ("Flow[x]", 5),
+            ("Flow[+3]", 5),
+            ("Flow[]", 5),
+            ("Flow[1", 4),
+            ("Flow[99999999999]", 5),
+            ("Pump1.V.", 8),
+            (".Pump1", 0),
+            ("A.B.1x", 4),
+            ("A.B.V-1", 5),
+            ("C[1].Pump1", 1),
+            ("Boiler1\\Temp", 7),
+            ("Flow rate", 4),
+            ("Tag\0", 3),
+        ];
+        for (path, at) in cases {
+            assert_eq!(parse(path).unwrap_err().at, at, "{path}");
+        }
+        assert_eq!(
+            parse("Pump1.").unwrap_err().culprit("Pump1."),
+            Some((5, '.'))
+        );
+        assert_eq!(
+            parse("温度 1").unwrap_err().culprit("温度 1"),
+            Some((2, ' '))
+        );
+        let rendered = parse("Flow[x]").unwrap_err().render("Flow[x]");
+        assert!(rendered.ends_with("\n  Flow[x]\n       ^"), "{rendered}");
+        assert_eq!(check_name("tag", 0, "A.B").unwrap_err().at, 1);
+    }
+
+    #[test]
+    fn test_length_limit() {
+        let longest = "T".repeat(MAX_TAG_NAME_LEN);
+        assert!(parse(&longest).is_ok());
+        let invalid = parse(&format!("{longest}é")).unwrap_err();
+        assert_eq!(invalid.at, MAX_TAG_NAME_LEN);
+        // Characters, not bytes, are counted
+        assert!(parse(&"é".repeat(MAX_TAG_NAME_LEN)).is_ok());
+    }
+
+    #[test]
+    fn test_known_fields() {
+        assert_eq!(known_field("qt"), Some(("QT", "QualityTimestamp")));
+        assert_eq!(known_field("Scale"), None);
+    }
+}
@@ -1,25 +1,17 @@
-use ctapi_rs::{AsyncCtClient, AsyncOperation, CtClient};
+use ctapi_rs::demos::{DemoConfig, run_async_cicode};
+use ctapi_rs::{AsyncCtClient, AsyncOperation, CicodeWindow};
 use std::sync::Arc;
 
-const COMPUTER: &str = "127.0.0.1";
-const USER: &str = "Engineer";
-const PASSWORD: &str = "Citect";
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("=== Async CtAPI Demo ===\n");
 
-    let client = Arc::new(CtClient::open(
-        Some(COMPUTER),
-        Some(USER),
-        Some(PASSWORD),
-        0,
-    )?);
+    let client = Arc::new(DemoConfig::from_env()?.open()?);
     println!("✓ Connected to Citect SCADA\n");
 
     // Example 1: Simple async cicode call
     println!("Example 1: Async Cicode Call");
     let mut async_op = AsyncOperation::new();
-    client.cicode_async("Time(1)", 0, 0, &mut async_op)?;
+    client.cicode_async("Time(1)", CicodeWindow::NONE, 0, &mut async_op)?;
     println!("  Started async operation...");
 
     // Do some other work while waiting
@@ -35,7 +27,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Example 2: Polling for completion
     println!("Example 2: Polling for Completion");
     async_op.reset();
-    client.cicode_async("Date(4)", 0, 0, &mut async_op)?;
+    client.cicode_async("Date(4)", CicodeWindow::NONE, 0, &mut async_op)?;
     println!("  Started async operation...");
 
     let mut attempts = 0;
@@ -61,25 +53,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 3: Multiple concurrent async operations
     println!("Example 3: Multiple Concurrent Operations");
-    let mut ops = [
-        AsyncOperation::new(),
-        AsyncOperation::new(),
-        AsyncOperation::new(),
-    ];
-
     let commands = ["Time(1)", "Date(4)", "DspGetEnv(\"Tag\")"];
-
-    for (i, (op, cmd)) in ops.iter_mut().zip(commands.iter()).enumerate() {
-        client.cicode_async(cmd, 0, 0, op)?;
-        println!("  Started operation {}: {}", i + 1, cmd);
-    }
-
-    println!("  Waiting for all operations to complete...");
-    for (i, op) in ops.iter_mut().enumerate() {
-        match op.get_result(&client) {
-            Ok(result) => println!("  Operation {}: {}", i + 1, result),
-            Err(e) => eprintln!("  Operation {} error: {}", i + 1, e),
-        }
+    for (i, result) in run_async_cicode(&client, &commands)?.iter().enumerate() {
+        println!("  Operation {}: {} = {}", i + 1, commands[i], result);
     }
     println!();
 
@@ -110,7 +86,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Example 5: Cancellation
     println!("Example 5: Operation Cancellation");
     let mut cancel_op = AsyncOperation::new();
-    client.cicode_async("PageDisplay(\"Summary\")", 0, 0, &mut cancel_op)?;
+    client.cicode_async(
+        "PageDisplay(\"Summary\")",
+        CicodeWindow::NONE,
+        0,
+        &mut cancel_op,
+    )?;
     println!("  Started long-running operation...");
 
     std::thread::sleep(std::time::Duration::from_millis(100));
@@ -1,14 +1,13 @@
-use ctapi_rs::CtClient;
+use ctapi_rs::demos::{DemoConfig, run_tag_roundtrip};
 
-const COMPUTER: &str = "127.0.0.1";
-const USER: &str = "Manager";
-const PASSWORD: &str = "Citect";
-
-fn main() {
-    // use ctapi_rs::sys::CtTagValueItems;
-    use ctapi_rs::CtTagValueItems;
-    let mut value = CtTagValueItems::default();
-    let client = CtClient::open(Some(COMPUTER), Some(USER), Some(PASSWORD), 0).unwrap();
-    let result = client.tag_read_ex("TagExt_DemoTag1", &mut value);
-    println!("{result:?} {value:#?}");
+fn main() -> anyhow::Result<()> {
+    let config = DemoConfig::from_env()?;
+    let client = config.open()?;
+    let value = config.write_value("1");
+    let roundtrip = run_tag_roundtrip(&client, "TagExt_DemoTag1", value)?;
+    println!(
+        "{} -> {} {:#?}",
+        roundtrip.before, roundtrip.after, roundtrip.items
+    );
+    Ok(())
 }
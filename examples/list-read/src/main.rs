@@ -1,23 +1,18 @@
-use ctapi_rs::CtClient;
+use ctapi_rs::demos::{DemoConfig, new_demo_list, run_list_cycle};
 use std::sync::Arc;
 
-const COMPUTER: &str = "127.0.0.1";
-const USER: &str = "Engineer";
-const PASSWORD: &str = "Citect";
-
-fn main() {
-    let client = Arc::new(CtClient::open(Some(COMPUTER), Some(USER), Some(PASSWORD), 0).unwrap());
-    let list = client.list_new(0).unwrap();
-    list.add_tag("TagExt_DemoTag1").unwrap();
-    list.add_tag("TagExt_DemoTag1_Mirror").unwrap();
-    list.read().unwrap();
+fn main() -> anyhow::Result<()> {
+    let config = DemoConfig::from_env()?;
+    let client = Arc::new(config.open()?);
+    let tags = ["TagExt_DemoTag1", "TagExt_DemoTag1_Mirror"];
+    let list = new_demo_list(Arc::clone(&client), &tags)?;
     loop {
-        let result = list.read_tag("TagExt_DemoTag1", 0).unwrap();
-        println!("{result}");
-        let result = list.read_tag("TagExt_DemoTag1_Mirror", 0).unwrap();
-        println!("{result}");
+        for (tag, value) in run_list_cycle(&list)? {
+            println!("{tag} = {value}");
+        }
+        if let Some(value) = config.write_value("1") {
+            client.tag_write_str("TagExt_DemoTag1", value)?;
+        }
         std::thread::sleep(std::time::Duration::from_secs(1));
-        list.write_tag("TagExt_DemoTag1", "1").unwrap();
-        list.read().unwrap();
     }
 }
@@ -0,0 +1,69 @@
+//! Publish tag changes to an MQTT broker
+//!
+//! The broker address is read from `MQTT_HOST` (default `localhost`).
+use ctapi_rs::TagWatcher;
+use ctapi_rs::bridge::{ChangeFeed, FeedOptions, PayloadEncoder, QoS};
+use ctapi_rs::demos::DemoConfig;
+use rumqttc::{Client, LastWill, MqttOptions};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn qos(qos: QoS) -> rumqttc::QoS {
+    match qos {
+        QoS::AtMostOnce => rumqttc::QoS::AtMostOnce,
+        QoS::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let client = Arc::new(DemoConfig::from_env()?.open()?);
+    let list = Arc::new(Arc::clone(&client).list_new(0)?);
+    let watcher = TagWatcher::new(list);
+    for tag in ["TagExt_DemoTag1", "TagExt_DemoTag1_Mirror"] {
+        watcher.watch(tag)?;
+    }
+
+    let options = FeedOptions::new("plant/{cluster}/{tag}")
+        .with_cluster("Cluster1")
+        .with_encoder(PayloadEncoder::Json)
+        .with_retain(true)
+        .with_status_topic("plant/{cluster}/status");
+    let mut feed = ChangeFeed::new(&watcher, options);
+
+    let host = std::env::var("MQTT_HOST").unwrap_or_else(|_| "localhost".to_string());
+    let mut mqtt_options = MqttOptions::new("ctapi-bridge", host, 1883);
+    mqtt_options.set_keep_alive(Duration::from_secs(5));
+    if let Some(will) = feed.options().death_message() {
+        mqtt_options.set_last_will(LastWill::new(
+            will.topic,
+            will.payload,
+            qos(will.qos),
+            will.retain,
+        ));
+    }
+    let (mqtt, mut connection) = Client::new(mqtt_options, 64);
+    std::thread::spawn(move || {
+        for notification in connection.iter() {
+            if let Err(e) = notification {
+                eprintln!("MQTT connection error: {e}");
+                std::thread::sleep(Duration::from_secs(1));
+            }
+        }
+    });
+
+    loop {
+        if let Err(e) = watcher.poll() {
+            eprintln!("poll failed: {e}");
+        }
+        for message in feed.drain() {
+            mqtt.publish(
+                message.topic,
+                qos(message.qos),
+                message.retain,
+                message.payload,
+            )?;
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
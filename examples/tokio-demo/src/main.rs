@@ -6,7 +6,8 @@
 //!
 //! Note: This demo will fail to connect without a running Citect SCADA instance.
 
-use ctapi_rs::{CtClient, FutureCtClient, TokioCtClient, TokioCtList};
+use ctapi_rs::demos::DemoConfig;
+use ctapi_rs::{CicodeWindow, CtClient, FutureCtClient, TokioCtClient, TokioCtList};
 use std::sync::Arc;
 use tokio::time::Duration;
 
@@ -15,7 +16,14 @@ async fn main() -> anyhow::Result<()> {
     println!("=== Tokio CtAPI Demo ===\n");
 
     // Connect to Citect SCADA
-    let client = match CtClient::open(Some("127.0.0.1"), Some("Engineer"), Some("Citect"), 0) {
+    let config = match DemoConfig::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("✗ Bad connection settings: {}", e);
+            return Ok(());
+        }
+    };
+    let client = match config.open() {
         Ok(c) => {
             println!("✓ Connected to Citect SCADA\n");
             Arc::new(c)
@@ -27,7 +35,7 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    let client = Arc::new(client);  
+    let client = Arc::new(client);
     // ── Demo 1: Simple async/await calls (spawn_blocking) ──────────────────
     println!("Demo 1: Simple async/await calls (spawn_blocking)");
     println!("--------------------------------------------------");
@@ -55,7 +63,7 @@ async fn main() -> anyhow::Result<()> {
     // ── Demo 4: Async tag operations ───────────────────────────────────────
     println!("Demo 4: Async tag read / write");
     println!("------------------------------");
-    match demo_tag_operations(&client.clone()).await {
+    match demo_tag_operations(&client.clone(), config.allow_writes).await {
         Ok(_) => println!("✓ Demo 4 completed\n"),
         Err(e) => eprintln!("✗ Demo 4 failed: {}\n", e),
     }
@@ -71,7 +79,7 @@ async fn main() -> anyhow::Result<()> {
     // ── Demo 6: Async list operations ──────────────────────────────────────
     println!("Demo 6: Async list operations");
     println!("------------------------------");
-    match demo_list_operations(&client).await {
+    match demo_list_operations(&client, config.allow_writes).await {
         Ok(_) => println!("✓ Demo 6 completed\n"),
         Err(e) => eprintln!("✗ Demo 6 failed: {}\n", e),
     }
@@ -108,13 +116,19 @@ async fn main() -> anyhow::Result<()> {
 
 /// Demo 1: Simple async/await calls using the spawn_blocking approach.
 async fn demo_simple_async(client: &Arc<CtClient>) -> anyhow::Result<()> {
-    let time = client.cicode_tokio("Time(1)", 0, 0).await?;
+    let time = client
+        .cicode_tokio("Time(1)", CicodeWindow::NONE, 0)
+        .await?;
     println!("  Current time    : {}", time);
 
-    let date = client.cicode_tokio("Date(4)", 0, 0).await?;
+    let date = client
+        .cicode_tokio("Date(4)", CicodeWindow::NONE, 0)
+        .await?;
     println!("  Current date    : {}", date);
 
-    let version = client.cicode_tokio("Version()", 0, 0).await?;
+    let version = client
+        .cicode_tokio("Version()", CicodeWindow::NONE, 0)
+        .await?;
     println!("  Citect version  : {}", version);
 
     Ok(())
@@ -125,10 +139,14 @@ async fn demo_simple_async(client: &Arc<CtClient>) -> anyhow::Result<()> {
 async fn demo_future_client(client: &Arc<CtClient>) -> anyhow::Result<()> {
     // cicode_future() returns a CtApiFuture that implements std::future::Future.
     // It can be .await-ed just like any other future.
-    let time = client.cicode_future("Time(1)", 0, 0)?.await?;
+    let time = client
+        .cicode_future("Time(1)", CicodeWindow::NONE, 0)?
+        .await?;
     println!("  Time (OVERLAPPED) : {}", time);
 
-    let date = client.cicode_future("Date(4)", 0, 0)?.await?;
+    let date = client
+        .cicode_future("Date(4)", CicodeWindow::NONE, 0)?
+        .await?;
     println!("  Date (OVERLAPPED) : {}", date);
 
     Ok(())
@@ -150,7 +168,7 @@ async fn demo_concurrent_operations(client: &Arc<CtClient>) -> anyhow::Result<()
             let cmd = cmd.to_string();
             let label = label.to_string();
             tokio::spawn(async move {
-                let result = c.cicode_tokio(&cmd, 0, 0).await?;
+                let result = c.cicode_tokio(&cmd, CicodeWindow::NONE, 0).await?;
                 Ok::<_, anyhow::Error>((label, result))
             })
         })
@@ -166,8 +184,8 @@ async fn demo_concurrent_operations(client: &Arc<CtClient>) -> anyhow::Result<()
     Ok(())
 }
 
-/// Demo 4: Async tag read and write.
-async fn demo_tag_operations(client: &Arc<CtClient>) -> anyhow::Result<()> {
+/// Demo 4: Async tag read and, when `CTAPI_DEMO_WRITES=1`, write.
+async fn demo_tag_operations(client: &Arc<CtClient>, allow_writes: bool) -> anyhow::Result<()> {
     let tags = vec!["BIT_1", "BIT_2", "BIT_3"];
 
     for tag in &tags {
@@ -177,6 +195,11 @@ async fn demo_tag_operations(client: &Arc<CtClient>) -> anyhow::Result<()> {
         }
     }
 
+    if !allow_writes {
+        println!("  writes skipped, set CTAPI_DEMO_WRITES=1 to write BIT_1");
+        return Ok(());
+    }
+
     // Write using tag_write_tokio (accepts any string value)
     match client.tag_write_tokio("BIT_1", "1").await {
         Ok(_) => println!("  write BIT_1 = 1  ✓"),
@@ -209,7 +232,7 @@ async fn demo_tag_read_ex(client: &Arc<CtClient>) -> anyhow::Result<()> {
 }
 
 /// Demo 6: Async list operations with `TokioCtList`.
-async fn demo_list_operations(client: &Arc<CtClient>) -> anyhow::Result<()> {
+async fn demo_list_operations(client: &Arc<CtClient>, allow_writes: bool) -> anyhow::Result<()> {
     let list = Arc::clone(client).list_new(0)?;
 
     let tags = vec!["BIT_1", "BIT_2", "BIT_3"];
@@ -230,8 +253,10 @@ async fn demo_list_operations(client: &Arc<CtClient>) -> anyhow::Result<()> {
     }
 
     // Async write for a single tag
-    list.write_tag_tokio("BIT_1", "1").await?;
-    println!("  Wrote BIT_1 = 1  ✓");
+    if allow_writes {
+        list.write_tag_tokio("BIT_1", "1").await?;
+        println!("  Wrote BIT_1 = 1  ✓");
+    }
 
     Ok(())
 }
@@ -243,7 +268,7 @@ async fn demo_tokio_tasks(client: &Arc<CtClient>) -> anyhow::Result<()> {
             let c = Arc::clone(client);
             tokio::spawn(async move {
                 let cmd = format!("StrToInt(\"{}\")", i);
-                let result = c.cicode_tokio(&cmd, 0, 0).await?;
+                let result = c.cicode_tokio(&cmd, CicodeWindow::NONE, 0).await?;
                 Ok::<_, anyhow::Error>(format!("  task {:2}: {}", i, result))
             })
         })
@@ -264,7 +289,12 @@ async fn demo_timeout_handling(client: &Arc<CtClient>) -> anyhow::Result<()> {
     use tokio::time::timeout;
 
     // Fast operation — should complete well within 2 s.
-    match timeout(Duration::from_secs(2), client.cicode_tokio("Time(1)", 0, 0)).await {
+    match timeout(
+        Duration::from_secs(2),
+        client.cicode_tokio("Time(1)", CicodeWindow::NONE, 0),
+    )
+    .await
+    {
         Ok(Ok(result)) => println!("  Time (within 2 s): {}", result),
         Ok(Err(e)) => eprintln!("  operation failed: {}", e),
         Err(_) => eprintln!("  timed out after 2 s"),
@@ -273,7 +303,7 @@ async fn demo_timeout_handling(client: &Arc<CtClient>) -> anyhow::Result<()> {
     // Same pattern with FutureCtClient — OVERLAPPED future also supports timeout.
     match timeout(
         Duration::from_secs(2),
-        client.cicode_future("Date(4)", 0, 0)?,
+        client.cicode_future("Date(4)", CicodeWindow::NONE, 0)?,
     )
     .await
     {
@@ -291,9 +321,9 @@ async fn demo_concurrent_futures(client: &Arc<CtClient>) -> anyhow::Result<()> {
     // cicode_future() returns a CtApiFuture (std::future::Future).
     // try_join! polls both futures concurrently on the same thread.
     let (time, date, version) = tokio::try_join!(
-        client.cicode_future("Time(1)", 0, 0)?,
-        client.cicode_future("Date(4)", 0, 0)?,
-        client.cicode_future("Version()", 0, 0)?,
+        client.cicode_future("Time(1)", CicodeWindow::NONE, 0)?,
+        client.cicode_future("Date(4)", CicodeWindow::NONE, 0)?,
+        client.cicode_future("Version()", CicodeWindow::NONE, 0)?,
     )?;
 
     println!("  time    = {}", time);